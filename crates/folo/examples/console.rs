@@ -0,0 +1,115 @@
+//! Reference client for the live instrumentation endpoint (`folo::console`), rendering the
+//! snapshots it receives as a refreshing table in the terminal.
+//!
+//! Run with an address (e.g. `cargo run --example console --features console -- 127.0.0.1:6669`)
+//! to connect to the endpoint of another process. Without an address, the example starts a runtime
+//! with some demo tasks and an endpoint of its own, and connects to that.
+
+use folo::{
+    console::{ConsoleBuilder, ConsoleServer},
+    rt::{RuntimeBuilder, RuntimeClient},
+    time::{Clock, Delay},
+};
+use serde_json::Value;
+use std::{
+    env,
+    error::Error,
+    io::{BufRead, BufReader},
+    net::{Ipv4Addr, SocketAddr, TcpStream},
+    time::Duration,
+};
+
+// Clears the terminal and moves the cursor to the top left corner.
+const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+
+fn main() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+    // Kept alive for as long as we are rendering, if we are observing ourselves.
+    let mut demo = None;
+
+    let addr = match env::args().nth(1) {
+        Some(addr) => addr.parse()?,
+        None => {
+            let (runtime, console) = start_demo()?;
+            let addr = console.local_addr();
+            demo = Some((runtime, console));
+            addr
+        }
+    };
+
+    let stream = TcpStream::connect(addr)?;
+
+    for line in BufReader::new(stream).lines() {
+        let snapshot: Value = serde_json::from_str(&line?)?;
+        render(&snapshot);
+    }
+
+    drop(demo);
+    Ok(())
+}
+
+fn start_demo() -> Result<(RuntimeClient, ConsoleServer), Box<dyn Error + Send + Sync + 'static>> {
+    let runtime = RuntimeBuilder::new().build()?;
+
+    for i in 0..4 {
+        runtime.spawn_on_any_named(format!("ticker-{i}"), move || async move {
+            let clock = Clock::new();
+
+            loop {
+                Delay::with_clock(&clock, Duration::from_millis(100 * (i + 1))).await;
+            }
+        });
+    }
+
+    let console =
+        ConsoleBuilder::new().start(&runtime, SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))?;
+
+    Ok((runtime, console))
+}
+
+fn render(snapshot: &Value) {
+    let mut output = String::from(CLEAR_SCREEN);
+
+    for worker in snapshot["workers"].as_array().into_iter().flatten() {
+        output.push_str(&format!(
+            "worker {} (processor {}){} - utilization {:.1}%, {} I/O operations in flight\n",
+            worker["index"],
+            worker["processor_id"],
+            if worker["responded"] == true {
+                ""
+            } else {
+                " NOT RESPONDING"
+            },
+            worker["utilization"].as_f64().unwrap_or_default() * 100.0,
+            worker["io_operations_in_flight"],
+        ));
+
+        output.push_str(&format!(
+            "  {:>8} {:<24} {:<8} {:>10} {:>10} {:>8} {:>10}\n",
+            "ID", "NAME", "STATE", "AGE", "IDLE", "POLLS", "BUSY"
+        ));
+
+        for task in worker["tasks"].as_array().into_iter().flatten() {
+            output.push_str(&format!(
+                "  {:>8} {:<24} {:<8} {:>10} {:>10} {:>8} {:>10}\n",
+                task["id"].to_string(),
+                task["name"].as_str().unwrap_or("-"),
+                task["state"].as_str().unwrap_or("?"),
+                format_ms(&task["age_ms"]),
+                format_ms(&task["since_last_poll_ms"]),
+                task["polls"].to_string(),
+                format_ms(&task["busy_time_ms"]),
+            ));
+        }
+
+        output.push('\n');
+    }
+
+    print!("{output}");
+}
+
+fn format_ms(value: &Value) -> String {
+    match value.as_u64() {
+        Some(ms) => format!("{ms} ms"),
+        None => "-".to_string(),
+    }
+}
@@ -1,13 +1,21 @@
+#[cfg(not(target_arch = "wasm32"))]
 use crate::metrics::{ReportBuilder, ReportPage};
+#[cfg(not(target_arch = "wasm32"))]
 use crossbeam::channel;
+use std::{future::Future, pin::Pin};
+
+// Used by the `join!` and `try_join!` macros to hold each future until all of them complete.
+pub use futures::future::{maybe_done, MaybeDone};
 
 /// Collects metrics from a channel and publishes a report when dropped. This is used by the Folo
 /// entrypoint macro when metrics publishing is enabled. It is not meant for direct consumption.
+#[cfg(not(target_arch = "wasm32"))]
 pub struct MetricsCollector {
     metrics_rx: channel::Receiver<ReportPage>,
     metrics_tx: channel::Sender<ReportPage>,
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 impl MetricsCollector {
     pub fn new() -> Self {
         let (metrics_tx, metrics_rx) = channel::unbounded();
@@ -38,14 +46,40 @@ impl MetricsCollector {
     }
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 impl Default for MetricsCollector {
     fn default() -> Self {
         Self::new()
     }
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 impl Drop for MetricsCollector {
     fn drop(&mut self) {
         self.publish_report();
     }
 }
+
+/// Takes the output of a completed future of `try_join!` that succeeded. It is not meant for
+/// direct consumption.
+pub fn take_ok<F, T, E>(future: Pin<&mut MaybeDone<F>>) -> T
+where
+    F: Future<Output = Result<T, E>>,
+{
+    match future.take_output() {
+        Some(Ok(value)) => value,
+        _ => panic!("try_join! only takes the output of futures that succeeded"),
+    }
+}
+
+/// Takes the output of a completed future of `try_join!` that failed. It is not meant for direct
+/// consumption.
+pub fn take_err<F, T, E>(future: Pin<&mut MaybeDone<F>>) -> E
+where
+    F: Future<Output = Result<T, E>>,
+{
+    match future.take_output() {
+        Some(Err(e)) => e,
+        _ => panic!("try_join! only takes the error of futures that failed"),
+    }
+}
@@ -0,0 +1,328 @@
+//! A micro-benchmark harness that executes an async benchmark body in a loop on every async worker
+//! thread of a dedicated runtime and reports the throughput and latency percentiles per worker.
+//!
+//! Typically used via the `#[folo::bench]` attribute but can also be used directly via
+//! [`BenchBuilder`].
+
+use crate::{
+    metrics::{self, EventBuilder, Magnitude, Report, ReportBuilder, ReportPage},
+    rt::RuntimeBuilder,
+};
+use std::{
+    fmt::{self, Display, Formatter},
+    future::Future,
+    hint,
+    time::{Duration, Instant},
+};
+
+const DEFAULT_DURATION: Duration = Duration::from_secs(5);
+const DEFAULT_WARMUP: Duration = Duration::from_secs(1);
+
+const LATENCY_EVENT: &str = "bench_iteration_latency_ns";
+
+// Roughly exponential, from the cost of a trivial future to that of a disk or network round trip.
+const LATENCY_NANOSECONDS_BUCKETS: &[Magnitude] = &[
+    50,
+    100,
+    250,
+    500,
+    1_000,
+    2_500,
+    5_000,
+    10_000,
+    25_000,
+    50_000,
+    100_000,
+    250_000,
+    500_000,
+    1_000_000,
+    2_500_000,
+    5_000_000,
+    10_000_000,
+    25_000_000,
+    50_000_000,
+    100_000_000,
+];
+
+const PERCENTILES: &[f64] = &[50.0, 90.0, 99.0];
+
+/// Configures and executes a benchmark.
+///
+/// Each benchmark starts a new runtime, which executes the benchmark body on every async worker
+/// thread, one iteration at a time, first for the warm-up period and then for the measured period.
+///
+/// # Examples
+///
+/// ```
+/// use folo::bench::BenchBuilder;
+/// use std::time::Duration;
+///
+/// let report = BenchBuilder::new("yield_now")
+///     .duration(Duration::from_millis(100))
+///     .warmup(Duration::ZERO)
+///     .max_processors(1)
+///     .run(folo::rt::yield_now);
+///
+/// println!("{report}");
+/// ```
+#[derive(Debug)]
+pub struct BenchBuilder {
+    name: String,
+    duration: Duration,
+    warmup: Duration,
+    max_processors: Option<usize>,
+}
+
+impl BenchBuilder {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            duration: DEFAULT_DURATION,
+            warmup: DEFAULT_WARMUP,
+            max_processors: None,
+        }
+    }
+
+    /// How long to execute the measured iterations for, on each worker thread.
+    pub fn duration(mut self, duration: Duration) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    /// How long to execute the benchmark body for before starting the measurement, allowing
+    /// caches, pools and similar to reach a steady state.
+    pub fn warmup(mut self, warmup: Duration) -> Self {
+        self.warmup = warmup;
+        self
+    }
+
+    /// Limits the number of processors (and therefore worker threads) the benchmark will use.
+    pub fn max_processors(mut self, max_processors: usize) -> Self {
+        self.max_processors = Some(max_processors);
+        self
+    }
+
+    /// Executes the benchmark, calling `body` to create the future of each iteration.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the runtime cannot be started or if the benchmark body panics.
+    pub fn run<FN, F, R>(self, body: FN) -> BenchReport
+    where
+        FN: Fn() -> F + Clone + Send + 'static,
+        F: Future<Output = R> + 'static,
+    {
+        let mut runtime_builder = RuntimeBuilder::new();
+
+        if let Some(max_processors) = self.max_processors {
+            runtime_builder = runtime_builder.max_processors(max_processors);
+        }
+
+        let runtime = runtime_builder
+            .build()
+            .expect("failed to start runtime for benchmark");
+
+        let handles = (0..runtime.worker_count())
+            .map(|worker_index| {
+                let body = body.clone();
+                let (warmup, duration) = (self.warmup, self.duration);
+
+                runtime.spawn_on(worker_index, move || async move {
+                    run_worker(worker_index, body, warmup, duration).await
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let outcomes = futures::executor::block_on(futures::future::join_all(handles));
+
+        runtime.stop();
+        runtime.wait();
+
+        BenchReport {
+            name: self.name,
+            workers: outcomes
+                .into_iter()
+                .map(WorkerOutcome::into_result)
+                .collect(),
+        }
+    }
+}
+
+async fn run_worker<FN, F, R>(
+    worker_index: usize,
+    body: FN,
+    warmup: Duration,
+    duration: Duration,
+) -> WorkerOutcome
+where
+    FN: Fn() -> F,
+    F: Future<Output = R>,
+{
+    let warmup_start = Instant::now();
+
+    while warmup_start.elapsed() < warmup {
+        hint::black_box(body().await);
+    }
+
+    // The runtime is dedicated to the benchmark, so nothing else observes this event.
+    let latency = EventBuilder::new(LATENCY_EVENT)
+        .buckets(LATENCY_NANOSECONDS_BUCKETS)
+        .build();
+
+    let start = Instant::now();
+    let mut iterations = 0;
+
+    let elapsed = loop {
+        let iteration_start = Instant::now();
+        hint::black_box(body().await);
+        let now = Instant::now();
+
+        latency.observe(now.duration_since(iteration_start).as_nanos() as Magnitude);
+        iterations += 1;
+
+        let elapsed = now.duration_since(start);
+
+        if elapsed >= duration {
+            break elapsed;
+        }
+    };
+
+    WorkerOutcome {
+        worker_index,
+        iterations,
+        elapsed,
+        page: metrics::report_page(),
+    }
+}
+
+// What a worker thread sends back to the thread that started the benchmark.
+struct WorkerOutcome {
+    worker_index: usize,
+    iterations: usize,
+    elapsed: Duration,
+    page: ReportPage,
+}
+
+impl WorkerOutcome {
+    fn into_result(self) -> WorkerBenchResult {
+        let mut report_builder = ReportBuilder::new();
+        report_builder.add_page(self.page);
+
+        WorkerBenchResult {
+            worker_index: self.worker_index,
+            iterations: self.iterations,
+            elapsed: self.elapsed,
+            latency: report_builder.build(),
+        }
+    }
+}
+
+/// The results of a benchmark, for each of the worker threads that executed it.
+#[derive(Debug)]
+pub struct BenchReport {
+    name: String,
+    workers: Vec<WorkerBenchResult>,
+}
+
+impl BenchReport {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn workers(&self) -> &[WorkerBenchResult] {
+        &self.workers
+    }
+
+    /// The number of iterations completed per second, summed over all worker threads.
+    pub fn throughput(&self) -> f64 {
+        self.workers.iter().map(WorkerBenchResult::throughput).sum()
+    }
+}
+
+impl Display for BenchReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}: {:.0} iterations/s", self.name, self.throughput())?;
+
+        for worker in &self.workers {
+            writeln!(f, "  {worker}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The results of a benchmark on one worker thread.
+pub struct WorkerBenchResult {
+    worker_index: usize,
+    iterations: usize,
+    elapsed: Duration,
+    latency: Report,
+}
+
+impl WorkerBenchResult {
+    /// The index of the worker thread, as used by `spawn_on()`.
+    pub fn worker_index(&self) -> usize {
+        self.worker_index
+    }
+
+    /// The number of measured iterations (excluding the warm-up).
+    pub fn iterations(&self) -> usize {
+        self.iterations
+    }
+
+    /// The number of iterations completed per second.
+    pub fn throughput(&self) -> f64 {
+        self.iterations as f64 / self.elapsed.as_secs_f64()
+    }
+
+    /// Estimates a percentile (0.0 to 100.0) of the latency of an iteration. The estimate is the
+    /// upper bound of the histogram bucket the percentile falls into.
+    ///
+    /// Returns `None` if the percentile is greater than the largest bucket of the histogram or if
+    /// the `metrics` feature is disabled.
+    pub fn latency_percentile(&self, percentile: f64) -> Option<Duration> {
+        self.latency
+            .percentile(LATENCY_EVENT, percentile)
+            .map(|nanos| Duration::from_nanos(nanos as u64))
+    }
+}
+
+impl Display for WorkerBenchResult {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "worker {}: {} iterations, {:.0} iterations/s",
+            self.worker_index,
+            self.iterations,
+            self.throughput()
+        )?;
+
+        for &percentile in PERCENTILES {
+            match self.latency_percentile(percentile) {
+                Some(latency) => write!(f, "; p{percentile} <= {latency:?}")?,
+                None => write!(f, "; p{percentile} > {:?}", max_latency())?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for WorkerBenchResult {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        // The latency report is not `Debug`, so we just show the summary.
+        f.debug_struct("WorkerBenchResult")
+            .field("worker_index", &self.worker_index)
+            .field("iterations", &self.iterations)
+            .field("elapsed", &self.elapsed)
+            .finish_non_exhaustive()
+    }
+}
+
+fn max_latency() -> Duration {
+    Duration::from_nanos(
+        *LATENCY_NANOSECONDS_BUCKETS
+            .last()
+            .expect("histogram has buckets") as u64,
+    )
+}
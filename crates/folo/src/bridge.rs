@@ -0,0 +1,3 @@
+//! Interoperation between Folo and other async runtimes in the same process.
+
+pub mod tokio;
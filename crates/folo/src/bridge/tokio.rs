@@ -0,0 +1,279 @@
+//! Running a Tokio runtime side by side with a Folo runtime, for applications that move to Folo
+//! incrementally - for example, keeping a Tokio-based database driver while the data plane moves to
+//! Folo.
+//!
+//! * [`spawn_on_tokio()`] runs a future on a Tokio runtime, returning a handle that Folo tasks can
+//!   await.
+//! * [`spawn_on_folo()`] runs a future on a Folo runtime, returning a handle that Tokio tasks can
+//!   await.
+//! * [`channel()`] creates a channel whose endpoints can be used by tasks of either runtime.
+//! * [`from_tokio()`] wraps any other future that is completed by Tokio threads (e.g. waiting on a
+//!   Tokio synchronization primitive), so Folo tasks can await it.
+//!
+//! Waking a Folo task from a thread that is not an async worker thread merely marks the task as
+//! awakened. If its worker thread is parked waiting for I/O, it only notices the task once the
+//! wait times out. Everything in this module that Folo tasks await also wakes up the worker thread
+//! of the awaiting task when woken from a Tokio thread, so results are delivered without delay.
+//! Tokio tasks need no such help, as Tokio wakers work from any thread.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use folo::bridge::tokio::spawn_on_tokio;
+//!
+//! #[folo::main]
+//! async fn main() {
+//!     let tokio = tokio::runtime::Runtime::new().unwrap();
+//!
+//!     // For example, a query via a database driver that is written against Tokio.
+//!     let rows = spawn_on_tokio(tokio.handle(), async { 42 }).await;
+//!
+//!     println!("{rows} rows");
+//!
+//!     tokio.shutdown_background();
+//! }
+//! ```
+
+use crate::{
+    rt::{
+        coop, current_async_agent, local_join::unwrap_join_result, remote_waker::RemoteWaker,
+        JoinError, JoinResult, RemoteJoinHandle, RuntimeClient,
+    },
+    sync::mpsc::SendError,
+};
+use futures::Stream;
+use pin_project::pin_project;
+use std::{
+    fmt::{self, Debug, Formatter},
+    future::{poll_fn, Future},
+    pin::Pin,
+    task::{ready, Context, Poll, Waker},
+};
+use tokio::{runtime::Handle, sync::mpsc, task};
+
+/// Spawns a task to execute a future on a Tokio runtime, returning a handle that can be awaited by
+/// Folo tasks.
+///
+/// The task continues even if the handle is dropped.
+pub fn spawn_on_tokio<F>(runtime: &Handle, future: F) -> TokioJoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    TokioJoinHandle {
+        inner: runtime.spawn(future),
+    }
+}
+
+/// Spawns a task to execute a future on any async worker thread of a Folo runtime, creating the
+/// future via closure. The returned handle can be awaited by Tokio tasks.
+///
+/// The future itself does not have to be thread-safe. However, the closure must be.
+pub fn spawn_on_folo<FN, F, R>(runtime: &RuntimeClient, future_fn: FN) -> RemoteJoinHandle<R>
+where
+    FN: FnOnce() -> F + Send + 'static,
+    F: Future<Output = R> + 'static,
+    R: Send + 'static,
+{
+    runtime.spawn_on_any(future_fn)
+}
+
+/// Allows a task spawned via [`spawn_on_tokio()`] to be awaited by Folo tasks.
+///
+/// Awaiting the handle directly yields the result of the task. If the task may have been aborted,
+/// use `result()` instead, which reports cancellation as `JoinError::Cancelled`. If the task
+/// panicked, awaiting directly resumes the panic.
+#[derive(Debug)]
+pub struct TokioJoinHandle<R> {
+    inner: task::JoinHandle<R>,
+}
+
+impl<R> TokioJoinHandle<R> {
+    /// Requests the task to be aborted. The task is dropped at its next yield point. If the task
+    /// has already completed, this has no effect.
+    pub fn abort(&self) {
+        self.inner.abort();
+    }
+
+    /// Whether the task has completed, either by producing a result or by being aborted.
+    pub fn is_finished(&self) -> bool {
+        self.inner.is_finished()
+    }
+
+    /// Returns a future that resolves to the result of the task or to the reason why the task
+    /// failed to produce a result.
+    pub fn result(mut self) -> impl Future<Output = JoinResult<R>> {
+        poll_fn(move |cx| self.poll_result(cx))
+    }
+
+    fn poll_result(&mut self, cx: &mut Context<'_>) -> Poll<JoinResult<R>> {
+        let result = ready!(poll_woken_by_tokio(cx, |cx| Pin::new(&mut self.inner).poll(cx)));
+
+        Poll::Ready(result.map_err(|e| match e.try_into_panic() {
+            Ok(payload) => JoinError::Panic(payload),
+            Err(_) => JoinError::Cancelled,
+        }))
+    }
+}
+
+impl<R> Future for TokioJoinHandle<R> {
+    type Output = R;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<R> {
+        self.poll_result(cx).map(unwrap_join_result)
+    }
+}
+
+/// Creates a bounded channel whose endpoints can be used by tasks of both Folo and Tokio, in either
+/// direction. Both endpoints may move between threads.
+///
+/// Sending waits for the receiver to make room if `capacity` values are already buffered.
+///
+/// To feed values into Folo from threads that do not use any async runtime, see
+/// [`sync::mpsc::shared_channel()`][crate::sync::mpsc::shared_channel].
+///
+/// # Panics
+///
+/// Panics if the capacity is zero.
+pub fn channel<T: Send>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    let (tx, rx) = mpsc::channel(capacity);
+
+    (Sender { inner: tx }, Receiver { inner: rx })
+}
+
+/// Sends values to a [`channel()`] from tasks of either runtime. Clone it to create more senders.
+///
+/// The receiver observes the end of the stream once all senders have been dropped.
+pub struct Sender<T> {
+    inner: mpsc::Sender<T>,
+}
+
+impl<T> Sender<T> {
+    /// Sends a value, waiting for the receiver to make room if the channel is at capacity. Fails if
+    /// the receiver has been dropped, returning the value back to the caller.
+    pub async fn send(&self, value: T) -> Result<(), SendError<T>> {
+        from_tokio(self.inner.send(value))
+            .await
+            .map_err(|e| SendError(e.0))
+    }
+
+    /// Whether the receiver has been dropped, in which case sending values is pointless.
+    pub fn is_closed(&self) -> bool {
+        self.inner.is_closed()
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> Debug for Sender<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        // The values are not necessarily `Debug`, so we only show the state of the channel.
+        f.debug_struct("Sender")
+            .field("is_closed", &self.inner.is_closed())
+            .finish()
+    }
+}
+
+/// Receives the values sent to a [`channel()`], in the order they were sent, by a task of either
+/// runtime. Can also be consumed as a [`Stream`].
+///
+/// Dropping the receiver drops the buffered values and makes further sends fail.
+pub struct Receiver<T> {
+    inner: mpsc::Receiver<T>,
+}
+
+impl<T> Receiver<T> {
+    /// Receives the next value, waiting for one to be sent if the channel is empty. Returns `None`
+    /// once the channel is empty and all senders have been dropped.
+    pub async fn recv(&mut self) -> Option<T> {
+        poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
+    /// Polls for the next value, registering the waker of the current task to be woken when one
+    /// is sent. Returns `None` once the channel is empty and all senders have been dropped.
+    pub fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        coop::poll_budgeted(cx, |cx| {
+            poll_woken_by_tokio(cx, |cx| self.inner.poll_recv(cx))
+        })
+    }
+}
+
+impl<T> Stream for Receiver<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.get_mut().poll_recv(cx)
+    }
+}
+
+impl<T> Debug for Receiver<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        // The values are not necessarily `Debug`, so we only show the state of the channel.
+        f.debug_struct("Receiver")
+            .field("len", &self.inner.len())
+            .finish()
+    }
+}
+
+/// Wraps a future that may be woken from Tokio threads, so Folo tasks can await it. Has no effect
+/// if the future is polled by a Tokio task.
+///
+/// This is only needed for futures that wait for something that happens on Tokio threads (e.g. a
+/// `tokio::sync::Notify` notified by a Tokio task). Futures that are driven by Folo itself do not
+/// need it and everything in this module that waits for Tokio already applies it.
+pub fn from_tokio<F>(future: F) -> FromTokio<F>
+where
+    F: Future,
+{
+    FromTokio { inner: future }
+}
+
+/// A future that also wakes up the Folo worker thread of the awaiting task when woken from a Tokio
+/// thread. Created via [`from_tokio()`].
+#[pin_project]
+#[derive(Debug)]
+pub struct FromTokio<F> {
+    #[pin]
+    inner: F,
+}
+
+impl<F> FromTokio<F> {
+    pub fn into_inner(self) -> F {
+        self.inner
+    }
+}
+
+impl<F> Future for FromTokio<F>
+where
+    F: Future,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        let inner = self.project().inner;
+
+        poll_woken_by_tokio(cx, |cx| inner.poll(cx))
+    }
+}
+
+/// Polls something that may be woken from a Tokio thread. On async worker threads, the waker given
+/// to it also wakes up the I/O driver of the current thread, in case it is parked waiting for I/O
+/// by the time the wake-up arrives.
+fn poll_woken_by_tokio<R>(
+    cx: &mut Context<'_>,
+    poll: impl FnOnce(&mut Context<'_>) -> Poll<R>,
+) -> Poll<R> {
+    match current_async_agent::try_with_io(|io| io.waker()) {
+        Some(io_waker) => {
+            let waker = Waker::from(RemoteWaker::new(io_waker, cx.waker().clone()));
+            poll(&mut Context::from_waker(&waker))
+        }
+        None => poll(cx),
+    }
+}
@@ -1,3 +1,7 @@
 mod ptr_hash;
+mod sharded_kv;
+mod sharded_local;
 
-pub use ptr_hash::*;
\ No newline at end of file
+pub use ptr_hash::*;
+pub use sharded_kv::*;
+pub use sharded_local::*;
@@ -0,0 +1,260 @@
+use crate::{
+    metrics::{observe, Event, EventBuilder},
+    rt::{current_async_agent, current_runtime, RuntimeClient, TaskMeta},
+};
+use core_affinity::CoreId;
+use std::{
+    any::Any,
+    cell::RefCell,
+    collections::HashMap,
+    fmt::{self, Debug, Formatter},
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+use xxhash_rust::xxh3::Xxh3;
+
+/// An in-memory key-value store partitioned across the async worker threads of the runtime, in
+/// the thread-per-core "shared nothing" style.
+///
+/// Every key is owned by exactly one worker (determined by the hash of the key) and the value is
+/// only ever accessed on that worker, so no locks are involved. Operations on keys owned by the
+/// current worker execute immediately. Operations on keys owned by another worker are sent to the
+/// owner as tasks and the result is sent back, so they cost a cross-thread round trip.
+///
+/// To get the most out of this, route the work for a key to the worker that owns it (see
+/// [`is_local()`][Self::is_local]) instead of fetching the data from other workers.
+///
+/// The handle is cheap to clone and can be sent to any thread. The data is released when the last
+/// handle is dropped.
+///
+/// # Example
+///
+/// ```
+/// use folo::collections::ShardedKv;
+///
+/// #[folo::main]
+/// async fn main() {
+///     let store = ShardedKv::<String, u64>::new();
+///
+///     store.insert("apples".to_string(), 5).await;
+///     store.update("apples".to_string(), |count| *count.unwrap() += 1).await;
+///
+///     assert_eq!(store.get("apples".to_string()).await, Some(6));
+/// }
+/// ```
+pub struct ShardedKv<K, V> {
+    inner: Arc<Inner>,
+
+    _types: PhantomData<fn() -> (K, V)>,
+}
+
+impl<K, V> ShardedKv<K, V>
+where
+    K: Hash + Eq + Send + 'static,
+    V: Send + 'static,
+{
+    /// Creates a new empty store, with one shard on each async worker thread of the runtime that
+    /// owns the current thread.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the current thread is not owned by the Folo runtime.
+    pub fn new() -> Self {
+        let runtime = current_runtime::with(RuntimeClient::clone);
+        let shard_processors = runtime.processor_ids().into();
+
+        Self {
+            inner: Arc::new(Inner {
+                id: NEXT_STORE_ID.fetch_add(1, Ordering::Relaxed),
+                runtime,
+                shard_processors,
+            }),
+            _types: PhantomData,
+        }
+    }
+
+    /// Returns a clone of the value associated with the key, if any.
+    pub async fn get(&self, key: K) -> Option<V>
+    where
+        V: Clone,
+    {
+        self.on_owner(key, |shard, key| shard.get(&key).cloned())
+            .await
+    }
+
+    /// Associates the value with the key, returning the previous value, if any.
+    pub async fn insert(&self, key: K, value: V) -> Option<V> {
+        self.on_owner(key, |shard, key| shard.insert(key, value))
+            .await
+    }
+
+    /// Removes the value associated with the key, returning it if it existed.
+    pub async fn remove(&self, key: K) -> Option<V> {
+        self.on_owner(key, |shard, key| shard.remove(&key)).await
+    }
+
+    /// Executes a closure on the worker that owns the key, giving it mutable access to the value
+    /// associated with the key (if any). This allows read-modify-write operations to be performed
+    /// with a single round trip.
+    ///
+    /// The closure must not access the same store.
+    pub async fn update<F, R>(&self, key: K, f: F) -> R
+    where
+        F: FnOnce(Option<&mut V>) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        self.on_owner(key, |shard, key| f(shard.get_mut(&key)))
+            .await
+    }
+
+    /// Returns the total number of entries in all the shards. Other workers may modify their
+    /// shards while this is being calculated, so the result is only a snapshot.
+    pub async fn len(&self) -> usize {
+        let id = self.inner.id;
+
+        let count_shard = move || async move { with_shard::<K, V, _>(id, |shard| shard.len()) };
+        let counts = self.inner.runtime.spawn_on_all(|| count_shard);
+
+        futures::future::join_all(counts.into_vec())
+            .await
+            .into_iter()
+            .sum()
+    }
+
+    /// Whether the key is owned by the current worker thread, in which case operations on it do
+    /// not require cross-thread communication.
+    pub fn is_local(&self, key: &K) -> bool {
+        current_processor_id() == Some(self.owner_of(key))
+    }
+
+    fn owner_of(&self, key: &K) -> CoreId {
+        let mut hasher = Xxh3::new();
+        key.hash(&mut hasher);
+
+        let index = (hasher.finish() % self.inner.shard_processors.len() as u64) as usize;
+        self.inner.shard_processors[index]
+    }
+
+    async fn on_owner<F, R>(&self, key: K, f: F) -> R
+    where
+        F: FnOnce(&mut HashMap<K, V>, K) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let owner = self.owner_of(&key);
+        let id = self.inner.id;
+
+        if current_processor_id() == Some(owner) {
+            observe!(LOCAL_OPERATIONS.observe_unit());
+            return with_shard(id, |shard| f(shard, key));
+        }
+
+        observe!(REMOTE_OPERATIONS.observe_unit());
+
+        self.inner
+            .runtime
+            .spawn_on_processor(owner, TaskMeta::anonymous(), move || async move {
+                with_shard(id, |shard| f(shard, key))
+            })
+            .await
+    }
+}
+
+impl<K, V> Default for ShardedKv<K, V>
+where
+    K: Hash + Eq + Send + 'static,
+    V: Send + 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V> Clone for ShardedKv<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+            _types: PhantomData,
+        }
+    }
+}
+
+impl<K, V> Debug for ShardedKv<K, V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShardedKv")
+            .field("id", &self.inner.id)
+            .field("shard_count", &self.inner.shard_processors.len())
+            .finish()
+    }
+}
+
+struct Inner {
+    // Identifies the shards of this store in the per-thread shard registry.
+    id: u64,
+
+    runtime: RuntimeClient,
+
+    // Shard N is owned by the async worker of processor N in this list.
+    shard_processors: Box<[CoreId]>,
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        // If the runtime is stopping, the shards are released along with the worker threads.
+        if self.runtime.is_stopping() {
+            return;
+        }
+
+        let id = self.id;
+
+        self.runtime.spawn_on_all(|| {
+            move || async move {
+                SHARDS.with_borrow_mut(|shards| shards.remove(&id));
+            }
+        });
+    }
+}
+
+/// Executes a closure with the current thread's shard of the specified store, creating the shard
+/// if this is the first time the store is used on this thread.
+fn with_shard<K, V, R>(id: u64, f: impl FnOnce(&mut HashMap<K, V>) -> R) -> R
+where
+    K: 'static,
+    V: 'static,
+{
+    // We take the shard out of the registry before calling the closure, so the registry is not
+    // borrowed while the closure runs.
+    let shard = SHARDS.with_borrow_mut(|shards| {
+        Rc::clone(shards.entry(id).or_insert_with(|| {
+            Rc::new(RefCell::new(HashMap::<K, V>::new())) as Rc<RefCell<dyn Any>>
+        }))
+    });
+
+    let mut shard = shard.borrow_mut();
+    let shard = shard
+        .downcast_mut::<HashMap<K, V>>()
+        .expect("store ID always refers to a shard of the same type");
+
+    f(shard)
+}
+
+fn current_processor_id() -> Option<CoreId> {
+    current_async_agent::try_with(|agent| agent.processor_id())
+}
+
+static NEXT_STORE_ID: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    // The shards of all stores owned by the current thread, by store ID.
+    static SHARDS: RefCell<HashMap<u64, Rc<RefCell<dyn Any>>>> = RefCell::new(HashMap::new());
+
+    static LOCAL_OPERATIONS: Event = EventBuilder::new("collections_sharded_kv_local_ops")
+        .build();
+
+    static REMOTE_OPERATIONS: Event = EventBuilder::new("collections_sharded_kv_remote_ops")
+        .build();
+}
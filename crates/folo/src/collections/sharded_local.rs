@@ -0,0 +1,221 @@
+use crate::{
+    metrics::{observe, Event, EventBuilder},
+    rt::{current_async_agent, current_runtime, RuntimeClient},
+};
+use std::{
+    any::Any,
+    cell::RefCell,
+    collections::HashMap,
+    fmt::{self, Debug, Formatter},
+    future::Future,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+/// Per-worker state in the thread-per-core style: one instance of `T` for each async worker thread
+/// of the runtime, created on that worker the first time it is accessed there.
+///
+/// Tasks access the instance of the worker they are executing on (their "shard") directly, without
+/// any locking or cross-thread communication. The shards can also be visited from any task, which
+/// executes a closure on every worker (e.g. to aggregate per-worker counters).
+///
+/// `T` does not need to be thread-safe because each instance is only ever accessed on the worker
+/// thread that created it. The handle itself is cheap to clone and can be sent to any thread. The
+/// shards are released when the last handle is dropped.
+///
+/// # Example
+///
+/// ```
+/// use folo::collections::ShardedLocal;
+///
+/// #[folo::main]
+/// async fn main() {
+///     let requests_handled = ShardedLocal::new(|| 0_u64);
+///
+///     requests_handled.with_mut(|count| *count += 1);
+///
+///     let total: u64 = requests_handled.visit_all(|count| *count).await.iter().sum();
+///     assert_eq!(total, 1);
+/// }
+/// ```
+pub struct ShardedLocal<T> {
+    inner: Arc<Inner>,
+
+    // Creates the shard of a worker, on that worker.
+    factory: Arc<dyn Fn() -> T + Send + Sync>,
+}
+
+impl<T> ShardedLocal<T>
+where
+    T: 'static,
+{
+    /// Creates a new set of shards, with one shard for each async worker thread of the runtime that
+    /// owns the current thread. Each shard is created via the factory function on its own worker,
+    /// the first time it is accessed there.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the current thread is not owned by the Folo runtime.
+    pub fn new(factory: impl Fn() -> T + Send + Sync + 'static) -> Self {
+        let runtime = current_runtime::with(RuntimeClient::clone);
+
+        Self {
+            inner: Arc::new(Inner {
+                id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+                runtime,
+            }),
+            factory: Arc::new(factory),
+        }
+    }
+
+    /// Executes a closure with a shared reference to the shard of the current worker thread.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the current thread is not an async worker thread of the runtime or if the shard
+    /// is already mutably borrowed (i.e. if called from within `with_mut()` on the same instance).
+    pub fn with<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&T) -> R,
+    {
+        let shard = self.local_shard();
+        let shard = shard.borrow();
+
+        f(&shard)
+    }
+
+    /// Executes a closure with an exclusive reference to the shard of the current worker thread.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the current thread is not an async worker thread of the runtime or if the shard
+    /// is already borrowed (i.e. if called from within `with()` or `with_mut()` on the same
+    /// instance).
+    pub fn with_mut<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut T) -> R,
+    {
+        let shard = self.local_shard();
+        let mut shard = shard.borrow_mut();
+
+        f(&mut shard)
+    }
+
+    /// Executes a closure on every worker thread with an exclusive reference to that worker's
+    /// shard, returning the results in worker index order. Shards that do not exist yet are
+    /// created first.
+    ///
+    /// Other tasks may modify their shards while this is in progress, so the results are not a
+    /// consistent snapshot across workers.
+    pub fn visit_all<F, R>(&self, f: F) -> impl Future<Output = Box<[R]>>
+    where
+        F: Fn(&mut T) -> R + Send + Sync + 'static,
+        R: Send + 'static,
+    {
+        observe!(VISITS.observe_unit());
+
+        let id = self.inner.id;
+        let factory = Arc::clone(&self.factory);
+        let f = Arc::new(f);
+
+        self.inner.runtime.for_each_worker(move |_| {
+            let factory = Arc::clone(&factory);
+            let f = Arc::clone(&f);
+
+            async move {
+                let shard = shard_of(id, &*factory);
+                let mut shard = shard.borrow_mut();
+
+                f(&mut shard)
+            }
+        })
+    }
+
+    fn local_shard(&self) -> Rc<RefCell<T>> {
+        assert!(
+            current_async_agent::is_some(),
+            "ShardedLocal can only be accessed from an async worker thread"
+        );
+
+        shard_of(self.inner.id, &*self.factory)
+    }
+}
+
+impl<T> Clone for ShardedLocal<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+            factory: Arc::clone(&self.factory),
+        }
+    }
+}
+
+impl<T> Debug for ShardedLocal<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShardedLocal")
+            .field("id", &self.inner.id)
+            .finish()
+    }
+}
+
+struct Inner {
+    // Identifies the shards of this instance in the per-thread shard registry.
+    id: u64,
+
+    runtime: RuntimeClient,
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        // If the runtime is stopping, the shards are released along with the worker threads.
+        if self.runtime.is_stopping() {
+            return;
+        }
+
+        let id = self.id;
+
+        self.runtime.spawn_on_all(|| {
+            move || async move {
+                SHARDS.with_borrow_mut(|shards| shards.remove(&id));
+            }
+        });
+    }
+}
+
+/// Returns the current thread's shard of the specified instance, creating the shard if this is the
+/// first time the instance is used on this thread.
+fn shard_of<T: 'static>(id: u64, factory: &dyn Fn() -> T) -> Rc<RefCell<T>> {
+    let existing = SHARDS.with_borrow(|shards| shards.get(&id).cloned());
+
+    let shard = match existing {
+        Some(shard) => shard,
+        None => {
+            // We call the factory while the registry is not borrowed, in case it accesses other
+            // instances. It must not access this instance, which does not exist yet.
+            observe!(SHARDS_CREATED.observe_unit());
+            let shard = Rc::new(RefCell::new(factory())) as Rc<dyn Any>;
+
+            SHARDS.with_borrow_mut(|shards| Rc::clone(shards.entry(id).or_insert(shard)))
+        }
+    };
+
+    shard
+        .downcast::<RefCell<T>>()
+        .expect("instance ID always refers to a shard of the same type")
+}
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    // The shards of all instances that exist on the current thread, by instance ID.
+    static SHARDS: RefCell<HashMap<u64, Rc<dyn Any>>> = RefCell::new(HashMap::new());
+
+    static SHARDS_CREATED: Event = EventBuilder::new("collections_sharded_local_shards_created")
+        .build();
+
+    static VISITS: Event = EventBuilder::new("collections_sharded_local_visits")
+        .build();
+}
@@ -0,0 +1,7 @@
+//! Loading of configuration files, with automatic reloading when the file changes.
+
+mod error;
+mod watch;
+
+pub use error::*;
+pub use watch::*;
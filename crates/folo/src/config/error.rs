@@ -0,0 +1,19 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] crate::io::Error),
+
+    #[error("failed to deserialize configuration: {0}")]
+    Deserialize(#[from] serde_json::Error),
+
+    /// The configuration was rejected by the validation of [`WatchBuilder`][super::WatchBuilder].
+    #[error("invalid configuration: {0}")]
+    Invalid(String),
+
+    #[error("environment variable {name} has invalid value {value:?}")]
+    InvalidEnvironmentVariable { name: &'static str, value: String },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
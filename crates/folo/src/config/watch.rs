@@ -0,0 +1,221 @@
+use crate::{
+    config::{Error, Result},
+    fs,
+    metrics::{observe, Event, EventBuilder},
+    rt::{self, SynchronousTaskType},
+    sync::watch::{self, Receiver, Sender},
+    time::{Clock, Delay, PeriodicTimer},
+};
+use futures::StreamExt;
+use serde::de::DeserializeOwned;
+use std::{
+    fmt::{self, Debug, Display, Formatter},
+    marker::PhantomData,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+use tracing::{event, Level};
+
+/// Loads a JSON configuration file and keeps it up to date, reloading it whenever the file
+/// changes. The latest successfully loaded configuration is available via the returned watch
+/// [`Receiver`], which can also be used to await changes.
+///
+/// Changes are detected by periodically polling the modification time and size of the file, not
+/// via notifications from the file system, so a change is noticed up to one poll interval late.
+///
+/// If a reload fails (e.g. because the file contains invalid data), the previous configuration
+/// remains published and the failure is logged. Only the initial load returns an error.
+///
+/// Uses the default poll interval and debounce duration and no validation. Use [`WatchBuilder`] to
+/// customize these.
+///
+/// # Panics
+///
+/// Panics if the current thread is not an async worker thread owned by a Folo runtime.
+pub async fn watch<T>(path: impl AsRef<Path>) -> Result<Receiver<T>>
+where
+    T: DeserializeOwned + Send + Sync + 'static,
+{
+    WatchBuilder::new(path).build().await
+}
+
+/// Configures and starts watching a configuration file. See [`watch()`].
+pub struct WatchBuilder<T> {
+    path: PathBuf,
+    poll_interval: Duration,
+    debounce: Duration,
+    validate: Option<Box<dyn Fn(&T) -> Result<()>>>,
+
+    _value: PhantomData<fn() -> T>,
+}
+
+impl<T> WatchBuilder<T>
+where
+    T: DeserializeOwned + Send + Sync + 'static,
+{
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            debounce: DEFAULT_DEBOUNCE,
+            validate: None,
+            _value: PhantomData,
+        }
+    }
+
+    /// How often to check the modification time and size of the file for changes.
+    pub fn poll_interval(mut self, value: Duration) -> Self {
+        self.poll_interval = value;
+        self
+    }
+
+    /// How long the file must remain unchanged before it is reloaded. Editors and deployment tools
+    /// often write files in multiple steps, so reloading on the first sign of change could easily
+    /// pick up a partially written file.
+    pub fn debounce(mut self, value: Duration) -> Self {
+        self.debounce = value;
+        self
+    }
+
+    /// Checks every loaded configuration before it is published, beyond it merely being valid
+    /// JSON of the right shape. A configuration that fails the check is treated like one that
+    /// fails to deserialize: the initial load returns [`Error::Invalid`] and a reload keeps the
+    /// previous configuration.
+    pub fn validate<F, E>(mut self, f: F) -> Self
+    where
+        F: Fn(&T) -> std::result::Result<(), E> + 'static,
+        E: Display,
+    {
+        self.validate = Some(Box::new(move |value| {
+            f(value).map_err(|e| Error::Invalid(e.to_string()))
+        }));
+        self
+    }
+
+    /// Loads the configuration file and starts polling it for changes on the current async worker
+    /// thread.
+    ///
+    /// The watcher stops once the returned [`Receiver`] and all its clones have been dropped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the current thread is not an async worker thread owned by a Folo runtime.
+    pub async fn build(self) -> Result<Receiver<T>> {
+        // We take the stamp before loading, so if the file changes while we load it, we will
+        // notice the change and load it again.
+        let stamp = read_stamp(self.path.clone()).await;
+
+        let (tx, rx) = watch::channel(self.load().await?);
+
+        _ = rt::spawn(watch_for_changes(self, tx, stamp));
+
+        Ok(rx)
+    }
+
+    async fn load(&self) -> Result<T> {
+        let bytes = fs::read(&self.path).await?;
+        let value = serde_json::from_slice(&bytes)?;
+
+        if let Some(validate) = &self.validate {
+            validate(&value)?;
+        }
+
+        Ok(value)
+    }
+}
+
+impl<T> Debug for WatchBuilder<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WatchBuilder")
+            .field("path", &self.path)
+            .field("poll_interval", &self.poll_interval)
+            .field("debounce", &self.debounce)
+            .field("validate", &self.validate.is_some())
+            .finish()
+    }
+}
+
+async fn watch_for_changes<T>(builder: WatchBuilder<T>, tx: Sender<T>, mut stamp: Option<FileStamp>)
+where
+    T: DeserializeOwned + Send + Sync + 'static,
+{
+    let clock = Clock::new();
+    let mut timer = PeriodicTimer::with_clock(&clock, builder.poll_interval);
+
+    while timer.next().await.is_some() {
+        if tx.is_closed() {
+            // Nobody is interested in the configuration anymore.
+            return;
+        }
+
+        let mut candidate = read_stamp(builder.path.clone()).await;
+
+        if candidate == stamp {
+            continue;
+        }
+
+        // The file has changed. Wait for it to settle down before reloading.
+        loop {
+            Delay::with_clock(&clock, builder.debounce).await;
+
+            let latest = read_stamp(builder.path.clone()).await;
+
+            if latest == candidate {
+                break;
+            }
+
+            candidate = latest;
+        }
+
+        stamp = candidate;
+
+        match builder.load().await {
+            Ok(value) => {
+                observe!(RELOAD_SUCCEEDED.observe_unit());
+                tx.send(value);
+            }
+            Err(e) => {
+                observe!(RELOAD_FAILED.observe_unit());
+
+                event!(
+                    Level::WARN,
+                    message = "failed to reload configuration file; keeping previous configuration",
+                    path = %builder.path.display(),
+                    error = %e
+                );
+            }
+        }
+    }
+}
+
+/// Identifies a specific version of a file. If the stamp changes, the file has changed.
+/// If the file does not exist or cannot be probed, there is no stamp.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct FileStamp {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+async fn read_stamp(path: PathBuf) -> Option<FileStamp> {
+    // Probing file metadata is a blocking operation, so we do it on a synchronous worker thread.
+    rt::spawn_sync(SynchronousTaskType::Syscall, move || {
+        let metadata = std::fs::metadata(path).ok()?;
+
+        Some(FileStamp {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+        })
+    })
+    .await
+}
+
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(5);
+const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(500);
+
+thread_local! {
+    static RELOAD_SUCCEEDED: Event = EventBuilder::new("config_reload_succeeded")
+        .build();
+
+    static RELOAD_FAILED: Event = EventBuilder::new("config_reload_failed")
+        .build();
+}
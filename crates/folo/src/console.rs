@@ -0,0 +1,367 @@
+//! Live inspection of a running runtime. The console endpoint periodically takes a snapshot of the
+//! tasks and worker threads of the runtime and streams it to every client connected to a local TCP
+//! socket, for display in a terminal UI (see the `console` example) or for collection by other
+//! tools.
+//!
+//! # Wire format
+//!
+//! Each snapshot is sent as one line of JSON (terminated by `\n`), with the following structure.
+//! Durations are in milliseconds. Fields may be added in future versions of the same format; the
+//! `version` changes only if existing fields change meaning or are removed.
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "timestamp_ms": 1700000000000,
+//!   "workers": [
+//!     {
+//!       "index": 0,
+//!       "processor_id": 0,
+//!       "responded": true,
+//!       "busy_time_ms": 1500,
+//!       "parked_time_ms": 8500,
+//!       "utilization": 0.15,
+//!       "cycles": 12345,
+//!       "io_operations_in_flight": 3,
+//!       "tasks": [
+//!         {
+//!           "id": 42,
+//!           "name": "connection-handler",
+//!           "state": "waiting",
+//!           "age_ms": 2000,
+//!           "since_last_poll_ms": 15,
+//!           "polls": 17,
+//!           "busy_time_ms": 4,
+//!           "memory_bytes": 1536
+//!         }
+//!       ]
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! * `timestamp_ms` is the time the snapshot was taken, in milliseconds since the Unix epoch.
+//! * `responded` is `false` if the worker did not respond in time, typically because a task is
+//!   blocking it. The only task listed for such a worker is the one being polled.
+//! * `busy_time_ms`, `parked_time_ms`, `utilization` and `cycles` of a worker are totals since the
+//!   runtime started, as in [`WorkerStats`][crate::rt::WorkerStats].
+//! * `state` of a task is one of `running`, `ready` or `waiting`.
+//! * `memory_bytes` of a task is its frame size plus the memory charged to it, as in
+//!   [`TaskDump::memory()`].
+//! * `name`, `age_ms` and `since_last_poll_ms` of a task are `null` if not known.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use folo::console::ConsoleBuilder;
+//! use folo::rt::RuntimeBuilder;
+//! use std::net::SocketAddr;
+//!
+//! let runtime = RuntimeBuilder::new().build().unwrap();
+//!
+//! let console = ConsoleBuilder::new()
+//!     .start(&runtime, SocketAddr::from(([127, 0, 0, 1], 6669)))
+//!     .unwrap();
+//!
+//! // ...
+//!
+//! console.stop();
+//! runtime.stop();
+//! runtime.wait();
+//! ```
+
+use crate::{
+    io,
+    rt::{RuntimeClient, TaskDump, TaskState, WorkerStats},
+};
+use serde::Serialize;
+use std::{
+    io::Write,
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tracing::{event, Level};
+
+/// The version of the wire format, included in every snapshot.
+pub const WIRE_FORMAT_VERSION: u32 = 1;
+
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(1);
+const DEFAULT_DUMP_TIMEOUT: Duration = Duration::from_millis(500);
+
+// How often the console thread checks for new clients and for being stopped.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+// A client that does not accept a snapshot within this time is disconnected, so one slow client
+// cannot hold up the others.
+const CLIENT_WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Configures and starts a console endpoint.
+#[derive(Debug)]
+pub struct ConsoleBuilder {
+    interval: Duration,
+    dump_timeout: Duration,
+}
+
+impl ConsoleBuilder {
+    pub fn new() -> Self {
+        Self {
+            interval: DEFAULT_INTERVAL,
+            dump_timeout: DEFAULT_DUMP_TIMEOUT,
+        }
+    }
+
+    /// How often to send a snapshot to the connected clients. The default is one second.
+    pub fn interval(mut self, value: Duration) -> Self {
+        self.interval = value;
+        self
+    }
+
+    /// How long to wait for the worker threads to describe their tasks when taking a snapshot,
+    /// as in [`RuntimeClient::dump()`]. The default is 500 milliseconds.
+    pub fn dump_timeout(mut self, value: Duration) -> Self {
+        self.dump_timeout = value;
+        self
+    }
+
+    /// Starts listening for clients on the specified address, on a dedicated thread. Use port 0 to
+    /// let the OS assign a port, which you can then look up via
+    /// [`local_addr()`][ConsoleServer::local_addr].
+    ///
+    /// The endpoint has no authentication, so bind it to a loopback address unless the network is
+    /// trusted.
+    pub fn start(self, runtime: &RuntimeClient, addr: SocketAddr) -> io::Result<ConsoleServer> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+
+        let local_addr = listener.local_addr()?;
+        let stopping = Arc::new(AtomicBool::new(false));
+
+        let join_handle = thread::Builder::new()
+            .name("folo-console".to_string())
+            .spawn({
+                let runtime = runtime.clone();
+                let stopping = Arc::clone(&stopping);
+
+                move || serve(listener, runtime, stopping, self)
+            })?;
+
+        event!(Level::INFO, message = "console endpoint started", %local_addr);
+
+        Ok(ConsoleServer {
+            local_addr,
+            stopping,
+            join_handle: Some(join_handle),
+        })
+    }
+}
+
+impl Default for ConsoleBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A running console endpoint. Stops when dropped, disconnecting all clients.
+#[derive(Debug)]
+pub struct ConsoleServer {
+    local_addr: SocketAddr,
+    stopping: Arc<AtomicBool>,
+    join_handle: Option<JoinHandle<()>>,
+}
+
+impl ConsoleServer {
+    /// The address the endpoint is listening on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Stops the endpoint and waits for its thread to exit.
+    pub fn stop(mut self) {
+        self.stop_core();
+    }
+
+    fn stop_core(&mut self) {
+        self.stopping.store(true, Ordering::Relaxed);
+
+        if let Some(join_handle) = self.join_handle.take() {
+            join_handle.join().expect("console thread panicked");
+        }
+    }
+}
+
+impl Drop for ConsoleServer {
+    fn drop(&mut self) {
+        self.stop_core();
+    }
+}
+
+fn serve(
+    listener: TcpListener,
+    runtime: RuntimeClient,
+    stopping: Arc<AtomicBool>,
+    options: ConsoleBuilder,
+) {
+    let mut clients = Vec::new();
+    let mut next_snapshot = Instant::now();
+
+    while !stopping.load(Ordering::Relaxed) && !runtime.is_stopping() {
+        accept_clients(&listener, &mut clients);
+
+        if Instant::now() < next_snapshot || clients.is_empty() {
+            thread::sleep(POLL_INTERVAL);
+            continue;
+        }
+
+        next_snapshot = Instant::now() + options.interval;
+
+        let mut line = snapshot(&runtime, options.dump_timeout);
+        line.push('\n');
+
+        clients.retain_mut(
+            |client: &mut TcpStream| match client.write_all(line.as_bytes()) {
+                Ok(()) => true,
+                Err(e) => {
+                    event!(Level::DEBUG, message = "console client disconnected", error = %e);
+                    false
+                }
+            },
+        );
+    }
+}
+
+fn accept_clients(listener: &TcpListener, clients: &mut Vec<TcpStream>) {
+    loop {
+        match listener.accept() {
+            Ok((client, peer_addr)) => {
+                // The listener is non-blocking but we want blocking writes with a timeout.
+                if client.set_nonblocking(false).is_err()
+                    || client
+                        .set_write_timeout(Some(CLIENT_WRITE_TIMEOUT))
+                        .is_err()
+                {
+                    continue;
+                }
+
+                _ = client.set_nodelay(true);
+
+                event!(Level::DEBUG, message = "console client connected", %peer_addr);
+                clients.push(client);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => return,
+            Err(e) => {
+                event!(Level::WARN, message = "failed to accept console client", error = %e);
+                return;
+            }
+        }
+    }
+}
+
+/// Takes a snapshot of the runtime and renders it in the wire format.
+fn snapshot(runtime: &RuntimeClient, dump_timeout: Duration) -> String {
+    let dump = runtime.dump(dump_timeout);
+    let stats = runtime.worker_stats();
+
+    let timestamp_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |x| x.as_millis() as u64);
+
+    // Both are in worker index order.
+    let workers = dump
+        .workers()
+        .iter()
+        .zip(stats.iter())
+        .map(|(worker, stats)| {
+            WireWorker::new(
+                worker.processor_id(),
+                worker.responded(),
+                stats,
+                worker.tasks(),
+            )
+        })
+        .collect();
+
+    let snapshot = WireSnapshot {
+        version: WIRE_FORMAT_VERSION,
+        timestamp_ms,
+        workers,
+    };
+
+    serde_json::to_string(&snapshot).expect("snapshot is always serializable")
+}
+
+#[derive(Serialize)]
+struct WireSnapshot<'a> {
+    version: u32,
+    timestamp_ms: u64,
+    workers: Vec<WireWorker<'a>>,
+}
+
+#[derive(Serialize)]
+struct WireWorker<'a> {
+    index: usize,
+    processor_id: usize,
+    responded: bool,
+    busy_time_ms: u64,
+    parked_time_ms: u64,
+    utilization: f64,
+    cycles: u64,
+    io_operations_in_flight: u64,
+    tasks: Vec<WireTask<'a>>,
+}
+
+impl<'a> WireWorker<'a> {
+    fn new(
+        processor_id: usize,
+        responded: bool,
+        stats: &WorkerStats,
+        tasks: &'a [TaskDump],
+    ) -> Self {
+        Self {
+            index: stats.worker_index(),
+            processor_id,
+            responded,
+            busy_time_ms: stats.busy_time().as_millis() as u64,
+            parked_time_ms: stats.parked_time().as_millis() as u64,
+            utilization: stats.utilization(),
+            cycles: stats.cycles(),
+            io_operations_in_flight: stats.io_operations_in_flight(),
+            tasks: tasks.iter().map(WireTask::new).collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct WireTask<'a> {
+    id: u64,
+    name: Option<&'a str>,
+    state: &'static str,
+    age_ms: Option<u64>,
+    since_last_poll_ms: Option<u64>,
+    polls: u64,
+    busy_time_ms: u64,
+    memory_bytes: u64,
+}
+
+impl<'a> WireTask<'a> {
+    fn new(task: &'a TaskDump) -> Self {
+        Self {
+            id: task.id().as_u64(),
+            name: task.name(),
+            state: match task.state() {
+                TaskState::Running => "running",
+                TaskState::Ready => "ready",
+                TaskState::Waiting => "waiting",
+            },
+            age_ms: task.age().map(|x| x.as_millis() as u64),
+            since_last_poll_ms: task.since_last_poll().map(|x| x.as_millis() as u64),
+            polls: task.polls(),
+            busy_time_ms: task.busy_time().as_millis() as u64,
+            memory_bytes: task.memory(),
+        }
+    }
+}
@@ -1,17 +1,31 @@
+use crate::rt::{current_async_agent, remote_waker::RemoteWaker, EmbeddedRuntime, RuntimeBuilder};
 use criterion::async_executor::AsyncExecutor;
 use futures::future::{BoxFuture, LocalBoxFuture};
 use std::{
-    sync::mpsc,
+    cell::RefCell,
+    future::Future,
+    pin::pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc,
+    },
+    task::{Context, Poll, Wake, Waker},
     thread::{self, JoinHandle},
+    time::Duration,
 };
 
+// How long the embedded worker waits for work while the benchmark future is not ready to be polled.
+// Wakeups from other threads interrupt the wait, so this only limits how long a single turn lasts.
+const EXECUTOR_TURN_MAX_WAIT: Duration = Duration::from_millis(10);
+
 /// Enables usage of the Folo runtime in Criterion benchmarks.
 ///
 /// # Special considerations
 ///
 /// Folo does not allow the entrypoint thread to be used to execute async tasks. You MUST call
 /// `folo::rt::spawn_on_any()` as the first thing in your benchmark function. This will move the
-/// logic to a worker thread, where you can operate normally.
+/// logic to a worker thread, where you can operate normally. [`FoloExecutor`] does not have this
+/// limitation.
 ///
 /// The Folo runtime is reused between benchmarks - the adapter automatically creates the runtime
 /// and reuses it on each benchmark execution.
@@ -38,7 +52,7 @@ pub struct FoloAdapter {}
 
 impl AsyncExecutor for FoloAdapter {
     fn block_on<T>(&self, future: impl std::future::Future<Output = T>) -> T {
-        _ = crate::rt::RuntimeBuilder::new()
+        _ = RuntimeBuilder::new()
             // This allows `spawn_on_any()` to work correctly and shovel work to Folo.
             // It also causes the runtime to be reused - it is only created on the first build.
             .ad_hoc_entrypoint()
@@ -54,6 +68,88 @@ impl AsyncExecutor for FoloAdapter {
     }
 }
 
+/// Executes Criterion benchmarks directly on a Folo async worker, so an existing async benchmark
+/// suite can be switched to Folo by only changing the executor it passes to `to_async()`.
+///
+/// Unlike with [`FoloAdapter`], there is no need to move the benchmark to a worker thread first -
+/// the thread that runs the benchmark becomes an embedded async worker of a single-worker runtime
+/// (see [`EmbeddedRuntime`]), so all Folo APIs (spawning, I/O, timers, ...) can be used directly in
+/// the benchmark. The runtime is created on first use and reused by later benchmarks on the same
+/// thread.
+///
+/// # Example
+///
+/// ```ignore
+/// use folo::criterion::FoloExecutor;
+///
+/// c.bench_function("spawn_and_await", |b| {
+///     // Previously: b.to_async(tokio::runtime::Runtime::new().unwrap())
+///     b.to_async(FoloExecutor::default()).iter(|| async {
+///         folo::rt::spawn(async { 42 }).await
+///     });
+/// });
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FoloExecutor {}
+
+impl AsyncExecutor for FoloExecutor {
+    fn block_on<T>(&self, future: impl Future<Output = T>) -> T {
+        EMBEDDED_RUNTIME.with_borrow_mut(|runtime| {
+            let runtime = runtime.get_or_insert_with(|| {
+                assert!(
+                    !current_async_agent::is_some(),
+                    "FoloExecutor cannot be used on a thread that is already a Folo worker"
+                );
+
+                RuntimeBuilder::new()
+                    .max_processors(1)
+                    .build_embedded()
+                    .expect("failed to start embedded Folo runtime for benchmark")
+            });
+
+            // The benchmark future is polled directly on this thread, in between turns of the
+            // embedded worker. If it is woken from another thread, the worker must also be woken
+            // up, as it may be waiting for work.
+            let woken = Arc::new(WokenFlag(AtomicBool::new(true)));
+            let io_waker = current_async_agent::with_io(|io| io.waker());
+            let waker = Waker::from(RemoteWaker::new(io_waker, Waker::from(Arc::clone(&woken))));
+            let mut cx = Context::from_waker(&waker);
+
+            let mut future = pin!(future);
+
+            loop {
+                if woken.0.swap(false, Ordering::AcqRel) {
+                    if let Poll::Ready(result) = future.as_mut().poll(&mut cx) {
+                        return result;
+                    }
+                }
+
+                let max_wait = if woken.0.load(Ordering::Acquire) {
+                    Duration::ZERO
+                } else {
+                    EXECUTOR_TURN_MAX_WAIT
+                };
+
+                runtime.turn(max_wait);
+            }
+        })
+    }
+}
+
+// Records that the benchmark future has been woken up and needs to be polled again.
+struct WokenFlag(AtomicBool);
+
+impl Wake for WokenFlag {
+    fn wake(self: Arc<Self>) {
+        self.0.store(true, Ordering::Release);
+    }
+}
+
+thread_local! {
+    // The runtime that `FoloExecutor` executes benchmarks on, if it has been used on this thread.
+    static EMBEDDED_RUNTIME: RefCell<Option<EmbeddedRuntime>> = const { RefCell::new(None) };
+}
+
 /// Allows you to compare async code execution on the Folo runtime and an arbitrary competing
 /// runtime. This adapter is useful because typical Rust runtimes take over the entrypoint thread
 /// and use it as one of their async worker threads. Folo does not! Instead, Folo always uses only
@@ -97,7 +193,7 @@ impl ComparativeAdapter {
         let (folo_jobs_tx, folo_jobs_rx) = mpsc::channel();
         let (competitor_jobs_tx, competitor_jobs_rx) = mpsc::channel();
 
-        let folo_client = crate::rt::RuntimeBuilder::new().build().unwrap();
+        let folo_client = RuntimeBuilder::new().build().unwrap();
 
         let competing_runtime_thread = thread::spawn(move || {
             let runtime = (create_competing_runtime)();
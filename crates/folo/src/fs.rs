@@ -1,3 +1,29 @@
+mod direct_file;
+mod file;
+mod file_management;
+mod file_writer;
 mod functions;
+mod io_priority;
+mod metadata;
+mod mmap;
+mod read_ahead_reader;
+mod read_dir;
+mod record_reader;
+mod temp_dir;
+mod temp_file;
+mod walk_dir;
 
+pub use direct_file::*;
+pub use file::*;
+pub use file_management::*;
+pub use file_writer::*;
 pub use functions::*;
+pub use io_priority::*;
+pub use metadata::*;
+pub use mmap::*;
+pub use read_ahead_reader::*;
+pub use read_dir::*;
+pub use record_reader::*;
+pub use temp_dir::*;
+pub use temp_file::*;
+pub use walk_dir::*;
@@ -0,0 +1,103 @@
+use crate::{
+    fs::{File, IoPriority},
+    io::{self, AlignedBuffer},
+};
+use std::path::Path;
+use windows::Win32::Storage::FileSystem::{CREATE_ALWAYS, FILE_FLAG_NO_BUFFERING, OPEN_EXISTING};
+
+/// A file opened for unbuffered (direct) I/O on the current async worker thread, for storage
+/// engines that manage their own caching and need to bypass the cache of the operating system.
+///
+/// Data is transferred directly between the storage device and [`AlignedBuffer`]s, which
+/// guarantee that the memory and the length of every transfer are aligned as the storage device
+/// requires. The offsets of reads and writes must be multiples of [`AlignedBuffer::ALIGNMENT`] as
+/// well, which is checked when the operation is started.
+///
+/// The file is bound to the async worker thread that opened it and cannot be moved to another
+/// thread.
+#[derive(Debug)]
+pub struct DirectFile {
+    file: File,
+}
+
+impl DirectFile {
+    /// Creates a new file for unbuffered reading and writing, truncating it if it already exists.
+    pub async fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self {
+            file: File::open_with(path, CREATE_ALWAYS, FILE_FLAG_NO_BUFFERING).await?,
+        })
+    }
+
+    /// Opens an existing file for unbuffered reading and writing.
+    pub async fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self {
+            file: File::open_with(path, OPEN_EXISTING, FILE_FLAG_NO_BUFFERING).await?,
+        })
+    }
+
+    /// Reads from the file at the specified offset into the active region of the buffer.
+    ///
+    /// Returns the buffer with the active region set to the bytes that were read. This may be fewer
+    /// bytes than requested if the end of the file was reached, with a length of 0 indicating that
+    /// the offset is at or beyond the end of the file.
+    pub async fn read_at(&self, offset: usize, buffer: AlignedBuffer) -> io::Result<AlignedBuffer> {
+        ensure_aligned(offset, &buffer)?;
+
+        let buffer = self.file.read_at(offset, buffer.into_inner()).await?;
+
+        Ok(AlignedBuffer::from_completed(buffer))
+    }
+
+    /// Writes the active region of the buffer to the file at the specified offset.
+    ///
+    /// Returns the buffer with the active region set to the bytes that were written.
+    pub async fn write_at(
+        &self,
+        offset: usize,
+        buffer: AlignedBuffer,
+    ) -> io::Result<AlignedBuffer> {
+        ensure_aligned(offset, &buffer)?;
+
+        let buffer = self.file.write_at(offset, buffer.into_inner()).await?;
+
+        Ok(AlignedBuffer::from_completed(buffer))
+    }
+
+    /// Truncates or extends the file to the specified length, which does not need to be aligned.
+    /// See [`File::set_len()`].
+    pub fn set_len(&self, len: usize) -> io::Result<()> {
+        self.file.set_len(len)
+    }
+
+    /// Sets the priority of the I/O operations performed via this file. See
+    /// [`File::set_io_priority()`].
+    pub fn set_io_priority(&self, priority: IoPriority) -> io::Result<()> {
+        self.file.set_io_priority(priority)
+    }
+
+    /// Waits until the metadata of the file has reached the storage device. The data itself
+    /// bypasses the cache of the operating system but may still be cached by the storage device
+    /// until this is called. See [`File::sync_all()`].
+    pub async fn sync_all(&self) -> io::Result<()> {
+        self.file.sync_all().await
+    }
+
+    /// Reserves space on the storage device for the file. See [`File::preallocate()`].
+    pub async fn preallocate(&self, len: usize) -> io::Result<()> {
+        self.file.preallocate(len).await
+    }
+}
+
+fn ensure_aligned(offset: usize, buffer: &AlignedBuffer) -> io::Result<()> {
+    // The type of the buffer ensures alignment of the length, except for a buffer returned by a
+    // read that reached the end of the file, which must not be reused without setting the length.
+    if offset % AlignedBuffer::ALIGNMENT != 0 || buffer.len() % AlignedBuffer::ALIGNMENT != 0 {
+        return Err(io::Error::InvalidOptions(format!(
+            "offset {offset} and length {} of unbuffered I/O must be multiples of {}",
+            buffer.len(),
+            AlignedBuffer::ALIGNMENT
+        )));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,461 @@
+use crate::{
+    fs::{read_buffer_from_file, FileWriter, IoPriority},
+    io::{self, reserve_handle, Buffer, OperationResultExt},
+    mem::isolation::Isolated,
+    rt::{current_async_agent, spawn_sync, SynchronousTaskType},
+    windows::OwnedHandle,
+};
+use negative_impl::negative_impl;
+use std::{ffi::CString, future::Future, mem, path::Path, rc::Rc};
+use windows::{
+    core::PCSTR,
+    Win32::{
+        Foundation::{DuplicateHandle, DUPLICATE_SAME_ACCESS, HANDLE},
+        Storage::FileSystem::{
+            CreateFileA, FileAllocationInfo, FileEndOfFileInfo, FileIoPriorityHintInfo,
+            FlushFileBuffers, SetFileInformationByHandle, WriteFile, CREATE_ALWAYS,
+            FILE_ALLOCATION_INFO, FILE_CREATION_DISPOSITION, FILE_END_OF_FILE_INFO,
+            FILE_FLAGS_AND_ATTRIBUTES, FILE_FLAG_OVERLAPPED, FILE_GENERIC_READ, FILE_GENERIC_WRITE,
+            FILE_IO_PRIORITY_HINT_INFO, FILE_SHARE_READ, OPEN_EXISTING,
+        },
+        System::Threading::GetCurrentProcess,
+    },
+};
+
+/// A file opened for asynchronous I/O on the current async worker thread.
+///
+/// All reads and writes are performed by the I/O driver of the current async worker thread as
+/// overlapped I/O operations - no synchronous worker thread is blocked while the data is in flight.
+///
+/// The file can be accessed either positionally via [`read_at()`][Self::read_at] and
+/// [`write_at()`][Self::write_at] or sequentially via [`read()`][Self::read] and
+/// [`write()`][Self::write], which start at the current position of the file and advance it by the
+/// number of bytes transferred. The position is tracked by the `File` itself, not by the OS, so it
+/// is not affected by positional operations.
+///
+/// The file is bound to the async worker thread that opened it and cannot be moved to another
+/// thread.
+#[derive(Debug)]
+pub struct File {
+    handle: Rc<OwnedHandle<HANDLE>>,
+
+    // The offset at which the next sequential read or write will be performed.
+    position: usize,
+}
+
+impl File {
+    /// Creates a new file for reading and writing, truncating it if it already exists.
+    pub async fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::open_with(path, CREATE_ALWAYS, FILE_FLAGS_AND_ATTRIBUTES::default()).await
+    }
+
+    /// Opens an existing file for reading and writing.
+    pub async fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::open_with(path, OPEN_EXISTING, FILE_FLAGS_AND_ATTRIBUTES::default()).await
+    }
+
+    /// Takes ownership of a file handle created outside Folo (e.g. by another library, inherited
+    /// from the parent process or duplicated from another process) and binds it to the I/O driver
+    /// of the current async worker thread. The file is closed when the `File` is dropped,
+    /// including when this fails.
+    ///
+    /// # Safety
+    ///
+    /// The handle must be a valid file handle opened for overlapped I/O (`FILE_FLAG_OVERLAPPED`),
+    /// which the caller owns and does not use or close after this call. If it is already associated
+    /// with an I/O completion port, operations on it fall back to blocking I/O (see
+    /// [`RuntimeBuilder::io_blocking_fallback()`][crate::rt::RuntimeBuilder::io_blocking_fallback]).
+    pub unsafe fn from_raw_handle(handle: HANDLE) -> io::Result<Self> {
+        // From now on the handle does not leave the current thread.
+        let handle = Rc::new(OwnedHandle::new(handle));
+
+        current_async_agent::with_io(|io| io.bind_io_primitive(&**handle))?;
+
+        Ok(Self {
+            handle,
+            position: 0,
+        })
+    }
+
+    /// Reads from the file at the specified offset into the active region of the buffer.
+    ///
+    /// Returns the buffer with the active region set to the bytes that were read. This may be fewer
+    /// bytes than requested, with a length of 0 indicating that the offset is at or beyond the end
+    /// of the file.
+    pub async fn read_at(
+        &self,
+        offset: usize,
+        buffer: Buffer<Isolated>,
+    ) -> io::Result<Buffer<Isolated>> {
+        read_buffer_from_file(Rc::clone(&self.handle), offset, buffer).await
+    }
+
+    /// Writes the active region of the buffer to the file at the specified offset.
+    ///
+    /// Returns the buffer with the active region set to the bytes that were written. This may be
+    /// fewer bytes than requested.
+    pub async fn write_at(
+        &self,
+        offset: usize,
+        buffer: Buffer<Isolated>,
+    ) -> io::Result<Buffer<Isolated>> {
+        write_buffer_to_file(Rc::clone(&self.handle), offset, buffer).await
+    }
+
+    /// Reads from the current position of the file into the active region of the buffer and
+    /// advances the position by the number of bytes read.
+    ///
+    /// Returns the buffer with the active region set to the bytes that were read. This may be fewer
+    /// bytes than requested, with a length of 0 indicating the end of the file.
+    pub async fn read(&mut self, buffer: Buffer<Isolated>) -> io::Result<Buffer<Isolated>> {
+        let buffer = self.read_at(self.position, buffer).await?;
+        self.position += buffer.len();
+
+        Ok(buffer)
+    }
+
+    /// Writes the active region of the buffer to the current position of the file and advances
+    /// the position by the number of bytes written.
+    ///
+    /// Returns the buffer with the active region set to the bytes that were written. This may be
+    /// fewer bytes than requested.
+    pub async fn write(&mut self, buffer: Buffer<Isolated>) -> io::Result<Buffer<Isolated>> {
+        let buffer = self.write_at(self.position, buffer).await?;
+        self.position += buffer.len();
+
+        Ok(buffer)
+    }
+
+    /// Reads from the file at the specified offset into multiple buffers, filling the active
+    /// regions of the buffers in order.
+    ///
+    /// Returns the buffers with the active regions set to the bytes that were read. Trailing
+    /// buffers are empty if fewer bytes were read than fit into the buffers.
+    pub async fn read_vectored_at(
+        &self,
+        offset: usize,
+        buffers: Vec<Buffer<Isolated>>,
+    ) -> io::Result<Vec<Buffer<Isolated>>> {
+        transfer_vectored(offset, buffers, |offset, buffer| {
+            read_buffer_from_file(Rc::clone(&self.handle), offset, buffer)
+        })
+        .await
+    }
+
+    /// Writes the active regions of multiple buffers to the file at the specified offset, as if
+    /// they were one contiguous buffer.
+    ///
+    /// Returns the buffers with the active regions set to the bytes that were written.
+    pub async fn write_vectored_at(
+        &self,
+        offset: usize,
+        buffers: Vec<Buffer<Isolated>>,
+    ) -> io::Result<Vec<Buffer<Isolated>>> {
+        transfer_vectored(offset, buffers, |offset, buffer| {
+            write_buffer_to_file(Rc::clone(&self.handle), offset, buffer)
+        })
+        .await
+    }
+
+    /// Reads from the current position of the file into multiple buffers, filling the active
+    /// regions of the buffers in order, and advances the position by the number of bytes read.
+    pub async fn read_vectored(
+        &mut self,
+        buffers: Vec<Buffer<Isolated>>,
+    ) -> io::Result<Vec<Buffer<Isolated>>> {
+        let buffers = self.read_vectored_at(self.position, buffers).await?;
+        self.position += buffers.iter().map(Buffer::len).sum::<usize>();
+
+        Ok(buffers)
+    }
+
+    /// Writes the active regions of multiple buffers to the current position of the file and
+    /// advances the position by the number of bytes written.
+    pub async fn write_vectored(
+        &mut self,
+        buffers: Vec<Buffer<Isolated>>,
+    ) -> io::Result<Vec<Buffer<Isolated>>> {
+        let buffers = self.write_vectored_at(self.position, buffers).await?;
+        self.position += buffers.iter().map(Buffer::len).sum::<usize>();
+
+        Ok(buffers)
+    }
+
+    /// The offset at which the next sequential read or write will be performed.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Sets the offset at which the next sequential read or write will be performed. The position
+    /// may be beyond the end of the file - writing there extends the file.
+    pub fn set_position(&mut self, position: usize) {
+        self.position = position;
+    }
+
+    /// Truncates or extends the file to the specified length. If the file is extended, the new
+    /// bytes read as zero.
+    ///
+    /// The position of the file is not changed, even if it is now beyond the end of the file.
+    pub fn set_len(&self, len: usize) -> io::Result<()> {
+        let info = FILE_END_OF_FILE_INFO {
+            EndOfFile: len as i64,
+        };
+
+        // This only updates file metadata (the OS lazily zero-fills any extended region), so it is
+        // fast enough to call directly from the async worker thread.
+        // SAFETY: Handle liveness is ensured by our ownership of the handle and the info structure
+        // outlives the call.
+        unsafe {
+            SetFileInformationByHandle(
+                **self.handle,
+                FileEndOfFileInfo,
+                &info as *const _ as *const _,
+                mem::size_of::<FILE_END_OF_FILE_INFO>() as u32,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Sets the priority of the I/O operations performed via this file from now on, relative to
+    /// other I/O operations on the same storage device. See [`IoPriority`].
+    ///
+    /// The priority applies to this handle only - other handles to the same file keep their own
+    /// priority.
+    pub fn set_io_priority(&self, priority: IoPriority) -> io::Result<()> {
+        let info = FILE_IO_PRIORITY_HINT_INFO {
+            PriorityHint: priority.to_native(),
+        };
+
+        // This only updates the state of the handle, so it is fast enough to call directly from
+        // the async worker thread.
+        // SAFETY: Handle liveness is ensured by our ownership of the handle and the info structure
+        // outlives the call.
+        unsafe {
+            SetFileInformationByHandle(
+                **self.handle,
+                FileIoPriorityHintInfo,
+                &info as *const _ as *const _,
+                mem::size_of::<FILE_IO_PRIORITY_HINT_INFO>() as u32,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Waits until all data and metadata of the file that has been written so far has reached the
+    /// storage device, so it survives a crash of the process or the operating system.
+    pub async fn sync_all(&self) -> io::Result<()> {
+        let handle = self.duplicate_handle()?;
+
+        // Flushing waits for the storage device, so we do it on a synchronous worker thread.
+        spawn_sync(SynchronousTaskType::Syscall, move || -> io::Result<_> {
+            // SAFETY: Handle liveness is ensured by our ownership of the handle.
+            Ok(unsafe { FlushFileBuffers(*handle) }?)
+        })
+        .await
+    }
+
+    /// Waits until all data of the file that has been written so far has reached the storage
+    /// device, so it survives a crash of the process or the operating system. Metadata that is not
+    /// needed to read the data back (e.g. the modification time) may not have been written yet.
+    ///
+    /// Windows does not offer a cheaper way to flush only the data, so this is currently the same
+    /// as [`sync_all()`][Self::sync_all].
+    pub async fn sync_data(&self) -> io::Result<()> {
+        self.sync_all().await
+    }
+
+    /// Reserves space on the storage device for at least `len` bytes of the file, so writes up to
+    /// this length do not need to allocate space one piece at a time, which fragments the file.
+    /// The length of the file is not changed.
+    ///
+    /// The operating system releases reserved space beyond the end of the file when the file is
+    /// closed, so extend the file (by writing to it or via [`set_len()`][Self::set_len]) to keep
+    /// the space.
+    pub async fn preallocate(&self, len: usize) -> io::Result<()> {
+        let handle = self.duplicate_handle()?;
+
+        // Allocating space may involve updating large amounts of file system metadata, so we do it
+        // on a synchronous worker thread.
+        spawn_sync(SynchronousTaskType::Syscall, move || -> io::Result<_> {
+            let info = FILE_ALLOCATION_INFO {
+                AllocationSize: len as i64,
+            };
+
+            // SAFETY: Handle liveness is ensured by our ownership of the handle and the info
+            // structure outlives the call.
+            unsafe {
+                SetFileInformationByHandle(
+                    *handle,
+                    FileAllocationInfo,
+                    &info as *const _ as *const _,
+                    mem::size_of::<FILE_ALLOCATION_INFO>() as u32,
+                )?;
+            }
+
+            Ok(())
+        })
+        .await
+    }
+
+    /// Creates a new handle for the file that can be handed to a synchronous worker thread. The
+    /// handle of the file itself must not leave the current thread, as the file may be closed
+    /// while a synchronous worker thread is still using it (if the caller stops waiting for it).
+    fn duplicate_handle(&self) -> io::Result<OwnedHandle<HANDLE>> {
+        let mut duplicate = HANDLE::default();
+
+        // SAFETY: All we need to be concerned about is passing in valid arguments, which we do.
+        // The new handle is ours to close anywhere.
+        unsafe {
+            let process = GetCurrentProcess();
+
+            DuplicateHandle(
+                process,
+                **self.handle,
+                process,
+                &mut duplicate,
+                0,
+                false,
+                DUPLICATE_SAME_ACCESS,
+            )?;
+
+            Ok(OwnedHandle::new(duplicate))
+        }
+    }
+
+    /// The handle of the file, bound to the I/O driver of the current async worker thread.
+    pub(crate) fn handle(&self) -> &Rc<OwnedHandle<HANDLE>> {
+        &self.handle
+    }
+
+    /// Creates a writer that coalesces many small positional writes into fewer, larger write
+    /// operations. See [`FileWriter`].
+    pub fn writer(&self) -> FileWriter {
+        FileWriter::new(Rc::clone(&self.handle))
+    }
+
+    /// Opens a file with the specified disposition and flags, in addition to the flags that every
+    /// file requires.
+    pub(super) async fn open_with(
+        path: impl AsRef<Path>,
+        disposition: FILE_CREATION_DISPOSITION,
+        flags: FILE_FLAGS_AND_ATTRIBUTES,
+    ) -> io::Result<Self> {
+        Self::open_with_access(path, 0, disposition, flags).await
+    }
+
+    /// Opens a file with the specified access rights (in addition to reading and writing),
+    /// disposition and flags.
+    pub(super) async fn open_with_access(
+        path: impl AsRef<Path>,
+        additional_access: u32,
+        disposition: FILE_CREATION_DISPOSITION,
+        flags: FILE_FLAGS_AND_ATTRIBUTES,
+    ) -> io::Result<Self> {
+        let path_cstr = CString::new(path.as_ref().to_str().unwrap()).unwrap();
+
+        reserve_handle().await?;
+
+        // Opening the file is a blocking operation, so we kick it off to a synchronous worker
+        // thread to avoid blocking the async workers with this slow call.
+        let handle = spawn_sync(SynchronousTaskType::Syscall, move || -> io::Result<_> {
+            // SAFETY: The path string outlives the call and the handle is ours to close anywhere.
+            unsafe {
+                Ok(OwnedHandle::new(CreateFileA(
+                    PCSTR::from_raw(path_cstr.as_ptr() as *const u8),
+                    (FILE_GENERIC_READ | FILE_GENERIC_WRITE).0 | additional_access,
+                    FILE_SHARE_READ,
+                    None,
+                    disposition,
+                    FILE_FLAG_OVERLAPPED | flags,
+                    None,
+                )?))
+            }
+        })
+        .await?;
+
+        // From now on the handle does not leave the current thread.
+        let handle = Rc::new(handle);
+
+        current_async_agent::with_io(|io| io.bind_io_primitive(&**handle))?;
+
+        Ok(Self {
+            handle,
+            position: 0,
+        })
+    }
+}
+
+#[negative_impl]
+impl !Send for File {}
+#[negative_impl]
+impl !Sync for File {}
+
+/// Performs a vectored file operation as a sequence of single-buffer operations at consecutive
+/// offsets, stopping at the first operation that transfers fewer bytes than requested.
+///
+/// The native scatter/gather file APIs (ReadFileScatter and WriteFileGather) require unbuffered
+/// I/O with page-sized and page-aligned buffers, which is incompatible with general purpose
+/// buffers, so we cannot delegate this to the operating system.
+async fn transfer_vectored<F, R>(
+    mut offset: usize,
+    buffers: Vec<Buffer<Isolated>>,
+    mut transfer: F,
+) -> io::Result<Vec<Buffer<Isolated>>>
+where
+    F: FnMut(usize, Buffer<Isolated>) -> R,
+    R: Future<Output = io::Result<Buffer<Isolated>>>,
+{
+    let mut results = Vec::with_capacity(buffers.len());
+    let mut is_short = false;
+
+    for mut buffer in buffers {
+        if is_short {
+            buffer.set_len(0);
+            results.push(buffer);
+            continue;
+        }
+
+        let requested = buffer.len();
+        let buffer = transfer(offset, buffer).await?;
+
+        offset += buffer.len();
+        is_short = buffer.len() < requested;
+
+        results.push(buffer);
+    }
+
+    Ok(results)
+}
+
+/// Writes the active region of the buffer to a file at a given offset.
+///
+/// Returns the buffer with the active region set to the bytes that were written.
+pub(crate) async fn write_buffer_to_file(
+    file_handle: Rc<OwnedHandle<HANDLE>>,
+    offset: usize,
+    buffer: Buffer<Isolated>,
+) -> io::Result<Buffer<Isolated>> {
+    let mut operation = current_async_agent::with_io(|io| io.new_operation(buffer));
+    operation.set_offset(offset);
+    operation.set_primitive(**file_handle);
+
+    // SAFETY: For safe usage of the I/O driver API, we are required to pass the `overlapped`
+    // argument to a native I/O call under all circumstances, to trigger an I/O completion. We do.
+    // We are also not allowed to use any of the callback arguments after the callback, even if
+    // the Rust compiler might allow us to.
+    unsafe {
+        operation
+            .begin(move |buffer, overlapped, bytes_transferred_immediately| {
+                Ok(WriteFile(
+                    **file_handle,
+                    Some(&*buffer),
+                    Some(bytes_transferred_immediately as *mut _),
+                    Some(overlapped),
+                )?)
+            })
+            .await
+    }
+    .into_inner()
+}
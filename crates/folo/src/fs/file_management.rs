@@ -0,0 +1,120 @@
+use crate::{
+    io,
+    metrics::{observe, Event, EventBuilder},
+    rt::{spawn_sync, SynchronousTaskType},
+    time::{Clock, Delay},
+};
+use std::{fs, path::Path, time::Duration};
+use tracing::{event, Level};
+use windows::Win32::Foundation::{
+    ERROR_DIR_NOT_EMPTY, ERROR_LOCK_VIOLATION, ERROR_SHARING_VIOLATION, WIN32_ERROR,
+};
+
+// Other processes (e.g. antivirus scanners, search indexers or backup agents) often open files
+// briefly, during which we may be unable to modify them. We retry a few times, with increasing
+// delays, before giving up.
+const MAX_ATTEMPTS: u32 = 5;
+const INITIAL_RETRY_DELAY: Duration = Duration::from_millis(10);
+
+// Errors that indicate that another process is briefly using the file.
+const SHARING_ERRORS: &[WIN32_ERROR] = &[ERROR_SHARING_VIOLATION, ERROR_LOCK_VIOLATION];
+
+// When deleting a tree, files that another process still has open are only deleted once that
+// process closes them, so their directory may briefly appear not empty.
+const REMOVE_TREE_ERRORS: &[WIN32_ERROR] = &[
+    ERROR_SHARING_VIOLATION,
+    ERROR_LOCK_VIOLATION,
+    ERROR_DIR_NOT_EMPTY,
+];
+
+/// Deletes a file.
+///
+/// If another process is briefly using the file, the operation is retried a few times before
+/// failing. This applies to all the file management functions of this module.
+pub async fn remove_file(path: impl AsRef<Path>) -> io::Result<()> {
+    let path = path.as_ref().to_path_buf();
+
+    with_retries(SHARING_ERRORS, move || fs::remove_file(&path)).await
+}
+
+/// Renames a file or directory, replacing the destination if it is a file that already exists.
+pub async fn rename(from: impl AsRef<Path>, to: impl AsRef<Path>) -> io::Result<()> {
+    let from = from.as_ref().to_path_buf();
+    let to = to.as_ref().to_path_buf();
+
+    with_retries(SHARING_ERRORS, move || fs::rename(&from, &to)).await
+}
+
+/// Copies the contents and permissions of a file to another file, replacing the destination if it
+/// already exists. Returns the number of bytes copied.
+///
+/// The data is copied by the operating system, which may use optimizations not available to
+/// regular reads and writes (e.g. copying on the remote side for network shares).
+pub async fn copy(from: impl AsRef<Path>, to: impl AsRef<Path>) -> io::Result<u64> {
+    let from = from.as_ref().to_path_buf();
+    let to = to.as_ref().to_path_buf();
+
+    with_retries(SHARING_ERRORS, move || fs::copy(&from, &to)).await
+}
+
+/// Creates a directory and any of its parents that do not exist yet. Succeeds if the directory
+/// already exists.
+pub async fn create_dir_all(path: impl AsRef<Path>) -> io::Result<()> {
+    let path = path.as_ref().to_path_buf();
+
+    with_retries(SHARING_ERRORS, move || fs::create_dir_all(&path)).await
+}
+
+/// Deletes a directory together with all of its contents.
+pub async fn remove_dir_all(path: impl AsRef<Path>) -> io::Result<()> {
+    let path = path.as_ref().to_path_buf();
+
+    with_retries(REMOVE_TREE_ERRORS, move || fs::remove_dir_all(&path)).await
+}
+
+/// Executes a blocking file system operation on a synchronous worker thread, retrying it if it
+/// fails with one of the specified errors. We wait between attempts on the async worker thread,
+/// so no synchronous worker thread is blocked while waiting.
+async fn with_retries<F, R>(retry_errors: &[WIN32_ERROR], f: F) -> io::Result<R>
+where
+    F: Fn() -> std::io::Result<R> + Clone + Send + 'static,
+    R: Send + 'static,
+{
+    let clock = Clock::new();
+    let mut retry_delay = INITIAL_RETRY_DELAY;
+    let mut attempt = 1;
+
+    loop {
+        let result = spawn_sync(SynchronousTaskType::Syscall, f.clone()).await;
+
+        match result {
+            Err(e) if attempt < MAX_ATTEMPTS && is_one_of(&e, retry_errors) => {
+                event!(
+                    Level::DEBUG,
+                    message = "retrying file system operation",
+                    attempt,
+                    error = %e
+                );
+
+                observe!(FS_OPERATION_RETRIES.observe_unit());
+
+                Delay::with_clock(&clock, retry_delay).await;
+
+                retry_delay *= 2;
+                attempt += 1;
+            }
+            result => return Ok(result?),
+        }
+    }
+}
+
+fn is_one_of(error: &std::io::Error, codes: &[WIN32_ERROR]) -> bool {
+    error
+        .raw_os_error()
+        .is_some_and(|raw| codes.iter().any(|code| code.0 as i32 == raw))
+}
+
+thread_local! {
+    static FS_OPERATION_RETRIES: Event = EventBuilder::new("fs_operation_retries")
+        .build();
+}
@@ -0,0 +1,310 @@
+use crate::{
+    fs::write_buffer_to_file,
+    io::{self, Buffer},
+    metrics::{observe, Event, EventBuilder},
+    rt::LocalJoinHandle,
+    windows::OwnedHandle,
+};
+use negative_impl::negative_impl;
+use std::{
+    cell::RefCell,
+    fmt::{self, Debug, Formatter},
+    mem,
+    rc::Rc,
+};
+use windows::Win32::Foundation::HANDLE;
+
+/// Coalesces many small positional writes to a file into fewer, larger write operations. Created
+/// via [`File::writer()`][crate::fs::File::writer].
+///
+/// Writes are buffered in memory and submitted to the operating system once per async worker
+/// cycle. All writes queued during the same cycle that touch or overlap each other are merged into
+/// a single write operation, so an append-heavy workload (e.g. a write-ahead log) that queues many
+/// small records results in only one operation per cycle.
+///
+/// If writes overlap, the one queued later wins. Write operations from one cycle only start after
+/// the ones from the previous cycle have completed, so this also holds across cycles.
+///
+/// Call [`flush()`][Self::flush] to wait for all queued writes to complete and to observe any
+/// errors. Queued writes are still submitted if the writer is dropped without flushing but any
+/// errors are then lost.
+///
+/// # Example
+///
+/// ```no_run
+/// use folo::fs::File;
+///
+/// #[folo::main]
+/// async fn main() {
+///     let file = File::create("wal.log").await.unwrap();
+///     let mut writer = file.writer();
+///
+///     let mut offset = 0;
+///
+///     for record in [&b"first\n"[..], b"second\n", b"third\n"] {
+///         writer.write_at(offset, record);
+///         offset += record.len();
+///     }
+///
+///     // All three records are written to the file in a single operation.
+///     writer.flush().await.unwrap();
+/// }
+/// ```
+pub struct FileWriter {
+    state: Rc<RefCell<WriterState>>,
+}
+
+impl FileWriter {
+    pub(crate) fn new(file_handle: Rc<OwnedHandle<HANDLE>>) -> Self {
+        Self {
+            state: Rc::new(RefCell::new(WriterState {
+                file_handle,
+                pending: Vec::new(),
+                submission_scheduled: false,
+                last_submission: None,
+                error: None,
+            })),
+        }
+    }
+
+    /// Queues the data to be written to the file at the specified offset. The write is submitted
+    /// to the operating system during the next async worker cycle, merged with any other writes
+    /// that touch the same region of the file.
+    pub fn write_at(&mut self, offset: usize, data: &[u8]) {
+        if data.is_empty() {
+            return;
+        }
+
+        observe!(QUEUED_WRITES.observe_unit());
+
+        let mut state = self.state.borrow_mut();
+        merge_write(&mut state.pending, offset, data);
+
+        if !state.submission_scheduled {
+            state.submission_scheduled = true;
+
+            let previous = state.last_submission.take();
+            state.last_submission =
+                Some(crate::rt::spawn(submit(Rc::clone(&self.state), previous)));
+        }
+    }
+
+    /// Waits for all queued writes to be written to the file.
+    ///
+    /// Returns the first error encountered by any write since the previous flush. Note that this
+    /// does not flush operating system caches to persistent storage.
+    pub async fn flush(&mut self) -> io::Result<()> {
+        let last_submission = self.state.borrow_mut().last_submission.take();
+
+        if let Some(last_submission) = last_submission {
+            last_submission.await;
+        }
+
+        match self.state.borrow_mut().error.take() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Debug for FileWriter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let state = self.state.borrow();
+
+        f.debug_struct("FileWriter")
+            .field("pending", &state.pending.len())
+            .field("submission_scheduled", &state.submission_scheduled)
+            .field("failed", &state.error.is_some())
+            .finish()
+    }
+}
+
+#[negative_impl]
+impl !Send for FileWriter {}
+#[negative_impl]
+impl !Sync for FileWriter {}
+
+struct WriterState {
+    file_handle: Rc<OwnedHandle<HANDLE>>,
+
+    // Contiguous regions of the file waiting to be written. They never touch or overlap each other.
+    pending: Vec<PendingWrite>,
+
+    // Whether a submission task has been spawned that has not yet picked up the pending writes.
+    submission_scheduled: bool,
+
+    // The most recently spawned submission task. Each submission task first waits for its
+    // predecessor, so awaiting this waits for all writes queued so far.
+    last_submission: Option<LocalJoinHandle<()>>,
+
+    // The first error encountered since the previous flush.
+    error: Option<io::Error>,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+struct PendingWrite {
+    offset: usize,
+    data: Vec<u8>,
+}
+
+impl PendingWrite {
+    fn end(&self) -> usize {
+        self.offset + self.data.len()
+    }
+}
+
+/// Adds a write to the set of pending writes, merging it with any pending writes it touches or
+/// overlaps. Where the new write overlaps existing data, the new data wins.
+fn merge_write(pending: &mut Vec<PendingWrite>, offset: usize, data: &[u8]) {
+    let end = offset + data.len();
+
+    let (touching, mut unaffected): (Vec<_>, Vec<_>) = mem::take(pending)
+        .into_iter()
+        .partition(|write| write.offset <= end && offset <= write.end());
+
+    let start = touching
+        .iter()
+        .map(|write| write.offset)
+        .fold(offset, usize::min);
+    let merged_end = touching.iter().map(PendingWrite::end).fold(end, usize::max);
+
+    // The touched writes together with the new write always form one contiguous region, so every
+    // byte of the merged buffer is filled in below.
+    let mut merged = vec![0; merged_end - start];
+
+    for write in touching {
+        merged[write.offset - start..write.end() - start].copy_from_slice(&write.data);
+    }
+
+    merged[offset - start..end - start].copy_from_slice(data);
+
+    unaffected.push(PendingWrite {
+        offset: start,
+        data: merged,
+    });
+
+    *pending = unaffected;
+}
+
+async fn submit(state: Rc<RefCell<WriterState>>, previous: Option<LocalJoinHandle<()>>) {
+    // We pick up whatever has been queued by the time the worker gets around to us. Anything
+    // queued after this point is picked up by the next submission.
+    let (file_handle, pending) = {
+        let mut state = state.borrow_mut();
+        state.submission_scheduled = false;
+
+        (Rc::clone(&state.file_handle), mem::take(&mut state.pending))
+    };
+
+    if let Some(previous) = previous {
+        previous.await;
+    }
+
+    observe!(SUBMISSION_WRITES.observe(pending.len() as i64));
+
+    let results = futures::future::join_all(
+        pending
+            .into_iter()
+            .map(|write| write_all(Rc::clone(&file_handle), write)),
+    )
+    .await;
+
+    let mut state = state.borrow_mut();
+
+    for result in results {
+        if let Err(e) = result {
+            state.error.get_or_insert(e);
+        }
+    }
+}
+
+async fn write_all(file_handle: Rc<OwnedHandle<HANDLE>>, write: PendingWrite) -> io::Result<()> {
+    let PendingWrite { mut offset, data } = write;
+    let mut buffer = Buffer::from_boxed_slice(data.into_boxed_slice());
+
+    observe!(WRITE_BYTES.observe(buffer.len() as i64));
+
+    loop {
+        buffer = write_buffer_to_file(Rc::clone(&file_handle), offset, buffer).await?;
+
+        if buffer.is_empty() {
+            return Err(io::Error::LogicError(
+                "file write completed without writing any bytes".to_string(),
+            ));
+        }
+
+        offset += buffer.len();
+        buffer = buffer.use_remainder();
+
+        if buffer.is_empty() {
+            return Ok(());
+        }
+    }
+}
+
+thread_local! {
+    static QUEUED_WRITES: Event = EventBuilder::new("fs_file_writer_queued_writes")
+        .build();
+
+    static SUBMISSION_WRITES: Event = EventBuilder::new("fs_file_writer_submission_writes")
+        .buckets(&[1, 2, 4, 16, 64])
+        .build();
+
+    static WRITE_BYTES: Event = EventBuilder::new("fs_file_writer_write_bytes")
+        .buckets(&[1024, 16 * 1024, 64 * 1024, 1024 * 1024])
+        .build();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(offset: usize, data: &[u8]) -> PendingWrite {
+        PendingWrite {
+            offset,
+            data: data.to_vec(),
+        }
+    }
+
+    #[test]
+    fn appends_are_coalesced() {
+        let mut pending = Vec::new();
+
+        merge_write(&mut pending, 0, b"abc");
+        merge_write(&mut pending, 3, b"def");
+        merge_write(&mut pending, 6, b"g");
+
+        assert_eq!(pending, vec![write(0, b"abcdefg")]);
+    }
+
+    #[test]
+    fn disjoint_writes_are_separate() {
+        let mut pending = Vec::new();
+
+        merge_write(&mut pending, 0, b"abc");
+        merge_write(&mut pending, 10, b"xyz");
+
+        assert_eq!(pending, vec![write(0, b"abc"), write(10, b"xyz")]);
+    }
+
+    #[test]
+    fn later_write_wins_on_overlap() {
+        let mut pending = Vec::new();
+
+        merge_write(&mut pending, 2, b"cdef");
+        merge_write(&mut pending, 0, b"ABCD");
+
+        assert_eq!(pending, vec![write(0, b"ABCDef")]);
+    }
+
+    #[test]
+    fn write_bridging_two_regions_merges_all() {
+        let mut pending = Vec::new();
+
+        merge_write(&mut pending, 0, b"ab");
+        merge_write(&mut pending, 5, b"fg");
+        merge_write(&mut pending, 2, b"CDE");
+
+        assert_eq!(pending, vec![write(0, b"abCDEfg")]);
+    }
+}
@@ -1,14 +1,14 @@
 use crate::{
-    io::{self, Buffer},
+    io::{self, reserve_handle, Buffer},
     mem::isolation::Isolated,
     rt::{current_async_agent, spawn_sync, SynchronousTaskType},
     windows::OwnedHandle,
 };
-use std::{ffi::CString, path::Path, rc::Rc};
+use std::{ffi::CString, future::Future, path::Path, rc::Rc};
 use windows::{
     core::PCSTR,
     Win32::{
-        Foundation::{HANDLE, STATUS_END_OF_FILE},
+        Foundation::{ERROR_HANDLE_EOF, HANDLE, STATUS_END_OF_FILE},
         Storage::FileSystem::{
             CreateFileA, GetFileSizeEx, ReadFile, FILE_FLAG_OVERLAPPED, FILE_FLAG_SEQUENTIAL_SCAN,
             FILE_GENERIC_READ, FILE_SHARE_READ, OPEN_EXISTING,
@@ -30,34 +30,9 @@ const MAX_READ_SIZE_BYTES: usize = 10 * 1024 * 1024;
 
 /// Read the contents of a file to a vector of bytes using one giant buffer for the entire file.
 pub async fn read_large_buffer(path: impl AsRef<Path>) -> io::Result<Vec<u8>> {
-    let path_cstr = CString::new(path.as_ref().to_str().unwrap()).unwrap();
+    let (file_handle, file_size) = open_for_sequential_read(path).await?;
 
     unsafe {
-        // Opening the file and probing its size are blocking operations, so we kick them off to
-        // a synchronous worker thread to avoid blocking the async workers with these slow calls.
-
-        let (file_handle, file_size) =
-            spawn_sync(SynchronousTaskType::Syscall, move || -> io::Result<_> {
-                let file_handle = OwnedHandle::new(CreateFileA(
-                    PCSTR::from_raw(path_cstr.as_ptr() as *const u8),
-                    FILE_GENERIC_READ.0,
-                    FILE_SHARE_READ,
-                    None,
-                    OPEN_EXISTING,
-                    FILE_FLAG_OVERLAPPED | FILE_FLAG_SEQUENTIAL_SCAN,
-                    None,
-                )?);
-
-                // Get the size first to allocate the buffer with the correct size. If the size changes
-                // while we read it, that is fine - this is just the initial allocation and may change.
-                let mut file_size: i64 = 0;
-
-                GetFileSizeEx(*file_handle, &mut file_size as *mut _)?;
-
-                Ok((file_handle, file_size))
-            })
-            .await?;
-
         // Now that we have it on our async worker thread, any further activities can use Rc
         // to share the lifetime between tasks because we know they will not leave this thread.
         let file_handle = Rc::new(file_handle);
@@ -108,45 +83,105 @@ pub async fn read_large_buffer(path: impl AsRef<Path>) -> io::Result<Vec<u8>> {
     }
 }
 
+/// Opens a file for overlapped sequential reading and probes its size.
+pub(crate) async fn open_for_sequential_read(
+    path: impl AsRef<Path>,
+) -> io::Result<(OwnedHandle<HANDLE>, i64)> {
+    let path_cstr = CString::new(path.as_ref().to_str().unwrap()).unwrap();
+
+    reserve_handle().await?;
+
+    // Opening the file and probing its size are blocking operations, so we kick them off to
+    // a synchronous worker thread to avoid blocking the async workers with these slow calls.
+    spawn_sync(SynchronousTaskType::Syscall, move || -> io::Result<_> {
+        // SAFETY: The path string outlives the call and the handle is ours to close anywhere.
+        unsafe {
+            let file_handle = OwnedHandle::new(CreateFileA(
+                PCSTR::from_raw(path_cstr.as_ptr() as *const u8),
+                FILE_GENERIC_READ.0,
+                FILE_SHARE_READ,
+                None,
+                OPEN_EXISTING,
+                FILE_FLAG_OVERLAPPED | FILE_FLAG_SEQUENTIAL_SCAN,
+                None,
+            )?);
+
+            // Get the size first to allocate the buffer with the correct size. If the size changes
+            // while we read it, that is fine - this is just the initial allocation and may change.
+            let mut file_size: i64 = 0;
+
+            GetFileSizeEx(*file_handle, &mut file_size as *mut _)?;
+
+            Ok((file_handle, file_size))
+        }
+    })
+    .await
+}
+
 /// Reads a chunk of bytes from a file at a given offset and fills the provided buffer with them,
 /// appending the bytes to the beginning of the buffer's active region (without changing the
 /// region).
 ///
 /// Returns the buffer in every case, with the action region of the buffer set to the data read.
 /// A zero-sized active region indicates end of file.
-async fn read_buffer_from_file(
+pub(crate) async fn read_buffer_from_file(
     file_handle: Rc<OwnedHandle<HANDLE>>,
     offset: usize,
-    mut buffer: Buffer<Isolated>,
+    buffer: Buffer<Isolated>,
 ) -> io::Result<Buffer<Isolated>> {
+    begin_read_buffer_from_file(file_handle, offset, buffer).await
+}
+
+/// Same as `read_buffer_from_file()` but the read is submitted to the operating system before this
+/// function returns, without waiting for the returned future to be polled. Dropping the returned
+/// future cancels the read.
+pub(crate) fn begin_read_buffer_from_file(
+    file_handle: Rc<OwnedHandle<HANDLE>>,
+    offset: usize,
+    mut buffer: Buffer<Isolated>,
+) -> impl Future<Output = io::Result<Buffer<Isolated>>> {
     if buffer.len() > MAX_READ_SIZE_BYTES {
         buffer.set_len(MAX_READ_SIZE_BYTES);
     }
 
     let mut operation = current_async_agent::with_io(|io| io.new_operation(buffer));
     operation.set_offset(offset);
+    operation.set_primitive(**file_handle);
 
     // SAFETY: For safe usage of the I/O driver API, we are required to pass the `overlapped`
     // argument to a native I/O call under all circumstances, to trigger an I/O completion. We do.
     // We are also not allowed to use any of the callback arguments after the callback, even if
     // the Rust compiler might allow us to.
-    match unsafe {
-        operation
-            .begin(move |buffer, overlapped, bytes_transferred_immediately| {
-                Ok(ReadFile(
-                    **file_handle,
-                    Some(buffer),
-                    Some(bytes_transferred_immediately as *mut _),
-                    Some(overlapped),
-                )?)
-            })
-            .await
-    } {
-        Ok(buffer) => Ok(buffer),
-        Err(io::OperationError {
-            inner: io::Error::Windows(external),
-            buffer,
-        }) if external.code() == STATUS_END_OF_FILE.into() => Ok(buffer),
-        Err(e) => Err(e.into_inner()),
+    let result = unsafe {
+        operation.begin(move |buffer, overlapped, bytes_transferred_immediately| {
+            Ok(ReadFile(
+                **file_handle,
+                Some(buffer),
+                Some(bytes_transferred_immediately as *mut _),
+                Some(overlapped),
+            )?)
+        })
+    };
+
+    async move {
+        match result.await {
+            Ok(buffer) => Ok(buffer),
+            Err(io::OperationError {
+                inner: io::Error::Windows(external),
+                buffer,
+                ..
+            }) if external.code() == STATUS_END_OF_FILE.into() => Ok(buffer),
+            // Reading at or beyond the end of the file may also fail immediately, before any I/O
+            // is started, in which case nothing has been written to the buffer.
+            Err(io::OperationError {
+                inner: io::Error::Windows(external),
+                mut buffer,
+                ..
+            }) if external.code() == ERROR_HANDLE_EOF.into() => {
+                buffer.set_len(0);
+                Ok(buffer)
+            }
+            Err(e) => Err(e.into_inner()),
+        }
     }
 }
@@ -0,0 +1,36 @@
+use windows::Win32::Storage::FileSystem::{
+    IoPriorityHintLow, IoPriorityHintNormal, IoPriorityHintVeryLow, PRIORITY_HINT,
+};
+
+/// The priority of the I/O operations on a file, relative to other I/O operations on the same
+/// storage device. Set via [`File::set_io_priority()`][super::File::set_io_priority].
+///
+/// Lowering the priority of bulk traffic (e.g. compaction, backups or scrubbing) lets the
+/// operating system serve the latency-sensitive operations of the rest of the system first, so the
+/// bulk traffic does not degrade the foreground work that shares the same device.
+///
+/// Windows does not allow user mode code to raise the priority above normal, so there is no
+/// "critical" class - instead, lower the priority of everything that is not critical.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum IoPriority {
+    /// Background work that should only use the device when nothing else needs it. Operations
+    /// at this priority may be delayed significantly while the device is busy.
+    Background,
+
+    /// Work that may yield to normal priority operations but should still make steady progress.
+    Low,
+
+    /// The default priority of all I/O operations.
+    #[default]
+    Normal,
+}
+
+impl IoPriority {
+    pub(crate) fn to_native(self) -> PRIORITY_HINT {
+        match self {
+            IoPriority::Background => IoPriorityHintVeryLow,
+            IoPriority::Low => IoPriorityHintLow,
+            IoPriority::Normal => IoPriorityHintNormal,
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use crate::{
+    io,
+    rt::{spawn_sync, SynchronousTaskType},
+};
+use std::{fs, os::windows::fs::MetadataExt, path::Path, time::SystemTime};
+
+/// Queries the metadata of a file or directory, following symbolic links.
+pub async fn metadata(path: impl AsRef<Path>) -> io::Result<Metadata> {
+    let path = path.as_ref().to_path_buf();
+
+    // Querying metadata is a blocking operation that may need to hit the disk (or the network, for
+    // remote paths), so we do it on a synchronous worker thread.
+    spawn_sync(SynchronousTaskType::Syscall, move || -> io::Result<_> {
+        Ok(Metadata::new(fs::metadata(path)?))
+    })
+    .await
+}
+
+/// Queries the metadata of a file or directory without following symbolic links, so the result
+/// describes the link itself if the path is a symbolic link.
+pub async fn symlink_metadata(path: impl AsRef<Path>) -> io::Result<Metadata> {
+    let path = path.as_ref().to_path_buf();
+
+    spawn_sync(SynchronousTaskType::Syscall, move || -> io::Result<_> {
+        Ok(Metadata::new(fs::symlink_metadata(path)?))
+    })
+    .await
+}
+
+/// Checks whether a file or directory exists at the specified path, following symbolic links.
+///
+/// Returns an error if the existence cannot be determined (e.g. due to lack of permissions), so a
+/// result of `false` means the path definitely does not exist.
+pub async fn exists(path: impl AsRef<Path>) -> io::Result<bool> {
+    let path = path.as_ref().to_path_buf();
+
+    spawn_sync(SynchronousTaskType::Syscall, move || -> io::Result<_> {
+        Ok(path.try_exists()?)
+    })
+    .await
+}
+
+/// Metadata of a file or directory, as returned by [`metadata()`] and [`symlink_metadata()`].
+#[derive(Clone, Debug)]
+pub struct Metadata {
+    inner: fs::Metadata,
+}
+
+impl Metadata {
+    pub(super) fn new(inner: fs::Metadata) -> Self {
+        Self { inner }
+    }
+
+    /// The size of the file in bytes.
+    pub fn len(&self) -> u64 {
+        self.inner.len()
+    }
+
+    /// Whether the size of the file is zero bytes.
+    pub fn is_empty(&self) -> bool {
+        self.inner.len() == 0
+    }
+
+    pub fn is_file(&self) -> bool {
+        self.inner.is_file()
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.inner.is_dir()
+    }
+
+    /// Whether this is the metadata of a symbolic link. Only ever `true` for metadata queried via
+    /// [`symlink_metadata()`].
+    pub fn is_symlink(&self) -> bool {
+        self.inner.is_symlink()
+    }
+
+    /// Whether the file has the read-only attribute.
+    pub fn is_readonly(&self) -> bool {
+        self.inner.permissions().readonly()
+    }
+
+    /// The permissions of the file, for use with the permission APIs of the standard library.
+    pub fn permissions(&self) -> fs::Permissions {
+        self.inner.permissions()
+    }
+
+    /// The raw Windows attributes of the file (`FILE_ATTRIBUTE_*`).
+    pub fn file_attributes(&self) -> u32 {
+        self.inner.file_attributes()
+    }
+
+    /// The time the file was last modified.
+    pub fn modified(&self) -> io::Result<SystemTime> {
+        Ok(self.inner.modified()?)
+    }
+
+    /// The time the file was last accessed. The operating system may update this lazily or not at
+    /// all, depending on the configuration of the volume.
+    pub fn accessed(&self) -> io::Result<SystemTime> {
+        Ok(self.inner.accessed()?)
+    }
+
+    /// The time the file was created.
+    pub fn created(&self) -> io::Result<SystemTime> {
+        Ok(self.inner.created()?)
+    }
+}
@@ -0,0 +1,292 @@
+use crate::{
+    io,
+    rt::{spawn_sync, SynchronousTaskType},
+    windows::OwnedHandle,
+};
+use std::{
+    ffi::{c_void, CString},
+    ops::{Deref, Range},
+    path::Path,
+    ptr::{self, NonNull},
+    slice,
+    sync::Arc,
+};
+use windows::{
+    core::PCSTR,
+    Win32::{
+        Storage::FileSystem::{
+            CreateFileA, GetFileSizeEx, FILE_FLAGS_AND_ATTRIBUTES, FILE_GENERIC_READ,
+            FILE_SHARE_READ, OPEN_EXISTING,
+        },
+        System::{
+            Memory::{
+                CreateFileMappingA, MapViewOfFile, PrefetchVirtualMemory, UnmapViewOfFile,
+                FILE_MAP, FILE_MAP_COPY, FILE_MAP_READ, MEMORY_MAPPED_VIEW_ADDRESS,
+                PAGE_PROTECTION_FLAGS, PAGE_READONLY, PAGE_WRITECOPY, WIN32_MEMORY_RANGE_ENTRY,
+            },
+            Threading::GetCurrentProcess,
+        },
+    },
+};
+
+// The granularity at which we touch the mapped memory to bring it into physical memory. Pages are
+// at least this large on every platform we support.
+const PAGE_SIZE: usize = 4096;
+
+/// A file mapped into the memory of the process, either read-only or copy-on-write.
+///
+/// Mapped files are convenient for serving large immutable datasets, as the data is shared with the
+/// cache of the operating system and can be accessed like any other slice of bytes.
+///
+/// # Blocking
+///
+/// Accessing a part of the mapping that is not in physical memory yet blocks the accessing thread
+/// until the operating system has read the data from the storage device (a "page fault"). On an
+/// async worker thread, this stalls all the tasks of the worker, just like any other blocking
+/// call. To avoid this, bring the data into physical memory before accessing it:
+///
+/// * [`advise()`][Self::advise] asks the operating system to start reading a range in the
+///   background and returns immediately. This is cheap and suitable for data you expect to access
+///   soon but it gives no guarantee that the data has been read by the time you access it.
+/// * [`prefetch()`][Self::prefetch] accesses every page of a range on a synchronous worker thread,
+///   so any page faults block that thread instead of the async worker thread, and completes once
+///   the entire range is in physical memory. Use this for large regions that are likely not in
+///   physical memory yet (e.g. the first access to a large file).
+///
+/// Even prefetched data may be evicted from physical memory again under memory pressure, so
+/// neither approach is a guarantee that accessing the mapping never blocks.
+#[derive(Debug)]
+pub struct Mmap {
+    // This is an Arc because the mapping must remain alive until any prefetch operation on it has
+    // completed, even if the caller stops waiting for the prefetch and drops the mapping.
+    view: Arc<MappedView>,
+    copy_on_write: bool,
+}
+
+impl Mmap {
+    /// Maps an existing file into memory for reading.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated while it is mapped, neither by this process nor
+    /// by any other (including via other mappings of the file). The mapping hands out slices over
+    /// the contents of the file, which must not change while the slices exist.
+    ///
+    /// The file is opened without write sharing, so opening it for writing fails while we are
+    /// mapping it. This does not protect the file once it is mapped, nor from writers that already
+    /// have it mapped, so it is up to the caller to ensure nobody modifies the file.
+    pub async unsafe fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::open_with(path, PAGE_READONLY, FILE_MAP_READ, false).await
+    }
+
+    /// Maps an existing file into memory for reading and private writing. Writes to the mapping
+    /// are not written to the file and are not visible to other mappings of the file.
+    ///
+    /// # Safety
+    ///
+    /// The same requirements as for [`open()`][Self::open] apply - pages of the mapping that have
+    /// not been written to are shared with the file, so changes to the file are visible through
+    /// them.
+    pub async unsafe fn open_copy_on_write(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::open_with(path, PAGE_WRITECOPY, FILE_MAP_COPY, true).await
+    }
+
+    /// The length of the mapping, which is the length of the file when it was mapped.
+    pub fn len(&self) -> usize {
+        self.view.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.view.len == 0
+    }
+
+    /// Obtains a view over the entire mapping. See the type-level documentation for the blocking
+    /// behavior of accessing the data.
+    pub fn as_slice(&self) -> &[u8] {
+        // SAFETY: The view remains mapped for as long as we hold a reference to it and the caller
+        // of the constructor guaranteed that the file does not change while mapped.
+        unsafe { slice::from_raw_parts(self.view.ptr.as_ptr(), self.view.len) }
+    }
+
+    /// Obtains a mutable view over the entire mapping.
+    ///
+    /// Returns `None` if the mapping is not copy-on-write or if a prefetch of the mapping is still
+    /// in progress (e.g. because the caller stopped waiting for it).
+    pub fn as_mut_slice(&mut self) -> Option<&mut [u8]> {
+        if !self.copy_on_write {
+            return None;
+        }
+
+        let view = Arc::get_mut(&mut self.view)?;
+
+        // SAFETY: The view remains mapped for as long as we hold a reference to it and we have
+        // exclusive access to it, as guaranteed by `Arc::get_mut()`.
+        Some(unsafe { slice::from_raw_parts_mut(view.ptr.as_ptr(), view.len) })
+    }
+
+    /// Asks the operating system to start reading the specified range of the mapping into physical
+    /// memory in the background. Returns without waiting for the data to be read.
+    ///
+    /// # Panics
+    ///
+    /// If the range is out of bounds.
+    pub fn advise(&self, range: Range<usize>) -> io::Result<()> {
+        assert!(range.start <= range.end && range.end <= self.view.len);
+
+        if range.is_empty() {
+            return Ok(());
+        }
+
+        let entry = WIN32_MEMORY_RANGE_ENTRY {
+            // SAFETY: We just verified that the range is in bounds.
+            VirtualAddress: unsafe { self.view.ptr.as_ptr().add(range.start) } as *mut c_void,
+            NumberOfBytes: range.len(),
+        };
+
+        // SAFETY: All we need to be concerned about is passing in valid arguments, which we do.
+        // The operating system reads the data asynchronously, without blocking the current thread.
+        unsafe { PrefetchVirtualMemory(GetCurrentProcess(), &[entry], 0) }?;
+
+        Ok(())
+    }
+
+    /// Brings the specified range of the mapping into physical memory by accessing every page of
+    /// it on a synchronous worker thread. Completes once the entire range has been accessed.
+    ///
+    /// # Panics
+    ///
+    /// If the range is out of bounds.
+    pub async fn prefetch(&self, range: Range<usize>) -> io::Result<()> {
+        assert!(range.start <= range.end && range.end <= self.view.len);
+
+        if range.is_empty() {
+            return Ok(());
+        }
+
+        // We start the operating system on reading the entire range, so the page faults we take
+        // below are mostly resolved by data that is already on its way.
+        self.advise(range.clone())?;
+
+        let view = Arc::clone(&self.view);
+
+        spawn_sync(SynchronousTaskType::Syscall, move || {
+            let start = view.ptr.as_ptr() as usize + range.start;
+            let end = view.ptr.as_ptr() as usize + range.end;
+
+            // Views always start at a page boundary, so rounding the start down to a page
+            // boundary does not take us out of the view.
+            for address in (start & !(PAGE_SIZE - 1)..end).step_by(PAGE_SIZE) {
+                // SAFETY: The address is within the view, which remains mapped for as long as we
+                // hold a reference to it. We only read, so we do not conflict with any reader and
+                // no writer can exist, as writing requires exclusive access to the view.
+                unsafe {
+                    ptr::read_volatile(address as *const u8);
+                }
+            }
+        })
+        .await;
+
+        Ok(())
+    }
+
+    async fn open_with(
+        path: impl AsRef<Path>,
+        protection: PAGE_PROTECTION_FLAGS,
+        access: FILE_MAP,
+        copy_on_write: bool,
+    ) -> io::Result<Self> {
+        let path_cstr = CString::new(path.as_ref().to_str().unwrap()).unwrap();
+
+        // Opening and mapping the file are blocking operations, so we do them on a synchronous
+        // worker thread. The view keeps the file mapped even after we close the handles.
+        let view = spawn_sync(SynchronousTaskType::Syscall, move || -> io::Result<_> {
+            // SAFETY: The path string outlives the call and the handles are ours to close
+            // anywhere. The view is unmapped when the returned object is dropped.
+            unsafe {
+                let file = OwnedHandle::new(CreateFileA(
+                    PCSTR::from_raw(path_cstr.as_ptr() as *const u8),
+                    FILE_GENERIC_READ.0,
+                    FILE_SHARE_READ,
+                    None,
+                    OPEN_EXISTING,
+                    FILE_FLAGS_AND_ATTRIBUTES::default(),
+                    None,
+                )?);
+
+                let mut len: i64 = 0;
+                GetFileSizeEx(*file, &mut len as *mut _)?;
+
+                // The operating system cannot map an empty file, so there is nothing to map.
+                if len == 0 {
+                    return Ok(MappedView {
+                        ptr: NonNull::dangling(),
+                        len: 0,
+                    });
+                }
+
+                let mapping = OwnedHandle::new(CreateFileMappingA(
+                    *file,
+                    None,
+                    protection,
+                    0,
+                    0,
+                    PCSTR::null(),
+                )?);
+
+                let address = MapViewOfFile(*mapping, access, 0, 0, 0);
+
+                let Some(ptr) = NonNull::new(address.Value as *mut u8) else {
+                    return Err(windows::core::Error::from_win32().into());
+                };
+
+                Ok(MappedView {
+                    ptr,
+                    len: len as usize,
+                })
+            }
+        })
+        .await?;
+
+        Ok(Self {
+            view: Arc::new(view),
+            copy_on_write,
+        })
+    }
+}
+
+impl Deref for Mmap {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        self.as_slice()
+    }
+}
+
+#[derive(Debug)]
+struct MappedView {
+    ptr: NonNull<u8>,
+    len: usize,
+}
+
+impl Drop for MappedView {
+    fn drop(&mut self) {
+        if self.len == 0 {
+            return;
+        }
+
+        // SAFETY: We own the view and nobody can reference it anymore, as we are being dropped.
+        // Unmapping is not expected to fail for a valid view and there is nothing we could do
+        // about it anyway.
+        _ = unsafe {
+            UnmapViewOfFile(MEMORY_MAPPED_VIEW_ADDRESS {
+                Value: self.ptr.as_ptr() as *mut c_void,
+            })
+        };
+    }
+}
+
+// SAFETY: The view is plain memory that may be accessed and unmapped from any thread. Mutable
+// access requires exclusive access to the view, so the borrow checker prevents data races.
+unsafe impl Send for MappedView {}
+// SAFETY: See above.
+unsafe impl Sync for MappedView {}
@@ -0,0 +1,243 @@
+use crate::{
+    fs::{begin_read_buffer_from_file, open_for_sequential_read},
+    io::{self, Buffer},
+    mem::isolation::Isolated,
+    metrics::{observe, Event, EventBuilder},
+    rt::current_async_agent,
+    windows::OwnedHandle,
+};
+use futures::{future::LocalBoxFuture, FutureExt};
+use negative_impl::negative_impl;
+use std::{
+    collections::VecDeque,
+    fmt::{self, Debug, Formatter},
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+use windows::Win32::Foundation::HANDLE;
+
+/// Streams the contents of a file in chunks, keeping a window of positional reads in flight ahead
+/// of the consumer. Intended for sequential scans of large files, so the storage device is kept
+/// busy while the caller processes the previous chunks, instead of alternating between reading and
+/// processing.
+///
+/// Each chunk is delivered in a pooled buffer, which the caller may keep for as long as needed.
+/// Dropping the reader cancels the reads that are still in flight.
+///
+/// # Example
+///
+/// ```no_run
+/// use folo::fs::ReadAheadReader;
+///
+/// #[folo::main]
+/// async fn main() {
+///     let mut reader = ReadAheadReader::builder("huge.bin")
+///         .window(8)
+///         .open()
+///         .await
+///         .unwrap();
+///
+///     let mut checksum = 0u64;
+///
+///     while let Some(chunk) = reader.next_chunk().await.unwrap() {
+///         checksum = chunk
+///             .as_slice()
+///             .iter()
+///             .fold(checksum, |sum, b| sum.wrapping_add(*b as u64));
+///     }
+///
+///     println!("checksum: {checksum}");
+/// }
+/// ```
+pub struct ReadAheadReader {
+    file_handle: Rc<OwnedHandle<HANDLE>>,
+
+    window: usize,
+    chunk_size: usize,
+
+    // Reads that have been submitted but whose results we have not yet returned, in file order.
+    in_flight: VecDeque<InFlightRead>,
+
+    // File offset at which the next read will be issued.
+    next_read_offset: usize,
+
+    end_of_file: bool,
+
+    bytes_read: u64,
+}
+
+impl ReadAheadReader {
+    /// Starts configuring a reader for the file at the specified path.
+    pub fn builder(path: impl AsRef<Path>) -> ReadAheadReaderBuilder {
+        ReadAheadReaderBuilder::new(path)
+    }
+
+    /// Returns the next chunk of the file, in a buffer with the active region set to the data, or
+    /// `None` if the end of the file has been reached.
+    ///
+    /// Chunks are usually of the configured chunk size, though the operating system may return
+    /// less data for any read, not only the last one.
+    pub async fn next_chunk(&mut self) -> io::Result<Option<Buffer<Isolated>>> {
+        if self.end_of_file {
+            return Ok(None);
+        }
+
+        self.issue_reads();
+
+        let read = self
+            .in_flight
+            .pop_front()
+            .expect("we just issued reads, so there must be at least one in flight");
+
+        let buffer = read.future.await?;
+        let bytes_read = buffer.len();
+
+        if bytes_read == 0 {
+            // Any other reads in flight are beyond the end of the file, so we cancel them.
+            self.end_of_file = true;
+            self.in_flight.clear();
+            return Ok(None);
+        }
+
+        self.bytes_read += bytes_read as u64;
+        observe!(READ_BYTES.observe(bytes_read as i64));
+
+        if bytes_read < read.requested {
+            // The operating system gave us less than we asked for without reaching the end of the
+            // file. The reads issued after this one started at the wrong offset, so we cancel
+            // them and continue from where this read actually ended.
+            self.in_flight.clear();
+            self.next_read_offset = read.offset + bytes_read;
+        }
+
+        // Top up the window right away, so the reads progress while the caller is processing.
+        self.issue_reads();
+
+        Ok(Some(buffer))
+    }
+
+    /// Total number of bytes returned so far.
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+
+    /// Ensures that the desired number of reads is in flight.
+    fn issue_reads(&mut self) {
+        while self.in_flight.len() < self.window {
+            let mut buffer = Buffer::<Isolated>::from_pool();
+            buffer.set_len(self.chunk_size.min(buffer.len()));
+
+            let requested = buffer.len();
+            let offset = self.next_read_offset;
+
+            // The read is submitted to the operating system right away, so it progresses even
+            // though nobody polls the future until the caller gets to this chunk.
+            let future = begin_read_buffer_from_file(Rc::clone(&self.file_handle), offset, buffer)
+                .boxed_local();
+
+            self.in_flight.push_back(InFlightRead {
+                offset,
+                requested,
+                future,
+            });
+
+            self.next_read_offset += requested;
+        }
+    }
+}
+
+impl Debug for ReadAheadReader {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReadAheadReader")
+            .field("window", &self.window)
+            .field("chunk_size", &self.chunk_size)
+            .field("in_flight", &self.in_flight.len())
+            .field("next_read_offset", &self.next_read_offset)
+            .field("end_of_file", &self.end_of_file)
+            .field("bytes_read", &self.bytes_read)
+            .finish()
+    }
+}
+
+#[negative_impl]
+impl !Send for ReadAheadReader {}
+#[negative_impl]
+impl !Sync for ReadAheadReader {}
+
+struct InFlightRead {
+    offset: usize,
+    requested: usize,
+
+    // Dropping the future cancels the read.
+    future: LocalBoxFuture<'static, io::Result<Buffer<Isolated>>>,
+}
+
+/// Configures and opens a [`ReadAheadReader`].
+#[derive(Debug)]
+pub struct ReadAheadReaderBuilder {
+    path: PathBuf,
+    window: usize,
+    chunk_size: usize,
+}
+
+impl ReadAheadReaderBuilder {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            window: DEFAULT_WINDOW,
+            chunk_size: usize::MAX,
+        }
+    }
+
+    /// How many reads to keep in flight ahead of the consumer. Defaults to 4.
+    pub fn window(mut self, value: usize) -> Self {
+        self.window = value;
+        self
+    }
+
+    /// The number of bytes to read with each read. Defaults to (and is limited to) the size of a
+    /// pooled buffer.
+    pub fn chunk_size(mut self, value: usize) -> Self {
+        self.chunk_size = value;
+        self
+    }
+
+    pub async fn open(self) -> io::Result<ReadAheadReader> {
+        if self.window == 0 {
+            return Err(io::Error::InvalidOptions(
+                "window must be at least 1".to_string(),
+            ));
+        }
+
+        if self.chunk_size == 0 {
+            return Err(io::Error::InvalidOptions(
+                "chunk_size must be at least 1".to_string(),
+            ));
+        }
+
+        let (file_handle, _) = open_for_sequential_read(&self.path).await?;
+
+        // From now on the handle does not leave the current thread.
+        let file_handle = Rc::new(file_handle);
+
+        current_async_agent::with_io(|io| io.bind_io_primitive(&**file_handle))?;
+
+        Ok(ReadAheadReader {
+            file_handle,
+            window: self.window,
+            chunk_size: self.chunk_size,
+            in_flight: VecDeque::with_capacity(self.window),
+            next_read_offset: 0,
+            end_of_file: false,
+            bytes_read: 0,
+        })
+    }
+}
+
+const DEFAULT_WINDOW: usize = 4;
+
+thread_local! {
+    static READ_BYTES: Event = EventBuilder::new("fs_read_ahead_reader_read_bytes")
+        .buckets(&[1024, 16 * 1024, 64 * 1024])
+        .build();
+}
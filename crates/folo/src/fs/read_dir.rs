@@ -0,0 +1,134 @@
+use crate::{
+    fs::Metadata,
+    io,
+    rt::{spawn_sync, RemoteJoinHandle, SynchronousTaskType},
+};
+use futures::{FutureExt, Stream, StreamExt};
+use std::{
+    collections::VecDeque,
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+// The number of entries we read from the operating system per trip to a synchronous worker thread.
+const ENTRIES_PER_BATCH: usize = 128;
+
+/// Opens a directory for reading its entries. See [`ReadDir`].
+pub async fn read_dir(path: impl AsRef<Path>) -> io::Result<ReadDir> {
+    let path = path.as_ref().to_path_buf();
+
+    // Opening the directory is a blocking operation, so we do it on a synchronous worker thread.
+    let inner = spawn_sync(SynchronousTaskType::Syscall, move || -> io::Result<_> {
+        Ok(fs::read_dir(path)?)
+    })
+    .await?;
+
+    Ok(ReadDir {
+        state: ReadDirState::Idle(Some(inner)),
+        entries: VecDeque::new(),
+    })
+}
+
+/// The entries of a directory, as an asynchronous stream. Created via [`read_dir()`].
+///
+/// Entries are read from the operating system in batches on a synchronous worker thread, so the
+/// async worker thread only waits for the operating system once per batch. The entries `.` and
+/// `..` are skipped and the order of the entries is not defined.
+#[derive(Debug)]
+pub struct ReadDir {
+    state: ReadDirState,
+
+    // Entries that have been read from the operating system but not yet returned to the caller.
+    entries: VecDeque<std::io::Result<fs::DirEntry>>,
+}
+
+#[derive(Debug)]
+enum ReadDirState {
+    // No batch is being read. `None` if the directory has no more entries.
+    Idle(Option<fs::ReadDir>),
+
+    Reading(RemoteJoinHandle<(fs::ReadDir, Vec<std::io::Result<fs::DirEntry>>)>),
+}
+
+impl ReadDir {
+    /// Waits for the next entry of the directory, returning `None` once all the entries have been
+    /// returned.
+    pub async fn next(&mut self) -> Option<io::Result<DirEntry>> {
+        StreamExt::next(self).await
+    }
+}
+
+impl Stream for ReadDir {
+    type Item = io::Result<DirEntry>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            if let Some(entry) = self.entries.pop_front() {
+                return Poll::Ready(Some(entry.map(DirEntry::new).map_err(io::Error::StdIo)));
+            }
+
+            match &mut self.state {
+                ReadDirState::Idle(inner) => {
+                    let Some(mut inner) = inner.take() else {
+                        return Poll::Ready(None);
+                    };
+
+                    self.state = ReadDirState::Reading(spawn_sync(
+                        SynchronousTaskType::Syscall,
+                        move || {
+                            let entries = inner.by_ref().take(ENTRIES_PER_BATCH).collect();
+                            (inner, entries)
+                        },
+                    ));
+                }
+                ReadDirState::Reading(join_handle) => {
+                    let (inner, entries): (_, Vec<_>) = ready!(join_handle.poll_unpin(cx));
+
+                    // A short batch means we have reached the end of the directory.
+                    let more = entries.len() == ENTRIES_PER_BATCH;
+
+                    self.entries.extend(entries);
+                    self.state = ReadDirState::Idle(more.then_some(inner));
+                }
+            }
+        }
+    }
+}
+
+/// An entry of a directory, as returned by [`ReadDir`].
+#[derive(Debug)]
+pub struct DirEntry {
+    inner: fs::DirEntry,
+}
+
+impl DirEntry {
+    fn new(inner: fs::DirEntry) -> Self {
+        Self { inner }
+    }
+
+    /// The full path of the entry, i.e. the path of the directory joined with the file name.
+    pub fn path(&self) -> PathBuf {
+        self.inner.path()
+    }
+
+    /// The name of the entry, without the path of the directory.
+    pub fn file_name(&self) -> OsString {
+        self.inner.file_name()
+    }
+
+    /// The metadata of the entry, without following symbolic links.
+    ///
+    /// The operating system returns the metadata together with the entry itself, so this does not
+    /// need to wait for the operating system.
+    pub fn metadata(&self) -> io::Result<Metadata> {
+        Ok(Metadata::new(self.inner.metadata()?))
+    }
+
+    /// Whether the entry is a directory. Symbolic links to directories are not directories.
+    pub fn is_dir(&self) -> bool {
+        self.inner.file_type().is_ok_and(|t| t.is_dir())
+    }
+}
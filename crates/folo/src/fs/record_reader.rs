@@ -0,0 +1,303 @@
+use crate::{
+    fs::{open_for_sequential_read, read_buffer_from_file},
+    io::{self, Buffer},
+    mem::isolation::Isolated,
+    metrics::{observe, Event, EventBuilder},
+    rt::{current_async_agent, LocalJoinHandle},
+    windows::OwnedHandle,
+};
+use std::{
+    collections::VecDeque,
+    fmt::{self, Debug, Formatter},
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+use windows::Win32::Foundation::HANDLE;
+
+/// Streams delimited records (e.g. lines) from a file, without loading the entire file into
+/// memory. Intended for processing very large files, such as logs.
+///
+/// Reads are pipelined - while the caller is processing the records from one chunk of the file,
+/// the next chunks are already being read from storage.
+///
+/// # Example
+///
+/// ```no_run
+/// use folo::fs::RecordReader;
+///
+/// #[folo::main]
+/// async fn main() {
+///     let mut reader = RecordReader::builder("huge.log").open().await.unwrap();
+///
+///     while let Some(line) = reader.next_record().await.unwrap() {
+///         println!("{}", String::from_utf8_lossy(&line));
+///     }
+/// }
+/// ```
+pub struct RecordReader {
+    file_handle: Rc<OwnedHandle<HANDLE>>,
+
+    delimiter: u8,
+    max_record_size: usize,
+    read_ahead: usize,
+
+    // Bytes that have been read from the file but not yet returned as records. Everything before
+    // `pending_start` has already been returned.
+    pending: Vec<u8>,
+    pending_start: usize,
+
+    // Reads that have been issued but whose results we have not yet consumed, in file order.
+    in_flight: VecDeque<InFlightRead>,
+
+    // File offset at which the next read will be issued.
+    next_read_offset: usize,
+
+    end_of_file: bool,
+
+    bytes_read: u64,
+    records_read: u64,
+}
+
+impl RecordReader {
+    /// Starts configuring a reader for the file at the specified path. By default, records are
+    /// delimited by `\n` (which is not included in the returned records).
+    pub fn builder(path: impl AsRef<Path>) -> RecordReaderBuilder {
+        RecordReaderBuilder::new(path)
+    }
+
+    /// Returns the next record (without the delimiter) or `None` if the end of the file has been
+    /// reached. The last record in the file does not need to be followed by a delimiter.
+    ///
+    /// Returns an error if a record (not counting the delimiter) exceeds the maximum record size.
+    pub async fn next_record(&mut self) -> io::Result<Option<Vec<u8>>> {
+        loop {
+            if let Some(record) = self.take_record()? {
+                self.records_read += 1;
+                observe!(RECORDS.observe_unit());
+                return Ok(Some(record));
+            }
+
+            // No delimiter yet, so everything pending is part of the same record.
+            if self.pending.len() - self.pending_start > self.max_record_size {
+                return Err(self.record_too_large());
+            }
+
+            if self.end_of_file {
+                // Whatever remains is the last record, not followed by a delimiter.
+                if self.pending_start == self.pending.len() {
+                    return Ok(None);
+                }
+
+                let record = self.pending.split_off(self.pending_start);
+                self.pending.clear();
+                self.pending_start = 0;
+
+                self.records_read += 1;
+                observe!(RECORDS.observe_unit());
+                return Ok(Some(record));
+            }
+
+            self.read_next_chunk().await?;
+        }
+    }
+
+    /// Total number of bytes read from the file so far. This may be ahead of the records returned
+    /// because of read-ahead.
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+
+    /// Total number of records returned so far.
+    pub fn records_read(&self) -> u64 {
+        self.records_read
+    }
+
+    fn take_record(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let unconsumed = &self.pending[self.pending_start..];
+
+        let Some(delimiter_index) = unconsumed.iter().position(|b| *b == self.delimiter) else {
+            return Ok(None);
+        };
+
+        // The record may have arrived in one chunk together with its delimiter, in which case we
+        // never saw it pending without a delimiter.
+        if delimiter_index > self.max_record_size {
+            return Err(self.record_too_large());
+        }
+
+        let record = unconsumed[..delimiter_index].to_vec();
+        self.pending_start += delimiter_index + 1;
+
+        Ok(Some(record))
+    }
+
+    fn record_too_large(&self) -> io::Error {
+        io::Error::LogicError(format!(
+            "record exceeds maximum record size of {} bytes",
+            self.max_record_size
+        ))
+    }
+
+    async fn read_next_chunk(&mut self) -> io::Result<()> {
+        self.issue_reads();
+
+        let read = self
+            .in_flight
+            .pop_front()
+            .expect("we just issued reads, so there must be at least one in flight");
+
+        let buffer = read.join_handle.await?;
+        let bytes_read = buffer.len();
+
+        if bytes_read == 0 {
+            // Any other reads in flight are beyond the end of the file, so we do not need them.
+            self.end_of_file = true;
+            self.in_flight.clear();
+            return Ok(());
+        }
+
+        self.bytes_read += bytes_read as u64;
+        observe!(READ_BYTES.observe(bytes_read as i64));
+
+        // Compact the pending bytes before appending, so the vector does not grow without bounds.
+        self.pending.drain(..self.pending_start);
+        self.pending_start = 0;
+        self.pending.extend_from_slice(&buffer.as_slice());
+
+        if bytes_read < read.requested {
+            // The operating system gave us less than we asked for without reaching the end of the
+            // file. The reads issued after this one started at the wrong offset, so we discard
+            // them and continue from where this read actually ended.
+            self.in_flight.clear();
+            self.next_read_offset = read.offset + bytes_read;
+        }
+
+        Ok(())
+    }
+
+    /// Ensures that the desired number of reads is in flight.
+    fn issue_reads(&mut self) {
+        while self.in_flight.len() < self.read_ahead {
+            let buffer = Buffer::<Isolated>::from_pool();
+            let requested = buffer.len();
+            let offset = self.next_read_offset;
+
+            // We spawn the reads as separate tasks so they get submitted to the operating system
+            // immediately and progress while we are busy with other things.
+            let join_handle = crate::rt::spawn(read_buffer_from_file(
+                Rc::clone(&self.file_handle),
+                offset,
+                buffer,
+            ));
+
+            self.in_flight.push_back(InFlightRead {
+                offset,
+                requested,
+                join_handle,
+            });
+
+            self.next_read_offset += requested;
+        }
+    }
+}
+
+impl Debug for RecordReader {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RecordReader")
+            .field("delimiter", &self.delimiter)
+            .field("max_record_size", &self.max_record_size)
+            .field("read_ahead", &self.read_ahead)
+            .field("pending", &(self.pending.len() - self.pending_start))
+            .field("in_flight", &self.in_flight.len())
+            .field("end_of_file", &self.end_of_file)
+            .field("bytes_read", &self.bytes_read)
+            .field("records_read", &self.records_read)
+            .finish()
+    }
+}
+
+struct InFlightRead {
+    offset: usize,
+    requested: usize,
+    join_handle: LocalJoinHandle<io::Result<Buffer<Isolated>>>,
+}
+
+/// Configures and opens a [`RecordReader`].
+#[derive(Debug)]
+pub struct RecordReaderBuilder {
+    path: PathBuf,
+    delimiter: u8,
+    max_record_size: usize,
+    read_ahead: usize,
+}
+
+impl RecordReaderBuilder {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            delimiter: b'\n',
+            max_record_size: DEFAULT_MAX_RECORD_SIZE,
+            read_ahead: DEFAULT_READ_AHEAD,
+        }
+    }
+
+    /// The byte that separates records. Defaults to `\n`.
+    pub fn delimiter(mut self, value: u8) -> Self {
+        self.delimiter = value;
+        self
+    }
+
+    /// The maximum size of a record in bytes. Reading a longer record is an error, which protects
+    /// against unbounded memory use if the file is not in the expected format.
+    pub fn max_record_size(mut self, value: usize) -> Self {
+        self.max_record_size = value;
+        self
+    }
+
+    /// How many chunks of the file to keep reading in the background.
+    pub fn read_ahead(mut self, value: usize) -> Self {
+        self.read_ahead = value;
+        self
+    }
+
+    pub async fn open(self) -> io::Result<RecordReader> {
+        if self.read_ahead == 0 {
+            return Err(io::Error::InvalidOptions(
+                "read_ahead must be at least 1".to_string(),
+            ));
+        }
+
+        let (file_handle, _) = open_for_sequential_read(&self.path).await?;
+
+        // From now on the handle does not leave the current thread.
+        let file_handle = Rc::new(file_handle);
+
+        current_async_agent::with_io(|io| io.bind_io_primitive(&**file_handle))?;
+
+        Ok(RecordReader {
+            file_handle,
+            delimiter: self.delimiter,
+            max_record_size: self.max_record_size,
+            read_ahead: self.read_ahead,
+            pending: Vec::new(),
+            pending_start: 0,
+            in_flight: VecDeque::with_capacity(self.read_ahead),
+            next_read_offset: 0,
+            end_of_file: false,
+            bytes_read: 0,
+            records_read: 0,
+        })
+    }
+}
+
+const DEFAULT_MAX_RECORD_SIZE: usize = 1024 * 1024;
+const DEFAULT_READ_AHEAD: usize = 2;
+
+thread_local! {
+    static READ_BYTES: Event = EventBuilder::new("fs_record_reader_read_bytes")
+        .buckets(&[1024, 16 * 1024, 64 * 1024])
+        .build();
+
+    static RECORDS: Event = EventBuilder::new("fs_record_reader_records")
+        .build();
+}
@@ -0,0 +1,123 @@
+use crate::{
+    fs::{remove_dir_all, temp_file::unique_temp_name},
+    io,
+    rt::{current_runtime, spawn_sync, spawn_sync_on_any, SynchronousTaskType},
+};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+use tracing::{event, Level};
+
+/// A uniquely named directory for temporary files, which is deleted together with its contents
+/// when the `TempDir` is dropped - including when the thread panics.
+///
+/// Dropping the directory starts the deletion on a synchronous worker thread and only logs any
+/// errors. Use [`close()`][Self::close] to wait for the deletion and observe the result.
+///
+/// Unlike a [`TempFile`][super::TempFile], the directory is not deleted if the process terminates
+/// abnormally, as the operating system has no equivalent of delete-on-close for directories.
+///
+/// # Example
+///
+/// ```no_run
+/// use folo::fs::{File, TempDir};
+///
+/// #[folo::main]
+/// async fn main() {
+///     let dir = TempDir::new().await.unwrap();
+///
+///     let partial = File::create(dir.path().join("partial.bin")).await.unwrap();
+///     partial.set_len(1024).unwrap();
+///     drop(partial);
+///
+///     dir.close().await.unwrap();
+/// }
+/// ```
+#[derive(Debug)]
+pub struct TempDir {
+    // Taken by `close()`, after which the drop has nothing left to do.
+    path: Option<PathBuf>,
+}
+
+impl TempDir {
+    /// Creates a temporary directory in the temporary directory of the current user.
+    pub async fn new() -> io::Result<Self> {
+        Self::new_in(env::temp_dir()).await
+    }
+
+    /// Creates a temporary directory in the specified directory.
+    pub async fn new_in(parent: impl AsRef<Path>) -> io::Result<Self> {
+        let parent = parent.as_ref().to_path_buf();
+
+        // Creating the directory is a blocking operation, so we kick it off to a synchronous
+        // worker thread to avoid blocking the async workers with this slow call.
+        let path = spawn_sync(SynchronousTaskType::Syscall, move || -> io::Result<_> {
+            loop {
+                let path = parent.join(unique_temp_name("dir"));
+
+                match fs::create_dir(&path) {
+                    Ok(()) => return Ok(path),
+                    // Someone else got to this name first - unlikely but possible, so try another
+                    // one.
+                    Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {}
+                    Err(e) => return Err(e.into()),
+                }
+            }
+        })
+        .await?;
+
+        Ok(Self { path: Some(path) })
+    }
+
+    /// The path of the directory.
+    pub fn path(&self) -> &Path {
+        self.path
+            .as_deref()
+            .expect("path is only taken when the directory is consumed")
+    }
+
+    /// Deletes the directory together with its contents, waiting for the deletion to complete.
+    pub async fn close(mut self) -> io::Result<()> {
+        let path = self
+            .path
+            .take()
+            .expect("path is only taken when the directory is consumed");
+
+        remove_dir_all(path).await
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        self.path()
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let Some(path) = self.path.take() else {
+            return;
+        };
+
+        let remove = move || {
+            if let Err(e) = fs::remove_dir_all(&path) {
+                event!(
+                    Level::WARN,
+                    message = "failed to delete temporary directory",
+                    path = %path.display(),
+                    error = %e
+                );
+            }
+        };
+
+        // Just like with handles, we may be dropped after the runtime has shut down, in which case
+        // we clean up synchronously because there is not much else to do.
+        if !current_runtime::is_some() || current_runtime::with(|x| x.is_stopping()) {
+            remove();
+            return;
+        }
+
+        _ = spawn_sync_on_any(SynchronousTaskType::HighPrioritySyscall, remove);
+    }
+}
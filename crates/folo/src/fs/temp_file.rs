@@ -0,0 +1,130 @@
+use crate::{fs::File, io};
+use negative_impl::negative_impl;
+use std::{
+    env,
+    ops::{Deref, DerefMut},
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use windows::{
+    core::HRESULT,
+    Win32::{
+        Foundation::ERROR_FILE_EXISTS,
+        Storage::FileSystem::{
+            CREATE_NEW, DELETE, FILE_ATTRIBUTE_TEMPORARY, FILE_FLAG_DELETE_ON_CLOSE,
+        },
+    },
+};
+
+/// A uniquely named file for temporary data (e.g. data spilled to disk by a pipeline that ran out
+/// of memory), which is deleted when it is closed.
+///
+/// The file is opened for asynchronous I/O on the current async worker thread and offers the
+/// full [`File`] API via `Deref`.
+///
+/// The file is created with `FILE_FLAG_DELETE_ON_CLOSE`, so the operating system deletes it once
+/// the `TempFile` is dropped - even if the thread panics or the process terminates abnormally.
+/// It is also marked as temporary, which tells the operating system to keep its data in memory
+/// for as long as possible instead of writing it to the storage device.
+///
+/// # Example
+///
+/// ```no_run
+/// use folo::fs::TempFile;
+/// use folo::io::Buffer;
+/// use folo::mem::isolation::Isolated;
+///
+/// #[folo::main]
+/// async fn main() {
+///     let mut spill = TempFile::new().await.unwrap();
+///
+///     let mut buffer = Buffer::<Isolated>::from_pool();
+///     buffer.as_mut_slice()[..5].copy_from_slice(b"hello");
+///     buffer.set_len(5);
+///
+///     spill.write(buffer).await.unwrap();
+///
+///     let buffer = spill.read_at(0, Buffer::from_pool()).await.unwrap();
+///     assert_eq!(&*buffer.as_slice(), b"hello");
+///
+///     // The file is deleted here.
+/// }
+/// ```
+#[derive(Debug)]
+pub struct TempFile {
+    file: File,
+    path: PathBuf,
+}
+
+impl TempFile {
+    /// Creates a temporary file in the temporary directory of the current user.
+    pub async fn new() -> io::Result<Self> {
+        Self::new_in(env::temp_dir()).await
+    }
+
+    /// Creates a temporary file in the specified directory, e.g. one on a storage device that is
+    /// dedicated to temporary data.
+    pub async fn new_in(dir: impl AsRef<Path>) -> io::Result<Self> {
+        loop {
+            let path = dir.as_ref().join(unique_temp_name("tmp"));
+
+            let result = File::open_with_access(
+                &path,
+                DELETE.0,
+                CREATE_NEW,
+                FILE_FLAG_DELETE_ON_CLOSE | FILE_ATTRIBUTE_TEMPORARY,
+            )
+            .await;
+
+            match result {
+                Ok(file) => return Ok(Self { file, path }),
+                // Someone else got to this name first - unlikely but possible, so try another one.
+                Err(io::Error::Windows(e))
+                    if e.code() == HRESULT::from_win32(ERROR_FILE_EXISTS.0) => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// The path of the file. Other handles to the file can only be opened with
+    /// `FILE_SHARE_DELETE` sharing, as the file is pending deletion.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Deref for TempFile {
+    type Target = File;
+
+    fn deref(&self) -> &Self::Target {
+        &self.file
+    }
+}
+
+impl DerefMut for TempFile {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.file
+    }
+}
+
+#[negative_impl]
+impl !Send for TempFile {}
+#[negative_impl]
+impl !Sync for TempFile {}
+
+/// Generates a name for a temporary file or directory that is unlikely to be used by anyone else.
+/// Callers must still create the entry exclusively and retry with another name on conflict.
+pub(super) fn unique_temp_name(extension: &str) -> String {
+    let sequence = NEXT_TEMP_SEQUENCE.fetch_add(1, Ordering::Relaxed);
+
+    // The time makes names differ between runs of a process that happens to get the same ID.
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |x| x.subsec_nanos());
+
+    format!("folo-{}-{sequence}-{nanos:08x}.{extension}", process::id())
+}
+
+static NEXT_TEMP_SEQUENCE: AtomicU64 = AtomicU64::new(0);
@@ -0,0 +1,126 @@
+use crate::{
+    fs::{read_dir, DirEntry, ReadDir},
+    io,
+};
+use std::{
+    fmt::{self, Debug, Formatter},
+    path::{Path, PathBuf},
+};
+
+/// Walks a directory tree recursively. See [`WalkDir`].
+pub fn walk_dir(root: impl AsRef<Path>) -> WalkDir {
+    WalkDir::new(root)
+}
+
+/// Walks a directory tree recursively, returning the entries of every directory in the tree
+/// (excluding the root itself) via [`next()`][Self::next].
+///
+/// Every directory is returned before its own entries. Symbolic links are returned as entries but
+/// not followed. Directories are read one at a time, via [`ReadDir`].
+///
+/// # Example
+///
+/// ```no_run
+/// use folo::fs::walk_dir;
+///
+/// #[folo::main]
+/// async fn main() {
+///     let mut walk = walk_dir("C:\\logs")
+///         .max_depth(2)
+///         .filter(|entry| entry.file_name() != "archive");
+///
+///     while let Some(entry) = walk.next().await {
+///         println!("{}", entry.unwrap().path().display());
+///     }
+/// }
+/// ```
+pub struct WalkDir {
+    // A directory whose entries we return next, once we have opened it.
+    pending_dir: Option<PathBuf>,
+
+    // The directories we are reading, from the root to the current one.
+    open_dirs: Vec<ReadDir>,
+
+    max_depth: usize,
+    filter: Option<Box<dyn Fn(&DirEntry) -> bool>>,
+}
+
+impl WalkDir {
+    pub fn new(root: impl AsRef<Path>) -> Self {
+        Self {
+            pending_dir: Some(root.as_ref().to_path_buf()),
+            open_dirs: Vec::new(),
+            max_depth: usize::MAX,
+            filter: None,
+        }
+    }
+
+    /// Sets the maximum depth of the returned entries, with the entries of the root directory at
+    /// depth 1. By default, there is no limit.
+    pub fn max_depth(mut self, value: usize) -> Self {
+        self.max_depth = value;
+        self
+    }
+
+    /// Sets a filter for the entries. Entries for which the filter returns `false` are skipped and,
+    /// if they are directories, their contents are skipped as well.
+    pub fn filter<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&DirEntry) -> bool + 'static,
+    {
+        self.filter = Some(Box::new(f));
+        self
+    }
+
+    /// Waits for the next entry of the tree, returning `None` once the walk is complete.
+    ///
+    /// If a directory cannot be read, the error is returned and the walk continues with the next
+    /// entry.
+    pub async fn next(&mut self) -> Option<io::Result<DirEntry>> {
+        loop {
+            if let Some(path) = self.pending_dir.take() {
+                if self.open_dirs.len() < self.max_depth {
+                    match read_dir(path).await {
+                        Ok(dir) => self.open_dirs.push(dir),
+                        Err(e) => return Some(Err(e)),
+                    }
+                }
+            }
+
+            let depth = self.open_dirs.len();
+
+            let entry = match self.open_dirs.last_mut()?.next().await {
+                Some(Ok(entry)) => entry,
+                Some(Err(e)) => return Some(Err(e)),
+                None => {
+                    self.open_dirs.pop();
+                    continue;
+                }
+            };
+
+            if let Some(filter) = &self.filter {
+                if !filter(&entry) {
+                    continue;
+                }
+            }
+
+            // We return the directory first and open it on the next call, so an error in reading
+            // the directory does not prevent the caller from seeing the directory itself.
+            if entry.is_dir() && depth < self.max_depth {
+                self.pending_dir = Some(entry.path());
+            }
+
+            return Some(Ok(entry));
+        }
+    }
+}
+
+impl Debug for WalkDir {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WalkDir")
+            .field("pending_dir", &self.pending_dir)
+            .field("depth", &self.open_dirs.len())
+            .field("max_depth", &self.max_depth)
+            .finish()
+    }
+}
@@ -0,0 +1,247 @@
+//! Serving and calling gRPC services on Folo worker threads, e.g. those generated by `tonic-build`.
+//!
+//! gRPC runs over HTTP/2, which is provided by Hyper via the adapters in [`crate::hyper`]. Both
+//! sides are generic over the `Service` trait of Tower, which generated servers implement and
+//! generated clients build upon:
+//!
+//! * [`serve_connection()`] serves a service (e.g. a generated `GreeterServer`, or several of them
+//!   combined via `tonic::service::Routes`) on an accepted connection.
+//! * [`Channel`] is a connection to a server, for use with generated clients (e.g. via
+//!   `GreeterClient::new(channel)`).
+//!
+//! Unlike the transport of Tonic, neither side requires the services, bodies or connections to be
+//! `Send`, so they can use thread-local state and Folo I/O types directly.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use folo::{grpc::serve_connection, hyper::FoloIo, net::TcpListener};
+//! use hello_world::greeter_server::GreeterServer;
+//! use std::net::SocketAddr;
+//!
+//! #[folo::main]
+//! async fn main() {
+//!     let listener = TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 1234)))
+//!         .await
+//!         .unwrap();
+//!
+//!     let service = GreeterServer::new(MyGreeter::default());
+//!
+//!     loop {
+//!         let (stream, _) = listener.accept().await.unwrap();
+//!         let service = service.clone();
+//!
+//!         folo::rt::spawn(async move {
+//!             _ = serve_connection(FoloIo::new(stream), service).await;
+//!         });
+//!     }
+//! }
+//! ```
+
+use crate::{
+    hyper::{FoloExecutor, FoloIo},
+    io, rt,
+};
+use futures::{
+    future::{poll_fn, LocalBoxFuture},
+    FutureExt,
+};
+use hyper::{
+    body::{Body, Incoming},
+    client::conn::http2::{self, SendRequest},
+    rt::{Read, Write},
+    server, Request, Response, Uri,
+};
+use std::{
+    error::Error,
+    fmt::{self, Debug, Formatter},
+    task::{Context, Poll},
+};
+use tonic::codegen::Service;
+use tracing::{event, Level};
+
+/// Serves gRPC requests received on a connection (e.g. a [`FoloIo`] or
+/// [`FoloTlsIo`][crate::hyper::FoloTlsIo]) until the client closes the connection.
+///
+/// Each request is handled by its own clone of the service, in a task on the current thread.
+pub async fn serve_connection<I, S, B>(io: I, service: S) -> Result<(), hyper::Error>
+where
+    I: Read + Write + Unpin + 'static,
+    S: Service<Request<Incoming>, Response = Response<B>> + Clone + 'static,
+    S::Error: Into<Box<dyn Error + Send + Sync>>,
+    S::Future: 'static,
+    B: Body + 'static,
+    B::Error: Into<Box<dyn Error + Send + Sync>>,
+{
+    server::conn::http2::Builder::new(FoloExecutor::new())
+        .serve_connection(io, TowerService(service))
+        .await
+}
+
+/// Adapts a Tower service to the `Service` trait of Hyper, which does not wait for readiness.
+struct TowerService<S>(S);
+
+impl<S, R> hyper::service::Service<R> for TowerService<S>
+where
+    S: Service<R> + Clone + 'static,
+    S::Future: 'static,
+    R: 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = LocalBoxFuture<'static, Result<S::Response, S::Error>>;
+
+    fn call(&self, request: R) -> Self::Future {
+        let mut service = self.0.clone();
+
+        async move {
+            poll_fn(|cx| service.poll_ready(cx)).await?;
+            service.call(request).await
+        }
+        .boxed_local()
+    }
+}
+
+/// A connection to a gRPC server, for use with clients generated by `tonic-build`.
+///
+/// The connection is driven by a task on the current thread, which ends when the last clone of the
+/// channel is dropped. Clones share the connection, with their requests multiplexed over it.
+///
+/// # Example
+///
+/// ```ignore
+/// use folo::grpc::Channel;
+/// use hello_world::{greeter_client::GreeterClient, HelloRequest};
+///
+/// #[folo::main]
+/// async fn main() {
+///     let channel = Channel::connect("localhost", 1234).await.unwrap();
+///     let mut client = GreeterClient::new(channel);
+///
+///     let request = HelloRequest {
+///         name: "Folo".to_string(),
+///     };
+///
+///     let reply = client.say_hello(request).await.unwrap();
+///     println!("{}", reply.into_inner().message);
+/// }
+/// ```
+pub struct Channel<B = tonic::body::BoxBody> {
+    sender: SendRequest<B>,
+
+    // Requests without a scheme and authority (as sent by generated clients by default) are sent
+    // to this origin, as HTTP/2 requires both to be present.
+    origin: Uri,
+}
+
+impl<B> Channel<B>
+where
+    B: Body + 'static,
+    B::Data: Send,
+    B::Error: Into<Box<dyn Error + Send + Sync>>,
+{
+    /// Performs the HTTP/2 handshake on a connection (e.g. a [`FoloIo`] or
+    /// [`FoloTlsIo`][crate::hyper::FoloTlsIo]) and spawns a task on the current thread to drive
+    /// it. Requests without a scheme and authority are sent to `origin`.
+    pub async fn new<I>(io: I, origin: Uri) -> Result<Self, hyper::Error>
+    where
+        I: Read + Write + Unpin + 'static,
+    {
+        let (sender, connection) = http2::handshake(FoloExecutor::new(), io).await?;
+
+        rt::spawn(async move {
+            if let Err(e) = connection.await {
+                event!(Level::DEBUG, message = "gRPC connection failed", error = %e);
+            }
+        });
+
+        Ok(Self { sender, origin })
+    }
+
+    /// Connects to a gRPC server via plaintext HTTP/2 (also known as h2c).
+    pub async fn connect(host: &str, port: u16) -> io::Result<Self> {
+        let stream = crate::net::TcpStream::connect_host(host, port).await?;
+
+        Self::new(FoloIo::new(stream), origin("http", host, port)?)
+            .await
+            .map_err(|e| io::Error::Other(e.into()))
+    }
+
+    /// Connects to a gRPC server via HTTP/2 over TLS. The configuration of the connector must
+    /// offer `h2` via ALPN, as gRPC servers do not accept other protocols.
+    #[cfg(feature = "tls")]
+    pub async fn connect_tls(
+        host: &str,
+        port: u16,
+        connector: &crate::tls::TlsConnector,
+    ) -> io::Result<Self> {
+        let server_name = rustls::pki_types::ServerName::try_from(host.to_string())
+            .map_err(|e| io::Error::InvalidOptions(format!("invalid server name {host}: {e}")))?;
+
+        let stream = crate::net::TcpStream::connect_host(host, port).await?;
+        let stream = connector.connect(server_name, stream).await?;
+
+        if stream.alpn_protocol() != Some(&b"h2"[..]) {
+            return Err(io::Error::InvalidOptions(
+                "the server did not agree to use HTTP/2 via ALPN".to_string(),
+            ));
+        }
+
+        Self::new(
+            crate::hyper::FoloTlsIo::new(stream),
+            origin("https", host, port)?,
+        )
+        .await
+        .map_err(|e| io::Error::Other(e.into()))
+    }
+}
+
+fn origin(scheme: &str, host: &str, port: u16) -> io::Result<Uri> {
+    format!("{scheme}://{host}:{port}")
+        .parse()
+        .map_err(|e| io::Error::InvalidOptions(format!("invalid host {host}: {e}")))
+}
+
+impl<B> Service<Request<B>> for Channel<B>
+where
+    B: Body + 'static,
+{
+    type Response = Response<Incoming>;
+    type Error = hyper::Error;
+    type Future = LocalBoxFuture<'static, Result<Response<Incoming>, hyper::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), hyper::Error>> {
+        self.sender.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<B>) -> Self::Future {
+        if request.uri().scheme().is_none() && request.uri().authority().is_none() {
+            let mut parts = request.uri().clone().into_parts();
+            parts.scheme = self.origin.scheme().cloned();
+            parts.authority = self.origin.authority().cloned();
+
+            *request.uri_mut() =
+                Uri::from_parts(parts).expect("origin and path are valid parts of a URI");
+        }
+
+        self.sender.send_request(request).boxed_local()
+    }
+}
+
+impl<B> Clone for Channel<B> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            origin: self.origin.clone(),
+        }
+    }
+}
+
+impl<B> Debug for Channel<B> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        // The request bodies are not necessarily `Debug`, so we leave the sender out.
+        f.debug_struct("Channel")
+            .field("origin", &self.origin)
+            .finish()
+    }
+}
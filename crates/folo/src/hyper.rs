@@ -1,20 +1,60 @@
+//! Integration with Hyper 1.x, allowing Hyper servers and clients to run on Folo worker threads.
+//!
+//! * [`FoloExecutor`] spawns the background tasks of Hyper connections (e.g. HTTP/2 streams) on
+//!   the current worker thread.
+//! * [`FoloIo`] exposes any Folo stream (e.g. a [`TcpConnection`]) via the I/O traits of Hyper.
+//! * [`FoloTlsIo`] does the same for a [`TlsStream`][crate::tls::TlsStream] (with the `tls`
+//!   feature).
+//! * [`FoloTimer`] provides Hyper with timers driven by a Folo [`Clock`].
+//!
+//! # Example
+//!
+//! ```no_run
+//! use folo::{
+//!     hyper::{FoloExecutor, FoloIo},
+//!     net::TcpStream,
+//! };
+//! use std::net::SocketAddr;
+//!
+//! # async fn request() -> http::Request<http_body_util::Empty<bytes::Bytes>> { unimplemented!() }
+//! #[folo::main]
+//! async fn main() {
+//!     let stream = TcpStream::connect(SocketAddr::from(([127, 0, 0, 1], 1234)))
+//!         .await
+//!         .unwrap();
+//!
+//!     let (mut sender, connection) =
+//!         hyper::client::conn::http2::handshake(FoloExecutor::new(), FoloIo::new(stream))
+//!             .await
+//!             .unwrap();
+//!
+//!     folo::rt::spawn(connection);
+//!
+//!     let response = sender.send_request(request().await).await.unwrap();
+//!     assert!(response.status().is_success());
+//! }
+//! ```
+
 use crate::{
-    io::{Buffer, OperationResultFuture},
-    mem::isolation::Isolated,
-    net::{ShutdownFuture, TcpConnection},
+    io::FuturesIo,
+    net::{ByteStream, TcpConnection},
     rt,
     time::{Clock, Delay},
 };
+use futures::io::{AsyncBufRead, AsyncWrite};
 use hyper::rt::{Executor, Read, ReadBufCursor, Sleep, Timer, Write};
+use negative_impl::negative_impl;
 use pin_project::pin_project;
 use std::{
     future::Future,
-    mem::{self, MaybeUninit},
-    pin::{pin, Pin},
-    task::{Context, Poll},
+    pin::Pin,
+    task::{ready, Context, Poll},
 };
 
 // Executor
+
+/// Spawns the background tasks of Hyper connections on the current worker thread. The tasks do not
+/// need to be `Send`, so neither do the services and bodies used with the connections.
 #[non_exhaustive]
 #[derive(Default, Debug, Clone)]
 pub struct FoloExecutor {}
@@ -27,8 +67,8 @@ impl FoloExecutor {
 
 impl<F> Executor<F> for FoloExecutor
 where
-    F: Future + Send + 'static,
-    F::Output: Send + 'static,
+    F: Future + 'static,
+    F::Output: 'static,
 {
     fn execute(&self, fut: F) {
         rt::spawn(fut);
@@ -36,174 +76,184 @@ where
 }
 
 // IO
-#[derive(Debug)]
-#[pin_project::pin_project]
-pub struct FoloIo {
-    connection: TcpConnection,
-
-    #[pin]
-    active_read: Option<OperationResultFuture>,
-
-    #[pin]
-    active_write: Option<OperationResultFuture>,
 
+/// Exposes a Folo stream via the `Read` and `Write` traits of Hyper.
+///
+/// Data is copied between the Hyper buffers and pooled buffers in the same way as in
+/// [`FuturesIo`], which this adapter is built upon. Hyper buffers are not safe to hand to the I/O
+/// driver directly because they may be dropped while an operation is still using them (if
+/// something drops the future polling us).
+#[pin_project]
+#[derive(Debug)]
+pub struct FoloIo<S = TcpConnection> {
     #[pin]
-    active_shutdown: Option<ShutdownFuture>,
+    inner: FuturesIo<S>,
 }
 
-impl FoloIo {
-    pub fn new(connection: TcpConnection) -> Self {
+impl<S> FoloIo<S>
+where
+    S: ByteStream,
+{
+    pub fn new(stream: S) -> Self {
         Self {
-            connection,
-            active_read: None,
-            active_write: None,
-            active_shutdown: None,
+            inner: FuturesIo::new(stream),
         }
     }
+
+    pub fn get_ref(&self) -> &S {
+        self.inner.get_ref()
+    }
+
+    /// Returns the inner stream. Any data received but not yet read is lost.
+    pub fn into_inner(self) -> S {
+        self.inner.into_inner()
+    }
 }
 
-impl Read for FoloIo {
+impl<S> Read for FoloIo<S>
+where
+    S: ByteStream,
+{
     fn poll_read(
-        mut self: Pin<&mut Self>,
+        self: Pin<&mut Self>,
         cx: &mut Context<'_>,
         mut buf: ReadBufCursor<'_>,
-    ) -> Poll<Result<(), std::io::Error>> {
-        loop {
-            let mut this = self.as_mut().project();
-
-            if let Some(active_read) = this.active_read.as_mut().as_pin_mut() {
-                match active_read.poll(cx) {
-                    Poll::Ready(result) => {
-                        // This future is finished, set it to none.
-                        this.active_read.set(None);
-
-                        return Poll::Ready(match result {
-                            Ok(buffer) => {
-                                // SAFETY: We are responsible for not uninitializing bytes.
-                                // We are also responsible for ensuring the right count is advanced.
-                                unsafe {
-                                    // They are just bytes, we are initializing them now, it's fine.
-                                    let buf_slice = &mut buf.as_mut()[..buffer.len()];
-                                    // MaybeUninit is layout-compatible, so it's cool.
-                                    let buf_slice_as_bytes =
-                                        mem::transmute::<&mut [MaybeUninit<u8>], &mut [u8]>(
-                                            buf_slice,
-                                        );
-
-                                    // Obviously, this copy is horribly inefficient to have in our I/O stack.
-                                    // We should work with Hyper authors to eliminate the need for this by
-                                    // enabling Hyper to use runtime-owned buffers.
-                                    buf_slice_as_bytes.copy_from_slice(&buffer.as_slice());
-                                    buf.advance(buffer.len());
-                                }
-                                Ok(())
-                            }
-                            Err(e) => Err(e.into_inner().into()),
-                        });
-                    }
-
-                    Poll::Pending => return Poll::Pending,
-                }
-            }
+    ) -> Poll<std::io::Result<()>> {
+        let mut inner = self.project().inner;
 
-            // There's no active read, so start a new one.
+        let available = ready!(inner.as_mut().poll_fill_buf(cx))?;
 
-            // Hyper buffers are not memory-safe to use directly because they may be dropped while
-            // we are still using them (if something drops the parent future polling us). Therefore,
-            // we first read into Folo buffers (which are always safe) and only if the future is
-            // still alive after the I/O operation do we copy the data into Hyper buffers.
-            let mut buffer = Buffer::<Isolated>::from_pool();
+        let len = available.len().min(buf.remaining());
+        buf.put_slice(&available[..len]);
 
-            // Make sure we do not try to read more than Hyper can accommodate.
-            // SAFETY: We are reponsible for not uninitializing bytes. Yeah okay.
-            buffer.set_len(buffer.len().min(unsafe { buf.as_mut().len() }));
+        inner.consume(len);
 
-            // The I/O driver takes ownership of the buffer and will keep it alive until it is safe
-            // to release it. Even if this future is dropped, memory is not released too early.
-            this.active_read.set(Some(this.connection.receive(buffer)));
-        }
+        Poll::Ready(Ok(()))
     }
 }
 
-impl Write for FoloIo {
+impl<S> Write for FoloIo<S>
+where
+    S: ByteStream,
+{
     fn poll_write(
-        mut self: Pin<&mut Self>,
+        self: Pin<&mut Self>,
         cx: &mut Context<'_>,
         buf: &[u8],
-    ) -> Poll<Result<usize, std::io::Error>> {
-        loop {
-            let mut this = self.as_mut().project();
-
-            if let Some(active_write) = this.active_write.as_mut().as_pin_mut() {
-                match active_write.poll(cx) {
-                    Poll::Ready(result) => {
-                        // This future is finished, set it to none.
-                        this.active_write.set(None);
-
-                        return Poll::Ready(match result {
-                            Ok(r) => Ok(r.len()),
-                            Err(e) => Err(e.into_inner().into()),
-                        });
-                    }
-
-                    Poll::Pending => return Poll::Pending,
-                }
-            }
+    ) -> Poll<std::io::Result<usize>> {
+        self.project().inner.poll_write(cx, buf)
+    }
 
-            // There's no active write, so start a new one.
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        self.project().inner.poll_flush(cx)
+    }
 
-            // Hyper buffers are not memory-safe to use directly because they may be dropped while
-            // we are still using them (if something drops the parent future polling us). Therefore,
-            // we copy the data into Folo buffers here (which are always safe)
-            let mut buffer = Buffer::<Isolated>::from_pool();
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        self.project().inner.poll_close(cx)
+    }
+}
 
-            let len_to_copy = buffer.len().min(buf.len());
-            buffer.set_len(len_to_copy);
+#[negative_impl]
+impl<S> !Send for FoloIo<S> {}
+#[negative_impl]
+impl<S> !Sync for FoloIo<S> {}
+
+#[cfg(feature = "tls")]
+mod tls {
+    use crate::{io::Buffer, mem::isolation::Isolated, net::ByteStream, tls::TlsStream};
+    use hyper::rt::{Read, ReadBufCursor, Write};
+    use std::{
+        pin::Pin,
+        task::{ready, Context, Poll},
+    };
+
+    /// Exposes a TLS session via the `Read` and `Write` traits of Hyper.
+    ///
+    /// Reads and writes of the underlying stream are independent, so a read that waits for the
+    /// peer does not hold up writes, as required by HTTP/2. Use
+    /// [`TlsStream::alpn_protocol()`] to find out which HTTP version the peer agreed to.
+    #[derive(Debug)]
+    pub struct FoloTlsIo<S> {
+        stream: TlsStream<S>,
+    }
 
-            // Obviously, this copy is horribly inefficient to have in our I/O stack.
-            // We should work with Hyper authors to eliminate the need for this by
-            // enabling Hyper to use runtime-owned buffers.
-            buffer.as_mut_slice().copy_from_slice(buf);
+    impl<S> FoloTlsIo<S>
+    where
+        S: ByteStream,
+    {
+        pub fn new(stream: TlsStream<S>) -> Self {
+            Self { stream }
+        }
 
-            // The I/O driver takes ownership of the buffer and will keep it alive until it is safe
-            // to release it. Even if this future is dropped, memory is not released too early.
-            this.active_write.set(Some(this.connection.send(buffer)));
+        pub fn get_ref(&self) -> &TlsStream<S> {
+            &self.stream
         }
-    }
 
-    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), std::io::Error>> {
-        // Flushing is not relevant yet.
-        Poll::Ready(Ok(()))
+        pub fn into_inner(self) -> TlsStream<S> {
+            self.stream
+        }
     }
 
-    fn poll_shutdown(
-        mut self: Pin<&mut Self>,
-        cx: &mut Context<'_>,
-    ) -> Poll<Result<(), std::io::Error>> {
-        loop {
-            let mut this = self.as_mut().project();
-
-            if let Some(active_shutdown) = this.active_shutdown.as_mut().as_pin_mut() {
-                match active_shutdown.poll(cx) {
-                    Poll::Ready(result) => {
-                        // This future is finished, set it to none.
-                        this.active_shutdown.set(None);
-
-                        match result {
-                            Ok(()) => return Poll::Ready(Ok(())),
-                            Err(e) => return Poll::Ready(Err(e.into())),
-                        }
-                    }
-                    Poll::Pending => return Poll::Pending,
-                }
+    // The session is never pinned, as it is driven via methods that take it by mutable reference.
+    impl<S> Unpin for FoloTlsIo<S> {}
+
+    impl<S> Read for FoloTlsIo<S>
+    where
+        S: ByteStream,
+    {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            mut buf: ReadBufCursor<'_>,
+        ) -> Poll<std::io::Result<()>> {
+            if buf.remaining() == 0 {
+                return Poll::Ready(Ok(()));
             }
 
-            // There's no active shutdown, so start a new one.
-            this.active_shutdown.set(Some(this.connection.shutdown()));
+            // The Hyper buffer may be uninitialized, which the TLS session cannot write into, so
+            // we decrypt into a pooled buffer and copy from there.
+            let mut buffer = Buffer::<Isolated>::from_pool();
+            buffer.set_len(buffer.len().min(buf.remaining()));
+
+            let len = ready!(self
+                .get_mut()
+                .stream
+                .poll_read(cx, &mut buffer.as_mut_slice()))?;
+
+            buf.put_slice(&buffer.as_slice()[..len]);
+
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    impl<S> Write for FoloTlsIo<S>
+    where
+        S: ByteStream,
+    {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<std::io::Result<usize>> {
+            self.get_mut()
+                .stream
+                .poll_write(cx, buf)
+                .map_err(Into::into)
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            self.get_mut().stream.poll_flush(cx).map_err(Into::into)
+        }
+
+        fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            self.get_mut().stream.poll_shutdown(cx).map_err(Into::into)
         }
     }
 }
 
+#[cfg(feature = "tls")]
+pub use tls::*;
+
 // Timer
 pub struct FoloTimer {
     clock: Clock,
@@ -1,27 +1,65 @@
+mod aligned_buffer;
+mod async_handle;
+mod blocking_fallback;
+mod buf_reader;
+mod buf_writer;
 mod buffer;
+mod buffer_pool;
 mod completion_port;
 mod completion_port_shared;
+mod copy;
 mod driver;
 mod driver_shared;
 mod error;
+#[cfg(feature = "fakes")]
+pub(crate) mod fault_injection;
+#[cfg(feature = "futures-io")]
+mod futures_io;
+mod handle_budget;
 mod operation;
 mod operation_result;
 mod operation_result_shared;
 mod operation_shared;
+#[cfg(feature = "op-tracing")]
+mod operation_trace;
 mod primitive;
+mod quota;
+mod stdio;
+#[cfg(feature = "tokio-compat")]
+mod tokio_compat;
 mod waker;
+mod write_queue;
 
+pub use aligned_buffer::*;
+pub use async_handle::*;
+pub use blocking_fallback::*;
+pub use buf_reader::*;
+pub use buf_writer::*;
 pub use buffer::*;
+pub use buffer_pool::*;
 pub(crate) use completion_port::*;
 pub(crate) use completion_port_shared::*;
+pub use copy::*;
 pub(crate) use driver::*;
 pub(crate) use driver_shared::*;
 pub use error::*;
+#[cfg(feature = "fakes")]
+pub use fault_injection::{inject_faults, Fault, FaultInjection, FaultPlan, InjectedError};
+#[cfg(feature = "futures-io")]
+pub use futures_io::*;
+pub use handle_budget::*;
 pub(crate) use operation::*;
 pub use operation_result::*;
 pub use operation_result_shared::*;
+#[cfg(feature = "op-tracing")]
+pub use operation_trace::*;
 pub(crate) use primitive::*;
+pub use quota::*;
+pub use stdio::*;
+#[cfg(feature = "tokio-compat")]
+pub use tokio_compat::*;
 pub(crate) use waker::*;
+pub use write_queue::*;
 
 /// Max number of I/O operations to dequeue in one go. Presumably getting more data from the OS with
 /// a single call is desirable but the exact impact of different values on performance is not known.
@@ -0,0 +1,94 @@
+use crate::{
+    io::{buffer::POOLED_BUFFER_ALIGNMENT, Buffer},
+    mem::isolation::Isolated,
+};
+use std::pin::Pin;
+
+/// A buffer suitable for unbuffered file I/O, which bypasses the cache of the operating system and
+/// transfers data directly between the storage device and the buffer. See
+/// [`DirectFile`][crate::fs::DirectFile].
+///
+/// Unbuffered I/O requires the memory of the buffer, the length of every transfer and the offset
+/// in the file to be multiples of the sector size of the storage device. This type guarantees the
+/// first two: the memory comes from the I/O buffer pool of the current thread, whose buffers are
+/// aligned to [`ALIGNMENT`][Self::ALIGNMENT], and the length of the active region can only be set
+/// to multiples of `ALIGNMENT`. The active region always starts at the start of the buffer.
+///
+/// The one exception is a read that reaches the end of the file, which returns the buffer with the
+/// length set to the bytes that were read, whatever their count.
+#[derive(Debug)]
+pub struct AlignedBuffer {
+    inner: Buffer<Isolated>,
+}
+
+impl AlignedBuffer {
+    /// The alignment of the memory and the granularity of the length of the buffer. This is a
+    /// multiple of the sector size of all common storage devices.
+    pub const ALIGNMENT: usize = POOLED_BUFFER_ALIGNMENT;
+
+    /// Takes a buffer from the I/O buffer pool of the current thread, with the active region
+    /// covering the entire buffer.
+    pub fn from_pool() -> Self {
+        Self {
+            inner: Buffer::<Isolated>::from_pool(),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.inner.capacity()
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// # Panics
+    ///
+    /// If the length is not a multiple of [`ALIGNMENT`][Self::ALIGNMENT] or exceeds the capacity
+    /// of the buffer.
+    pub fn set_len(&mut self, new_len: usize) {
+        assert!(
+            new_len % Self::ALIGNMENT == 0,
+            "length of aligned buffer must be a multiple of {}",
+            Self::ALIGNMENT
+        );
+
+        self.inner.set_len(new_len);
+    }
+
+    /// Marks the entire buffer as the active area.
+    pub fn use_all(self) -> Self {
+        Self {
+            inner: self.inner.use_all(),
+        }
+    }
+
+    /// Obtains a mutable view over the active region of the buffer.
+    pub fn as_mut_slice(&mut self) -> Pin<&mut [u8]> {
+        self.inner.as_mut_slice()
+    }
+
+    /// Obtains an immutable view over the active region of the buffer.
+    pub fn as_slice(&self) -> Pin<&[u8]> {
+        self.inner.as_slice()
+    }
+
+    /// Converts the aligned buffer into a regular buffer, e.g. to write the data read from a file
+    /// to a socket.
+    pub fn into_inner(self) -> Buffer<Isolated> {
+        self.inner
+    }
+
+    /// Wraps a buffer that an unbuffered I/O operation has returned. The caller guarantees that
+    /// the buffer started out as an aligned buffer.
+    pub(crate) fn from_completed(inner: Buffer<Isolated>) -> Self {
+        debug_assert_eq!(inner.start(), 0);
+
+        Self { inner }
+    }
+}
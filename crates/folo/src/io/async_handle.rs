@@ -0,0 +1,206 @@
+use crate::{
+    io::{self, Buffer},
+    mem::isolation::Isolated,
+    net::windows::named_pipe,
+    rt::{current_async_agent, spawn_sync, SynchronousTaskType},
+    windows::OwnedHandle,
+};
+use negative_impl::negative_impl;
+use std::{ffi::c_void, mem, os::windows::io::IntoRawHandle, rc::Rc, sync::Arc};
+use windows::{
+    Wdk::Storage::FileSystem::{FileModeInformation, NtQueryInformationFile},
+    Win32::{
+        Foundation::{ERROR_BROKEN_PIPE, ERROR_HANDLE_EOF, HANDLE},
+        Storage::FileSystem::{GetFileType, ReadFile, WriteFile, FILE_TYPE_CHAR},
+        System::{Console::GetConsoleMode, IO::IO_STATUS_BLOCK},
+    },
+};
+
+// Not yet exposed by the windows crate. If either flag is set in the mode of a handle, the handle
+// was opened for synchronous I/O (i.e. without FILE_FLAG_OVERLAPPED).
+const FILE_SYNCHRONOUS_IO_ALERT: u32 = 0x0000_0010;
+const FILE_SYNCHRONOUS_IO_NONALERT: u32 = 0x0000_0020;
+
+/// An arbitrary handle to a stream-like OS object (e.g. a device, a pipe or a console) adopted
+/// from outside of Folo, with reads and writes exposed as async operations.
+///
+/// Not all handles support asynchronous I/O - consoles, handles opened without
+/// `FILE_FLAG_OVERLAPPED` and some device drivers can only perform I/O synchronously. Such handles
+/// are detected when the `AsyncHandle` is created and their operations are transparently performed
+/// on a synchronous worker thread instead, with the async worker thread free to do other work while
+/// waiting. Use [`is_blocking()`][Self::is_blocking] to find out which mode is used.
+///
+/// In blocking mode, an operation that is waiting for the OS occupies a synchronous worker thread
+/// until it completes, even if the caller stops waiting for it. Any data read after the caller has
+/// stopped waiting is lost.
+///
+/// Operations are not positioned - this type is not suitable for random access to files opened
+/// for asynchronous I/O. Use [`folo::fs::File`][crate::fs::File] for those.
+#[derive(Debug)]
+pub struct AsyncHandle {
+    mode: HandleMode,
+}
+
+#[derive(Debug)]
+enum HandleMode {
+    // The handle is bound to the I/O driver of the current async worker thread.
+    Overlapped(Rc<OwnedHandle<HANDLE>>),
+
+    // Operations are performed on synchronous worker threads, which share the handle.
+    Blocking(Arc<OwnedHandle<HANDLE>>),
+}
+
+impl AsyncHandle {
+    /// Takes ownership of the handle, binding it to the I/O driver of the current async worker
+    /// thread if it supports asynchronous I/O.
+    pub fn new(handle: std::os::windows::io::OwnedHandle) -> io::Result<Self> {
+        // SAFETY: Ownership of the handle is transferred to us. File-like handles are valid to
+        // close from any thread.
+        let handle = unsafe { OwnedHandle::new(HANDLE(handle.into_raw_handle())) };
+
+        if !supports_overlapped_io(*handle) {
+            return Ok(Self {
+                mode: HandleMode::Blocking(Arc::new(handle)),
+            });
+        }
+
+        // Some objects report themselves as capable of asynchronous I/O yet refuse to be bound to
+        // a completion port. We treat those the same as synchronous handles, also if the runtime
+        // has accepted them via its own blocking fallback.
+        let mode = match current_async_agent::with_io(|io| io.bind_io_primitive(&*handle)) {
+            Ok(()) if io::blocking_fallback_waker(*handle).is_none() => {
+                HandleMode::Overlapped(Rc::new(handle))
+            }
+            _ => HandleMode::Blocking(Arc::new(handle)),
+        };
+
+        Ok(Self { mode })
+    }
+
+    /// Whether operations on the handle are performed on a synchronous worker thread because the
+    /// handle does not support asynchronous I/O.
+    pub fn is_blocking(&self) -> bool {
+        matches!(self.mode, HandleMode::Blocking(_))
+    }
+
+    /// Reads the next available data from the handle into the active region of the buffer.
+    ///
+    /// Returns the buffer with the active region set to the bytes read, with a length of 0 if the
+    /// end of the stream has been reached.
+    pub async fn read(&mut self, mut buffer: Buffer<Isolated>) -> io::Result<Buffer<Isolated>> {
+        let handle = match &self.mode {
+            HandleMode::Overlapped(handle) => {
+                return named_pipe::read(Rc::clone(handle), buffer).await
+            }
+            HandleMode::Blocking(handle) => Arc::clone(handle),
+        };
+
+        let capacity = buffer.len();
+
+        // Buffers from the isolated pool cannot leave the current thread, so the data is received
+        // into a temporary buffer and copied over.
+        let data = spawn_sync(
+            SynchronousTaskType::Syscall,
+            move || -> io::Result<Vec<u8>> {
+                let mut data = vec![0; capacity];
+                let mut bytes_read = 0;
+
+                // SAFETY: All we need to be concerned about is passing in valid arguments, which
+                // we do. Handle liveness is ensured by our shared ownership of the handle.
+                match unsafe { ReadFile(**handle, Some(&mut data), Some(&mut bytes_read), None) } {
+                    Ok(()) => {}
+                    // The other end of a pipe has been closed. We report this as the end of the
+                    // stream, just like in overlapped mode.
+                    Err(e)
+                        if e.code() == ERROR_BROKEN_PIPE.into()
+                            || e.code() == ERROR_HANDLE_EOF.into() => {}
+                    Err(e) => return Err(e.into()),
+                }
+
+                data.truncate(bytes_read as usize);
+                Ok(data)
+            },
+        )
+        .await?;
+
+        buffer.as_mut_slice()[..data.len()].copy_from_slice(&data);
+        buffer.set_len(data.len());
+
+        Ok(buffer)
+    }
+
+    /// Writes the active region of the buffer to the handle.
+    ///
+    /// Returns the buffer with the active region set to the bytes that were written.
+    pub async fn write(&mut self, mut buffer: Buffer<Isolated>) -> io::Result<Buffer<Isolated>> {
+        let handle = match &self.mode {
+            HandleMode::Overlapped(handle) => {
+                return named_pipe::write(Rc::clone(handle), buffer).await
+            }
+            HandleMode::Blocking(handle) => Arc::clone(handle),
+        };
+
+        // Buffers from the isolated pool cannot leave the current thread, so the data is copied
+        // into a temporary buffer for the synchronous worker thread.
+        let data = buffer.as_slice().to_vec();
+
+        let bytes_written = spawn_sync(SynchronousTaskType::Syscall, move || -> io::Result<u32> {
+            let mut bytes_written = 0;
+
+            // SAFETY: All we need to be concerned about is passing in valid arguments, which we
+            // do. Handle liveness is ensured by our shared ownership of the handle.
+            unsafe { WriteFile(**handle, Some(&data), Some(&mut bytes_written), None) }?;
+
+            Ok(bytes_written)
+        })
+        .await?;
+
+        buffer.set_len(bytes_written as usize);
+
+        Ok(buffer)
+    }
+}
+
+#[negative_impl]
+impl !Send for AsyncHandle {}
+#[negative_impl]
+impl !Sync for AsyncHandle {}
+
+/// Whether the handle was opened for asynchronous I/O. Consoles never support it, regardless of
+/// how they were opened.
+fn supports_overlapped_io(handle: HANDLE) -> bool {
+    // SAFETY: All we need to be concerned about is passing in a valid handle, which we do.
+    if unsafe { GetFileType(handle) } == FILE_TYPE_CHAR && is_console(handle) {
+        return false;
+    }
+
+    let mut status = IO_STATUS_BLOCK::default();
+    let mut mode: u32 = 0;
+
+    // SAFETY: All we need to be concerned about is passing in valid arguments, which we do.
+    let result = unsafe {
+        NtQueryInformationFile(
+            handle,
+            &mut status,
+            &mut mode as *mut _ as *mut c_void,
+            mem::size_of::<u32>() as u32,
+            FileModeInformation,
+        )
+    };
+
+    // If the OS cannot tell us the mode, we optimistically attempt asynchronous I/O. Binding the
+    // handle to the completion port will tell us soon enough if that is not going to work.
+    if result.is_err() {
+        return true;
+    }
+
+    mode & (FILE_SYNCHRONOUS_IO_ALERT | FILE_SYNCHRONOUS_IO_NONALERT) == 0
+}
+
+fn is_console(handle: HANDLE) -> bool {
+    let mut console_mode = Default::default();
+
+    // SAFETY: All we need to be concerned about is passing in valid arguments, which we do.
+    // The call only succeeds for console handles.
+    unsafe { GetConsoleMode(handle, &mut console_mode) }.is_ok()
+}
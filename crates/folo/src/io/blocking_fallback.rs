@@ -0,0 +1,103 @@
+use crate::{
+    io::{self, IoWaker},
+    metrics::{observe, Event, EventBuilder},
+    rt::diagnostics::{self, Diagnostic},
+};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+use windows::Win32::Foundation::HANDLE;
+
+/// How the I/O operations of a runtime are completed. Reported by
+/// [`RuntimeClient::io_backend()`][crate::rt::RuntimeClient::io_backend].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum IoBackend {
+    /// All I/O operations complete via the I/O completion ports of the async worker threads.
+    CompletionPort,
+
+    /// Some I/O primitives could not be associated with an I/O completion port (e.g. because they
+    /// were opened for synchronous I/O or are already associated with a completion port owned by
+    /// other code), so operations on them are performed on synchronous worker threads instead.
+    /// Operations on other I/O primitives still complete via completion ports.
+    BlockingFallback,
+}
+
+/// Sets whether I/O primitives that cannot be associated with the completion port of the current
+/// thread fall back to blocking I/O. `used` is shared by all the async workers of the runtime and
+/// is set once any I/O primitive falls back.
+pub(crate) fn set_blocking_fallback(used: Option<Arc<AtomicBool>>) {
+    FALLBACK.set(used.map(|used| Fallback {
+        used,
+        primitives: HashMap::new(),
+    }));
+}
+
+/// Registers an I/O primitive that could not be associated with the completion port of the
+/// current thread, so operations on it are performed on synchronous worker threads, which deliver
+/// the results to the completion port via the waker. Returns the error from the association if
+/// the blocking fallback is disabled.
+pub(crate) fn fall_back_to_blocking(
+    primitive: HANDLE,
+    waker: IoWaker,
+    error: io::Error,
+) -> io::Result<()> {
+    FALLBACK.with_borrow_mut(|fallback| {
+        let Some(fallback) = fallback else {
+            return Err(error);
+        };
+
+        fallback.primitives.insert(primitive.0 as usize, waker);
+        observe!(PRIMITIVES_FALLEN_BACK.observe_unit());
+
+        // We only report the first one - once the operator knows, every other one is just noise.
+        if !fallback.used.swap(true, Ordering::Relaxed) {
+            diagnostics::emit(Diagnostic::IoBlockingFallback { error: &error });
+        }
+
+        Ok(())
+    })
+}
+
+/// Forgets an I/O primitive that has been associated with the completion port of the current
+/// thread. Handle values are reused after a handle is closed, so a handle that once fell back may
+/// now identify a different I/O primitive.
+pub(crate) fn clear_blocking_fallback(primitive: HANDLE) {
+    FALLBACK.with_borrow_mut(|fallback| {
+        if let Some(fallback) = fallback {
+            fallback.primitives.remove(&(primitive.0 as usize));
+        }
+    });
+}
+
+/// If operations on the I/O primitive are performed on synchronous worker threads, returns the
+/// waker through which the results are delivered to the completion port of the current thread.
+pub(crate) fn blocking_fallback_waker(primitive: HANDLE) -> Option<IoWaker> {
+    FALLBACK.with_borrow(|fallback| {
+        fallback
+            .as_ref()?
+            .primitives
+            .get(&(primitive.0 as usize))
+            .cloned()
+    })
+}
+
+#[derive(Debug)]
+struct Fallback {
+    used: Arc<AtomicBool>,
+
+    // The I/O primitives that fell back, by raw handle value.
+    primitives: HashMap<usize, IoWaker>,
+}
+
+thread_local! {
+    static FALLBACK: RefCell<Option<Fallback>> = const { RefCell::new(None) };
+
+    static PRIMITIVES_FALLEN_BACK: Event = EventBuilder::new("io_primitives_blocking_fallback")
+        .build();
+}
@@ -0,0 +1,191 @@
+use crate::{
+    io::{self, Buffer, OperationResultExt},
+    mem::isolation::Isolated,
+    net::ByteStream,
+};
+use negative_impl::negative_impl;
+
+/// Adds buffering to the reading side of a stream, so protocol code can read small pieces of data
+/// (e.g. lines or length prefixes) without issuing an I/O operation for each of them.
+///
+/// Data is received from the stream into a pooled buffer, from which it is handed out to the
+/// caller. A new read is only issued once all the data in the buffer has been consumed.
+///
+/// The methods are not cancellation-safe: if the future of a method is dropped before it
+/// completes, any data received by the read in progress is lost.
+///
+/// # Example
+///
+/// ```no_run
+/// use folo::{io::BufReader, net::TcpStream};
+/// use std::net::{Ipv4Addr, SocketAddr};
+///
+/// #[folo::main]
+/// async fn main() {
+///     let stream = TcpStream::connect(SocketAddr::from((Ipv4Addr::LOCALHOST, 1234)))
+///         .await
+///         .unwrap();
+///
+///     let mut reader = BufReader::new(stream);
+///     let mut line = String::new();
+///
+///     while reader.read_line(&mut line).await.unwrap() != 0 {
+///         print!("{line}");
+///         line.clear();
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct BufReader<S> {
+    stream: S,
+
+    // Data received from the stream that has not yet been consumed, in the active region. `None`
+    // if everything has been consumed.
+    received: Option<Buffer<Isolated>>,
+}
+
+impl<S> BufReader<S>
+where
+    S: ByteStream,
+{
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream,
+            received: None,
+        }
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Obtains a mutable reference to the inner stream. Reading from the stream directly skips any
+    /// data that has been received but not yet consumed.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// The data that has been received but not yet consumed.
+    pub fn buffer(&self) -> &[u8] {
+        self.received
+            .as_ref()
+            .map_or(&[], |received| received.as_slice().get_ref())
+    }
+
+    /// Returns the inner stream. Any data received but not yet consumed is lost.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    /// Returns the data that has been received but not yet consumed, reading more from the stream
+    /// if there is none. An empty slice indicates that the peer has closed the stream.
+    ///
+    /// The data remains in the reader until marked as consumed via [`consume()`][Self::consume].
+    pub async fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.received.is_none() {
+            let received = self
+                .stream
+                .read(Buffer::<Isolated>::from_pool())
+                .await
+                .into_inner()?;
+
+            self.received = Some(received);
+        }
+
+        Ok(self.buffer())
+    }
+
+    /// Marks the specified number of bytes returned by [`fill_buf()`][Self::fill_buf] as consumed,
+    /// so they are not returned again.
+    ///
+    /// # Panics
+    ///
+    /// If more bytes are consumed than are in the buffer.
+    pub fn consume(&mut self, amt: usize) {
+        let Some(received) = self.received.as_mut() else {
+            assert_eq!(amt, 0, "cannot consume data that has not been received");
+            return;
+        };
+
+        let remaining = received
+            .len()
+            .checked_sub(amt)
+            .expect("cannot consume more data than has been received");
+
+        if remaining == 0 {
+            // Once everything has been consumed, the next read receives a new buffer. We also get
+            // here after reporting the end of the stream, so the next read checks the stream again.
+            self.received = None;
+        } else {
+            received.set_len(remaining);
+            received.set_start(received.start() + amt);
+        }
+    }
+
+    /// Reads data into the provided slice, returning the number of bytes read. This is 0 if the
+    /// peer has closed the stream (or if the slice is empty).
+    pub async fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let available = self.fill_buf().await?;
+
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+
+        self.consume(len);
+
+        Ok(len)
+    }
+
+    /// Reads data until the delimiter or the end of the stream is reached, appending it to `out`
+    /// including the delimiter (if found). Returns the number of bytes appended, which is 0 if the
+    /// peer has closed the stream.
+    pub async fn read_until(&mut self, delimiter: u8, out: &mut Vec<u8>) -> io::Result<usize> {
+        let mut total = 0;
+
+        loop {
+            let available = self.fill_buf().await?;
+
+            if available.is_empty() {
+                return Ok(total);
+            }
+
+            let (len, found) = match available.iter().position(|b| *b == delimiter) {
+                Some(index) => (index + 1, true),
+                None => (available.len(), false),
+            };
+
+            out.extend_from_slice(&available[..len]);
+            self.consume(len);
+            total += len;
+
+            if found {
+                return Ok(total);
+            }
+        }
+    }
+
+    /// Reads a line of UTF-8 text, appending it to `out` including the terminating newline (if
+    /// found). Returns the number of bytes appended, which is 0 if the peer has closed the stream.
+    ///
+    /// If the line is not valid UTF-8, an error is returned and `out` is left unchanged, though
+    /// the line is still consumed.
+    pub async fn read_line(&mut self, out: &mut String) -> io::Result<usize> {
+        let mut line = Vec::new();
+        let len = self.read_until(b'\n', &mut line).await?;
+
+        let line = String::from_utf8(line)
+            .map_err(|_| io::Error::StdIo(std::io::ErrorKind::InvalidData.into()))?;
+
+        out.push_str(&line);
+
+        Ok(len)
+    }
+}
+
+#[negative_impl]
+impl<S> !Send for BufReader<S> {}
+#[negative_impl]
+impl<S> !Sync for BufReader<S> {}
@@ -0,0 +1,126 @@
+use crate::{
+    io::{self, Buffer, OperationResultExt},
+    mem::isolation::Isolated,
+    net::ByteStream,
+};
+use negative_impl::negative_impl;
+
+/// Adds buffering to the writing side of a stream, so protocol code can write small pieces of data
+/// (e.g. headers field by field) without issuing an I/O operation for each of them.
+///
+/// Data is collected in a pooled buffer, which is written to the stream once it is full or when
+/// [`flush()`][Self::flush] is called. Data still in the buffer when the writer is dropped is lost,
+/// so always flush (or [`shutdown()`][Self::shutdown]) when done writing.
+///
+/// The methods are not cancellation-safe: if the future of a method is dropped before it
+/// completes, the data being written to the stream may be partially written or lost.
+#[derive(Debug)]
+pub struct BufWriter<S> {
+    stream: S,
+
+    // Data not yet written to the stream, in the active region, which always starts at the start
+    // of the buffer. `None` only while the buffer is owned by the I/O driver for a write.
+    pending: Option<Buffer<Isolated>>,
+}
+
+impl<S> BufWriter<S>
+where
+    S: ByteStream,
+{
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream,
+            pending: Some(empty_buffer()),
+        }
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Obtains a mutable reference to the inner stream. Writing to the stream directly bypasses
+    /// any data that has been written to the writer but not yet flushed.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// The data that has been written to the writer but not yet flushed.
+    pub fn buffer(&self) -> &[u8] {
+        self.pending
+            .as_ref()
+            .map_or(&[], |pending| pending.as_slice().get_ref())
+    }
+
+    /// Returns the inner stream. Any data written to the writer but not yet flushed is lost.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    /// Writes all the data to the writer, flushing the buffer to the stream whenever it fills up.
+    pub async fn write_all(&mut self, mut data: &[u8]) -> io::Result<()> {
+        while !data.is_empty() {
+            let pending = self.pending.get_or_insert_with(empty_buffer);
+
+            let len = pending.len();
+            let available = pending.capacity() - len;
+
+            if available == 0 {
+                self.flush_buffer().await?;
+                continue;
+            }
+
+            let chunk = available.min(data.len());
+
+            pending.set_len(len + chunk);
+            pending.as_mut_slice()[len..].copy_from_slice(&data[..chunk]);
+
+            data = &data[chunk..];
+        }
+
+        Ok(())
+    }
+
+    /// Writes any buffered data to the stream, waiting for the write to complete.
+    pub async fn flush(&mut self) -> io::Result<()> {
+        self.flush_buffer().await
+    }
+
+    /// Flushes any buffered data and performs a graceful shutdown of the stream.
+    pub async fn shutdown(&mut self) -> io::Result<()> {
+        self.flush().await?;
+        self.stream.shutdown().await
+    }
+
+    async fn flush_buffer(&mut self) -> io::Result<()> {
+        let Some(pending) = self.pending.take() else {
+            // A previous flush was abandoned while the write was in progress. The buffer went with
+            // it, so there is nothing left to write.
+            return Ok(());
+        };
+
+        if pending.is_empty() {
+            self.pending = Some(pending);
+            return Ok(());
+        }
+
+        let written = self.stream.write(pending).await.into_inner()?;
+
+        // We reuse the buffer for the next batch of data.
+        let mut buffer = written.use_all();
+        buffer.set_len(0);
+        self.pending = Some(buffer);
+
+        Ok(())
+    }
+}
+
+fn empty_buffer() -> Buffer<Isolated> {
+    let mut buffer = Buffer::<Isolated>::from_pool();
+    buffer.set_len(0);
+    buffer
+}
+
+#[negative_impl]
+impl<S> !Send for BufWriter<S> {}
+#[negative_impl]
+impl<S> !Sync for BufWriter<S> {}
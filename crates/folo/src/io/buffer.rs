@@ -1,6 +1,7 @@
 use crate::linked::link_ref;
 use crate::mem::isolation::{markers, Isolated, Shared};
 use crate::mem::{DropPolicy, PinnedSlabChain, PooledArrayLease, SharedArrayPool};
+use crate::rt::{charge_current_task, MemoryCharge};
 use std::cell::{RefCell, UnsafeCell};
 use std::mem::MaybeUninit;
 use std::ops::Range;
@@ -36,6 +37,10 @@ where
     // This is the start offset of the active range of the buffer.
     start: usize,
 
+    // Pooled buffers are charged to the task that obtained them, if task memory accounting is
+    // enabled. Caller-provided slices are the caller's memory, so they are not charged.
+    memory_charge: MemoryCharge,
+
     _isolation_mode: PhantomData<IsolationMode>,
 }
 
@@ -54,6 +59,7 @@ where
             },
             len,
             start: 0,
+            memory_charge: MemoryCharge::none(),
             _isolation_mode: PhantomData,
         }
     }
@@ -66,7 +72,10 @@ where
         // We are destroying the buffer without going through the usual drop logic.
         // SAFETY: We are forgetting self, so nobody should mind that we stole its contents.
         let storage = unsafe { ptr::read(&self.storage) };
+        // SAFETY: As above.
+        let memory_charge = unsafe { ptr::read(&self.memory_charge) };
         mem::forget(self);
+        drop(memory_charge);
 
         if let Storage::BoxedSlice { inner } = storage {
             Some(Pin::into_inner(inner))
@@ -235,7 +244,7 @@ impl Buffer<Isolated> {
             let storage = unsafe { (*storage).assume_init_mut() };
 
             // SAFETY: Obviously it is not going to be null, as we just got it from our storage.
-            let storage_ptr = unsafe { NonNull::new_unchecked(storage.get() as *mut [u8]) };
+            let storage_ptr = unsafe { NonNull::new_unchecked(storage.0.get() as *mut [u8]) };
 
             Buffer {
                 storage: Storage::IsolatedPool {
@@ -244,10 +253,29 @@ impl Buffer<Isolated> {
                 },
                 len: POOLED_BUFFER_CAPACITY_BYTES,
                 start: 0,
+                memory_charge: charge_current_task(POOLED_BUFFER_CAPACITY_BYTES),
                 _isolation_mode: PhantomData,
             }
         })
     }
+
+    /// The index of the buffer in the buffer pool of the current thread, if the buffer was
+    /// obtained from there.
+    pub(super) fn index_in_pool(&self) -> Option<usize> {
+        match self.storage {
+            Storage::IsolatedPool { index_in_pool, .. } => Some(index_in_pool),
+            _ => None,
+        }
+    }
+}
+
+/// The number of buffers in the buffer pool of the current thread that are in use and the number
+/// of buffers the pool can hold without growing.
+pub(super) fn isolated_pool_usage() -> (usize, usize) {
+    THREAD_ISOLATED_POOL.with(|pool| {
+        let pool = pool.borrow();
+        (pool.len(), pool.capacity())
+    })
 }
 
 impl Buffer<Shared> {
@@ -272,6 +300,7 @@ impl Buffer<Shared> {
             },
             len: POOLED_BUFFER_CAPACITY_BYTES,
             start: 0,
+            memory_charge: charge_current_task(POOLED_BUFFER_CAPACITY_BYTES),
             _isolation_mode: PhantomData,
         }
     }
@@ -337,7 +366,19 @@ impl fmt::Debug for Storage {
 /// Pooled buffers are always of a constant size defined here. In principle, we should one day
 /// allow callers to request a specific minimum size (and fall back to manual alloc for unrealistic
 /// sizes).
-const POOLED_BUFFER_CAPACITY_BYTES: usize = 64 * 1024;
+pub(super) const POOLED_BUFFER_CAPACITY_BYTES: usize = 64 * 1024;
+
+/// The alignment of the memory of the buffers in the thread-isolated pools. This satisfies the
+/// requirements of unbuffered file I/O on storage devices with sectors of up to this size.
+pub(super) const POOLED_BUFFER_ALIGNMENT: usize = 4096;
+
+/// The memory of a buffer in the thread-isolated pool. Aligning it to a page makes every pooled
+/// buffer usable for unbuffered file I/O. As the capacity is a multiple of the alignment, this
+/// costs no padding between the buffers in the pool.
+#[repr(C, align(4096))]
+struct AlignedBufferMemory(UnsafeCell<[u8; POOLED_BUFFER_CAPACITY_BYTES]>);
+
+const _: () = assert!(mem::align_of::<AlignedBufferMemory>() == POOLED_BUFFER_ALIGNMENT);
 
 link_ref!(static SHARED_POOL: SharedArrayPool<POOLED_BUFFER_CAPACITY_BYTES> = SharedArrayPool::new());
 
@@ -348,7 +389,7 @@ thread_local! {
     // We use MustNotDropItems policy because buffers are often referenced via raw pointers, so if
     // some items still exist in the collection, we have a high probability of dangling pointers,
     // which can be a big safety problem. All I/O buffers must be dropped before the thread exits.
-    static THREAD_ISOLATED_POOL: RefCell<PinnedSlabChain<UnsafeCell<[u8; POOLED_BUFFER_CAPACITY_BYTES]>>> =
+    static THREAD_ISOLATED_POOL: RefCell<PinnedSlabChain<AlignedBufferMemory>> =
         RefCell::new(PinnedSlabChain::new(DropPolicy::MustNotDropItems));
 }
 
@@ -0,0 +1,239 @@
+use crate::{
+    io::{self, buffer, Buffer},
+    mem::isolation::Isolated,
+};
+use std::{
+    cell::RefCell,
+    collections::HashSet,
+    ffi::c_void,
+    sync::{Mutex, PoisonError},
+};
+use tracing::{event, Level};
+use windows::Win32::{
+    Foundation::ERROR_WORKING_SET_QUOTA,
+    System::{
+        Memory::VirtualLock,
+        Threading::{GetCurrentProcess, GetProcessWorkingSetSize, SetProcessWorkingSetSize},
+    },
+};
+
+/// The pool of fixed-size buffers that the current thread uses for I/O operations.
+///
+/// Every thread has its own pool. [`Buffer::<Isolated>::from_pool()`][Buffer::from_pool] takes
+/// buffers from the pool of the current thread and dropping a buffer returns it to the pool, so
+/// once the pool has grown to fit the workload, I/O operations no longer allocate memory for
+/// their buffers.
+///
+/// The pool grows on demand but you can also reserve buffers in advance via
+/// [`reserve()`][Self::reserve], to move the cost of growing out of the hot path. Reserved buffers
+/// can also be registered with the operating system via
+/// [`reserve_registered()`][Self::reserve_registered], which locks their memory into physical
+/// memory. This ensures that I/O operations never wait for the buffers to be paged in and makes
+/// it cheap for the kernel to pin the pages of the buffers for the duration of every operation.
+///
+/// To reserve buffers on every async worker thread when the runtime starts, use
+/// [`RuntimeBuilder::io_buffers_per_worker()`][crate::rt::RuntimeBuilder::io_buffers_per_worker].
+#[derive(Debug)]
+pub struct BufferPool {
+    _private: (),
+}
+
+impl BufferPool {
+    /// The capacity of every buffer in the pool, in bytes.
+    pub const BUFFER_SIZE: usize = buffer::POOLED_BUFFER_CAPACITY_BYTES;
+
+    /// Takes a buffer from the pool of the current thread. Equivalent to
+    /// `Buffer::<Isolated>::from_pool()`.
+    pub fn take() -> Buffer<Isolated> {
+        Buffer::<Isolated>::from_pool()
+    }
+
+    /// Grows the pool of the current thread so that at least `count` more buffers can be taken
+    /// from it without allocating memory.
+    pub fn reserve(count: usize) {
+        // Taking the buffers from the pool forces it to grow until they all fit. When we drop them,
+        // they go back to the pool, ready for reuse.
+        let buffers = (0..count).map(|_| Self::take()).collect::<Vec<_>>();
+        drop(buffers);
+    }
+
+    /// Grows the pool of the current thread so that at least `count` more buffers can be taken
+    /// from it without allocating memory and locks the memory of these buffers into physical
+    /// memory.
+    ///
+    /// If the process is not allowed to lock enough memory, the minimum working set of the
+    /// process is raised to make room for the buffers. The memory remains locked until the thread
+    /// exits.
+    pub fn reserve_registered(count: usize) -> io::Result<()> {
+        let mut buffers = (0..count).map(|_| Self::take()).collect::<Vec<_>>();
+
+        REGISTERED_BUFFERS.with_borrow_mut(|registered| {
+            let mut unregistered = buffers
+                .iter_mut()
+                .filter(|buffer| {
+                    !registered.contains(
+                        &buffer
+                            .index_in_pool()
+                            .expect("buffer was just taken from the pool"),
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            let mut remaining = unregistered.len();
+
+            for buffer in &mut unregistered {
+                lock_buffer(buffer, remaining * Self::BUFFER_SIZE)?;
+
+                registered.insert(
+                    buffer
+                        .index_in_pool()
+                        .expect("buffer was just taken from the pool"),
+                );
+                remaining -= 1;
+            }
+
+            event!(
+                Level::DEBUG,
+                message = "registered I/O buffers",
+                count = unregistered.len()
+            );
+
+            Ok(())
+        })
+    }
+
+    /// Current usage statistics of the pool of the current thread.
+    pub fn stats() -> BufferPoolStats {
+        let (in_use, capacity) = buffer::isolated_pool_usage();
+
+        BufferPoolStats {
+            in_use,
+            capacity,
+            registered: REGISTERED_BUFFERS.with_borrow(HashSet::len),
+        }
+    }
+}
+
+/// Usage statistics of the I/O buffer pool of a thread, as returned by [`BufferPool::stats()`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct BufferPoolStats {
+    in_use: usize,
+    capacity: usize,
+    registered: usize,
+}
+
+impl BufferPoolStats {
+    /// The number of buffers that have been taken from the pool and not yet returned.
+    pub fn in_use(&self) -> usize {
+        self.in_use
+    }
+
+    /// The number of buffers the pool can hold without allocating more memory.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// The number of buffers whose memory is locked into physical memory.
+    pub fn registered(&self) -> usize {
+        self.registered
+    }
+}
+
+/// Locks the memory of a pooled buffer into physical memory. If the process has reached the limit
+/// of lockable memory, the limit is raised by `expected_bytes` (the size of all the buffers the
+/// caller is about to lock, so we do not need to raise the limit for each of them separately).
+fn lock_buffer(buffer: &mut Buffer<Isolated>, expected_bytes: usize) -> io::Result<()> {
+    let slice = buffer.as_mut_slice();
+    let (ptr, len) = (slice.as_ptr() as *const c_void, slice.len());
+
+    // SAFETY: The memory belongs to the pool of the current thread, which never releases it while
+    // the thread is alive. Locking does not affect the contents of the memory.
+    match unsafe { VirtualLock(ptr, len) } {
+        Ok(()) => Ok(()),
+        Err(e) if e.code() == ERROR_WORKING_SET_QUOTA.into() => {
+            grow_working_set(expected_bytes)?;
+
+            // SAFETY: See above.
+            Ok(unsafe { VirtualLock(ptr, len) }?)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Raises the minimum and maximum working set size of the process by the specified number of
+/// bytes. Pages locked into physical memory count against the minimum working set size.
+fn grow_working_set(bytes: usize) -> io::Result<()> {
+    // The working set size is a process-wide setting, so all threads that register buffers must
+    // take turns to update it.
+    let _guard = WORKING_SET_LOCK
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+
+    let mut minimum = 0;
+    let mut maximum = 0;
+
+    // SAFETY: All we need to be concerned about is passing in valid arguments, which we do.
+    unsafe {
+        let process = GetCurrentProcess();
+
+        GetProcessWorkingSetSize(process, &mut minimum, &mut maximum)?;
+        SetProcessWorkingSetSize(process, minimum + bytes, maximum + bytes)?;
+    }
+
+    event!(
+        Level::DEBUG,
+        message = "raised working set size to fit registered I/O buffers",
+        minimum = minimum + bytes,
+        maximum = maximum + bytes
+    );
+
+    Ok(())
+}
+
+static WORKING_SET_LOCK: Mutex<()> = Mutex::new(());
+
+thread_local! {
+    // The indexes of the pooled buffers of the current thread whose memory has been locked. The
+    // pool never releases memory while the thread is alive, so once locked, a buffer slot stays
+    // locked, no matter how many times it is taken and returned.
+    static REGISTERED_BUFFERS: RefCell<HashSet<usize>> = RefCell::new(HashSet::new());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reserve_grows_capacity() {
+        BufferPool::reserve(300);
+
+        let stats = BufferPool::stats();
+        assert!(stats.capacity() >= 300);
+        assert_eq!(stats.in_use(), 0);
+    }
+
+    #[test]
+    fn reserved_buffers_are_reused() {
+        BufferPool::reserve(10);
+        let capacity = BufferPool::stats().capacity();
+
+        let buffers = (0..10).map(|_| BufferPool::take()).collect::<Vec<_>>();
+        assert_eq!(BufferPool::stats().in_use(), 10);
+        assert_eq!(BufferPool::stats().capacity(), capacity);
+
+        drop(buffers);
+        assert_eq!(BufferPool::stats().in_use(), 0);
+    }
+
+    #[test]
+    fn registered_buffers_are_counted_once() {
+        BufferPool::reserve_registered(4).unwrap();
+        assert!(BufferPool::stats().registered() >= 4);
+
+        let registered = BufferPool::stats().registered();
+
+        // Registering again reuses the same buffers, which are already registered.
+        BufferPool::reserve_registered(4).unwrap();
+        assert_eq!(BufferPool::stats().registered(), registered);
+    }
+}
@@ -1,16 +1,23 @@
 use crate::{
     io::{self, IoPrimitive, IoWaker},
-    metrics::{Event, EventBuilder},
+    metrics::{observe, Event, EventBuilder},
     windows::OwnedHandle,
 };
 use negative_impl::negative_impl;
-use std::sync::Arc;
-use windows::Win32::{
-    Foundation::{HANDLE, INVALID_HANDLE_VALUE},
-    Storage::FileSystem::SetFileCompletionNotificationModes,
-    System::{
-        WindowsProgramming::{FILE_SKIP_COMPLETION_PORT_ON_SUCCESS, FILE_SKIP_SET_EVENT_ON_HANDLE},
-        IO::CreateIoCompletionPort,
+use std::{ffi::c_void, mem, ptr, sync::Arc};
+use windows::{
+    Wdk::Storage::FileSystem::{
+        FileReplaceCompletionInformation, NtSetInformationFile, FILE_COMPLETION_INFORMATION,
+    },
+    Win32::{
+        Foundation::{HANDLE, INVALID_HANDLE_VALUE},
+        Storage::FileSystem::SetFileCompletionNotificationModes,
+        System::{
+            WindowsProgramming::{
+                FILE_SKIP_COMPLETION_PORT_ON_SUCCESS, FILE_SKIP_SET_EVENT_ON_HANDLE,
+            },
+            IO::{CreateIoCompletionPort, IO_STATUS_BLOCK},
+        },
     },
 };
 
@@ -64,10 +71,12 @@ impl CompletionPort {
         // SAFETY: Our own handle cannot be invalid because we are keeping it alive via Arc.
         // We have to assume the user provided a valid handle (but if not, it will just be an
         // error result). We ignore the return value because it is our own handle on success.
-        unsafe {
-            CreateIoCompletionPort(handle, **self.handle, 0, 1)?;
+        if let Err(e) = unsafe { CreateIoCompletionPort(handle, **self.handle, 0, 1) } {
+            return io::fall_back_to_blocking(handle, self.waker(), e.into());
         }
 
+        io::clear_blocking_fallback(handle);
+
         // Why FILE_SKIP_SET_EVENT_ON_HANDLE: https://devblogs.microsoft.com/oldnewthing/20200221-00/?p=103466/
         //
         // SAFETY:
@@ -85,7 +94,7 @@ impl CompletionPort {
             )?;
         }
 
-        PRIMITIVES_BOUND.with(Event::observe_unit);
+        observe!(PRIMITIVES_BOUND.observe_unit());
 
         Ok(())
     }
@@ -107,6 +116,40 @@ impl !Send for CompletionPort {}
 #[negative_impl]
 impl !Sync for CompletionPort {}
 
+/// Removes the association between an I/O primitive and whatever completion port it is bound to,
+/// so it can be handed over to code outside Folo. Completion notification modes set when binding
+/// remain in effect.
+///
+/// The caller must ensure that no operations on the I/O primitive are in progress, as their
+/// completions would no longer reach the I/O driver.
+pub(crate) fn unbind_io_primitive(handle: &(impl Into<IoPrimitive> + Copy)) -> io::Result<()> {
+    let handle = HANDLE::from((*handle).into());
+
+    io::clear_blocking_fallback(handle);
+
+    // A null port removes the existing association.
+    let information = FILE_COMPLETION_INFORMATION {
+        Port: HANDLE::default(),
+        Key: ptr::null_mut(),
+    };
+
+    let mut status = IO_STATUS_BLOCK::default();
+
+    // SAFETY: All we need to be concerned about is passing in valid arguments, which we do.
+    unsafe {
+        NtSetInformationFile(
+            handle,
+            &mut status,
+            &information as *const _ as *const c_void,
+            mem::size_of::<FILE_COMPLETION_INFORMATION>() as u32,
+            FileReplaceCompletionInformation,
+        )
+    }
+    .ok()?;
+
+    Ok(())
+}
+
 thread_local! {
     static PRIMITIVES_BOUND: Event = EventBuilder::new("isolated_io_primitives_bound")
         .build();
@@ -1,6 +1,6 @@
 use crate::{
     io::{self, IoPrimitive},
-    metrics::{Event, EventBuilder},
+    metrics::{observe, Event, EventBuilder},
     windows::OwnedHandle,
 };
 use windows::Win32::{
@@ -79,7 +79,7 @@ impl CompletionPortShared {
             )?;
         }
 
-        PRIMITIVES_BOUND.with(Event::observe_unit);
+        observe!(PRIMITIVES_BOUND.observe_unit());
 
         Ok(())
     }
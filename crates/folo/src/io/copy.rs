@@ -0,0 +1,175 @@
+use crate::{
+    fs::File,
+    io::{self, Buffer, OperationResultExt, OperationResultFuture},
+    mem::isolation::Isolated,
+    net::{ByteStream, TcpStream},
+};
+use futures::{future::LocalBoxFuture, FutureExt};
+use std::task::{Context, Poll};
+use windows::Win32::Storage::FileSystem::GetFileSizeEx;
+
+/// Copies all data from the reader to the writer until the reader reaches the end of the stream.
+/// Returns the number of bytes copied.
+///
+/// The data is received into a pooled buffer, which is then handed to the writer as-is, so no data
+/// is copied in the memory of the process. The writer is not shut down at the end of the stream.
+///
+/// Windows offers no general-purpose way to transfer data between two streams inside the
+/// operating system. To copy a file to a TCP stream without the data passing through the process,
+/// use [`copy_file()`].
+pub async fn copy<R, W>(reader: &mut R, writer: &mut W) -> io::Result<u64>
+where
+    R: ByteStream,
+    W: ByteStream,
+{
+    let mut buffer = Buffer::<Isolated>::from_pool();
+    let mut copied = 0;
+
+    loop {
+        buffer = reader.read(buffer.use_all()).await.into_inner()?;
+
+        if buffer.is_empty() {
+            return Ok(copied);
+        }
+
+        let len = buffer.len() as u64;
+
+        buffer = writer.write(buffer).await.into_inner()?;
+        copied += len;
+    }
+}
+
+/// Copies data in both directions between two streams until both have reached the end of the
+/// stream, for example to connect a client to a backend in a proxy. Returns the number of bytes
+/// copied from `a` to `b` and from `b` to `a`.
+///
+/// When one stream reaches the end of the stream, the write direction of the other stream is shut
+/// down (see [`ByteStream::shutdown_write()`]), so the end of the stream is passed on to its peer.
+/// Data keeps flowing in the other direction until it reaches the end of the stream as well.
+///
+/// If either direction fails, copying stops in both directions and the error is returned.
+pub async fn copy_bidirectional<A, B>(a: &mut A, b: &mut B) -> io::Result<(u64, u64)>
+where
+    A: ByteStream,
+    B: ByteStream,
+{
+    let mut a_to_b = Transfer::new(a);
+    let mut b_to_a = Transfer::new(b);
+
+    futures::future::poll_fn(|cx| {
+        // Each direction is polled until it is done, after which it keeps reporting its total.
+        let a_to_b = a_to_b.poll(cx, a, b)?;
+        let b_to_a = b_to_a.poll(cx, b, a)?;
+
+        match (a_to_b, b_to_a) {
+            (Poll::Ready(a_to_b), Poll::Ready(b_to_a)) => Poll::Ready(Ok((a_to_b, b_to_a))),
+            _ => Poll::Pending,
+        }
+    })
+    .await
+}
+
+/// Copies the rest of a file, starting at its current position, to a TCP stream and advances the
+/// position to the end of the file. Returns the number of bytes copied.
+///
+/// The operating system transfers the data from the file to the socket directly, without copying
+/// it through the memory of the process. See [`TcpStream::send_file()`].
+pub async fn copy_file(file: &mut File, writer: &mut TcpStream) -> io::Result<u64> {
+    let mut len: i64 = 0;
+
+    // This only reads file metadata, so it is fast enough to call from the async worker thread.
+    // SAFETY: Handle liveness is ensured by the file, which outlives the call.
+    unsafe { GetFileSizeEx(**file.handle(), &mut len as *mut _) }?;
+
+    let start = file.position();
+    let end = (len as usize).max(start);
+
+    writer.send_file(file, start..end).await?;
+    file.set_position(end);
+
+    Ok((end - start) as u64)
+}
+
+/// One direction of a bidirectional copy, tracking the bytes copied so far.
+struct Transfer {
+    state: TransferState,
+    copied: u64,
+}
+
+enum TransferState {
+    Reading(OperationResultFuture),
+
+    // The length of the data being written is stored alongside the write.
+    Writing(OperationResultFuture, usize),
+
+    // The reader has reached the end of the stream, which we pass on to the writer.
+    ShuttingDown(LocalBoxFuture<'static, io::Result<()>>),
+
+    Done,
+}
+
+impl Transfer {
+    fn new<R>(reader: &mut R) -> Self
+    where
+        R: ByteStream,
+    {
+        Self {
+            state: TransferState::Reading(reader.read(Buffer::<Isolated>::from_pool())),
+            copied: 0,
+        }
+    }
+
+    /// Advances the transfer as far as possible, returning the number of bytes copied once the
+    /// transfer is done.
+    fn poll<R, W>(
+        &mut self,
+        cx: &mut Context<'_>,
+        reader: &mut R,
+        writer: &mut W,
+    ) -> io::Result<Poll<u64>>
+    where
+        R: ByteStream,
+        W: ByteStream,
+    {
+        loop {
+            match &mut self.state {
+                TransferState::Reading(read) => {
+                    let Poll::Ready(result) = read.poll_unpin(cx) else {
+                        return Ok(Poll::Pending);
+                    };
+
+                    let buffer = result.into_inner()?;
+
+                    self.state = if buffer.is_empty() {
+                        TransferState::ShuttingDown(writer.shutdown_write())
+                    } else {
+                        let len = buffer.len();
+                        TransferState::Writing(writer.write(buffer), len)
+                    };
+                }
+                TransferState::Writing(write, len) => {
+                    let len = *len;
+
+                    let Poll::Ready(result) = write.poll_unpin(cx) else {
+                        return Ok(Poll::Pending);
+                    };
+
+                    let buffer = result.into_inner()?;
+                    self.copied += len as u64;
+
+                    self.state = TransferState::Reading(reader.read(buffer.use_all()));
+                }
+                TransferState::ShuttingDown(shutdown) => {
+                    let Poll::Ready(result) = shutdown.poll_unpin(cx) else {
+                        return Ok(Poll::Pending);
+                    };
+
+                    result?;
+
+                    self.state = TransferState::Done;
+                }
+                TransferState::Done => return Ok(Poll::Ready(self.copied)),
+            }
+        }
+    }
+}
@@ -5,10 +5,11 @@ use crate::io::{
     Buffer, CompletionPort, IoPrimitive, IoWaker, IO_DEQUEUE_BATCH_SIZE, WAKE_UP_COMPLETION_KEY,
 };
 use crate::mem::isolation::Isolated;
-use crate::metrics::{Event, EventBuilder, Magnitude};
+use crate::metrics::{observe, Event, EventBuilder, Magnitude};
+use std::cell::Cell;
 use std::mem::{self, MaybeUninit};
 use windows::Win32::{
-    Foundation::WAIT_TIMEOUT,
+    Foundation::{WAIT_IO_COMPLETION, WAIT_TIMEOUT},
     System::IO::{GetQueuedCompletionStatusEx, OVERLAPPED_ENTRY},
 };
 use windows_result::HRESULT;
@@ -50,6 +51,11 @@ impl Driver {
         self.operation_store.is_empty()
     }
 
+    /// The number of I/O operations that have been started and not yet completed.
+    pub(crate) fn operations_in_flight(&self) -> usize {
+        self.operation_store.len()
+    }
+
     /// Binds an I/O primitive to the completion port of this driver, provided a handle to the I/O
     /// primitive in question (file handle, socket, ...). This must be called once for every I/O
     /// primitive used with this I/O driver.
@@ -88,51 +94,91 @@ impl Driver {
     /// Process any I/O completion notifications and return their results to the callers. If there
     /// is no queued I/O, we wait up to `max_wait_time_ms` milliseconds for new I/O activity, after
     /// which we simply return.
-    pub(crate) fn process_completions(&mut self, max_wait_time_ms: u32) {
+    ///
+    /// If `alertable` is set, the wait also ends when an APC is queued to the current thread (e.g.
+    /// by a timer that is due).
+    ///
+    /// Completions are dequeued in batches of up to the configured batch size. If a batch comes
+    /// back full, more completions are likely waiting, so we dequeue more batches (without
+    /// waiting) until the configured number of batches per cycle is reached.
+    pub(crate) fn process_completions(&mut self, max_wait_time_ms: u32, alertable: bool) {
+        let batch_size = COMPLETION_BATCH_SIZE.with(Cell::get);
+        let batches_per_cycle = COMPLETION_BATCHES_PER_CYCLE.with(Cell::get);
+
+        let mut wait_time_ms = max_wait_time_ms;
+        let mut alertable = alertable;
+
+        for _ in 0..batches_per_cycle.max(1) {
+            let dequeued = self.process_completion_batch(wait_time_ms, alertable, batch_size);
+
+            // A partial batch means we have drained the queue, so there is no point asking again.
+            if dequeued < batch_size {
+                return;
+            }
+
+            observe!(ADDITIONAL_BATCH_NEEDED.observe_unit());
+
+            // We only ever wait for the first batch.
+            wait_time_ms = 0;
+            alertable = false;
+        }
+    }
+
+    /// Dequeues up to `batch_size` completion notifications and processes them, returning the
+    /// number of notifications dequeued.
+    fn process_completion_batch(
+        &mut self,
+        max_wait_time_ms: u32,
+        alertable: bool,
+        batch_size: usize,
+    ) -> usize {
         let mut completed: [MaybeUninit<OVERLAPPED_ENTRY>; IO_DEQUEUE_BATCH_SIZE] =
             [MaybeUninit::uninit(); IO_DEQUEUE_BATCH_SIZE];
         let mut completed_items: u32 = 0;
 
-        // We intentionally do not loop here because we want to give the caller the opportunity to
-        // process received I/O as soon as possible. Otherwise we might start taking too small
-        // chunks out of the I/O completion stream. Tuning the batch size above is valuable to make
-        // sure we make best use of each iteration and do not leave too much queued in the OS.
+        // We intentionally only dequeue one batch per call because we want to give the caller the
+        // opportunity to process received I/O as soon as possible. Otherwise we might start taking
+        // too small chunks out of the I/O completion stream. Tuning the batch size is valuable to
+        // make sure we make best use of each iteration and do not leave too much queued in the OS.
+        let batch_size = batch_size.clamp(1, IO_DEQUEUE_BATCH_SIZE);
 
         // SAFETY: TODO
         unsafe {
-            let result = GET_COMPLETED_DURATION.with(|x| {
-                x.observe_duration_millis(|| {
-                    GetQueuedCompletionStatusEx(
-                        *self.completion_port.as_native_handle(),
-                        // MaybeUninit is a ZST and binary-compatible. We use it to avoid
-                        // initializing the array, which is only used for collecting output.
-                        mem::transmute::<
-                            &mut [std::mem::MaybeUninit<OVERLAPPED_ENTRY>],
-                            &mut [OVERLAPPED_ENTRY],
-                        >(completed.as_mut_slice()),
-                        &mut completed_items as *mut _,
-                        max_wait_time_ms,
-                        false,
-                    )
-                })
-            });
+            let result = observe!(GET_COMPLETED_DURATION.observe_duration_millis(|| {
+                GetQueuedCompletionStatusEx(
+                    *self.completion_port.as_native_handle(),
+                    // MaybeUninit is a ZST and binary-compatible. We use it to avoid
+                    // initializing the array, which is only used for collecting output.
+                    mem::transmute::<
+                        &mut [std::mem::MaybeUninit<OVERLAPPED_ENTRY>],
+                        &mut [OVERLAPPED_ENTRY],
+                    >(&mut completed[..batch_size]),
+                    &mut completed_items as *mut _,
+                    max_wait_time_ms,
+                    alertable,
+                )
+            }));
 
             match result {
                 Ok(()) => {}
                 // Timeout just means there was nothing to do - no I/O operations completed.
                 Err(e) if e.code() == HRESULT::from_win32(WAIT_TIMEOUT.0) => {
                     if max_wait_time_ms == 0 {
-                        POLL_TIMEOUTS.with(Event::observe_unit);
+                        observe!(POLL_TIMEOUTS.observe_unit());
                     } else {
-                        WAIT_TIMEOUTS.with(Event::observe_unit);
+                        observe!(WAIT_TIMEOUTS.observe_unit());
                     }
 
-                    return;
+                    return 0;
+                }
+                // An APC ended the wait early, e.g. because a timer is due. Same as a timeout.
+                Err(e) if e.code() == HRESULT::from_win32(WAIT_IO_COMPLETION.0) => {
+                    return 0;
                 }
                 Err(e) => panic!("unexpected error from GetQueuedCompletionStatusEx: {:?}", e),
             }
 
-            ASYNC_COMPLETIONS_DEQUEUED.with(|x| x.observe(completed_items as Magnitude));
+            observe!(ASYNC_COMPLETIONS_DEQUEUED.observe(completed_items as Magnitude));
 
             for index in 0..completed_items {
                 let overlapped_entry = completed[index as usize].assume_init();
@@ -148,9 +194,22 @@ impl Driver {
                 self.operation_store.complete_operation(overlapped_entry);
             }
         }
+
+        completed_items as usize
     }
 }
 
+/// Sets how completions are dequeued by the I/O driver of the current thread: the maximum number
+/// of completions dequeued at once (up to `IO_DEQUEUE_BATCH_SIZE`) and the maximum number of
+/// batches dequeued per cycle of the worker when the batches come back full.
+pub(crate) fn set_completion_batching(batch_size: usize, batches_per_cycle: usize) {
+    COMPLETION_BATCH_SIZE.with(|x| x.set(batch_size.clamp(1, IO_DEQUEUE_BATCH_SIZE)));
+    COMPLETION_BATCHES_PER_CYCLE.with(|x| x.set(batches_per_cycle.max(1)));
+}
+
+/// Default number of batches of completions dequeued per cycle of the worker.
+pub(crate) const DEFAULT_COMPLETION_BATCHES_PER_CYCLE: usize = 1;
+
 impl Drop for Driver {
     fn drop(&mut self) {
         // We must ensure that all I/O operations are completed before we drop the driver. This is
@@ -165,6 +224,11 @@ impl Drop for Driver {
 const ASYNC_COMPLETIONS_DEQUEUED_BUCKETS: &[Magnitude] = &[0, 1, 16, 64, 256, 512];
 
 thread_local! {
+    static COMPLETION_BATCH_SIZE: Cell<usize> = const { Cell::new(IO_DEQUEUE_BATCH_SIZE) };
+
+    static COMPLETION_BATCHES_PER_CYCLE: Cell<usize> =
+        const { Cell::new(DEFAULT_COMPLETION_BATCHES_PER_CYCLE) };
+
     static ASYNC_COMPLETIONS_DEQUEUED: Event = EventBuilder::new("io_async_completions_dequeued")
         .buckets(ASYNC_COMPLETIONS_DEQUEUED_BUCKETS)
         .build();
@@ -177,6 +241,11 @@ thread_local! {
     static WAIT_TIMEOUTS: Event = EventBuilder::new("io_async_completions_wait_timeouts")
         .build();
 
+    // A batch came back full, so we dequeued another one in the same cycle (or would have, if the
+    // limit of batches per cycle had not been reached).
+    static ADDITIONAL_BATCH_NEEDED: Event = EventBuilder::new("io_async_completions_full_batches")
+        .build();
+
     static GET_COMPLETED_DURATION: Event = EventBuilder::new("io_async_completions_get_duration_millis")
         .buckets(GENERAL_MILLISECONDS_BUCKETS)
         .build();
@@ -5,7 +5,7 @@ use crate::io::{
     Buffer, CompletionPortShared, IoPrimitive, IO_DEQUEUE_BATCH_SIZE,
 };
 use crate::mem::isolation::Shared;
-use crate::metrics::{Event, EventBuilder, Magnitude};
+use crate::metrics::{observe, Event, EventBuilder, Magnitude};
 use std::mem::{self, MaybeUninit};
 use windows::Win32::{
     Foundation::WAIT_TIMEOUT,
@@ -97,35 +97,33 @@ impl DriverShared {
 
         // SAFETY: TODO
         unsafe {
-            let result = GET_COMPLETED_DURATION.with(|x| {
-                x.observe_duration_millis(|| {
-                    GetQueuedCompletionStatusEx(
-                        *self.completion_port.as_native_handle(),
-                        // MaybeUninit is a ZST and binary-compatible. We use it to avoid
-                        // initializing the array, which is only used for collecting output.
-                        mem::transmute::<
-                            &mut [std::mem::MaybeUninit<OVERLAPPED_ENTRY>],
-                            &mut [OVERLAPPED_ENTRY],
-                        >(completed.as_mut_slice()),
-                        &mut completed_items as *mut _,
-                        // No waiting, poll and get out.
-                        0,
-                        false,
-                    )
-                })
-            });
+            let result = observe!(GET_COMPLETED_DURATION.observe_duration_millis(|| {
+                GetQueuedCompletionStatusEx(
+                    *self.completion_port.as_native_handle(),
+                    // MaybeUninit is a ZST and binary-compatible. We use it to avoid
+                    // initializing the array, which is only used for collecting output.
+                    mem::transmute::<
+                        &mut [std::mem::MaybeUninit<OVERLAPPED_ENTRY>],
+                        &mut [OVERLAPPED_ENTRY],
+                    >(completed.as_mut_slice()),
+                    &mut completed_items as *mut _,
+                    // No waiting, poll and get out.
+                    0,
+                    false,
+                )
+            }));
 
             match result {
                 Ok(()) => {}
                 // Timeout just means there was nothing to do - no I/O operations completed.
                 Err(e) if e.code() == HRESULT::from_win32(WAIT_TIMEOUT.0) => {
-                    POLL_TIMEOUTS.with(Event::observe_unit);
+                    observe!(POLL_TIMEOUTS.observe_unit());
                     return;
                 }
                 Err(e) => panic!("unexpected error from GetQueuedCompletionStatusEx: {:?}", e),
             }
 
-            ASYNC_COMPLETIONS_DEQUEUED.with(|x| x.observe(completed_items as Magnitude));
+            observe!(ASYNC_COMPLETIONS_DEQUEUED.observe(completed_items as Magnitude));
 
             for index in 0..completed_items {
                 let overlapped_entry = completed[index as usize].assume_init();
@@ -1,5 +1,18 @@
+use crate::{
+    io::{OperationError, OperationErrorShared},
+    rt::SpawnError,
+    time::DeadlineExceeded,
+};
+use std::io::ErrorKind;
 use thiserror::Error;
-use windows::Win32::Networking::WinSock::WSA_ERROR;
+use windows::Win32::{
+    Foundation::{RtlNtStatusToDosError, NTSTATUS},
+    Networking::WinSock::WSA_ERROR,
+};
+
+// HRESULT facility codes that wrap an error code of another kind, which we can unwrap.
+const FACILITY_WIN32: i32 = 7;
+const FACILITY_NT_BIT: i32 = 0x1000_0000;
 
 #[derive(Debug, Error)]
 pub enum Error {
@@ -9,6 +22,9 @@ pub enum Error {
     #[error("logic error: {0}")]
     LogicError(String),
 
+    #[error("soft limit of {0} open handles reached")]
+    HandleLimitReached(usize),
+
     #[error("Winsock error {} ({})", .code, .detail.0)]
     Winsock { code: i32, detail: WSA_ERROR },
 
@@ -18,22 +34,210 @@ pub enum Error {
     #[error(transparent)]
     StdIo(#[from] std::io::Error),
 
+    /// An I/O operation failed. Identifies the operation and the handle it was performed on (if
+    /// known) in addition to the error reported by the operating system. Created when converting
+    /// an [`OperationError`] into an `Error`, e.g. via `?`.
+    #[error("{operation} failed{}: {source}", describe_handle(.handle))]
+    Operation {
+        operation: &'static str,
+        handle: Option<usize>,
+        source: Box<Error>,
+    },
+
     // This is for unexpected situations like a thread disappearing without ever reporting status.
     // Things that we are not expecting, things that are programming errors in the library itself.
     #[error("internal error: {0}")]
     Internal(String),
 
     #[error(transparent)]
-    Other(#[from] Box<dyn std::error::Error + Send + Sync>), 
+    Other(#[from] Box<dyn std::error::Error + Send + Sync>),
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+impl Error {
+    /// The error code reported by the operating system, if the error originates from the operating
+    /// system. This is a Win32 error code (including Winsock error codes), as accepted by
+    /// [`std::io::Error::from_raw_os_error()`].
+    pub fn raw_os_error(&self) -> Option<i32> {
+        match self {
+            Error::Winsock { detail, .. } => Some(detail.0),
+            Error::Windows(e) => win32_code(e.code().0),
+            Error::StdIo(e) => e.raw_os_error(),
+            Error::Operation { source, .. } => source.raw_os_error(),
+            _ => None,
+        }
+    }
+
+    /// The category of the error, in the terms used by the standard library.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::InvalidOptions(_) => ErrorKind::InvalidInput,
+            Error::StdIo(e) => e.kind(),
+            Error::Operation { source, .. } => source.kind(),
+            _ => self.raw_os_error().map_or(ErrorKind::Other, |code| {
+                std::io::Error::from_raw_os_error(code).kind()
+            }),
+        }
+    }
+}
+
+/// Extracts the Win32 error code from an HRESULT, if it wraps one (possibly in the form of an
+/// NTSTATUS, as reported by I/O completions).
+fn win32_code(hresult: i32) -> Option<i32> {
+    if hresult & FACILITY_NT_BIT != 0 {
+        // SAFETY: No safety requirements, this only looks up a value in a table.
+        let code = unsafe { RtlNtStatusToDosError(NTSTATUS(hresult & !FACILITY_NT_BIT)) };
+        return Some(code as i32);
+    }
+
+    ((hresult >> 16) & 0x1FFF == FACILITY_WIN32).then_some(hresult & 0xFFFF)
+}
+
+fn describe_handle(handle: &Option<usize>) -> String {
+    handle.map_or_else(String::new, |handle| format!(" on handle {handle:#x}"))
+}
+
+impl From<OperationError> for Error {
+    fn from(value: OperationError) -> Self {
+        let (operation, handle) = (value.operation(), value.handle());
+        with_operation(value.into_inner(), operation, handle)
+    }
+}
+
+impl From<OperationErrorShared> for Error {
+    fn from(value: OperationErrorShared) -> Self {
+        let operation = value.operation();
+        with_operation(value.into_inner(), operation, None)
+    }
+}
+
+fn with_operation(error: Error, operation: Option<&'static str>, handle: Option<usize>) -> Error {
+    match operation {
+        Some(operation) => Error::Operation {
+            operation,
+            handle,
+            source: Box::new(error),
+        },
+        None => error,
+    }
+}
+
+impl From<DeadlineExceeded> for Error {
+    fn from(_: DeadlineExceeded) -> Self {
+        Error::StdIo(std::io::ErrorKind::TimedOut.into())
+    }
+}
+
 impl From<Error> for std::io::Error {
     fn from(value: Error) -> Self {
         match value {
             Error::StdIo(error) => error,
-            _ => std::io::Error::other(value)
+            _ => std::io::Error::new(value.kind(), value),
         }
     }
-}
\ No newline at end of file
+}
+
+impl From<OperationError> for std::io::Error {
+    fn from(value: OperationError) -> Self {
+        Error::from(value).into()
+    }
+}
+
+impl From<OperationErrorShared> for std::io::Error {
+    fn from(value: OperationErrorShared) -> Self {
+        Error::from(value).into()
+    }
+}
+
+impl From<DeadlineExceeded> for std::io::Error {
+    fn from(_: DeadlineExceeded) -> Self {
+        ErrorKind::TimedOut.into()
+    }
+}
+
+impl From<SpawnError> for std::io::Error {
+    fn from(value: SpawnError) -> Self {
+        std::io::Error::other(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::Buffer;
+    use windows::Win32::{
+        Foundation::{ERROR_HANDLE_EOF, ERROR_OPERATION_ABORTED, STATUS_END_OF_FILE},
+        Networking::WinSock::{SOCKET_ERROR, WSAECONNRESET},
+    };
+
+    #[test]
+    fn os_errors_are_unwrapped() {
+        let winsock = Error::Winsock {
+            code: SOCKET_ERROR,
+            detail: WSAECONNRESET,
+        };
+        assert_eq!(winsock.raw_os_error(), Some(WSAECONNRESET.0));
+        assert_eq!(winsock.kind(), ErrorKind::ConnectionReset);
+
+        let win32 = Error::Windows(windows_result::Error::from_hresult(
+            ERROR_OPERATION_ABORTED.to_hresult(),
+        ));
+        assert_eq!(win32.raw_os_error(), Some(ERROR_OPERATION_ABORTED.0 as i32));
+
+        let ntstatus = Error::Windows(STATUS_END_OF_FILE.into());
+        assert_eq!(ntstatus.raw_os_error(), Some(ERROR_HANDLE_EOF.0 as i32));
+
+        assert_eq!(Error::LogicError("oops".to_string()).raw_os_error(), None);
+        assert_eq!(
+            Error::InvalidOptions("oops".to_string()).kind(),
+            ErrorKind::InvalidInput
+        );
+    }
+
+    #[test]
+    fn operation_error_identifies_operation() {
+        let error = OperationError::new(
+            Error::Winsock {
+                code: SOCKET_ERROR,
+                detail: WSAECONNRESET,
+            },
+            Buffer::from_boxed_slice(vec![0; 16].into_boxed_slice()),
+        )
+        .with_operation("folo::net::receive", Some(0x1234));
+
+        let error = Error::from(error);
+        assert!(matches!(
+            error,
+            Error::Operation {
+                operation: "folo::net::receive",
+                handle: Some(0x1234),
+                ..
+            }
+        ));
+        assert_eq!(error.raw_os_error(), Some(WSAECONNRESET.0));
+
+        let message = error.to_string();
+        assert!(message.contains("folo::net::receive"));
+        assert!(message.contains("0x1234"));
+
+        let std_error = std::io::Error::from(error);
+        assert_eq!(std_error.kind(), ErrorKind::ConnectionReset);
+    }
+
+    #[test]
+    fn operation_error_without_context_is_unwrapped() {
+        let error = OperationError::new(
+            Error::LogicError("oops".to_string()),
+            Buffer::from_boxed_slice(vec![0; 16].into_boxed_slice()),
+        );
+
+        assert!(matches!(Error::from(error), Error::LogicError(_)));
+    }
+
+    #[test]
+    fn deadline_exceeded_is_timed_out() {
+        let error = std::io::Error::from(DeadlineExceeded);
+        assert_eq!(error.kind(), ErrorKind::TimedOut);
+    }
+}
@@ -0,0 +1,398 @@
+//! Fault injection for testing how code copes with misbehaving I/O. While a [`FaultPlan`] is
+//! installed on a thread via [`inject_faults()`], the I/O operations started on that thread may be
+//! made to fail with an injected error, to transfer fewer bytes than requested or to complete
+//! later than they otherwise would.
+//!
+//! Faults are either chosen pseudo-randomly from a seed or taken from an explicit script. The same
+//! seed and the same sequence of operations always produce the same faults, so a failure found
+//! with a random seed can be reproduced by running the test again with the seed it reports.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use folo::io::{inject_faults, FaultPlan, InjectedError};
+//!
+//! let faults = inject_faults(
+//!     FaultPlan::seeded(1234)
+//!         .errors(0.1, [InjectedError::ConnectionReset])
+//!         .short_transfers(0.25)
+//!         .matching("tcp_connection"),
+//! );
+//!
+//! // ... run the code under test on this thread ...
+//!
+//! println!("{} faults injected", faults.injected());
+//! ```
+
+use crate::{io, util::SplitMix64};
+use std::{cell::RefCell, collections::VecDeque, rc::Rc, time::Duration};
+use tracing::{event, Level};
+use windows::Win32::{
+    Foundation::ERROR_OPERATION_ABORTED,
+    Networking::WinSock::{SOCKET_ERROR, WSAECONNRESET},
+};
+
+/// An error that fault injection can make an I/O operation fail with.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum InjectedError {
+    /// The connection was reset by the remote peer (`WSAECONNRESET`).
+    ConnectionReset,
+
+    /// The operation was aborted, as if canceled (`ERROR_OPERATION_ABORTED`).
+    OperationAborted,
+}
+
+impl InjectedError {
+    fn to_error(self) -> io::Error {
+        match self {
+            InjectedError::ConnectionReset => io::Error::Winsock {
+                code: SOCKET_ERROR,
+                detail: WSAECONNRESET,
+            },
+            InjectedError::OperationAborted => io::Error::Windows(
+                windows_result::Error::from_hresult(ERROR_OPERATION_ABORTED.to_hresult()),
+            ),
+        }
+    }
+}
+
+/// A fault to inject into one I/O operation, as a step of a [scripted][FaultPlan::script] plan.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Fault {
+    /// The operation is performed normally.
+    None,
+
+    /// The operation fails with the error without being started.
+    Error(InjectedError),
+
+    /// The operation transfers at most this many bytes. It always transfers at least one byte
+    /// and, if its buffer is longer than one byte, fewer bytes than the buffer can hold.
+    ShortTransfer(usize),
+
+    /// The result of the operation is delivered only after this much additional time.
+    Latency(Duration),
+}
+
+/// Describes which faults to inject into I/O operations. Install with [`inject_faults()`].
+///
+/// Operations are affected in the order they are started. A scripted plan injects the faults of
+/// its script into the first operations, one per operation, before falling back to the seeded
+/// rates (which are zero unless set).
+#[derive(Clone, Debug)]
+pub struct FaultPlan {
+    seed: u64,
+    rng: SplitMix64,
+
+    error_rate: f64,
+    errors: Vec<InjectedError>,
+    short_transfer_rate: f64,
+    latency_rate: f64,
+    latency: Duration,
+
+    script: VecDeque<Fault>,
+    matching: Option<String>,
+}
+
+impl FaultPlan {
+    /// Creates a plan that makes its pseudo-random choices from the seed. Nothing is injected
+    /// until rates are set or a script is given.
+    pub fn seeded(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: SplitMix64::new(seed),
+            error_rate: 0.0,
+            errors: Vec::new(),
+            short_transfer_rate: 0.0,
+            latency_rate: 0.0,
+            latency: Duration::ZERO,
+            script: VecDeque::new(),
+            matching: None,
+        }
+    }
+
+    /// Creates a plan that injects the faults of the script into the first operations, in order.
+    pub fn script(faults: impl IntoIterator<Item = Fault>) -> Self {
+        let mut plan = Self::seeded(0);
+        plan.script = faults.into_iter().collect();
+        plan
+    }
+
+    /// The seed the plan was created with, for reproducing a run.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Fails the given fraction (0.0 to 1.0) of operations, each with one of the given errors.
+    pub fn errors(mut self, rate: f64, errors: impl IntoIterator<Item = InjectedError>) -> Self {
+        self.errors = errors.into_iter().collect();
+        self.error_rate = if self.errors.is_empty() { 0.0 } else { rate };
+        self
+    }
+
+    /// Makes the given fraction (0.0 to 1.0) of operations transfer only part of their buffer.
+    ///
+    /// Only reads and writes tolerate a shorter buffer - if other operations that use a buffer
+    /// (e.g. accepting a connection) may run on the thread, limit the plan to reads and writes via
+    /// [`matching()`][Self::matching].
+    pub fn short_transfers(mut self, rate: f64) -> Self {
+        self.short_transfer_rate = rate;
+        self
+    }
+
+    /// Delays the result of the given fraction (0.0 to 1.0) of operations by up to `max_latency`.
+    /// The delay uses the clock of the thread, so it passes instantly when time is paused.
+    pub fn latency(mut self, rate: f64, max_latency: Duration) -> Self {
+        self.latency_rate = rate;
+        self.latency = max_latency;
+        self
+    }
+
+    /// Only injects faults into operations started by code whose path contains the given text
+    /// (e.g. `tcp_connection` or `read_buffer_from_file`). By default, all operations are
+    /// affected.
+    pub fn matching(mut self, text: impl Into<String>) -> Self {
+        self.matching = Some(text.into());
+        self
+    }
+
+    fn next(&mut self, operation: &str) -> Chosen {
+        if let Some(text) = &self.matching {
+            if !operation.contains(text.as_str()) {
+                return Chosen::Fault(Fault::None);
+            }
+        }
+
+        if let Some(fault) = self.script.pop_front() {
+            return Chosen::Fault(fault);
+        }
+
+        // Every roll is always made, so the faults chosen for an operation do not depend on what
+        // was chosen for earlier ones.
+        let error = self.rng.next_below(self.errors.len().max(1) as u64) as usize;
+        let fail = self.rng.chance(self.error_rate);
+        let short = self.rng.chance(self.short_transfer_rate);
+        let short_fraction = self.rng.next_f64();
+        let delay = self.rng.chance(self.latency_rate);
+        let latency = self.latency.mul_f64(self.rng.next_f64());
+
+        if fail {
+            Chosen::Fault(Fault::Error(self.errors[error]))
+        } else if short {
+            // The length of the buffer is not known yet, so we choose a fraction of it.
+            Chosen::ShortTransferFraction(short_fraction)
+        } else if delay {
+            Chosen::Fault(Fault::Latency(latency))
+        } else {
+            Chosen::Fault(Fault::None)
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Chosen {
+    Fault(Fault),
+    ShortTransferFraction(f64),
+}
+
+/// Installs the plan on the current thread, replacing any plan installed before. The plan stays
+/// installed until the returned guard is dropped.
+pub fn inject_faults(plan: FaultPlan) -> FaultInjection {
+    let state = Rc::new(RefCell::new(FaultState { plan, injected: 0 }));
+
+    event!(
+        Level::DEBUG,
+        message = "I/O fault injection enabled",
+        seed = state.borrow().plan.seed
+    );
+
+    CURRENT.set(Some(Rc::clone(&state)));
+
+    FaultInjection { state }
+}
+
+/// Keeps a [`FaultPlan`] installed on the current thread. Dropping it stops the injection.
+#[derive(Debug)]
+pub struct FaultInjection {
+    state: Rc<RefCell<FaultState>>,
+}
+
+impl FaultInjection {
+    /// The number of operations a fault has been injected into so far.
+    pub fn injected(&self) -> u64 {
+        self.state.borrow().injected
+    }
+
+    /// The seed of the installed plan, for reproducing a run.
+    pub fn seed(&self) -> u64 {
+        self.state.borrow().plan.seed
+    }
+}
+
+impl Drop for FaultInjection {
+    fn drop(&mut self) {
+        CURRENT.with_borrow_mut(|current| {
+            // Only if a later plan has not replaced ours.
+            if current.as_ref().is_some_and(|x| Rc::ptr_eq(x, &self.state)) {
+                *current = None;
+            }
+        });
+    }
+}
+
+#[derive(Debug)]
+struct FaultState {
+    plan: FaultPlan,
+    injected: u64,
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<Rc<RefCell<FaultState>>>> = const { RefCell::new(None) };
+}
+
+/// The faults chosen for one I/O operation.
+#[derive(Debug, Default)]
+pub(crate) struct InjectedFaults {
+    chosen: Option<Chosen>,
+}
+
+impl InjectedFaults {
+    /// The error to fail the operation with instead of starting it, if any.
+    pub fn error(&self) -> Option<io::Error> {
+        match self.chosen {
+            Some(Chosen::Fault(Fault::Error(e))) => Some(e.to_error()),
+            _ => None,
+        }
+    }
+
+    /// Shortens the operation buffer if a short transfer was chosen.
+    pub fn limit_buffer(&self, buffer: &'static mut [u8]) -> &'static mut [u8] {
+        if buffer.len() <= 1 {
+            return buffer;
+        }
+
+        let max_len = buffer.len() - 1;
+
+        let len = match self.chosen {
+            Some(Chosen::Fault(Fault::ShortTransfer(len))) => len.clamp(1, max_len),
+            Some(Chosen::ShortTransferFraction(fraction)) => {
+                1 + (fraction * (max_len - 1) as f64) as usize
+            }
+            _ => return buffer,
+        };
+
+        &mut buffer[..len]
+    }
+
+    /// The additional time to wait before delivering the result of the operation, if any.
+    pub fn latency(&self) -> Option<Duration> {
+        match self.chosen {
+            Some(Chosen::Fault(Fault::Latency(latency))) if !latency.is_zero() => Some(latency),
+            _ => None,
+        }
+    }
+}
+
+/// Chooses the faults to inject into an I/O operation that is about to start. The operation is
+/// identified by the type name of the callback that starts it.
+pub(crate) fn next(operation: &str) -> InjectedFaults {
+    let Some(state) = CURRENT.with_borrow(|current| current.clone()) else {
+        return InjectedFaults::default();
+    };
+
+    let mut state = state.borrow_mut();
+    let chosen = state.plan.next(operation);
+
+    if chosen == Chosen::Fault(Fault::None) {
+        return InjectedFaults::default();
+    }
+
+    state.injected += 1;
+
+    event!(
+        Level::DEBUG,
+        message = "injecting I/O fault",
+        seed = state.plan.seed,
+        fault = ?chosen,
+        operation
+    );
+
+    InjectedFaults {
+        chosen: Some(chosen),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_faults() {
+        let plan = FaultPlan::seeded(42)
+            .errors(
+                0.3,
+                [
+                    InjectedError::ConnectionReset,
+                    InjectedError::OperationAborted,
+                ],
+            )
+            .short_transfers(0.3)
+            .latency(0.3, Duration::from_millis(100));
+
+        let mut a = plan.clone();
+        let mut b = plan;
+
+        let a = (0..100).map(|_| a.next("op")).collect::<Vec<_>>();
+        let b = (0..100).map(|_| b.next("op")).collect::<Vec<_>>();
+
+        assert_eq!(a, b);
+        assert!(a
+            .iter()
+            .any(|x| matches!(x, Chosen::Fault(Fault::Error(_)))));
+        assert!(a
+            .iter()
+            .any(|x| matches!(x, Chosen::ShortTransferFraction(_))));
+        assert!(a
+            .iter()
+            .any(|x| matches!(x, Chosen::Fault(Fault::Latency(_)))));
+        assert!(a.iter().any(|x| *x == Chosen::Fault(Fault::None)));
+    }
+
+    #[test]
+    fn script_then_rates() {
+        let mut plan = FaultPlan::script([
+            Fault::Error(InjectedError::OperationAborted),
+            Fault::ShortTransfer(3),
+        ])
+        .matching("read");
+
+        assert_eq!(plan.next("write"), Chosen::Fault(Fault::None));
+        assert_eq!(
+            plan.next("read"),
+            Chosen::Fault(Fault::Error(InjectedError::OperationAborted))
+        );
+        assert_eq!(plan.next("read"), Chosen::Fault(Fault::ShortTransfer(3)));
+
+        // No rates are set, so nothing more is injected.
+        assert_eq!(plan.next("read"), Chosen::Fault(Fault::None));
+    }
+
+    #[test]
+    fn short_transfer_keeps_at_least_one_byte() {
+        let limited_len = |chosen, len| {
+            let faults = InjectedFaults {
+                chosen: Some(chosen),
+            };
+
+            let buffer: &'static mut [u8] = Box::leak(vec![0u8; len].into_boxed_slice());
+            faults.limit_buffer(buffer).len()
+        };
+
+        assert_eq!(limited_len(Chosen::Fault(Fault::ShortTransfer(0)), 10), 1);
+        assert_eq!(limited_len(Chosen::Fault(Fault::ShortTransfer(4)), 10), 4);
+        assert_eq!(limited_len(Chosen::Fault(Fault::ShortTransfer(50)), 10), 9);
+        assert_eq!(limited_len(Chosen::ShortTransferFraction(0.0), 10), 1);
+        assert_eq!(limited_len(Chosen::ShortTransferFraction(0.99), 10), 8);
+        assert_eq!(limited_len(Chosen::Fault(Fault::ShortTransfer(4)), 1), 1);
+    }
+}
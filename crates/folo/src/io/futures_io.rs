@@ -0,0 +1,210 @@
+use crate::{
+    io::{Buffer, OperationResultFuture},
+    mem::isolation::Isolated,
+    net::{ByteStream, ShutdownFuture},
+};
+use futures::io::{AsyncBufRead, AsyncRead, AsyncWrite};
+use negative_impl::negative_impl;
+use pin_project::pin_project;
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+/// Exposes a Folo stream via the `AsyncRead`, `AsyncBufRead` and `AsyncWrite` traits of the
+/// `futures` crate, for use with codecs and protocol implementations written against these traits.
+///
+/// Folo streams transfer data via buffers owned by the I/O driver, whereas the traits transfer data
+/// via caller-provided slices that may be dropped at any time. The adapter bridges the two by
+/// copying data between the slices and pooled buffers:
+///
+/// * Reads receive data into a pooled buffer, from which it is copied into the slices of
+///   successive `poll_read()` calls. `poll_fill_buf()` exposes the buffer without copying.
+/// * Writes copy the data into a pooled buffer and report it as written right away, with the
+///   write to the stream continuing in the background. The next write (or flush) waits for it to
+///   complete, which also reports any error that occurred in the meantime.
+#[pin_project]
+#[derive(Debug)]
+pub struct FuturesIo<S> {
+    stream: S,
+
+    #[pin]
+    active_read: Option<OperationResultFuture>,
+
+    // Data received from the stream that has not yet been read by the caller, in the active region.
+    received: Option<Buffer<Isolated>>,
+
+    #[pin]
+    active_write: Option<OperationResultFuture>,
+
+    #[pin]
+    active_shutdown: Option<ShutdownFuture>,
+}
+
+impl<S> FuturesIo<S>
+where
+    S: ByteStream,
+{
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream,
+            active_read: None,
+            received: None,
+            active_write: None,
+            active_shutdown: None,
+        }
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Returns the inner stream. Any data received but not yet read is lost.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<S> AsyncRead for FuturesIo<S>
+where
+    S: ByteStream,
+{
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let available = ready!(self.as_mut().poll_fill_buf(cx))?;
+
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+
+        self.consume(len);
+
+        Poll::Ready(Ok(len))
+    }
+}
+
+impl<S> AsyncBufRead for FuturesIo<S>
+where
+    S: ByteStream,
+{
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<&[u8]>> {
+        let mut this = self.project();
+
+        if this.received.is_none() {
+            if this.active_read.is_none() {
+                // The I/O driver takes ownership of the buffer and keeps it alive until it is safe
+                // to release it, even if the adapter is dropped before the read completes.
+                this.active_read
+                    .set(Some(this.stream.read(Buffer::<Isolated>::from_pool())));
+            }
+
+            let result = ready!(this
+                .active_read
+                .as_mut()
+                .as_pin_mut()
+                .expect("we just ensured there is an active read")
+                .poll(cx));
+
+            this.active_read.set(None);
+
+            // An empty buffer signals the end of the stream, which we report as an empty slice.
+            *this.received = Some(result.map_err(|e| e.into_inner())?);
+        }
+
+        let received = this
+            .received
+            .as_ref()
+            .expect("we just ensured there is received data");
+
+        Poll::Ready(Ok(Pin::into_inner(received.as_slice())))
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        let this = self.project();
+
+        let Some(received) = this.received.as_mut() else {
+            assert_eq!(amt, 0, "cannot consume data that has not been received");
+            return;
+        };
+
+        let remaining = received.len() - amt;
+
+        if remaining == 0 {
+            // Once everything has been consumed, the next read receives a new buffer. We also get
+            // here after reporting the end of the stream, so the next read checks the stream again.
+            *this.received = None;
+        } else {
+            received.set_len(remaining);
+            received.set_start(received.start() + amt);
+        }
+    }
+}
+
+impl<S> AsyncWrite for FuturesIo<S>
+where
+    S: ByteStream,
+{
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        // Only one write is in flight at a time, which limits how much data we buffer.
+        ready!(self.as_mut().poll_flush(cx))?;
+
+        let mut this = self.project();
+
+        let mut buffer = Buffer::<Isolated>::from_pool();
+        let len = buffer.len().min(buf.len());
+        buffer.set_len(len);
+        buffer.as_mut_slice().copy_from_slice(&buf[..len]);
+
+        // The I/O driver takes ownership of the buffer and keeps it alive until it is safe to
+        // release it, even if the adapter is dropped before the write completes.
+        this.active_write.set(Some(this.stream.write(buffer)));
+
+        Poll::Ready(Ok(len))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let mut this = self.project();
+
+        let Some(active_write) = this.active_write.as_mut().as_pin_mut() else {
+            return Poll::Ready(Ok(()));
+        };
+
+        let result = ready!(active_write.poll(cx));
+        this.active_write.set(None);
+
+        Poll::Ready(result.map(|_| ()).map_err(|e| e.into_inner().into()))
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        ready!(self.as_mut().poll_flush(cx))?;
+
+        let mut this = self.project();
+
+        if this.active_shutdown.is_none() {
+            this.active_shutdown.set(Some(this.stream.shutdown()));
+        }
+
+        let result = ready!(this
+            .active_shutdown
+            .as_mut()
+            .as_pin_mut()
+            .expect("we just ensured there is an active shutdown")
+            .poll(cx));
+
+        this.active_shutdown.set(None);
+
+        Poll::Ready(result.map_err(Into::into))
+    }
+}
+
+#[negative_impl]
+impl<S> !Send for FuturesIo<S> {}
+#[negative_impl]
+impl<S> !Sync for FuturesIo<S> {}
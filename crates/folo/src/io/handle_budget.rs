@@ -0,0 +1,243 @@
+use crate::{
+    constants::POISONED_LOCK,
+    io,
+    metrics::{observe, Event, EventBuilder, Magnitude},
+};
+use std::{
+    cell::Cell,
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    task::{Context, Poll, Waker},
+};
+
+/// What happens when an operation that opens a handle (e.g. accepting a connection or opening a
+/// file) starts while the soft limit set via
+/// [`RuntimeBuilder::max_open_handles()`][crate::rt::RuntimeBuilder::max_open_handles] has been
+/// reached.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum HandleLimitPolicy {
+    /// The operation waits until some of the open handles are closed. Incoming connections wait
+    /// in the backlog of the listening socket meanwhile.
+    #[default]
+    Wait,
+
+    /// The operation fails with [`Error::HandleLimitReached`][io::Error::HandleLimitReached].
+    Fail,
+}
+
+/// The number of handles (files, sockets, pipes and other operating system objects) currently
+/// owned by Folo in this process, across all runtimes and threads.
+pub fn open_handles() -> usize {
+    HANDLES.current()
+}
+
+/// The highest number of handles that have been owned by Folo in this process at the same time.
+pub fn peak_open_handles() -> usize {
+    HANDLES.peak()
+}
+
+/// Counts the handles owned by the process. There is only one instance, shared by all threads,
+/// because the operating system limits are per process.
+#[derive(Debug)]
+struct HandleCounter {
+    current: AtomicUsize,
+    peak: AtomicUsize,
+
+    // Operations waiting for a handle to be closed. Each closed handle wakes one of them.
+    waiting: Mutex<Vec<Waker>>,
+}
+
+impl HandleCounter {
+    const fn new() -> Self {
+        Self {
+            current: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+            waiting: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn current(&self) -> usize {
+        self.current.load(Ordering::Relaxed)
+    }
+
+    fn peak(&self) -> usize {
+        self.peak.load(Ordering::Relaxed)
+    }
+
+    fn opened(&self) {
+        let current = self.current.fetch_add(1, Ordering::Relaxed) + 1;
+
+        self.peak.fetch_max(current, Ordering::Relaxed);
+        observe!(OPEN_HANDLES.observe(current as Magnitude));
+    }
+
+    fn closed(&self) {
+        self.current.fetch_sub(1, Ordering::Relaxed);
+
+        let waker = self.waiting.lock().expect(POISONED_LOCK).pop();
+
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    fn has_room(&self, limit: usize) -> bool {
+        self.current() < limit
+    }
+
+    /// Waits until fewer than `limit` handles are open, or fails immediately if the policy says
+    /// so.
+    fn reserve(&self, limit: usize, policy: HandleLimitPolicy) -> ReserveHandle<'_> {
+        ReserveHandle {
+            counter: self,
+            limit,
+            policy,
+            paused: false,
+        }
+    }
+}
+
+/// Called whenever Folo takes ownership of a handle.
+pub(crate) fn handle_opened() {
+    HANDLES.opened();
+}
+
+/// Called whenever Folo closes a handle it owns or gives up its ownership.
+pub(crate) fn handle_closed() {
+    HANDLES.closed();
+}
+
+/// Sets the soft limit on open handles for the runtime that owns the current thread.
+pub(crate) fn set_handle_limit(limit: Option<usize>, policy: HandleLimitPolicy) {
+    HANDLE_LIMIT.set(limit.map(|limit| (limit, policy)));
+}
+
+/// Waits until there is room under the soft limit of the current runtime for one more handle, if
+/// a limit is set. Call this before starting an operation that opens a handle.
+///
+/// This does not reserve the handle - operations that start at the same time may all proceed,
+/// opening a few handles over the limit. This is fine because the limit exists to keep well clear
+/// of the hard operating system limit, not to enforce an exact count.
+pub(crate) async fn reserve_handle() -> io::Result<()> {
+    let Some((limit, policy)) = HANDLE_LIMIT.get() else {
+        return Ok(());
+    };
+
+    HANDLES.reserve(limit, policy).await
+}
+
+/// Waits for room for one more handle under a soft limit.
+#[derive(Debug)]
+struct ReserveHandle<'a> {
+    counter: &'a HandleCounter,
+    limit: usize,
+    policy: HandleLimitPolicy,
+
+    // Whether we have already reported that the operation was paused, to count each pause once.
+    paused: bool,
+}
+
+impl Future for ReserveHandle<'_> {
+    type Output = io::Result<()>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if self.counter.has_room(self.limit) {
+            return Poll::Ready(Ok(()));
+        }
+
+        if self.policy == HandleLimitPolicy::Fail {
+            observe!(HANDLE_LIMIT_REJECTIONS.observe_unit());
+            return Poll::Ready(Err(io::Error::HandleLimitReached(self.limit)));
+        }
+
+        self.counter
+            .waiting
+            .lock()
+            .expect(POISONED_LOCK)
+            .push(cx.waker().clone());
+
+        // A handle may have been closed between our check and the registration of the waker, in
+        // which case nobody would wake us up, so we need to check again.
+        if self.counter.has_room(self.limit) {
+            return Poll::Ready(Ok(()));
+        }
+
+        if !self.paused {
+            self.paused = true;
+            observe!(HANDLE_LIMIT_WAITS.observe_unit());
+        }
+
+        Poll::Pending
+    }
+}
+
+static HANDLES: HandleCounter = HandleCounter::new();
+
+thread_local! {
+    static HANDLE_LIMIT: Cell<Option<(usize, HandleLimitPolicy)>> = const { Cell::new(None) };
+
+    static OPEN_HANDLES: Event = EventBuilder::new("io_open_handles")
+        .buckets(OPEN_HANDLES_BUCKETS)
+        .build();
+
+    static HANDLE_LIMIT_WAITS: Event = EventBuilder::new("io_handle_limit_waits")
+        .build();
+
+    static HANDLE_LIMIT_REJECTIONS: Event = EventBuilder::new("io_handle_limit_rejections")
+        .build();
+}
+
+const OPEN_HANDLES_BUCKETS: &[Magnitude] = &[0, 10, 100, 1000, 10000, 100000];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{executor::block_on, task::noop_waker_ref};
+    use std::pin::pin;
+
+    #[test]
+    fn counts_current_and_peak() {
+        let counter = HandleCounter::new();
+
+        counter.opened();
+        counter.opened();
+        assert_eq!(counter.current(), 2);
+        assert_eq!(counter.peak(), 2);
+
+        counter.closed();
+        assert_eq!(counter.current(), 1);
+        assert_eq!(counter.peak(), 2);
+    }
+
+    #[test]
+    fn waits_for_room() {
+        let counter = HandleCounter::new();
+        counter.opened();
+
+        let mut cx = Context::from_waker(noop_waker_ref());
+        let mut reserve = pin!(counter.reserve(1, HandleLimitPolicy::Wait));
+        assert!(reserve.as_mut().poll(&mut cx).is_pending());
+
+        counter.closed();
+        assert!(matches!(
+            reserve.as_mut().poll(&mut cx),
+            Poll::Ready(Ok(()))
+        ));
+    }
+
+    #[test]
+    fn fails_at_limit() {
+        let counter = HandleCounter::new();
+        counter.opened();
+
+        let result = block_on(counter.reserve(1, HandleLimitPolicy::Fail));
+        assert!(matches!(result, Err(io::Error::HandleLimitReached(1))));
+
+        counter.closed();
+        assert!(block_on(counter.reserve(1, HandleLimitPolicy::Fail)).is_ok());
+    }
+}
@@ -1,26 +1,42 @@
+#[cfg(feature = "fakes")]
+use crate::io::fault_injection;
+#[cfg(feature = "op-tracing")]
+use crate::io::{OperationId, OperationTrace};
 use crate::{
     constants::{GENERAL_BYTES_BUCKETS, GENERAL_MILLISECONDS_BUCKETS},
-    io::{self, Buffer, OperationResult},
+    io::{self, Buffer, IoPrimitive, IoQuota, IoWaker, OperationResult},
     mem::{isolation::Isolated, DropPolicy, PinnedSlabChain},
-    metrics::{Event, EventBuilder, Magnitude},
-    time::UltraLowPrecisionInstant,
+    metrics::{observe, Event, EventBuilder, Magnitude},
+    rt::{
+        self, coop,
+        diagnostics::{self, Diagnostic},
+        flight_recorder::{self, FlightEvent},
+        task_trace, SynchronousTaskType,
+    },
+    time::{Clock, Delay, UltraLowPrecisionInstant},
 };
 use negative_impl::negative_impl;
-use pin_project::pin_project;
+use pin_project::{pin_project, pinned_drop};
 use std::{
     cell::{RefCell, UnsafeCell},
     fmt,
     future::Future,
+    iter,
     mem::{self, ManuallyDrop},
     pin::Pin,
     ptr,
-    task::Poll,
+    rc::Rc,
+    task::{ready, Poll},
+    time::Duration,
 };
 use tracing::{event, Level};
 use windows::Win32::{
-    Foundation::{ERROR_IO_PENDING, NTSTATUS, STATUS_SUCCESS},
+    Foundation::{
+        ERROR_INVALID_HANDLE, ERROR_IO_PENDING, ERROR_NOT_FOUND, HANDLE, NTSTATUS,
+        STATUS_CANCELLED, STATUS_SUCCESS,
+    },
     Networking::WinSock::{SOCKET_ERROR, WSA_IO_PENDING},
-    System::IO::{OVERLAPPED, OVERLAPPED_ENTRY},
+    System::IO::{CancelIoEx, GetOverlappedResult, OVERLAPPED, OVERLAPPED_ENTRY},
 };
 
 /// Maintains the backing storage for the metadata structures of I/O operations submitted to the
@@ -64,12 +80,17 @@ impl OperationStore {
         self.items.borrow().is_empty()
     }
 
+    /// The number of operations that have been created and not yet completed.
+    pub fn len(&self) -> usize {
+        self.items.borrow().len()
+    }
+
     /// Creates a new operation for performing I/O. You need to wrap each native I/O API call you
     /// make into a new one of these operations. The caller provides a buffer for any input/output
     /// data, which the operation takes ownership of. Once the operation has completed, the buffer
     /// is returned to the caller for reading, reuse or disposal.
     pub fn new_operation(&self, buffer: Buffer<Isolated>) -> Operation {
-        OPERATIONS_ALLOCATED.with(Event::observe_unit);
+        observe!(OPERATIONS_ALLOCATED.observe_unit());
 
         let mut items = self.items.borrow_mut();
 
@@ -110,8 +131,8 @@ impl OperationStore {
         let bytes_transferred = overlapped_entry.dwNumberOfBytesTransferred as usize;
         let status = NTSTATUS(overlapped_entry.Internal as i32);
 
-        OPERATIONS_COMPLETED_ASYNC.with(Event::observe_unit);
-        OPERATION_COMPLETED_BYTES.with(|x| x.observe(bytes_transferred as Magnitude));
+        observe!(OPERATIONS_COMPLETED_ASYNC.observe_unit());
+        observe!(OPERATION_COMPLETED_BYTES.observe(bytes_transferred as Magnitude));
 
         // SAFETY: The core is only referenced by either Operation or the operating system at any
         // given time, so there is no possibility of multiple exclusive references being created.
@@ -124,7 +145,13 @@ impl OperationStore {
             .take()
             .expect("buffer must exist because we only remove it after completion");
 
-        buffer.set_len(bytes_transferred);
+        core.set_bytes_transferred(&mut buffer, bytes_transferred);
+
+        // Operations performed on synchronous worker threads report their errors via the core.
+        let error = core
+            .fallback_error
+            .take()
+            .or_else(|| (status != STATUS_SUCCESS).then(|| io::Error::Windows(status.into())));
 
         let duration = UltraLowPrecisionInstant::now().duration_since(
             core.started
@@ -132,22 +159,52 @@ impl OperationStore {
                 .expect("must have an operation start time because the operation is completed"),
         );
 
-        OPERATION_COMPLETED_ASYNC_OK_DURATION.with(|x| x.observe_millis(duration));
+        observe!(OPERATION_COMPLETED_ASYNC_OK_DURATION.observe_millis(duration));
+
+        flight_recorder::record(FlightEvent::IoCompleted {
+            operation: core.key,
+            bytes_transferred,
+            duration,
+            succeeded: error.is_none(),
+        });
+
+        #[cfg(feature = "runtime-tracing")]
+        trace_finished(
+            mem::replace(&mut core.span, tracing::Span::none()),
+            bytes_transferred,
+            duration,
+            error.as_ref().map(ToString::to_string),
+        );
+
+        if status == STATUS_CANCELLED {
+            observe!(OPERATIONS_CANCELED.observe_unit());
+        }
 
         let result_tx = core
             .result_tx
             .take()
             .expect("result tx must exist because we have not yet sent the result");
 
+        #[cfg(feature = "op-tracing")]
+        core.trace.completed();
+
         // The operation may not have been successful, so we need to investigate the status.
-        // We ignore the tx return value because the receiver may have dropped already.
-        if status != STATUS_SUCCESS {
-            _ = result_tx.send(Err(io::OperationError::new(
-                io::Error::Windows(status.into()),
-                buffer,
-            )));
-        } else {
-            _ = result_tx.send(Ok(buffer));
+        let result = match error {
+            Some(error) => Err(core.error(error, buffer)),
+            None => Ok(buffer),
+        };
+
+        // If the receiver has been dropped, nobody is interested in the result. The buffer is
+        // dropped together with the undelivered result, which returns it to its pool.
+        if result_tx
+            .send(OperationOutcome {
+                result,
+                #[cfg(feature = "op-tracing")]
+                trace: core.trace,
+            })
+            .is_err()
+        {
+            observe!(OPERATIONS_ABANDONED.observe_unit());
         }
 
         // All done!
@@ -184,18 +241,43 @@ impl OperationStore {
             .expect("buffer must exist because we only remove it after completion");
 
         let bytes_transferred = core.immediate_bytes_transferred as usize;
-        assert!(bytes_transferred <= buffer.len());
 
-        OPERATIONS_COMPLETED_SYNC.with(Event::observe_unit);
-        OPERATION_COMPLETED_BYTES.with(|x| x.observe(bytes_transferred as Magnitude));
+        observe!(OPERATIONS_COMPLETED_SYNC.observe_unit());
+        observe!(OPERATION_COMPLETED_BYTES.observe(bytes_transferred as Magnitude));
+
+        core.set_bytes_transferred(&mut buffer, bytes_transferred);
 
-        buffer.set_len(bytes_transferred);
+        #[cfg(feature = "op-tracing")]
+        core.trace.completed();
+
+        let duration = core.started.map_or(Duration::ZERO, |started| {
+            UltraLowPrecisionInstant::now().duration_since(started)
+        });
+
+        flight_recorder::record(FlightEvent::IoCompleted {
+            operation: core.key,
+            bytes_transferred,
+            duration,
+            succeeded: true,
+        });
+
+        #[cfg(feature = "runtime-tracing")]
+        trace_finished(
+            mem::replace(&mut core.span, tracing::Span::none()),
+            bytes_transferred,
+            duration,
+            None,
+        );
 
         _ = core
             .result_tx
             .take()
             .expect("result tx must exist because we have not yet sent the result")
-            .send(Ok(buffer));
+            .send(OperationOutcome {
+                result: Ok(buffer),
+                #[cfg(feature = "op-tracing")]
+                trace: core.trace,
+            });
 
         // All done!
         self.release(core.key);
@@ -219,6 +301,12 @@ impl OperationStore {
 
 type OperationKey = usize;
 
+/// The buffers of a vectored operation that follow the primary buffer given to
+/// `OperationStore::new_operation()`. They are shared between the operation and its originator -
+/// the operation keeps them alive for as long as the operating system may access them, after which
+/// the originator can take them back.
+pub(crate) type AdditionalBuffers = Rc<RefCell<Vec<Buffer<Isolated>>>>;
+
 /// Constrained API surface that allows an operation to command the store that owns it. This creates
 /// a circular reference between an operation and the OperationStore, so we always use
 /// OperationStore via interior mutability to prevent accidents here.
@@ -269,6 +357,26 @@ struct OperationCore {
     /// the buffer to the caller and set this to None.
     buffer: Option<Buffer<Isolated>>,
 
+    /// For vectored operations, the buffers that follow `buffer`. The bytes transferred are
+    /// distributed over `buffer` and these, in order.
+    additional_buffers: Option<AdditionalBuffers>,
+
+    /// Whether the operation transfers data via the buffers. If not, the bytes transferred do not
+    /// affect the buffers and the buffer is returned to the originator unchanged.
+    uses_buffers: bool,
+
+    /// The I/O primitive the operation is performed on, if known. Used to cancel the operation if
+    /// the originator loses interest in the result before the operation completes.
+    primitive: Option<HANDLE>,
+
+    /// The name of the operation, known once it has been started. Identifies the operation in the
+    /// error returned if it fails.
+    operation: Option<&'static str>,
+
+    /// For operations performed on a synchronous worker thread (see `call_native()`), the error
+    /// from the native I/O function, as it cannot be delivered via the completion port.
+    fallback_error: Option<io::Error>,
+
     /// Used to operate the control node, which requires us to know our own key.
     key: OperationKey,
 
@@ -279,12 +387,21 @@ struct OperationCore {
 
     /// This is where the I/O completion handler will deliver the result of the operation.
     /// Value is cleared when consumed, to make it obvious if any accidental reuse occurs.
-    result_tx: Option<oneshot::Sender<io::OperationResult>>,
-    result_rx: Option<oneshot::Receiver<io::OperationResult>>,
+    result_tx: Option<oneshot::Sender<OperationOutcome>>,
+    result_rx: Option<oneshot::Receiver<OperationOutcome>>,
 
     /// Timestamp of when the operation is started. Used to report I/O operation durations.
     started: Option<UltraLowPrecisionInstant>,
 
+    /// Identity and lifecycle timestamps of the operation, for diagnostic purposes.
+    #[cfg(feature = "op-tracing")]
+    trace: OperationTrace,
+
+    /// Covers the operation from when it is started until it completes. Its parent is the span of
+    /// the task that started the operation.
+    #[cfg(feature = "runtime-tracing")]
+    span: tracing::Span,
+
     // Once pinned, this type cannot be unpinned.
     _phantom_pin: std::marker::PhantomPinned,
 }
@@ -301,20 +418,73 @@ impl OperationCore {
         Self {
             overlapped: OVERLAPPED::default(),
             buffer: Some(buffer),
+            additional_buffers: None,
+            uses_buffers: true,
+            primitive: None,
+            operation: None,
+            fallback_error: None,
             key,
             immediate_bytes_transferred: 0,
             result_tx: Some(result_tx),
             result_rx: Some(result_rx),
             started: None,
+            #[cfg(feature = "op-tracing")]
+            trace: OperationTrace::new(),
+            #[cfg(feature = "runtime-tracing")]
+            span: tracing::Span::none(),
             _phantom_pin: std::marker::PhantomPinned,
         }
     }
+
+    /// Sets the active region of the buffers to the bytes transferred by the operation. For
+    /// vectored operations, the buffers are filled in order, so trailing buffers may end up empty.
+    fn set_bytes_transferred(&self, buffer: &mut Buffer<Isolated>, bytes_transferred: usize) {
+        if !self.uses_buffers {
+            return;
+        }
+
+        let Some(additional_buffers) = &self.additional_buffers else {
+            assert!(bytes_transferred <= buffer.len());
+
+            buffer.set_len(bytes_transferred);
+            return;
+        };
+
+        let mut remaining = bytes_transferred;
+
+        for buffer in iter::once(buffer).chain(additional_buffers.borrow_mut().iter_mut()) {
+            let len = remaining.min(buffer.len());
+            buffer.set_len(len);
+            remaining -= len;
+        }
+
+        assert_eq!(remaining, 0);
+    }
+
+    /// Creates the error reported to the originator if the operation fails, identifying the
+    /// operation and the I/O primitive it was performed on.
+    fn error(&self, inner: io::Error, buffer: Buffer<Isolated>) -> io::OperationError {
+        let error = io::OperationError::new(inner, buffer);
+
+        match self.operation {
+            Some(operation) => error.with_operation(
+                operation,
+                self.primitive.map(|primitive| primitive.0 as usize),
+            ),
+            None => error,
+        }
+    }
 }
 
 impl fmt::Debug for OperationCore {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("OperationCore")
-            .field("buffer", &self.buffer)
+        let mut s = f.debug_struct("OperationCore");
+
+        s.field("buffer", &self.buffer)
+            .field("additional_buffers", &self.additional_buffers)
+            .field("uses_buffers", &self.uses_buffers)
+            .field("primitive", &self.primitive)
+            .field("operation", &self.operation)
             .field("key", &self.key)
             .field(
                 "immediate_bytes_transferred",
@@ -322,11 +492,24 @@ impl fmt::Debug for OperationCore {
             )
             .field("result_tx", &self.result_tx)
             .field("result_rx", &self.result_rx)
-            .field("started", &self.started)
-            .finish()
+            .field("started", &self.started);
+
+        #[cfg(feature = "op-tracing")]
+        s.field("trace", &self.trace);
+
+        s.finish()
     }
 }
 
+/// The message sent from the I/O driver to the `OperationResultFuture` when an operation completes.
+#[derive(Debug)]
+struct OperationOutcome {
+    result: io::OperationResult,
+
+    #[cfg(feature = "op-tracing")]
+    trace: OperationTrace,
+}
+
 // We need to to avoid accidents. All our I/O operations need to stay on the same thread when they
 // are in the Rust universe. The OS can do what it wants when it holds ownership but for us they
 // are single-threaded.
@@ -348,6 +531,12 @@ pub(crate) struct Operation {
 }
 
 impl Operation {
+    /// The unique identifier of the operation, used to correlate diagnostic output.
+    #[cfg(feature = "op-tracing")]
+    pub fn id(&self) -> OperationId {
+        self.core.trace.id()
+    }
+
     /// For seekable I/O primitives (e.g. files), sets the offset in the file where the operation
     /// should be performed.
     pub fn set_offset(&mut self, offset: usize) {
@@ -355,6 +544,33 @@ impl Operation {
         self.core.overlapped.Anonymous.Anonymous.OffsetHigh = (offset >> 32) as u32;
     }
 
+    /// Turns the operation into a vectored operation, which transfers data to/from the specified
+    /// buffers after the primary buffer. The operation keeps the buffers alive until it has
+    /// completed, after which the caller can take them back from the shared collection.
+    ///
+    /// The callback given to `begin()` only receives the primary buffer - the caller is responsible
+    /// for passing the additional buffers to the native API, which it may do by obtaining pointers
+    /// to their contents before calling this (the contents of a buffer are pinned and never move).
+    pub fn set_additional_buffers(&mut self, buffers: AdditionalBuffers) {
+        self.core.additional_buffers = Some(buffers);
+    }
+
+    /// Marks the operation as one that transfers data without using the operation buffer (e.g.
+    /// directly from a file to a socket). The buffer is returned to the caller unchanged, no
+    /// matter how many bytes the operation transfers.
+    pub fn set_buffer_unused(&mut self) {
+        self.core.uses_buffers = false;
+    }
+
+    /// Sets the I/O primitive the operation is performed on. This allows the operation to be
+    /// canceled if the future returned by `begin()` is dropped before the operation completes.
+    ///
+    /// Without this, an abandoned operation runs until the operating system completes it on its
+    /// own, which for some operations (e.g. a read from an idle socket) may never happen.
+    pub fn set_primitive(&mut self, primitive: impl Into<IoPrimitive>) {
+        self.core.primitive = Some(primitive.into().into());
+    }
+
     /// Executes an I/O operation, using the specified callback to pass the operation buffer and
     /// OVERLAPPED metadata structure to native OS functions.
     ///
@@ -394,12 +610,50 @@ impl Operation {
             .take()
             .expect("operation is always expected to have result rx when beginning I/O");
 
+        self.core.operation = Some(operation_name::<F>());
+
+        #[cfg(feature = "runtime-tracing")]
+        {
+            self.core.span = operation_span::<F>();
+        }
+
         // We clone the control node because we may need to release the operation core if the
         // callback fails or even resurrect it immediately if the callback completes synchronously.
         let mut control_node = self.control.clone();
+        let primitive = self.core.primitive;
+        let key = self.core.key;
+
+        let fallback = primitive.and_then(|primitive| {
+            io::blocking_fallback_waker(primitive).map(|waker| (primitive, waker))
+        });
+
+        flight_recorder::record(FlightEvent::IoSubmitted {
+            operation: key,
+            kind: std::any::type_name::<F>(),
+        });
+
+        #[cfg(feature = "fakes")]
+        let faults = fault_injection::next(std::any::type_name::<F>());
+
         let (buffer, overlapped, immediate_bytes_transferred) = self.into_callback_arguments();
 
-        match f(buffer, overlapped, immediate_bytes_transferred) {
+        #[cfg(feature = "fakes")]
+        let result = match faults.error() {
+            // The native API is never called, so this fails like a native call that was rejected.
+            Some(e) => Err(e),
+            None => call_native(
+                f,
+                fallback,
+                faults.limit_buffer(buffer),
+                overlapped,
+                immediate_bytes_transferred,
+            ),
+        };
+
+        #[cfg(not(feature = "fakes"))]
+        let result = call_native(f, fallback, buffer, overlapped, immediate_bytes_transferred);
+
+        match result {
             // The operation was started asynchronously. This is what we want to see.
             Err(io::Error::Windows(e)) if e.code() == ERROR_IO_PENDING.into() => {}
             Err(io::Error::Winsock { code, detail })
@@ -430,11 +684,31 @@ impl Operation {
                     "buffer must exist because we only remove it after completion or failure and right now we are doing the latter",
                 );
 
+                flight_recorder::record(FlightEvent::IoCompleted {
+                    operation: key,
+                    bytes_transferred: 0,
+                    duration: Duration::ZERO,
+                    succeeded: false,
+                });
+
+                #[cfg(feature = "runtime-tracing")]
+                trace_finished(
+                    mem::replace(&mut (*core).span, tracing::Span::none()),
+                    0,
+                    Duration::ZERO,
+                    Some(e.to_string()),
+                );
+
                 control_node.release((*core).key);
 
                 return OperationResultFuture {
                     receiver: result_rx,
-                    error: Some(io::OperationError::new(e, buffer)),
+                    error: Some((*core).error(e, buffer)),
+                    cancel_target: None,
+                    quota: None,
+                    throttled: None,
+                    #[cfg(feature = "fakes")]
+                    latency: None,
                 };
             }
         }
@@ -442,6 +716,16 @@ impl Operation {
         OperationResultFuture {
             receiver: result_rx,
             error: None,
+            cancel_target: primitive.map(|primitive| CancelTarget {
+                primitive,
+                overlapped,
+            }),
+            quota: io::current_io_quota(),
+            throttled: None,
+            #[cfg(feature = "fakes")]
+            latency: faults
+                .latency()
+                .map(|latency| Delay::with_clock(&Clock::new(), latency)),
         }
     }
 
@@ -456,6 +740,9 @@ impl Operation {
 
         operation.started = Some(UltraLowPrecisionInstant::now());
 
+        #[cfg(feature = "op-tracing")]
+        operation.trace.submitted();
+
         (
             // SAFETY: Sets the lifetime to 'static because I cannot figure out a straightforward way to declare lifetimes here.
             // As long as the value is only used during the callback, this is fine (caller is responsible for not using it afterwards).
@@ -478,12 +765,182 @@ impl Operation {
     }
 }
 
-#[pin_project]
+/// Calls the native I/O function of an operation via the callback given to `Operation::begin()`.
+///
+/// If the I/O primitive could not be associated with the completion port (see
+/// `fall_back_to_blocking()`), a synchronous worker thread calls the native I/O function instead
+/// and delivers the result to the completion port as if the operating system had completed the
+/// operation asynchronously. To the caller, such an operation looks like it is in progress.
+///
+/// # Safety
+///
+/// The arguments must come from `Operation::into_callback_arguments()`.
+unsafe fn call_native<F>(
+    f: F,
+    fallback: Option<(HANDLE, IoWaker)>,
+    buffer: &'static mut [u8],
+    overlapped: *mut OVERLAPPED,
+    immediate_bytes_transferred: &'static mut u32,
+) -> io::Result<()>
+where
+    F: FnOnce(&'static mut [u8], *mut OVERLAPPED, &mut u32) -> io::Result<()> + 'static,
+{
+    let Some((primitive, waker)) = fallback else {
+        return f(buffer, overlapped, immediate_bytes_transferred);
+    };
+
+    let operation = BlockingOperation {
+        f,
+        primitive,
+        buffer,
+        overlapped,
+        immediate_bytes_transferred,
+        waker,
+    };
+
+    // High priority tasks are executed even during shutdown, which cannot complete before every
+    // operation has completed. We do not need the join handle, the result arrives via the port.
+    _ = rt::spawn_sync(SynchronousTaskType::HighPrioritySyscall, move || {
+        operation.execute()
+    });
+
+    observe!(OPERATIONS_BLOCKING_FALLBACK.observe_unit());
+
+    Err(io::Error::Windows(windows_result::Error::from_hresult(
+        ERROR_IO_PENDING.to_hresult(),
+    )))
+}
+
+/// An I/O operation performed on a synchronous worker thread because its I/O primitive could not be
+/// associated with the completion port. See `call_native()`.
+struct BlockingOperation<F> {
+    f: F,
+    primitive: HANDLE,
+    buffer: &'static mut [u8],
+    overlapped: *mut OVERLAPPED,
+    immediate_bytes_transferred: &'static mut u32,
+    waker: IoWaker,
+}
+
+// SAFETY: The originating thread does not touch the buffer or the OVERLAPPED structure until the
+// operation has completed, same as if the operating system were performing the operation. The
+// callback only calls the native I/O function on the I/O primitive, which any thread may do.
+unsafe impl<F> Send for BlockingOperation<F> {}
+
+impl<F> BlockingOperation<F>
+where
+    F: FnOnce(&'static mut [u8], *mut OVERLAPPED, &mut u32) -> io::Result<()>,
+{
+    fn execute(self) {
+        let Self {
+            f,
+            primitive,
+            buffer,
+            overlapped,
+            immediate_bytes_transferred,
+            waker,
+        } = self;
+
+        let result = match f(buffer, overlapped, immediate_bytes_transferred) {
+            Ok(()) => Ok(*immediate_bytes_transferred),
+            // The I/O primitive supports overlapped I/O after all (e.g. it is associated with a
+            // completion port owned by other code), so we wait here for the operation to complete.
+            Err(e) if is_pending(&e) => {
+                let mut bytes_transferred = 0;
+
+                // SAFETY: The OVERLAPPED structure belongs to the operation we just started.
+                unsafe { GetOverlappedResult(primitive, overlapped, &mut bytes_transferred, true) }
+                    .map(|()| bytes_transferred)
+                    .map_err(io::Error::from)
+            }
+            Err(e) => Err(e),
+        };
+
+        let bytes_transferred = match result {
+            Ok(bytes_transferred) => bytes_transferred,
+            Err(e) => {
+                // SAFETY: The operation core is ours until we deliver the completion.
+                unsafe {
+                    (*(overlapped as *mut OperationCore)).fallback_error = Some(e);
+                }
+
+                0
+            }
+        };
+
+        // SAFETY: The OVERLAPPED pointer came from `Operation::begin()` and the operation is done.
+        unsafe {
+            waker.post_completion(overlapped, bytes_transferred);
+        }
+    }
+}
+
+fn is_pending(error: &io::Error) -> bool {
+    match error {
+        io::Error::Windows(e) => e.code() == ERROR_IO_PENDING.into(),
+        io::Error::Winsock { code, detail } => *code == SOCKET_ERROR && *detail == WSA_IO_PENDING,
+        _ => false,
+    }
+}
+
+/// Names the type of an I/O operation after the function that started it, which is where the
+/// callback given to `Operation::begin()` is defined.
+pub(crate) fn operation_name<F>() -> &'static str {
+    std::any::type_name::<F>().trim_end_matches("::{{closure}}")
+}
+
+/// Creates the span of an I/O operation.
+#[cfg(feature = "runtime-tracing")]
+fn operation_span<F>() -> tracing::Span {
+    let operation = operation_name::<F>();
+
+    tracing::trace_span!("io_operation", operation)
+}
+
+/// Records the outcome of an I/O operation in its span, which ends with it.
+#[cfg(feature = "runtime-tracing")]
+fn trace_finished(
+    span: tracing::Span,
+    bytes_transferred: usize,
+    duration: Duration,
+    error: Option<String>,
+) {
+    event!(
+        parent: &span,
+        Level::TRACE,
+        message = "I/O operation finished",
+        bytes_transferred,
+        ?duration,
+        error = error.as_deref()
+    );
+}
+
+#[pin_project(PinnedDrop)]
 #[derive(Debug)]
 pub struct OperationResultFuture {
     #[pin]
-    receiver: oneshot::Receiver<io::OperationResult>,
+    receiver: oneshot::Receiver<OperationOutcome>,
     error: Option<io::OperationError>,
+
+    // If set, we cancel the operation when the future is dropped before receiving the result.
+    cancel_target: Option<CancelTarget>,
+
+    // The quota the operation is charged to once it completes, if any.
+    quota: Option<IoQuota>,
+
+    // The result of an operation that exhausted its quota, returned once the delay has elapsed.
+    throttled: Option<(Delay, OperationResult)>,
+
+    // Injected latency to wait out before returning the result.
+    #[cfg(feature = "fakes")]
+    latency: Option<Delay>,
+}
+
+/// Identifies a started operation for the purpose of canceling it.
+#[derive(Debug)]
+struct CancelTarget {
+    primitive: HANDLE,
+    overlapped: *mut OVERLAPPED,
 }
 
 impl Future for OperationResultFuture {
@@ -496,9 +953,103 @@ impl Future for OperationResultFuture {
             return Poll::Ready(Err(err));
         }
 
-        match this.receiver.poll(cx) {
-            Poll::Ready(v) => Poll::Ready(v.expect("")),
-            Poll::Pending => Poll::Pending,
+        if let Some((delay, _)) = this.throttled {
+            ready!(Pin::new(delay).poll(cx));
+
+            let (_, result) = this.throttled.take().expect("we just polled the delay");
+            return Poll::Ready(result);
+        }
+
+        coop::poll_budgeted(cx, |cx| {
+            #[cfg(feature = "fakes")]
+            if let Some(latency) = this.latency {
+                ready!(Pin::new(latency).poll(cx));
+                *this.latency = None;
+            }
+
+            match this.receiver.poll(cx) {
+                Poll::Ready(v) => {
+                    let outcome = v.expect("");
+
+                    // The operation has completed, so there is nothing left to cancel.
+                    *this.cancel_target = None;
+
+                    #[cfg(feature = "op-tracing")]
+                    outcome.trace.dispatched();
+
+                    let Some(quota) = this.quota.take() else {
+                        return Poll::Ready(outcome.result);
+                    };
+
+                    let bytes_transferred = match &outcome.result {
+                        Ok(buffer) => buffer.len(),
+                        Err(_) => 0,
+                    };
+
+                    let throttle = quota.charge(bytes_transferred);
+
+                    if throttle.is_zero() {
+                        return Poll::Ready(outcome.result);
+                    }
+
+                    // The quota is exhausted, so we hold on to the result until it has recovered.
+                    let mut delay = Delay::with_clock(&Clock::new(), throttle);
+
+                    if Pin::new(&mut delay).poll(cx).is_ready() {
+                        return Poll::Ready(outcome.result);
+                    }
+
+                    task_trace::awaiting("I/O quota");
+                    *this.throttled = Some((delay, outcome.result));
+                    Poll::Pending
+                }
+                Poll::Pending => {
+                    task_trace::awaiting("I/O operation");
+                    Poll::Pending
+                }
+            }
+        })
+    }
+}
+
+#[pinned_drop]
+impl PinnedDrop for OperationResultFuture {
+    fn drop(self: Pin<&mut Self>) {
+        let this = self.project();
+
+        let Some(target) = this.cancel_target.take() else {
+            return;
+        };
+
+        // Once the result has been sent, the operation core has been released and may already be
+        // reused by a different operation, so we must not touch it. Completions are processed on
+        // the current thread, so this cannot change between the check and the cancellation.
+        if this.receiver.has_message() || this.receiver.is_closed() {
+            return;
+        }
+
+        // The operation core stays in the store until the completion notification arrives, at
+        // which point the buffer is dropped together with the undelivered result and returns to
+        // its pool. Until then, no other operation can use the same OVERLAPPED pointer.
+        //
+        // SAFETY: Canceling only affects the operation identified by the OVERLAPPED pointer, which
+        // is still owned by the operating system. If the primitive has already been closed, its
+        // operations have been canceled already and the call merely fails.
+        match unsafe { CancelIoEx(target.primitive, Some(target.overlapped)) } {
+            Ok(()) => {
+                observe!(OPERATION_CANCELS_REQUESTED.observe_unit());
+            }
+            // The operation completed before we could cancel it, or the primitive was closed
+            // (which cancels all its operations). Either way, the completion is on its way.
+            Err(e)
+                if e.code() == ERROR_NOT_FOUND.into()
+                    || e.code() == ERROR_INVALID_HANDLE.into() =>
+            {
+                observe!(OPERATION_CANCELS_TOO_LATE.observe_unit());
+            }
+            Err(e) => {
+                diagnostics::emit(Diagnostic::CancellationFailed { error: &e });
+            }
         }
     }
 }
@@ -519,6 +1070,21 @@ thread_local! {
     static OPERATIONS_COMPLETED_SYNC: Event = EventBuilder::new("io_ops_completed_sync")
         .build();
 
+    static OPERATION_CANCELS_REQUESTED: Event = EventBuilder::new("io_op_cancels_requested")
+        .build();
+
+    static OPERATION_CANCELS_TOO_LATE: Event = EventBuilder::new("io_op_cancels_too_late")
+        .build();
+
+    static OPERATIONS_CANCELED: Event = EventBuilder::new("io_ops_canceled")
+        .build();
+
+    static OPERATIONS_ABANDONED: Event = EventBuilder::new("io_ops_abandoned")
+        .build();
+
+    static OPERATIONS_BLOCKING_FALLBACK: Event = EventBuilder::new("io_ops_blocking_fallback")
+        .build();
+
     static OPERATION_COMPLETED_BYTES: Event = EventBuilder::new("io_completed_bytes")
         .buckets(GENERAL_BYTES_BUCKETS)
         .build();
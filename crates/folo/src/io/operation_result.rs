@@ -5,17 +5,45 @@ use thiserror::Error;
 /// An error for an I/O operation that was attempted on a data buffer. Contains not only the error
 /// information but also the data buffer that was used, enabling it to be inspected or reused.
 ///
-/// If you do not care about the buffer, simply call `into_inner()` to extract the inner error.
+/// If you do not care about the buffer, simply call `into_inner()` to extract the inner error or
+/// convert the error into an [`io::Error`][crate::io::Error], which (unlike the inner error) also
+/// identifies the operation that failed.
 #[derive(Debug, Error)]
 #[error("I/O operation failed: {inner}")]
 pub struct OperationError {
     pub inner: crate::io::Error,
     pub buffer: Buffer<Isolated>,
+
+    // Describes the operation that failed, for diagnostic purposes. Not known for errors created
+    // outside the I/O driver.
+    operation: Option<&'static str>,
+    handle: Option<usize>,
 }
 
 impl OperationError {
     pub fn new(inner: crate::io::Error, buffer: Buffer<Isolated>) -> Self {
-        Self { inner, buffer }
+        Self {
+            inner,
+            buffer,
+            operation: None,
+            handle: None,
+        }
+    }
+
+    pub(crate) fn with_operation(mut self, operation: &'static str, handle: Option<usize>) -> Self {
+        self.operation = Some(operation);
+        self.handle = handle;
+        self
+    }
+
+    /// The name of the operation that failed, if known.
+    pub fn operation(&self) -> Option<&'static str> {
+        self.operation
+    }
+
+    /// The raw value of the handle the operation was performed on, if known.
+    pub fn handle(&self) -> Option<usize> {
+        self.handle
     }
 
     pub fn into_inner(self) -> crate::io::Error {
@@ -5,7 +5,9 @@ use thiserror::Error;
 /// An error for an I/O operation that was attempted on a data buffer. Contains not only the error
 /// information but also the data buffer that was used, enabling it to be inspected or reused.
 ///
-/// If you do not care about the buffer, simply call `into_inner()` to extract the inner error.
+/// If you do not care about the buffer, simply call `into_inner()` to extract the inner error or
+/// convert the error into an [`io::Error`][crate::io::Error], which (unlike the inner error) also
+/// identifies the operation that failed.
 ///
 /// This is used for multithreaded I/O operations where execution of the operation is shared
 /// across any number of threads.
@@ -14,11 +16,29 @@ use thiserror::Error;
 pub struct OperationErrorShared {
     pub inner: crate::io::Error,
     pub buffer: Buffer<Shared>,
+
+    // Describes the operation that failed, for diagnostic purposes. Not known for errors created
+    // outside the I/O driver.
+    operation: Option<&'static str>,
 }
 
 impl OperationErrorShared {
     pub fn new(inner: crate::io::Error, buffer: Buffer<Shared>) -> Self {
-        Self { inner, buffer }
+        Self {
+            inner,
+            buffer,
+            operation: None,
+        }
+    }
+
+    pub(crate) fn with_operation(mut self, operation: &'static str) -> Self {
+        self.operation = Some(operation);
+        self
+    }
+
+    /// The name of the operation that failed, if known.
+    pub fn operation(&self) -> Option<&'static str> {
+        self.operation
     }
 
     pub fn into_inner(self) -> crate::io::Error {
@@ -1,8 +1,11 @@
+#[cfg(feature = "op-tracing")]
+use crate::io::{OperationId, OperationTrace};
 use crate::{
     constants::{self, GENERAL_BYTES_BUCKETS, GENERAL_MILLISECONDS_BUCKETS},
     io::{self, Buffer, OperationResultShared},
     mem::{isolation::Shared, DropPolicy, PinnedSlabChain},
-    metrics::{Event, EventBuilder, Magnitude},
+    metrics::{observe, Event, EventBuilder, Magnitude},
+    rt::coop,
     time::UltraLowPrecisionInstant,
 };
 use pin_project::pin_project;
@@ -75,7 +78,7 @@ impl OperationStoreShared {
     /// data, which the operation takes ownership of. Once the operation has completed, the buffer
     /// is returned to the caller for reading, reuse or disposal.
     pub fn new_operation(&self, buffer: Buffer<Shared>) -> OperationShared {
-        OPERATIONS_ALLOCATED.with(Event::observe_unit);
+        observe!(OPERATIONS_ALLOCATED.observe_unit());
 
         let items_guard = self.items.lock().expect(constants::POISONED_LOCK);
         let mut items = (*items_guard).borrow_mut();
@@ -111,8 +114,8 @@ impl OperationStoreShared {
         let bytes_transferred = overlapped_entry.dwNumberOfBytesTransferred as usize;
         let status = NTSTATUS(overlapped_entry.Internal as i32);
 
-        OPERATIONS_COMPLETED_ASYNC.with(Event::observe_unit);
-        OPERATION_COMPLETED_BYTES.with(|x| x.observe(bytes_transferred as Magnitude));
+        observe!(OPERATIONS_COMPLETED_ASYNC.observe_unit());
+        observe!(OPERATION_COMPLETED_BYTES.observe(bytes_transferred as Magnitude));
 
         // SAFETY: The core is only referenced by either Operation or the operating system at any
         // given time, so there is no possibility of multiple exclusive references being created.
@@ -133,23 +136,29 @@ impl OperationStoreShared {
                 .expect("must have an operation start time because the operation is completed"),
         );
 
-        OPERATION_COMPLETED_ASYNC_OK_DURATION.with(|x| x.observe_millis(duration));
+        observe!(OPERATION_COMPLETED_ASYNC_OK_DURATION.observe_millis(duration));
 
         let result_tx = core
             .result_tx
             .take()
             .expect("result tx must exist because we have not yet sent the result");
 
+        #[cfg(feature = "op-tracing")]
+        core.trace.completed();
+
         // The operation may not have been successful, so we need to investigate the status.
-        // We ignore the tx return value because the receiver may have dropped already.
-        if status != STATUS_SUCCESS {
-            _ = result_tx.send(Err(io::OperationErrorShared::new(
-                io::Error::Windows(status.into()),
-                buffer,
-            )));
+        let result = if status != STATUS_SUCCESS {
+            Err(core.error(io::Error::Windows(status.into()), buffer))
         } else {
-            _ = result_tx.send(Ok(buffer));
-        }
+            Ok(buffer)
+        };
+
+        // We ignore the tx return value because the receiver may have dropped already.
+        _ = result_tx.send(OperationOutcome {
+            result,
+            #[cfg(feature = "op-tracing")]
+            trace: core.trace,
+        });
 
         // All done!
         self.release(core.key);
@@ -185,16 +194,23 @@ impl OperationStoreShared {
         let bytes_transferred = core.immediate_bytes_transferred as usize;
         assert!(bytes_transferred <= buffer.len());
 
-        OPERATIONS_COMPLETED_SYNC.with(Event::observe_unit);
-        OPERATION_COMPLETED_BYTES.with(|x| x.observe(bytes_transferred as Magnitude));
+        observe!(OPERATIONS_COMPLETED_SYNC.observe_unit());
+        observe!(OPERATION_COMPLETED_BYTES.observe(bytes_transferred as Magnitude));
 
         buffer.set_len(bytes_transferred);
 
+        #[cfg(feature = "op-tracing")]
+        core.trace.completed();
+
         _ = core
             .result_tx
             .take()
             .expect("result tx must exist because we have not yet sent the result")
-            .send(Ok(buffer));
+            .send(OperationOutcome {
+                result: Ok(buffer),
+                #[cfg(feature = "op-tracing")]
+                trace: core.trace,
+            });
 
         // All done!
         self.release(core.key);
@@ -263,6 +279,10 @@ struct OperationCore {
     /// the buffer to the caller and set this to None.
     buffer: Option<Buffer<Shared>>,
 
+    /// The name of the operation, known once it has been started. Identifies the operation in the
+    /// error returned if it fails.
+    operation: Option<&'static str>,
+
     /// Used to operate the control node, which requires us to know our own key.
     key: OperationKey,
 
@@ -273,12 +293,16 @@ struct OperationCore {
 
     /// This is where the I/O completion handler will deliver the result of the operation.
     /// Value is cleared when consumed, to make it obvious if any accidental reuse occurs.
-    result_tx: Option<oneshot::Sender<io::OperationResultShared>>,
-    result_rx: Option<oneshot::Receiver<io::OperationResultShared>>,
+    result_tx: Option<oneshot::Sender<OperationOutcome>>,
+    result_rx: Option<oneshot::Receiver<OperationOutcome>>,
 
     /// Timestamp of when the operation is started. Used to report I/O operation durations.
     started: Option<UltraLowPrecisionInstant>,
 
+    /// Identity and lifecycle timestamps of the operation, for diagnostic purposes.
+    #[cfg(feature = "op-tracing")]
+    trace: OperationTrace,
+
     // Once pinned, this type cannot be unpinned.
     _phantom_pin: std::marker::PhantomPinned,
 }
@@ -295,14 +319,28 @@ impl OperationCore {
         Self {
             overlapped: OVERLAPPED::default(),
             buffer: Some(buffer),
+            operation: None,
             key,
             immediate_bytes_transferred: 0,
             result_tx: Some(result_tx),
             result_rx: Some(result_rx),
             started: None,
+            #[cfg(feature = "op-tracing")]
+            trace: OperationTrace::new(),
             _phantom_pin: std::marker::PhantomPinned,
         }
     }
+
+    /// Creates the error reported to the originator if the operation fails, identifying the
+    /// operation.
+    fn error(&self, inner: io::Error, buffer: Buffer<Shared>) -> io::OperationErrorShared {
+        let error = io::OperationErrorShared::new(inner, buffer);
+
+        match self.operation {
+            Some(operation) => error.with_operation(operation),
+            None => error,
+        }
+    }
 }
 
 // SAFETY: It's OK, I promise (and hope).
@@ -311,8 +349,10 @@ unsafe impl Sync for OperationCore {}
 
 impl fmt::Debug for OperationCore {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("OperationCore")
-            .field("buffer", &self.buffer)
+        let mut s = f.debug_struct("OperationCore");
+
+        s.field("buffer", &self.buffer)
+            .field("operation", &self.operation)
             .field("key", &self.key)
             .field(
                 "immediate_bytes_transferred",
@@ -320,11 +360,25 @@ impl fmt::Debug for OperationCore {
             )
             .field("result_tx", &self.result_tx)
             .field("result_rx", &self.result_rx)
-            .field("started", &self.started)
-            .finish()
+            .field("started", &self.started);
+
+        #[cfg(feature = "op-tracing")]
+        s.field("trace", &self.trace);
+
+        s.finish()
     }
 }
 
+/// The message sent from the I/O driver to the `OperationResultSharedFuture` when an operation
+/// completes.
+#[derive(Debug)]
+struct OperationOutcome {
+    result: io::OperationResultShared,
+
+    #[cfg(feature = "op-tracing")]
+    trace: OperationTrace,
+}
+
 #[derive(Debug)]
 pub(crate) struct OperationShared {
     // We erase the lifetime because the lifetime of this extends outside the Rust universe and
@@ -335,6 +389,12 @@ pub(crate) struct OperationShared {
 }
 
 impl OperationShared {
+    /// The unique identifier of the operation, used to correlate diagnostic output.
+    #[cfg(feature = "op-tracing")]
+    pub fn id(&self) -> OperationId {
+        self.core.trace.id()
+    }
+
     /// For seekable I/O primitives (e.g. files), sets the offset in the file where the operation
     /// should be performed.
     pub fn set_offset(&mut self, offset: usize) {
@@ -384,6 +444,8 @@ impl OperationShared {
             .take()
             .expect("operation is always expected to have result rx when beginning I/O");
 
+        self.core.operation = Some(io::operation_name::<F>());
+
         // We clone the control node because we may need to release the operation core if the
         // callback fails or even resurrect it immediately if the callback completes synchronously.
         let mut control_node = self.control.clone();
@@ -424,7 +486,7 @@ impl OperationShared {
 
                 return OperationResultSharedFuture {
                     receiver: result_rx,
-                    error: Some(io::OperationErrorShared::new(e, buffer)),
+                    error: Some((*core).error(e, buffer)),
                 };
             }
         }
@@ -446,6 +508,9 @@ impl OperationShared {
 
         operation.started = Some(UltraLowPrecisionInstant::now());
 
+        #[cfg(feature = "op-tracing")]
+        operation.trace.submitted();
+
         (
             // SAFETY: Sets the lifetime to 'static because I cannot figure out a straightforward way to declare lifetimes here.
             // As long as the value is only used during the callback, this is fine (caller is responsible for not using it afterwards).
@@ -472,7 +537,7 @@ impl OperationShared {
 #[derive(Debug)]
 pub struct OperationResultSharedFuture {
     #[pin]
-    receiver: oneshot::Receiver<io::OperationResultShared>,
+    receiver: oneshot::Receiver<OperationOutcome>,
     error: Option<io::OperationErrorShared>,
 }
 
@@ -486,10 +551,17 @@ impl Future for OperationResultSharedFuture {
             return Poll::Ready(Err(err));
         }
 
-        match this.receiver.poll(cx) {
-            Poll::Ready(v) => Poll::Ready(v.expect("")),
+        coop::poll_budgeted(cx, |cx| match this.receiver.poll(cx) {
+            Poll::Ready(v) => {
+                let outcome = v.expect("");
+
+                #[cfg(feature = "op-tracing")]
+                outcome.trace.dispatched();
+
+                Poll::Ready(outcome.result)
+            }
             Poll::Pending => Poll::Pending,
-        }
+        })
     }
 }
 
@@ -0,0 +1,118 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    sync::atomic::{AtomicU32, AtomicU64, Ordering},
+    time::Instant,
+};
+use tracing::{event, Level};
+
+/// Identifies an I/O operation for tracing purposes. IDs are unique within the process.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+pub struct OperationId(u64);
+
+impl OperationId {
+    fn next() -> Self {
+        Self(NEXT_OPERATION_ID.fetch_add(1, Ordering::Relaxed))
+    }
+
+    pub fn as_u64(&self) -> u64 {
+        self.0
+    }
+}
+
+impl Display for OperationId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "io-op-{}", self.0)
+    }
+}
+
+/// Sets how often I/O operations are sampled for tracing. A value of N means that every Nth
+/// operation is traced. A value of 0 disables operation tracing.
+///
+/// Every operation gets an ID regardless of sampling but only sampled operations have their
+/// timestamps captured and reported.
+pub fn set_operation_trace_sample_interval(interval: u32) {
+    SAMPLE_INTERVAL.store(interval, Ordering::Relaxed);
+}
+
+/// Gets how often I/O operations are sampled for tracing. See
+/// [`set_operation_trace_sample_interval()`].
+pub fn operation_trace_sample_interval() -> u32 {
+    SAMPLE_INTERVAL.load(Ordering::Relaxed)
+}
+
+/// Tracks the lifecycle timestamps of a single I/O operation:
+///
+/// 1. Submitted - the operation was handed to the operating system.
+/// 2. Completed - the I/O driver received the completion notification from the operating system
+///    (or the operation completed immediately, in which case this equals the submit timestamp).
+/// 3. Dispatched - the result was delivered to the user code awaiting it.
+///
+/// The difference between these is what we are interested in: 1->2 is time spent in the kernel and
+/// 2->3 is time spent in our own queues, waiting for the task awaiting the result to get polled.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct OperationTrace {
+    id: OperationId,
+    sampled: bool,
+
+    submitted: Option<Instant>,
+    completed: Option<Instant>,
+}
+
+impl OperationTrace {
+    pub fn new() -> Self {
+        let id = OperationId::next();
+        let interval = operation_trace_sample_interval() as u64;
+
+        Self {
+            id,
+            sampled: interval != 0 && id.0 % interval == 0,
+            submitted: None,
+            completed: None,
+        }
+    }
+
+    pub fn id(&self) -> OperationId {
+        self.id
+    }
+
+    pub fn submitted(&mut self) {
+        if self.sampled {
+            self.submitted = Some(Instant::now());
+
+            event!(
+                Level::TRACE,
+                message = "I/O operation submitted",
+                operation_id = %self.id
+            );
+        }
+    }
+
+    pub fn completed(&mut self) {
+        if self.sampled {
+            self.completed = Some(Instant::now());
+        }
+    }
+
+    pub fn dispatched(&self) {
+        let (Some(submitted), Some(completed)) = (self.submitted, self.completed) else {
+            return;
+        };
+
+        let dispatched = Instant::now();
+
+        event!(
+            Level::DEBUG,
+            message = "I/O operation dispatched",
+            operation_id = %self.id,
+            kernel_micros = completed.duration_since(submitted).as_micros() as u64,
+            dispatch_micros = dispatched.duration_since(completed).as_micros() as u64,
+        );
+    }
+}
+
+/// By default we trace one in this many operations.
+const DEFAULT_SAMPLE_INTERVAL: u32 = 1024;
+
+static SAMPLE_INTERVAL: AtomicU32 = AtomicU32::new(DEFAULT_SAMPLE_INTERVAL);
+
+static NEXT_OPERATION_ID: AtomicU64 = AtomicU64::new(0);
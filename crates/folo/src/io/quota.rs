@@ -0,0 +1,341 @@
+use crate::{
+    constants::{GENERAL_BYTES_BUCKETS, GENERAL_MILLISECONDS_BUCKETS, POISONED_LOCK},
+    metrics::{Event, EventBuilder, Magnitude},
+};
+use pin_project::pin_project;
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+/// Limits the rate of I/O performed by the tasks it is attached to, to keep one tenant of a shared
+/// server from monopolizing the disk and network at the expense of the others.
+///
+/// A quota is attached to a task by wrapping the future of the task via [`scope()`][Self::scope]
+/// or to all the children of a [`TaskGroup`][crate::rt::TaskGroup] via
+/// [`TaskGroup::io_quota()`][crate::rt::TaskGroup::io_quota]. Clones share the same budget, so
+/// attaching clones of one quota to all the tasks of a tenant limits the tenant as a whole, across
+/// all worker threads.
+///
+/// The budget is charged when an I/O operation completes, with the number of bytes transferred.
+/// Once the budget is exhausted, the I/O operations of the tasks wait before returning their
+/// results, until the budget has recovered. Up to one second worth of unused budget accumulates
+/// for bursts.
+///
+/// Usage is available via [`usage()`][Self::usage] and exported as metrics named after the quota:
+/// `io_quota_<name>_bytes`, `io_quota_<name>_ops` and `io_quota_<name>_throttled_millis`.
+#[derive(Clone, Debug)]
+pub struct IoQuota {
+    inner: Arc<QuotaInner>,
+}
+
+impl IoQuota {
+    /// Sets the quota for the duration of the future, so the I/O operations the future performs
+    /// each time it is polled are charged to it. Tasks spawned by the future do not inherit the
+    /// quota - they are separate tasks.
+    pub fn scope<F>(&self, future: F) -> WithIoQuota<F>
+    where
+        F: Future,
+    {
+        WithIoQuota {
+            quota: self.clone(),
+            future,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.inner.name
+    }
+
+    /// The I/O performed under the quota so far, by all the tasks it is attached to.
+    pub fn usage(&self) -> IoQuotaUsage {
+        IoQuotaUsage {
+            bytes: self.inner.bytes_used.load(Ordering::Relaxed),
+            operations: self.inner.operations_used.load(Ordering::Relaxed),
+            throttled: Duration::from_micros(self.inner.throttled_micros.load(Ordering::Relaxed)),
+        }
+    }
+
+    /// Charges a completed I/O operation to the quota, returning how long the operation must wait
+    /// before returning its result.
+    pub(crate) fn charge(&self, bytes: usize) -> Duration {
+        self.inner
+            .bytes_used
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+        self.inner.operations_used.fetch_add(1, Ordering::Relaxed);
+
+        let now = Instant::now();
+
+        let throttle = [
+            self.inner
+                .bytes
+                .as_ref()
+                .map(|bucket| charge(bucket, bytes as f64, now)),
+            self.inner
+                .operations
+                .as_ref()
+                .map(|bucket| charge(bucket, 1.0, now)),
+        ]
+        .into_iter()
+        .flatten()
+        .max()
+        .unwrap_or_default();
+
+        self.inner
+            .throttled_micros
+            .fetch_add(throttle.as_micros() as u64, Ordering::Relaxed);
+
+        self.with_events(|events| {
+            events.bytes.observe(bytes as Magnitude);
+            events.operations.observe_unit();
+
+            if !throttle.is_zero() {
+                events.throttled.observe_millis(throttle);
+            }
+        });
+
+        throttle
+    }
+
+    fn with_events(&self, f: impl FnOnce(&QuotaEvents)) {
+        // The events of a quota are looked up by its ID, which is not worth doing for nothing.
+        if !cfg!(feature = "metrics") {
+            return;
+        }
+
+        let events = QUOTA_EVENTS.with_borrow_mut(|events| {
+            Rc::clone(
+                events
+                    .entry(self.inner.id)
+                    .or_insert_with(|| Rc::new(QuotaEvents::new(&self.inner.name))),
+            )
+        });
+
+        f(&events);
+    }
+}
+
+/// Creates an [`IoQuota`]. Without any limits set, the quota only accounts for usage.
+#[derive(Debug)]
+pub struct IoQuotaBuilder {
+    name: Arc<str>,
+    bytes_per_second: Option<u64>,
+    operations_per_second: Option<u64>,
+}
+
+impl IoQuotaBuilder {
+    /// The name identifies the quota in its metrics, so it should be unique (e.g. the tenant ID).
+    pub fn new(name: impl Into<Arc<str>>) -> Self {
+        Self {
+            name: name.into(),
+            bytes_per_second: None,
+            operations_per_second: None,
+        }
+    }
+
+    /// Limits the number of bytes transferred per second. By default, there is no limit.
+    pub fn bytes_per_second(mut self, value: u64) -> Self {
+        self.bytes_per_second = Some(value);
+        self
+    }
+
+    /// Limits the number of I/O operations completed per second. By default, there is no limit.
+    pub fn operations_per_second(mut self, value: u64) -> Self {
+        self.operations_per_second = Some(value);
+        self
+    }
+
+    pub fn build(self) -> IoQuota {
+        IoQuota {
+            inner: Arc::new(QuotaInner {
+                id: NEXT_QUOTA_ID.fetch_add(1, Ordering::Relaxed),
+                name: self.name,
+                bytes: self.bytes_per_second.map(Bucket::new),
+                operations: self.operations_per_second.map(Bucket::new),
+                bytes_used: AtomicU64::new(0),
+                operations_used: AtomicU64::new(0),
+                throttled_micros: AtomicU64::new(0),
+            }),
+        }
+    }
+}
+
+/// The I/O performed under an [`IoQuota`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct IoQuotaUsage {
+    pub bytes: u64,
+    pub operations: u64,
+
+    /// The total time that I/O operations waited for the budget to recover.
+    pub throttled: Duration,
+}
+
+/// A future with an [`IoQuota`] set for the duration of each poll. See [`IoQuota::scope()`].
+#[pin_project]
+#[derive(Debug)]
+pub struct WithIoQuota<F> {
+    quota: IoQuota,
+
+    #[pin]
+    future: F,
+}
+
+impl<F> Future for WithIoQuota<F>
+where
+    F: Future,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        let previous = CURRENT_QUOTA.replace(Some(this.quota.clone()));
+        let result = this.future.poll(cx);
+        CURRENT_QUOTA.set(previous);
+
+        result
+    }
+}
+
+/// The quota that the I/O operations started on the current thread are charged to, if any.
+pub(crate) fn current_io_quota() -> Option<IoQuota> {
+    CURRENT_QUOTA.with_borrow(Clone::clone)
+}
+
+#[derive(Debug)]
+struct QuotaInner {
+    // Identifies the quota in the thread-local metrics cache, as names need not be unique.
+    id: u64,
+    name: Arc<str>,
+
+    // `None` if there is no limit.
+    bytes: Option<Mutex<Bucket>>,
+    operations: Option<Mutex<Bucket>>,
+
+    bytes_used: AtomicU64,
+    operations_used: AtomicU64,
+    throttled_micros: AtomicU64,
+}
+
+/// A token bucket that may go into debt. Whoever takes the bucket into debt waits until the debt
+/// has been repaid, so the wait of each operation is proportional to its own cost.
+#[derive(Debug)]
+struct Bucket {
+    per_second: f64,
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl Bucket {
+    fn new(per_second: u64) -> Mutex<Self> {
+        let per_second = per_second.max(1) as f64;
+
+        Mutex::new(Self {
+            per_second,
+            tokens: per_second,
+            refilled_at: Instant::now(),
+        })
+    }
+
+    fn take(&mut self, amount: f64, now: Instant) -> Duration {
+        let elapsed = now
+            .saturating_duration_since(self.refilled_at)
+            .as_secs_f64();
+
+        // At most one second worth of tokens accumulates, which is how much we allow to burst.
+        self.tokens = (self.tokens + elapsed * self.per_second).min(self.per_second);
+        self.refilled_at = now;
+
+        self.tokens -= amount;
+
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.per_second)
+        }
+    }
+}
+
+fn charge(bucket: &Mutex<Bucket>, amount: f64, now: Instant) -> Duration {
+    bucket.lock().expect(POISONED_LOCK).take(amount, now)
+}
+
+struct QuotaEvents {
+    bytes: Event,
+    operations: Event,
+    throttled: Event,
+}
+
+impl QuotaEvents {
+    fn new(name: &str) -> Self {
+        Self {
+            bytes: EventBuilder::new(format!("io_quota_{name}_bytes"))
+                .buckets(GENERAL_BYTES_BUCKETS)
+                .build(),
+            operations: EventBuilder::new(format!("io_quota_{name}_ops")).build(),
+            throttled: EventBuilder::new(format!("io_quota_{name}_throttled_millis"))
+                .buckets(GENERAL_MILLISECONDS_BUCKETS)
+                .build(),
+        }
+    }
+}
+
+static NEXT_QUOTA_ID: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    static CURRENT_QUOTA: RefCell<Option<IoQuota>> = const { RefCell::new(None) };
+
+    static QUOTA_EVENTS: RefCell<HashMap<u64, Rc<QuotaEvents>>> = RefCell::new(HashMap::new());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+
+    #[test]
+    fn bucket_allows_burst_then_throttles() {
+        let start = Instant::now();
+        let bucket = Bucket::new(1000);
+        let mut bucket = bucket.lock().unwrap();
+        bucket.refilled_at = start;
+
+        assert_eq!(bucket.take(1000.0, start), Duration::ZERO);
+        assert_eq!(bucket.take(500.0, start), Duration::from_millis(500));
+
+        // The debt is repaid after half a second, after which there is budget again.
+        let later = start + Duration::from_secs(1);
+        assert_eq!(bucket.take(250.0, later), Duration::ZERO);
+    }
+
+    #[test]
+    fn quota_is_set_only_within_scope() {
+        let quota = IoQuotaBuilder::new("test").build();
+
+        let name = block_on(quota.scope(async { current_io_quota().map(|x| x.name().to_owned()) }));
+        assert_eq!(name.as_deref(), Some("test"));
+        assert!(current_io_quota().is_none());
+    }
+
+    #[test]
+    fn usage_is_shared_by_clones() {
+        let quota = IoQuotaBuilder::new("test").build();
+
+        assert_eq!(quota.clone().charge(100), Duration::ZERO);
+        assert_eq!(quota.charge(50), Duration::ZERO);
+
+        let usage = quota.usage();
+        assert_eq!(usage.bytes, 150);
+        assert_eq!(usage.operations, 2);
+        assert_eq!(usage.throttled, Duration::ZERO);
+    }
+}
@@ -0,0 +1,184 @@
+use crate::{
+    io::{self, Buffer},
+    mem::isolation::Isolated,
+    rt::{spawn_sync, SynchronousTaskType},
+};
+use negative_impl::negative_impl;
+use std::io::{Read, Write};
+
+/// Returns a handle to the standard input of the process. See [`Stdin`].
+pub fn stdin() -> Stdin {
+    Stdin { _private: () }
+}
+
+/// Returns a handle to the standard output of the process. See [`Stdout`].
+pub fn stdout() -> Stdout {
+    Stdout { _private: () }
+}
+
+/// Returns a handle to the standard error of the process. See [`Stderr`].
+pub fn stderr() -> Stderr {
+    Stderr { _private: () }
+}
+
+/// A handle to the standard input of the process, which may be a console, a pipe or a file.
+///
+/// Windows does not support asynchronous I/O on consoles and anonymous pipes, so reads are
+/// performed on a synchronous worker thread, with the async worker thread free to do other work
+/// while waiting. Input from a console is converted from UTF-16 to UTF-8, just like with
+/// [`std::io::stdin()`].
+///
+/// A read that is waiting for input occupies a synchronous worker thread until input arrives or
+/// the standard input is closed, even if the caller stops waiting for it. Any data read after the
+/// caller has stopped waiting is lost.
+#[derive(Debug)]
+pub struct Stdin {
+    _private: (),
+}
+
+impl Stdin {
+    /// Reads the next available data from the standard input into the active region of the
+    /// buffer.
+    ///
+    /// Returns the buffer with the active region set to the bytes read, with a length of 0 if the
+    /// standard input has been closed.
+    pub async fn read(&mut self, mut buffer: Buffer<Isolated>) -> io::Result<Buffer<Isolated>> {
+        let capacity = buffer.len();
+
+        // Buffers from the isolated pool cannot leave the current thread, so the data is received
+        // into a temporary buffer and copied over.
+        let data = spawn_sync(
+            SynchronousTaskType::Syscall,
+            move || -> io::Result<Vec<u8>> {
+                let mut data = vec![0; capacity];
+                let len = std::io::stdin().read(&mut data)?;
+                data.truncate(len);
+                Ok(data)
+            },
+        )
+        .await?;
+
+        buffer.as_mut_slice()[..data.len()].copy_from_slice(&data);
+        buffer.set_len(data.len());
+
+        Ok(buffer)
+    }
+
+    /// Reads a line of text from the standard input, appending it to `out` including the
+    /// terminating newline (if any). Returns the number of bytes appended, which is 0 if the
+    /// standard input has been closed.
+    pub async fn read_line(&mut self, out: &mut String) -> io::Result<usize> {
+        let line = spawn_sync(
+            SynchronousTaskType::Syscall,
+            move || -> io::Result<String> {
+                let mut line = String::new();
+                std::io::stdin().read_line(&mut line)?;
+                Ok(line)
+            },
+        )
+        .await?;
+
+        out.push_str(&line);
+
+        Ok(line.len())
+    }
+}
+
+/// A handle to the standard output of the process, which may be a console, a pipe or a file.
+///
+/// Windows does not support asynchronous I/O on consoles and anonymous pipes, so writes are
+/// performed on a synchronous worker thread, with the async worker thread free to do other work
+/// while waiting. Output to a console is converted from UTF-8 to UTF-16, just like with
+/// [`std::io::stdout()`].
+///
+/// Writes via the same handle are performed in order. Writes via different handles (e.g. from
+/// different tasks) may be performed in any order relative to each other, though the data of one
+/// write is never interleaved with the data of another.
+#[derive(Debug)]
+pub struct Stdout {
+    _private: (),
+}
+
+impl Stdout {
+    /// Writes the active region of the buffer to the standard output and flushes it.
+    ///
+    /// The buffer will be returned in the result to allow reuse.
+    pub async fn write(&mut self, buffer: Buffer<Isolated>) -> io::Result<Buffer<Isolated>> {
+        write_to(OutputStream::Stdout, buffer).await
+    }
+
+    /// Writes the data to the standard output and flushes it.
+    pub async fn write_all(&mut self, data: &[u8]) -> io::Result<()> {
+        write_all_to(OutputStream::Stdout, data).await
+    }
+}
+
+/// A handle to the standard error of the process, which may be a console, a pipe or a file. The
+/// behavior matches that of [`Stdout`].
+#[derive(Debug)]
+pub struct Stderr {
+    _private: (),
+}
+
+impl Stderr {
+    /// Writes the active region of the buffer to the standard error.
+    ///
+    /// The buffer will be returned in the result to allow reuse.
+    pub async fn write(&mut self, buffer: Buffer<Isolated>) -> io::Result<Buffer<Isolated>> {
+        write_to(OutputStream::Stderr, buffer).await
+    }
+
+    /// Writes the data to the standard error.
+    pub async fn write_all(&mut self, data: &[u8]) -> io::Result<()> {
+        write_all_to(OutputStream::Stderr, data).await
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+async fn write_to(stream: OutputStream, buffer: Buffer<Isolated>) -> io::Result<Buffer<Isolated>> {
+    write_all_to(stream, &buffer.as_slice()).await?;
+
+    Ok(buffer)
+}
+
+async fn write_all_to(stream: OutputStream, data: &[u8]) -> io::Result<()> {
+    // The caller's data cannot leave the current thread, so it is copied into a temporary buffer.
+    let data = data.to_vec();
+
+    spawn_sync(SynchronousTaskType::Syscall, move || -> io::Result<()> {
+        // We hold the lock for the entire write, so the data is not interleaved with other writes.
+        match stream {
+            OutputStream::Stdout => {
+                let mut stdout = std::io::stdout().lock();
+                stdout.write_all(&data)?;
+                stdout.flush()?;
+            }
+            OutputStream::Stderr => {
+                let mut stderr = std::io::stderr().lock();
+                stderr.write_all(&data)?;
+                stderr.flush()?;
+            }
+        }
+
+        Ok(())
+    })
+    .await
+}
+
+#[negative_impl]
+impl !Send for Stdin {}
+#[negative_impl]
+impl !Sync for Stdin {}
+#[negative_impl]
+impl !Send for Stdout {}
+#[negative_impl]
+impl !Sync for Stdout {}
+#[negative_impl]
+impl !Send for Stderr {}
+#[negative_impl]
+impl !Sync for Stderr {}
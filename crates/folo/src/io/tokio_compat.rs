@@ -0,0 +1,244 @@
+#[cfg(feature = "tls")]
+use crate::tls::TlsStream;
+use crate::{
+    io::{self, Buffer, FuturesIo},
+    mem::isolation::Isolated,
+    net::ByteStream,
+};
+use futures::io::{AsyncBufRead, AsyncWrite as _};
+use negative_impl::negative_impl;
+use pin_project::pin_project;
+use std::{
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
+
+/// Converts a Folo stream into a [`Compat`] that implements the I/O traits of Tokio.
+pub trait CompatExt: ByteStream + Sized {
+    /// Wraps the stream into an adapter that implements [`tokio::io::AsyncRead`] and
+    /// [`tokio::io::AsyncWrite`], for use with crates written against Tokio (e.g. `h2`, `tonic` or
+    /// `tokio-tungstenite`).
+    fn compat(self) -> Compat<Self> {
+        Compat::new(self)
+    }
+}
+
+impl<S> CompatExt for S where S: ByteStream {}
+
+/// Exposes a Folo stream via the `AsyncRead` and `AsyncWrite` traits of Tokio. Created via
+/// [`CompatExt::compat()`].
+///
+/// Data is copied between the caller-provided slices and pooled buffers in the same way as in
+/// [`FuturesIo`], which this adapter is built upon.
+#[pin_project]
+#[derive(Debug)]
+pub struct Compat<S> {
+    #[pin]
+    inner: FuturesIo<S>,
+}
+
+impl<S> Compat<S>
+where
+    S: ByteStream,
+{
+    pub fn new(stream: S) -> Self {
+        Self {
+            inner: FuturesIo::new(stream),
+        }
+    }
+
+    pub fn get_ref(&self) -> &S {
+        self.inner.get_ref()
+    }
+
+    /// Returns the inner stream. Any data received but not yet read is lost.
+    pub fn into_inner(self) -> S {
+        self.inner.into_inner()
+    }
+}
+
+impl<S> AsyncRead for Compat<S>
+where
+    S: ByteStream,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let mut inner = self.project().inner;
+
+        let available = ready!(inner.as_mut().poll_fill_buf(cx))?;
+
+        let len = available.len().min(buf.remaining());
+        buf.put_slice(&available[..len]);
+
+        inner.consume(len);
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S> AsyncWrite for Compat<S>
+where
+    S: ByteStream,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        self.project().inner.poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        self.project().inner.poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        self.project().inner.poll_close(cx)
+    }
+}
+
+#[negative_impl]
+impl<S> !Send for Compat<S> {}
+#[negative_impl]
+impl<S> !Sync for Compat<S> {}
+
+/// Exposes a TLS session via the `AsyncRead` and `AsyncWrite` traits of Tokio, e.g. for serving
+/// or calling gRPC via `h2` on a session that agreed to use HTTP/2 via ALPN.
+///
+/// Reads and writes of the underlying stream are independent, so a read that waits for the peer
+/// does not hold up writes, as required by HTTP/2.
+#[cfg(feature = "tls")]
+#[derive(Debug)]
+pub struct TlsCompat<S> {
+    stream: TlsStream<S>,
+}
+
+#[cfg(feature = "tls")]
+impl<S> TlsCompat<S>
+where
+    S: ByteStream,
+{
+    pub fn new(stream: TlsStream<S>) -> Self {
+        Self { stream }
+    }
+
+    pub fn get_ref(&self) -> &TlsStream<S> {
+        &self.stream
+    }
+
+    pub fn into_inner(self) -> TlsStream<S> {
+        self.stream
+    }
+}
+
+// The session is never pinned, as it is driven via methods that take it by mutable reference.
+#[cfg(feature = "tls")]
+impl<S> Unpin for TlsCompat<S> {}
+
+#[cfg(feature = "tls")]
+impl<S> AsyncRead for TlsCompat<S>
+where
+    S: ByteStream,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        if buf.remaining() == 0 {
+            return Poll::Ready(Ok(()));
+        }
+
+        let len = ready!(self
+            .get_mut()
+            .stream
+            .poll_read(cx, buf.initialize_unfilled()))?;
+
+        buf.advance(len);
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(feature = "tls")]
+impl<S> AsyncWrite for TlsCompat<S>
+where
+    S: ByteStream,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        self.get_mut()
+            .stream
+            .poll_write(cx, buf)
+            .map_err(Into::into)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        self.get_mut().stream.poll_flush(cx).map_err(Into::into)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        self.get_mut().stream.poll_shutdown(cx).map_err(Into::into)
+    }
+}
+
+/// Drives a type that implements the I/O traits of Tokio from Folo, exposing it via the same
+/// buffer-based methods as Folo streams.
+///
+/// The Tokio type must not depend on the Tokio runtime (e.g. a `tokio::net::TcpStream` does not
+/// work but an in-memory `tokio::io::DuplexStream` or a stream from a crate that is generic over
+/// the I/O traits does).
+#[derive(Debug)]
+pub struct TokioStream<T> {
+    inner: T,
+}
+
+impl<T> TokioStream<T>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    pub fn new(inner: T) -> Self {
+        Self { inner }
+    }
+
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Reads the next buffer of data from the stream.
+    ///
+    /// Returns the buffer with the active region set to the bytes read, with a length of 0 if the
+    /// stream has ended.
+    pub async fn read(&mut self, mut buffer: Buffer<Isolated>) -> io::Result<Buffer<Isolated>> {
+        let len = self.inner.read(&mut buffer.as_mut_slice()).await?;
+        buffer.set_len(len);
+
+        Ok(buffer)
+    }
+
+    /// Writes the active region of the buffer to the stream.
+    ///
+    /// Returns the buffer to allow reuse.
+    pub async fn write(&mut self, buffer: Buffer<Isolated>) -> io::Result<Buffer<Isolated>> {
+        self.inner.write_all(&buffer.as_slice()).await?;
+        self.inner.flush().await?;
+
+        Ok(buffer)
+    }
+
+    /// Flushes any buffered data and shuts down the write side of the stream.
+    pub async fn shutdown(&mut self) -> io::Result<()> {
+        Ok(self.inner.shutdown().await?)
+    }
+}
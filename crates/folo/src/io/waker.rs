@@ -5,7 +5,10 @@ use std::{
     hash::{Hash, Hasher},
     sync::Weak,
 };
-use windows::Win32::{Foundation::HANDLE, System::IO::PostQueuedCompletionStatus};
+use windows::Win32::{
+    Foundation::HANDLE,
+    System::IO::{PostQueuedCompletionStatus, OVERLAPPED},
+};
 
 // Value is meaningless, just has to be unique.
 pub(crate) const WAKE_UP_COMPLETION_KEY: usize = 0x23546789897;
@@ -53,6 +56,13 @@ impl IoWaker {
         });
     }
 
+    /// Submits any queued wakeups and disables batching on the current thread, after which a call
+    /// to wake() is submitted immediately again.
+    pub fn disable_batching() {
+        Self::submit_batch();
+        BATCH.with_borrow_mut(|batch| *batch = None);
+    }
+
     pub fn submit_batch() {
         BATCH.with_borrow_mut(|batch| {
             let batch = batch.as_mut().expect(
@@ -70,6 +80,28 @@ impl IoWaker {
         });
     }
 
+    /// Delivers the completion of an I/O operation to the I/O driver as if the operating system
+    /// had completed the operation. For operations performed outside the completion port, on
+    /// synchronous worker threads (see `fall_back_to_blocking()`).
+    ///
+    /// # Safety
+    ///
+    /// The OVERLAPPED pointer must be the one handed to the callback in `Operation::begin()` and
+    /// the operation must have completed. Ownership of the operation returns to the I/O driver.
+    pub(crate) unsafe fn post_completion(
+        &self,
+        overlapped: *mut OVERLAPPED,
+        bytes_transferred: u32,
+    ) {
+        let completion_port = self.completion_port.upgrade().expect(
+            "the I/O driver outlives all its operations, so its completion port must still exist",
+        );
+
+        // The completion key is the same as for completions of I/O primitives bound to the port.
+        PostQueuedCompletionStatus(**completion_port, bytes_transferred, 0, Some(overlapped))
+            .expect("posting to an I/O completion port should never fail unless the OS is critically out of resources");
+    }
+
     // Returns true if the wake was added to the batch and no explicit wake is needed.
     fn try_add_to_batch(&self) -> bool {
         BATCH.with_borrow_mut(|batch| {
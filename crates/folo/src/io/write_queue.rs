@@ -0,0 +1,316 @@
+use crate::{
+    constants::GENERAL_BYTES_BUCKETS,
+    io::{self, Buffer, OperationResultExt, OperationResultFuture},
+    mem::isolation::Isolated,
+    metrics::{observe, Event, EventBuilder, Magnitude},
+    net::ByteStream,
+    rt::spawn,
+    time::{Clock, Delay},
+};
+use negative_impl::negative_impl;
+use std::{
+    cell::RefCell,
+    mem,
+    rc::{Rc, Weak},
+    time::{Duration, Instant},
+};
+
+/// How long data may wait in the queue for more data to join it, by default.
+pub const DEFAULT_WRITE_QUEUE_FLUSH_DELAY: Duration = Duration::from_millis(1);
+
+/// Coalesces many small writes to a stream into fewer write operations, for chatty protocols that
+/// would otherwise issue one I/O operation per message.
+///
+/// Written data is held back ("corked") until either enough of it has been queued to fill a batch
+/// or the flush delay has elapsed since the first byte of the batch was queued, at which point the
+/// whole batch is written to the stream with one operation. The delay bounds the latency added by
+/// the queue - use [`flush()`][Self::flush] to write the queued data immediately, e.g. at the end
+/// of a response.
+///
+/// The queue can be shared by multiple tasks on the same async worker thread, with writes from
+/// different tasks written to the stream in the order they were queued. If a
+/// [`write_all()`][Self::write_all] call has to wait for a full batch to be written, data queued
+/// by other tasks in the meantime may end up between its parts.
+///
+/// If a write performed after the flush delay fails, the error is reported by the next call to
+/// `write_all()` or `flush()`.
+///
+/// The queue reports the number of bytes in each batch (`io_write_queue_depth_bytes`), the number
+/// of writes coalesced into each batch (`io_write_queue_batch_writes`) and how long the first
+/// byte of each batch waited in the queue (`io_write_queue_flush_latency_micros`) via the
+/// [`metrics`][crate::metrics] module.
+///
+/// # Example
+///
+/// ```no_run
+/// use folo::io::WriteQueue;
+/// use folo::net::TcpStream;
+/// use std::{net::SocketAddr, time::Duration};
+///
+/// #[folo::main]
+/// async fn main() {
+///     let stream = TcpStream::connect(SocketAddr::from(([127, 0, 0, 1], 1234)))
+///         .await
+///         .unwrap();
+///
+///     let queue = WriteQueue::new(stream).with_flush_delay(Duration::from_micros(500));
+///
+///     for i in 0..100 {
+///         queue.write_all(format!("message {i}\n").as_bytes()).await.unwrap();
+///     }
+///
+///     queue.shutdown().await.unwrap();
+/// }
+/// ```
+#[derive(Debug)]
+pub struct WriteQueue<S> {
+    state: Rc<RefCell<QueueState<S>>>,
+}
+
+#[derive(Debug)]
+struct QueueState<S> {
+    stream: S,
+
+    // Data queued but not yet written, in the active region, which always starts at the start of
+    // the buffer.
+    batch: Buffer<Isolated>,
+
+    // The number of `write_all()` calls that contributed data to the current batch.
+    batch_writes: usize,
+
+    // When the first byte of the current batch was queued. `None` if the batch is empty.
+    batch_started: Option<Instant>,
+
+    // Whether a task has been spawned to flush the current batch once the flush delay elapses.
+    flush_scheduled: bool,
+
+    flush_delay: Duration,
+    max_batch_bytes: usize,
+
+    // An error from a write that nobody was waiting for, to be reported to the next caller.
+    deferred_error: Option<io::Error>,
+}
+
+impl<S> WriteQueue<S>
+where
+    S: ByteStream + 'static,
+{
+    pub fn new(stream: S) -> Self {
+        let batch = empty_buffer();
+        let max_batch_bytes = batch.capacity();
+
+        Self {
+            state: Rc::new(RefCell::new(QueueState {
+                stream,
+                batch,
+                batch_writes: 0,
+                batch_started: None,
+                flush_scheduled: false,
+                flush_delay: DEFAULT_WRITE_QUEUE_FLUSH_DELAY,
+                max_batch_bytes,
+                deferred_error: None,
+            })),
+        }
+    }
+
+    /// Sets how long queued data may wait for more data to join it before it is written to the
+    /// stream. A delay of zero still coalesces the writes made before the flush task gets to run.
+    pub fn with_flush_delay(self, value: Duration) -> Self {
+        self.state.borrow_mut().flush_delay = value;
+        self
+    }
+
+    /// Sets the number of queued bytes at which the batch is written to the stream without
+    /// waiting for the flush delay. Limited to the capacity of a pooled buffer, which is also the
+    /// default.
+    pub fn with_max_batch_bytes(self, value: usize) -> Self {
+        {
+            let mut state = self.state.borrow_mut();
+            state.max_batch_bytes = value.clamp(1, state.batch.capacity());
+        }
+
+        self
+    }
+
+    /// The number of bytes queued but not yet written to the stream.
+    pub fn queue_depth(&self) -> usize {
+        self.state.borrow().batch.len()
+    }
+
+    /// Calls a function with a mutable reference to the inner stream, e.g. to start a read.
+    /// Writing to the stream directly bypasses any data that is still queued.
+    pub fn with_stream<R>(&self, f: impl FnOnce(&mut S) -> R) -> R {
+        f(&mut self.state.borrow_mut().stream)
+    }
+
+    /// Queues all the data for writing, writing full batches to the stream as they fill up.
+    pub async fn write_all(&self, mut data: &[u8]) -> io::Result<()> {
+        if let Some(error) = self.state.borrow_mut().deferred_error.take() {
+            return Err(error);
+        }
+
+        let mut counted = false;
+
+        while !data.is_empty() {
+            let full_batch = {
+                let mut state = self.state.borrow_mut();
+
+                let len = state.batch.len();
+                let chunk = (state.max_batch_bytes - len).min(data.len());
+
+                state.batch.set_len(len + chunk);
+                state.batch.as_mut_slice()[len..].copy_from_slice(&data[..chunk]);
+                data = &data[chunk..];
+
+                if !counted {
+                    state.batch_writes += 1;
+                    counted = true;
+                }
+
+                if state.batch_started.is_none() {
+                    state.batch_started = Some(Instant::now());
+                }
+
+                if state.batch.len() >= state.max_batch_bytes {
+                    Some(state.start_flush())
+                } else {
+                    None
+                }
+            };
+
+            if let Some(write) = full_batch {
+                write.await.into_inner()?;
+            }
+        }
+
+        self.schedule_flush();
+
+        Ok(())
+    }
+
+    /// Writes any queued data to the stream, waiting for the write to complete.
+    pub async fn flush(&self) -> io::Result<()> {
+        if let Some(error) = self.state.borrow_mut().deferred_error.take() {
+            return Err(error);
+        }
+
+        let write = {
+            let mut state = self.state.borrow_mut();
+
+            if state.batch.is_empty() {
+                return Ok(());
+            }
+
+            state.start_flush()
+        };
+
+        write.await.into_inner()?;
+
+        Ok(())
+    }
+
+    /// Writes any queued data to the stream and performs a graceful shutdown of the stream.
+    pub async fn shutdown(&self) -> io::Result<()> {
+        self.flush().await?;
+
+        let shutdown = self.state.borrow_mut().stream.shutdown();
+        shutdown.await
+    }
+
+    fn schedule_flush(&self) {
+        let delay = {
+            let mut state = self.state.borrow_mut();
+
+            if state.flush_scheduled || state.batch.is_empty() {
+                return;
+            }
+
+            state.flush_scheduled = true;
+            state.flush_delay
+        };
+
+        // The task does not keep the queue alive - if the queue is dropped, so is the queued data.
+        let state = Rc::downgrade(&self.state);
+
+        _ = spawn(flush_after(state, delay));
+    }
+}
+
+impl<S> QueueState<S>
+where
+    S: ByteStream,
+{
+    /// Starts writing the current batch to the stream, replacing it with an empty one, so more
+    /// data can be queued while the write is in progress. The I/O operation is started before this
+    /// returns, so batches are written in the order they are flushed.
+    fn start_flush(&mut self) -> OperationResultFuture {
+        let batch = mem::replace(&mut self.batch, empty_buffer());
+
+        if let Some(started) = self.batch_started.take() {
+            observe!(WRITE_QUEUE_FLUSH_LATENCY.observe(started.elapsed().as_micros() as i64));
+        }
+
+        observe!(WRITE_QUEUE_DEPTH.observe(batch.len() as i64));
+        observe!(WRITE_QUEUE_BATCH_WRITES.observe(mem::take(&mut self.batch_writes) as i64));
+
+        self.stream.write(batch)
+    }
+}
+
+async fn flush_after<S>(state: Weak<RefCell<QueueState<S>>>, delay: Duration)
+where
+    S: ByteStream,
+{
+    Delay::with_clock(&Clock::new(), delay).await;
+
+    let write = {
+        let Some(state) = state.upgrade() else {
+            return;
+        };
+
+        let mut state = state.borrow_mut();
+        state.flush_scheduled = false;
+
+        // Someone else may have already flushed the batch while we were waiting.
+        if state.batch.is_empty() {
+            return;
+        }
+
+        state.start_flush()
+    };
+
+    if let Err(e) = write.await.into_inner() {
+        if let Some(state) = state.upgrade() {
+            state.borrow_mut().deferred_error.get_or_insert(e);
+        }
+    }
+}
+
+fn empty_buffer() -> Buffer<Isolated> {
+    let mut buffer = Buffer::<Isolated>::from_pool();
+    buffer.set_len(0);
+    buffer
+}
+
+#[negative_impl]
+impl<S> !Send for WriteQueue<S> {}
+#[negative_impl]
+impl<S> !Sync for WriteQueue<S> {}
+
+const BATCH_WRITES_BUCKETS: &[Magnitude] = &[1, 2, 4, 16, 64, 256];
+const FLUSH_LATENCY_MICROS_BUCKETS: &[Magnitude] = &[0, 100, 500, 1000, 5000, 20000];
+
+thread_local! {
+    static WRITE_QUEUE_DEPTH: Event = EventBuilder::new("io_write_queue_depth_bytes")
+        .buckets(GENERAL_BYTES_BUCKETS)
+        .build();
+
+    static WRITE_QUEUE_BATCH_WRITES: Event = EventBuilder::new("io_write_queue_batch_writes")
+        .buckets(BATCH_WRITES_BUCKETS)
+        .build();
+
+    static WRITE_QUEUE_FLUSH_LATENCY: Event =
+        EventBuilder::new("io_write_queue_flush_latency_micros")
+            .buckets(FLUSH_LATENCY_MICROS_BUCKETS)
+            .build();
+}
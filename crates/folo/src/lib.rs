@@ -1,25 +1,62 @@
 #![allow(dead_code)] // Under active development, dead code is fine.
 
+// On wasm32, only spawning and timers are available, with tasks executing on the event loop of
+// the host. Everything that depends on the I/O driver or on threads is excluded, including the
+// modules of optional features, which have no effect on wasm32.
 #[doc(hidden)]
 pub mod __private;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod bench;
+#[cfg(all(feature = "tokio-bridge", not(target_arch = "wasm32")))]
+pub mod bridge;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod collections;
+#[cfg(all(feature = "config", not(target_arch = "wasm32")))]
+pub mod config;
+#[cfg(all(feature = "console", not(target_arch = "wasm32")))]
+pub mod console;
+#[cfg(not(target_arch = "wasm32"))]
 mod constants;
-#[cfg(feature = "criterion")]
+#[cfg(all(feature = "criterion", not(target_arch = "wasm32")))]
 pub mod criterion;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod fs;
+#[cfg(all(feature = "grpc", not(target_arch = "wasm32")))]
+pub mod grpc;
+#[cfg(all(feature = "hyper", not(target_arch = "wasm32")))]
+pub mod hyper;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod io;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod linked;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod mem;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod metrics;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod net;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod process;
+#[cfg_attr(target_arch = "wasm32", path = "wasm/rt.rs")]
 pub mod rt;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod signal;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod stream;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod sync;
+#[cfg(all(feature = "fakes", not(target_arch = "wasm32")))]
+pub mod test_rt;
+#[cfg_attr(target_arch = "wasm32", path = "wasm/time.rs")]
 pub mod time;
+#[cfg(all(feature = "tls", not(target_arch = "wasm32")))]
+pub mod tls;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod util;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod windows;
 
-#[cfg(feature = "hyper")]
-pub mod hyper;
+pub use rt::yield_now;
 
 /// Marks a `main()` function as the async entry point of an app based on the Folo runtime.
 ///
@@ -65,11 +102,88 @@ pub mod hyper;
 ///     yield_now().await;
 ///     println!("Hello, world!");
 /// }
+#[cfg(not(target_arch = "wasm32"))]
 pub use folo_proc_macros::__macro_main as main;
 
 /// Same as [`#[folo::main]`][main] but also marks the entrypoint as a test.
+#[cfg(not(target_arch = "wasm32"))]
 pub use folo_proc_macros::__macro_test as test;
 
+/// Marks an async function as the body of a benchmark, executed in a loop on every async worker
+/// thread of a dedicated runtime by the [`bench`][mod@bench] harness. The function is replaced with
+/// a synchronous function of the same name that runs the benchmark, prints the report to stdout and
+/// returns it.
+///
+/// # Arguments
+///
+/// * `duration_ms` - how long to execute the measured iterations for. Defaults to 5 seconds.
+/// * `warmup_ms` - how long to execute the body for before starting the measurement. Defaults to 1
+///    second.
+/// * `max_processors` - maximum number of processors to execute on.
+///
+/// # Examples
+///
+/// ```ignore
+/// #[folo::bench(duration_ms = 2000, max_processors = 4)]
+/// async fn spawn_and_await() {
+///     folo::rt::spawn(async {}).await;
+/// }
+///
+/// fn main() {
+///     spawn_and_await();
+/// }
+/// ```
+#[cfg(not(target_arch = "wasm32"))]
+pub use folo_proc_macros::__macro_bench as bench;
+
+/// Awaits a fixed set of futures concurrently on the current task, completing with a tuple of
+/// their outputs once all of them have completed.
+///
+/// The futures may have different output types. Unlike spawning each future as a separate task,
+/// the futures may borrow from the caller and need not be `'static` or `Send`. All the futures
+/// are polled whenever the task is woken up, so this is meant for a handful of futures, not for
+/// thousands.
+///
+/// Must be used in an async context.
+///
+/// # Example
+///
+/// ```no_run
+/// use folo::fs;
+///
+/// #[folo::main]
+/// async fn main() {
+///     let (config, secrets) = folo::join!(fs::read("config.toml"), fs::read("secrets.toml"));
+///
+///     println!("{} bytes of config", config.unwrap().len());
+///     println!("{} bytes of secrets", secrets.unwrap().len());
+/// }
+/// ```
+pub use folo_decl_macros::__macro_join as join;
+
+/// Awaits a fixed set of fallible futures concurrently on the current task, like [`join!`],
+/// completing with a tuple of their successful outputs once all of them have succeeded.
+///
+/// The futures must have outputs of type `Result<T, E>` with the same error type `E` but possibly
+/// different success types. As soon as one of the futures fails, the macro completes with its error
+/// and the remaining futures are dropped.
+///
+/// # Example
+///
+/// ```no_run
+/// use folo::fs;
+///
+/// #[folo::main]
+/// async fn main() -> folo::io::Result<()> {
+///     let (config, secrets) =
+///         folo::try_join!(fs::read("config.toml"), fs::read("secrets.toml"))?;
+///
+///     println!("{} bytes of config and {} of secrets", config.len(), secrets.len());
+///     Ok(())
+/// }
+/// ```
+pub use folo_decl_macros::__macro_try_join as try_join;
+
 // This is so macros can produce code which refers to
 // ::folo::* which will work both in the crate and in the
 // service code.
@@ -50,6 +50,11 @@ impl<T, const SLAB_CAPACITY: usize> PinnedSlabChain<T, SLAB_CAPACITY> {
         self.slabs.iter().all(|slab| slab.is_empty())
     }
 
+    /// The number of items the chain can hold without growing.
+    pub fn capacity(&self) -> usize {
+        self.slabs.len() * SLAB_CAPACITY
+    }
+
     /// # Panics
     ///
     /// Panics if the index is out of bounds or is not associated with an item.
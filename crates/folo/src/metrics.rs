@@ -1,15 +1,26 @@
+mod crash_report;
+#[cfg(feature = "metrics-persistence")]
+mod persistence;
+
+pub use crash_report::*;
+#[cfg(feature = "metrics-persistence")]
+pub use persistence::*;
+
 use crate::time::LowPrecisionInstant;
 use negative_impl::negative_impl;
 use std::{
     borrow::Cow,
-    cell::{Cell, RefCell, UnsafeCell},
     cmp,
     collections::HashMap,
     fmt::{Display, Write},
     future::Future,
-    rc::Rc,
     time::Duration,
 };
+#[cfg(feature = "metrics")]
+use std::{
+    cell::{Cell, RefCell, UnsafeCell},
+    rc::Rc,
+};
 
 pub type Magnitude = i64;
 
@@ -21,33 +32,49 @@ pub type Magnitude = i64;
 ///
 /// This type is single-threaded. Create a separate instance for each thread.
 /// The data will be merged across all threads to yield a combined report.
+///
+/// # Disabling metrics
+///
+/// Without the `metrics` feature, events are not registered anywhere and all observations are
+/// discarded without doing any work, so the instrumentation can stay in latency-critical code.
 pub struct Event {
+    #[cfg(feature = "metrics")]
     bag: Rc<ObservationBag>,
 }
 
 impl Event {
     /// Observes an event with a magnitude of 1. An event that only takes observations of this kind
     /// is a counter and undergoes simplified reporting.
+    #[inline]
     pub fn observe_unit(&self) {
-        self.bag.insert(1, 1);
+        self.insert(1, 1);
     }
 
+    #[inline]
     pub fn observe(&self, magnitude: Magnitude) {
-        self.bag.insert(magnitude, 1);
+        self.insert(magnitude, 1);
     }
 
+    #[inline]
     pub fn observe_millis(&self, duration: Duration) {
-        self.bag.insert(duration.as_millis() as i64, 1);
+        self.insert(duration.as_millis() as i64, 1);
     }
 
+    #[inline]
     pub fn observe_many(&self, magnitude: Magnitude, count: usize) {
-        self.bag.insert(magnitude, count);
+        self.insert(magnitude, count);
     }
 
+    #[inline]
     pub fn observe_duration_millis<F, R>(&self, f: F) -> R
     where
         F: FnOnce() -> R,
     {
+        // Not even the clock is read if the observation would be discarded anyway.
+        if !cfg!(feature = "metrics") {
+            return f();
+        }
+
         // We do not use ultra-low precision here because this is a synchronous call in a
         // situation where the async task engine could not possible update the ultra low precision
         // instant, so it would always record an elapsed time of zero.
@@ -65,6 +92,10 @@ impl Event {
         F: FnOnce() -> FF,
         FF: Future<Output = R>,
     {
+        if !cfg!(feature = "metrics") {
+            return f().await;
+        }
+
         // We do not use ultra-low precision here because this is a synchronous call in a
         // situation where the async task engine could not possible update the ultra low precision
         // instant, so it would always record an elapsed time of zero.
@@ -77,9 +108,20 @@ impl Event {
         result
     }
 
+    #[cfg(feature = "metrics")]
     fn new(bag: Rc<ObservationBag>) -> Self {
         Self { bag }
     }
+
+    #[cfg(feature = "metrics")]
+    #[inline]
+    fn insert(&self, magnitude: Magnitude, count: usize) {
+        self.bag.insert(magnitude, count);
+    }
+
+    #[cfg(not(feature = "metrics"))]
+    #[inline(always)]
+    fn insert(&self, _magnitude: Magnitude, _count: usize) {}
 }
 
 #[negative_impl]
@@ -87,6 +129,34 @@ impl !Send for Event {}
 #[negative_impl]
 impl !Sync for Event {}
 
+/// Observes an event held in a thread-local variable, e.g. `observe!(EVENT.observe_unit())`.
+///
+/// Without the `metrics` feature, this compiles to nothing - the thread-local variable is not
+/// accessed and the arguments are not evaluated. Only `observe_duration_millis()` still calls the
+/// function it is given, as the caller needs its result.
+#[cfg(feature = "metrics")]
+macro_rules! observe {
+    ($event:ident . $method:ident ( $($arg:expr),* $(,)? )) => {
+        $event.with(|event| event.$method($($arg),*))
+    };
+}
+
+#[cfg(not(feature = "metrics"))]
+macro_rules! observe {
+    ($event:ident . observe_duration_millis ( $f:expr $(,)? )) => {{
+        let _ = &$event;
+        let f = $f;
+        f()
+    }};
+    ($event:ident . $method:ident ( $($arg:expr),* $(,)? )) => {{
+        // Only references everything, so nothing becomes unused when metrics are compiled out.
+        let _ = &$event;
+        let _ = || ($($arg,)*);
+    }};
+}
+
+pub(crate) use observe;
+
 pub struct EventBuilder {
     name: Cow<'static, str>,
 
@@ -107,6 +177,7 @@ impl EventBuilder {
         self
     }
 
+    #[cfg(feature = "metrics")]
     pub fn build(self) -> Event {
         let bag = BAGS.with_borrow_mut(|bags| {
             Rc::clone(
@@ -117,8 +188,14 @@ impl EventBuilder {
 
         Event::new(bag)
     }
+
+    #[cfg(not(feature = "metrics"))]
+    pub fn build(self) -> Event {
+        Event {}
+    }
 }
 
+#[cfg(feature = "metrics")]
 thread_local! {
     static BAGS: RefCell<HashMap<String, Rc<ObservationBag>>> = RefCell::new(HashMap::new());
 }
@@ -126,6 +203,7 @@ thread_local! {
 /// Collects all the observations made about a particular event and processes the data for analysis.
 ///
 /// Data from different bags of the same event is merged together to yield a combined report later.
+#[cfg(feature = "metrics")]
 struct ObservationBag {
     count: Cell<usize>,
     sum: Cell<Magnitude>,
@@ -137,6 +215,7 @@ struct ObservationBag {
     bucket_magnitudes: &'static [Magnitude],
 }
 
+#[cfg(feature = "metrics")]
 impl ObservationBag {
     fn insert(&self, magnitude: Magnitude, count: usize) {
         self.count.set(self.count.get() + count);
@@ -185,6 +264,8 @@ impl ObservationBag {
     }
 }
 
+#[derive(Clone)]
+#[cfg_attr(feature = "metrics-persistence", derive(serde::Serialize))]
 struct ObservationBagSnapshot {
     count: usize,
     sum: Magnitude,
@@ -208,11 +289,15 @@ impl ObservationBagSnapshot {
 
 /// A report page is a single thread's contribution to a report. Collect all the pages from all
 /// the threads and you can assemble a report to show to the operator or to export.
+#[derive(Clone)]
 pub struct ReportPage {
     bags: HashMap<String, ObservationBagSnapshot>,
 }
 
 /// Assembles a report page representing the latest state of observations on the current thread.
+///
+/// Without the `metrics` feature, the page is always empty.
+#[cfg(feature = "metrics")]
 pub fn report_page() -> ReportPage {
     ReportPage {
         bags: BAGS.with_borrow(|bags| {
@@ -223,6 +308,37 @@ pub fn report_page() -> ReportPage {
     }
 }
 
+/// Same as `report_page()` but returns `None` instead of panicking if the metrics of the current
+/// thread are not accessible (e.g. because the thread is being torn down). Used in situations where
+/// panicking is not an option, such as in panic hooks.
+#[cfg(feature = "metrics")]
+pub(crate) fn try_report_page() -> Option<ReportPage> {
+    BAGS.try_with(|bags| {
+        let bags = bags.try_borrow().ok()?;
+
+        Some(ReportPage {
+            bags: bags
+                .iter()
+                .map(|(name, bag)| (name.clone(), bag.snapshot()))
+                .collect(),
+        })
+    })
+    .ok()
+    .flatten()
+}
+
+#[cfg(not(feature = "metrics"))]
+pub fn report_page() -> ReportPage {
+    ReportPage {
+        bags: HashMap::new(),
+    }
+}
+
+#[cfg(not(feature = "metrics"))]
+pub(crate) fn try_report_page() -> Option<ReportPage> {
+    Some(report_page())
+}
+
 pub struct ReportBuilder {
     pages: Vec<ReportPage>,
 }
@@ -269,10 +385,51 @@ impl ReportBuilder {
 }
 
 /// An analysis of collected data, designed for display to console output.
+///
+/// With the `metrics-persistence` feature, the report can be serialized (e.g. to JSON via
+/// `serde_json`), which is also the format written by [`set_metrics_persistence()`].
+#[cfg_attr(feature = "metrics-persistence", derive(serde::Serialize))]
 pub struct Report {
+    #[cfg_attr(feature = "metrics-persistence", serde(rename = "events"))]
     bags: HashMap<String, ObservationBagSnapshot>,
 }
 
+impl Report {
+    /// Returns the number of observations of the event, or 0 if the event has not been observed.
+    pub fn count(&self, event_name: &str) -> usize {
+        self.bags.get(event_name).map_or(0, |snapshot| snapshot.count)
+    }
+
+    /// Estimates a percentile (0.0 to 100.0) of the magnitudes observed for the event, returning
+    /// the upper bound of the histogram bucket that the percentile falls into.
+    ///
+    /// Returns `None` if the event has not been observed or if the percentile falls above the
+    /// largest bucket of the histogram.
+    pub fn percentile(&self, event_name: &str, percentile: f64) -> Option<Magnitude> {
+        let snapshot = self.bags.get(event_name)?;
+
+        if snapshot.count == 0 {
+            return None;
+        }
+
+        // The number of observations at or below the percentile, rounded up so that e.g. the 50th
+        // percentile of a single observation is that observation.
+        let target = ((percentile / 100.0) * snapshot.count as f64).ceil().max(1.0) as usize;
+
+        let mut cumulative = 0;
+
+        for (index, &count) in snapshot.bucket_counts.iter().enumerate() {
+            cumulative += count;
+
+            if cumulative >= target {
+                return Some(snapshot.bucket_magnitudes[index]);
+            }
+        }
+
+        None
+    }
+}
+
 impl Display for Report {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         // Sort by name for consistent output.
@@ -391,7 +548,7 @@ impl Display for ObservationBagSnapshot {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "metrics"))]
 mod tests {
     use std::thread;
 
@@ -536,7 +693,66 @@ mod tests {
         println!("{}", report);
     }
 
+    #[test]
+    fn percentile() {
+        clear();
+
+        let event = EventBuilder::new("test").buckets(&[1, 2, 3]).build();
+
+        event.observe_many(1, 50);
+        event.observe_many(2, 40);
+        event.observe_many(3, 9);
+        event.observe(100);
+
+        let mut report_builder = ReportBuilder::new();
+        report_builder.add_page(report_page());
+
+        let report = report_builder.build();
+
+        assert_eq!(report.count("test"), 100);
+        assert_eq!(report.percentile("test", 50.0), Some(1));
+        assert_eq!(report.percentile("test", 90.0), Some(2));
+        assert_eq!(report.percentile("test", 99.0), Some(3));
+        assert_eq!(report.percentile("test", 100.0), None);
+
+        assert_eq!(report.count("missing"), 0);
+        assert_eq!(report.percentile("missing", 50.0), None);
+    }
+
+    #[test]
+    fn try_report_page_while_registry_borrowed() {
+        clear();
+
+        let event = EventBuilder::new("test").build();
+        event.observe_unit();
+
+        let page = try_report_page().unwrap();
+        assert_eq!(page.bags.len(), 1);
+
+        // If the registry is being modified, we get nothing instead of a panic.
+        BAGS.with_borrow_mut(|_| {
+            assert!(try_report_page().is_none());
+        });
+    }
+
     fn clear() {
         BAGS.with_borrow_mut(|bags| bags.clear());
     }
 }
+
+#[cfg(all(test, not(feature = "metrics")))]
+mod tests_metrics_off {
+    use super::*;
+
+    #[test]
+    fn observations_are_discarded() {
+        let event = EventBuilder::new("test").buckets(&[1, 2, 3]).build();
+
+        event.observe(1);
+        event.observe_unit();
+        event.observe_many(2, 10);
+        assert_eq!(event.observe_duration_millis(|| 42), 42);
+
+        assert!(report_page().bags.is_empty());
+    }
+}
@@ -0,0 +1,99 @@
+use crate::{
+    constants,
+    metrics::{try_report_page, ReportBuilder},
+    rt::{flight_recorder, try_describe_current_task},
+};
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    panic::{self, PanicHookInfo},
+    path::PathBuf,
+    sync::{Mutex, Once},
+    thread,
+    time::SystemTime,
+};
+
+/// Configures the process to write a final metrics report to the specified file when a thread
+/// panics. The report contains the metrics collected by the panicking thread up to the moment of
+/// the panic - this is typically the worker thread whose task panicked, so the report describes
+/// what that worker was doing before it died.
+///
+/// Reports are appended to the file, so if multiple threads panic, each of them adds a report.
+///
+/// The panic hook is installed once per process and chains to any previously installed hook, so
+/// the default panic output is preserved. Calling this again merely changes the target path.
+///
+/// Only panics are covered. Crashes that do not unwind through the Rust panic machinery (e.g.
+/// access violations or process termination) do not produce a report.
+pub fn set_crash_report_path(path: impl Into<PathBuf>) {
+    *CRASH_REPORT_PATH.lock().expect(constants::POISONED_LOCK) = Some(path.into());
+
+    install_panic_hook();
+}
+
+/// Installs the panic hook that captures the state of the runtime to wherever the process has been
+/// configured to write it - the crash report, the flight records and, with the
+/// `metrics-persistence` feature, the persisted metrics report. Installed once per process,
+/// chaining to any previously installed hook.
+pub(crate) fn install_panic_hook() {
+    INSTALL_HOOK.call_once(|| {
+        let previous_hook = panic::take_hook();
+
+        panic::set_hook(Box::new(move |info| {
+            write_crash_report(info);
+            flight_recorder::dump_on_panic(info);
+
+            #[cfg(feature = "metrics-persistence")]
+            super::persistence::persist_on_panic();
+
+            previous_hook(info);
+        }));
+    });
+}
+
+fn write_crash_report(info: &PanicHookInfo<'_>) {
+    // We must not panic in here, as a panic inside a panic hook aborts the process. Everything is
+    // best-effort - if we cannot write the report, we just move on.
+    let Ok(path) = CRASH_REPORT_PATH.try_lock() else {
+        return;
+    };
+
+    let Some(path) = path.as_ref() else {
+        return;
+    };
+
+    // The metrics may be unavailable if the panic happened while the metrics registry was being
+    // modified or if the thread-local storage is already being torn down.
+    let Some(page) = try_report_page() else {
+        return;
+    };
+
+    let mut report_builder = ReportBuilder::new();
+    report_builder.add_page(page);
+    let report = report_builder.build();
+
+    let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) else {
+        return;
+    };
+
+    let timestamp = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|x| x.as_secs())
+        .unwrap_or_default();
+
+    let task = try_describe_current_task().unwrap_or_else(|| "<no task>".to_string());
+
+    _ = writeln!(
+        file,
+        "=== crash report: thread '{}' panicked in {} at unix time {} ===\n{}\n\n{}",
+        thread::current().name().unwrap_or("<unnamed>"),
+        task,
+        timestamp,
+        info,
+        report
+    );
+    _ = file.flush();
+}
+
+static CRASH_REPORT_PATH: Mutex<Option<PathBuf>> = Mutex::new(None);
+static INSTALL_HOOK: Once = Once::new();
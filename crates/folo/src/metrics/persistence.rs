@@ -0,0 +1,303 @@
+use crate::{
+    constants,
+    metrics::{
+        crash_report::install_panic_hook, report_page, try_report_page, Report, ReportBuilder,
+        ReportPage,
+    },
+};
+use std::{
+    cell::Cell,
+    collections::HashMap,
+    ffi::OsString,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Condvar, Mutex, Once,
+    },
+    thread::{self, ThreadId},
+    time::{Duration, Instant},
+};
+use tracing::{event, Level};
+
+/// Configures the process to periodically write the latest metrics of the runtime worker threads,
+/// merged into a single [`Report`] in JSON format, to the specified file. The last known state of
+/// the metrics thereby survives the process and can be inspected after a crash.
+///
+/// Each worker thread contributes its metrics at most once per interval - async workers at their
+/// maintenance ticks, synchronous workers after executing tasks - and the file is rewritten at most
+/// once per interval. Intervals shorter than the maintenance interval of the runtime are therefore
+/// not meaningful. A final report is written when each worker thread shuts down and when any
+/// thread panics, including the metrics of the panicking thread up to the moment of the panic.
+///
+/// The file is replaced atomically - it is written under a temporary name next to the target and
+/// then renamed - so readers never see a partially written report. The periodic reports are written
+/// by a dedicated background thread, so worker threads never block on the file system, except for
+/// waiting for their final report to be written when they shut down.
+///
+/// The panic hook is shared with [`set_crash_report_path()`][super::set_crash_report_path], is
+/// installed once per process and chains to any previously installed hook. Calling this again
+/// merely changes the target path and interval.
+pub fn set_metrics_persistence(path: impl Into<PathBuf>, interval: Duration) {
+    let mut state = STATE.lock().expect(constants::POISONED_LOCK);
+
+    match state.as_mut() {
+        Some(state) => state.path = path.into(),
+        None => {
+            *state = Some(PersistenceState {
+                path: path.into(),
+                pages: HashMap::new(),
+                written_at: None,
+            })
+        }
+    }
+
+    // Zero means disabled, so we persist at least once per millisecond.
+    INTERVAL_MILLIS.store((interval.as_millis() as u64).max(1), Ordering::Relaxed);
+
+    START_WRITER.call_once(|| {
+        let result = thread::Builder::new()
+            .name("folo-metrics-persistence".to_string())
+            .spawn(run_writer);
+
+        match result {
+            Ok(_) => WRITER_RUNNING.store(true, Ordering::Relaxed),
+            Err(e) => event!(
+                Level::WARN,
+                message = "failed to start metrics persistence thread; writing reports inline",
+                error = %e
+            ),
+        }
+    });
+
+    install_panic_hook();
+}
+
+/// Contributes the metrics of the current thread to the persisted report and rewrites the file if
+/// an interval has passed since it was last written. Does nothing if persistence is not enabled or
+/// if the current thread has contributed within the last interval.
+pub(crate) fn persist_if_due() {
+    let Some(interval) = interval() else {
+        return;
+    };
+
+    let now = Instant::now();
+
+    if PUBLISHED_AT
+        .get()
+        .is_some_and(|published_at| now < published_at + interval)
+    {
+        return;
+    }
+
+    PUBLISHED_AT.set(Some(now));
+
+    let page = report_page();
+
+    let (path, report) = {
+        let mut state = STATE.lock().expect(constants::POISONED_LOCK);
+        let Some(state) = state.as_mut() else {
+            return;
+        };
+
+        state.pages.insert(thread::current().id(), page);
+
+        if state
+            .written_at
+            .is_some_and(|written_at| now < written_at + interval)
+        {
+            return;
+        }
+
+        state.written_at = Some(now);
+        (state.path.clone(), state.report())
+    };
+
+    submit(path, report);
+}
+
+/// Contributes the final metrics of the current thread to the persisted report and rewrites the
+/// file, waiting for the report to be written. Called by worker threads when they shut down.
+pub(crate) fn persist_final() {
+    if interval().is_none() {
+        return;
+    }
+
+    let page = report_page();
+
+    let (path, report) = {
+        let mut state = STATE.lock().expect(constants::POISONED_LOCK);
+        let Some(state) = state.as_mut() else {
+            return;
+        };
+
+        state.pages.insert(thread::current().id(), page);
+        state.written_at = Some(Instant::now());
+        (state.path.clone(), state.report())
+    };
+
+    let generation = submit(path, report);
+    wait_written(generation);
+}
+
+/// Contributes the metrics of the panicking thread to the persisted report and rewrites the file
+/// directly from the panicking thread, as the process may not survive long enough for the
+/// background thread to do it. Called from the panic hook.
+pub(crate) fn persist_on_panic() {
+    // We must not panic in here, as a panic inside a panic hook aborts the process. Everything is
+    // best-effort - if we cannot write the report, we just move on.
+    if interval().is_none() {
+        return;
+    }
+
+    let Ok(mut state) = STATE.try_lock() else {
+        return;
+    };
+
+    let Some(state) = state.as_mut() else {
+        return;
+    };
+
+    // The metrics of the current thread may be unavailable if the panic happened while they were
+    // being modified, in which case the last contribution of the thread will have to do.
+    if let Some(page) = try_report_page() {
+        state.pages.insert(thread::current().id(), page);
+    }
+
+    // A different temporary file than the background thread uses, in case it is busy writing.
+    _ = write_atomically(&state.path, &state.report(), ".panic.tmp");
+}
+
+fn interval() -> Option<Duration> {
+    match INTERVAL_MILLIS.load(Ordering::Relaxed) {
+        0 => None,
+        millis => Some(Duration::from_millis(millis)),
+    }
+}
+
+struct PersistenceState {
+    path: PathBuf,
+
+    // The latest contribution of every thread, including threads that have since exited, so the
+    // report describes everything the process has done.
+    pages: HashMap<ThreadId, ReportPage>,
+
+    written_at: Option<Instant>,
+}
+
+impl PersistenceState {
+    fn report(&self) -> Report {
+        let mut report_builder = ReportBuilder::new();
+
+        for page in self.pages.values() {
+            report_builder.add_page(page.clone());
+        }
+
+        report_builder.build()
+    }
+}
+
+/// Hands a report to the background thread for writing, replacing any report that it has not yet
+/// started writing - only the latest one matters. Returns the generation of the report, for
+/// `wait_written()`.
+fn submit(path: PathBuf, report: Report) -> u64 {
+    if !WRITER_RUNNING.load(Ordering::Relaxed) {
+        write_and_log(&path, &report);
+        return 0;
+    }
+
+    let mut writer = WRITER.lock().expect(constants::POISONED_LOCK);
+
+    writer.next = Some((path, report));
+    writer.submitted += 1;
+    WRITER_CHANGED.notify_all();
+
+    writer.submitted
+}
+
+/// Waits until the report of the specified generation (or a later one) has been written, for at
+/// most `FINAL_WRITE_TIMEOUT` so a stuck file system cannot block shutdown forever.
+fn wait_written(generation: u64) {
+    let writer = WRITER.lock().expect(constants::POISONED_LOCK);
+
+    _ = WRITER_CHANGED
+        .wait_timeout_while(writer, FINAL_WRITE_TIMEOUT, |writer| {
+            writer.written < generation
+        })
+        .expect(constants::POISONED_LOCK);
+}
+
+fn run_writer() {
+    let mut writer = WRITER.lock().expect(constants::POISONED_LOCK);
+
+    loop {
+        writer = WRITER_CHANGED
+            .wait_while(writer, |writer| writer.next.is_none())
+            .expect(constants::POISONED_LOCK);
+
+        let (path, report) = writer.next.take().expect("we just waited for a report");
+        let generation = writer.submitted;
+        drop(writer);
+
+        write_and_log(&path, &report);
+
+        writer = WRITER.lock().expect(constants::POISONED_LOCK);
+        writer.written = generation;
+        WRITER_CHANGED.notify_all();
+    }
+}
+
+fn write_and_log(path: &Path, report: &Report) {
+    if let Err(e) = write_atomically(path, report, ".tmp") {
+        event!(
+            Level::WARN,
+            message = "failed to persist metrics report",
+            path = %path.display(),
+            error = %e
+        );
+    }
+}
+
+fn write_atomically(path: &Path, report: &Report, temp_suffix: &str) -> io::Result<()> {
+    let json = serde_json::to_vec_pretty(report).map_err(io::Error::other)?;
+
+    let mut temp_name = path.file_name().map(OsString::from).unwrap_or_default();
+    temp_name.push(temp_suffix);
+    let temp_path = path.with_file_name(temp_name);
+
+    let mut file = fs::File::create(&temp_path)?;
+    file.write_all(&json)?;
+    file.sync_all()?;
+    drop(file);
+
+    fs::rename(&temp_path, path)
+}
+
+/// The report waiting to be written by the background thread.
+struct WriterState {
+    next: Option<(PathBuf, Report)>,
+
+    // Generations of the latest submitted report and of the latest written (or skipped) one.
+    submitted: u64,
+    written: u64,
+}
+
+/// How long a worker thread that is shutting down waits for its final report to be written.
+const FINAL_WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+
+static STATE: Mutex<Option<PersistenceState>> = Mutex::new(None);
+static INTERVAL_MILLIS: AtomicU64 = AtomicU64::new(0);
+
+static WRITER: Mutex<WriterState> = Mutex::new(WriterState {
+    next: None,
+    submitted: 0,
+    written: 0,
+});
+static WRITER_CHANGED: Condvar = Condvar::new();
+static WRITER_RUNNING: AtomicBool = AtomicBool::new(false);
+static START_WRITER: Once = Once::new();
+
+thread_local! {
+    static PUBLISHED_AT: Cell<Option<Instant>> = const { Cell::new(None) };
+}
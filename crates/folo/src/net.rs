@@ -1,11 +1,42 @@
+mod async_socket;
+mod byte_stream;
+mod connection_limit;
 mod http_context;
 mod http_server;
+mod incoming;
+mod raw_socket;
+mod registered_tcp_stream;
 pub(crate) mod http_sys;
+pub(crate) mod socket_addr;
+mod stream_socket;
 mod tcp_connection;
+mod tcp_connector;
+mod tcp_listener;
 mod tcp_server;
+mod tcp_stream;
+mod udp_batch;
+mod udp_message;
+mod udp_socket;
+mod unix_listener;
+mod unix_stream;
+pub mod windows;
 pub(crate) mod winsock;
 
+pub use async_socket::*;
+pub use byte_stream::*;
+pub use connection_limit::*;
 pub use http_context::*;
 pub use http_server::*;
+pub use incoming::*;
+pub use raw_socket::*;
+pub use registered_tcp_stream::*;
 pub use tcp_connection::*;
+pub use tcp_connector::*;
+pub use tcp_listener::*;
 pub use tcp_server::*;
+pub use tcp_stream::*;
+pub use udp_batch::*;
+pub use udp_message::*;
+pub use udp_socket::*;
+pub use unix_listener::*;
+pub use unix_stream::*;
@@ -0,0 +1,226 @@
+use crate::{
+    io::{self, Buffer},
+    mem::isolation::Isolated,
+    net::{udp_socket::is_truncation, winsock},
+    rt::current_async_agent,
+    windows::OwnedHandle,
+};
+use negative_impl::negative_impl;
+use std::rc::Rc;
+use tracing::{event, Level};
+use windows::{
+    core::PSTR,
+    Win32::Networking::WinSock::{
+        ioctlsocket, WSARecv, WSASend, FIONBIO, MSG_PEEK, SOCKET, WSABUF, WSAEMSGSIZE,
+        WSA_IO_PENDING,
+    },
+};
+
+/// A socket owned by a third-party library that performs its own non-blocking I/O on it, with
+/// Folo telling the library when the socket is ready for that I/O. This allows libraries built
+/// around readiness loops (e.g. DNS resolvers or protocol implementations written for `poll()`) to
+/// be driven from an async worker thread.
+///
+/// The socket is put into non-blocking mode, so the calls of the library fail with
+/// [`WouldBlock`][std::io::ErrorKind::WouldBlock] instead of blocking the worker thread. Use
+/// [`read_with()`][Self::read_with] and [`write_with()`][Self::write_with] to retry such calls
+/// once the socket is ready, or [`readable()`][Self::readable] and [`writable()`][Self::writable]
+/// to wait for readiness directly.
+///
+/// Readiness is detected via zero-byte overlapped operations, which complete once the socket has
+/// data to receive or room to send without transferring any data. Readiness is therefore a hint -
+/// another caller may consume the data first, in which case the call of the library fails with
+/// `WouldBlock` again and the wait starts over.
+///
+/// Only connected stream sockets and datagram sockets are supported. Listening sockets never report
+/// themselves as readable.
+///
+/// # Example
+///
+/// ```no_run
+/// use folo::net::AsyncSocket;
+/// use windows::Win32::Networking::WinSock::{recv, SEND_RECV_FLAGS, SOCKET};
+///
+/// # fn create_library_socket() -> SOCKET { unimplemented!() }
+/// #[folo::main]
+/// async fn main() {
+///     // A connected socket created with WSA_FLAG_OVERLAPPED by some library.
+///     let socket = unsafe { AsyncSocket::from_raw_socket(create_library_socket()) }.unwrap();
+///
+///     let mut data = [0; 1024];
+///
+///     let len = socket
+///         .read_with(|socket| {
+///             // SAFETY: The socket is valid and the buffer is writable.
+///             match unsafe { recv(socket, &mut data, SEND_RECV_FLAGS::default()) } {
+///                 len if len >= 0 => Ok(len as usize),
+///                 _ => Err(std::io::Error::last_os_error()),
+///             }
+///         })
+///         .await
+///         .unwrap();
+///
+///     println!("received {len} bytes");
+/// }
+/// ```
+#[derive(Debug)]
+pub struct AsyncSocket {
+    socket: Rc<OwnedHandle<SOCKET>>,
+}
+
+impl AsyncSocket {
+    /// Takes ownership of a socket created outside Folo, switches it to non-blocking mode and
+    /// binds it to the I/O driver of the current async worker thread. The socket is closed when
+    /// the `AsyncSocket` is dropped, including when this fails.
+    ///
+    /// # Safety
+    ///
+    /// The socket must be a valid socket created with `WSA_FLAG_OVERLAPPED`, which the caller owns
+    /// and does not close after this call. It must not already be associated with an I/O
+    /// completion port.
+    pub unsafe fn from_raw_socket(socket: SOCKET) -> io::Result<Self> {
+        winsock::ensure_initialized();
+
+        let socket = Rc::new(OwnedHandle::new(socket));
+
+        let mut non_blocking: u32 = 1;
+        winsock::to_io_result(ioctlsocket(**socket, FIONBIO, &mut non_blocking))?;
+
+        current_async_agent::with_io(|io| io.bind_io_primitive(&**socket))?;
+
+        event!(
+            Level::TRACE,
+            message = "socket adopted for readiness-based I/O"
+        );
+
+        Ok(Self { socket })
+    }
+
+    /// The underlying socket, for use by the library that performs I/O on it.
+    pub fn as_raw_socket(&self) -> SOCKET {
+        **self.socket
+    }
+
+    /// Waits until the socket has data to receive (or the peer has closed the connection).
+    pub async fn readable(&self) -> io::Result<()> {
+        wait_until_ready(Rc::clone(&self.socket), Direction::Read).await
+    }
+
+    /// Waits until the socket can accept more data to send.
+    pub async fn writable(&self) -> io::Result<()> {
+        wait_until_ready(Rc::clone(&self.socket), Direction::Write).await
+    }
+
+    /// Calls `f` with the socket until it completes without failing with
+    /// [`WouldBlock`][std::io::ErrorKind::WouldBlock], waiting for the socket to become readable
+    /// before every retry.
+    pub async fn read_with<F, R>(&self, f: F) -> io::Result<R>
+    where
+        F: FnMut(SOCKET) -> std::io::Result<R>,
+    {
+        self.io_with(Direction::Read, f).await
+    }
+
+    /// Calls `f` with the socket until it completes without failing with
+    /// [`WouldBlock`][std::io::ErrorKind::WouldBlock], waiting for the socket to become writable
+    /// before every retry.
+    pub async fn write_with<F, R>(&self, f: F) -> io::Result<R>
+    where
+        F: FnMut(SOCKET) -> std::io::Result<R>,
+    {
+        self.io_with(Direction::Write, f).await
+    }
+
+    async fn io_with<F, R>(&self, direction: Direction, mut f: F) -> io::Result<R>
+    where
+        F: FnMut(SOCKET) -> std::io::Result<R>,
+    {
+        loop {
+            match f(**self.socket) {
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                    wait_until_ready(Rc::clone(&self.socket), direction).await?;
+                }
+                result => return Ok(result?),
+            }
+        }
+    }
+}
+
+#[negative_impl]
+impl !Send for AsyncSocket {}
+#[negative_impl]
+impl !Sync for AsyncSocket {}
+
+#[derive(Clone, Copy, Debug)]
+enum Direction {
+    Read,
+    Write,
+}
+
+async fn wait_until_ready(socket: Rc<OwnedHandle<SOCKET>>, direction: Direction) -> io::Result<()> {
+    let mut buffer = Buffer::<Isolated>::from_pool();
+    buffer.set_len(0);
+
+    let mut operation = current_async_agent::with_io(|io| io.new_operation(buffer));
+    operation.set_primitive(**socket);
+
+    // SAFETY: We are required to pass the OVERLAPPED pointer to the completion routine. We do.
+    let result = unsafe {
+        operation.begin(move |buffer, overlapped, immediate_bytes_transferred| {
+            let wsabufs = [WSABUF {
+                len: 0,
+                buf: PSTR::from_raw(buffer.as_mut_ptr()),
+            }];
+
+            match direction {
+                Direction::Read => {
+                    // Peeking ensures a datagram is not consumed by the zero-byte receive.
+                    let mut flags = MSG_PEEK.0 as u32;
+
+                    match winsock::to_io_result(WSARecv(
+                        **socket,
+                        &wsabufs,
+                        Some(immediate_bytes_transferred as *mut u32),
+                        &mut flags as *mut u32,
+                        Some(overlapped),
+                        None,
+                    )) {
+                        // A datagram that does not fit into zero bytes is reported as truncated,
+                        // which still posts a completion notification. We must wait for it as if
+                        // the operation were pending, as the OS still owns the operation.
+                        Err(io::Error::Winsock { code, detail }) if detail == WSAEMSGSIZE => {
+                            Err(io::Error::Winsock {
+                                code,
+                                detail: WSA_IO_PENDING,
+                            })
+                        }
+                        result => result,
+                    }
+                }
+                Direction::Write => winsock::to_io_result(WSASend(
+                    **socket,
+                    &wsabufs,
+                    Some(immediate_bytes_transferred as *mut u32),
+                    0,
+                    Some(overlapped),
+                    None,
+                )),
+            }
+        })
+    }
+    .await;
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => {
+            let (error, _) = e.into_inner_and_buffer();
+
+            // A pending datagram was "truncated" to zero bytes, meaning there is one to receive.
+            if is_truncation(&error) {
+                Ok(())
+            } else {
+                Err(error)
+            }
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use crate::{
+    io::{self, Buffer, OperationResultFuture},
+    mem::isolation::Isolated,
+    net::{
+        tcp_connection::socket_shutdown, RegisteredTcpStream, ShutdownFuture, TcpConnection,
+        TcpStream, UnixStream,
+    },
+};
+use futures::{future::LocalBoxFuture, FutureExt};
+use std::{net::Shutdown, sync::Arc};
+
+/// A connected stream of bytes that transfers data via buffers owned by the I/O driver.
+///
+/// This allows protocol layers (e.g. TLS) to be written once and used on top of any type of
+/// stream. The semantics of the methods match the equally named methods of the implementing types.
+pub trait ByteStream {
+    /// Reads the next buffer of data from the stream. A buffer with a length of 0 indicates that
+    /// the peer has closed the stream.
+    fn read(&mut self, buffer: Buffer<Isolated>) -> OperationResultFuture;
+
+    /// Writes the active region of the buffer to the stream.
+    fn write(&mut self, buffer: Buffer<Isolated>) -> OperationResultFuture;
+
+    /// Performs a graceful shutdown of the stream.
+    fn shutdown(&mut self) -> ShutdownFuture;
+
+    /// Shuts down the write direction of the stream without waiting for the peer, which sees it
+    /// as the end of the stream. Reading from the stream remains possible.
+    ///
+    /// The returned future does not borrow the stream, so reads may be started while it is pending.
+    fn shutdown_write(&mut self) -> LocalBoxFuture<'static, io::Result<()>>;
+}
+
+impl ByteStream for TcpConnection {
+    fn read(&mut self, buffer: Buffer<Isolated>) -> OperationResultFuture {
+        self.receive(buffer)
+    }
+
+    fn write(&mut self, buffer: Buffer<Isolated>) -> OperationResultFuture {
+        self.send(buffer)
+    }
+
+    fn shutdown(&mut self) -> ShutdownFuture {
+        TcpConnection::shutdown(self)
+    }
+
+    fn shutdown_write(&mut self) -> LocalBoxFuture<'static, io::Result<()>> {
+        socket_shutdown(Arc::clone(&self.socket), Shutdown::Write).boxed_local()
+    }
+}
+
+impl ByteStream for RegisteredTcpStream {
+    fn read(&mut self, buffer: Buffer<Isolated>) -> OperationResultFuture {
+        RegisteredTcpStream::read(self, buffer)
+    }
+
+    fn write(&mut self, buffer: Buffer<Isolated>) -> OperationResultFuture {
+        RegisteredTcpStream::write(self, buffer)
+    }
+
+    fn shutdown(&mut self) -> ShutdownFuture {
+        RegisteredTcpStream::shutdown(self)
+    }
+
+    fn shutdown_write(&mut self) -> LocalBoxFuture<'static, io::Result<()>> {
+        socket_shutdown(Arc::clone(&self.get_ref().socket), Shutdown::Write).boxed_local()
+    }
+}
+
+impl ByteStream for TcpStream {
+    fn read(&mut self, buffer: Buffer<Isolated>) -> OperationResultFuture {
+        TcpStream::read(self, buffer)
+    }
+
+    fn write(&mut self, buffer: Buffer<Isolated>) -> OperationResultFuture {
+        TcpStream::write(self, buffer)
+    }
+
+    fn shutdown(&mut self) -> ShutdownFuture {
+        TcpStream::shutdown(self)
+    }
+
+    fn shutdown_write(&mut self) -> LocalBoxFuture<'static, io::Result<()>> {
+        socket_shutdown(Arc::clone(&self.socket), Shutdown::Write).boxed_local()
+    }
+}
+
+impl ByteStream for UnixStream {
+    fn read(&mut self, buffer: Buffer<Isolated>) -> OperationResultFuture {
+        UnixStream::read(self, buffer)
+    }
+
+    fn write(&mut self, buffer: Buffer<Isolated>) -> OperationResultFuture {
+        UnixStream::write(self, buffer)
+    }
+
+    fn shutdown(&mut self) -> ShutdownFuture {
+        UnixStream::shutdown(self)
+    }
+
+    fn shutdown_write(&mut self) -> LocalBoxFuture<'static, io::Result<()>> {
+        socket_shutdown(Arc::clone(&self.socket), Shutdown::Write).boxed_local()
+    }
+}
@@ -0,0 +1,221 @@
+use crate::{
+    constants::POISONED_LOCK,
+    metrics::{observe, Event, EventBuilder, Magnitude},
+};
+use std::{
+    cell::RefCell,
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll, Waker},
+};
+
+/// Limits the number of connections that are open at the same time.
+///
+/// Assign a limit to a listener via
+/// [`TcpListener::with_connection_limit()`][crate::net::TcpListener::with_connection_limit] or to
+/// all the listeners of a runtime via
+/// [`RuntimeBuilder::max_connections()`][crate::rt::RuntimeBuilder::max_connections]. When the
+/// limit is reached, the listener stops accepting connections until some of the open connections
+/// are closed. Meanwhile, incoming connections wait in the backlog of the listening socket (and
+/// are refused by the operating system once the backlog is full), so an overloaded process sheds
+/// load before it runs out of resources.
+///
+/// Clones share the same limit and count, so one limit can be assigned to multiple listeners,
+/// including listeners owned by different async worker threads.
+#[derive(Clone, Debug)]
+pub struct ConnectionLimit {
+    inner: Arc<ConnectionLimitInner>,
+}
+
+#[derive(Debug)]
+struct ConnectionLimitInner {
+    limit: usize,
+    current: AtomicUsize,
+    peak: AtomicUsize,
+
+    // Accepts waiting for a connection to be closed. Each closed connection wakes one of them.
+    waiting: Mutex<Vec<Waker>>,
+}
+
+impl ConnectionLimit {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            inner: Arc::new(ConnectionLimitInner {
+                limit,
+                current: AtomicUsize::new(0),
+                peak: AtomicUsize::new(0),
+                waiting: Mutex::new(Vec::new()),
+            }),
+        }
+    }
+
+    /// The maximum number of connections that may be open at the same time.
+    pub fn limit(&self) -> usize {
+        self.inner.limit
+    }
+
+    /// The number of connections currently open.
+    pub fn current(&self) -> usize {
+        self.inner.current.load(Ordering::Relaxed)
+    }
+
+    /// The highest number of connections that have been open at the same time.
+    pub fn peak(&self) -> usize {
+        self.inner.peak.load(Ordering::Relaxed)
+    }
+
+    /// Waits until there is room for one more connection and counts it against the limit. The
+    /// connection is counted until the returned permit is dropped.
+    pub(crate) fn acquire(&self) -> AcquireConnection {
+        AcquireConnection {
+            limit: self.clone(),
+            paused: false,
+        }
+    }
+
+    fn try_acquire(&self) -> Option<ConnectionPermit> {
+        let current = self
+            .inner
+            .current
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |current| {
+                (current < self.inner.limit).then_some(current + 1)
+            })
+            .ok()?
+            + 1;
+
+        self.inner.peak.fetch_max(current, Ordering::Relaxed);
+        observe!(OPEN_CONNECTIONS.observe(current as Magnitude));
+
+        Some(ConnectionPermit {
+            limit: self.clone(),
+        })
+    }
+
+    fn release(&self) {
+        self.inner.current.fetch_sub(1, Ordering::Relaxed);
+
+        let waker = self.inner.waiting.lock().expect(POISONED_LOCK).pop();
+
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+/// Waits for room for one more connection under a [`ConnectionLimit`].
+#[derive(Debug)]
+pub(crate) struct AcquireConnection {
+    limit: ConnectionLimit,
+
+    // Whether we have already reported that the accept was paused, to count each pause once.
+    paused: bool,
+}
+
+impl Future for AcquireConnection {
+    type Output = ConnectionPermit;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if let Some(permit) = self.limit.try_acquire() {
+            return Poll::Ready(permit);
+        }
+
+        self.limit
+            .inner
+            .waiting
+            .lock()
+            .expect(POISONED_LOCK)
+            .push(cx.waker().clone());
+
+        // A connection may have been closed between our attempt and the registration of the
+        // waker, in which case nobody would wake us up, so we need to try again.
+        if let Some(permit) = self.limit.try_acquire() {
+            return Poll::Ready(permit);
+        }
+
+        if !self.paused {
+            self.paused = true;
+            observe!(ACCEPTS_PAUSED.observe_unit());
+        }
+
+        Poll::Pending
+    }
+}
+
+/// Counts one open connection against a [`ConnectionLimit`], until dropped.
+#[derive(Debug)]
+pub(crate) struct ConnectionPermit {
+    limit: ConnectionLimit,
+}
+
+impl Drop for ConnectionPermit {
+    fn drop(&mut self) {
+        self.limit.release();
+    }
+}
+
+/// Sets the limit shared by all the listeners of the runtime that owns the current thread.
+pub(crate) fn set_runtime_connection_limit(value: Option<ConnectionLimit>) {
+    RUNTIME_LIMIT.with_borrow_mut(|x| *x = value);
+}
+
+/// The limit on connections accepted by all the listeners of the runtime that owns the current
+/// thread, if one has been set via
+/// [`RuntimeBuilder::max_connections()`][crate::rt::RuntimeBuilder::max_connections].
+pub fn runtime_connection_limit() -> Option<ConnectionLimit> {
+    RUNTIME_LIMIT.with_borrow(Clone::clone)
+}
+
+thread_local! {
+    static RUNTIME_LIMIT: RefCell<Option<ConnectionLimit>> = const { RefCell::new(None) };
+
+    static OPEN_CONNECTIONS: Event = EventBuilder::new("net_limited_connections_open")
+        .buckets(OPEN_CONNECTIONS_BUCKETS)
+        .build();
+
+    static ACCEPTS_PAUSED: Event = EventBuilder::new("net_accepts_paused")
+        .build();
+}
+
+const OPEN_CONNECTIONS_BUCKETS: &[Magnitude] = &[0, 10, 100, 1000, 10000, 100000];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{executor::block_on, task::noop_waker_ref};
+    use std::pin::pin;
+
+    #[test]
+    fn counts_current_and_peak() {
+        let limit = ConnectionLimit::new(2);
+
+        let first = block_on(limit.acquire());
+        let second = block_on(limit.acquire());
+        assert_eq!(limit.current(), 2);
+        assert_eq!(limit.peak(), 2);
+
+        drop(first);
+        assert_eq!(limit.current(), 1);
+        assert_eq!(limit.peak(), 2);
+
+        drop(second);
+        assert_eq!(limit.current(), 0);
+    }
+
+    #[test]
+    fn waits_for_room() {
+        let limit = ConnectionLimit::new(1);
+        let permit = block_on(limit.acquire());
+
+        let mut cx = Context::from_waker(noop_waker_ref());
+        let mut acquire = pin!(limit.acquire());
+        assert!(acquire.as_mut().poll(&mut cx).is_pending());
+
+        drop(permit);
+        assert!(acquire.as_mut().poll(&mut cx).is_ready());
+        assert_eq!(limit.current(), 1);
+    }
+}
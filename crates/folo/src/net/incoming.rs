@@ -0,0 +1,99 @@
+use crate::io;
+use futures::{future::LocalBoxFuture, stream::FusedStream, FutureExt, Stream};
+use negative_impl::negative_impl;
+use std::{
+    fmt::{self, Debug, Formatter},
+    future::Future,
+    pin::Pin,
+    task::{self, ready},
+};
+
+/// An endless stream of incoming connections, accepting one connection at a time. Obtained via
+/// `incoming()` on a listener, e.g. [`TcpListener::incoming()`][super::TcpListener::incoming].
+///
+/// A failed accept is yielded as an error without ending the stream, so the caller decides whether
+/// to stop (e.g. via `take_while()`) or carry on with the next connection. Dropping the stream
+/// cancels the pending accept, if any.
+///
+/// # Example
+///
+/// ```no_run
+/// use folo::net::TcpListener;
+/// use folo::rt::spawn;
+/// use futures::StreamExt;
+/// use std::net::SocketAddr;
+///
+/// #[folo::main]
+/// async fn main() {
+///     let listener = TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 1234)))
+///         .await
+///         .unwrap();
+///
+///     // Stop accepting after 10 connections.
+///     let mut incoming = listener.incoming().take(10);
+///
+///     while let Some(connection) = incoming.next().await {
+///         let (mut stream, _) = connection.unwrap();
+///
+///         spawn(async move {
+///             stream.shutdown().await.unwrap();
+///         });
+///     }
+/// }
+/// ```
+pub struct Incoming<'a, T> {
+    accept: Box<dyn FnMut() -> LocalBoxFuture<'a, io::Result<T>> + 'a>,
+
+    // The accept operation we are currently waiting for, if the stream has been polled since the
+    // last connection was yielded.
+    pending: Option<LocalBoxFuture<'a, io::Result<T>>>,
+}
+
+impl<'a, T> Incoming<'a, T> {
+    pub(crate) fn new<F>(mut accept: impl FnMut() -> F + 'a) -> Self
+    where
+        F: Future<Output = io::Result<T>> + 'a,
+    {
+        Self {
+            accept: Box::new(move || accept().boxed_local()),
+            pending: None,
+        }
+    }
+}
+
+impl<T> Stream for Incoming<'_, T> {
+    type Item = io::Result<T>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+    ) -> task::Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        let pending = this.pending.get_or_insert_with(|| (this.accept)());
+        let result = ready!(pending.poll_unpin(cx));
+
+        this.pending = None;
+
+        task::Poll::Ready(Some(result))
+    }
+}
+
+impl<T> FusedStream for Incoming<'_, T> {
+    fn is_terminated(&self) -> bool {
+        false
+    }
+}
+
+impl<T> Debug for Incoming<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Incoming")
+            .field("pending", &self.pending.is_some())
+            .finish()
+    }
+}
+
+#[negative_impl]
+impl<T> !Send for Incoming<'_, T> {}
+#[negative_impl]
+impl<T> !Sync for Incoming<'_, T> {}
@@ -0,0 +1,221 @@
+use crate::{
+    io::{self, Buffer, OperationResultFuture},
+    mem::isolation::Isolated,
+    net::{
+        socket_addr::{self, NativeSocketAddr},
+        udp_socket::{receive_datagram, send_datagram},
+        winsock, ReceivedDatagram, UdpSocket,
+    },
+    rt::{current_async_agent, spawn_sync, SynchronousTaskType},
+    windows::OwnedHandle,
+};
+use negative_impl::negative_impl;
+use std::{net::SocketAddr, rc::Rc};
+use tracing::{event, Level};
+use windows::{
+    core::PSTR,
+    Win32::Networking::WinSock::{
+        bind, connect, getsockopt, setsockopt, WSASocketA, MSG_PEEK, SEND_RECV_FLAGS, SOCKET,
+        WSA_FLAG_OVERLAPPED,
+    },
+};
+
+/// A message-oriented socket of an arbitrary address family, type and protocol (e.g. ICMP via
+/// `SOCK_RAW`, or a vendor protocol), owned by the async worker thread that created it.
+///
+/// Every operation transfers one message (e.g. one packet), just like with a [`UdpSocket`], whose
+/// buffer conventions apply here as well. The socket type and protocol determine what a message
+/// contains - for example, a message received from a raw IPv4 socket starts with the IP header.
+///
+/// Raw sockets (`SOCK_RAW`) require the process to run with administrative privileges.
+///
+/// # Example
+///
+/// ```no_run
+/// use folo::io::{Buffer, OperationResultExt};
+/// use folo::mem::isolation::Isolated;
+/// use folo::net::RawSocket;
+/// use std::net::SocketAddr;
+///
+/// // Values of AF_INET, SOCK_RAW and IPPROTO_ICMP.
+/// const AF_INET: i32 = 2;
+/// const SOCK_RAW: i32 = 3;
+/// const IPPROTO_ICMP: i32 = 1;
+///
+/// #[folo::main]
+/// async fn main() {
+///     let socket = RawSocket::new(AF_INET, SOCK_RAW, IPPROTO_ICMP).await.unwrap();
+///     socket.bind(SocketAddr::from(([0, 0, 0, 0], 0))).unwrap();
+///
+///     // An ICMP echo request with identifier 1 and sequence number 1.
+///     let request = [8, 0, 0xf7, 0xfd, 0, 1, 0, 1];
+///
+///     let mut buffer = Buffer::<Isolated>::from_pool();
+///     buffer.as_mut_slice()[..request.len()].copy_from_slice(&request);
+///     buffer.set_len(request.len());
+///
+///     socket
+///         .send_to(buffer, SocketAddr::from(([127, 0, 0, 1], 0)))
+///         .await
+///         .into_inner()
+///         .unwrap();
+///
+///     // The reply includes the IP header, followed by the ICMP echo reply.
+///     let reply = socket
+///         .recv_from(Buffer::<Isolated>::from_pool())
+///         .await
+///         .unwrap();
+///
+///     println!("received {} bytes from {}", reply.buffer().len(), reply.peer_addr());
+/// }
+/// ```
+#[derive(Debug)]
+pub struct RawSocket {
+    socket: Rc<OwnedHandle<SOCKET>>,
+}
+
+impl RawSocket {
+    /// The number of bytes at the end of the active region of a receive buffer that are reserved
+    /// for the address of the sender. The rest of the active region receives the message.
+    pub const RECEIVE_ADDRESS_SPACE: usize = UdpSocket::RECEIVE_ADDRESS_SPACE;
+
+    /// Creates a socket with the specified address family (e.g. `AF_INET`), type (e.g.
+    /// `SOCK_RAW`) and protocol (e.g. `IPPROTO_ICMP`), as defined by Winsock.
+    ///
+    /// The socket type must be message-oriented (e.g. `SOCK_RAW` or `SOCK_DGRAM`). Use
+    /// [`TcpStream`][super::TcpStream] and friends for stream-oriented sockets.
+    pub async fn new(domain: i32, kind: i32, protocol: i32) -> io::Result<Self> {
+        winsock::ensure_initialized();
+
+        // Creating the socket is an expensive synchronous operation, so do it on a synchronous
+        // worker thread.
+        let socket = spawn_sync(SynchronousTaskType::Syscall, move || -> io::Result<_> {
+            // SAFETY: All we need to worry about here is cleanup, which we do via OwnedHandle.
+            Ok(unsafe {
+                OwnedHandle::new(WSASocketA(
+                    domain,
+                    kind,
+                    protocol,
+                    None,
+                    0,
+                    WSA_FLAG_OVERLAPPED,
+                )?)
+            })
+        })
+        .await?;
+
+        let socket = Rc::new(socket);
+
+        current_async_agent::with_io(|io| io.bind_io_primitive(&**socket))?;
+
+        event!(
+            Level::TRACE,
+            message = "raw socket created",
+            domain,
+            kind,
+            protocol
+        );
+
+        Ok(Self { socket })
+    }
+
+    /// Binds the socket to a local address. Some protocols (e.g. raw IP) require the socket to be
+    /// bound before it can receive anything.
+    pub fn bind(&self, addr: SocketAddr) -> io::Result<()> {
+        let native_addr = NativeSocketAddr::new(addr);
+
+        // SAFETY: All we need to be concerned about is passing in valid arguments, which we do.
+        winsock::to_io_result(unsafe {
+            bind(**self.socket, native_addr.as_ptr(), native_addr.len())
+        })
+    }
+
+    /// Sets the default destination of messages sent via [`send()`][Self::send] and limits the
+    /// messages received via [`recv()`][Self::recv] to those sent by this address.
+    pub fn connect(&self, addr: SocketAddr) -> io::Result<()> {
+        let native_addr = NativeSocketAddr::new(addr);
+
+        // SAFETY: All we need to be concerned about is passing in valid arguments, which we do.
+        winsock::to_io_result(unsafe {
+            connect(**self.socket, native_addr.as_ptr(), native_addr.len())
+        })
+    }
+
+    /// Sends the active region of the buffer as a single message to the specified address.
+    ///
+    /// The buffer will be returned in the result to allow reuse.
+    pub fn send_to(&self, buffer: Buffer<Isolated>, addr: SocketAddr) -> OperationResultFuture {
+        send_datagram(
+            Rc::clone(&self.socket),
+            buffer,
+            Some(NativeSocketAddr::new(addr)),
+        )
+    }
+
+    /// Sends the active region of the buffer as a single message to the address the socket is
+    /// connected to.
+    ///
+    /// The buffer will be returned in the result to allow reuse.
+    pub fn send(&self, buffer: Buffer<Isolated>) -> OperationResultFuture {
+        send_datagram(Rc::clone(&self.socket), buffer, None)
+    }
+
+    /// Receives the next message, together with the address of its sender.
+    ///
+    /// The buffer is used in the same way as by [`UdpSocket::recv_from()`].
+    pub async fn recv_from(&self, buffer: Buffer<Isolated>) -> io::Result<ReceivedDatagram> {
+        receive_datagram(Rc::clone(&self.socket), buffer, SEND_RECV_FLAGS::default()).await
+    }
+
+    /// Receives the next message from the address the socket is connected to.
+    ///
+    /// The buffer is used in the same way as by [`UdpSocket::recv_from()`].
+    pub async fn recv(&self, buffer: Buffer<Isolated>) -> io::Result<ReceivedDatagram> {
+        receive_datagram(Rc::clone(&self.socket), buffer, SEND_RECV_FLAGS::default()).await
+    }
+
+    /// Receives the next message, together with the address of its sender, without removing it
+    /// from the receive queue of the socket.
+    ///
+    /// The buffer is used in the same way as by [`UdpSocket::recv_from()`].
+    pub async fn peek_from(&self, buffer: Buffer<Isolated>) -> io::Result<ReceivedDatagram> {
+        receive_datagram(Rc::clone(&self.socket), buffer, MSG_PEEK).await
+    }
+
+    /// Sets a socket option, with the value given as the bytes of the native option value. Use
+    /// this for options specific to the protocol of the socket (e.g. `IP_HDRINCL` to provide the
+    /// IP header as part of the sent messages).
+    pub fn set_option(&self, level: i32, name: i32, value: &[u8]) -> io::Result<()> {
+        // SAFETY: All we need to be concerned about is passing in valid arguments, which we do.
+        winsock::to_io_result(unsafe { setsockopt(**self.socket, level, name, Some(value)) })
+    }
+
+    /// Gets a socket option, writing the bytes of the native option value into `value`. Returns
+    /// the number of bytes written.
+    pub fn option(&self, level: i32, name: i32, value: &mut [u8]) -> io::Result<usize> {
+        let mut value_len = value.len() as i32;
+
+        // SAFETY: All we need to be concerned about is passing in valid arguments, which we do.
+        winsock::to_io_result(unsafe {
+            getsockopt(
+                **self.socket,
+                level,
+                name,
+                PSTR::from_raw(value.as_mut_ptr()),
+                &mut value_len,
+            )
+        })?;
+
+        Ok(value_len as usize)
+    }
+
+    /// The address the socket is bound to.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        socket_addr::local_addr(**self.socket)
+    }
+}
+
+#[negative_impl]
+impl !Send for RawSocket {}
+#[negative_impl]
+impl !Sync for RawSocket {}
@@ -0,0 +1,83 @@
+use crate::{
+    io::{self, Buffer, OperationResultFuture},
+    mem::isolation::Isolated,
+    net::{
+        tcp_connection::{socket_receive_unowned, socket_send_unowned},
+        ShutdownFuture, TcpStream,
+    },
+};
+use negative_impl::negative_impl;
+use std::net::Shutdown;
+use windows::Win32::Networking::WinSock::SOCKET;
+
+/// A [`TcpStream`] registered for use on a hot path, created via
+/// [`TcpStream::register()`].
+///
+/// Every read and write on a regular stream takes a thread-safe reference to the socket, which
+/// costs an atomic reference count update. The socket of a registered stream is already bound to
+/// the I/O driver of the current async worker thread and the stream keeps a copy of the native
+/// handle, which it hands to the operating system directly, so reads and writes involve no
+/// reference counting at all.
+///
+/// The stream is otherwise equivalent to a regular stream, which you can get back via
+/// [`into_inner()`][Self::into_inner].
+#[derive(Debug)]
+pub struct RegisteredTcpStream {
+    stream: TcpStream,
+
+    // The native handle of the socket owned by `stream`, which keeps it open.
+    socket: SOCKET,
+}
+
+impl RegisteredTcpStream {
+    pub(super) fn new(stream: TcpStream) -> Self {
+        let socket = **stream.socket;
+
+        Self { stream, socket }
+    }
+
+    /// Reads the next buffer of data from the stream.
+    ///
+    /// The buffer will be returned in the result with the active region set to the bytes read, with
+    /// a length of 0 if the peer has closed the connection.
+    pub fn read(&mut self, buffer: Buffer<Isolated>) -> OperationResultFuture {
+        // SAFETY: The socket is kept open by the stream we own, which outlives this call.
+        unsafe { socket_receive_unowned(self.socket, buffer) }
+    }
+
+    /// Writes the active region of the buffer to the stream.
+    ///
+    /// The buffer will be returned in the result to allow reuse.
+    pub fn write(&mut self, buffer: Buffer<Isolated>) -> OperationResultFuture {
+        // SAFETY: The socket is kept open by the stream we own, which outlives this call.
+        unsafe { socket_send_unowned(self.socket, buffer) }
+    }
+
+    /// Performs a graceful shutdown of the connection. See [`TcpStream::shutdown()`].
+    pub fn shutdown(&mut self) -> ShutdownFuture {
+        self.stream.shutdown()
+    }
+
+    /// Shuts down one or both directions of the connection without waiting for the peer. See
+    /// [`TcpStream::shutdown_with()`].
+    pub async fn shutdown_with(&mut self, how: Shutdown) -> io::Result<()> {
+        self.stream.shutdown_with(how).await
+    }
+
+    pub fn get_ref(&self) -> &TcpStream {
+        &self.stream
+    }
+
+    pub fn get_mut(&mut self) -> &mut TcpStream {
+        &mut self.stream
+    }
+
+    pub fn into_inner(self) -> TcpStream {
+        self.stream
+    }
+}
+
+#[negative_impl]
+impl !Send for RegisteredTcpStream {}
+#[negative_impl]
+impl !Sync for RegisteredTcpStream {}
@@ -0,0 +1,277 @@
+use crate::{io, net::winsock};
+use std::{
+    mem,
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6},
+    path::Path,
+    ptr,
+};
+use windows::Win32::Networking::WinSock::{
+    getpeername, getsockname, ADDRESS_FAMILY, AF_INET, AF_INET6, AF_UNIX, IN6_ADDR, IN6_ADDR_0,
+    IN_ADDR, IN_ADDR_0, SOCKADDR, SOCKADDR_IN, SOCKADDR_IN6, SOCKADDR_IN6_0, SOCKADDR_STORAGE,
+    SOCKET,
+};
+
+// The layout of SOCKADDR_UN: the address family followed by a path of up to 108 bytes.
+const UNIX_PATH_OFFSET: usize = mem::size_of::<ADDRESS_FAMILY>();
+const UNIX_PATH_MAX: usize = 108;
+
+/// A socket address in the native Winsock representation, with storage large enough for any
+/// supported address family.
+#[derive(Clone, Copy)]
+pub(crate) struct NativeSocketAddr {
+    storage: SOCKADDR_STORAGE,
+    len: i32,
+}
+
+impl NativeSocketAddr {
+    pub fn new(addr: SocketAddr) -> Self {
+        let mut storage = SOCKADDR_STORAGE::default();
+
+        // Winsock expects the port and IPv4 address in network byte order (big-endian).
+        let len = match addr {
+            SocketAddr::V4(addr) => {
+                let native = SOCKADDR_IN {
+                    sin_family: AF_INET,
+                    sin_port: addr.port().to_be(),
+                    sin_addr: native_ipv4(*addr.ip()),
+                    sin_zero: [0; 8],
+                };
+
+                // SAFETY: SOCKADDR_STORAGE is large enough and suitably aligned for any address.
+                unsafe { ptr::write(&mut storage as *mut _ as *mut SOCKADDR_IN, native) };
+                mem::size_of::<SOCKADDR_IN>()
+            }
+            SocketAddr::V6(addr) => {
+                let native = SOCKADDR_IN6 {
+                    sin6_family: AF_INET6,
+                    sin6_port: addr.port().to_be(),
+                    sin6_flowinfo: addr.flowinfo(),
+                    sin6_addr: native_ipv6(*addr.ip()),
+                    Anonymous: SOCKADDR_IN6_0 {
+                        sin6_scope_id: addr.scope_id(),
+                    },
+                };
+
+                // SAFETY: SOCKADDR_STORAGE is large enough and suitably aligned for any address.
+                unsafe { ptr::write(&mut storage as *mut _ as *mut SOCKADDR_IN6, native) };
+                mem::size_of::<SOCKADDR_IN6>()
+            }
+        };
+
+        Self {
+            storage,
+            len: len as i32,
+        }
+    }
+
+    /// The address of a Unix domain socket at the specified path in the file system.
+    pub fn from_unix_path(path: &Path) -> io::Result<Self> {
+        // Winsock expects Unix domain socket paths to be UTF-8.
+        let path = path
+            .to_str()
+            .ok_or_else(|| {
+                io::Error::InvalidOptions("Unix domain socket path must be UTF-8".to_string())
+            })?
+            .as_bytes();
+
+        // The path must fit into `sun_path`, including the terminating NUL.
+        if path.len() >= UNIX_PATH_MAX {
+            return Err(io::Error::InvalidOptions(format!(
+                "Unix domain socket path must be shorter than {UNIX_PATH_MAX} bytes"
+            )));
+        }
+
+        let mut storage = SOCKADDR_STORAGE {
+            ss_family: AF_UNIX,
+            ..Default::default()
+        };
+
+        // SAFETY: SOCKADDR_STORAGE is larger than SOCKADDR_UN, so the path fits after the family.
+        unsafe {
+            ptr::copy_nonoverlapping(
+                path.as_ptr(),
+                (&mut storage as *mut _ as *mut u8).add(UNIX_PATH_OFFSET),
+                path.len(),
+            )
+        };
+
+        Ok(Self {
+            storage,
+            len: (UNIX_PATH_OFFSET + path.len() + 1) as i32,
+        })
+    }
+
+    /// An address that was filled by Winsock into storage owned by someone else.
+    pub fn from_storage(storage: SOCKADDR_STORAGE, len: i32) -> Self {
+        Self { storage, len }
+    }
+
+    /// Copies an address that was filled by Winsock into memory owned by someone else.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must point to a valid socket address that is at least `len` bytes long.
+    pub unsafe fn copy_from(addr: *const SOCKADDR, len: i32) -> Self {
+        let mut storage = SOCKADDR_STORAGE::default();
+        let len = (len.max(0) as usize).min(mem::size_of::<SOCKADDR_STORAGE>());
+
+        ptr::copy_nonoverlapping(addr as *const u8, &mut storage as *mut _ as *mut u8, len);
+
+        Self {
+            storage,
+            len: len as i32,
+        }
+    }
+
+    /// Storage for an address that is to be filled by Winsock (e.g. via `getsockname()`).
+    pub fn empty() -> Self {
+        Self {
+            storage: SOCKADDR_STORAGE::default(),
+            len: mem::size_of::<SOCKADDR_STORAGE>() as i32,
+        }
+    }
+
+    pub fn as_ptr(&self) -> *const SOCKADDR {
+        &self.storage as *const _ as *const SOCKADDR
+    }
+
+    pub fn as_mut_ptr(&mut self) -> *mut SOCKADDR {
+        &mut self.storage as *mut _ as *mut SOCKADDR
+    }
+
+    pub fn len(&self) -> i32 {
+        self.len
+    }
+
+    /// The length of the address, for Winsock to update when it fills the storage.
+    pub fn len_mut(&mut self) -> &mut i32 {
+        &mut self.len
+    }
+
+    pub fn to_socket_addr(&self) -> io::Result<SocketAddr> {
+        // SAFETY: The storage is valid for `len` bytes, as it is large enough for any address.
+        unsafe { to_socket_addr(self.as_ptr(), self.len) }
+    }
+}
+
+/// Converts a native Winsock socket address into a Rust socket address.
+///
+/// # Safety
+///
+/// The pointer must point to a valid socket address that is at least `len` bytes long.
+pub(crate) unsafe fn to_socket_addr(addr: *const SOCKADDR, len: i32) -> io::Result<SocketAddr> {
+    let len = len as usize;
+
+    match (*addr).sa_family {
+        AF_INET if len >= mem::size_of::<SOCKADDR_IN>() => {
+            let addr = &*(addr as *const SOCKADDR_IN);
+
+            Ok(SocketAddr::V4(SocketAddrV4::new(
+                Ipv4Addr::from(addr.sin_addr.S_un.S_addr.to_ne_bytes()),
+                u16::from_be(addr.sin_port),
+            )))
+        }
+        AF_INET6 if len >= mem::size_of::<SOCKADDR_IN6>() => {
+            let addr = &*(addr as *const SOCKADDR_IN6);
+
+            Ok(SocketAddr::V6(SocketAddrV6::new(
+                Ipv6Addr::from(addr.sin6_addr.u.Byte),
+                u16::from_be(addr.sin6_port),
+                addr.sin6_flowinfo,
+                addr.Anonymous.sin6_scope_id,
+            )))
+        }
+        family => Err(io::Error::LogicError(format!(
+            "unsupported socket address family {} of length {len}",
+            family.0
+        ))),
+    }
+}
+
+/// The local address a socket is bound to.
+pub(crate) fn local_addr(socket: SOCKET) -> io::Result<SocketAddr> {
+    let mut addr = NativeSocketAddr::empty();
+
+    // SAFETY: All we need to be concerned about is passing in valid arguments, which we do.
+    winsock::to_io_result(unsafe { getsockname(socket, addr.as_mut_ptr(), addr.len_mut()) })?;
+
+    addr.to_socket_addr()
+}
+
+/// The address of the remote peer a socket is connected to.
+pub(crate) fn peer_addr(socket: SOCKET) -> io::Result<SocketAddr> {
+    let mut addr = NativeSocketAddr::empty();
+
+    // SAFETY: All we need to be concerned about is passing in valid arguments, which we do.
+    winsock::to_io_result(unsafe { getpeername(socket, addr.as_mut_ptr(), addr.len_mut()) })?;
+
+    addr.to_socket_addr()
+}
+
+pub(crate) fn address_family(addr: &SocketAddr) -> ADDRESS_FAMILY {
+    match addr {
+        SocketAddr::V4(_) => AF_INET,
+        SocketAddr::V6(_) => AF_INET6,
+    }
+}
+
+/// The "any address, any port" address of the same address family as the provided address.
+pub(crate) fn unspecified_like(addr: &SocketAddr) -> SocketAddr {
+    match addr {
+        SocketAddr::V4(_) => SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)),
+        SocketAddr::V6(_) => SocketAddr::from((Ipv6Addr::UNSPECIFIED, 0)),
+    }
+}
+
+/// Converts an IPv4 address to its native Winsock representation, in network byte order.
+pub(crate) fn native_ipv4(addr: Ipv4Addr) -> IN_ADDR {
+    IN_ADDR {
+        S_un: IN_ADDR_0 {
+            S_addr: u32::from_ne_bytes(addr.octets()),
+        },
+    }
+}
+
+/// Converts an IPv6 address to its native Winsock representation.
+pub(crate) fn native_ipv6(addr: Ipv6Addr) -> IN6_ADDR {
+    IN6_ADDR {
+        u: IN6_ADDR_0 {
+            Byte: addr.octets(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let addrs = [
+            SocketAddr::from((Ipv4Addr::new(10, 1, 2, 3), 1234)),
+            SocketAddr::V6(SocketAddrV6::new(
+                Ipv6Addr::new(0xfe80, 0, 0, 0, 1, 2, 3, 4),
+                4321,
+                5,
+                6,
+            )),
+        ];
+
+        for addr in addrs {
+            assert_eq!(NativeSocketAddr::new(addr).to_socket_addr().unwrap(), addr);
+        }
+    }
+
+    #[test]
+    fn unix_path_length_is_limited() {
+        assert!(NativeSocketAddr::from_unix_path(Path::new(r"C:\Temp\folo.sock")).is_ok());
+
+        let too_long = "x".repeat(UNIX_PATH_MAX);
+        assert!(NativeSocketAddr::from_unix_path(Path::new(&too_long)).is_err());
+    }
+
+    #[test]
+    fn empty_is_not_an_address() {
+        assert!(NativeSocketAddr::empty().to_socket_addr().is_err());
+    }
+}
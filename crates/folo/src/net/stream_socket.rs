@@ -0,0 +1,260 @@
+//! Plumbing shared by the connection-oriented socket types (TCP and Unix domain sockets).
+
+use crate::{
+    io::{self, reserve_handle, Buffer, OperationResultExt, OperationResultSharedExt},
+    mem::isolation::{Isolated, Shared},
+    net::{socket_addr::NativeSocketAddr, winsock},
+    rt::{current_async_agent, spawn_sync, SynchronousTaskType},
+    windows::OwnedHandle,
+};
+use std::{mem, ptr, sync::Arc};
+use windows::Win32::{
+    Networking::WinSock::{
+        bind, listen, setsockopt, AcceptEx, GetAcceptExSockaddrs, WSASocketA, ADDRESS_FAMILY,
+        AF_UNIX, IPPROTO_TCP, SOCKADDR, SOCKADDR_STORAGE, SOCKET, SOCK_STREAM, SOL_SOCKET,
+        SOMAXCONN, SO_UPDATE_ACCEPT_CONTEXT, WSA_FLAG_OVERLAPPED,
+    },
+    System::IO::OVERLAPPED,
+};
+
+// AcceptEx requires the space reserved for each address to be at least 16 bytes more than the
+// maximum address length of the transport protocol.
+const ADDRESS_LENGTH: usize = mem::size_of::<SOCKADDR_STORAGE>() + 16;
+
+/// Creates a new stream socket of the specified address family, for use with overlapped I/O.
+pub(crate) fn new_stream_socket(family: ADDRESS_FAMILY) -> io::Result<OwnedHandle<SOCKET>> {
+    // Unix domain sockets have no protocol to choose from.
+    let protocol = if family == AF_UNIX { 0 } else { IPPROTO_TCP.0 };
+
+    // SAFETY: All we need to worry about here is cleanup, which we do via OwnedHandle.
+    Ok(unsafe {
+        OwnedHandle::new(WSASocketA(
+            family.0 as i32,
+            SOCK_STREAM.0,
+            protocol,
+            None,
+            0,
+            WSA_FLAG_OVERLAPPED,
+        )?)
+    })
+}
+
+/// Creates a stream socket that listens on the specified address and binds it to the I/O driver of
+/// the current async worker thread.
+pub(crate) async fn listen_on(
+    family: ADDRESS_FAMILY,
+    addr: NativeSocketAddr,
+) -> io::Result<Arc<OwnedHandle<SOCKET>>> {
+    let socket = Arc::new(create_listen_socket(family, addr).await?);
+
+    current_async_agent::with_io(|io| io.bind_io_primitive(&**socket))?;
+
+    Ok(socket)
+}
+
+/// Creates a stream socket that listens on the specified address and binds it to the shared I/O
+/// driver, so every async worker thread can accept connections from it via `accept_shared()`.
+pub(crate) async fn listen_on_shared(
+    family: ADDRESS_FAMILY,
+    addr: NativeSocketAddr,
+) -> io::Result<Arc<OwnedHandle<SOCKET>>> {
+    let socket = Arc::new(create_listen_socket(family, addr).await?);
+
+    current_async_agent::with_io_shared(|io| io.bind_io_primitive(&**socket))?;
+
+    Ok(socket)
+}
+
+async fn create_listen_socket(
+    family: ADDRESS_FAMILY,
+    addr: NativeSocketAddr,
+) -> io::Result<OwnedHandle<SOCKET>> {
+    winsock::ensure_initialized();
+
+    // Creating the socket is an expensive synchronous operation, so do it on a synchronous
+    // worker thread.
+    spawn_sync(SynchronousTaskType::Syscall, move || -> io::Result<_> {
+        let socket = new_stream_socket(family)?;
+
+        // SAFETY: All we need to be concerned about is passing in valid arguments, which we do.
+        unsafe {
+            winsock::to_io_result(bind(*socket, addr.as_ptr(), addr.len()))?;
+            winsock::to_io_result(listen(*socket, SOMAXCONN as i32))?;
+        }
+
+        Ok(socket)
+    })
+    .await
+}
+
+/// Waits for the next incoming connection on a listening socket bound to the I/O driver of the
+/// current async worker thread and accepts it. Returns the connected socket, bound to the same
+/// I/O driver, together with the address of the remote peer.
+pub(crate) async fn accept(
+    listen_socket: &Arc<OwnedHandle<SOCKET>>,
+    family: ADDRESS_FAMILY,
+) -> io::Result<(Arc<OwnedHandle<SOCKET>>, NativeSocketAddr)> {
+    let connection_socket = new_connection_socket(family).await?;
+
+    // We do not ask AcceptEx to read any data, so the buffer only receives the addresses.
+    let buffer = Buffer::<Isolated>::from_pool();
+    assert!(buffer.len() >= ADDRESS_LENGTH * 2);
+
+    let mut operation = current_async_agent::with_io(|io| io.new_operation(buffer));
+    operation.set_primitive(**listen_socket);
+
+    // SAFETY: We are required to pass the OVERLAPPED pointer to the native I/O function to
+    // avoid a resource leak. We do.
+    let accept_result = unsafe {
+        operation.begin({
+            let listen_socket = Arc::clone(listen_socket);
+            let connection_socket = Arc::clone(&connection_socket);
+
+            move |buffer, overlapped, immediate_bytes_transferred| {
+                begin_accept(
+                    &listen_socket,
+                    &connection_socket,
+                    buffer,
+                    overlapped,
+                    immediate_bytes_transferred,
+                )
+            }
+        })
+    }
+    .await
+    .into_inner()?;
+
+    let peer_addr = complete_accept(listen_socket, &connection_socket, &accept_result.as_slice())?;
+
+    Ok((connection_socket, peer_addr))
+}
+
+/// Waits for the next incoming connection on a listening socket bound to the shared I/O driver and
+/// accepts it. Returns the connected socket, bound to the I/O driver of the current async worker
+/// thread, together with the address of the remote peer.
+pub(crate) async fn accept_shared(
+    listen_socket: &Arc<OwnedHandle<SOCKET>>,
+    family: ADDRESS_FAMILY,
+) -> io::Result<(Arc<OwnedHandle<SOCKET>>, NativeSocketAddr)> {
+    let connection_socket = new_connection_socket(family).await?;
+
+    // We do not ask AcceptEx to read any data, so the buffer only receives the addresses.
+    let buffer = Buffer::<Shared>::from_pool();
+    assert!(buffer.len() >= ADDRESS_LENGTH * 2);
+
+    // SAFETY: We are required to pass the OVERLAPPED pointer to the native I/O function to
+    // avoid a resource leak. We do.
+    let accept_result = unsafe {
+        current_async_agent::with_io_shared(|io| io.new_operation(buffer)).begin({
+            let listen_socket = Arc::clone(listen_socket);
+            let connection_socket = Arc::clone(&connection_socket);
+
+            move |buffer, overlapped, immediate_bytes_transferred| {
+                begin_accept(
+                    &listen_socket,
+                    &connection_socket,
+                    buffer,
+                    overlapped,
+                    immediate_bytes_transferred,
+                )
+            }
+        })
+    }
+    .await
+    .into_inner()?;
+
+    let peer_addr = complete_accept(listen_socket, &connection_socket, &accept_result.as_slice())?;
+
+    Ok((connection_socket, peer_addr))
+}
+
+async fn new_connection_socket(family: ADDRESS_FAMILY) -> io::Result<Arc<OwnedHandle<SOCKET>>> {
+    // If the runtime has a soft limit on open handles, we wait for room (or fail) before creating
+    // the socket, so connections we cannot handle yet remain in the backlog.
+    reserve_handle().await?;
+
+    // AcceptEx requires us to provide the socket for the incoming connection. Creating the
+    // socket is an expensive synchronous operation, so do it on a synchronous worker thread.
+    Ok(Arc::new(
+        spawn_sync(SynchronousTaskType::Syscall, move || {
+            new_stream_socket(family)
+        })
+        .await?,
+    ))
+}
+
+/// # Safety
+///
+/// The caller must pass the OVERLAPPED pointer of an operation that owns the buffer.
+unsafe fn begin_accept(
+    listen_socket: &OwnedHandle<SOCKET>,
+    connection_socket: &OwnedHandle<SOCKET>,
+    buffer: &mut [u8],
+    overlapped: *mut OVERLAPPED,
+    immediate_bytes_transferred: &mut u32,
+) -> io::Result<()> {
+    if AcceptEx(
+        **listen_socket,
+        **connection_socket,
+        buffer.as_mut_ptr() as *mut _,
+        0,
+        ADDRESS_LENGTH as u32,
+        ADDRESS_LENGTH as u32,
+        immediate_bytes_transferred,
+        overlapped,
+    )
+    .as_bool()
+    {
+        Ok(())
+    } else {
+        Err(windows::core::Error::from_win32().into())
+    }
+}
+
+/// Finishes accepting a connection after AcceptEx has completed, binding the connected socket to
+/// the I/O driver of the current async worker thread. Returns the address of the remote peer.
+fn complete_accept(
+    listen_socket: &OwnedHandle<SOCKET>,
+    connection_socket: &OwnedHandle<SOCKET>,
+    address_buffer: &[u8],
+) -> io::Result<NativeSocketAddr> {
+    let mut local_addr: *mut SOCKADDR = ptr::null_mut();
+    let mut local_addr_len: i32 = 0;
+    let mut remote_addr: *mut SOCKADDR = ptr::null_mut();
+    let mut remote_addr_len: i32 = 0;
+
+    // SAFETY: As long as we pass in valid pointers that match the AcceptEx call, we are good.
+    // The returned pointers point into the buffer, which we keep alive until we are done.
+    let peer_addr = unsafe {
+        GetAcceptExSockaddrs(
+            address_buffer.as_ptr() as *const _,
+            0,
+            ADDRESS_LENGTH as u32,
+            ADDRESS_LENGTH as u32,
+            &mut local_addr as *mut _,
+            &mut local_addr_len as *mut _,
+            &mut remote_addr as *mut _,
+            &mut remote_addr_len as *mut _,
+        );
+
+        NativeSocketAddr::copy_from(remote_addr, remote_addr_len)
+    };
+
+    // This makes the accepted socket inherit the properties of the listen socket. Without it,
+    // functions like getpeername() and shutdown() would fail on the accepted socket.
+    let listen_socket_bytes = listen_socket.0.to_ne_bytes();
+
+    // SAFETY: All we need to be concerned about is passing in valid arguments, which we do.
+    winsock::to_io_result(unsafe {
+        setsockopt(
+            **connection_socket,
+            SOL_SOCKET,
+            SO_UPDATE_ACCEPT_CONTEXT,
+            Some(&listen_socket_bytes),
+        )
+    })?;
+
+    current_async_agent::with_io(|io| io.bind_io_primitive(&**connection_socket))?;
+
+    Ok(peer_addr)
+}
@@ -1,16 +1,28 @@
 use crate::{
-    io::{self, Buffer, OperationResultFuture},
+    io::{
+        self, AdditionalBuffers, Buffer, OperationError, OperationResult, OperationResultExt,
+        OperationResultFuture,
+    },
     mem::isolation::Isolated,
     net::winsock,
-    rt::{current_async_agent, current_runtime, RemoteJoinHandle, SynchronousTaskType},
+    rt::{current_async_agent, current_runtime, spawn_sync, RemoteJoinHandle, SynchronousTaskType},
     windows::OwnedHandle,
 };
 use negative_impl::negative_impl;
 use pin_project::pin_project;
-use std::{future::Future, sync::Arc, task::Poll};
+use std::{
+    cell::RefCell, future::Future, io::ErrorKind, iter, mem, net::Shutdown, rc::Rc, sync::Arc,
+    task::Poll,
+};
 use windows::{
     core::PSTR,
-    Win32::Networking::WinSock::{WSARecv, WSASend, WSASendDisconnect, SOCKET, WSABUF},
+    Win32::{
+        Networking::WinSock::{
+            shutdown, WSARecv, WSASend, WSASendDisconnect, MSG_PEEK, MSG_WAITALL, SD_BOTH,
+            SD_RECEIVE, SD_SEND, SEND_RECV_FLAGS, SOCKET, WSABUF, WSAEOPNOTSUPP,
+        },
+        System::IO::OVERLAPPED,
+    },
 };
 
 #[derive(Debug)]
@@ -50,6 +62,12 @@ impl TcpConnection {
     pub fn shutdown(&mut self) -> ShutdownFuture {
         ShutdownFuture::new(Arc::clone(&self.socket))
     }
+
+    /// Shuts down one or both directions of the connection without waiting for the peer. See
+    /// [`TcpStream::shutdown_with()`][crate::net::TcpStream::shutdown_with].
+    pub async fn shutdown_with(&mut self, how: Shutdown) -> io::Result<()> {
+        socket_shutdown(Arc::clone(&self.socket), how).await
+    }
 }
 
 #[negative_impl]
@@ -57,20 +75,245 @@ impl !Send for TcpConnection {}
 #[negative_impl]
 impl !Sync for TcpConnection {}
 
-fn socket_receive(
+pub(super) fn socket_receive(
+    socket: Arc<OwnedHandle<SOCKET>>,
+    buffer: Buffer<Isolated>,
+) -> OperationResultFuture {
+    socket_receive_with_flags(socket, buffer, SEND_RECV_FLAGS::default())
+}
+
+/// Receives data into the active region of the buffer without removing it from the receive queue
+/// of the socket, so the next receive operation returns the same data again.
+pub(super) fn socket_peek(
+    socket: Arc<OwnedHandle<SOCKET>>,
+    buffer: Buffer<Isolated>,
+) -> OperationResultFuture {
+    socket_receive_with_flags(socket, buffer, MSG_PEEK)
+}
+
+/// Receives exactly as many bytes as fit into the active region of the buffer, failing if the
+/// connection is closed before that. The OS is asked to fill the whole buffer with one operation
+/// via `MSG_WAITALL`, with a fallback to receiving in a loop if the transport does not support it.
+///
+/// If an error occurs, the active region of the returned buffer is set to the bytes that were
+/// received before the error.
+pub(super) async fn socket_receive_exact(
+    socket: Arc<OwnedHandle<SOCKET>>,
+    mut buffer: Buffer<Isolated>,
+) -> OperationResult {
+    let start = buffer.start();
+    let len = buffer.len();
+
+    let mut received = 0;
+    let mut flags = MSG_WAITALL;
+
+    while received < len {
+        set_active_region(&mut buffer, start + received, len - received);
+
+        buffer = match socket_receive_with_flags(Arc::clone(&socket), buffer, flags).await {
+            Ok(mut buffer) if buffer.is_empty() => {
+                set_active_region(&mut buffer, start, received);
+
+                return Err(OperationError::new(
+                    io::Error::StdIo(ErrorKind::UnexpectedEof.into()),
+                    buffer,
+                ));
+            }
+            Ok(buffer) => {
+                received += buffer.len();
+                buffer
+            }
+            Err(OperationError {
+                inner: io::Error::Winsock { detail, .. },
+                buffer,
+                ..
+            }) if detail == WSAEOPNOTSUPP && flags == MSG_WAITALL => {
+                flags = SEND_RECV_FLAGS::default();
+                buffer
+            }
+            Err(mut e) => {
+                set_active_region(&mut e.buffer, start, received);
+                return Err(e);
+            }
+        };
+    }
+
+    set_active_region(&mut buffer, start, len);
+
+    Ok(buffer)
+}
+
+fn set_active_region(buffer: &mut Buffer<Isolated>, start: usize, len: usize) {
+    // The length goes first, so the region never extends past the end of the buffer in between.
+    buffer.set_len(0);
+    buffer.set_start(start);
+    buffer.set_len(len);
+}
+
+fn socket_receive_with_flags(
+    socket: Arc<OwnedHandle<SOCKET>>,
+    buffer: Buffer<Isolated>,
+    flags: SEND_RECV_FLAGS,
+) -> OperationResultFuture {
+    let mut operation = current_async_agent::with_io(|io| io.new_operation(buffer));
+    operation.set_primitive(**socket);
+
+    // SAFETY: We are required to pass the OVERLAPPED pointer to the completion routine. We do.
+    unsafe {
+        operation.begin(move |buffer, overlapped, immediate_bytes_transferred| {
+            wsa_receive(
+                **socket,
+                buffer,
+                flags,
+                overlapped,
+                immediate_bytes_transferred,
+            )
+        })
+    }
+}
+
+pub(super) fn socket_send(
     socket: Arc<OwnedHandle<SOCKET>>,
     buffer: Buffer<Isolated>,
 ) -> OperationResultFuture {
+    let mut operation = current_async_agent::with_io(|io| io.new_operation(buffer));
+    operation.set_primitive(**socket);
+
+    // SAFETY: We are required to pass the OVERLAPPED pointer to the completion routine. We do.
+    unsafe {
+        operation.begin(move |buffer, overlapped, immediate_bytes_transferred| {
+            wsa_send(**socket, buffer, overlapped, immediate_bytes_transferred)
+        })
+    }
+}
+
+/// Same as `socket_receive()` but without taking a reference to the socket.
+///
+/// # Safety
+///
+/// The socket must remain open until this function returns. The native I/O function is called
+/// before this function returns and after that the OS no longer needs the socket handle.
+pub(super) unsafe fn socket_receive_unowned(
+    socket: SOCKET,
+    buffer: Buffer<Isolated>,
+) -> OperationResultFuture {
+    let mut operation = current_async_agent::with_io(|io| io.new_operation(buffer));
+    operation.set_primitive(socket);
+
+    // SAFETY: We are required to pass the OVERLAPPED pointer to the completion routine. We do.
+    unsafe {
+        operation.begin(move |buffer, overlapped, immediate_bytes_transferred| {
+            wsa_receive(
+                socket,
+                buffer,
+                SEND_RECV_FLAGS::default(),
+                overlapped,
+                immediate_bytes_transferred,
+            )
+        })
+    }
+}
+
+/// Same as `socket_send()` but without taking a reference to the socket.
+///
+/// # Safety
+///
+/// The socket must remain open until this function returns. The native I/O function is called
+/// before this function returns and after that the OS no longer needs the socket handle.
+pub(super) unsafe fn socket_send_unowned(
+    socket: SOCKET,
+    buffer: Buffer<Isolated>,
+) -> OperationResultFuture {
+    let mut operation = current_async_agent::with_io(|io| io.new_operation(buffer));
+    operation.set_primitive(socket);
+
     // SAFETY: We are required to pass the OVERLAPPED pointer to the completion routine. We do.
     unsafe {
-        current_async_agent::with_io(|io| io.new_operation(buffer)).begin(
-            move |buffer, overlapped, immediate_bytes_transferred| {
-                let wsabuf = WSABUF {
+        operation.begin(move |buffer, overlapped, immediate_bytes_transferred| {
+            wsa_send(socket, buffer, overlapped, immediate_bytes_transferred)
+        })
+    }
+}
+
+/// # Safety
+///
+/// The arguments must be the callback arguments of an operation bound to the socket.
+unsafe fn wsa_receive(
+    socket: SOCKET,
+    buffer: &mut [u8],
+    flags: SEND_RECV_FLAGS,
+    overlapped: *mut OVERLAPPED,
+    immediate_bytes_transferred: &mut u32,
+) -> io::Result<()> {
+    let wsabuf = WSABUF {
+        len: buffer.len() as u32,
+        buf: PSTR::from_raw(buffer.as_mut_ptr()),
+    };
+
+    let wsabufs = [wsabuf];
+    let mut flags = flags.0 as u32;
+
+    winsock::to_io_result(WSARecv(
+        socket,
+        &wsabufs,
+        Some(immediate_bytes_transferred as *mut u32),
+        &mut flags as *mut u32,
+        Some(overlapped),
+        None,
+    ))
+}
+
+/// # Safety
+///
+/// The arguments must be the callback arguments of an operation bound to the socket.
+unsafe fn wsa_send(
+    socket: SOCKET,
+    buffer: &mut [u8],
+    overlapped: *mut OVERLAPPED,
+    immediate_bytes_transferred: &mut u32,
+) -> io::Result<()> {
+    let wsabuf = WSABUF {
+        len: buffer.len() as u32,
+        buf: PSTR::from_raw(buffer.as_mut_ptr()),
+    };
+
+    let wsabufs = [wsabuf];
+
+    winsock::to_io_result(WSASend(
+        socket,
+        &wsabufs,
+        Some(immediate_bytes_transferred as *mut u32),
+        0,
+        Some(overlapped),
+        None,
+    ))
+}
+
+/// Receives data into multiple buffers with one operation, filling them in order.
+///
+/// Returns the buffers with the active regions set to the bytes read. Trailing buffers are empty if
+/// fewer bytes were received than fit into the buffers, with all buffers empty if the peer has
+/// closed the connection.
+pub(super) async fn socket_receive_vectored(
+    socket: Arc<OwnedHandle<SOCKET>>,
+    buffers: Vec<Buffer<Isolated>>,
+) -> io::Result<Vec<Buffer<Isolated>>> {
+    let (primary, additional_buffers, mut wsabufs) = prepare_vectored(buffers)?;
+
+    let mut operation = current_async_agent::with_io(|io| io.new_operation(primary));
+    operation.set_additional_buffers(Rc::clone(&additional_buffers));
+    operation.set_primitive(**socket);
+
+    // SAFETY: We are required to pass the OVERLAPPED pointer to the completion routine. We do.
+    // The additional buffers are kept alive by the operation until it completes.
+    let result = unsafe {
+        operation
+            .begin(move |buffer, overlapped, immediate_bytes_transferred| {
+                wsabufs[0] = WSABUF {
                     len: buffer.len() as u32,
                     buf: PSTR::from_raw(buffer.as_mut_ptr()),
                 };
 
-                let wsabufs = [wsabuf];
                 let mut flags: u32 = 0;
 
                 winsock::to_io_result(WSARecv(
@@ -81,26 +324,37 @@ fn socket_receive(
                     Some(overlapped),
                     None,
                 ))
-            },
-        )
-    }
+            })
+            .await
+    };
+
+    complete_vectored(result, additional_buffers)
 }
 
-fn socket_send(
+/// Sends the active regions of multiple buffers to the peer with one operation, as if they were
+/// one contiguous buffer.
+///
+/// Returns the buffers with the active regions set to the bytes that were sent.
+pub(super) async fn socket_send_vectored(
     socket: Arc<OwnedHandle<SOCKET>>,
-    buffer: Buffer<Isolated>,
-) -> OperationResultFuture {
+    buffers: Vec<Buffer<Isolated>>,
+) -> io::Result<Vec<Buffer<Isolated>>> {
+    let (primary, additional_buffers, mut wsabufs) = prepare_vectored(buffers)?;
+
+    let mut operation = current_async_agent::with_io(|io| io.new_operation(primary));
+    operation.set_additional_buffers(Rc::clone(&additional_buffers));
+    operation.set_primitive(**socket);
+
     // SAFETY: We are required to pass the OVERLAPPED pointer to the completion routine. We do.
-    unsafe {
-        current_async_agent::with_io(|io| io.new_operation(buffer)).begin(
-            move |buffer, overlapped, immediate_bytes_transferred| {
-                let wsabuf = WSABUF {
+    // The additional buffers are kept alive by the operation until it completes.
+    let result = unsafe {
+        operation
+            .begin(move |buffer, overlapped, immediate_bytes_transferred| {
+                wsabufs[0] = WSABUF {
                     len: buffer.len() as u32,
                     buf: PSTR::from_raw(buffer.as_mut_ptr()),
                 };
 
-                let wsabufs = [wsabuf];
-
                 winsock::to_io_result(WSASend(
                     **socket,
                     &wsabufs,
@@ -109,9 +363,73 @@ fn socket_send(
                     Some(overlapped),
                     None,
                 ))
-            },
-        )
-    }
+            })
+            .await
+    };
+
+    complete_vectored(result, additional_buffers)
+}
+
+/// Splits the buffers of a vectored operation into the primary buffer and the additional buffers,
+/// also describing the active regions of the additional buffers for Winsock. The first WSABUF is a
+/// placeholder for the primary buffer, which is only available once the operation begins.
+pub(super) fn prepare_vectored(
+    buffers: Vec<Buffer<Isolated>>,
+) -> io::Result<(Buffer<Isolated>, AdditionalBuffers, Vec<WSABUF>)> {
+    let mut buffers = buffers.into_iter();
+
+    let primary = buffers.next().ok_or_else(|| {
+        io::Error::InvalidOptions("vectored I/O requires at least one buffer".to_string())
+    })?;
+
+    let mut additional_buffers = buffers.collect::<Vec<_>>();
+
+    let wsabufs = iter::once(WSABUF::default())
+        .chain(additional_buffers.iter_mut().map(|buffer| {
+            let mut slice = buffer.as_mut_slice();
+
+            // The contents of a buffer are pinned, so the pointer remains valid after we move the
+            // buffer into the operation.
+            WSABUF {
+                len: slice.len() as u32,
+                buf: PSTR::from_raw(slice.as_mut_ptr()),
+            }
+        }))
+        .collect();
+
+    Ok((primary, Rc::new(RefCell::new(additional_buffers)), wsabufs))
+}
+
+pub(super) fn complete_vectored(
+    result: OperationResult,
+    additional_buffers: AdditionalBuffers,
+) -> io::Result<Vec<Buffer<Isolated>>> {
+    let primary = result.into_inner()?;
+
+    // The operating system no longer accesses the buffers once the operation has completed.
+    let additional_buffers = mem::take(&mut *additional_buffers.borrow_mut());
+
+    Ok(iter::once(primary).chain(additional_buffers).collect())
+}
+
+/// Shuts down one or both directions of a connected socket.
+pub(super) async fn socket_shutdown(
+    socket: Arc<OwnedHandle<SOCKET>>,
+    how: Shutdown,
+) -> io::Result<()> {
+    let how = match how {
+        Shutdown::Read => SD_RECEIVE,
+        Shutdown::Write => SD_SEND,
+        Shutdown::Both => SD_BOTH,
+    };
+
+    // Like the graceful shutdown, this releases resources, so it is worth doing as soon as
+    // possible.
+    spawn_sync(SynchronousTaskType::HighPrioritySyscall, move || {
+        // SAFETY: Socket liveness is ensured by our shared ownership of the socket handle.
+        winsock::to_io_result(unsafe { shutdown(**socket, how) })
+    })
+    .await
 }
 
 #[derive(Debug)]
@@ -142,7 +460,7 @@ enum ShutdownState {
 }
 
 impl ShutdownFuture {
-    fn new(socket: Arc<OwnedHandle<SOCKET>>) -> Self {
+    pub(super) fn new(socket: Arc<OwnedHandle<SOCKET>>) -> Self {
         let disconnecting = current_runtime::with({
             let socket = Arc::clone(&socket);
 
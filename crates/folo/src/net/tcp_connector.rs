@@ -0,0 +1,239 @@
+use crate::{
+    io,
+    net::TcpStream,
+    rt::{spawn_sync, SynchronousTaskType},
+    time::{Clock, Deadline, Delay},
+};
+use futures::{stream::FuturesUnordered, FutureExt, StreamExt};
+use std::{
+    future::poll_fn,
+    io::ErrorKind,
+    net::{IpAddr, SocketAddr, ToSocketAddrs},
+    task::Poll,
+    time::Duration,
+};
+use tracing::{event, Level};
+
+/// The delay between starting connection attempts, as recommended by RFC 8305.
+const DEFAULT_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+/// Connects to a host by name, trying all of its IPv4 and IPv6 addresses via the "Happy Eyeballs"
+/// algorithm of RFC 8305.
+///
+/// Connecting to one address at a time gives terrible latency on networks where one of the address
+/// families is broken (typically IPv6), as every attempt to a broken address must time out before
+/// the next one starts. Instead, the connector starts a new attempt whenever the previous one has
+/// not succeeded within the attempt delay (or has failed), alternating between address families,
+/// and keeps the earlier attempts running. The first attempt to succeed wins and the rest are
+/// canceled.
+///
+/// # Example
+///
+/// ```no_run
+/// use folo::net::TcpConnector;
+/// use std::time::Duration;
+///
+/// #[folo::main]
+/// async fn main() {
+///     let mut stream = TcpConnector::new()
+///         .timeout(Duration::from_secs(10))
+///         .connect("example.com", 80)
+///         .await
+///         .unwrap();
+///
+///     stream.shutdown().await.unwrap();
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct TcpConnector {
+    attempt_delay: Duration,
+    timeout: Option<Duration>,
+}
+
+impl TcpConnector {
+    pub fn new() -> Self {
+        Self {
+            attempt_delay: DEFAULT_ATTEMPT_DELAY,
+            timeout: None,
+        }
+    }
+
+    /// Sets how long to wait for a connection attempt to succeed before starting the next one in
+    /// parallel. The default is 250 milliseconds.
+    pub fn attempt_delay(mut self, value: Duration) -> Self {
+        self.attempt_delay = value;
+        self
+    }
+
+    /// Sets the time limit for the entire connect operation, including resolving the host name. By
+    /// default, there is no limit beyond the timeouts of the operating system and the
+    /// [`Deadline`] of the calling code, if any.
+    pub fn timeout(mut self, value: Duration) -> Self {
+        self.timeout = Some(value);
+        self
+    }
+
+    /// Resolves the host name (or parses it, if it is an IP address) and connects to one of its
+    /// addresses.
+    ///
+    /// If no attempt succeeds, returns the error of the last attempt to fail. If the time limit or
+    /// the current [`Deadline`] is exceeded, returns an error of kind [`ErrorKind::TimedOut`].
+    pub async fn connect(&self, host: &str, port: u16) -> io::Result<TcpStream> {
+        let connect = self.resolve_and_connect(host, port);
+
+        // The deadline scope limits the timeout to the current deadline, if there is one.
+        let Some(deadline) = self.timeout.map(Deadline::after).or_else(Deadline::current) else {
+            return connect.await;
+        };
+
+        // Dropping the pending attempts when we time out cancels them.
+        deadline.scope(connect).await?
+    }
+
+    async fn resolve_and_connect(&self, host: &str, port: u16) -> io::Result<TcpStream> {
+        let addrs = resolve(host, port).await?;
+
+        if addrs.is_empty() {
+            return Err(io::Error::StdIo(ErrorKind::NotFound.into()));
+        }
+
+        event!(
+            Level::TRACE,
+            message = "connecting to host",
+            host,
+            port,
+            addresses = addrs.len()
+        );
+
+        race(interleave_families(addrs), self.attempt_delay).await
+    }
+}
+
+impl Default for TcpConnector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Resolves the IPv4 and IPv6 addresses of a host. IP addresses are parsed without a lookup.
+async fn resolve(host: &str, port: u16) -> io::Result<Vec<SocketAddr>> {
+    if let Ok(ip) = host.parse::<IpAddr>() {
+        return Ok(vec![SocketAddr::new(ip, port)]);
+    }
+
+    // Name resolution is a slow blocking call, so we do it on a synchronous worker thread. The
+    // operating system returns the addresses of both families, sorted by preference (RFC 6724).
+    let host = host.to_string();
+
+    spawn_sync(SynchronousTaskType::Syscall, move || -> io::Result<_> {
+        Ok((host.as_str(), port).to_socket_addrs()?.collect())
+    })
+    .await
+}
+
+/// Reorders the addresses to alternate between address families, starting with the family of the
+/// most preferred address, as recommended by RFC 8305. Within each family, the order is retained.
+fn interleave_families(addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let Some(first) = addrs.first() else {
+        return addrs;
+    };
+
+    let first_is_ipv6 = first.is_ipv6();
+
+    let (mut preferred, mut other): (Vec<_>, Vec<_>) = addrs
+        .into_iter()
+        .partition(|addr| addr.is_ipv6() == first_is_ipv6);
+
+    let mut result = Vec::with_capacity(preferred.len() + other.len());
+    let mut preferred = preferred.drain(..);
+    let mut other = other.drain(..);
+
+    loop {
+        match (preferred.next(), other.next()) {
+            (None, None) => return result,
+            (a, b) => result.extend(a.into_iter().chain(b)),
+        }
+    }
+}
+
+/// Starts connection attempts to the addresses in order, starting the next one whenever the
+/// previous one fails or has not succeeded within the attempt delay. Returns the first stream that
+/// connects, canceling the other attempts, or the error of the last attempt to fail.
+async fn race(addrs: Vec<SocketAddr>, attempt_delay: Duration) -> io::Result<TcpStream> {
+    let clock = Clock::new();
+
+    let mut remaining = addrs.into_iter();
+    let mut attempts = FuturesUnordered::new();
+    let mut next_attempt: Option<Delay> = None;
+    let mut last_error = None;
+
+    // Every attempt starts the delay after which we start the next attempt, if any remain.
+    let mut start_next_attempt =
+        |attempts: &mut FuturesUnordered<_>, next_attempt: &mut Option<Delay>| {
+            *next_attempt = remaining.next().map(|addr| {
+                attempts.push(TcpStream::connect(addr).boxed_local());
+                Delay::with_clock(&clock, attempt_delay)
+            });
+        };
+
+    start_next_attempt(&mut attempts, &mut next_attempt);
+
+    poll_fn(|cx| loop {
+        match attempts.poll_next_unpin(cx) {
+            Poll::Ready(Some(Ok(stream))) => return Poll::Ready(Ok(stream)),
+            Poll::Ready(Some(Err(e))) => {
+                event!(Level::TRACE, message = "connection attempt failed", error = %e);
+                last_error = Some(e);
+
+                // There is no point in waiting for the delay if the previous attempt has failed.
+                start_next_attempt(&mut attempts, &mut next_attempt);
+                continue;
+            }
+            Poll::Ready(None) if next_attempt.is_none() => {
+                return Poll::Ready(Err(last_error
+                    .take()
+                    .expect("we only run out of attempts after at least one has failed")));
+            }
+            Poll::Ready(None) | Poll::Pending => {}
+        }
+
+        match next_attempt.as_mut().map(|delay| delay.poll_unpin(cx)) {
+            Some(Poll::Ready(())) => start_next_attempt(&mut attempts, &mut next_attempt),
+            _ => return Poll::Pending,
+        }
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    fn v4(last: u8) -> SocketAddr {
+        SocketAddr::from((Ipv4Addr::new(192, 0, 2, last), 80))
+    }
+
+    fn v6(last: u16) -> SocketAddr {
+        SocketAddr::from((Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, last), 80))
+    }
+
+    #[test]
+    fn interleave_starts_with_preferred_family() {
+        assert_eq!(
+            interleave_families(vec![v6(1), v6(2), v6(3), v4(1), v4(2)]),
+            vec![v6(1), v4(1), v6(2), v4(2), v6(3)]
+        );
+
+        assert_eq!(
+            interleave_families(vec![v4(1), v6(1), v4(2)]),
+            vec![v4(1), v6(1), v4(2)]
+        );
+    }
+
+    #[test]
+    fn interleave_single_family() {
+        assert_eq!(interleave_families(vec![v4(1), v4(2)]), vec![v4(1), v4(2)]);
+        assert_eq!(interleave_families(Vec::new()), Vec::new());
+    }
+}
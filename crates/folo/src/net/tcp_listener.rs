@@ -0,0 +1,289 @@
+use crate::{
+    io,
+    net::{
+        runtime_connection_limit,
+        socket_addr::{self, NativeSocketAddr},
+        stream_socket, winsock, ConnectionLimit, Incoming, TcpStream,
+    },
+    rt::current_async_agent,
+    windows::OwnedHandle,
+};
+use negative_impl::negative_impl;
+#[cfg(feature = "socket2")]
+use std::os::windows::io::{FromRawSocket, IntoRawSocket};
+use std::{net::SocketAddr, sync::Arc};
+use tracing::{event, Level};
+use windows::Win32::Networking::WinSock::{ADDRESS_FAMILY, SOCKET};
+
+/// A TCP socket that listens for incoming connections, owned by the async worker thread that
+/// created it.
+///
+/// Unlike [`TcpServerBuilder`][crate::net::TcpServerBuilder], which dispatches connections to all
+/// async workers, the listener accepts connections only when the owner asks for one via
+/// [`accept()`][Self::accept] and every accepted connection is owned by the current async worker.
+///
+/// The socket stops listening when the listener is dropped.
+///
+/// # Example
+///
+/// ```no_run
+/// use folo::net::TcpListener;
+/// use std::net::SocketAddr;
+///
+/// #[folo::main]
+/// async fn main() {
+///     let listener = TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 1234)))
+///         .await
+///         .unwrap();
+///
+///     loop {
+///         let (mut stream, peer_addr) = listener.accept().await.unwrap();
+///         println!("accepted connection from {peer_addr}");
+///
+///         stream.shutdown().await.unwrap();
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct TcpListener {
+    // This is an Arc because the socket must remain alive until any pending accept operation on
+    // it has completed, even if the listener is dropped in the meantime.
+    socket: Arc<OwnedHandle<SOCKET>>,
+
+    family: ADDRESS_FAMILY,
+
+    // Whether the socket is bound to the shared I/O driver, accepting connections on behalf of
+    // every async worker thread that has a listener for it.
+    shared: bool,
+
+    connection_limit: Option<ConnectionLimit>,
+}
+
+impl TcpListener {
+    /// Creates a listener bound to the specified address. Use port 0 to let the OS assign a port,
+    /// which you can then look up via [`local_addr()`][Self::local_addr].
+    pub async fn bind(addr: SocketAddr) -> io::Result<Self> {
+        let family = socket_addr::address_family(&addr);
+        let socket = stream_socket::listen_on(family, NativeSocketAddr::new(addr)).await?;
+
+        event!(Level::TRACE, message = "TCP listener bound", %addr);
+
+        Ok(Self {
+            socket,
+            family,
+            shared: false,
+            connection_limit: None,
+        })
+    }
+
+    /// Takes ownership of a listening TCP socket created outside Folo (e.g. inherited from a
+    /// parent process for socket activation) and binds it to the I/O driver of the current async
+    /// worker thread. The socket is closed when the listener is dropped, including when this
+    /// fails.
+    ///
+    /// # Safety
+    ///
+    /// The socket must be a valid TCP socket created with `WSA_FLAG_OVERLAPPED`, bound and
+    /// listening, which the caller owns and does not use or close after this call. It must not
+    /// already be associated with an I/O completion port.
+    pub unsafe fn from_raw_socket(socket: SOCKET) -> io::Result<Self> {
+        winsock::ensure_initialized();
+
+        let socket = Arc::new(OwnedHandle::new(socket));
+
+        // Accepting connections requires us to create sockets of the same family.
+        let addr = socket_addr::local_addr(**socket)?;
+        let family = socket_addr::address_family(&addr);
+
+        current_async_agent::with_io(|io| io.bind_io_primitive(&**socket))?;
+
+        event!(Level::TRACE, message = "TCP listener adopted", %addr);
+
+        Ok(Self {
+            socket,
+            family,
+            shared: false,
+            connection_limit: None,
+        })
+    }
+
+    /// Takes ownership of a listening TCP socket that the caller has created and configured (e.g.
+    /// with `SO_REUSEADDR` or a non-default backlog), for options that Folo does not expose, and
+    /// binds it to the I/O driver of the current async worker thread. The socket is closed when
+    /// the listener is dropped, including when this fails.
+    ///
+    /// The socket must be bound and listening.
+    #[cfg(feature = "socket2")]
+    pub fn from_socket(socket: socket2::Socket) -> io::Result<Self> {
+        // SAFETY: Ownership of the socket is transferred to us. Sockets created by socket2 are
+        // always created for overlapped I/O. Sockets already associated with a completion port
+        // simply fail to bind to ours.
+        unsafe { Self::from_raw_socket(SOCKET(socket.into_raw_socket() as usize)) }
+    }
+
+    /// Releases the socket from Folo, for use with APIs that Folo does not cover (e.g. to hand it
+    /// over to another process). The socket keeps listening but is no longer bound to the I/O
+    /// driver of the current async worker thread.
+    ///
+    /// Fails if an accept operation is still in progress or if the listener is shared by all
+    /// async worker threads.
+    #[cfg(feature = "socket2")]
+    pub fn into_socket2(self) -> io::Result<socket2::Socket> {
+        let socket = Arc::try_unwrap(self.socket).map_err(|_| {
+            io::Error::LogicError(
+                "cannot release a TCP listener that is in use by other operations".to_string(),
+            )
+        })?;
+
+        io::unbind_io_primitive(&*socket)?;
+
+        // SAFETY: We own the socket and give up that ownership here.
+        Ok(unsafe { socket2::Socket::from_raw_socket(SOCKET::from(socket).0 as u64) })
+    }
+
+    /// Creates a listening socket bound to the specified address that every async worker thread
+    /// can accept connections from, for a thread-per-core architecture where each worker accepts
+    /// and handles its own connections.
+    ///
+    /// Call [`PerWorkerTcpListener::local()`] on each worker to obtain its listener. Every
+    /// connection is owned by the worker that accepted it, with no handoff between workers.
+    ///
+    /// Windows does not balance connections between multiple sockets bound to the same port, so
+    /// all workers share one listening socket, bound to the shared I/O driver. The OS hands each
+    /// incoming connection to one of the accept operations issued by the workers, so connections
+    /// are distributed among the workers that are currently accepting.
+    pub async fn bind_per_worker(addr: SocketAddr) -> io::Result<PerWorkerTcpListener> {
+        let family = socket_addr::address_family(&addr);
+        let socket = stream_socket::listen_on_shared(family, NativeSocketAddr::new(addr)).await?;
+
+        event!(Level::TRACE, message = "per-worker TCP listener bound", %addr);
+
+        Ok(PerWorkerTcpListener { socket, family })
+    }
+
+    /// Limits the number of connections accepted by this listener that may be open at the same
+    /// time. Clones of the same [`ConnectionLimit`] may be assigned to multiple listeners to
+    /// limit their connections together.
+    ///
+    /// This applies in addition to the limit for the entire runtime, if one is set via
+    /// [`RuntimeBuilder::max_connections()`][crate::rt::RuntimeBuilder::max_connections].
+    pub fn with_connection_limit(mut self, limit: ConnectionLimit) -> Self {
+        self.connection_limit = Some(limit);
+        self
+    }
+
+    /// Waits for the next incoming connection and accepts it, returning the connected stream
+    /// together with the address of the remote peer.
+    ///
+    /// You may call this multiple times concurrently to accept multiple connections in parallel.
+    ///
+    /// If a connection limit has been reached, the listener stops accepting connections until
+    /// enough of the connections it counts have been closed. Meanwhile, incoming connections wait
+    /// in the backlog of the listening socket.
+    pub async fn accept(&self) -> io::Result<(TcpStream, SocketAddr)> {
+        // We only start accepting once there is room for the connection, so connections we cannot
+        // handle yet remain in the backlog instead of being accepted and left waiting.
+        let mut connection_permits = Vec::new();
+
+        if let Some(limit) = runtime_connection_limit() {
+            connection_permits.push(limit.acquire().await);
+        }
+
+        if let Some(limit) = &self.connection_limit {
+            connection_permits.push(limit.acquire().await);
+        }
+
+        let (socket, peer_addr) = if self.shared {
+            stream_socket::accept_shared(&self.socket, self.family).await?
+        } else {
+            stream_socket::accept(&self.socket, self.family).await?
+        };
+        let peer_addr = peer_addr.to_socket_addr()?;
+
+        event!(Level::TRACE, message = "TCP connection accepted", %peer_addr);
+
+        Ok((
+            TcpStream::from_accepted_socket(socket, connection_permits),
+            peer_addr,
+        ))
+    }
+
+    /// Returns a stream that accepts incoming connections one after another, yielding each
+    /// connected stream together with the address of the remote peer. See
+    /// [`accept()`][Self::accept] for details, including how the connection limit applies.
+    pub fn incoming(&self) -> Incoming<'_, (TcpStream, SocketAddr)> {
+        Incoming::new(move || self.accept())
+    }
+
+    /// The address the listener is bound to.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        socket_addr::local_addr(**self.socket)
+    }
+}
+
+#[negative_impl]
+impl !Send for TcpListener {}
+#[negative_impl]
+impl !Sync for TcpListener {}
+
+/// A listening socket shared by all async worker threads, created via
+/// [`TcpListener::bind_per_worker()`].
+///
+/// This type can be cloned and sent to other threads, so each async worker thread can obtain its
+/// own [`TcpListener`] for the socket. The socket stops listening when the last clone and the last
+/// listener are dropped.
+///
+/// # Example
+///
+/// ```no_run
+/// use folo::net::TcpListener;
+/// use folo::rt::spawn_on_all;
+/// use std::net::SocketAddr;
+///
+/// #[folo::main]
+/// async fn main() {
+///     let per_worker = TcpListener::bind_per_worker(SocketAddr::from(([127, 0, 0, 1], 1234)))
+///         .await
+///         .unwrap();
+///
+///     let join_handles = spawn_on_all(move || {
+///         let per_worker = per_worker.clone();
+///
+///         || async move {
+///             let listener = per_worker.local();
+///
+///             loop {
+///                 let (mut stream, _) = listener.accept().await.unwrap();
+///                 stream.shutdown().await.unwrap();
+///             }
+///         }
+///     });
+///
+///     for join_handle in join_handles.into_vec() {
+///         join_handle.await;
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct PerWorkerTcpListener {
+    socket: Arc<OwnedHandle<SOCKET>>,
+    family: ADDRESS_FAMILY,
+}
+
+impl PerWorkerTcpListener {
+    /// Creates a listener that accepts connections from the shared socket on behalf of the current
+    /// async worker thread.
+    pub fn local(&self) -> TcpListener {
+        TcpListener {
+            socket: Arc::clone(&self.socket),
+            family: self.family,
+            shared: true,
+            connection_limit: None,
+        }
+    }
+
+    /// The address the listener is bound to.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        socket_addr::local_addr(**self.socket)
+    }
+}
@@ -0,0 +1,531 @@
+use crate::{
+    fs::File,
+    io::{
+        self, Buffer, OperationError, OperationResult, OperationResultExt, OperationResultFuture,
+    },
+    mem::isolation::Isolated,
+    net::{
+        socket_addr::{self, NativeSocketAddr},
+        stream_socket,
+        tcp_connection::{
+            socket_peek, socket_receive, socket_receive_exact, socket_receive_vectored,
+            socket_send, socket_send_vectored, socket_shutdown,
+        },
+        winsock, ConnectionPermit, RegisteredTcpStream, ShutdownFuture, TcpConnection,
+        TcpConnector,
+    },
+    rt::{current_async_agent, spawn_sync, SynchronousTaskType},
+    windows::OwnedHandle,
+};
+use negative_impl::negative_impl;
+#[cfg(feature = "socket2")]
+use std::os::windows::io::{FromRawSocket, IntoRawSocket};
+use std::{
+    net::{Shutdown, SocketAddr},
+    ops::Range,
+    ptr,
+    rc::Rc,
+    sync::Arc,
+};
+use tracing::{event, Level};
+use windows::{
+    core::HRESULT,
+    Win32::{
+        Foundation::{HANDLE, STATUS_NOT_SUPPORTED},
+        Networking::WinSock::{
+            bind, setsockopt, TransmitFile, LPFN_CONNECTEX, SOCKET, SOL_SOCKET, SO_SNDBUF,
+            SO_UPDATE_CONNECT_CONTEXT, WSAEOPNOTSUPP,
+        },
+    },
+};
+
+// TransmitFile can send at most 2^31 - 2 bytes per call, so larger ranges are sent in chunks.
+const MAX_TRANSMIT_FILE_BYTES: usize = i32::MAX as usize - 1;
+
+/// A TCP connection between a local and a remote socket, owned by the async worker thread that
+/// created it.
+///
+/// A stream is created either by connecting to a remote listener via [`connect()`][Self::connect]
+/// or by accepting an incoming connection via
+/// [`TcpListener::accept()`][crate::net::TcpListener::accept]. All operations on the stream are
+/// performed by the I/O driver of the current async worker thread, so the stream cannot be moved
+/// to another thread.
+///
+/// The connection is closed when the stream is dropped.
+#[derive(Debug)]
+pub struct TcpStream {
+    // This is an Arc because some operations (e.g. shutdown) involve synchronous logic and
+    // therefore we must share the socket between multiple threads.
+    pub(super) socket: Arc<OwnedHandle<SOCKET>>,
+
+    // If the stream was accepted by a listener with connection limits, the stream counts against
+    // these limits until it is dropped.
+    _connection_permits: Vec<ConnectionPermit>,
+
+    // Whether the send buffer of the socket has been disabled for zero-copy writes.
+    zero_copy: bool,
+}
+
+impl TcpStream {
+    /// Opens a TCP connection to the remote socket at the specified address.
+    ///
+    /// To connect to a host by name, use [`connect_host()`][Self::connect_host].
+    pub async fn connect(addr: SocketAddr) -> io::Result<Self> {
+        winsock::ensure_initialized();
+
+        event!(Level::TRACE, message = "connecting TCP stream", %addr);
+
+        // Creating the socket is an expensive synchronous operation, so do it on a synchronous
+        // worker thread, together with the rest of the preparations that ConnectEx requires.
+        let (socket, connect_ex) =
+            spawn_sync(SynchronousTaskType::Syscall, move || -> io::Result<_> {
+                let socket = stream_socket::new_stream_socket(socket_addr::address_family(&addr))?;
+
+                // ConnectEx requires the socket to be bound, so we let the OS pick a local address.
+                let local_addr = NativeSocketAddr::new(socket_addr::unspecified_like(&addr));
+
+                // SAFETY: All we need to be concerned about is passing in valid arguments, which
+                // we do.
+                winsock::to_io_result(unsafe {
+                    bind(*socket, local_addr.as_ptr(), local_addr.len())
+                })?;
+
+                let connect_ex = winsock::connect_ex_fn(*socket)?;
+
+                Ok((socket, connect_ex))
+            })
+            .await?;
+
+        Self::connect_bound_socket(socket, connect_ex, addr).await
+    }
+
+    /// Opens a TCP connection to the remote socket at the specified address via a socket that the
+    /// caller has created and configured (e.g. with `TCP_FASTOPEN` or bound to a specific local
+    /// address), for options that Folo does not expose. The socket is bound to an OS-assigned
+    /// local address if it has not been bound yet.
+    ///
+    /// The socket is closed if this fails.
+    #[cfg(feature = "socket2")]
+    pub async fn connect_with(socket: socket2::Socket, addr: SocketAddr) -> io::Result<Self> {
+        winsock::ensure_initialized();
+
+        event!(Level::TRACE, message = "connecting TCP stream via provided socket", %addr);
+
+        let (socket, connect_ex) =
+            spawn_sync(SynchronousTaskType::Syscall, move || -> io::Result<_> {
+                // ConnectEx requires the socket to be bound, so unless the caller already did it,
+                // we let the OS pick a local address.
+                if socket.local_addr().is_err() {
+                    socket.bind(&socket_addr::unspecified_like(&addr).into())?;
+                }
+
+                // SAFETY: Ownership of the socket is transferred to us. Sockets created by socket2
+                // are always created for overlapped I/O.
+                let socket = unsafe { OwnedHandle::new(SOCKET(socket.into_raw_socket() as usize)) };
+
+                let connect_ex = winsock::connect_ex_fn(*socket)?;
+
+                Ok((socket, connect_ex))
+            })
+            .await?;
+
+        Self::connect_bound_socket(socket, connect_ex, addr).await
+    }
+
+    /// Connects a bound socket to the remote socket at the specified address, on the I/O driver of
+    /// the current async worker thread.
+    async fn connect_bound_socket(
+        socket: OwnedHandle<SOCKET>,
+        connect_ex: LPFN_CONNECTEX,
+        addr: SocketAddr,
+    ) -> io::Result<Self> {
+        let connect_ex =
+            connect_ex.expect("connect_ex_fn() only returns Ok if the function exists");
+
+        // From now on, the socket is operated on by the I/O driver of the current thread.
+        let socket = Arc::new(socket);
+
+        current_async_agent::with_io(|io| io.bind_io_primitive(&**socket))?;
+
+        let remote_addr = NativeSocketAddr::new(addr);
+
+        let mut operation =
+            current_async_agent::with_io(|io| io.new_operation(Buffer::<Isolated>::from_pool()));
+        operation.set_primitive(**socket);
+
+        // SAFETY: We are required to pass the OVERLAPPED pointer to the native I/O function to
+        // avoid a resource leak. We do. ConnectEx copies the address before it returns, so the
+        // address does not need to outlive the callback.
+        unsafe {
+            operation.begin({
+                let socket = Arc::clone(&socket);
+
+                move |_buffer, overlapped, immediate_bytes_transferred| {
+                    if connect_ex(
+                        **socket,
+                        remote_addr.as_ptr(),
+                        remote_addr.len(),
+                        ptr::null(),
+                        0,
+                        immediate_bytes_transferred,
+                        overlapped,
+                    )
+                    .as_bool()
+                    {
+                        Ok(())
+                    } else {
+                        Err(windows::core::Error::from_win32().into())
+                    }
+                }
+            })
+        }
+        .await
+        .into_inner()?;
+
+        // Without this, the socket does not know it is connected, so functions like getpeername()
+        // and shutdown() would fail.
+        // SAFETY: All we need to be concerned about is passing in valid arguments, which we do.
+        winsock::to_io_result(unsafe {
+            setsockopt(**socket, SOL_SOCKET, SO_UPDATE_CONNECT_CONTEXT, None)
+        })?;
+
+        event!(Level::TRACE, message = "TCP stream connected", %addr);
+
+        Ok(Self::from_connected_socket(socket))
+    }
+
+    /// Opens a TCP connection to a host, specified by name or IP address, racing connection
+    /// attempts to its IPv4 and IPv6 addresses. Use [`TcpConnector`] to set a timeout or to tune
+    /// the attempts.
+    pub async fn connect_host(host: &str, port: u16) -> io::Result<Self> {
+        TcpConnector::new().connect(host, port).await
+    }
+
+    /// Takes ownership of a connected TCP socket created outside Folo (e.g. by another library or
+    /// duplicated from another process via `WSADuplicateSocket`) and binds it to the I/O driver of
+    /// the current async worker thread. The socket is closed when the stream is dropped,
+    /// including when this fails.
+    ///
+    /// # Safety
+    ///
+    /// The socket must be a valid, connected TCP socket created with `WSA_FLAG_OVERLAPPED`, which
+    /// the caller owns and does not use or close after this call. It must not already be
+    /// associated with an I/O completion port.
+    pub unsafe fn from_raw_socket(socket: SOCKET) -> io::Result<Self> {
+        winsock::ensure_initialized();
+
+        let socket = Arc::new(OwnedHandle::new(socket));
+
+        current_async_agent::with_io(|io| io.bind_io_primitive(&**socket))?;
+
+        event!(Level::TRACE, message = "TCP stream adopted");
+
+        Ok(Self::from_connected_socket(socket))
+    }
+
+    /// Releases the socket from Folo, for use with APIs that Folo does not cover (e.g. to hand it
+    /// over to another library). The socket remains connected but is no longer bound to the I/O
+    /// driver of the current async worker thread.
+    ///
+    /// Fails if an operation on the stream (e.g. a shutdown) is still in progress.
+    #[cfg(feature = "socket2")]
+    pub fn into_socket2(self) -> io::Result<socket2::Socket> {
+        let socket = Arc::try_unwrap(self.socket).map_err(|_| {
+            io::Error::LogicError(
+                "cannot release a TCP stream with an operation in progress".to_string(),
+            )
+        })?;
+
+        io::unbind_io_primitive(&*socket)?;
+
+        // SAFETY: We own the socket and give up that ownership here.
+        Ok(unsafe { socket2::Socket::from_raw_socket(SOCKET::from(socket).0 as u64) })
+    }
+
+    /// Creates a stream from a connected socket that is already bound to the I/O driver of the
+    /// current async worker thread.
+    pub(super) fn from_connected_socket(socket: Arc<OwnedHandle<SOCKET>>) -> Self {
+        Self::from_accepted_socket(socket, Vec::new())
+    }
+
+    /// Creates a stream from an accepted socket that is already bound to the I/O driver of the
+    /// current async worker thread, counting against the connection limits of the listener.
+    pub(super) fn from_accepted_socket(
+        socket: Arc<OwnedHandle<SOCKET>>,
+        connection_permits: Vec<ConnectionPermit>,
+    ) -> Self {
+        Self {
+            socket,
+            _connection_permits: connection_permits,
+            zero_copy: false,
+        }
+    }
+
+    /// Reads the next buffer of data from the stream.
+    ///
+    /// The buffer will be returned in the result with the active region set to the bytes read, with
+    /// a length of 0 if the peer has closed the connection or shut down its write direction (see
+    /// [`shutdown_with()`][Self::shutdown_with]). A connection that ends abnormally (e.g. because
+    /// it was reset by the peer) is reported as an error instead.
+    ///
+    /// You should not call this multiple times concurrently because there is no guarantee that the
+    /// continuations will be called in a particular order.
+    pub fn read(&mut self, buffer: Buffer<Isolated>) -> OperationResultFuture {
+        socket_receive(Arc::clone(&self.socket), buffer)
+    }
+
+    /// Reads the next buffer of data from the stream without consuming it, so the next read
+    /// returns the same data again. Useful for inspecting the start of a protocol (e.g. to detect
+    /// its version) before handing the stream over to the code that implements it.
+    ///
+    /// The buffer will be returned in the result with the active region set to the bytes read, with
+    /// a length of 0 if the peer has closed the connection.
+    pub fn peek(&mut self, buffer: Buffer<Isolated>) -> OperationResultFuture {
+        socket_peek(Arc::clone(&self.socket), buffer)
+    }
+
+    /// Reads from the stream until the active region of the buffer is full. This allows, for
+    /// example, a length-prefixed frame to be received without a read loop in every protocol.
+    ///
+    /// If the peer closes the connection before the buffer is full, an error is returned, with
+    /// the active region of the buffer in the error set to the bytes that were received.
+    pub async fn read_exact(&mut self, buffer: Buffer<Isolated>) -> OperationResult {
+        socket_receive_exact(Arc::clone(&self.socket), buffer).await
+    }
+
+    /// Writes the active region of the buffer to the stream.
+    ///
+    /// The buffer will be returned in the result to allow reuse.
+    ///
+    /// You may call this multiple times concurrently. The buffers will be sent in the order they
+    /// are submitted.
+    pub fn write(&mut self, buffer: Buffer<Isolated>) -> OperationResultFuture {
+        socket_send(Arc::clone(&self.socket), buffer)
+    }
+
+    /// Writes the active region of the buffer to the stream without copying it into the send
+    /// buffer of the socket. The network stack transmits the data directly from the buffer instead,
+    /// which saves a copy of every byte for large writes.
+    ///
+    /// The buffer remains in use by the operating system until the data has been transmitted, so
+    /// it is only returned in the result once the operating system no longer references it. If
+    /// the future is dropped before then, the write is canceled and the buffer goes back to the
+    /// pool only once the operating system has released it. To avoid the cost of the operating
+    /// system pinning the memory of the buffer for every write, use buffers registered via
+    /// [`BufferPool::reserve_registered()`][crate::io::BufferPool::reserve_registered].
+    ///
+    /// The first zero-copy write disables the send buffer of the socket for the rest of the life
+    /// of the connection, which also affects regular writes: every write then waits for the data
+    /// to be transmitted. Keep multiple writes in flight to keep the connection busy.
+    pub async fn write_zero_copy(&mut self, buffer: Buffer<Isolated>) -> OperationResult {
+        if !self.zero_copy {
+            let send_buffer_size: i32 = 0;
+
+            // SAFETY: All we need to be concerned about is passing in valid arguments, which we do.
+            if let Err(e) = winsock::to_io_result(unsafe {
+                setsockopt(
+                    **self.socket,
+                    SOL_SOCKET,
+                    SO_SNDBUF,
+                    Some(&send_buffer_size.to_ne_bytes()),
+                )
+            }) {
+                return Err(OperationError::new(e, buffer));
+            }
+
+            event!(
+                Level::TRACE,
+                message = "send buffer disabled for zero-copy writes"
+            );
+
+            self.zero_copy = true;
+        }
+
+        socket_send(Arc::clone(&self.socket), buffer).await
+    }
+
+    /// Reads the next data from the stream into multiple buffers with one operation, filling the
+    /// active regions of the buffers in order. This allows, for example, a header and a body to be
+    /// received into separate buffers without copying.
+    ///
+    /// Returns the buffers with the active regions set to the bytes read. Trailing buffers are
+    /// empty if less data was available than fits into the buffers, with all buffers empty if the
+    /// peer has closed the connection.
+    pub async fn read_vectored(
+        &mut self,
+        buffers: Vec<Buffer<Isolated>>,
+    ) -> io::Result<Vec<Buffer<Isolated>>> {
+        socket_receive_vectored(Arc::clone(&self.socket), buffers).await
+    }
+
+    /// Writes the active regions of multiple buffers to the stream with one operation, as if they
+    /// were one contiguous buffer. This allows, for example, a header and a body to be sent from
+    /// separate buffers without copying.
+    ///
+    /// Returns the buffers with the active regions set to the bytes that were written.
+    pub async fn write_vectored(
+        &mut self,
+        buffers: Vec<Buffer<Isolated>>,
+    ) -> io::Result<Vec<Buffer<Isolated>>> {
+        socket_send_vectored(Arc::clone(&self.socket), buffers).await
+    }
+
+    /// Sends the specified range of bytes of a file to the stream. The operating system transfers
+    /// the data from the file to the socket directly, without copying it through the memory of
+    /// the process.
+    ///
+    /// If the operating system cannot transmit the file directly, the data is transparently sent
+    /// via regular reads and writes instead.
+    ///
+    /// The range must not extend beyond the end of the file.
+    pub async fn send_file(&mut self, file: &File, range: Range<usize>) -> io::Result<()> {
+        let mut offset = range.start;
+
+        while offset < range.end {
+            let len = (range.end - offset).min(MAX_TRANSMIT_FILE_BYTES);
+
+            match transmit_file(
+                Arc::clone(&self.socket),
+                Rc::clone(file.handle()),
+                offset,
+                len,
+            )
+            .await
+            {
+                Ok(()) => offset += len,
+                Err(io::Error::Windows(e))
+                    if e.code() == STATUS_NOT_SUPPORTED.into()
+                        || e.code() == HRESULT::from_win32(WSAEOPNOTSUPP.0 as u32) =>
+                {
+                    event!(
+                        Level::DEBUG,
+                        message = "file cannot be transmitted directly, falling back to copying"
+                    );
+
+                    return self.send_file_via_buffers(file, offset..range.end).await;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn send_file_via_buffers(&mut self, file: &File, range: Range<usize>) -> io::Result<()> {
+        let mut offset = range.start;
+
+        while offset < range.end {
+            let mut buffer = Buffer::<Isolated>::from_pool();
+            buffer.set_len(buffer.len().min(range.end - offset));
+
+            let buffer = file.read_at(offset, buffer).await?;
+
+            if buffer.is_empty() {
+                return Err(io::Error::InvalidOptions(
+                    "range must not extend beyond the end of the file".to_string(),
+                ));
+            }
+
+            offset += buffer.len();
+
+            self.write(buffer).await.into_inner()?;
+        }
+
+        Ok(())
+    }
+
+    /// Performs a graceful shutdown of the connection, allowing time for all pending data transfers
+    /// to complete. After this, you may drop the stream and be assured that no data was lost in
+    /// transit - this guarantee does not exist without calling the shutdown method.
+    ///
+    /// An error result indicates that a graceful shutdown was not possible.
+    pub fn shutdown(&mut self) -> ShutdownFuture {
+        ShutdownFuture::new(Arc::clone(&self.socket))
+    }
+
+    /// Shuts down one or both directions of the connection, without waiting for the peer.
+    ///
+    /// Shutting down the write direction sends a FIN to the peer, which sees it as the end of the
+    /// stream, while you may continue to read whatever the peer sends in response until it closes
+    /// its end of the connection. Many protocols (e.g. HTTP/1.0 or some RPC framings) use this to
+    /// signal the end of a request.
+    ///
+    /// Unlike [`shutdown()`][Self::shutdown], this does not wait for the peer to close its end of
+    /// the connection, so it does not guarantee that the peer has received all the data.
+    pub async fn shutdown_with(&mut self, how: Shutdown) -> io::Result<()> {
+        socket_shutdown(Arc::clone(&self.socket), how).await
+    }
+
+    /// The address of the local end of the connection.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        socket_addr::local_addr(**self.socket)
+    }
+
+    /// The address of the remote end of the connection.
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        socket_addr::peer_addr(**self.socket)
+    }
+
+    /// Registers the stream for use on a hot path, returning a stream whose reads and writes do
+    /// not need to take a reference to the socket. See [`RegisteredTcpStream`].
+    pub fn register(self) -> RegisteredTcpStream {
+        RegisteredTcpStream::new(self)
+    }
+}
+
+/// Transmits a range of bytes of a file to a socket via the operating system, without copying the
+/// data through the memory of the process.
+async fn transmit_file(
+    socket: Arc<OwnedHandle<SOCKET>>,
+    file_handle: Rc<OwnedHandle<HANDLE>>,
+    offset: usize,
+    len: usize,
+) -> io::Result<()> {
+    let mut operation =
+        current_async_agent::with_io(|io| io.new_operation(Buffer::<Isolated>::from_pool()));
+
+    // TransmitFile reads the file at the offset specified in the OVERLAPPED structure.
+    operation.set_offset(offset);
+    operation.set_buffer_unused();
+    operation.set_primitive(**socket);
+
+    // SAFETY: We are required to pass the OVERLAPPED pointer to the native I/O function to avoid a
+    // resource leak. We do.
+    unsafe {
+        operation
+            .begin(move |_buffer, overlapped, immediate_bytes_transferred| {
+                if TransmitFile(
+                    **socket,
+                    **file_handle,
+                    len as u32,
+                    0,
+                    Some(overlapped),
+                    None,
+                    0,
+                )
+                .as_bool()
+                {
+                    // TransmitFile either sends everything or fails.
+                    *immediate_bytes_transferred = len as u32;
+                    Ok(())
+                } else {
+                    Err(windows::core::Error::from_win32().into())
+                }
+            })
+            .await
+    }
+    .into_inner()?;
+
+    Ok(())
+}
+
+impl From<TcpConnection> for TcpStream {
+    fn from(connection: TcpConnection) -> Self {
+        Self::from_connected_socket(connection.socket)
+    }
+}
+
+#[negative_impl]
+impl !Send for TcpStream {}
+#[negative_impl]
+impl !Sync for TcpStream {}
@@ -0,0 +1,192 @@
+use crate::{
+    io::{self, Buffer},
+    mem::isolation::Isolated,
+    net::{
+        socket_addr::NativeSocketAddr,
+        tcp_connection::{complete_vectored, prepare_vectored},
+        udp_message::{new_message_storage, write_control_message, UDP_SEND_MSG_SIZE},
+        winsock, ReceivedMessage,
+    },
+    rt::current_async_agent,
+    windows::OwnedHandle,
+};
+use std::{mem, net::SocketAddr, ptr, rc::Rc};
+use windows::{
+    core::PSTR,
+    Win32::Networking::WinSock::{getsockopt, WSASendMsg, IPPROTO_UDP, SOCKADDR, SOCKET, WSABUF},
+};
+
+// The largest number of bytes we send with one segmented send operation, leaving room for the
+// headers within the maximum size of an IP packet (which the OS uses internally for the batch).
+pub(super) const MAX_SEGMENTED_SEND_BYTES: usize = 65_000;
+
+/// The datagrams received by [`UdpSocket::recv_many()`][super::UdpSocket::recv_many] from one
+/// sender in one operation.
+///
+/// If receive coalescing is enabled on the socket, the OS may deliver multiple datagrams of the
+/// same size (except the last one, which may be shorter) back to back in one buffer. Otherwise,
+/// the batch contains exactly one datagram.
+#[derive(Debug)]
+pub struct ReceivedBatch {
+    buffer: Buffer<Isolated>,
+    peer_addr: SocketAddr,
+    segment_size: usize,
+    truncated: bool,
+}
+
+impl ReceivedBatch {
+    pub(super) fn new(message: ReceivedMessage) -> Self {
+        let segment_size = message
+            .coalesced_segment_size()
+            .map(|size| size as usize)
+            .unwrap_or(message.buffer().len());
+
+        let peer_addr = message.peer_addr();
+        let truncated = message.is_truncated();
+
+        Self {
+            buffer: message.into_buffer(),
+            peer_addr,
+            segment_size,
+            truncated,
+        }
+    }
+
+    /// The buffer with the active region set to the received bytes of all the datagrams.
+    pub fn buffer(&self) -> &Buffer<Isolated> {
+        &self.buffer
+    }
+
+    pub fn into_buffer(self) -> Buffer<Isolated> {
+        self.buffer
+    }
+
+    /// The address of the sender of the datagrams.
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.peer_addr
+    }
+
+    /// The size of each datagram in the batch, except the last one, which may be shorter.
+    pub fn segment_size(&self) -> usize {
+        self.segment_size
+    }
+
+    /// Whether the last datagram was larger than the rest of the buffer, in which case the buffer
+    /// only contains the part of it that fit and the rest was discarded.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// The number of datagrams in the batch.
+    pub fn len(&self) -> usize {
+        self.buffer.len().div_ceil(self.segment_size.max(1))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    /// The contents of the individual datagrams in the batch.
+    pub fn datagrams(&self) -> impl Iterator<Item = &[u8]> {
+        let segment_size = self.segment_size.max(1);
+
+        std::pin::Pin::into_inner(self.buffer.as_slice()).chunks(segment_size)
+    }
+}
+
+/// Whether the OS supports segmentation offload for the socket.
+pub(super) fn is_segmentation_supported(socket: SOCKET) -> bool {
+    let mut value: u32 = 0;
+    let mut value_len = mem::size_of::<u32>() as i32;
+
+    // SAFETY: All we need to be concerned about is passing in valid arguments, which we do.
+    winsock::to_io_result(unsafe {
+        getsockopt(
+            socket,
+            IPPROTO_UDP.0,
+            UDP_SEND_MSG_SIZE,
+            PSTR::from_raw(&mut value as *mut _ as *mut u8),
+            &mut value_len,
+        )
+    })
+    .is_ok()
+}
+
+/// Sends the active regions of the buffers as datagrams with one operation, letting the OS (or the
+/// network adapter) split the data into datagrams of `segment_size` bytes. All the buffers except
+/// the last one must be exactly `segment_size` bytes long.
+pub(super) async fn send_segmented(
+    socket: Rc<OwnedHandle<SOCKET>>,
+    buffers: Vec<Buffer<Isolated>>,
+    addr: Option<NativeSocketAddr>,
+    segment_size: u32,
+) -> io::Result<Vec<Buffer<Isolated>>> {
+    let (primary, additional_buffers, mut wsabufs) = prepare_vectored(buffers)?;
+
+    // The message storage goes last, so the bytes transferred are attributed to the data buffers.
+    let (metadata, storage) = new_message_storage();
+    additional_buffers.borrow_mut().push(metadata);
+
+    let mut operation = current_async_agent::with_io(|io| io.new_operation(primary));
+    operation.set_additional_buffers(Rc::clone(&additional_buffers));
+    operation.set_primitive(**socket);
+
+    // SAFETY: We are required to pass the OVERLAPPED pointer to the completion routine. We do.
+    // The message storage and additional buffers are kept alive by the operation until it
+    // completes. Like with other send operations, the OS captures the WSABUF array when the
+    // operation is started, so it does not need to outlive the callback.
+    let result = unsafe {
+        operation
+            .begin(move |buffer, overlapped, immediate_bytes_transferred| {
+                let storage = &mut *storage;
+
+                wsabufs[0] = WSABUF {
+                    len: buffer.len() as u32,
+                    buf: PSTR::from_raw(buffer.as_mut_ptr()),
+                };
+
+                if let Some(addr) = &addr {
+                    ptr::copy_nonoverlapping(
+                        addr.as_ptr() as *const u8,
+                        &mut storage.address as *mut _ as *mut u8,
+                        addr.len() as usize,
+                    );
+
+                    storage.header.name = &mut storage.address as *mut _ as *mut SOCKADDR;
+                    storage.header.namelen = addr.len();
+                }
+
+                storage.header.lpBuffers = wsabufs.as_mut_ptr();
+                storage.header.dwBufferCount = wsabufs.len() as u32;
+
+                let control = storage.control.as_mut_ptr() as *mut u8;
+
+                storage.header.Control = WSABUF {
+                    len: write_control_message(
+                        control,
+                        IPPROTO_UDP.0,
+                        UDP_SEND_MSG_SIZE,
+                        &segment_size,
+                    ) as u32,
+                    buf: PSTR::from_raw(control),
+                };
+
+                winsock::to_io_result(WSASendMsg(
+                    **socket,
+                    &storage.header,
+                    0,
+                    Some(immediate_bytes_transferred as *mut u32),
+                    Some(overlapped),
+                    None,
+                ))
+            })
+            .await
+    };
+
+    let mut buffers = complete_vectored(result, additional_buffers)?;
+
+    // We do not return the message storage to the caller.
+    buffers.pop();
+
+    Ok(buffers)
+}
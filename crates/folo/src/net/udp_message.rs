@@ -0,0 +1,496 @@
+use crate::{
+    io::{self, Buffer, OperationResultFuture},
+    mem::isolation::Isolated,
+    net::{
+        socket_addr::{self, NativeSocketAddr},
+        udp_socket::is_truncation,
+        winsock,
+    },
+    rt::current_async_agent,
+    windows::OwnedHandle,
+};
+use std::{
+    cell::RefCell,
+    mem,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    ptr,
+    rc::Rc,
+};
+use windows::{
+    core::PSTR,
+    Win32::Networking::WinSock::{
+        WSASendMsg, CMSGHDR, IN6_PKTINFO, IN_PKTINFO, IPPROTO_IP, IPPROTO_IPV6, IPPROTO_UDP,
+        IPV6_HOPLIMIT, IPV6_PKTINFO, IPV6_TCLASS, IP_PKTINFO, IP_TOS, IP_TTL, LPFN_WSARECVMSG,
+        SOCKADDR, SOCKADDR_STORAGE, SOCKET, SOL_SOCKET, WSABUF, WSAEMSGSIZE, WSAMSG,
+        WSA_IO_PENDING,
+    },
+};
+
+// Not yet exposed by the windows crate.
+pub(super) const SIO_TIMESTAMPING: u32 = 0x9800_00EB;
+pub(super) const TIMESTAMPING_FLAG_RX: u32 = 0x1;
+const SO_TIMESTAMP: i32 = 0x300A;
+pub(super) const UDP_SEND_MSG_SIZE: i32 = 2;
+pub(super) const UDP_RECV_MAX_COALESCED_SIZE: i32 = 3;
+const UDP_COALESCED_INFO: i32 = 3;
+
+/// The configuration passed to `SIO_TIMESTAMPING`.
+#[repr(C)]
+pub(super) struct TimestampingConfig {
+    pub flags: u32,
+    pub tx_timestamps_buffered: u16,
+}
+
+// Enough for every kind of control message we enable, with room to spare.
+const CONTROL_LENGTH: usize = 256;
+
+/// Everything the OS accesses during a message operation apart from the data itself. This must
+/// remain valid until the operation completes, which may be after the caller has stopped waiting
+/// for it, so we keep it in a pooled buffer owned by the operation.
+#[repr(C)]
+pub(super) struct MessageStorage {
+    pub header: WSAMSG,
+    pub data: WSABUF,
+    pub address: SOCKADDR_STORAGE,
+
+    // Control messages are aligned to the natural alignment of the platform.
+    pub control: [usize; CONTROL_LENGTH / mem::size_of::<usize>()],
+}
+
+/// Information about the local end of the path of a datagram: the local address it was sent to
+/// and the index of the network interface it arrived on.
+///
+/// When sending a message, this selects the source address and outgoing interface instead.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct PacketInfo {
+    local_addr: IpAddr,
+    interface: u32,
+}
+
+impl PacketInfo {
+    /// Use 0 as the interface index to let the OS choose an interface.
+    pub fn new(local_addr: IpAddr, interface: u32) -> Self {
+        Self {
+            local_addr,
+            interface,
+        }
+    }
+
+    pub fn local_addr(&self) -> IpAddr {
+        self.local_addr
+    }
+
+    /// The index of the network interface.
+    pub fn interface(&self) -> u32 {
+        self.interface
+    }
+}
+
+/// A datagram received by [`UdpSocket::recv_msg()`][super::UdpSocket::recv_msg], together with
+/// the ancillary data that was enabled on the socket.
+///
+/// Passing handles between processes via control messages (`SCM_RIGHTS` on Unix) is not available,
+/// as Windows does not support it on any socket type. Use `DuplicateHandle()` instead.
+#[derive(Debug)]
+pub struct ReceivedMessage {
+    buffer: Buffer<Isolated>,
+    peer_addr: SocketAddr,
+    truncated: bool,
+    control: ControlData,
+}
+
+impl ReceivedMessage {
+    /// The buffer with the active region set to the received bytes.
+    pub fn buffer(&self) -> &Buffer<Isolated> {
+        &self.buffer
+    }
+
+    pub fn into_buffer(self) -> Buffer<Isolated> {
+        self.buffer
+    }
+
+    /// The address of the sender of the datagram.
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.peer_addr
+    }
+
+    /// Whether the datagram was larger than the buffer, in which case the buffer only contains the
+    /// part of the datagram that fit and the rest was discarded.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// The local address and interface the datagram arrived on, if enabled via
+    /// [`UdpSocket::set_recv_packet_info()`][super::UdpSocket::set_recv_packet_info].
+    pub fn packet_info(&self) -> Option<PacketInfo> {
+        self.control.packet_info
+    }
+
+    /// The remaining time-to-live (IPv4) or hop limit (IPv6) of the datagram, if enabled via
+    /// [`UdpSocket::set_recv_ttl()`][super::UdpSocket::set_recv_ttl].
+    pub fn ttl(&self) -> Option<u8> {
+        self.control.ttl
+    }
+
+    /// The type of service (IPv4) or traffic class (IPv6) of the datagram, including the ECN
+    /// bits, if enabled via [`UdpSocket::set_recv_tos()`][super::UdpSocket::set_recv_tos].
+    pub fn tos(&self) -> Option<u8> {
+        self.control.tos
+    }
+
+    /// The time the datagram was received by the network stack, as a value of the high
+    /// resolution performance counter (see `QueryPerformanceCounter`), if enabled via
+    /// [`UdpSocket::set_recv_timestamps()`][super::UdpSocket::set_recv_timestamps].
+    pub fn timestamp(&self) -> Option<u64> {
+        self.control.timestamp
+    }
+
+    /// If the buffer contains multiple datagrams coalesced by the OS, the size of each of them
+    /// (except the last one, which may be shorter).
+    pub(super) fn coalesced_segment_size(&self) -> Option<u32> {
+        self.control.coalesced_segment_size
+    }
+}
+
+#[derive(Debug, Default, Eq, PartialEq)]
+struct ControlData {
+    packet_info: Option<PacketInfo>,
+    ttl: Option<u8>,
+    tos: Option<u8>,
+    timestamp: Option<u64>,
+    coalesced_segment_size: Option<u32>,
+}
+
+/// Obtains a pooled buffer for the message storage and initializes the storage in it. The pooled
+/// buffers are aligned to the page size, so the storage is suitably aligned.
+pub(super) fn new_message_storage() -> (Buffer<Isolated>, *mut MessageStorage) {
+    let mut metadata = Buffer::<Isolated>::from_pool();
+    assert!(metadata.len() >= mem::size_of::<MessageStorage>());
+
+    let storage = metadata.as_mut_slice().as_mut_ptr() as *mut MessageStorage;
+    debug_assert!(storage.is_aligned());
+
+    // SAFETY: The buffer is large enough and suitably aligned, as asserted above.
+    unsafe {
+        storage.write(MessageStorage {
+            header: WSAMSG::default(),
+            data: WSABUF::default(),
+            address: SOCKADDR_STORAGE::default(),
+            control: [0; CONTROL_LENGTH / mem::size_of::<usize>()],
+        });
+    }
+
+    (metadata, storage)
+}
+
+pub(super) fn send_message(
+    socket: Rc<OwnedHandle<SOCKET>>,
+    buffer: Buffer<Isolated>,
+    addr: Option<NativeSocketAddr>,
+    packet_info: Option<PacketInfo>,
+) -> OperationResultFuture {
+    let (metadata, storage) = new_message_storage();
+
+    let mut operation = current_async_agent::with_io(|io| io.new_operation(buffer));
+    operation.set_primitive(**socket);
+    operation.set_additional_buffers(Rc::new(RefCell::new(vec![metadata])));
+
+    // SAFETY: We are required to pass the OVERLAPPED pointer to the completion routine. We do.
+    // The message storage is owned by the operation, so it remains valid until it completes.
+    unsafe {
+        operation.begin(move |buffer, overlapped, immediate_bytes_transferred| {
+            let storage = &mut *storage;
+
+            storage.data = WSABUF {
+                len: buffer.len() as u32,
+                buf: PSTR::from_raw(buffer.as_mut_ptr()),
+            };
+
+            if let Some(addr) = &addr {
+                ptr::copy_nonoverlapping(
+                    addr.as_ptr() as *const u8,
+                    &mut storage.address as *mut _ as *mut u8,
+                    addr.len() as usize,
+                );
+
+                storage.header.name = &mut storage.address as *mut _ as *mut SOCKADDR;
+                storage.header.namelen = addr.len();
+            }
+
+            storage.header.lpBuffers = &mut storage.data;
+            storage.header.dwBufferCount = 1;
+
+            if let Some(packet_info) = packet_info {
+                let control = storage.control.as_mut_ptr() as *mut u8;
+
+                storage.header.Control = WSABUF {
+                    len: write_packet_info(control, packet_info) as u32,
+                    buf: PSTR::from_raw(control),
+                };
+            }
+
+            winsock::to_io_result(WSASendMsg(
+                **socket,
+                &storage.header,
+                0,
+                Some(immediate_bytes_transferred as *mut u32),
+                Some(overlapped),
+                None,
+            ))
+        })
+    }
+}
+
+pub(super) async fn receive_message(
+    socket: Rc<OwnedHandle<SOCKET>>,
+    recv_msg: LPFN_WSARECVMSG,
+    buffer: Buffer<Isolated>,
+) -> io::Result<ReceivedMessage> {
+    let recv_msg = recv_msg.expect("WSARecvMsg is looked up when the socket is created");
+
+    let (metadata, storage) = new_message_storage();
+    let additional_buffers = Rc::new(RefCell::new(vec![metadata]));
+
+    let mut operation = current_async_agent::with_io(|io| io.new_operation(buffer));
+    operation.set_primitive(**socket);
+    operation.set_additional_buffers(Rc::clone(&additional_buffers));
+
+    // SAFETY: We are required to pass the OVERLAPPED pointer to the completion routine. We do.
+    // The message storage is owned by the operation, so it remains valid until it completes.
+    let result = unsafe {
+        operation.begin(move |buffer, overlapped, immediate_bytes_transferred| {
+            let storage = &mut *storage;
+
+            storage.data = WSABUF {
+                len: buffer.len() as u32,
+                buf: PSTR::from_raw(buffer.as_mut_ptr()),
+            };
+
+            storage.header.name = &mut storage.address as *mut _ as *mut SOCKADDR;
+            storage.header.namelen = mem::size_of::<SOCKADDR_STORAGE>() as i32;
+            storage.header.lpBuffers = &mut storage.data;
+            storage.header.dwBufferCount = 1;
+            storage.header.Control = WSABUF {
+                len: CONTROL_LENGTH as u32,
+                buf: PSTR::from_raw(storage.control.as_mut_ptr() as *mut u8),
+            };
+
+            match winsock::to_io_result(recv_msg(
+                **socket,
+                &mut storage.header,
+                immediate_bytes_transferred as *mut u32,
+                overlapped,
+                None,
+            )) {
+                // Truncation is only a warning for the OS, so even if it is reported
+                // immediately, a completion notification is still posted. We must wait for
+                // it as if the operation were pending, as the OS still owns the operation.
+                Err(io::Error::Winsock { code, detail }) if detail == WSAEMSGSIZE => {
+                    Err(io::Error::Winsock {
+                        code,
+                        detail: WSA_IO_PENDING,
+                    })
+                }
+                result => result,
+            }
+        })
+    }
+    .await;
+
+    let (buffer, truncated) = match result {
+        Ok(buffer) => (buffer, false),
+        Err(e) => {
+            let (error, buffer) = e.into_inner_and_buffer();
+
+            // The active region is set to the part of the datagram that fit into the buffer.
+            if !is_truncation(&error) {
+                return Err(error);
+            }
+
+            (buffer, true)
+        }
+    };
+
+    // The operation has completed, so the OS no longer accesses the message storage. The active
+    // region of the metadata buffer was reset by the operation but the storage is still there.
+    let mut metadata = additional_buffers
+        .borrow_mut()
+        .pop()
+        .expect("the message storage is returned by the operation");
+    metadata.set_len(mem::size_of::<MessageStorage>());
+
+    // SAFETY: The storage was initialized when we created it and the buffer is aligned.
+    let storage = unsafe { &*(metadata.as_slice().as_ptr() as *const MessageStorage) };
+
+    let peer_addr =
+        NativeSocketAddr::from_storage(storage.address, storage.header.namelen).to_socket_addr()?;
+
+    let control_len = (storage.header.Control.len as usize).min(CONTROL_LENGTH);
+
+    // SAFETY: The OS wrote this many bytes of control messages into the control buffer.
+    let control =
+        unsafe { std::slice::from_raw_parts(storage.control.as_ptr() as *const u8, control_len) };
+
+    Ok(ReceivedMessage {
+        buffer,
+        peer_addr,
+        truncated,
+        control: parse_control(control),
+    })
+}
+
+/// Writes a packet info control message to the start of the control buffer, returning the number
+/// of bytes used.
+///
+/// # Safety
+///
+/// The control buffer must be aligned to the natural alignment of the platform and large enough.
+unsafe fn write_packet_info(control: *mut u8, packet_info: PacketInfo) -> usize {
+    match packet_info.local_addr {
+        IpAddr::V4(addr) => {
+            let info = IN_PKTINFO {
+                ipi_addr: socket_addr::native_ipv4(addr),
+                ipi_ifindex: packet_info.interface,
+            };
+
+            write_control_message(control, IPPROTO_IP.0, IP_PKTINFO, &info)
+        }
+        IpAddr::V6(addr) => {
+            let info = IN6_PKTINFO {
+                ipi6_addr: socket_addr::native_ipv6(addr),
+                ipi6_ifindex: packet_info.interface,
+            };
+
+            write_control_message(control, IPPROTO_IPV6.0, IPV6_PKTINFO, &info)
+        }
+    }
+}
+
+/// Writes a control message with the specified value to the start of the control buffer,
+/// returning the number of bytes used.
+///
+/// # Safety
+///
+/// The control buffer must be aligned to the natural alignment of the platform and large enough.
+pub(super) unsafe fn write_control_message<T: Copy>(
+    control: *mut u8,
+    level: i32,
+    kind: i32,
+    value: &T,
+) -> usize {
+    let header_len = align(mem::size_of::<CMSGHDR>());
+
+    (control as *mut CMSGHDR).write(CMSGHDR {
+        cmsg_len: header_len + mem::size_of::<T>(),
+        cmsg_level: level,
+        cmsg_type: kind,
+    });
+
+    (control.add(header_len) as *mut T).write_unaligned(*value);
+
+    header_len + align(mem::size_of::<T>())
+}
+
+/// Extracts the ancillary data we understand from the control messages received with a datagram,
+/// ignoring any others.
+fn parse_control(control: &[u8]) -> ControlData {
+    let mut result = ControlData::default();
+
+    let header_len = align(mem::size_of::<CMSGHDR>());
+    let mut offset = 0;
+
+    while offset + header_len <= control.len() {
+        // SAFETY: We just checked that the header is within the bounds of the control buffer.
+        let header = unsafe { ptr::read_unaligned(control.as_ptr().add(offset) as *const CMSGHDR) };
+
+        if header.cmsg_len < header_len || offset + header.cmsg_len > control.len() {
+            // Malformed or truncated - there is nothing more we can trust.
+            break;
+        }
+
+        let data = &control[offset + header_len..offset + header.cmsg_len];
+
+        match (header.cmsg_level, header.cmsg_type) {
+            (level, IP_PKTINFO) if level == IPPROTO_IP.0 => {
+                if data.len() >= mem::size_of::<IN_PKTINFO>() {
+                    // SAFETY: We just checked that the data is large enough.
+                    let info = unsafe { ptr::read_unaligned(data.as_ptr() as *const IN_PKTINFO) };
+
+                    result.packet_info = Some(PacketInfo {
+                        // SAFETY: All views of the address union are plain bytes.
+                        local_addr: IpAddr::V4(Ipv4Addr::from(unsafe {
+                            info.ipi_addr.S_un.S_addr.to_ne_bytes()
+                        })),
+                        interface: info.ipi_ifindex,
+                    });
+                }
+            }
+            (level, IPV6_PKTINFO) if level == IPPROTO_IPV6.0 => {
+                if data.len() >= mem::size_of::<IN6_PKTINFO>() {
+                    // SAFETY: We just checked that the data is large enough.
+                    let info = unsafe { ptr::read_unaligned(data.as_ptr() as *const IN6_PKTINFO) };
+
+                    result.packet_info = Some(PacketInfo {
+                        // SAFETY: All views of the address union are plain bytes.
+                        local_addr: IpAddr::V6(Ipv6Addr::from(unsafe { info.ipi6_addr.u.Byte })),
+                        interface: info.ipi6_ifindex,
+                    });
+                }
+            }
+            // The values are delivered as integers but only the lowest byte is meaningful, which
+            // on a little-endian platform is the first one.
+            (level, IP_TTL) if level == IPPROTO_IP.0 => result.ttl = data.first().copied(),
+            (level, IPV6_HOPLIMIT) if level == IPPROTO_IPV6.0 => result.ttl = data.first().copied(),
+            (level, IP_TOS) if level == IPPROTO_IP.0 => result.tos = data.first().copied(),
+            (level, IPV6_TCLASS) if level == IPPROTO_IPV6.0 => result.tos = data.first().copied(),
+            (level, UDP_COALESCED_INFO) if level == IPPROTO_UDP.0 => {
+                if let Ok(bytes) = data[..data.len().min(4)].try_into() {
+                    result.coalesced_segment_size = Some(u32::from_ne_bytes(bytes));
+                }
+            }
+            (SOL_SOCKET, SO_TIMESTAMP) => {
+                if let Ok(bytes) = data[..data.len().min(8)].try_into() {
+                    result.timestamp = Some(u64::from_ne_bytes(bytes));
+                }
+            }
+            _ => {}
+        }
+
+        offset += align(header.cmsg_len);
+    }
+
+    result
+}
+
+/// Rounds up a length to the natural alignment of the platform, as used for control messages.
+const fn align(len: usize) -> usize {
+    (len + mem::size_of::<usize>() - 1) & !(mem::size_of::<usize>() - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packet_info_round_trip() {
+        let mut control = [0usize; CONTROL_LENGTH / mem::size_of::<usize>()];
+
+        let packet_info = PacketInfo::new(IpAddr::V4(Ipv4Addr::new(10, 1, 2, 3)), 7);
+
+        // SAFETY: The control buffer is aligned and large enough.
+        let len = unsafe { write_packet_info(control.as_mut_ptr() as *mut u8, packet_info) };
+
+        // SAFETY: We just wrote this many bytes into the control buffer.
+        let control = unsafe { std::slice::from_raw_parts(control.as_ptr() as *const u8, len) };
+
+        let parsed = parse_control(control);
+        assert_eq!(parsed.packet_info, Some(packet_info));
+        assert_eq!(parsed.ttl, None);
+    }
+
+    #[test]
+    fn malformed_control_is_ignored() {
+        let control = [0xFFu8; 64];
+        assert_eq!(parse_control(&control), ControlData::default());
+    }
+}
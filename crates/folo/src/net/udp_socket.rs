@@ -0,0 +1,765 @@
+use crate::{
+    io::{self, Buffer, OperationResultExt, OperationResultFuture},
+    mem::isolation::Isolated,
+    net::{
+        socket_addr::{self, NativeSocketAddr},
+        udp_batch::{is_segmentation_supported, send_segmented, MAX_SEGMENTED_SEND_BYTES},
+        udp_message::{
+            receive_message, send_message, TimestampingConfig, SIO_TIMESTAMPING,
+            TIMESTAMPING_FLAG_RX, UDP_RECV_MAX_COALESCED_SIZE,
+        },
+        winsock, PacketInfo, ReceivedBatch, ReceivedMessage,
+    },
+    rt::{current_async_agent, spawn_sync, SynchronousTaskType},
+    windows::OwnedHandle,
+};
+use negative_impl::negative_impl;
+use std::{
+    cell::Cell,
+    ffi::c_void,
+    mem,
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr},
+    ptr,
+    rc::Rc,
+    slice,
+};
+use tracing::{event, Level};
+use windows::{
+    core::PSTR,
+    Win32::{
+        Foundation::{BOOL, FALSE, STATUS_BUFFER_OVERFLOW},
+        Networking::WinSock::{
+            bind, connect, setsockopt, WSAIoctl, WSARecvFrom, WSASendTo, WSASocketA, IPPROTO,
+            IPPROTO_IP, IPPROTO_IPV6, IPPROTO_UDP, IPV6_ADD_MEMBERSHIP, IPV6_DROP_MEMBERSHIP,
+            IPV6_HOPLIMIT, IPV6_MREQ, IPV6_MULTICAST_HOPS, IPV6_MULTICAST_IF, IPV6_MULTICAST_LOOP,
+            IPV6_PKTINFO, IPV6_RECVTCLASS, IP_ADD_MEMBERSHIP, IP_DROP_MEMBERSHIP, IP_MREQ,
+            IP_MULTICAST_IF, IP_MULTICAST_LOOP, IP_MULTICAST_TTL, IP_PKTINFO, IP_RECVTOS,
+            IP_RECVTTL, LPFN_WSARECVMSG, MSG_PEEK, SEND_RECV_FLAGS, SIO_UDP_CONNRESET, SOCKADDR,
+            SOCKADDR_STORAGE, SOCKET, SOCK_DGRAM, WSABUF, WSAEMSGSIZE, WSA_FLAG_OVERLAPPED,
+            WSA_IO_PENDING,
+        },
+    },
+};
+
+// The address of the sender of a received datagram is written by the OS when the operation
+// completes, which may be after the caller has stopped waiting for it. Therefore, we cannot keep it
+// in the future - instead, we reserve space for it at the end of the receive buffer, which lives
+// for as long as the operation.
+const ADDRESS_LENGTH: usize = mem::size_of::<SOCKADDR_STORAGE>();
+
+/// A UDP socket, owned by the async worker thread that created it.
+///
+/// All operations on the socket are performed by the I/O driver of the current async worker
+/// thread, so the socket cannot be moved to another thread. Every operation transfers exactly one
+/// datagram, using the buffer provided by the caller (typically a pooled buffer obtained via
+/// `Buffer::<Isolated>::from_pool()`).
+///
+/// You may start any number of send and receive operations concurrently. Each receive operation
+/// receives one whole datagram, though there is no guarantee about which of the concurrent
+/// operations receives which datagram.
+///
+/// # Example
+///
+/// ```no_run
+/// use folo::io::{Buffer, OperationResultExt};
+/// use folo::mem::isolation::Isolated;
+/// use folo::net::UdpSocket;
+/// use std::net::SocketAddr;
+///
+/// #[folo::main]
+/// async fn main() {
+///     let socket = UdpSocket::bind(SocketAddr::from(([127, 0, 0, 1], 1234)))
+///         .await
+///         .unwrap();
+///
+///     loop {
+///         let datagram = socket
+///             .recv_from(Buffer::<Isolated>::from_pool())
+///             .await
+///             .unwrap();
+///
+///         if datagram.is_truncated() {
+///             // Not a valid message for our protocol.
+///             continue;
+///         }
+///
+///         // Echo the datagram back to the sender.
+///         let peer_addr = datagram.peer_addr();
+///         socket
+///             .send_to(datagram.into_buffer(), peer_addr)
+///             .await
+///             .into_inner()
+///             .unwrap();
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct UdpSocket {
+    socket: Rc<OwnedHandle<SOCKET>>,
+
+    // Always `Some`, looked up when the socket is created.
+    recv_msg: LPFN_WSARECVMSG,
+
+    // Whether the OS supports segmentation offload for the socket, once we have checked.
+    segmentation_supported: Cell<Option<bool>>,
+}
+
+impl UdpSocket {
+    /// The number of bytes at the end of the active region of a receive buffer that are reserved
+    /// for the address of the sender. The rest of the active region receives the datagram.
+    pub const RECEIVE_ADDRESS_SPACE: usize = ADDRESS_LENGTH + mem::size_of::<i32>();
+
+    /// Creates a UDP socket bound to the specified address. Use port 0 to let the OS assign a
+    /// port, which you can then look up via [`local_addr()`][Self::local_addr].
+    pub async fn bind(addr: SocketAddr) -> io::Result<Self> {
+        winsock::ensure_initialized();
+
+        // Creating the socket is an expensive synchronous operation, so do it on a synchronous
+        // worker thread.
+        let (socket, recv_msg) =
+            spawn_sync(SynchronousTaskType::Syscall, move || -> io::Result<_> {
+                // SAFETY: All we need to worry about here is cleanup, which we do via OwnedHandle.
+                let socket = unsafe {
+                    OwnedHandle::new(WSASocketA(
+                        socket_addr::address_family(&addr).0 as i32,
+                        SOCK_DGRAM.0,
+                        IPPROTO_UDP.0,
+                        None,
+                        0,
+                        WSA_FLAG_OVERLAPPED,
+                    )?)
+                };
+
+                let native_addr = NativeSocketAddr::new(addr);
+
+                // SAFETY: All we need to be concerned about is passing in valid arguments, which we
+                // do.
+                winsock::to_io_result(unsafe {
+                    bind(*socket, native_addr.as_ptr(), native_addr.len())
+                })?;
+
+                let recv_msg = prepare_socket(*socket)?;
+
+                Ok((socket, recv_msg))
+            })
+            .await?;
+
+        let socket = Rc::new(socket);
+
+        current_async_agent::with_io(|io| io.bind_io_primitive(&**socket))?;
+
+        event!(Level::TRACE, message = "UDP socket bound", %addr);
+
+        Ok(Self {
+            socket,
+            recv_msg,
+            segmentation_supported: Cell::new(None),
+        })
+    }
+
+    /// Takes ownership of a UDP socket created outside Folo (e.g. by another library or
+    /// duplicated from another process via `WSADuplicateSocket`) and binds it to the I/O driver of
+    /// the current async worker thread. The socket is closed when the `UdpSocket` is dropped,
+    /// including when this fails.
+    ///
+    /// The socket is configured just like one created via [`bind()`][Self::bind], e.g. to ignore
+    /// ICMP "port unreachable" responses.
+    ///
+    /// # Safety
+    ///
+    /// The socket must be a valid UDP socket created with `WSA_FLAG_OVERLAPPED`, which the caller
+    /// owns and does not use or close after this call. It must not already be associated with an
+    /// I/O completion port.
+    pub unsafe fn from_raw_socket(socket: SOCKET) -> io::Result<Self> {
+        winsock::ensure_initialized();
+
+        let socket = Rc::new(OwnedHandle::new(socket));
+
+        let recv_msg = prepare_socket(**socket)?;
+
+        current_async_agent::with_io(|io| io.bind_io_primitive(&**socket))?;
+
+        event!(Level::TRACE, message = "UDP socket adopted");
+
+        Ok(Self {
+            socket,
+            recv_msg,
+            segmentation_supported: Cell::new(None),
+        })
+    }
+
+    /// Sets the default destination of datagrams sent via [`send()`][Self::send] and limits the
+    /// datagrams received via [`recv()`][Self::recv] to those sent by this address.
+    ///
+    /// This only updates the state of the local socket and completes immediately - there is no
+    /// handshake with the peer.
+    pub fn connect(&self, addr: SocketAddr) -> io::Result<()> {
+        let native_addr = NativeSocketAddr::new(addr);
+
+        // SAFETY: All we need to be concerned about is passing in valid arguments, which we do.
+        winsock::to_io_result(unsafe {
+            connect(**self.socket, native_addr.as_ptr(), native_addr.len())
+        })
+    }
+
+    /// Sends the active region of the buffer as a single datagram to the specified address.
+    ///
+    /// The buffer will be returned in the result to allow reuse.
+    pub fn send_to(&self, buffer: Buffer<Isolated>, addr: SocketAddr) -> OperationResultFuture {
+        send_datagram(
+            Rc::clone(&self.socket),
+            buffer,
+            Some(NativeSocketAddr::new(addr)),
+        )
+    }
+
+    /// Sends the active region of the buffer as a single datagram to the address the socket is
+    /// connected to.
+    ///
+    /// The buffer will be returned in the result to allow reuse.
+    pub fn send(&self, buffer: Buffer<Isolated>) -> OperationResultFuture {
+        send_datagram(Rc::clone(&self.socket), buffer, None)
+    }
+
+    /// Receives the next datagram, together with the address of its sender.
+    ///
+    /// The last [`RECEIVE_ADDRESS_SPACE`][Self::RECEIVE_ADDRESS_SPACE] bytes of the active region
+    /// of the buffer are reserved for the address of the sender, with the datagram received into
+    /// the rest of the active region. If the datagram does not fit, it is truncated and the
+    /// returned datagram reports this via [`is_truncated()`][ReceivedDatagram::is_truncated].
+    pub async fn recv_from(&self, buffer: Buffer<Isolated>) -> io::Result<ReceivedDatagram> {
+        receive_datagram(Rc::clone(&self.socket), buffer, SEND_RECV_FLAGS::default()).await
+    }
+
+    /// Receives the next datagram from the address the socket is connected to.
+    ///
+    /// The buffer is used in the same way as by [`recv_from()`][Self::recv_from].
+    pub async fn recv(&self, buffer: Buffer<Isolated>) -> io::Result<ReceivedDatagram> {
+        receive_datagram(Rc::clone(&self.socket), buffer, SEND_RECV_FLAGS::default()).await
+    }
+
+    /// Receives the next datagram, together with the address of its sender, without removing it
+    /// from the receive queue of the socket, so the next receive operation returns the same
+    /// datagram again.
+    ///
+    /// The buffer is used in the same way as by [`recv_from()`][Self::recv_from].
+    pub async fn peek_from(&self, buffer: Buffer<Isolated>) -> io::Result<ReceivedDatagram> {
+        receive_datagram(Rc::clone(&self.socket), buffer, MSG_PEEK).await
+    }
+
+    /// Receives the next datagram from the address the socket is connected to, without removing
+    /// it from the receive queue of the socket.
+    ///
+    /// The buffer is used in the same way as by [`recv_from()`][Self::recv_from].
+    pub async fn peek(&self, buffer: Buffer<Isolated>) -> io::Result<ReceivedDatagram> {
+        receive_datagram(Rc::clone(&self.socket), buffer, MSG_PEEK).await
+    }
+
+    /// Sends the active region of the buffer as a single datagram, optionally selecting the local
+    /// address and network interface to send it from via a packet info control message.
+    ///
+    /// If no destination address is specified, the datagram is sent to the address the socket is
+    /// connected to. The buffer will be returned in the result to allow reuse.
+    pub fn send_msg(
+        &self,
+        buffer: Buffer<Isolated>,
+        addr: Option<SocketAddr>,
+        packet_info: Option<PacketInfo>,
+    ) -> OperationResultFuture {
+        send_message(
+            Rc::clone(&self.socket),
+            buffer,
+            addr.map(NativeSocketAddr::new),
+            packet_info,
+        )
+    }
+
+    /// Receives the next datagram, together with the address of its sender and the ancillary data
+    /// enabled via [`set_recv_packet_info()`][Self::set_recv_packet_info],
+    /// [`set_recv_ttl()`][Self::set_recv_ttl], [`set_recv_tos()`][Self::set_recv_tos] and
+    /// [`set_recv_timestamps()`][Self::set_recv_timestamps].
+    ///
+    /// Unlike [`recv_from()`][Self::recv_from], the entire active region of the buffer receives
+    /// the datagram. If the datagram does not fit, it is truncated and the returned message
+    /// reports this via [`is_truncated()`][ReceivedMessage::is_truncated].
+    pub async fn recv_msg(&self, buffer: Buffer<Isolated>) -> io::Result<ReceivedMessage> {
+        receive_message(Rc::clone(&self.socket), self.recv_msg, buffer).await
+    }
+
+    /// Sends the active region of each buffer as a separate datagram, to the specified address or
+    /// (if none is specified) to the address the socket is connected to.
+    ///
+    /// If all the buffers except the last one have the same length and the last one is no
+    /// longer, the datagrams are sent with as few operations as possible using segmentation
+    /// offload, which greatly reduces the cost per datagram. Otherwise, or if the OS does not
+    /// support segmentation offload, the datagrams are sent with one operation each.
+    ///
+    /// The buffers will be returned in the result to allow reuse.
+    pub async fn send_many(
+        &self,
+        buffers: Vec<Buffer<Isolated>>,
+        addr: Option<SocketAddr>,
+    ) -> io::Result<Vec<Buffer<Isolated>>> {
+        let addr = addr.map(NativeSocketAddr::new);
+
+        let Some(segment_size) = self.segment_size(&buffers) else {
+            // We start all the operations before waiting for any of them to complete.
+            let sends = buffers
+                .into_iter()
+                .map(|buffer| send_datagram(Rc::clone(&self.socket), buffer, addr))
+                .collect::<Vec<_>>();
+
+            let mut buffers = Vec::with_capacity(sends.len());
+
+            for send in sends {
+                buffers.push(send.await.into_inner()?);
+            }
+
+            return Ok(buffers);
+        };
+
+        let segments_per_send = (MAX_SEGMENTED_SEND_BYTES / segment_size).max(1);
+
+        let mut sent = Vec::with_capacity(buffers.len());
+        let mut buffers = buffers.into_iter().peekable();
+
+        while buffers.peek().is_some() {
+            let batch = buffers.by_ref().take(segments_per_send).collect();
+
+            sent.extend(
+                send_segmented(Rc::clone(&self.socket), batch, addr, segment_size as u32).await?,
+            );
+        }
+
+        Ok(sent)
+    }
+
+    /// Receives the next batch of datagrams from one sender. Unless receive coalescing is enabled
+    /// via [`set_recv_coalescing()`][Self::set_recv_coalescing], the batch contains exactly one
+    /// datagram.
+    ///
+    /// The entire active region of the buffer receives the datagrams.
+    pub async fn recv_many(&self, buffer: Buffer<Isolated>) -> io::Result<ReceivedBatch> {
+        let message = receive_message(Rc::clone(&self.socket), self.recv_msg, buffer).await?;
+
+        Ok(ReceivedBatch::new(message))
+    }
+
+    /// Allows the OS to coalesce up to `max_bytes` of datagrams of the same size from the same
+    /// sender and deliver them to [`recv_many()`][Self::recv_many] as one batch, which greatly
+    /// reduces the cost per datagram. Use 0 to disable coalescing, which is the default.
+    ///
+    /// While coalescing is enabled, receive datagrams only via [`recv_many()`][Self::recv_many],
+    /// as the other receive operations would treat a batch as one large datagram.
+    ///
+    /// Requires Windows 11 or newer.
+    pub fn set_recv_coalescing(&self, max_bytes: u32) -> io::Result<()> {
+        self.set_option(IPPROTO_UDP, UDP_RECV_MAX_COALESCED_SIZE, &max_bytes)
+    }
+
+    /// Whether datagrams received via [`recv_msg()`][Self::recv_msg] report the local address
+    /// and network interface they arrived on. Disabled by default.
+    pub fn set_recv_packet_info(&self, value: bool) -> io::Result<()> {
+        let value = u32::from(value);
+
+        if self.local_addr()?.is_ipv4() {
+            self.set_option(IPPROTO_IP, IP_PKTINFO, &value)
+        } else {
+            self.set_option(IPPROTO_IPV6, IPV6_PKTINFO, &value)
+        }
+    }
+
+    /// Whether datagrams received via [`recv_msg()`][Self::recv_msg] report their remaining
+    /// time-to-live (IPv4) or hop limit (IPv6). Disabled by default.
+    pub fn set_recv_ttl(&self, value: bool) -> io::Result<()> {
+        let value = u32::from(value);
+
+        if self.local_addr()?.is_ipv4() {
+            self.set_option(IPPROTO_IP, IP_RECVTTL, &value)
+        } else {
+            self.set_option(IPPROTO_IPV6, IPV6_HOPLIMIT, &value)
+        }
+    }
+
+    /// Whether datagrams received via [`recv_msg()`][Self::recv_msg] report their type of service
+    /// (IPv4) or traffic class (IPv6). Disabled by default.
+    pub fn set_recv_tos(&self, value: bool) -> io::Result<()> {
+        let value = u32::from(value);
+
+        if self.local_addr()?.is_ipv4() {
+            self.set_option(IPPROTO_IP, IP_RECVTOS, &value)
+        } else {
+            self.set_option(IPPROTO_IPV6, IPV6_RECVTCLASS, &value)
+        }
+    }
+
+    /// Whether datagrams received via [`recv_msg()`][Self::recv_msg] report the time they were
+    /// received by the network stack. Disabled by default.
+    ///
+    /// Requires Windows 10 version 2004 or newer.
+    pub fn set_recv_timestamps(&self, value: bool) -> io::Result<()> {
+        let config = TimestampingConfig {
+            flags: if value { TIMESTAMPING_FLAG_RX } else { 0 },
+            tx_timestamps_buffered: 0,
+        };
+
+        let mut bytes_returned: u32 = 0;
+
+        // SAFETY: All we need to be concerned about is passing in valid arguments, which we do.
+        winsock::to_io_result(unsafe {
+            WSAIoctl(
+                **self.socket,
+                SIO_TIMESTAMPING,
+                Some(&config as *const _ as *const c_void),
+                mem::size_of::<TimestampingConfig>() as u32,
+                None,
+                0,
+                &mut bytes_returned as *mut _,
+                None,
+                None,
+            )
+        })
+    }
+
+    /// The address the socket is bound to.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        socket_addr::local_addr(**self.socket)
+    }
+
+    /// The address the socket is connected to.
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        socket_addr::peer_addr(**self.socket)
+    }
+
+    /// Joins the IPv4 multicast group with the specified address on the network interface with the
+    /// specified address, after which the socket receives datagrams sent to the group.
+    ///
+    /// Use [`Ipv4Addr::UNSPECIFIED`] as the interface to let the OS choose an interface.
+    pub fn join_multicast_v4(&self, group: Ipv4Addr, interface: Ipv4Addr) -> io::Result<()> {
+        let request = IP_MREQ {
+            imr_multiaddr: socket_addr::native_ipv4(group),
+            imr_interface: socket_addr::native_ipv4(interface),
+        };
+
+        self.set_option(IPPROTO_IP, IP_ADD_MEMBERSHIP, &request)
+    }
+
+    /// Leaves an IPv4 multicast group previously joined via
+    /// [`join_multicast_v4()`][Self::join_multicast_v4] with the same arguments.
+    pub fn leave_multicast_v4(&self, group: Ipv4Addr, interface: Ipv4Addr) -> io::Result<()> {
+        let request = IP_MREQ {
+            imr_multiaddr: socket_addr::native_ipv4(group),
+            imr_interface: socket_addr::native_ipv4(interface),
+        };
+
+        self.set_option(IPPROTO_IP, IP_DROP_MEMBERSHIP, &request)
+    }
+
+    /// Joins the IPv6 multicast group with the specified address on the network interface with the
+    /// specified index, after which the socket receives datagrams sent to the group.
+    ///
+    /// Use 0 as the interface index to let the OS choose an interface.
+    pub fn join_multicast_v6(&self, group: Ipv6Addr, interface: u32) -> io::Result<()> {
+        let request = IPV6_MREQ {
+            ipv6mr_multiaddr: socket_addr::native_ipv6(group),
+            ipv6mr_interface: interface,
+        };
+
+        self.set_option(IPPROTO_IPV6, IPV6_ADD_MEMBERSHIP, &request)
+    }
+
+    /// Leaves an IPv6 multicast group previously joined via
+    /// [`join_multicast_v6()`][Self::join_multicast_v6] with the same arguments.
+    pub fn leave_multicast_v6(&self, group: Ipv6Addr, interface: u32) -> io::Result<()> {
+        let request = IPV6_MREQ {
+            ipv6mr_multiaddr: socket_addr::native_ipv6(group),
+            ipv6mr_interface: interface,
+        };
+
+        self.set_option(IPPROTO_IPV6, IPV6_DROP_MEMBERSHIP, &request)
+    }
+
+    /// Sets the network interface (identified by its address) used to send IPv4 multicast
+    /// datagrams. By default, the OS chooses an interface.
+    pub fn set_multicast_interface_v4(&self, interface: Ipv4Addr) -> io::Result<()> {
+        // This option takes the address in network byte order, which is what the octets are in.
+        let value = u32::from_ne_bytes(interface.octets());
+
+        self.set_option(IPPROTO_IP, IP_MULTICAST_IF, &value)
+    }
+
+    /// Sets the network interface (identified by its index) used to send IPv6 multicast
+    /// datagrams. By default (0), the OS chooses an interface.
+    pub fn set_multicast_interface_v6(&self, interface: u32) -> io::Result<()> {
+        self.set_option(IPPROTO_IPV6, IPV6_MULTICAST_IF, &interface)
+    }
+
+    /// Whether IPv4 multicast datagrams sent by this socket are also delivered to sockets on the
+    /// local machine that have joined the group. Enabled by default.
+    pub fn set_multicast_loop_v4(&self, value: bool) -> io::Result<()> {
+        self.set_option(IPPROTO_IP, IP_MULTICAST_LOOP, &u32::from(value))
+    }
+
+    /// Whether IPv6 multicast datagrams sent by this socket are also delivered to sockets on the
+    /// local machine that have joined the group. Enabled by default.
+    pub fn set_multicast_loop_v6(&self, value: bool) -> io::Result<()> {
+        self.set_option(IPPROTO_IPV6, IPV6_MULTICAST_LOOP, &u32::from(value))
+    }
+
+    /// Sets the time-to-live of IPv4 multicast datagrams sent by this socket, limiting how many
+    /// routers they may pass through. The default is 1, which keeps them on the local network.
+    pub fn set_multicast_ttl_v4(&self, value: u32) -> io::Result<()> {
+        self.set_option(IPPROTO_IP, IP_MULTICAST_TTL, &value)
+    }
+
+    /// Sets the hop limit of IPv6 multicast datagrams sent by this socket, limiting how many
+    /// routers they may pass through. The default is 1, which keeps them on the local network.
+    pub fn set_multicast_hops_v6(&self, value: u32) -> io::Result<()> {
+        self.set_option(IPPROTO_IPV6, IPV6_MULTICAST_HOPS, &value)
+    }
+
+    /// Returns the segment size to use for sending the buffers with segmentation offload, or
+    /// `None` if they must be sent one by one.
+    fn segment_size(&self, buffers: &[Buffer<Isolated>]) -> Option<usize> {
+        let (last, rest) = buffers.split_last()?;
+        let segment_size = rest.first()?.len();
+
+        if segment_size == 0
+            || rest.iter().any(|buffer| buffer.len() != segment_size)
+            || last.len() > segment_size
+        {
+            return None;
+        }
+
+        let supported = match self.segmentation_supported.get() {
+            Some(supported) => supported,
+            None => {
+                let supported = is_segmentation_supported(**self.socket);
+                self.segmentation_supported.set(Some(supported));
+                supported
+            }
+        };
+
+        supported.then_some(segment_size)
+    }
+
+    fn set_option<T>(&self, level: IPPROTO, name: i32, value: &T) -> io::Result<()> {
+        // SAFETY: The option values are plain data structures without padding, so viewing them as
+        // bytes is valid. The slice only lives for the duration of the call below.
+        let value_bytes =
+            unsafe { slice::from_raw_parts(value as *const T as *const u8, mem::size_of::<T>()) };
+
+        // SAFETY: All we need to be concerned about is passing in valid arguments, which we do.
+        winsock::to_io_result(unsafe {
+            setsockopt(**self.socket, level.0, name, Some(value_bytes))
+        })
+    }
+}
+
+/// Applies the configuration that every UDP socket requires and looks up the extension functions
+/// we use with it.
+fn prepare_socket(socket: SOCKET) -> io::Result<LPFN_WSARECVMSG> {
+    // By default, an ICMP "port unreachable" response to a datagram we sent makes the next receive
+    // operation fail, which is never what a UDP server wants - one misbehaving peer would disrupt
+    // the traffic of all peers.
+    let connreset_enabled: BOOL = FALSE;
+    let mut bytes_returned: u32 = 0;
+
+    // SAFETY: All we need to be concerned about is passing in valid arguments, which we do.
+    winsock::to_io_result(unsafe {
+        WSAIoctl(
+            socket,
+            SIO_UDP_CONNRESET,
+            Some(&connreset_enabled as *const _ as *const c_void),
+            mem::size_of::<BOOL>() as u32,
+            None,
+            0,
+            &mut bytes_returned as *mut _,
+            None,
+            None,
+        )
+    })?;
+
+    winsock::recv_msg_fn(socket)
+}
+
+#[negative_impl]
+impl !Send for UdpSocket {}
+#[negative_impl]
+impl !Sync for UdpSocket {}
+
+/// A datagram received by a [`UdpSocket`] or a [`RawSocket`][super::RawSocket].
+#[derive(Debug)]
+pub struct ReceivedDatagram {
+    buffer: Buffer<Isolated>,
+    peer_addr: SocketAddr,
+    truncated: bool,
+}
+
+impl ReceivedDatagram {
+    /// The buffer with the active region set to the received bytes.
+    pub fn buffer(&self) -> &Buffer<Isolated> {
+        &self.buffer
+    }
+
+    pub fn into_buffer(self) -> Buffer<Isolated> {
+        self.buffer
+    }
+
+    /// The address of the sender of the datagram.
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.peer_addr
+    }
+
+    /// Whether the datagram was larger than the buffer, in which case the buffer only contains the
+    /// part of the datagram that fit and the rest was discarded.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+}
+
+pub(super) fn send_datagram(
+    socket: Rc<OwnedHandle<SOCKET>>,
+    buffer: Buffer<Isolated>,
+    addr: Option<NativeSocketAddr>,
+) -> OperationResultFuture {
+    let mut operation = current_async_agent::with_io(|io| io.new_operation(buffer));
+    operation.set_primitive(**socket);
+
+    // SAFETY: We are required to pass the OVERLAPPED pointer to the completion routine. We do.
+    // The destination address is captured by the OS when the operation is started, so it does not
+    // need to outlive the callback.
+    unsafe {
+        operation.begin(move |buffer, overlapped, immediate_bytes_transferred| {
+            let wsabuf = WSABUF {
+                len: buffer.len() as u32,
+                buf: PSTR::from_raw(buffer.as_mut_ptr()),
+            };
+
+            let wsabufs = [wsabuf];
+
+            let (addr_ptr, addr_len) = match &addr {
+                Some(addr) => (Some(addr.as_ptr()), addr.len()),
+                None => (None, 0),
+            };
+
+            winsock::to_io_result(WSASendTo(
+                **socket,
+                &wsabufs,
+                Some(immediate_bytes_transferred as *mut u32),
+                0,
+                addr_ptr,
+                addr_len,
+                Some(overlapped),
+                None,
+            ))
+        })
+    }
+}
+
+pub(super) async fn receive_datagram(
+    socket: Rc<OwnedHandle<SOCKET>>,
+    buffer: Buffer<Isolated>,
+    flags: SEND_RECV_FLAGS,
+) -> io::Result<ReceivedDatagram> {
+    if buffer.len() <= UdpSocket::RECEIVE_ADDRESS_SPACE {
+        return Err(io::Error::InvalidOptions(format!(
+            "a buffer for receiving a datagram must be longer than {} bytes",
+            UdpSocket::RECEIVE_ADDRESS_SPACE
+        )));
+    }
+
+    let data_len = buffer.len() - UdpSocket::RECEIVE_ADDRESS_SPACE;
+
+    let mut operation = current_async_agent::with_io(|io| io.new_operation(buffer));
+    operation.set_primitive(**socket);
+
+    // SAFETY: We are required to pass the OVERLAPPED pointer to the completion routine. We do.
+    // The address storage is part of the buffer, so it remains valid until the operation completes.
+    let result = unsafe {
+        operation.begin(move |buffer, overlapped, immediate_bytes_transferred| {
+            let (data, address) = buffer.split_at_mut(data_len);
+
+            let wsabuf = WSABUF {
+                len: data.len() as u32,
+                buf: PSTR::from_raw(data.as_mut_ptr()),
+            };
+
+            let wsabufs = [wsabuf];
+            let mut flags = flags.0 as u32;
+
+            // The buffer has no alignment guarantees, so the OS may need to write these
+            // unaligned. This is fine for the platforms we support.
+            let address_ptr = address.as_mut_ptr() as *mut SOCKADDR;
+            let address_len_ptr = address.as_mut_ptr().add(ADDRESS_LENGTH) as *mut i32;
+            address_len_ptr.write_unaligned(ADDRESS_LENGTH as i32);
+
+            match winsock::to_io_result(WSARecvFrom(
+                **socket,
+                &wsabufs,
+                Some(immediate_bytes_transferred as *mut u32),
+                &mut flags as *mut u32,
+                Some(address_ptr),
+                Some(address_len_ptr),
+                Some(overlapped),
+                None,
+            )) {
+                // Truncation is only a warning for the OS, so even if it is reported
+                // immediately, a completion notification is still posted. We must wait for
+                // it as if the operation were pending, as the OS still owns the operation.
+                Err(io::Error::Winsock { code, detail }) if detail == WSAEMSGSIZE => {
+                    Err(io::Error::Winsock {
+                        code,
+                        detail: WSA_IO_PENDING,
+                    })
+                }
+                result => result,
+            }
+        })
+    }
+    .await;
+
+    let (mut buffer, truncated) = match result {
+        Ok(buffer) => (buffer, false),
+        Err(e) => {
+            let (error, buffer) = e.into_inner_and_buffer();
+
+            // The active region is set to the part of the datagram that fit into the buffer.
+            if !is_truncation(&error) {
+                return Err(error);
+            }
+
+            (buffer, true)
+        }
+    };
+
+    // We briefly extend the active region to read the sender address from the end of the buffer.
+    let received_len = buffer.len();
+    buffer.set_len(data_len + UdpSocket::RECEIVE_ADDRESS_SPACE);
+
+    let peer_addr = {
+        let address = &buffer.as_slice()[data_len..];
+
+        // SAFETY: The reserved space is large enough for both values and we read them unaligned.
+        let (storage, len) = unsafe {
+            (
+                ptr::read_unaligned(address.as_ptr() as *const SOCKADDR_STORAGE),
+                ptr::read_unaligned(address.as_ptr().add(ADDRESS_LENGTH) as *const i32),
+            )
+        };
+
+        NativeSocketAddr::from_storage(storage, len).to_socket_addr()?
+    };
+
+    buffer.set_len(received_len);
+
+    Ok(ReceivedDatagram {
+        buffer,
+        peer_addr,
+        truncated,
+    })
+}
+
+pub(super) fn is_truncation(error: &io::Error) -> bool {
+    matches!(error, io::Error::Windows(e) if e.code() == STATUS_BUFFER_OVERFLOW.to_hresult())
+}
@@ -0,0 +1,88 @@
+use crate::{
+    io,
+    net::{socket_addr::NativeSocketAddr, stream_socket, Incoming, UnixStream},
+    windows::OwnedHandle,
+};
+use negative_impl::negative_impl;
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use tracing::{event, Level};
+use windows::Win32::Networking::WinSock::{AF_UNIX, SOCKET};
+
+/// A Unix domain socket that listens for incoming connections, owned by the async worker thread
+/// that created it. Every accepted connection is owned by the current async worker.
+///
+/// Unix domain sockets require Windows 10 version 1803 or newer.
+///
+/// The socket stops listening when the listener is dropped. The socket file is not removed when
+/// the listener is dropped - remove it before binding to the same path again.
+///
+/// # Example
+///
+/// ```no_run
+/// use folo::net::UnixListener;
+///
+/// #[folo::main]
+/// async fn main() {
+///     let listener = UnixListener::bind(r"C:\Temp\my_service.sock").await.unwrap();
+///
+///     loop {
+///         let mut stream = listener.accept().await.unwrap();
+///         stream.shutdown().await.unwrap();
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct UnixListener {
+    // This is an Arc because the socket must remain alive until any pending accept operation on
+    // it has completed, even if the listener is dropped in the meantime.
+    socket: Arc<OwnedHandle<SOCKET>>,
+
+    path: PathBuf,
+}
+
+impl UnixListener {
+    /// Creates a listener bound to the specified path. The path must not already exist.
+    pub async fn bind(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+
+        let socket =
+            stream_socket::listen_on(AF_UNIX, NativeSocketAddr::from_unix_path(&path)?).await?;
+
+        event!(
+            Level::TRACE,
+            message = "Unix domain socket listener bound",
+            ?path
+        );
+
+        Ok(Self { socket, path })
+    }
+
+    /// Waits for the next incoming connection and accepts it.
+    ///
+    /// You may call this multiple times concurrently to accept multiple connections in parallel.
+    pub async fn accept(&self) -> io::Result<UnixStream> {
+        // Connecting sockets are rarely bound to a path, so we do not bother with the peer address.
+        let (socket, _) = stream_socket::accept(&self.socket, AF_UNIX).await?;
+
+        Ok(UnixStream::from_connected_socket(socket))
+    }
+
+    /// Returns a stream that accepts incoming connections one after another. See
+    /// [`accept()`][Self::accept] for details.
+    pub fn incoming(&self) -> Incoming<'_, UnixStream> {
+        Incoming::new(move || self.accept())
+    }
+
+    /// The path the listener is bound to.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+#[negative_impl]
+impl !Send for UnixListener {}
+#[negative_impl]
+impl !Sync for UnixListener {}
@@ -0,0 +1,155 @@
+use crate::{
+    io::{self, Buffer, OperationResult, OperationResultFuture},
+    mem::isolation::Isolated,
+    net::{
+        socket_addr::NativeSocketAddr,
+        stream_socket,
+        tcp_connection::{
+            socket_peek, socket_receive, socket_receive_exact, socket_receive_vectored,
+            socket_send, socket_send_vectored,
+        },
+        winsock, ShutdownFuture,
+    },
+    rt::{current_async_agent, spawn_sync, SynchronousTaskType},
+    windows::OwnedHandle,
+};
+use negative_impl::negative_impl;
+use std::{path::Path, sync::Arc};
+use tracing::{event, Level};
+use windows::Win32::Networking::WinSock::{connect, AF_UNIX, SOCKET};
+
+/// A connection between two Unix domain sockets, owned by the async worker thread that created it.
+///
+/// A stream is created either by connecting to a listener via [`connect()`][Self::connect] or by
+/// accepting an incoming connection via
+/// [`UnixListener::accept()`][crate::net::UnixListener::accept]. All operations on the stream are
+/// performed by the I/O driver of the current async worker thread, so the stream cannot be moved
+/// to another thread.
+///
+/// Unix domain sockets require Windows 10 version 1803 or newer. Windows only supports stream
+/// sockets in the Unix domain, so there is no datagram counterpart to this type.
+///
+/// The connection is closed when the stream is dropped.
+#[derive(Debug)]
+pub struct UnixStream {
+    // This is an Arc because some operations (e.g. shutdown) involve synchronous logic and
+    // therefore we must share the socket between multiple threads.
+    pub(super) socket: Arc<OwnedHandle<SOCKET>>,
+}
+
+impl UnixStream {
+    /// Connects to the Unix domain socket listening at the specified path.
+    pub async fn connect(path: impl AsRef<Path>) -> io::Result<Self> {
+        winsock::ensure_initialized();
+
+        let addr = NativeSocketAddr::from_unix_path(path.as_ref())?;
+
+        event!(Level::TRACE, message = "connecting Unix domain socket stream", path = ?path.as_ref());
+
+        // ConnectEx does not support Unix domain sockets. Connecting to a local listener does not
+        // involve any network round trips, so we connect synchronously on a synchronous worker
+        // thread, together with the expensive socket creation.
+        let socket = spawn_sync(SynchronousTaskType::Syscall, move || -> io::Result<_> {
+            let socket = stream_socket::new_stream_socket(AF_UNIX)?;
+
+            // SAFETY: All we need to be concerned about is passing in valid arguments, which we do.
+            winsock::to_io_result(unsafe { connect(*socket, addr.as_ptr(), addr.len()) })?;
+
+            Ok(socket)
+        })
+        .await?;
+
+        // From now on, the socket is operated on by the I/O driver of the current thread.
+        let socket = Arc::new(socket);
+
+        current_async_agent::with_io(|io| io.bind_io_primitive(&**socket))?;
+
+        Ok(Self { socket })
+    }
+
+    /// Creates a stream from a connected socket that is already bound to the I/O driver of the
+    /// current async worker thread.
+    pub(super) fn from_connected_socket(socket: Arc<OwnedHandle<SOCKET>>) -> Self {
+        Self { socket }
+    }
+
+    /// Reads the next buffer of data from the stream.
+    ///
+    /// The buffer will be returned in the result with the active region set to the bytes read, with
+    /// a length of 0 if the peer has closed the connection.
+    ///
+    /// You should not call this multiple times concurrently because there is no guarantee that the
+    /// continuations will be called in a particular order.
+    pub fn read(&mut self, buffer: Buffer<Isolated>) -> OperationResultFuture {
+        socket_receive(Arc::clone(&self.socket), buffer)
+    }
+
+    /// Reads the next buffer of data from the stream without consuming it, so the next read
+    /// returns the same data again. Useful for inspecting the start of a protocol (e.g. to detect
+    /// its version) before handing the stream over to the code that implements it.
+    ///
+    /// The buffer will be returned in the result with the active region set to the bytes read, with
+    /// a length of 0 if the peer has closed the connection.
+    pub fn peek(&mut self, buffer: Buffer<Isolated>) -> OperationResultFuture {
+        socket_peek(Arc::clone(&self.socket), buffer)
+    }
+
+    /// Reads from the stream until the active region of the buffer is full. This allows, for
+    /// example, a length-prefixed frame to be received without a read loop in every protocol.
+    ///
+    /// If the peer closes the connection before the buffer is full, an error is returned, with
+    /// the active region of the buffer in the error set to the bytes that were received.
+    pub async fn read_exact(&mut self, buffer: Buffer<Isolated>) -> OperationResult {
+        socket_receive_exact(Arc::clone(&self.socket), buffer).await
+    }
+
+    /// Writes the active region of the buffer to the stream.
+    ///
+    /// The buffer will be returned in the result to allow reuse.
+    ///
+    /// You may call this multiple times concurrently. The buffers will be sent in the order they
+    /// are submitted.
+    pub fn write(&mut self, buffer: Buffer<Isolated>) -> OperationResultFuture {
+        socket_send(Arc::clone(&self.socket), buffer)
+    }
+
+    /// Reads the next data from the stream into multiple buffers with one operation, filling the
+    /// active regions of the buffers in order. This allows, for example, a header and a body to be
+    /// received into separate buffers without copying.
+    ///
+    /// Returns the buffers with the active regions set to the bytes read. Trailing buffers are
+    /// empty if less data was available than fits into the buffers, with all buffers empty if the
+    /// peer has closed the connection.
+    pub async fn read_vectored(
+        &mut self,
+        buffers: Vec<Buffer<Isolated>>,
+    ) -> io::Result<Vec<Buffer<Isolated>>> {
+        socket_receive_vectored(Arc::clone(&self.socket), buffers).await
+    }
+
+    /// Writes the active regions of multiple buffers to the stream with one operation, as if they
+    /// were one contiguous buffer. This allows, for example, a header and a body to be sent from
+    /// separate buffers without copying.
+    ///
+    /// Returns the buffers with the active regions set to the bytes that were written.
+    pub async fn write_vectored(
+        &mut self,
+        buffers: Vec<Buffer<Isolated>>,
+    ) -> io::Result<Vec<Buffer<Isolated>>> {
+        socket_send_vectored(Arc::clone(&self.socket), buffers).await
+    }
+
+    /// Performs a graceful shutdown of the connection, allowing time for all pending data transfers
+    /// to complete. After this, you may drop the stream and be assured that no data was lost in
+    /// transit - this guarantee does not exist without calling the shutdown method.
+    ///
+    /// An error result indicates that a graceful shutdown was not possible.
+    pub fn shutdown(&mut self) -> ShutdownFuture {
+        ShutdownFuture::new(Arc::clone(&self.socket))
+    }
+}
+
+#[negative_impl]
+impl !Send for UnixStream {}
+#[negative_impl]
+impl !Sync for UnixStream {}
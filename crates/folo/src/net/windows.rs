@@ -0,0 +1,7 @@
+pub(crate) mod named_pipe;
+mod named_pipe_client;
+mod named_pipe_server;
+
+pub use named_pipe::*;
+pub use named_pipe_client::*;
+pub use named_pipe_server::*;
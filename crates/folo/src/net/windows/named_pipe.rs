@@ -0,0 +1,159 @@
+use crate::{
+    io::{self, Buffer, OperationResultExt},
+    mem::isolation::Isolated,
+    rt::current_async_agent,
+    windows::OwnedHandle,
+};
+use std::rc::Rc;
+use windows::{
+    core::HRESULT,
+    Win32::{
+        Foundation::{
+            ERROR_BROKEN_PIPE, ERROR_IO_PENDING, ERROR_MORE_DATA, HANDLE, STATUS_BUFFER_OVERFLOW,
+            STATUS_PIPE_BROKEN,
+        },
+        Storage::FileSystem::{ReadFile, WriteFile},
+    },
+};
+
+/// The result of reading from a named pipe in message mode: the buffer with the active region set
+/// to the bytes read, which may be only a part of a message if the message did not fit.
+#[derive(Debug)]
+pub struct MessageRead {
+    buffer: Buffer<Isolated>,
+    is_message_end: bool,
+}
+
+impl MessageRead {
+    pub fn buffer(&self) -> &Buffer<Isolated> {
+        &self.buffer
+    }
+
+    pub fn into_buffer(self) -> Buffer<Isolated> {
+        self.buffer
+    }
+
+    /// Whether the read reached the end of the message. If not, the rest of the message can be
+    /// obtained by reading again.
+    pub fn is_message_end(&self) -> bool {
+        self.is_message_end
+    }
+}
+
+/// Reads from a pipe bound to the I/O driver of the current async worker thread.
+///
+/// Returns a buffer with the active region set to the bytes read, with a length of 0 if the other
+/// end of the pipe has been closed.
+pub(crate) async fn read(
+    handle: Rc<OwnedHandle<HANDLE>>,
+    buffer: Buffer<Isolated>,
+) -> io::Result<Buffer<Isolated>> {
+    match read_core(handle, buffer).await? {
+        // In byte mode, there are no message boundaries to report.
+        ReadOutcome::Complete(buffer) | ReadOutcome::Partial(buffer) => Ok(buffer),
+    }
+}
+
+/// Reads the next message (or the next part of a message, if it does not fit into the buffer)
+/// from a pipe in message read mode, bound to the I/O driver of the current async worker thread.
+pub(super) async fn read_message(
+    handle: Rc<OwnedHandle<HANDLE>>,
+    buffer: Buffer<Isolated>,
+) -> io::Result<MessageRead> {
+    Ok(match read_core(handle, buffer).await? {
+        ReadOutcome::Complete(buffer) => MessageRead {
+            buffer,
+            is_message_end: true,
+        },
+        ReadOutcome::Partial(buffer) => MessageRead {
+            buffer,
+            is_message_end: false,
+        },
+    })
+}
+
+/// Writes the active region of the buffer to a pipe bound to the I/O driver of the current async
+/// worker thread. In message mode, each write is one message.
+///
+/// Returns the buffer with the active region set to the bytes that were written.
+pub(crate) async fn write(
+    handle: Rc<OwnedHandle<HANDLE>>,
+    buffer: Buffer<Isolated>,
+) -> io::Result<Buffer<Isolated>> {
+    let mut operation = current_async_agent::with_io(|io| io.new_operation(buffer));
+    operation.set_primitive(**handle);
+
+    // SAFETY: We are required to pass the OVERLAPPED pointer to the native I/O function to avoid a
+    // resource leak. We do.
+    unsafe {
+        operation.begin(move |buffer, overlapped, immediate_bytes_transferred| {
+            Ok(WriteFile(
+                **handle,
+                Some(&*buffer),
+                Some(immediate_bytes_transferred as *mut _),
+                Some(overlapped),
+            )?)
+        })
+    }
+    .await
+    .into_inner()
+}
+
+enum ReadOutcome {
+    Complete(Buffer<Isolated>),
+
+    // The message did not fit into the buffer. Only possible in message read mode.
+    Partial(Buffer<Isolated>),
+}
+
+async fn read_core(
+    handle: Rc<OwnedHandle<HANDLE>>,
+    buffer: Buffer<Isolated>,
+) -> io::Result<ReadOutcome> {
+    let mut operation = current_async_agent::with_io(|io| io.new_operation(buffer));
+    operation.set_primitive(**handle);
+
+    // SAFETY: We are required to pass the OVERLAPPED pointer to the native I/O function to avoid a
+    // resource leak. We do.
+    let result = unsafe {
+        operation.begin(move |buffer, overlapped, immediate_bytes_transferred| {
+            match ReadFile(
+                **handle,
+                Some(buffer),
+                Some(immediate_bytes_transferred as *mut _),
+                Some(overlapped),
+            ) {
+                Ok(()) => Ok(()),
+                // A partial message is only a warning for the OS, so even if it is reported
+                // immediately, a completion notification is still posted. We must wait for
+                // it as if the operation were pending, as the OS still owns the operation.
+                Err(e) if e.code() == ERROR_MORE_DATA.into() => {
+                    Err(windows::core::Error::from(HRESULT::from(ERROR_IO_PENDING)).into())
+                }
+                Err(e) => Err(e.into()),
+            }
+        })
+    }
+    .await;
+
+    match result {
+        Ok(buffer) => Ok(ReadOutcome::Complete(buffer)),
+        Err(io::OperationError {
+            inner: io::Error::Windows(external),
+            buffer,
+            ..
+        }) if external.code() == STATUS_BUFFER_OVERFLOW.into() => Ok(ReadOutcome::Partial(buffer)),
+        // The other end has closed the pipe. We report this as the end of the stream.
+        Err(io::OperationError {
+            inner: io::Error::Windows(external),
+            mut buffer,
+            ..
+        }) if external.code() == STATUS_PIPE_BROKEN.into()
+            || external.code() == ERROR_BROKEN_PIPE.into() =>
+        {
+            buffer.set_len(0);
+            Ok(ReadOutcome::Complete(buffer))
+        }
+        Err(e) => Err(e.into_inner()),
+    }
+}
@@ -0,0 +1,149 @@
+use crate::{
+    io::{self, Buffer},
+    mem::isolation::Isolated,
+    net::windows::{named_pipe, MessageRead},
+    rt::{current_async_agent, spawn_sync, SynchronousTaskType},
+    windows::OwnedHandle,
+};
+use negative_impl::negative_impl;
+use std::{ffi::CString, rc::Rc};
+use tracing::{event, Level};
+use windows::{
+    core::PCSTR,
+    Win32::{
+        Foundation::HANDLE,
+        Storage::FileSystem::{
+            CreateFileA, FILE_FLAG_OVERLAPPED, FILE_GENERIC_READ, FILE_GENERIC_WRITE,
+            FILE_SHARE_NONE, OPEN_EXISTING,
+        },
+        System::Pipes::{SetNamedPipeHandleState, PIPE_READMODE_MESSAGE},
+    },
+};
+
+/// Connects to a named pipe created by a [`NamedPipeServerBuilder`][super::NamedPipeServerBuilder]
+/// on the same machine.
+///
+/// Connecting fails with `ERROR_PIPE_BUSY` if all instances of the pipe are serving other clients.
+/// In this case, the caller may try again after a short delay.
+#[derive(Clone, Debug)]
+pub struct NamedPipeClientBuilder {
+    name: Option<String>,
+    message_mode: bool,
+}
+
+impl NamedPipeClientBuilder {
+    pub fn new() -> Self {
+        Self {
+            name: None,
+            message_mode: false,
+        }
+    }
+
+    /// Sets the name of the pipe, in the form `\\.\pipe\<name>`.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Whether to read the data as a stream of messages instead of a stream of bytes, which
+    /// allows the message boundaries to be observed via `read_message()`. Requires the pipe to
+    /// have been created in message mode.
+    pub fn message_mode(mut self, value: bool) -> Self {
+        self.message_mode = value;
+        self
+    }
+
+    /// Connects to the pipe, binding the client to the current async worker thread.
+    pub async fn build(self) -> io::Result<NamedPipeClient> {
+        let name = self
+            .name
+            .ok_or_else(|| io::Error::InvalidOptions("name must be set".to_string()))?;
+
+        let name_cstr = CString::new(name.as_str()).map_err(|_| {
+            io::Error::InvalidOptions("name must not contain NUL characters".to_string())
+        })?;
+
+        let message_mode = self.message_mode;
+
+        // Opening the pipe is a blocking operation, so we kick it off to a synchronous worker
+        // thread to avoid blocking the async workers with this slow call.
+        let handle = spawn_sync(SynchronousTaskType::Syscall, move || -> io::Result<_> {
+            // SAFETY: The name string outlives the call and the handle is ours to close anywhere.
+            let handle = unsafe {
+                OwnedHandle::new(CreateFileA(
+                    PCSTR::from_raw(name_cstr.as_ptr() as *const u8),
+                    (FILE_GENERIC_READ | FILE_GENERIC_WRITE).0,
+                    FILE_SHARE_NONE,
+                    None,
+                    OPEN_EXISTING,
+                    FILE_FLAG_OVERLAPPED,
+                    None,
+                )?)
+            };
+
+            // The client end of a pipe always starts out in byte read mode.
+            if message_mode {
+                // SAFETY: Handle liveness is ensured by our ownership of the handle.
+                unsafe {
+                    SetNamedPipeHandleState(*handle, Some(&PIPE_READMODE_MESSAGE), None, None)
+                }?;
+            }
+
+            Ok(handle)
+        })
+        .await?;
+
+        // From now on the handle does not leave the current thread.
+        let handle = Rc::new(handle);
+
+        current_async_agent::with_io(|io| io.bind_io_primitive(&**handle))?;
+
+        event!(Level::TRACE, message = "connected to named pipe", %name);
+
+        Ok(NamedPipeClient { handle })
+    }
+}
+
+impl Default for NamedPipeClientBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The client end of a named pipe, owned by the async worker thread that connected it. Created via
+/// [`NamedPipeClientBuilder`].
+///
+/// The connection is closed when the client is dropped.
+#[derive(Debug)]
+pub struct NamedPipeClient {
+    handle: Rc<OwnedHandle<HANDLE>>,
+}
+
+impl NamedPipeClient {
+    /// Reads the next buffer of data from the pipe.
+    ///
+    /// Returns the buffer with the active region set to the bytes read, with a length of 0 if the
+    /// server has closed its end of the pipe.
+    pub async fn read(&self, buffer: Buffer<Isolated>) -> io::Result<Buffer<Isolated>> {
+        named_pipe::read(Rc::clone(&self.handle), buffer).await
+    }
+
+    /// Reads the next message from a pipe connected in message mode. If the message does not fit
+    /// into the buffer, the rest of it can be obtained by reading again.
+    pub async fn read_message(&self, buffer: Buffer<Isolated>) -> io::Result<MessageRead> {
+        named_pipe::read_message(Rc::clone(&self.handle), buffer).await
+    }
+
+    /// Writes the active region of the buffer to the pipe. If the pipe was created in message
+    /// mode, the buffer is written as one message.
+    ///
+    /// Returns the buffer with the active region set to the bytes that were written.
+    pub async fn write(&self, buffer: Buffer<Isolated>) -> io::Result<Buffer<Isolated>> {
+        named_pipe::write(Rc::clone(&self.handle), buffer).await
+    }
+}
+
+#[negative_impl]
+impl !Send for NamedPipeClient {}
+#[negative_impl]
+impl !Sync for NamedPipeClient {}
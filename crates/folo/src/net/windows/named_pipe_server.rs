@@ -0,0 +1,307 @@
+use crate::{
+    io::{self, Buffer, OperationResultExt},
+    mem::isolation::Isolated,
+    net::{
+        windows::{named_pipe, MessageRead},
+        Incoming,
+    },
+    rt::{current_async_agent, spawn_sync, SynchronousTaskType},
+    windows::OwnedHandle,
+};
+use negative_impl::negative_impl;
+use std::{cell::Cell, ffi::CString, mem, rc::Rc};
+use tracing::{event, Level};
+use windows::{
+    core::PCSTR,
+    Win32::{
+        Foundation::{ERROR_PIPE_CONNECTED, HANDLE},
+        Storage::FileSystem::{
+            FILE_FLAG_FIRST_PIPE_INSTANCE, FILE_FLAG_OVERLAPPED, PIPE_ACCESS_DUPLEX,
+        },
+        System::Pipes::{
+            ConnectNamedPipe, CreateNamedPipeA, DisconnectNamedPipe, PIPE_READMODE_BYTE,
+            PIPE_READMODE_MESSAGE, PIPE_REJECT_REMOTE_CLIENTS, PIPE_TYPE_BYTE, PIPE_TYPE_MESSAGE,
+            PIPE_UNLIMITED_INSTANCES, PIPE_WAIT,
+        },
+    },
+};
+
+// The size of the buffers the OS reserves for each direction of a pipe instance. This is only a
+// hint - the OS grows the buffers as needed.
+const PIPE_BUFFER_SIZE_BYTES: u32 = 64 * 1024;
+
+/// Creates instances of a named pipe, to which [`NamedPipeClient`][super::NamedPipeClient]s on
+/// the same machine can connect.
+///
+/// Each instance serves one client at a time. To serve multiple clients concurrently, create a new
+/// instance (by building a clone of the same builder) whenever a client connects to the previous
+/// one. The OS distributes connecting clients among the instances waiting for a connection.
+///
+/// # Example
+///
+/// ```no_run
+/// use folo::net::windows::NamedPipeServerBuilder;
+/// use folo::rt::spawn;
+/// use std::mem;
+///
+/// #[folo::main]
+/// async fn main() {
+///     let builder = NamedPipeServerBuilder::new().name(r"\\.\pipe\my-service");
+///
+///     // The first instance makes sure we are the owner of the pipe name.
+///     let mut server = builder.clone().first_instance(true).build().await.unwrap();
+///
+///     loop {
+///         server.connect().await.unwrap();
+///
+///         // The next client can connect to a new instance while we serve this one.
+///         let connected = mem::replace(&mut server, builder.clone().build().await.unwrap());
+///
+///         spawn(async move {
+///             // Serve the client via `connected.read()` and `connected.write()`.
+///             drop(connected);
+///         });
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct NamedPipeServerBuilder {
+    name: Option<String>,
+    message_mode: bool,
+    max_instances: Option<u8>,
+    first_instance: bool,
+}
+
+impl NamedPipeServerBuilder {
+    pub fn new() -> Self {
+        Self {
+            name: None,
+            message_mode: false,
+            max_instances: None,
+            first_instance: false,
+        }
+    }
+
+    /// Sets the name of the pipe, in the form `\\.\pipe\<name>`.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Whether the pipe transfers data as a stream of messages instead of a stream of bytes. In
+    /// message mode, every write is delivered as one message and the message boundaries can be
+    /// observed via `read_message()`.
+    ///
+    /// All instances of the pipe must use the same mode.
+    pub fn message_mode(mut self, value: bool) -> Self {
+        self.message_mode = value;
+        self
+    }
+
+    /// Limits the number of instances of the pipe that may exist at the same time, up to 254.
+    /// By default, the number of instances is not limited.
+    ///
+    /// All instances of the pipe must use the same limit.
+    pub fn max_instances(mut self, value: u8) -> Self {
+        self.max_instances = Some(value);
+        self
+    }
+
+    /// Whether building the instance must fail if an instance of the pipe already exists. Use this
+    /// for the first instance, to guard against another process having already taken the name.
+    pub fn first_instance(mut self, value: bool) -> Self {
+        self.first_instance = value;
+        self
+    }
+
+    /// Creates a new instance of the pipe, bound to the current async worker thread.
+    pub async fn build(self) -> io::Result<NamedPipeServer> {
+        let name = self
+            .name
+            .ok_or_else(|| io::Error::InvalidOptions("name must be set".to_string()))?;
+
+        let max_instances = match self.max_instances {
+            None => PIPE_UNLIMITED_INSTANCES,
+            Some(value @ 1..=254) => value as u32,
+            Some(value) => {
+                return Err(io::Error::InvalidOptions(format!(
+                    "max_instances must be between 1 and 254 but was {value}"
+                )))
+            }
+        };
+
+        let name_cstr = CString::new(name.as_str()).map_err(|_| {
+            io::Error::InvalidOptions("name must not contain NUL characters".to_string())
+        })?;
+
+        let mut open_mode = PIPE_ACCESS_DUPLEX | FILE_FLAG_OVERLAPPED;
+
+        if self.first_instance {
+            open_mode |= FILE_FLAG_FIRST_PIPE_INSTANCE;
+        }
+
+        let pipe_type = if self.message_mode {
+            PIPE_TYPE_MESSAGE | PIPE_READMODE_MESSAGE
+        } else {
+            PIPE_TYPE_BYTE | PIPE_READMODE_BYTE
+        };
+
+        // Named pipes can also be accessed over the network but this is not something we want to
+        // expose by accident, so remote clients are always rejected.
+        let pipe_mode = pipe_type | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS;
+
+        // Creating the pipe is a blocking operation, so we kick it off to a synchronous worker
+        // thread to avoid blocking the async workers with this slow call.
+        let handle = spawn_sync(SynchronousTaskType::Syscall, move || -> io::Result<_> {
+            // SAFETY: The name string outlives the call and the handle is ours to close anywhere.
+            let handle = unsafe {
+                CreateNamedPipeA(
+                    PCSTR::from_raw(name_cstr.as_ptr() as *const u8),
+                    open_mode,
+                    pipe_mode,
+                    max_instances,
+                    PIPE_BUFFER_SIZE_BYTES,
+                    PIPE_BUFFER_SIZE_BYTES,
+                    0,
+                    None,
+                )
+            };
+
+            if handle.is_invalid() {
+                return Err(windows::core::Error::from_win32().into());
+            }
+
+            // SAFETY: The handle is ours to close anywhere.
+            Ok(unsafe { OwnedHandle::new(handle) })
+        })
+        .await?;
+
+        // From now on the handle does not leave the current thread.
+        let handle = Rc::new(handle);
+
+        current_async_agent::with_io(|io| io.bind_io_primitive(&**handle))?;
+
+        event!(Level::TRACE, message = "named pipe instance created", %name);
+
+        Ok(NamedPipeServer { handle })
+    }
+
+    /// Returns a stream that yields pipe instances with a connected client, one after another.
+    ///
+    /// Whenever a client connects, a new instance is created for the next client to connect to
+    /// while the caller serves this one. If [`first_instance()`][Self::first_instance] is set, it
+    /// only applies to the first instance created by the stream.
+    pub fn incoming(self) -> Incoming<'static, NamedPipeServer> {
+        // The instance that the next client will connect to, if we have already created it.
+        let waiting = Rc::new(Cell::new(None::<NamedPipeServer>));
+        let mut first_instance = self.first_instance;
+
+        Incoming::new(move || {
+            let builder = self.clone().first_instance(mem::take(&mut first_instance));
+            let next_builder = self.clone().first_instance(false);
+            let waiting = Rc::clone(&waiting);
+
+            async move {
+                let server = match waiting.take() {
+                    Some(server) => server,
+                    None => builder.build().await?,
+                };
+
+                server.connect().await?;
+
+                // If this fails, the next accept tries again, leaving the error to be reported
+                // from there instead of discarding a perfectly good connection.
+                if let Ok(next) = next_builder.build().await {
+                    waiting.set(Some(next));
+                }
+
+                Ok(server)
+            }
+        })
+    }
+}
+
+impl Default for NamedPipeServerBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// One instance of a named pipe, owned by the async worker thread that created it. Created via
+/// [`NamedPipeServerBuilder`].
+///
+/// An instance serves one client at a time - wait for a client via [`connect()`][Self::connect],
+/// then communicate with it via [`read()`][Self::read] and [`write()`][Self::write]. Once done, you
+/// may either drop the instance or [`disconnect()`][Self::disconnect] the client and reuse the
+/// instance for the next client.
+#[derive(Debug)]
+pub struct NamedPipeServer {
+    handle: Rc<OwnedHandle<HANDLE>>,
+}
+
+impl NamedPipeServer {
+    /// Waits for a client to connect to this instance of the pipe. Completes immediately if a
+    /// client has already connected after the instance was created.
+    pub async fn connect(&self) -> io::Result<()> {
+        let handle = Rc::clone(&self.handle);
+
+        let mut operation =
+            current_async_agent::with_io(|io| io.new_operation(Buffer::<Isolated>::from_pool()));
+        operation.set_primitive(**handle);
+
+        // SAFETY: We are required to pass the OVERLAPPED pointer to the native I/O function to
+        // avoid a resource leak. We do.
+        unsafe {
+            operation.begin(move |_buffer, overlapped, _immediate_bytes_transferred| {
+                match ConnectNamedPipe(**handle, Some(overlapped)) {
+                    Ok(()) => Ok(()),
+                    // The client connected between the creation of the instance and now. This
+                    // is a success and no completion notification will be posted.
+                    Err(e) if e.code() == ERROR_PIPE_CONNECTED.into() => Ok(()),
+                    Err(e) => Err(e.into()),
+                }
+            })
+        }
+        .await
+        .into_inner()?;
+
+        Ok(())
+    }
+
+    /// Disconnects the client from this instance of the pipe, discarding any data not yet read by
+    /// the client. Afterwards, the instance can wait for the next client via `connect()`.
+    ///
+    /// To ensure the client has received all data, wait for the client to close its end of the
+    /// pipe (indicated by a read of 0 bytes) before disconnecting.
+    pub fn disconnect(&self) -> io::Result<()> {
+        // SAFETY: Handle liveness is ensured by our ownership of the handle.
+        Ok(unsafe { DisconnectNamedPipe(**self.handle) }?)
+    }
+
+    /// Reads the next buffer of data from the pipe.
+    ///
+    /// Returns the buffer with the active region set to the bytes read, with a length of 0 if the
+    /// client has closed its end of the pipe.
+    pub async fn read(&self, buffer: Buffer<Isolated>) -> io::Result<Buffer<Isolated>> {
+        named_pipe::read(Rc::clone(&self.handle), buffer).await
+    }
+
+    /// Reads the next message from a pipe created in message mode. If the message does not fit
+    /// into the buffer, the rest of it can be obtained by reading again.
+    pub async fn read_message(&self, buffer: Buffer<Isolated>) -> io::Result<MessageRead> {
+        named_pipe::read_message(Rc::clone(&self.handle), buffer).await
+    }
+
+    /// Writes the active region of the buffer to the pipe. In message mode, the buffer is written
+    /// as one message.
+    ///
+    /// Returns the buffer with the active region set to the bytes that were written.
+    pub async fn write(&self, buffer: Buffer<Isolated>) -> io::Result<Buffer<Isolated>> {
+        named_pipe::write(Rc::clone(&self.handle), buffer).await
+    }
+}
+
+#[negative_impl]
+impl !Send for NamedPipeServer {}
+#[negative_impl]
+impl !Sync for NamedPipeServer {}
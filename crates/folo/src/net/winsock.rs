@@ -1,6 +1,9 @@
 use crate::io;
-use std::sync::LazyLock;
-use windows::Win32::Networking::WinSock::{WSAGetLastError, WSAStartup, WSADATA};
+use std::{ffi::c_void, mem, sync::LazyLock};
+use windows::Win32::Networking::WinSock::{
+    WSAGetLastError, WSAIoctl, WSAStartup, LPFN_CONNECTEX, LPFN_WSARECVMSG,
+    SIO_GET_EXTENSION_FUNCTION_POINTER, SOCKET, WSADATA, WSAID_CONNECTEX, WSAID_WSARECVMSG,
+};
 
 pub fn ensure_initialized() {
     *WINSOCK_STARTUP;
@@ -28,3 +31,63 @@ pub fn to_io_result(winsock_result: i32) -> io::Result<()> {
         })
     }
 }
+
+/// Looks up the `ConnectEx` extension function, which Winsock only exposes via a function pointer
+/// obtained from the provider of a specific socket. The returned value is always `Some`.
+pub fn connect_ex_fn(socket: SOCKET) -> io::Result<LPFN_CONNECTEX> {
+    let mut connect_ex: LPFN_CONNECTEX = None;
+    let mut bytes_returned: u32 = 0;
+
+    // SAFETY: All we need to be concerned about is passing in valid arguments, which we do.
+    to_io_result(unsafe {
+        WSAIoctl(
+            socket,
+            SIO_GET_EXTENSION_FUNCTION_POINTER,
+            Some(&WSAID_CONNECTEX as *const _ as *const c_void),
+            mem::size_of_val(&WSAID_CONNECTEX) as u32,
+            Some(&mut connect_ex as *mut _ as *mut c_void),
+            mem::size_of::<LPFN_CONNECTEX>() as u32,
+            &mut bytes_returned as *mut _,
+            None,
+            None,
+        )
+    })?;
+
+    if connect_ex.is_none() {
+        return Err(io::Error::LogicError(
+            "Winsock provider did not return a ConnectEx function".to_string(),
+        ));
+    }
+
+    Ok(connect_ex)
+}
+
+/// Looks up the `WSARecvMsg` extension function, which Winsock only exposes via a function pointer
+/// obtained from the provider of a specific socket. The returned value is always `Some`.
+pub fn recv_msg_fn(socket: SOCKET) -> io::Result<LPFN_WSARECVMSG> {
+    let mut recv_msg: LPFN_WSARECVMSG = None;
+    let mut bytes_returned: u32 = 0;
+
+    // SAFETY: All we need to be concerned about is passing in valid arguments, which we do.
+    to_io_result(unsafe {
+        WSAIoctl(
+            socket,
+            SIO_GET_EXTENSION_FUNCTION_POINTER,
+            Some(&WSAID_WSARECVMSG as *const _ as *const c_void),
+            mem::size_of_val(&WSAID_WSARECVMSG) as u32,
+            Some(&mut recv_msg as *mut _ as *mut c_void),
+            mem::size_of::<LPFN_WSARECVMSG>() as u32,
+            &mut bytes_returned as *mut _,
+            None,
+            None,
+        )
+    })?;
+
+    if recv_msg.is_none() {
+        return Err(io::Error::LogicError(
+            "Winsock provider did not return a WSARecvMsg function".to_string(),
+        ));
+    }
+
+    Ok(recv_msg)
+}
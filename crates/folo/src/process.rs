@@ -0,0 +1,9 @@
+mod child;
+mod child_stdio;
+mod command;
+mod exit_wait;
+
+pub use child::*;
+pub use child_stdio::*;
+pub use command::*;
+pub(crate) use exit_wait::*;
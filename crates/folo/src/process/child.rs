@@ -0,0 +1,172 @@
+use crate::{
+    io,
+    process::{ChildStderr, ChildStdin, ChildStdout, ExitWait},
+    rt::current_async_agent,
+    windows::OwnedHandle,
+};
+use negative_impl::negative_impl;
+use std::{
+    os::windows::io::AsRawHandle,
+    process::{self, ExitStatus},
+    rc::Rc,
+};
+use tracing::{event, Level};
+use windows::Win32::Foundation::HANDLE;
+
+/// A child process spawned via [`Command`][super::Command], owned by the async worker thread that
+/// spawned it.
+///
+/// The standard streams of the child process that were configured as
+/// [`Stdio::Piped`][super::Stdio::Piped] are available via [`stdin()`][Self::stdin],
+/// [`stdout()`][Self::stdout] and [`stderr()`][Self::stderr] (or the `take_*()` variants, to move
+/// them elsewhere).
+///
+/// Dropping the `Child` does not wait for the child process to exit. Unless
+/// [`Command::kill_on_drop()`][super::Command::kill_on_drop] is set, the child process keeps
+/// running on its own.
+#[derive(Debug)]
+pub struct Child {
+    // Dropped before the process, as the wait references the process handle.
+    exit_wait: Option<ExitWait>,
+
+    process: process::Child,
+
+    stdin: Option<ChildStdin>,
+    stdout: Option<ChildStdout>,
+    stderr: Option<ChildStderr>,
+
+    kill_on_drop: bool,
+}
+
+impl Child {
+    pub(super) fn new(
+        process: process::Child,
+        stdin: Option<OwnedHandle<HANDLE>>,
+        stdout: Option<OwnedHandle<HANDLE>>,
+        stderr: Option<OwnedHandle<HANDLE>>,
+        kill_on_drop: bool,
+    ) -> io::Result<Self> {
+        let mut child = Self {
+            exit_wait: None,
+            process,
+            stdin: None,
+            stdout: None,
+            stderr: None,
+            kill_on_drop,
+        };
+
+        // If binding fails, the child is dropped, which kills the process if requested.
+        child.stdin = stdin.map(bind_pipe).transpose()?.map(ChildStdin::new);
+        child.stdout = stdout.map(bind_pipe).transpose()?.map(ChildStdout::new);
+        child.stderr = stderr.map(bind_pipe).transpose()?.map(ChildStderr::new);
+
+        Ok(child)
+    }
+
+    /// The operating system identifier of the child process.
+    pub fn id(&self) -> u32 {
+        self.process.id()
+    }
+
+    pub fn stdin(&self) -> Option<&ChildStdin> {
+        self.stdin.as_ref()
+    }
+
+    pub fn stdout(&self) -> Option<&ChildStdout> {
+        self.stdout.as_ref()
+    }
+
+    pub fn stderr(&self) -> Option<&ChildStderr> {
+        self.stderr.as_ref()
+    }
+
+    /// Takes the standard input of the child process. Dropping it signals the end of the standard
+    /// input to the child process.
+    pub fn take_stdin(&mut self) -> Option<ChildStdin> {
+        self.stdin.take()
+    }
+
+    pub fn take_stdout(&mut self) -> Option<ChildStdout> {
+        self.stdout.take()
+    }
+
+    pub fn take_stderr(&mut self) -> Option<ChildStderr> {
+        self.stderr.take()
+    }
+
+    /// Waits for the child process to exit, returning its exit status.
+    ///
+    /// The standard input of the child process is not closed first, so a child process that waits
+    /// for the end of its standard input never exits unless you drop it (see
+    /// [`take_stdin()`][Self::take_stdin]) before waiting.
+    ///
+    /// No thread is blocked while waiting. If the caller stops waiting, waiting can be resumed by
+    /// calling this again.
+    pub async fn wait(&mut self) -> io::Result<ExitStatus> {
+        loop {
+            if let Some(status) = self.try_wait()? {
+                return Ok(status);
+            }
+
+            if self.exit_wait.is_none() {
+                // SAFETY: The wait is owned by us and dropped before the process handle.
+                self.exit_wait =
+                    Some(unsafe { ExitWait::new(HANDLE(self.process.as_raw_handle())) }?);
+            }
+
+            let exit_wait = self
+                .exit_wait
+                .as_mut()
+                .expect("we just ensured there is an exit wait");
+
+            exit_wait.exited().await;
+        }
+    }
+
+    /// Returns the exit status of the child process if it has exited, without waiting.
+    pub fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        Ok(self.process.try_wait()?)
+    }
+
+    /// Forcibly terminates the child process. Does nothing if the child process has already
+    /// exited.
+    ///
+    /// Use [`wait()`][Self::wait] to wait for the termination to complete.
+    pub fn kill(&mut self) -> io::Result<()> {
+        Ok(self.process.kill()?)
+    }
+}
+
+impl Drop for Child {
+    fn drop(&mut self) {
+        if !self.kill_on_drop {
+            return;
+        }
+
+        if let Ok(None) = self.process.try_wait() {
+            event!(
+                Level::TRACE,
+                message = "killing child process on drop",
+                id = self.process.id()
+            );
+
+            // There is nothing we can do if this fails - most likely the process exited just now.
+            _ = self.process.kill();
+        }
+    }
+}
+
+/// Binds our end of a pipe to the I/O driver of the current async worker thread, after which the
+/// handle must not leave the current thread.
+fn bind_pipe(handle: OwnedHandle<HANDLE>) -> io::Result<Rc<OwnedHandle<HANDLE>>> {
+    let handle = Rc::new(handle);
+
+    current_async_agent::with_io(|io| io.bind_io_primitive(&**handle))?;
+
+    Ok(handle)
+}
+
+#[negative_impl]
+impl !Send for Child {}
+#[negative_impl]
+impl !Sync for Child {}
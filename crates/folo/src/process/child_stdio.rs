@@ -0,0 +1,184 @@
+use crate::{
+    io::{self, Buffer},
+    mem::isolation::Isolated,
+    net::windows::named_pipe,
+    windows::OwnedHandle,
+};
+use negative_impl::negative_impl;
+use std::{
+    ffi::CString,
+    os::windows::io::FromRawHandle,
+    process,
+    rc::Rc,
+    sync::atomic::{AtomicU64, Ordering},
+};
+use windows::{
+    core::PCSTR,
+    Win32::{
+        Foundation::HANDLE,
+        Storage::FileSystem::{
+            CreateFileA, FILE_FLAGS_AND_ATTRIBUTES, FILE_FLAG_FIRST_PIPE_INSTANCE,
+            FILE_FLAG_OVERLAPPED, FILE_GENERIC_READ, FILE_GENERIC_WRITE, FILE_SHARE_NONE,
+            OPEN_EXISTING, PIPE_ACCESS_INBOUND, PIPE_ACCESS_OUTBOUND,
+        },
+        System::Pipes::{
+            CreateNamedPipeA, PIPE_READMODE_BYTE, PIPE_REJECT_REMOTE_CLIENTS, PIPE_TYPE_BYTE,
+            PIPE_WAIT,
+        },
+    },
+};
+
+// The size of the buffers the OS reserves for each direction of a pipe. This is only a hint - the
+// OS grows the buffers as needed.
+const PIPE_BUFFER_SIZE_BYTES: u32 = 64 * 1024;
+
+// Anonymous pipes do not support asynchronous I/O, so we create a uniquely named pipe instead,
+// like the Rust standard library does.
+static NEXT_PIPE_ID: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(super) enum PipeDirection {
+    ToChild,
+    FromChild,
+}
+
+/// Creates a pipe for one of the standard streams of a child process. Returns our end, opened for
+/// asynchronous I/O, and the end for the child process, opened for synchronous I/O as most
+/// programs expect.
+///
+/// This is a blocking operation that must be called on a synchronous worker thread.
+pub(super) fn create_pipe(
+    direction: PipeDirection,
+) -> io::Result<(OwnedHandle<HANDLE>, std::os::windows::io::OwnedHandle)> {
+    let name = format!(
+        r"\\.\pipe\folo-process-{}-{}",
+        process::id(),
+        NEXT_PIPE_ID.fetch_add(1, Ordering::Relaxed)
+    );
+
+    let name_cstr = CString::new(name).expect("generated pipe name contains no NUL characters");
+
+    let (our_access, their_access) = match direction {
+        PipeDirection::ToChild => (PIPE_ACCESS_OUTBOUND, FILE_GENERIC_READ),
+        PipeDirection::FromChild => (PIPE_ACCESS_INBOUND, FILE_GENERIC_WRITE),
+    };
+
+    // SAFETY: The name string outlives the call and the handle is ours to close anywhere. The
+    // pipe instance is the first and only one, so nobody else can connect to it in between.
+    let ours = unsafe {
+        let handle = CreateNamedPipeA(
+            PCSTR::from_raw(name_cstr.as_ptr() as *const u8),
+            our_access | FILE_FLAG_OVERLAPPED | FILE_FLAG_FIRST_PIPE_INSTANCE,
+            PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+            1,
+            PIPE_BUFFER_SIZE_BYTES,
+            PIPE_BUFFER_SIZE_BYTES,
+            0,
+            None,
+        );
+
+        if handle.is_invalid() {
+            return Err(windows::core::Error::from_win32().into());
+        }
+
+        OwnedHandle::new(handle)
+    };
+
+    // SAFETY: The name string outlives the call. We transfer ownership of the handle to the
+    // standard library type, which closes it on drop.
+    let theirs = unsafe {
+        let handle = CreateFileA(
+            PCSTR::from_raw(name_cstr.as_ptr() as *const u8),
+            their_access.0,
+            FILE_SHARE_NONE,
+            None,
+            OPEN_EXISTING,
+            FILE_FLAGS_AND_ATTRIBUTES::default(),
+            None,
+        )?;
+
+        std::os::windows::io::OwnedHandle::from_raw_handle(handle.0)
+    };
+
+    Ok((ours, theirs))
+}
+
+/// Writes to the standard input of a child process. Obtained via
+/// [`Child::stdin()`][super::Child::stdin] or
+/// [`Child::take_stdin()`][super::Child::take_stdin].
+///
+/// The child process sees the end of its standard input once this is dropped.
+#[derive(Debug)]
+pub struct ChildStdin {
+    handle: Rc<OwnedHandle<HANDLE>>,
+}
+
+impl ChildStdin {
+    pub(super) fn new(handle: Rc<OwnedHandle<HANDLE>>) -> Self {
+        Self { handle }
+    }
+
+    /// Writes the active region of the buffer to the standard input of the child process.
+    ///
+    /// Returns the buffer with the active region set to the bytes that were written.
+    pub async fn write(&self, buffer: Buffer<Isolated>) -> io::Result<Buffer<Isolated>> {
+        named_pipe::write(Rc::clone(&self.handle), buffer).await
+    }
+}
+
+/// Reads from the standard output of a child process. Obtained via
+/// [`Child::stdout()`][super::Child::stdout] or
+/// [`Child::take_stdout()`][super::Child::take_stdout].
+#[derive(Debug)]
+pub struct ChildStdout {
+    handle: Rc<OwnedHandle<HANDLE>>,
+}
+
+impl ChildStdout {
+    pub(super) fn new(handle: Rc<OwnedHandle<HANDLE>>) -> Self {
+        Self { handle }
+    }
+
+    /// Reads the next buffer of data from the standard output of the child process.
+    ///
+    /// Returns the buffer with the active region set to the bytes read, with a length of 0 if the
+    /// child process has closed its standard output (e.g. because it has exited).
+    pub async fn read(&self, buffer: Buffer<Isolated>) -> io::Result<Buffer<Isolated>> {
+        named_pipe::read(Rc::clone(&self.handle), buffer).await
+    }
+}
+
+/// Reads from the standard error of a child process. Obtained via
+/// [`Child::stderr()`][super::Child::stderr] or
+/// [`Child::take_stderr()`][super::Child::take_stderr].
+#[derive(Debug)]
+pub struct ChildStderr {
+    handle: Rc<OwnedHandle<HANDLE>>,
+}
+
+impl ChildStderr {
+    pub(super) fn new(handle: Rc<OwnedHandle<HANDLE>>) -> Self {
+        Self { handle }
+    }
+
+    /// Reads the next buffer of data from the standard error of the child process.
+    ///
+    /// Returns the buffer with the active region set to the bytes read, with a length of 0 if the
+    /// child process has closed its standard error (e.g. because it has exited).
+    pub async fn read(&self, buffer: Buffer<Isolated>) -> io::Result<Buffer<Isolated>> {
+        named_pipe::read(Rc::clone(&self.handle), buffer).await
+    }
+}
+
+#[negative_impl]
+impl !Send for ChildStdin {}
+#[negative_impl]
+impl !Sync for ChildStdin {}
+#[negative_impl]
+impl !Send for ChildStdout {}
+#[negative_impl]
+impl !Sync for ChildStdout {}
+#[negative_impl]
+impl !Send for ChildStderr {}
+#[negative_impl]
+impl !Sync for ChildStderr {}
@@ -0,0 +1,236 @@
+use crate::{
+    io,
+    process::{create_pipe, Child, PipeDirection},
+    rt::{spawn_sync, SynchronousTaskType},
+    windows::OwnedHandle,
+};
+use std::{
+    ffi::{OsStr, OsString},
+    path::{Path, PathBuf},
+};
+use tracing::{event, Level};
+use windows::Win32::Foundation::HANDLE;
+
+/// What to connect a standard stream of a child process to.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Stdio {
+    /// The child process uses the same stream as the current process.
+    #[default]
+    Inherit,
+
+    /// The stream is connected to nothing: reads see the end of the stream and writes are
+    /// discarded.
+    Null,
+
+    /// The stream is connected to a pipe, the other end of which is available via [`Child`] for
+    /// asynchronous reading or writing on the current async worker thread.
+    Piped,
+}
+
+/// Builds and spawns a child process.
+///
+/// # Example
+///
+/// ```no_run
+/// use folo::io::Buffer;
+/// use folo::mem::isolation::Isolated;
+/// use folo::process::{Command, Stdio};
+///
+/// #[folo::main]
+/// async fn main() {
+///     let mut child = Command::new("cmd")
+///         .args(["/C", "echo hello"])
+///         .stdout(Stdio::Piped)
+///         .spawn()
+///         .await
+///         .unwrap();
+///
+///     let stdout = child.stdout().unwrap();
+///     let buffer = stdout.read(Buffer::<Isolated>::from_pool()).await.unwrap();
+///     println!("{}", String::from_utf8_lossy(&buffer.as_slice()));
+///
+///     let status = child.wait().await.unwrap();
+///     assert!(status.success());
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Command {
+    program: OsString,
+    args: Vec<OsString>,
+
+    // `None` removes the variable from the environment of the child process.
+    envs: Vec<(OsString, Option<OsString>)>,
+    env_clear: bool,
+
+    current_dir: Option<PathBuf>,
+
+    stdin: Stdio,
+    stdout: Stdio,
+    stderr: Stdio,
+
+    kill_on_drop: bool,
+}
+
+impl Command {
+    /// Creates a command for the specified program. If the program is not an absolute path, it is
+    /// searched for in the same way as by [`std::process::Command`].
+    pub fn new(program: impl AsRef<OsStr>) -> Self {
+        Self {
+            program: program.as_ref().to_os_string(),
+            args: Vec::new(),
+            envs: Vec::new(),
+            env_clear: false,
+            current_dir: None,
+            stdin: Stdio::default(),
+            stdout: Stdio::default(),
+            stderr: Stdio::default(),
+            kill_on_drop: false,
+        }
+    }
+
+    /// Adds an argument to pass to the program.
+    pub fn arg(mut self, arg: impl AsRef<OsStr>) -> Self {
+        self.args.push(arg.as_ref().to_os_string());
+        self
+    }
+
+    /// Adds multiple arguments to pass to the program.
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        self.args
+            .extend(args.into_iter().map(|arg| arg.as_ref().to_os_string()));
+        self
+    }
+
+    /// Sets an environment variable for the child process.
+    pub fn env(mut self, key: impl AsRef<OsStr>, value: impl AsRef<OsStr>) -> Self {
+        self.envs.push((
+            key.as_ref().to_os_string(),
+            Some(value.as_ref().to_os_string()),
+        ));
+        self
+    }
+
+    /// Removes an environment variable from the environment of the child process.
+    pub fn env_remove(mut self, key: impl AsRef<OsStr>) -> Self {
+        self.envs.push((key.as_ref().to_os_string(), None));
+        self
+    }
+
+    /// Whether the child process starts with an empty environment instead of inheriting the
+    /// environment of the current process. Variables set via [`env()`][Self::env] are still set.
+    pub fn env_clear(mut self, value: bool) -> Self {
+        self.env_clear = value;
+        self
+    }
+
+    /// Sets the working directory of the child process. By default, the child process inherits the
+    /// working directory of the current process.
+    pub fn current_dir(mut self, path: impl AsRef<Path>) -> Self {
+        self.current_dir = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Sets what to connect the standard input of the child process to. By default, it is
+    /// inherited from the current process.
+    pub fn stdin(mut self, value: Stdio) -> Self {
+        self.stdin = value;
+        self
+    }
+
+    /// Sets what to connect the standard output of the child process to. By default, it is
+    /// inherited from the current process.
+    pub fn stdout(mut self, value: Stdio) -> Self {
+        self.stdout = value;
+        self
+    }
+
+    /// Sets what to connect the standard error of the child process to. By default, it is
+    /// inherited from the current process.
+    pub fn stderr(mut self, value: Stdio) -> Self {
+        self.stderr = value;
+        self
+    }
+
+    /// Whether to kill the child process when the [`Child`] is dropped before the process has
+    /// exited. By default, the child process keeps running on its own.
+    pub fn kill_on_drop(mut self, value: bool) -> Self {
+        self.kill_on_drop = value;
+        self
+    }
+
+    /// Spawns the child process, binding the returned [`Child`] (including any pipes to the child
+    /// process) to the current async worker thread.
+    pub async fn spawn(self) -> io::Result<Child> {
+        let kill_on_drop = self.kill_on_drop;
+
+        // Creating pipes and processes are blocking operations, so we kick them off to a
+        // synchronous worker thread to avoid blocking the async workers with these slow calls.
+        let (process, stdin, stdout, stderr) =
+            spawn_sync(SynchronousTaskType::Syscall, move || -> io::Result<_> {
+                let mut command = std::process::Command::new(&self.program);
+                command.args(&self.args);
+
+                if self.env_clear {
+                    command.env_clear();
+                }
+
+                for (key, value) in &self.envs {
+                    match value {
+                        Some(value) => command.env(key, value),
+                        None => command.env_remove(key),
+                    };
+                }
+
+                if let Some(current_dir) = &self.current_dir {
+                    command.current_dir(current_dir);
+                }
+
+                let (stdin, child_stdin) = child_stdio(self.stdin, PipeDirection::ToChild)?;
+                let (stdout, child_stdout) = child_stdio(self.stdout, PipeDirection::FromChild)?;
+                let (stderr, child_stderr) = child_stdio(self.stderr, PipeDirection::FromChild)?;
+
+                command
+                    .stdin(child_stdin)
+                    .stdout(child_stdout)
+                    .stderr(child_stderr);
+
+                let process = command.spawn()?;
+
+                // The command holds our copies of the child ends of the pipes. We drop it here, so
+                // the only remaining copies are owned by the child process and we see the end of
+                // the stream once the child process closes them.
+                drop(command);
+
+                Ok((process, stdin, stdout, stderr))
+            })
+            .await?;
+
+        event!(
+            Level::TRACE,
+            message = "child process spawned",
+            id = process.id()
+        );
+
+        Child::new(process, stdin, stdout, stderr, kill_on_drop)
+    }
+}
+
+/// Creates the handle to pass to the child process for one of its standard streams, together
+/// with our end of the pipe if the stream is piped.
+fn child_stdio(
+    stdio: Stdio,
+    direction: PipeDirection,
+) -> io::Result<(Option<OwnedHandle<HANDLE>>, std::process::Stdio)> {
+    Ok(match stdio {
+        Stdio::Inherit => (None, std::process::Stdio::inherit()),
+        Stdio::Null => (None, std::process::Stdio::null()),
+        Stdio::Piped => {
+            let (ours, theirs) = create_pipe(direction)?;
+            (Some(ours), theirs.into())
+        }
+    })
+}
@@ -0,0 +1,109 @@
+use crate::{constants, io};
+use std::{ffi::c_void, sync::Mutex};
+use windows::Win32::{
+    Foundation::{BOOLEAN, HANDLE, INVALID_HANDLE_VALUE},
+    System::Threading::{
+        RegisterWaitForSingleObject, UnregisterWaitEx, INFINITE, WT_EXECUTEONLYONCE,
+    },
+};
+
+/// Waits for a process to exit without occupying a thread of our own. The operating system thread
+/// pool waits for the process handle to be signaled and notifies us via a callback.
+///
+/// The wait is canceled when this is dropped.
+#[derive(Debug)]
+pub(crate) struct ExitWait {
+    wait_handle: HANDLE,
+
+    // The callback may run on any thread pool thread at any time until the wait is unregistered,
+    // so the context it references is on the heap and only released after unregistering.
+    context: *mut WaitContext,
+
+    exited_rx: oneshot::Receiver<()>,
+}
+
+#[derive(Debug)]
+struct WaitContext {
+    exited_tx: Mutex<Option<oneshot::Sender<()>>>,
+}
+
+impl ExitWait {
+    /// Starts waiting for the process to exit.
+    ///
+    /// # Safety
+    ///
+    /// The process handle must remain valid until the wait is dropped.
+    pub(crate) unsafe fn new(process: HANDLE) -> io::Result<Self> {
+        let (exited_tx, exited_rx) = oneshot::channel();
+
+        let context = Box::into_raw(Box::new(WaitContext {
+            exited_tx: Mutex::new(Some(exited_tx)),
+        }));
+
+        let mut wait_handle = HANDLE::default();
+
+        // SAFETY: The context remains valid until the wait is unregistered, which the caller
+        // ensures happens before the process handle is closed.
+        let result = unsafe {
+            RegisterWaitForSingleObject(
+                &mut wait_handle,
+                process,
+                Some(on_exited),
+                Some(context as *const c_void),
+                INFINITE,
+                WT_EXECUTEONLYONCE,
+            )
+        };
+
+        if let Err(e) = result {
+            // SAFETY: The wait was not registered, so nobody else references the context.
+            drop(unsafe { Box::from_raw(context) });
+            return Err(e.into());
+        }
+
+        Ok(Self {
+            wait_handle,
+            context,
+            exited_rx,
+        })
+    }
+
+    /// Waits until the process has exited. This may be called again if the caller stopped waiting
+    /// before the process exited.
+    pub(crate) async fn exited(&mut self) {
+        // The sender is only dropped without sending when the wait is unregistered, which cannot
+        // happen while we hold a reference to the wait.
+        (&mut self.exited_rx)
+            .await
+            .expect("exit notification sender dropped before the wait was unregistered");
+    }
+}
+
+impl Drop for ExitWait {
+    fn drop(&mut self) {
+        // Passing INVALID_HANDLE_VALUE makes this wait for any running callback to complete, after
+        // which the callback can no longer reference the context. The callback only sends a
+        // message, so this is not a significant wait.
+        // SAFETY: We registered the wait and it has not been unregistered yet.
+        _ = unsafe { UnregisterWaitEx(self.wait_handle, Some(INVALID_HANDLE_VALUE)) };
+
+        // SAFETY: The wait has been unregistered, so nobody else references the context.
+        drop(unsafe { Box::from_raw(self.context) });
+    }
+}
+
+unsafe extern "system" fn on_exited(context: *mut c_void, _timed_out: BOOLEAN) {
+    // SAFETY: The context remains valid until the wait is unregistered, which waits for this
+    // callback to complete.
+    let context = unsafe { &*(context as *const WaitContext) };
+
+    if let Some(exited_tx) = context
+        .exited_tx
+        .lock()
+        .expect(constants::POISONED_LOCK)
+        .take()
+    {
+        // The receiver may already be gone if the wait is being dropped, which is fine.
+        _ = exited_tx.send(());
+    }
+}
@@ -1,26 +1,84 @@
+mod abort;
+mod admission;
+mod affinity;
 mod async_agent;
 mod async_task_engine;
 mod builder;
+pub(crate) mod coop;
 pub(crate) mod current_async_agent;
 pub(crate) mod current_runtime;
 pub(crate) mod current_sync_agent;
+mod deadline;
+pub(crate) mod diagnostics;
+mod dump;
+mod embedded;
 mod erased_async_task;
+pub(crate) mod flight_recorder;
 mod functions;
-mod local_join;
+mod idle;
+mod in_flight;
+mod join_error;
+pub(crate) mod local_join;
+mod local_spawner;
 mod local_task;
+mod local_task_set;
+mod maintenance;
+mod panic_policy;
+mod profile;
 mod ready_after_poll;
 mod remote_join;
 mod remote_result_box;
 mod remote_task;
-mod remote_waker;
+pub(crate) mod remote_waker;
 mod runtime_client;
+#[cfg(feature = "config")]
+mod runtime_config;
+mod scope;
+mod singleton;
+mod stealing;
 mod sync_agent;
+mod task_frames;
+mod task_group;
+mod task_local;
+mod task_memory;
+mod task_meta;
+pub(crate) mod task_trace;
 mod types;
 mod waker;
+mod watchdog;
+mod worker_context;
+mod worker_stats;
 
+pub use admission::SpawnError;
+pub use affinity::Affinity;
 pub use builder::*;
+pub use coop::{unconstrained, Unconstrained};
+pub use diagnostics::{Diagnostic, DiagnosticsBackend};
+pub use dump::{RuntimeDump, TaskDump, TaskState, WorkerDump};
+pub use embedded::EmbeddedRuntime;
+pub use flight_recorder::{FlightEvent, FlightRecord, FlightRecordEntry, WorkerFlightRecord};
 pub use functions::*;
+pub use idle::IdleStrategy;
+pub use join_error::*;
 pub use local_join::*;
+pub use local_spawner::*;
+pub use local_task_set::*;
+pub use panic_policy::PanicPolicy;
+pub use profile::Profile;
 pub use remote_join::*;
 pub use runtime_client::*;
+#[cfg(feature = "config")]
+pub use runtime_config::*;
+pub use scope::*;
+pub use singleton::*;
+pub use task_group::*;
+pub use task_local::*;
+pub use task_memory::{
+    charge_current_task, set_task_memory_accounting, task_memory_accounting, MemoryCharge,
+};
+pub use task_meta::*;
+pub use task_trace::{traced, TaskTrace, Traced};
 pub(crate) use types::*;
+pub use watchdog::{BlockedWorker, WatchdogPolicy};
+pub use worker_context::*;
+pub use worker_stats::WorkerStats;
@@ -0,0 +1,125 @@
+use crate::rt::{panic_policy, JoinError};
+use futures::task::AtomicWaker;
+use pin_project::{pin_project, pinned_drop};
+use std::{
+    future::Future,
+    panic::{self, AssertUnwindSafe},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::{self, Waker},
+};
+
+/// Shared between a task and its join handle(s), allowing the task to be aborted from the outside.
+///
+/// This is thread-safe because join handles may be converted to remote join handles and the abort
+/// request may therefore arrive from any thread.
+#[derive(Debug, Default)]
+pub(crate) struct AbortState {
+    requested: AtomicBool,
+
+    // The waker of the task, registered on every poll (if it changed). We use this to wake up the
+    // task when an abort is requested, so it gets to observe the request at its next yield point
+    // even if it would otherwise sleep forever.
+    //
+    // This is cleared when the task completes or is dropped, so we do not keep the task alive
+    // (i.e. not inert) just because someone is holding on to a join handle.
+    waker: AtomicWaker,
+}
+
+impl AbortState {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// Requests the task to be aborted. Safe to call multiple times and safe to call after the task
+    /// has already completed (in which case this has no effect).
+    pub fn request(&self) {
+        self.requested.store(true, Ordering::Release);
+
+        self.waker.wake();
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::Acquire)
+    }
+
+    fn register(&self, waker: &Waker) {
+        // This only clones the waker if it is different from the already registered one.
+        self.waker.register(waker);
+    }
+
+    fn unregister(&self) {
+        _ = self.waker.take();
+    }
+}
+
+/// Wraps the future of a task, checking for an abort request every time the task is polled.
+///
+/// If an abort has been requested, the task completes with `JoinError::Cancelled` without polling
+/// the inner future again. The inner future is dropped when the task engine clears the task.
+///
+/// This is also the task boundary at which panics are caught and handled according to the
+/// `PanicPolicy` of the worker thread.
+#[pin_project(PinnedDrop)]
+#[derive(Debug)]
+pub(crate) struct Abortable<F> {
+    #[pin]
+    inner: F,
+    state: Arc<AbortState>,
+}
+
+impl<F> Abortable<F> {
+    pub fn new(inner: F, state: Arc<AbortState>) -> Self {
+        Self { inner, state }
+    }
+}
+
+impl<F> Future for Abortable<F>
+where
+    F: Future,
+{
+    type Output = Result<F::Output, JoinError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> task::Poll<Self::Output> {
+        let this = self.project();
+
+        if this.state.is_requested() {
+            this.state.unregister();
+            return task::Poll::Ready(Err(JoinError::Cancelled));
+        }
+
+        this.state.register(cx.waker());
+
+        // The inner future is never polled again after a panic, so we do not care if it is left
+        // in a broken state by the unwinding.
+        let poll_result = panic::catch_unwind(AssertUnwindSafe(|| this.inner.poll(cx)));
+
+        match poll_result {
+            Ok(task::Poll::Ready(result)) => {
+                this.state.unregister();
+                task::Poll::Ready(Ok(result))
+            }
+            Ok(task::Poll::Pending) => task::Poll::Pending,
+            Err(payload) => {
+                this.state.unregister();
+
+                match panic_policy::handle_task_panic(payload) {
+                    Some(payload) => task::Poll::Ready(Err(JoinError::Panic(payload))),
+                    None => task::Poll::Ready(Err(JoinError::Cancelled)),
+                }
+            }
+        }
+    }
+}
+
+#[pinned_drop]
+impl<F> PinnedDrop for Abortable<F> {
+    fn drop(self: Pin<&mut Self>) {
+        // The waker may be keeping the task from becoming inert, so we release it as soon as the
+        // future is dropped (e.g. when the task is cleared during runtime shutdown).
+        self.state.unregister();
+    }
+}
@@ -0,0 +1,103 @@
+use crate::metrics::{observe, Event, EventBuilder};
+use std::{
+    cell::{Cell, RefCell},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+use thiserror::Error;
+
+/// The reason why [`try_spawn()`][crate::rt::try_spawn] refused to spawn a task.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum SpawnError {
+    /// The current worker thread already has the maximum number of live tasks configured via
+    /// [`RuntimeBuilder::max_tasks_per_worker()`][crate::rt::RuntimeBuilder::max_tasks_per_worker].
+    #[error("worker thread already has the maximum of {limit} live tasks")]
+    WorkerAtCapacity { limit: usize },
+
+    /// The runtime already has the maximum number of live tasks configured via
+    /// [`RuntimeBuilder::max_tasks()`][crate::rt::RuntimeBuilder::max_tasks].
+    #[error("runtime already has the maximum of {limit} live tasks")]
+    RuntimeAtCapacity { limit: usize },
+}
+
+/// The task count limits of a runtime. The global counter is shared by all the async worker
+/// threads of the runtime.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct TaskLimits {
+    pub per_worker: Option<usize>,
+    pub global: Option<(usize, Arc<AtomicUsize>)>,
+}
+
+/// Sets the limits for tasks spawned on the current thread.
+pub(crate) fn set_task_limits(value: TaskLimits) {
+    LIMITS.with_borrow_mut(|x| *x = value);
+}
+
+/// Counts a new task against the limits of the current thread, returning a permit that must be
+/// kept alive for as long as the task is. If `enforce` is set, the task is refused if any limit
+/// has been reached; otherwise it is counted even if this takes the count over the limit.
+pub(crate) fn admit(enforce: bool) -> Result<TaskPermit, SpawnError> {
+    LIMITS.with_borrow(|limits| {
+        let local_count = LOCAL_COUNT.with(Cell::get);
+
+        if enforce {
+            if let Some(limit) = limits.per_worker {
+                if local_count >= limit {
+                    observe!(SPAWNS_REJECTED.observe_unit());
+                    return Err(SpawnError::WorkerAtCapacity { limit });
+                }
+            }
+        }
+
+        let global_count = match &limits.global {
+            Some((limit, count)) => {
+                let previous = count.fetch_add(1, Ordering::Relaxed);
+
+                if enforce && previous >= *limit {
+                    count.fetch_sub(1, Ordering::Relaxed);
+
+                    observe!(SPAWNS_REJECTED.observe_unit());
+                    return Err(SpawnError::RuntimeAtCapacity { limit: *limit });
+                }
+
+                Some(Arc::clone(count))
+            }
+            None => None,
+        };
+
+        LOCAL_COUNT.with(|x| x.set(local_count + 1));
+
+        Ok(TaskPermit { global_count })
+    })
+}
+
+/// Keeps a task counted against the task limits. Dropped together with the task.
+#[derive(Debug)]
+pub(crate) struct TaskPermit {
+    global_count: Option<Arc<AtomicUsize>>,
+}
+
+impl Drop for TaskPermit {
+    fn drop(&mut self) {
+        // Tasks are dropped on the thread that admitted them, before thread-local state is torn
+        // down, but we do not want a panic in drop if that ever changes.
+        _ = LOCAL_COUNT.try_with(|x| x.set(x.get().saturating_sub(1)));
+
+        if let Some(count) = &self.global_count {
+            count.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+}
+
+thread_local! {
+    static LIMITS: RefCell<TaskLimits> = RefCell::new(TaskLimits::default());
+
+    // The number of live tasks admitted on the current thread.
+    static LOCAL_COUNT: Cell<usize> = const { Cell::new(0) };
+
+    static SPAWNS_REJECTED: Event = EventBuilder::new("rt_spawns_rejected")
+        .build();
+}
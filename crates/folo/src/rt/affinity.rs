@@ -0,0 +1,268 @@
+use crate::io;
+use core_affinity::CoreId;
+use std::mem;
+use windows::Win32::System::{
+    Kernel::PROCESSOR_NUMBER,
+    SystemInformation::{
+        GetLogicalProcessorInformationEx, GetNumaProcessorNodeEx, RelationProcessorCore,
+        GROUP_AFFINITY, SYSTEM_LOGICAL_PROCESSOR_INFORMATION_EX,
+    },
+    Threading::{GetCurrentThread, SetThreadGroupAffinity},
+};
+
+/// Determines which processors the runtime places its worker threads on. There is one async worker
+/// thread (plus its synchronous helper threads) for each selected processor.
+///
+/// Processors are identified by their global index, which is `group * 64 + index_in_group` for
+/// Windows processor groups, so machines with more than 64 logical processors are fully supported.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "config",
+    derive(serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Affinity {
+    /// One worker per logical processor, pinned to that processor.
+    #[default]
+    AllProcessors,
+
+    /// One worker per physical core, pinned to the first logical processor of that core. The other
+    /// logical processors of cores with simultaneous multithreading (SMT, hyper-threading) are left
+    /// unused, which avoids workers competing for the execution units of the same core.
+    PhysicalCores,
+
+    /// One worker per listed logical processor (by global index), pinned to that processor.
+    /// Processors that do not exist are ignored.
+    Processors(Vec<usize>),
+
+    /// One worker per logical processor of the listed NUMA nodes, pinned to that processor. Use
+    /// this to keep a runtime (and the memory its workers allocate) local to specific nodes of a
+    /// multi-socket machine.
+    NumaNodes(Vec<u16>),
+
+    /// One worker per logical processor but the workers are not pinned - the operating system may
+    /// move them between processors at will.
+    Unpinned,
+}
+
+impl Affinity {
+    /// Whether worker threads are pinned to their processors.
+    pub(crate) fn is_pinned(&self) -> bool {
+        !matches!(self, Self::Unpinned)
+    }
+
+    /// Returns the processors selected by this policy, in the order workers are assigned to them.
+    pub(crate) fn select_processors(&self) -> io::Result<Vec<CoreId>> {
+        self.select_processors_from(physical_cores()?, numa_node_of)
+    }
+
+    /// Selects from the logical processors of each physical core, given a way to tell the NUMA node
+    /// of a logical processor. Separate from `select_processors()` so the selection can be tested
+    /// against any processor topology.
+    fn select_processors_from(
+        &self,
+        cores: Vec<Vec<CoreId>>,
+        numa_node_of: impl Fn(CoreId) -> Option<u16>,
+    ) -> io::Result<Vec<CoreId>> {
+        let processors = match self {
+            Self::AllProcessors | Self::Unpinned => cores.into_iter().flatten().collect(),
+            Self::PhysicalCores => cores
+                .into_iter()
+                .filter_map(|logical_processors| logical_processors.first().copied())
+                .collect(),
+            Self::Processors(indexes) => {
+                let available = cores.into_iter().flatten().collect::<Vec<_>>();
+
+                indexes
+                    .iter()
+                    .map(|index| CoreId { id: *index })
+                    .filter(|processor| available.contains(processor))
+                    .collect()
+            }
+            Self::NumaNodes(nodes) => cores
+                .into_iter()
+                .flatten()
+                .filter(|processor| {
+                    numa_node_of(*processor).is_some_and(|node| nodes.contains(&node))
+                })
+                .collect(),
+        };
+
+        if processors.is_empty() {
+            return Err(io::Error::InvalidOptions(format!(
+                "affinity policy {self:?} did not select any processors"
+            )));
+        }
+
+        Ok(processors)
+    }
+}
+
+/// Pins the current thread to a single logical processor.
+pub(crate) fn pin_current_thread(processor: CoreId) -> io::Result<()> {
+    let affinity = GROUP_AFFINITY {
+        Mask: 1 << (processor.id % PROCESSORS_PER_GROUP),
+        Group: (processor.id / PROCESSORS_PER_GROUP) as u16,
+        ..Default::default()
+    };
+
+    // SAFETY: The affinity structure outlives the call and we do not ask for the previous value.
+    unsafe { SetThreadGroupAffinity(GetCurrentThread(), &affinity, None)? };
+
+    Ok(())
+}
+
+/// Returns the NUMA node that a logical processor belongs to, if the operating system can tell.
+pub(crate) fn numa_node_of(processor: CoreId) -> Option<u16> {
+    let processor_number = PROCESSOR_NUMBER {
+        Group: (processor.id / PROCESSORS_PER_GROUP) as u16,
+        Number: (processor.id % PROCESSORS_PER_GROUP) as u8,
+        Reserved: 0,
+    };
+
+    let mut node: u16 = 0;
+
+    // SAFETY: Both pointers refer to live local variables for the duration of the call.
+    unsafe { GetNumaProcessorNodeEx(&processor_number, &mut node) }.ok()?;
+
+    Some(node)
+}
+
+/// Lists the logical processors of each physical core in the system, across all processor groups.
+fn physical_cores() -> io::Result<Vec<Vec<CoreId>>> {
+    let mut length: u32 = 0;
+
+    // The first call tells us how big a buffer we need. It always "fails" because of this.
+    // SAFETY: We do not provide a buffer, so there is nothing that could be written out of bounds.
+    _ = unsafe { GetLogicalProcessorInformationEx(RelationProcessorCore, None, &mut length) };
+
+    // We use u64 elements to get a sufficiently aligned buffer for the variable-size records.
+    let mut buffer = vec![0_u64; (length as usize).div_ceil(mem::size_of::<u64>())];
+
+    // SAFETY: The buffer is at least `length` bytes, as the API requested.
+    unsafe {
+        GetLogicalProcessorInformationEx(
+            RelationProcessorCore,
+            Some(buffer.as_mut_ptr() as *mut SYSTEM_LOGICAL_PROCESSOR_INFORMATION_EX),
+            &mut length,
+        )?
+    };
+
+    let mut cores = Vec::new();
+    let mut offset = 0;
+
+    while offset < length as usize {
+        // SAFETY: The operating system filled the buffer with a sequence of records, each of which
+        // states its own size, and we stay within the length it reported.
+        let record = unsafe {
+            &*((buffer.as_ptr() as *const u8).add(offset)
+                as *const SYSTEM_LOGICAL_PROCESSOR_INFORMATION_EX)
+        };
+
+        offset += record.Size as usize;
+
+        // SAFETY: We asked for processor core records, so this is the active union member.
+        let processor = unsafe { &record.Anonymous.Processor };
+
+        // A core is always in exactly one group, so there is always exactly one group mask.
+        cores.push(logical_processors(&processor.GroupMask[0]));
+    }
+
+    Ok(cores)
+}
+
+/// Lists the logical processors in a group affinity mask, by global index.
+fn logical_processors(group_mask: &GROUP_AFFINITY) -> Vec<CoreId> {
+    let group_start = group_mask.Group as usize * PROCESSORS_PER_GROUP;
+
+    (0..PROCESSORS_PER_GROUP)
+        .filter(|bit| group_mask.Mask & (1 << bit) != 0)
+        .map(|bit| CoreId {
+            id: group_start + bit,
+        })
+        .collect()
+}
+
+const PROCESSORS_PER_GROUP: usize = 64;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(processors: &[CoreId]) -> Vec<usize> {
+        processors.iter().map(|processor| processor.id).collect()
+    }
+
+    fn cores(cores: &[&[usize]]) -> Vec<Vec<CoreId>> {
+        cores
+            .iter()
+            .map(|core| core.iter().map(|id| CoreId { id: *id }).collect())
+            .collect()
+    }
+
+    // Two groups with two SMT cores each, the second group being only partially populated.
+    fn two_groups() -> Vec<Vec<CoreId>> {
+        cores(&[&[0, 1], &[2, 3], &[64, 65], &[66, 67]])
+    }
+
+    fn select(affinity: Affinity) -> io::Result<Vec<CoreId>> {
+        affinity.select_processors_from(two_groups(), |_| Some(0))
+    }
+
+    #[test]
+    fn global_index_includes_group() {
+        let group_mask = GROUP_AFFINITY {
+            Mask: 0b1010,
+            Group: 2,
+            ..Default::default()
+        };
+
+        assert_eq!(ids(&logical_processors(&group_mask)), vec![129, 131]);
+    }
+
+    #[test]
+    fn all_processors_span_groups() {
+        let expected = vec![0, 1, 2, 3, 64, 65, 66, 67];
+
+        assert_eq!(ids(&select(Affinity::AllProcessors).unwrap()), expected);
+        assert_eq!(ids(&select(Affinity::Unpinned).unwrap()), expected);
+    }
+
+    #[test]
+    fn physical_cores_take_first_logical_processor() {
+        assert_eq!(
+            ids(&select(Affinity::PhysicalCores).unwrap()),
+            vec![0, 2, 64, 66]
+        );
+    }
+
+    #[test]
+    fn processors_keep_order_and_ignore_missing() {
+        let selected = select(Affinity::Processors(vec![65, 4, 1])).unwrap();
+
+        // Processor 4 would be in the first group, which only has processors 0-3.
+        assert_eq!(ids(&selected), vec![65, 1]);
+    }
+
+    #[test]
+    fn numa_nodes_select_processors_of_listed_nodes() {
+        // Each group is its own NUMA node and the operating system does not know about 66-67.
+        let numa_node_of = |processor: CoreId| match processor.id {
+            0..=3 => Some(0),
+            64..=65 => Some(1),
+            _ => None,
+        };
+
+        let select =
+            |nodes| Affinity::NumaNodes(nodes).select_processors_from(two_groups(), numa_node_of);
+
+        assert_eq!(ids(&select(vec![1]).unwrap()), vec![64, 65]);
+        assert_eq!(ids(&select(vec![1, 0]).unwrap()), vec![0, 1, 2, 3, 64, 65]);
+        assert!(select(vec![2]).is_err());
+    }
+
+    #[test]
+    fn empty_selection_is_error() {
+        assert!(select(Affinity::Processors(vec![4, 128])).is_err());
+    }
+}
@@ -1,25 +1,47 @@
 use super::erased_async_task::ErasedResultAsyncTask;
 use crate::{
+    constants::GENERAL_MILLISECONDS_BUCKETS,
     io,
-    metrics::{self, Event, EventBuilder, ReportPage},
+    metrics::{self, observe, Event, EventBuilder, ReportPage},
     rt::{
+        abort::{AbortState, Abortable},
+        admission::{self, SpawnError, TaskPermit},
+        affinity,
         async_task_engine::{AsyncTaskEngine, CycleResult},
         current_runtime,
+        deadline::WithDeadline,
+        dump::{PollingTask, TaskDump},
+        idle::{IdleAction, IdleStrategy},
+        in_flight::InFlightTasks,
         local_task::LocalTask,
-        LocalJoinHandle,
+        maintenance::MaintenanceTicker,
+        stealing::StealableQueues,
+        waker,
+        worker_stats::WorkerCounters,
+        JoinResult, LocalJoinHandle, TaskMeta,
+    },
+    time::{
+        advance_local_timers, next_local_timer, update_coarse_now, Deadline,
+        UltraLowPrecisionInstant, WakeTimer,
     },
-    time::{advance_local_timers, UltraLowPrecisionInstant},
 };
 use core_affinity::CoreId;
 use crossbeam::channel;
+use futures::FutureExt;
 use std::{
     cell::{Cell, RefCell},
     collections::VecDeque,
+    convert,
     fmt::{self, Debug, Formatter},
     future::Future,
+    hint,
     pin::Pin,
-    sync::Arc,
-    time::Instant,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
 };
 use tracing::{event, Level};
 
@@ -50,6 +72,7 @@ pub struct AsyncAgent {
     command_rx: channel::Receiver<AsyncAgentCommand>,
     metrics_tx: Option<channel::Sender<ReportPage>>,
     processor_id: CoreId,
+    numa_node: Option<u16>,
 
     // Becomes None when `run()` has finished and we are safe top drop the AsyncAgent.
     engine: RefCell<Option<AsyncTaskEngine>>,
@@ -68,28 +91,88 @@ pub struct AsyncAgent {
     // If we are shutting down, we try ignore requests to schedule new tasks and do our best to
     // cleanup ASAP.
     shutting_down: Cell<bool>,
+
+    // If we are draining, we no longer accept remote tasks spawned from outside the runtime but
+    // keep executing the tasks we already have (including any tasks they spawn, here or on other
+    // workers) until the runtime tells us to terminate.
+    draining: Cell<bool>,
+
+    // Published once we have started draining, from which point on the runtime client can be sure
+    // that we accept no more remote tasks spawned from outside the runtime.
+    drain_acknowledged: Arc<AtomicBool>,
+
+    // Shared by all the workers of the runtime. Every local task we spawn is counted here until it
+    // completes. See `RuntimeClient::shutdown()`.
+    in_flight_tasks: InFlightTasks,
+
+    // If work stealing is enabled, remote tasks that may execute on any worker are queued here
+    // instead of arriving as commands, so idle siblings can take them off our hands.
+    stealable_queues: Option<StealableQueues>,
+
+    // What we do when we run out of work.
+    idle_strategy: IdleStrategy,
+
+    // How we spend our time, published for `RuntimeClient::worker_stats()`.
+    counters: Arc<WorkerCounters>,
+
+    // Periodic housekeeping, checked once per cycle.
+    maintenance: RefCell<MaintenanceTicker>,
+
+    // Worker loop state that carries over from one cycle to the next. See `turn()`.
+    allow_io_sleep: Cell<bool>,
+    idle_cycles: Cell<u32>,
+    previous_cycle_ended: Cell<Instant>,
+
+    // Ends waits for I/O when the next timer is due.
+    wake_timer: RefCell<WakeTimer>,
+
+    // Requests for a task dump that have been received but not yet answered. We answer them when
+    // we have access to the async task engine, which is not the case while processing commands.
+    pending_dumps: RefCell<Vec<oneshot::Sender<Box<[TaskDump]>>>>,
 }
 
 impl AsyncAgent {
+    #[allow(clippy::too_many_arguments)] // Ssssshhhhh, sleep little Clippy!
     pub fn new(
         command_rx: channel::Receiver<AsyncAgentCommand>,
         metrics_tx: Option<channel::Sender<ReportPage>>,
         io_shared: Arc<io::DriverShared>,
         processor_id: CoreId,
+        stealable_queues: Option<StealableQueues>,
+        idle_strategy: IdleStrategy,
+        maintenance: MaintenanceTicker,
+        in_flight_tasks: InFlightTasks,
     ) -> Self {
+        // SAFETY: The I/O driver must not be dropped while there are pending I/O operations.
+        // We ensure this by waiting for I/O to complete before returning from `run()`.
+        let io = unsafe { io::Driver::new() };
+
+        // SAFETY: The async task engine must not be dropped until we get a
+        // `CycleResult::Shutdown` from it. We do wait for this in `run()`.
+        let engine = unsafe { AsyncTaskEngine::new(io.waker()) };
+
         Self {
             command_rx,
             metrics_tx,
             processor_id,
-            // SAFETY: The async task engine must not be dropped until we get a
-            // `CycleResult::Shutdown` from it. We do wait for this in `run()`.
-            engine: RefCell::new(Some(unsafe { AsyncTaskEngine::new() })),
-            // SAFETY: The I/O driver must not be dropped while there are pending I/O operations.
-            // We ensure this by waiting for I/O to complete before returning from `run()`.
-            io: RefCell::new(Some(unsafe { io::Driver::new() })),
+            numa_node: affinity::numa_node_of(processor_id),
+            engine: RefCell::new(Some(engine)),
+            io: RefCell::new(Some(io)),
             io_shared: RefCell::new(Some(io_shared)),
             new_tasks: RefCell::new(VecDeque::new()),
             shutting_down: Cell::new(false),
+            draining: Cell::new(false),
+            drain_acknowledged: Arc::new(AtomicBool::new(false)),
+            in_flight_tasks,
+            stealable_queues,
+            idle_strategy,
+            counters: Arc::new(WorkerCounters::default()),
+            maintenance: RefCell::new(maintenance),
+            allow_io_sleep: Cell::new(false),
+            idle_cycles: Cell::new(0),
+            previous_cycle_ended: Cell::new(Instant::now()),
+            wake_timer: RefCell::new(WakeTimer::new()),
+            pending_dumps: RefCell::new(Vec::new()),
         }
     }
 
@@ -97,6 +180,30 @@ impl AsyncAgent {
         self.processor_id
     }
 
+    /// The NUMA node of the processor this agent is assigned to, if known.
+    pub fn numa_node(&self) -> Option<u16> {
+        self.numa_node
+    }
+
+    /// The flag that the agent sets once it has started draining. See `RuntimeClient::shutdown()`.
+    pub fn drain_acknowledged(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.drain_acknowledged)
+    }
+
+    /// The time accounting that the agent publishes. See `RuntimeClient::worker_stats()`.
+    pub fn counters(&self) -> Arc<WorkerCounters> {
+        Arc::clone(&self.counters)
+    }
+
+    /// Identifies the task the agent is polling to other threads. See `RuntimeClient::dump()`.
+    pub fn polling_task(&self) -> Arc<PollingTask> {
+        self.engine
+            .borrow()
+            .as_ref()
+            .expect("the engine is only removed on shutdown so it must still be there")
+            .polling_task()
+    }
+
     pub fn with_io<F, R>(&self, f: F) -> R
     where
         F: FnOnce(&mut io::Driver) -> R,
@@ -121,23 +228,80 @@ impl AsyncAgent {
         f(io_ref)
     }
 
-    /// Spawns a task to execute a future on the current async worker thread.
+    /// Spawns a task to execute a future on the current async worker thread. The task is
+    /// identified in diagnostics by the provided metadata.
     ///
     /// # Panics
     ///
     /// Panics if the current thread is not an async worker thread. This is possible because there
     /// are more types of runtime threads than async worker threads - e.g. sync worker threads.
-    pub fn spawn<F, R>(&self, future: F) -> LocalJoinHandle<R>
+    pub fn spawn<F, R>(&self, meta: TaskMeta, future: F) -> LocalJoinHandle<R>
+    where
+        F: Future<Output = R> + 'static,
+        R: 'static,
+    {
+        let permit =
+            admission::admit(false).expect("admission without enforcement always succeeds");
+
+        self.spawn_admitted(meta, future.map(Ok), permit)
+    }
+
+    /// Spawns a task like `spawn()` but cancels it if it has not completed by the deadline, in
+    /// which case the join handle reports `JoinError::DeadlineExceeded`.
+    pub fn spawn_with_deadline<F, R>(
+        &self,
+        meta: TaskMeta,
+        deadline: Deadline,
+        future: F,
+    ) -> LocalJoinHandle<R>
+    where
+        F: Future<Output = R> + 'static,
+        R: 'static,
+    {
+        let permit =
+            admission::admit(false).expect("admission without enforcement always succeeds");
+
+        // The task is polled outside of the current scope, so it inherits the deadline of the
+        // current scope here, if that is earlier.
+        let deadline = Deadline::current().map_or(deadline, |current| current.min(deadline));
+
+        self.spawn_admitted(meta, WithDeadline::new(future, deadline), permit)
+    }
+
+    /// Spawns a task like `spawn()` but only if the task count limits of the runtime have not been
+    /// reached. See `RuntimeBuilder::max_tasks_per_worker()` and `RuntimeBuilder::max_tasks()`.
+    pub fn try_spawn<F, R>(
+        &self,
+        meta: TaskMeta,
+        future: F,
+    ) -> Result<LocalJoinHandle<R>, SpawnError>
     where
         F: Future<Output = R> + 'static,
         R: 'static,
+    {
+        let permit = admission::admit(true)?;
+
+        Ok(self.spawn_admitted(meta, future.map(Ok), permit))
+    }
+
+    // The future is fallible so that wrappers like `WithDeadline` can fail the task with their own
+    // `JoinError`, which is merged with the errors detected by `Abortable`.
+    fn spawn_admitted<F, R>(
+        &self,
+        meta: TaskMeta,
+        future: F,
+        permit: TaskPermit,
+    ) -> LocalJoinHandle<R>
+    where
+        F: Future<Output = JoinResult<R>> + 'static,
+        R: 'static,
     {
         assert!(
             !self.shutting_down.get(),
             "local tasks can only be spawned by the current thread; no call path takes us to spawning local tasks when we have already started local shutdown"
         );
 
-        LOCAL_TASKS.with(Event::observe_unit);
+        observe!(LOCAL_TASKS.observe_unit());
 
         // SAFETY: We must ensure that the LocalTask is not dropped while any references to its
         // outcome exist (i.e. as long as the join handle is referenced by someone). The join handle
@@ -157,8 +321,22 @@ impl AsyncAgent {
         // shutdown! The answer is `ErasedTask::clear()` which drops the future and any captured
         // state such as the join handle! The task engine also relies on that capability to drop
         // wakers and break waker reference cycles and we use this to also cancel pending I/O.
-        let mut task = unsafe { LocalTask::new(future) };
-        let join_handle = task.as_mut().join_handle();
+        //
+        // The future is wrapped in `Abortable` so the join handle can be used to abort the task.
+        // The admission permit and the in-flight guard live as long as the future, so the task
+        // stops counting against the task limits and as in-flight work as soon as it has
+        // completed, before its result is delivered.
+        let abort = AbortState::new();
+        let id = meta.id();
+        let future = Abortable::new(future, Arc::clone(&abort));
+        let in_flight = self.in_flight_tasks.track();
+        let future = async move {
+            let _permit = permit;
+            let _in_flight = in_flight;
+            future.await.and_then(convert::identity)
+        };
+        let mut task = unsafe { LocalTask::new(meta, future) };
+        let join_handle = LocalJoinHandle::new(task.as_mut().take_result_rx(), abort, id);
 
         // We queue up the tasks because we may be being called from within the async task engine
         // itself, so we cannot call back into it immediately.
@@ -167,13 +345,58 @@ impl AsyncAgent {
     }
 
     pub fn run(&self) {
+        self.begin_run();
+        self.enable_batching();
+
+        while self.turn(CROSS_THREAD_WORK_POLL_INTERVAL_MS) {}
+
+        self.finish_run();
+        self.disable_batching();
+    }
+
+    /// Prepares the current thread for executing the worker loop one cycle at a time via `turn()`.
+    pub fn begin_run(&self) {
         event!(Level::TRACE, "Started");
 
+        self.previous_cycle_ended.set(Instant::now());
+    }
+
+    /// Enables batching of wakeups made from the current thread, which `turn()` and `finish_run()`
+    /// require. The batches are submitted at the end of each cycle but a thread that does anything
+    /// else between cycles (e.g. an embedded worker returning to its host) must disable batching
+    /// in the meantime, as its wakeups would otherwise wait for the next cycle.
+    pub fn enable_batching(&self) {
         // Any I/O wakeups from async task threads are batched and submitted at the end of each
         // loop cycle, to avoid double-dispatch when a loop processes many I/O wakeups for the same
         // target.
         io::IoWaker::enable_batching();
 
+        // Similarly, wakes of tasks owned by other worker threads are batched and delivered at the
+        // end of each loop cycle, so waking many tasks of the same worker only locks its queue of
+        // awakened tasks once and wakes up its thread once.
+        waker::enable_remote_wake_batching(
+            self.engine
+                .borrow()
+                .as_ref()
+                .expect("the engine is only removed on shutdown so it must still be there")
+                .awakened_queue(),
+        );
+    }
+
+    /// Submits any pending wakeups and disables batching on the current thread.
+    pub fn disable_batching(&self) {
+        // Remote wakes may wake up I/O drivers, so they go first.
+        waker::disable_remote_wake_batching();
+        io::IoWaker::disable_batching();
+    }
+
+    /// Performs one cycle of the worker loop. If there is nothing to do, the idle strategy decides
+    /// whether to sleep, for at most `max_wait_ms`, waiting for I/O completions or wakeups from
+    /// other threads.
+    ///
+    /// Returns `false` once the async task engine has finished shutting down, after which no more
+    /// cycles may be performed and `finish_run()` must be called to release resources.
+    pub fn turn(&self, max_wait_ms: u32) -> bool {
         // We want to do useful work in this loop as much as possible, yet without burning CPU on
         // just pinning and waiting for work.
         //
@@ -181,7 +404,7 @@ impl AsyncAgent {
         // * I/O completion arrived on the I/O driver.
         // * An "enqueue new task" command was received from an arbitrary thread.
         // * Some task on the current thread enqueued another task.
-        // * A timer expired (not implemented yet).
+        // * A timer expired.
         // * A sleeping task was woken up
         //     If it wakes up due to current thread activity, we can just think of it as a
         //     consequence of that activity (e.g. I/O completion). However, a task can also be woken
@@ -202,148 +425,239 @@ impl AsyncAgent {
         // fallback, we wake up every N milliseconds to check for new work from other sources even
         // if there is no activity on the I/O driver. This may add some latency to the cases where
         // we cannot immediately trigger an I/O wakeup (typically up to 20 milliseconds).
+        //
+        // The state that carries over from one cycle to the next is stored in the agent, so the
+        // cycles can be driven either by `run()` or, for embedded runtimes, by the host thread.
 
         // If we have any reason to believe that we have non-I/O work to do, we set this to false,
         // which only dequeues already existing I/O completions and does not wait for new ones.
-        let mut allow_io_sleep = false;
+        let mut allow_io_sleep = self.allow_io_sleep.get();
 
-        // We are the only one referencing the engine, so just keep the reference around for good.
+        // Nobody else references the engine during a cycle.
         let mut engine_guard = self.engine.borrow_mut();
         let engine = engine_guard
             .as_mut()
             .expect("the engine is only removed on shutdown so it must still be there");
 
-        loop {
-            // At the start of each iteration, we update the ultra-low precision clock. All
-            // observations of its value during this cycle will use the value we set here.
-            UltraLowPrecisionInstant::update();
-
-            match self.process_commands() {
-                ProcessCommandsResult::ContinueAfterCommand => {
-                    // Commands were received. We probably have non-I/O work to do.
-                    allow_io_sleep = false;
-                }
-                ProcessCommandsResult::ContinueWithoutCommands => {
-                    // No commands received - we have no information saying we have non-I/O work to do.
-                }
-                ProcessCommandsResult::Terminate => {
-                    // Given various eventual consistency scenarios that may apply to the
-                    // coordination of worker threads, it is conceivable that somehow we might get
-                    // multiple shutdown commands. Just ignore any extra ones - we cannot be
-                    // shutting down any harder than we already are.
-                    if !self.shutting_down.get() {
-                        // This *starts* our shutdown - we still need to wait for the async task
-                        // engine to clean up and for pending I/O operations to complete.
-                        event!(
-                            Level::TRACE,
-                            "received terminate command; shutdown process starting"
-                        );
+        // At the start of each iteration, we update the ultra-low precision clock. All
+        // observations of its value during this cycle will use the value we set here.
+        UltraLowPrecisionInstant::update();
 
-                        self.shutting_down.set(true);
-
-                        // The tasks in this list may own resources that are already referenced by other
-                        // tasks or external entities. We need to accept them into our regular process
-                        // before dropping them - they are not safe to drop just because they are new.
-                        while let Some(erased_task) = self.new_tasks.borrow_mut().pop_front() {
-                            engine.enqueue_erased(erased_task);
-                        }
-
-                        // Start cleaning up the async task engine. This may require some time if there
-                        // are foreign threads holding our wakers. We wait for all wakers to be dropped.
-                        engine.begin_shutdown();
-
-                        // The I/O driver itself does not have a shutdown process - we simply need
-                        // to wait for all pending operations to complete. This will occur naturally
-                        // over time, speeded up by the fact that the async task engine dropped a
-                        // bunch of tasks that were hopefully holding I/O handles that now got
-                        // closed and resulted in pending I/O being canceled (which we still need to
-                        // wait for - a cancellation is just a regular I/O completion for us).
-                    }
-                }
-            }
+        // The watchdog considers the worker blocked if it stays busy for too long.
+        self.counters.record_busy();
 
-            // If new tasks have been enqueued but not yet handed over to the engine, we inhibit I/O
-            // sleep to get to processing those new tasks ASAP after any pending I/O is completed.
-            allow_io_sleep &= self.new_tasks.borrow().is_empty();
+        match self.process_commands() {
+            ProcessCommandsResult::ContinueAfterCommand => {
+                // Commands were received. We probably have non-I/O work to do.
+                allow_io_sleep = false;
+            }
+            ProcessCommandsResult::ContinueWithoutCommands => {
+                // No commands received - we have no information saying we have non-I/O work to do.
+            }
+            ProcessCommandsResult::Terminate => {
+                // Given various eventual consistency scenarios that may apply to the
+                // coordination of worker threads, it is conceivable that somehow we might get
+                // multiple shutdown commands. Just ignore any extra ones - we cannot be
+                // shutting down any harder than we already are.
+                if !self.shutting_down.get() {
+                    // This *starts* our shutdown - we still need to wait for the async task
+                    // engine to clean up and for pending I/O operations to complete.
+                    event!(
+                        Level::TRACE,
+                        "received terminate command; shutdown process starting"
+                    );
 
-            let io_wait_time_ms = if allow_io_sleep {
-                CYCLES_WITH_SLEEP.with(Event::observe_unit);
+                    self.shutting_down.set(true);
 
-                CROSS_THREAD_WORK_POLL_INTERVAL_MS
-            } else {
-                CYCLES_WITHOUT_SLEEP.with(Event::observe_unit);
+                    // The tasks in this list may own resources that are already referenced by other
+                    // tasks or external entities. We need to accept them into our regular process
+                    // before dropping them - they are not safe to drop just because they are new.
+                    while let Some(erased_task) = self.new_tasks.borrow_mut().pop_front() {
+                        engine.enqueue_erased(erased_task);
+                    }
 
-                0
-            };
+                    // Start cleaning up the async task engine. This may require some time if there
+                    // are foreign threads holding our wakers. We wait for all wakers to be dropped.
+                    engine.begin_shutdown();
 
-            self.io
-                .borrow_mut()
-                .as_mut()
-                .expect("the I/O driver is only removed on shutdown so it must still be there")
-                .process_completions(io_wait_time_ms);
-
-            // We always only poll this, never wait on it - any waiting occurs above. One
-            // implication of this is that if a completion arrives here, we may still end up waiting
-            // on the above for some milliseconds. That's OK - this is shared so there are many
-            // threads polling it all the time, the delay is negligible in the big picture.
-            self.io_shared
-                .borrow()
-                .as_ref()
-                .expect(
-                    "the shared I/O driver is only removed on shutdown so it must still be there",
-                )
-                .process_completions();
-
-            // TODO: Timers require that we provide an instant value. Some additional work we can explore:
-            //
-            // - What are the perf implications of this call?
-            // - Shall we pass the current instant to `execute_cycle` and get rid of low-resolution watch?
-            // - Shall we introduce some cached sink for current time (both relative and absolute) that is updated with each cycle?
-            let now = Instant::now();
-            advance_local_timers(now);
-
-            {
-                let mut new_tasks = self.new_tasks.borrow_mut();
-
-                while let Some(erased_task) = new_tasks.pop_front() {
-                    engine.enqueue_erased(erased_task);
+                    // The I/O driver itself does not have a shutdown process - we simply need
+                    // to wait for all pending operations to complete. This will occur naturally
+                    // over time, speeded up by the fact that the async task engine dropped a
+                    // bunch of tasks that were hopefully holding I/O handles that now got
+                    // closed and resulted in pending I/O being canceled (which we still need to
+                    // wait for - a cancellation is just a regular I/O completion for us).
                 }
             }
+        }
+
+        for reply_tx in self.pending_dumps.borrow_mut().drain(..) {
+            // The requester may have given up waiting already, which is fine.
+            _ = reply_tx.send(engine.dump_tasks());
+        }
 
-            let execute_cycle_result = engine.execute_cycle();
+        if self.accept_stealable_tasks() {
+            allow_io_sleep = false;
+        }
 
-            // The async task engine may have scheduled some runtime commands to be sent out.
-            // Deliver them to runtime agents now so we ensure commands are sent every cycle.
-            current_runtime::with(|runtime| runtime.submit_pending_tasks());
+        // If new tasks have been enqueued but not yet handed over to the engine, we inhibit I/O
+        // sleep to get to processing those new tasks ASAP after any pending I/O is completed.
+        allow_io_sleep &= self.new_tasks.borrow().is_empty();
 
-            // Now is a good time to submit any I/O wakeups for other threads.
-            io::IoWaker::submit_batch();
+        let io_wait_time_ms = if allow_io_sleep {
+            let idle_cycles = self.idle_cycles.get();
+            self.idle_cycles.set(idle_cycles.saturating_add(1));
 
-            match execute_cycle_result {
-                CycleResult::Continue => {
-                    // The async task engine believes there may be more work to do, so no sleep.
-                    allow_io_sleep = false;
-                    continue;
+            match self.idle_strategy.action(idle_cycles) {
+                IdleAction::Park => {
+                    observe!(CYCLES_WITH_SLEEP.observe_unit());
+
+                    max_wait_ms
                 }
-                CycleResult::Suspend => {
-                    // The async task engine had nothing to do, so it thinks we can sleep now. OK.
-                    allow_io_sleep = true;
+                IdleAction::Spin => {
+                    observe!(CYCLES_IDLE_SPIN.observe_unit());
+                    hint::spin_loop();
+
+                    0
                 }
-                CycleResult::Shutdown => {
-                    // The async task engine has finished shutting down, so we can now exit.
-                    event!(
-                        Level::TRACE,
-                        "async tasks engine reported it is safe to shut down"
-                    );
-                    break;
+                IdleAction::Yield => {
+                    observe!(CYCLES_IDLE_YIELD.observe_unit());
+                    thread::yield_now();
+
+                    0
                 }
-            };
+            }
+        } else {
+            observe!(CYCLES_WITHOUT_SLEEP.observe_unit());
+            self.idle_cycles.set(0);
+
+            0
+        };
+
+        // Any time we spend waiting for I/O is time spent parked, not doing work.
+        let park_started = (io_wait_time_ms > 0).then(Instant::now);
+
+        // The wait must not outlast the next timer, or sleeps and timeouts would fire late.
+        let (io_wait_time_ms, alertable) = match park_started {
+            Some(now) => {
+                self.wake_timer
+                    .borrow_mut()
+                    .prepare_wait(now, next_local_timer(), io_wait_time_ms)
+            }
+            None => (0, false),
+        };
+
+        {
+            let mut io = self.io.borrow_mut();
+            let io = io
+                .as_mut()
+                .expect("the I/O driver is only removed on shutdown so it must still be there");
+
+            // Parking is not being blocked, so the watchdog only observes the time around it.
+            if io_wait_time_ms > 0 {
+                self.counters.record_not_busy();
+                io.process_completions(io_wait_time_ms, alertable);
+                self.counters.record_busy();
+            } else {
+                io.process_completions(io_wait_time_ms, alertable);
+            }
+
+            self.counters
+                .record_io_operations(io.operations_in_flight());
         }
 
+        // We always only poll this, never wait on it - any waiting occurs above. One
+        // implication of this is that if a completion arrives here, we may still end up waiting
+        // on the above for some milliseconds. That's OK - this is shared so there are many
+        // threads polling it all the time, the delay is negligible in the big picture.
+        self.io_shared
+            .borrow()
+            .as_ref()
+            .expect("the shared I/O driver is only removed on shutdown so it must still be there")
+            .process_completions();
+
+        // TODO: Timers require that we provide an instant value. Some additional work we can explore:
+        //
+        // - What are the perf implications of this call?
+        // - Shall we pass the current instant to `execute_cycle` and get rid of low-resolution watch?
+        let now = Instant::now();
+
+        // Tasks polled in this cycle observe this instant via `time::coarse_now()`.
+        update_coarse_now(now);
+        advance_local_timers(now);
+
+        let parked = park_started.map_or(Duration::ZERO, |started| now - started);
+
+        if !parked.is_zero() {
+            observe!(PARKED_TIME.observe_millis(parked));
+        }
+
+        self.counters
+            .record_cycle(now - self.previous_cycle_ended.get(), parked);
+        self.previous_cycle_ended.set(now);
+
+        if self.maintenance.borrow_mut().tick_if_due(now) {
+            // Stalls are measured in seconds, so there is no need to look for them more often.
+            engine.check_stalled_tasks();
+
+            #[cfg(feature = "metrics-persistence")]
+            metrics::persist_if_due();
+        }
+
+        {
+            let mut new_tasks = self.new_tasks.borrow_mut();
+
+            while let Some(erased_task) = new_tasks.pop_front() {
+                engine.enqueue_erased(erased_task);
+            }
+        }
+
+        let execute_cycle_result = engine.execute_cycle();
+
+        // The async task engine may have scheduled some runtime commands to be sent out.
+        // Deliver them to runtime agents now so we ensure commands are sent every cycle.
+        current_runtime::with(|runtime| runtime.submit_pending_tasks());
+
+        // Now is a good time to submit any wakeups for other threads. The task wakeups go first
+        // because they may add I/O wakeups to the batch.
+        waker::submit_remote_wake_batch();
+        io::IoWaker::submit_batch();
+
+        self.counters.record_not_busy();
+
+        match execute_cycle_result {
+            CycleResult::Continue => {
+                // The async task engine believes there may be more work to do, so no sleep.
+                self.allow_io_sleep.set(false);
+                true
+            }
+            CycleResult::Suspend => {
+                // The async task engine had nothing to do, so it thinks we can sleep now. OK.
+                // Unless we can help out a sibling who has more work queued than it can handle.
+                self.allow_io_sleep.set(!self.try_steal_tasks());
+                true
+            }
+            CycleResult::Shutdown => {
+                // The async task engine has finished shutting down, so we can now exit.
+                event!(
+                    Level::TRACE,
+                    "async tasks engine reported it is safe to shut down"
+                );
+                false
+            }
+        }
+    }
+
+    /// Waits for pending I/O to complete and releases the resources of the agent. Called once
+    /// `turn()` has returned `false`.
+    pub fn finish_run(&self) {
         // Release resources before we finish shutdown, as now is a good time to clean up.
         // We can start by cleaning up the task engine because we know all tasks have been dropped
         // and no more can be scheduled. There is nothing for the task engine to do anymore.
-        *engine_guard = None;
+        *self.engine.borrow_mut() = None;
+
+        // Dropping the engine may have woken up tasks on other threads.
+        waker::submit_remote_wake_batch();
+        io::IoWaker::submit_batch();
 
         {
             let mut io_guard = self.io.borrow_mut();
@@ -365,10 +679,11 @@ impl AsyncAgent {
             // Really, there is nothing else to do because task execution logic has been shut down
             // already.
             while !io.is_inert() || !io_shared.is_inert() {
-                io.process_completions(CROSS_THREAD_WORK_POLL_INTERVAL_MS);
+                io.process_completions(CROSS_THREAD_WORK_POLL_INTERVAL_MS, false);
                 io_shared.process_completions();
 
                 // I/O completions could trigger wakeups of other threads.
+                waker::submit_remote_wake_batch();
                 io::IoWaker::submit_batch();
             }
 
@@ -381,11 +696,78 @@ impl AsyncAgent {
 
         event!(Level::TRACE, "shutdown completed");
 
+        #[cfg(feature = "metrics-persistence")]
+        metrics::persist_final();
+
         if let Some(tx) = &self.metrics_tx {
             _ = tx.send(metrics::report_page());
         }
     }
 
+    /// Moves tasks from our own stealable queue to the new tasks list. Returns whether any tasks
+    /// were accepted.
+    fn accept_stealable_tasks(&self) -> bool {
+        let Some(stealable_queues) = &self.stealable_queues else {
+            return false;
+        };
+
+        let mut accepted_any = false;
+
+        while let Some(erased_task) = stealable_queues.pop_own() {
+            // Same as with remote tasks arriving via commands, we reject them if we are no longer
+            // accepting new work.
+            if !self.accepts_remote_task(&*erased_task) {
+                reject_remote_task(&*erased_task);
+                continue;
+            }
+
+            accepted_any = true;
+            observe!(REMOTE_TASKS.observe_unit());
+            self.new_tasks.borrow_mut().push_back(erased_task);
+        }
+
+        accepted_any
+    }
+
+    /// Takes not-yet-started remote tasks from a sibling worker, if work stealing is enabled and
+    /// some sibling has tasks waiting. Returns whether any tasks were stolen.
+    fn try_steal_tasks(&self) -> bool {
+        let Some(stealable_queues) = &self.stealable_queues else {
+            return false;
+        };
+
+        if self.shutting_down.get() || self.draining.get() {
+            return false;
+        }
+
+        let mut new_tasks = self.new_tasks.borrow_mut();
+        let stolen = stealable_queues.steal(|erased_task| new_tasks.push_back(erased_task));
+
+        if stolen == 0 {
+            return false;
+        }
+
+        event!(
+            Level::TRACE,
+            message = "stole tasks from sibling",
+            count = stolen
+        );
+        observe!(STOLEN_TASKS.observe(stolen as i64));
+
+        true
+    }
+
+    /// Whether we are still accepting a remote task that has arrived at us. While draining, we only
+    /// accept tasks spawned by the work already in flight on the runtime (e.g. a task spawning
+    /// another via `spawn_on_any()`), as otherwise the in-flight work could never complete.
+    fn accepts_remote_task(&self, erased_task: &dyn ErasedResultAsyncTask) -> bool {
+        if self.shutting_down.get() {
+            return false;
+        }
+
+        !self.draining.get() || erased_task.is_spawned_by_runtime()
+    }
+
     fn process_commands(&self) -> ProcessCommandsResult {
         let mut received_commands = false;
         let mut received_terminate = false;
@@ -394,22 +776,40 @@ impl AsyncAgent {
             match self.command_rx.try_recv() {
                 Ok(AsyncAgentCommand::EnqueueTask { erased_task }) => {
                     // This is how remote tasks arrive at us. If we are shutting down then we do NOT
-                    // want to process them and will simply drop them on the floor. This is safe
-                    // because remote tasks are expected to always be inert (they hold no resources
-                    // that need special cleanup, at least not yet, because they have no local
-                    // presence yet).
-                    if self.shutting_down.get() || received_terminate {
-                        assert!(
-                            erased_task.is_inert(),
-                            "all remote tasks must be always inert"
-                        );
+                    // want to process them and will simply reject them. This is safe because remote
+                    // tasks are expected to always be inert (they hold no resources that need
+                    // special cleanup, at least not yet, because they have no local presence yet).
+                    //
+                    // The same applies if we are draining, unless the task was spawned by work that
+                    // is already in flight.
+                    if received_terminate || !self.accepts_remote_task(&*erased_task) {
+                        reject_remote_task(&*erased_task);
                         continue;
                     }
 
                     received_commands = true;
-                    REMOTE_TASKS.with(Event::observe_unit);
+                    observe!(REMOTE_TASKS.observe_unit());
                     self.new_tasks.borrow_mut().push_back(erased_task);
                 }
+                Ok(AsyncAgentCommand::Drain) => {
+                    if !self.draining.get() {
+                        event!(
+                            Level::TRACE,
+                            "received drain command; no longer accepting remote tasks"
+                        );
+                    }
+
+                    // Any remote task that arrives after this point is rejected unless it was
+                    // spawned by in-flight work, which has already counted it as in flight.
+                    self.draining.set(true);
+                    self.drain_acknowledged.store(true, Ordering::Release);
+                    received_commands = true;
+                    continue;
+                }
+                Ok(AsyncAgentCommand::Dump { reply_tx }) => {
+                    self.pending_dumps.borrow_mut().push(reply_tx);
+                    continue;
+                }
                 Ok(AsyncAgentCommand::Terminate) => {
                     // We continue processing commands even after the terminate signal because
                     // we need to clean up any messages received during the shutdown process,
@@ -437,6 +837,17 @@ impl AsyncAgent {
     }
 }
 
+/// Drops a remote task that we are not going to execute, resolving its join handle as cancelled so
+/// whoever is awaiting it does not wait forever.
+fn reject_remote_task(erased_task: &dyn ErasedResultAsyncTask) {
+    assert!(
+        erased_task.is_inert(),
+        "all remote tasks must be always inert"
+    );
+
+    erased_task.reject();
+}
+
 /// How often to poll for cross-thread work, in milliseconds. We do not have cross-thread real time
 /// signals for everything (though we have for most things, just not always guaranteed) and use
 /// polling to check for arriving work. This sets our maximum sleep time, although
@@ -450,6 +861,13 @@ impl Debug for AsyncAgent {
             .field("engine", &self.engine)
             .field("io", &self.io)
             .field("shutting_down", &self.shutting_down)
+            .field("draining", &self.draining)
+            .field("drain_acknowledged", &self.drain_acknowledged)
+            .field("in_flight_tasks", &self.in_flight_tasks)
+            .field("stealable_queues", &self.stealable_queues)
+            .field("idle_strategy", &self.idle_strategy)
+            .field("counters", &self.counters)
+            .field("maintenance", &self.maintenance)
             .finish()
     }
 }
@@ -473,6 +891,16 @@ pub enum AsyncAgentCommand {
         erased_task: Pin<Box<dyn ErasedResultAsyncTask + Send>>,
     },
 
+    /// Stops accepting remote tasks but keeps executing existing tasks. The worker will publish
+    /// the number of tasks that have not yet completed, so the runtime can wait for them to finish
+    /// before sending `Terminate`.
+    Drain,
+
+    /// Requests a description of all the live tasks of the worker. See `RuntimeClient::dump()`.
+    Dump {
+        reply_tx: oneshot::Sender<Box<[TaskDump]>>,
+    },
+
     /// Shuts down the worker thread immediately, without waiting for any pending operations to
     /// complete. The worker will still complete the current task and perform necessary cleanup
     /// to avoid resource leaks, which may take some time.
@@ -483,6 +911,8 @@ impl Debug for AsyncAgentCommand {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
             Self::EnqueueTask { .. } => write!(f, "EnqueueTask"),
+            Self::Drain => write!(f, "Drain"),
+            Self::Dump { .. } => write!(f, "Dump"),
             Self::Terminate => write!(f, "Terminate"),
         }
     }
@@ -508,9 +938,23 @@ thread_local! {
     static REMOTE_TASKS: Event = EventBuilder::new("rt_async_tasks_remote")
         .build();
 
+    static STOLEN_TASKS: Event = EventBuilder::new("rt_async_tasks_stolen")
+        .buckets(&[1, 2, 4, 8, 16, 32])
+        .build();
+
     static CYCLES_WITH_SLEEP: Event = EventBuilder::new("rt_async_cycles_with_sleep")
         .build();
 
     static CYCLES_WITHOUT_SLEEP: Event = EventBuilder::new("rt_async_cycles_without_sleep")
         .build();
+
+    static CYCLES_IDLE_SPIN: Event = EventBuilder::new("rt_async_cycles_idle_spin")
+        .build();
+
+    static CYCLES_IDLE_YIELD: Event = EventBuilder::new("rt_async_cycles_idle_yield")
+        .build();
+
+    static PARKED_TIME: Event = EventBuilder::new("rt_async_parked_millis")
+        .buckets(GENERAL_MILLISECONDS_BUCKETS)
+        .build();
 }
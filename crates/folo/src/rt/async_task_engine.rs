@@ -1,24 +1,35 @@
 use crate::{
     collections::BuildPointerHasher,
-    constants::{GENERAL_MILLISECONDS_BUCKETS, POISONED_LOCK},
-    io::IO_DEQUEUE_BATCH_SIZE,
+    constants::{GENERAL_BYTES_BUCKETS, GENERAL_MILLISECONDS_BUCKETS, POISONED_LOCK},
+    io::{IoWaker, IO_DEQUEUE_BATCH_SIZE},
     mem::{DropPolicy, PinnedSlabChain},
-    metrics::{Event, EventBuilder},
-    rt::{erased_async_task::ErasedResultAsyncTask, waker::WakeSignal},
+    metrics::{observe, Event, EventBuilder, Magnitude},
+    rt::{
+        coop,
+        diagnostics::{self, Diagnostic},
+        dump::{PollingTask, TaskDump, TaskState},
+        erased_async_task::ErasedResultAsyncTask,
+        flight_recorder::{self, FlightEvent},
+        task_frames,
+        task_trace::{self, Suspension},
+        waker::WakeSignal,
+    },
     time::LowPrecisionInstant,
 };
 use negative_impl::negative_impl;
-use pin_project::pin_project;
+use pin_project::{pin_project, pinned_drop};
 use std::{
-    cell::RefCell,
+    cell::{Cell, RefCell},
     collections::{HashSet, VecDeque},
     fmt::{self, Debug, Formatter},
+    mem::{self, ManuallyDrop},
     pin::Pin,
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc, Mutex,
     },
     task,
+    time::Duration,
 };
 
 type TaskKey = usize;
@@ -109,6 +120,23 @@ pub struct AsyncTaskEngine {
 
     // Used to report interval between cycles.
     last_cycle_ended: Option<LowPrecisionInstant>,
+
+    // A task woken by the task that was just polled, to be polled next instead of going to the back
+    // of the queue. In request/response patterns (task A sends a message to task B and waits for
+    // the response), this means B gets to respond while the message is still hot in the cache.
+    // The items are pinned pointers into the `tasks` collection.
+    lifo_slot: Option<*mut Task>,
+
+    // How many tasks in a row have been polled from the LIFO slot. Two tasks that keep waking each
+    // other could otherwise monopolize the worker thread, so we limit this.
+    lifo_streak: usize,
+
+    // Identifies the task being polled to other threads, for diagnostic dumps.
+    polling_task: Arc<PollingTask>,
+
+    // Wakes up the thread that owns the engine, used by wakers on other threads. Every task gets a
+    // copy for its wake signal.
+    io_waker: IoWaker,
 }
 
 // We prefer to get wakeup notifications via the "awakened" queue. This may not always be possible
@@ -123,7 +151,7 @@ impl AsyncTaskEngine {
     /// # Safety
     ///
     /// You must receive the `CycleResult::Shutdown` result before it is safe to drop the engine.
-    pub unsafe fn new() -> Self {
+    pub unsafe fn new(io_waker: IoWaker) -> Self {
         Self {
             // We use MustNotDropItems because the tasks contain elements referenced via raw
             // pointers (e.g. the wake signal) which means their lifetime must be carefully managed.
@@ -138,6 +166,10 @@ impl AsyncTaskEngine {
             completed: VecDeque::new(),
             shutting_down: false,
             last_cycle_ended: None,
+            lifo_slot: None,
+            lifo_streak: 0,
+            polling_task: Arc::new(PollingTask::default()),
+            io_waker,
         }
     }
 
@@ -155,6 +187,9 @@ impl AsyncTaskEngine {
 
         let inserter = self.tasks.begin_insert();
 
+        let frame_size = mem::size_of_val(&*erased_task);
+        observe!(TASK_FRAME_SIZE.observe(frame_size as Magnitude));
+
         // SAFETY: We are responsible for not dropping the task until it is inert. We accomplish
         // this by only removing tasks after they pass through the `completed` list and indicate
         // that they have become inert. We must also initialize the task with ::initialize() before
@@ -163,11 +198,17 @@ impl AsyncTaskEngine {
             Task::new(
                 inserter.index(),
                 erased_task,
+                frame_size,
                 Arc::clone(&self.awakened),
                 Arc::clone(&self.probe_embedded_wake_signals),
+                self.io_waker.clone(),
             )
         };
 
+        flight_recorder::record(FlightEvent::TaskSpawned {
+            task_id: task.inner.borrow().meta().id(),
+        });
+
         let task_ptr = inserter.insert_raw(task);
 
         // We must initialize it once pinned, to set up the self-referential pointer.
@@ -182,7 +223,7 @@ impl AsyncTaskEngine {
         let cycle_start = LowPrecisionInstant::now();
 
         if let Some(last_end) = self.last_cycle_ended {
-            CYCLE_INTERVAL.with(|x| x.observe_millis(cycle_start.duration_since(last_end)));
+            observe!(CYCLE_INTERVAL.observe_millis(cycle_start.duration_since(last_end)));
         }
 
         // This is the moment we wake up any tasks that were signaled to wake up. The signals may
@@ -195,16 +236,51 @@ impl AsyncTaskEngine {
         // We do not really care why/how the wake signal was sent - same handling for all cases.
         self.activate_awakened_tasks();
 
-        while let Some(task_ptr) = self.active.pop_front() {
+        while let Some(task_ptr) = self.next_task() {
             // SAFETY: This comes from a pinned slab and we are responsible for dropping tasks, which
             // we never do until they progress through the lifecycle into the `completed` list.
             let task = unsafe { Pin::new_unchecked(&*task_ptr) };
 
+            let awakened_before_poll = self.awakened.lock().expect(POISONED_LOCK).len();
+
+            let task_id = task.inner.borrow().meta().id();
+
+            let poll_start = LowPrecisionInstant::now();
+            task.last_polled.set(Some(poll_start));
+            task.stall_reported.set(false);
+            self.polling_task
+                .enter(task_id, poll_start, task.inner.borrow().meta().name());
+            task_trace::begin_poll();
+
             let poll_result = task.poll();
 
+            let suspension = task_trace::end_poll();
+            self.polling_task.exit();
+
+            let poll_duration = poll_start.elapsed();
+            task.record_poll(poll_duration);
+
+            flight_recorder::record(FlightEvent::TaskPolled {
+                task_id,
+                duration: poll_duration,
+                completed: poll_result.is_ready(),
+            });
+
+            check_poll_duration(&task, poll_duration);
+
+            #[cfg(feature = "runtime-tracing")]
+            task.inner
+                .borrow()
+                .meta()
+                .trace_poll(poll_duration, poll_result.is_ready());
+
             match poll_result {
                 task::Poll::Ready(()) => {
-                    TASKS_COMPLETED.with(Event::observe_unit);
+                    observe!(TASKS_COMPLETED.observe_unit());
+
+                    if let Some(memory) = task.inner.borrow().meta().memory() {
+                        observe!(TASK_PEAK_CHARGED_MEMORY.observe(memory.peak() as Magnitude));
+                    }
 
                     // This ensures that any state held by the task is dropped. Most importantly, it
                     // may be holding a clone of a waker, which could create a circular reference
@@ -214,10 +290,13 @@ impl AsyncTaskEngine {
                     self.completed.push_back(task_ptr);
                 }
                 task::Poll::Pending => {
-                    TASK_INACTIVATED.with(Event::observe_unit);
+                    observe!(TASK_INACTIVATED.observe_unit());
+                    task.suspension.replace(suspension);
                     self.inactive.insert(task_ptr);
                 }
             }
+
+            self.fill_lifo_slot(task_ptr, awakened_before_poll);
         }
 
         self.drop_inert_tasks();
@@ -225,7 +304,7 @@ impl AsyncTaskEngine {
         let cycle_end = LowPrecisionInstant::now();
         self.last_cycle_ended = Some(cycle_end);
 
-        CYCLE_DURATION.with(|x| x.observe_millis(cycle_end.duration_since(cycle_start)));
+        observe!(CYCLE_DURATION.observe_millis(cycle_end.duration_since(cycle_start)));
 
         if self.shutting_down && self.completed.is_empty() {
             // Shutdown is finished if all completed tasks (== all tasks) have been removed from the
@@ -240,6 +319,163 @@ impl AsyncTaskEngine {
         }
     }
 
+    /// Returns the task to poll next, preferring the LIFO slot unless it has been used too many
+    /// times in a row.
+    fn next_task(&mut self) -> Option<*mut Task> {
+        if let Some(task_ptr) = self.lifo_slot.take() {
+            self.lifo_streak += 1;
+            return Some(task_ptr);
+        }
+
+        self.lifo_streak = 0;
+        self.active.pop_front()
+    }
+
+    /// If the task that was just polled woke another task, moves the most recently woken task into
+    /// the LIFO slot, so it is polled next.
+    fn fill_lifo_slot(&mut self, polled_task_ptr: *mut Task, awakened_before_poll: usize) {
+        if self.lifo_streak >= MAX_LIFO_STREAK.with(Cell::get) {
+            return;
+        }
+
+        let mut awakened = self.awakened.lock().expect(POISONED_LOCK);
+
+        // If nothing was woken during the poll, there is nothing to do.
+        if awakened.len() <= awakened_before_poll {
+            return;
+        }
+
+        // A task that wakes itself (e.g. to yield) wants to go to the back of the queue, so we
+        // never put it in the LIFO slot.
+        let Some(&task_ptr) = awakened.back() else {
+            return;
+        };
+
+        if task_ptr == polled_task_ptr {
+            return;
+        }
+
+        // It may have been a spurious wake of a task that is not sleeping, in which case we leave
+        // it for the regular wake processing, which knows how to deal with that.
+        if !self.inactive.remove(&task_ptr) {
+            return;
+        }
+
+        awakened.pop_back();
+        self.lifo_slot = Some(task_ptr);
+
+        observe!(TASK_ACTIVATED_VIA_LIFO_SLOT.observe_unit());
+    }
+
+    /// Reports the tasks that have been waiting for longer than the stall threshold, each once per
+    /// stall. Does nothing if stall detection is disabled.
+    pub fn check_stalled_tasks(&self) {
+        let threshold = STALLED_TASK_THRESHOLD.with(Cell::get);
+
+        if threshold.is_zero() {
+            return;
+        }
+
+        for task_ptr in self.inactive.iter() {
+            // SAFETY: This comes from a pinned slab and we are responsible for dropping tasks,
+            // which we never do until they progress through the lifecycle into the `completed`
+            // list. We only read from the task here.
+            let task = unsafe { &**task_ptr };
+
+            if task.stall_reported.get() {
+                continue;
+            }
+
+            // Inactive tasks have always been polled at least once.
+            let Some(last_polled) = task.last_polled.get() else {
+                continue;
+            };
+
+            let idle = last_polled.elapsed();
+
+            if idle < threshold {
+                continue;
+            }
+
+            task.stall_reported.set(true);
+
+            let inner = task.inner.borrow();
+            let meta = inner.meta();
+            let task_id = meta.id();
+            let task_name = meta.name().map(|x| &**x);
+
+            // If a wake had been sent, the task would no longer be inactive, so if nobody holds a
+            // waker either, nothing can ever wake the task up.
+            if task.wake_signal.is_inert() {
+                observe!(LOST_WAKERS.observe_unit());
+
+                diagnostics::emit(Diagnostic::LostWaker {
+                    task_id,
+                    task_name,
+                    idle,
+                });
+            } else {
+                observe!(STALLED_TASKS.observe_unit());
+
+                diagnostics::emit(Diagnostic::TaskStalled {
+                    task_id,
+                    task_name,
+                    idle,
+                });
+            }
+        }
+    }
+
+    /// Describes all the tasks that have not yet completed, for diagnostic purposes.
+    pub fn dump_tasks(&self) -> Box<[TaskDump]> {
+        let ready = self
+            .lifo_slot
+            .iter()
+            .chain(self.active.iter())
+            .map(|task_ptr| (task_ptr, TaskState::Ready));
+        let waiting = self
+            .inactive
+            .iter()
+            .map(|task_ptr| (task_ptr, TaskState::Waiting));
+
+        ready
+            .chain(waiting)
+            .map(|(task_ptr, state)| {
+                // SAFETY: This comes from a pinned slab and we are responsible for dropping tasks,
+                // which we never do until they progress through the lifecycle into the `completed`
+                // list. We only read from the task here.
+                let task = unsafe { &**task_ptr };
+                let inner = task.inner.borrow();
+                let meta = inner.meta();
+
+                TaskDump::new(
+                    meta.id(),
+                    meta.name().cloned(),
+                    state,
+                    Some(meta.spawned_at().elapsed()),
+                    task.last_polled.get().map(|x| x.elapsed()),
+                )
+                .with_polls(task.polls.get(), task.busy_time.get())
+                .with_memory(
+                    task.frame_size,
+                    meta.memory().map_or(0, |memory| memory.charged()),
+                )
+                .with_suspension(task.suspension.borrow().clone())
+            })
+            .collect()
+    }
+
+    /// Identifies the task being polled to other threads, for diagnostic dumps.
+    pub fn polling_task(&self) -> Arc<PollingTask> {
+        Arc::clone(&self.polling_task)
+    }
+
+    /// The queue that wake signals of our tasks use to report that they have been awakened. Used to
+    /// tell apart wakes of tasks owned by the current thread from wakes of tasks on other threads.
+    pub fn awakened_queue(&self) -> *const Mutex<VecDeque<*mut Task>> {
+        Arc::as_ptr(&self.awakened)
+    }
+
     /// Returns whether there is any work to do in the engine. This is used to determine if the
     /// engine should be polled again immediately or if it should be suspended until new work
     /// arrives.
@@ -273,9 +509,9 @@ impl AsyncTaskEngine {
                 if self.inactive.remove(&task_ptr) {
                     self.active.push_back(task_ptr);
 
-                    TASK_ACTIVATED_VIA_SET.with(Event::observe_unit);
+                    observe!(TASK_ACTIVATED_VIA_SET.observe_unit());
                 } else {
-                    TASK_ACTIVATED_SPURIOUS.with(Event::observe_unit);
+                    observe!(TASK_ACTIVATED_SPURIOUS.observe_unit());
                 }
             }
         }
@@ -290,7 +526,7 @@ impl AsyncTaskEngine {
                 let task = unsafe { Pin::new_unchecked(&**task_ptr) };
 
                 if task.wake_signal.consume_awakened() {
-                    TASK_ACTIVATED_VIA_SIGNAL.with(Event::observe_unit);
+                    observe!(TASK_ACTIVATED_VIA_SIGNAL.observe_unit());
                     self.active.push_back(*task_ptr);
                     false
                 } else {
@@ -309,7 +545,7 @@ impl AsyncTaskEngine {
             let is_inert = task.is_inert();
 
             if is_inert {
-                TASKS_DROPPED.with(Event::observe_unit);
+                observe!(TASKS_DROPPED.observe_unit());
                 self.tasks.remove(task.index);
             }
 
@@ -391,14 +627,27 @@ pub enum CycleResult {
     Shutdown,
 }
 
-#[pin_project]
+#[pin_project(PinnedDrop)]
 pub(super) struct Task {
     // Behind this may be either a local or a remote task - we do not know or care which.
-    inner: RefCell<Pin<Box<dyn ErasedResultAsyncTask>>>,
+    // Released to the task frame pool when the task is dropped, so the allocation can be reused.
+    inner: RefCell<ManuallyDrop<Pin<Box<dyn ErasedResultAsyncTask>>>>,
 
     // Used for dropping the task once we are done with it.
     index: usize,
 
+    // The size of the future the task was spawned with, for memory accounting.
+    frame_size: usize,
+
+    // For diagnostic purposes only.
+    last_polled: Cell<Option<LowPrecisionInstant>>,
+    polls: Cell<u64>,
+    busy_time: Cell<Duration>,
+    suspension: RefCell<Option<Suspension>>,
+
+    // Whether the task has been reported as stalled since it was last polled.
+    stall_reported: Cell<bool>,
+
     #[pin]
     wake_signal: WakeSignal,
 }
@@ -411,13 +660,25 @@ impl Task {
     unsafe fn new(
         index: usize,
         inner: Pin<Box<dyn ErasedResultAsyncTask>>,
+        frame_size: usize,
         awakened_queue: Arc<Mutex<VecDeque<*mut Task>>>,
         probe_embedded_wake_signals: Arc<AtomicBool>,
+        io_waker: IoWaker,
     ) -> Self {
         Self {
-            inner: RefCell::new(inner),
+            inner: RefCell::new(ManuallyDrop::new(inner)),
             index,
-            wake_signal: WakeSignal::new(awakened_queue, probe_embedded_wake_signals),
+            frame_size,
+            last_polled: Cell::new(None),
+            polls: Cell::new(0),
+            busy_time: Cell::new(Duration::ZERO),
+            suspension: RefCell::new(None),
+            stall_reported: Cell::new(false),
+            wake_signal: WakeSignal::new(
+                awakened_queue,
+                probe_embedded_wake_signals,
+                Some(io_waker),
+            ),
         }
     }
 
@@ -438,7 +699,21 @@ impl Task {
 
         // We are only accessing the erased task in poll() which is only called by the current
         // thread and never recursively, so we are not at risk of conflicting borrows.
-        self.inner.borrow_mut().as_mut().poll(&mut context)
+        let mut inner = self.inner.borrow_mut();
+
+        // Anything that happens during the poll (including panics) is attributed to this task.
+        let _current_task = inner.meta().enter();
+
+        // Every poll gets a fresh cooperative scheduling budget.
+        let _budget = coop::enter_task();
+
+        inner.as_mut().poll(&mut context)
+    }
+
+    /// Accounts for a finished poll in the statistics reported via diagnostic dumps.
+    fn record_poll(&self, duration: Duration) {
+        self.polls.set(self.polls.get() + 1);
+        self.busy_time.set(self.busy_time.get() + duration);
     }
 
     fn is_inert(&self) -> bool {
@@ -446,6 +721,16 @@ impl Task {
     }
 }
 
+#[pinned_drop]
+impl PinnedDrop for Task {
+    fn drop(self: Pin<&mut Self>) {
+        let inner = self.project().inner.get_mut();
+
+        // SAFETY: This is the only place where we take the value and we never touch it again.
+        task_frames::release(unsafe { ManuallyDrop::take(inner) });
+    }
+}
+
 impl Debug for Task {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         f.debug_struct("Task")
@@ -454,7 +739,79 @@ impl Debug for Task {
     }
 }
 
+/// Reports the poll that just finished if it took longer than the slow poll threshold. A slow poll
+/// blocks every other task on the worker thread, typically because the task performed blocking
+/// I/O or a long computation without yielding.
+fn check_poll_duration(task: &Task, duration: Duration) {
+    let threshold = SLOW_POLL_THRESHOLD.with(Cell::get);
+
+    if threshold.is_zero() || duration < threshold {
+        return;
+    }
+
+    observe!(SLOW_POLLS.observe_millis(duration));
+
+    let inner = task.inner.borrow();
+    let meta = inner.meta();
+
+    diagnostics::emit(Diagnostic::SlowPoll {
+        task_id: meta.id(),
+        task_name: meta.name().map(|x| &**x),
+        duration,
+        threshold,
+    });
+}
+
+/// Sets the duration above which a single poll of a task on the current thread is reported as
+/// slow. Zero disables the check.
+pub(crate) fn set_slow_poll_threshold(value: Duration) {
+    SLOW_POLL_THRESHOLD.with(|x| x.set(value));
+}
+
+/// Sets how long a task on the current thread may wait before it is reported as stalled. Zero
+/// disables the check.
+pub(crate) fn set_stalled_task_threshold(value: Duration) {
+    STALLED_TASK_THRESHOLD.with(|x| x.set(value));
+}
+
+/// Sets how many tasks in a row may be polled from the LIFO slot on the current thread before the
+/// worker goes back to polling tasks in queue order. Zero disables the LIFO slot.
+pub(crate) fn set_max_lifo_streak(value: usize) {
+    MAX_LIFO_STREAK.with(|x| x.set(value));
+}
+
+/// Default number of tasks in a row that may be polled from the LIFO slot. Once reached, woken
+/// tasks go to the back of the queue as usual.
+pub(crate) const DEFAULT_MAX_LIFO_STREAK: usize = 3;
+
+/// Default duration above which a single poll of a task is reported as slow.
+pub(crate) const DEFAULT_SLOW_POLL_THRESHOLD: Duration = Duration::from_millis(100);
+
 thread_local! {
+    static SLOW_POLL_THRESHOLD: Cell<Duration> = const { Cell::new(DEFAULT_SLOW_POLL_THRESHOLD) };
+
+    static STALLED_TASK_THRESHOLD: Cell<Duration> = const { Cell::new(Duration::ZERO) };
+
+    static MAX_LIFO_STREAK: Cell<usize> = const { Cell::new(DEFAULT_MAX_LIFO_STREAK) };
+
+    static SLOW_POLLS: Event = EventBuilder::new("rt_async_slow_poll_millis")
+        .buckets(GENERAL_MILLISECONDS_BUCKETS)
+        .build();
+
+    static TASK_FRAME_SIZE: Event = EventBuilder::new("rt_async_task_frame_bytes")
+        .buckets(GENERAL_BYTES_BUCKETS)
+        .build();
+
+    static TASK_PEAK_CHARGED_MEMORY: Event = EventBuilder::new("rt_async_task_peak_charged_bytes")
+        .buckets(GENERAL_BYTES_BUCKETS)
+        .build();
+
+    static STALLED_TASKS: Event = EventBuilder::new("rt_async_stalled_tasks")
+        .build();
+
+    static LOST_WAKERS: Event = EventBuilder::new("rt_async_lost_wakers")
+        .build();
+
     static TASKS_CANCELED_ON_SHUTDOWN: Event = EventBuilder::new("rt_async_tasks_canceled_on_shutdown")
         .build();
 
@@ -464,6 +821,9 @@ thread_local! {
     static TASK_ACTIVATED_VIA_SIGNAL: Event = EventBuilder::new("rt_async_task_activated_via_signal")
         .build();
 
+    static TASK_ACTIVATED_VIA_LIFO_SLOT: Event = EventBuilder::new("rt_async_task_activated_via_lifo_slot")
+        .build();
+
     static TASK_ACTIVATED_SPURIOUS: Event = EventBuilder::new("rt_async_task_activated_spurious")
         .build();
 
@@ -1,27 +1,58 @@
 use std::collections::HashMap;
 use std::fmt::{self, Debug, Formatter};
+use std::path::PathBuf;
 use std::rc::Rc;
-use std::sync::atomic::AtomicBool;
+use std::sync::atomic::{AtomicBool, AtomicUsize};
 use std::sync::Arc;
 use std::thread;
+use std::time::Duration;
 
+use core_affinity::CoreId;
 use crossbeam::channel;
 use crossbeam::queue::SegQueue;
 use tracing::{event, Level};
 
+use super::affinity::{self, Affinity};
+use super::dump::PollingTask;
+use super::stealing::StealableQueues;
 use super::sync_agent::{SyncAgent, SyncAgentCommand};
+use super::worker_stats::WorkerCounters;
 use super::{current_sync_agent, ErasedSyncTask};
-use crate::io::{self, IoWaker};
-use crate::metrics::ReportPage;
+use crate::io::{self, HandleLimitPolicy, IoWaker};
+use crate::metrics::{self, ReportPage};
+use crate::net::{self, ConnectionLimit};
+use crate::rt::admission::{self, TaskLimits};
 use crate::rt::async_agent::{AsyncAgent, AsyncAgentCommand};
-use crate::rt::{current_async_agent, current_runtime, CoreClient, RuntimeClient};
+use crate::rt::async_task_engine;
+use crate::rt::diagnostics::{self, Diagnostic, DiagnosticsBackend};
+use crate::rt::flight_recorder::{self, FlightRecorder};
+use crate::rt::in_flight::InFlightTasks;
+use crate::rt::maintenance::{self, MaintenanceCallback, MaintenanceTicker};
+use crate::rt::watchdog::{self, WatchdogPolicy, WatchedWorker};
+use crate::rt::{
+    coop, current_async_agent, current_runtime, panic_policy, CoreClient, EmbeddedRuntime,
+    IdleStrategy, PanicPolicy, Profile, RuntimeClient,
+};
+use crate::sync;
 
 /// The thing with synchronous worker threads is that they often get blocked and spend time doing
 /// essentially nothing due to offloading blocking I/O onto these threads. Therefore, we spawn many
 /// of them to ensure that we can keep processing synchronous work when a large batch comes in.
 /// In the future we might replace this with a more dynamically sizing thread pool but for now the
 /// fixed size might be acceptable.
-const SYNC_WORKERS_PER_PROCESSOR: usize = 2;
+const DEFAULT_SYNC_WORKERS_PER_PROCESSOR: usize = 2;
+
+// Tuning of the runtime profiles. See `Profile` for the reasoning.
+const LOW_LATENCY_COOP_BUDGET: u32 = 32;
+const LOW_LATENCY_SPIN_CYCLES: u32 = 1000;
+const LOW_LATENCY_YIELD_CYCLES: u32 = 100;
+const LOW_LATENCY_IO_COMPLETION_BATCH_SIZE: usize = 64;
+const THROUGHPUT_COOP_BUDGET: u32 = 512;
+const THROUGHPUT_MAX_LIFO_STREAK: usize = 16;
+const THROUGHPUT_IO_COMPLETION_BATCHES_PER_CYCLE: usize = 4;
+
+const DEFAULT_ASYNC_THREAD_NAME: &str = "async-{index}";
+const DEFAULT_SYNC_THREAD_NAME: &str = "sync-{processor}-{index}";
 
 struct ThreadStartResult<AgentReady, R> {
     join_handle: std::thread::JoinHandle<()>,
@@ -35,6 +66,38 @@ pub struct RuntimeBuilder {
     ad_hoc_entrypoint: bool,
     metrics_tx: Option<channel::Sender<ReportPage>>,
     max_processors: Option<usize>,
+    affinity: Affinity,
+    crash_report_path: Option<PathBuf>,
+    #[cfg(feature = "metrics-persistence")]
+    metrics_persistence: Option<(PathBuf, Duration)>,
+    flight_recorder_capacity: usize,
+    flight_record_path: Option<PathBuf>,
+    coop_budget: u32,
+    stealing: bool,
+    panic_policy: PanicPolicy,
+    diagnostics: DiagnosticsBackend,
+    slow_poll_threshold: Duration,
+    detect_local_deadlocks: bool,
+    stalled_task_threshold: Duration,
+    watchdog: Option<(Duration, WatchdogPolicy)>,
+    max_tasks_per_worker: Option<usize>,
+    max_tasks: Option<usize>,
+    async_thread_name: Arc<str>,
+    sync_thread_name: Arc<str>,
+    stack_size: Option<usize>,
+    idle_strategy: IdleStrategy,
+    maintenance_interval: Duration,
+    maintenance_callback: Option<MaintenanceCallback>,
+    max_lifo_streak: usize,
+    io_buffers_per_worker: usize,
+    register_io_buffers: bool,
+    sync_workers_per_processor: usize,
+    io_completion_batch_size: usize,
+    io_completion_batches_per_cycle: usize,
+    connection_limit: Option<ConnectionLimit>,
+    max_open_handles: Option<usize>,
+    handle_limit_policy: HandleLimitPolicy,
+    io_blocking_fallback: bool,
 }
 
 impl RuntimeBuilder {
@@ -44,6 +107,38 @@ impl RuntimeBuilder {
             ad_hoc_entrypoint: false,
             metrics_tx: None,
             max_processors: None,
+            affinity: Affinity::default(),
+            crash_report_path: None,
+            #[cfg(feature = "metrics-persistence")]
+            metrics_persistence: None,
+            flight_recorder_capacity: 0,
+            flight_record_path: None,
+            coop_budget: coop::DEFAULT_BUDGET_SIZE,
+            stealing: false,
+            panic_policy: PanicPolicy::default(),
+            diagnostics: DiagnosticsBackend::default(),
+            slow_poll_threshold: async_task_engine::DEFAULT_SLOW_POLL_THRESHOLD,
+            detect_local_deadlocks: false,
+            stalled_task_threshold: Duration::ZERO,
+            watchdog: None,
+            max_tasks_per_worker: None,
+            max_tasks: None,
+            async_thread_name: DEFAULT_ASYNC_THREAD_NAME.into(),
+            sync_thread_name: DEFAULT_SYNC_THREAD_NAME.into(),
+            stack_size: None,
+            idle_strategy: IdleStrategy::default(),
+            maintenance_interval: maintenance::DEFAULT_MAINTENANCE_INTERVAL,
+            maintenance_callback: None,
+            max_lifo_streak: async_task_engine::DEFAULT_MAX_LIFO_STREAK,
+            io_buffers_per_worker: 0,
+            register_io_buffers: false,
+            sync_workers_per_processor: DEFAULT_SYNC_WORKERS_PER_PROCESSOR,
+            io_completion_batch_size: io::IO_DEQUEUE_BATCH_SIZE,
+            io_completion_batches_per_cycle: io::DEFAULT_COMPLETION_BATCHES_PER_CYCLE,
+            connection_limit: None,
+            max_open_handles: None,
+            handle_limit_policy: HandleLimitPolicy::default(),
+            io_blocking_fallback: true,
         }
     }
 
@@ -86,36 +181,452 @@ impl RuntimeBuilder {
         self
     }
 
+    /// Selects the processors that the runtime places its worker threads on and whether the
+    /// threads are pinned to them. By default, there is one pinned async worker per logical
+    /// processor. See [`Affinity`] for the options.
+    ///
+    /// If `max_processors()` is also set, it limits the number of processors selected here.
+    pub fn affinity(mut self, value: Affinity) -> Self {
+        self.affinity = value;
+        self
+    }
+
+    /// Sets the path of a file to which a final metrics report is written if a runtime thread
+    /// panics. See `metrics::set_crash_report_path()` for details.
+    pub fn crash_report_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.crash_report_path = Some(path.into());
+        self
+    }
+
+    /// Sets the path of a file to which the latest metrics report of the process is written as
+    /// JSON every interval, when worker threads shut down and when a thread panics. See
+    /// `metrics::set_metrics_persistence()` for details.
+    #[cfg(feature = "metrics-persistence")]
+    pub fn metrics_persistence(mut self, path: impl Into<PathBuf>, interval: Duration) -> Self {
+        self.metrics_persistence = Some((path.into(), interval));
+        self
+    }
+
+    /// Enables the flight recorder, which keeps the specified number of most recent runtime events
+    /// (task spawns and polls, I/O submissions and completions, timer fires) of every async worker
+    /// in a ring buffer. Take a record via [`RuntimeClient::dump_flight_record()`] or have it
+    /// written on panic or Ctrl+Break via [`flight_record_path()`][Self::flight_record_path].
+    ///
+    /// Disabled (zero) by default, as recording adds a small cost to every event.
+    pub fn flight_recorder(mut self, capacity: usize) -> Self {
+        self.flight_recorder_capacity = capacity;
+        self
+    }
+
+    /// Sets the path of a file to which the flight records of all async workers are appended if a
+    /// thread panics or if Ctrl+Break is pressed in the console of the process, for post-mortem
+    /// debugging. Has no effect unless the flight recorder is enabled via
+    /// [`flight_recorder()`][Self::flight_recorder].
+    ///
+    /// Ctrl+Break is only observed - it still reaches [`ctrl_c()`][crate::signal::ctrl_c] or, if
+    /// nothing else handles it, terminates the process.
+    pub fn flight_record_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.flight_record_path = Some(path.into());
+        self
+    }
+
+    /// Sets how many ready operations (e.g. channel receives or completed I/O operations) a task
+    /// may consume in a single poll before the runtime forces it to yield to other tasks on the
+    /// same worker thread. This prevents one busy task from starving the others.
+    ///
+    /// Zero disables the budget. Individual futures can opt out via [`unconstrained()`][1].
+    ///
+    /// [1]: crate::rt::unconstrained
+    pub fn coop_budget(mut self, value: u32) -> Self {
+        self.coop_budget = value;
+        self
+    }
+
+    /// Enables work stealing between async worker threads. When enabled, an idle worker may take
+    /// tasks spawned via `spawn_on_any()` that are still waiting to start on a busy sibling.
+    ///
+    /// Tasks that have started executing never move between threads, so `!Send` local state is
+    /// never affected. Tasks spawned via `spawn()` (local tasks) or on a specific worker are never
+    /// stolen.
+    ///
+    /// Disabled by default - each task runs on the worker it was assigned to.
+    pub fn stealing(mut self, value: bool) -> Self {
+        self.stealing = value;
+        self
+    }
+
+    /// Sets what happens when an async task panics. By default, the process is aborted. See
+    /// [`PanicPolicy`] for the options.
+    pub fn panic_policy(mut self, value: PanicPolicy) -> Self {
+        self.panic_policy = value;
+        self
+    }
+
+    /// Sets where the runtime delivers its own diagnostics - warnings and errors about problems it
+    /// detects on its worker threads, such as slow polls, failures to cancel I/O operations and
+    /// deadlocks between tasks.
+    ///
+    /// The default is to emit them as `tracing` events. Applications that use the `log` crate can
+    /// route them there instead (requires the `log` feature), and applications with their own
+    /// monitoring can receive them via a callback.
+    pub fn diagnostics(mut self, value: DiagnosticsBackend) -> Self {
+        self.diagnostics = value;
+        self
+    }
+
+    /// Sets the duration above which a single poll of an async task is reported as slow, via a
+    /// [diagnostic][Self::diagnostics] (with the task ID and name) and the
+    /// `rt_async_slow_poll_millis` metric.
+    ///
+    /// A slow poll blocks every other task on the same worker thread. The usual culprit is
+    /// synchronous I/O or a long computation performed directly in an async task instead of being
+    /// offloaded via `spawn_sync()`.
+    ///
+    /// The default is 100 milliseconds. Zero disables the check. The poll duration is measured with
+    /// a low precision clock, so thresholds below a few tens of milliseconds are not meaningful.
+    pub fn slow_poll_threshold(mut self, value: Duration) -> Self {
+        self.slow_poll_threshold = value;
+        self
+    }
+
+    /// Enables the detection of deadlocks between tasks that wait for each other's thread-local
+    /// locks ([`LocalMutex`][crate::sync::LocalMutex] and
+    /// [`LocalRwLock`][crate::sync::LocalRwLock]), e.g. a task holding lock X waiting for lock Y
+    /// held by a task that waits for lock X. A detected deadlock is reported via an error
+    /// [diagnostic][Self::diagnostics] that names the tasks involved.
+    ///
+    /// Only tasks on the same worker thread can share thread-local locks, so the worker thread
+    /// detects the deadlock as soon as the last task in the cycle starts waiting. Keeping track of
+    /// the holders of every lock has a cost, so this is disabled by default and meant for
+    /// diagnosing hangs.
+    pub fn detect_local_deadlocks(mut self, value: bool) -> Self {
+        self.detect_local_deadlocks = value;
+        self
+    }
+
+    /// Enables the detection of tasks that have been waiting for longer than the specified
+    /// duration, to find leaked tasks and lost wakers that otherwise manifest as mysterious hangs.
+    /// Each stall is reported once via a [diagnostic][Self::diagnostics] that names the task:
+    ///
+    /// * [`LostWaker`][crate::rt::Diagnostic::LostWaker] (an error) if nothing holds the waker
+    ///   of the task, so it can never be woken up.
+    /// * [`TaskStalled`][crate::rt::Diagnostic::TaskStalled] otherwise.
+    ///
+    /// Tasks that legitimately wait for a long time (e.g. for incoming connections) are also
+    /// reported, so this is meant for debugging and testing. Workers check for stalls at each
+    /// maintenance tick. Zero (the default) disables the detection.
+    pub fn detect_stalled_tasks(mut self, threshold: Duration) -> Self {
+        self.stalled_task_threshold = threshold;
+        self
+    }
+
+    /// Starts a watchdog thread that detects async worker threads that have been busy without
+    /// completing a cycle of their loop for longer than the threshold - typically because a task
+    /// made a blocking call - and responds as the policy says, naming the task being polled.
+    ///
+    /// Unlike the [slow poll threshold][Self::slow_poll_threshold], which can only report a slow
+    /// poll once it has finished, the watchdog reports a worker while it is still blocked, so it
+    /// also catches polls that never finish. Each time a worker gets blocked is reported once.
+    ///
+    /// By default, there is no watchdog. Thresholds below a few tens of milliseconds are not
+    /// meaningful, as the watchdog uses a low precision clock.
+    pub fn watchdog(mut self, threshold: Duration, policy: WatchdogPolicy) -> Self {
+        self.watchdog = Some((threshold, policy));
+        self
+    }
+
+    /// Sets the maximum number of live tasks on each async worker thread, beyond which
+    /// [`try_spawn()`][crate::rt::try_spawn] refuses to spawn more. By default, there is no limit.
+    ///
+    /// Tasks spawned via `spawn()` and friends are never refused but do count towards the limit.
+    pub fn max_tasks_per_worker(mut self, value: usize) -> Self {
+        self.max_tasks_per_worker = Some(value);
+        self
+    }
+
+    /// Sets the maximum number of live tasks across all async worker threads of the runtime,
+    /// beyond which [`try_spawn()`][crate::rt::try_spawn] refuses to spawn more. By default, there
+    /// is no limit.
+    ///
+    /// Tasks spawned via `spawn()` and friends are never refused but do count towards the limit.
+    /// Enforcing a runtime-wide limit requires the worker threads to share a counter, so prefer
+    /// the per-worker limit if it is sufficient.
+    pub fn max_tasks(mut self, value: usize) -> Self {
+        self.max_tasks = Some(value);
+        self
+    }
+
+    /// Sets the name template of the async worker threads. The `{index}` placeholder is replaced
+    /// with the worker index and `{processor}` with the ID of the processor the worker is assigned
+    /// to. The default is `async-{index}`.
+    ///
+    /// Meaningful thread names make profilers, debuggers and crash dumps much easier to work with,
+    /// especially in processes that host multiple runtimes or thread pools.
+    pub fn async_thread_name(mut self, template: impl Into<Arc<str>>) -> Self {
+        self.async_thread_name = template.into();
+        self
+    }
+
+    /// Sets the name template of the sync worker threads, which execute blocking work offloaded
+    /// via `spawn_sync()`. The `{index}` placeholder is replaced with the index of the worker among
+    /// the sync workers of the same processor and `{processor}` with the ID of the processor. The
+    /// default is `sync-{processor}-{index}`.
+    pub fn sync_thread_name(mut self, template: impl Into<Arc<str>>) -> Self {
+        self.sync_thread_name = template.into();
+        self
+    }
+
+    /// Sets the stack size in bytes of all the worker threads (async and sync) of the runtime. By
+    /// default, the Rust standard library default is used.
+    pub fn stack_size(mut self, value: usize) -> Self {
+        self.stack_size = Some(value);
+        self
+    }
+
+    /// Sets what async worker threads do when they run out of work: sleep right away, keep polling
+    /// for a while before sleeping or never sleep. By default, they sleep right away. See
+    /// [`IdleStrategy`] for the options.
+    pub fn idle_strategy(mut self, value: IdleStrategy) -> Self {
+        self.idle_strategy = value;
+        self
+    }
+
+    /// Sets how many tasks in a row a worker may poll from its LIFO slot before going back to
+    /// polling tasks in queue order.
+    ///
+    /// When a task wakes another task on the same worker (e.g. by sending it a message), the woken
+    /// task is placed in the LIFO slot and polled next, while the data it needs is still in the
+    /// processor cache. Two tasks that keep waking each other could monopolize the worker this
+    /// way, so the streak is limited. The default is 3. Zero disables the LIFO slot, so woken tasks
+    /// always go to the back of the queue.
+    pub fn max_lifo_streak(mut self, value: usize) -> Self {
+        self.max_lifo_streak = value;
+        self
+    }
+
+    /// Applies a preset that tunes several options together for a class of workloads. See
+    /// [`Profile`] for the options. Options set after the profile override the values set by it.
+    pub fn profile(mut self, value: Profile) -> Self {
+        match value {
+            Profile::Balanced => {
+                self.coop_budget = coop::DEFAULT_BUDGET_SIZE;
+                self.idle_strategy = IdleStrategy::default();
+                self.stealing = false;
+                self.max_lifo_streak = async_task_engine::DEFAULT_MAX_LIFO_STREAK;
+                self.io_completion_batch_size = io::IO_DEQUEUE_BATCH_SIZE;
+                self.io_completion_batches_per_cycle = io::DEFAULT_COMPLETION_BATCHES_PER_CYCLE;
+            }
+            Profile::LowLatency => {
+                self.coop_budget = LOW_LATENCY_COOP_BUDGET;
+                self.idle_strategy = IdleStrategy::SpinThenPark {
+                    spin_cycles: LOW_LATENCY_SPIN_CYCLES,
+                    yield_cycles: LOW_LATENCY_YIELD_CYCLES,
+                };
+                self.stealing = true;
+                self.max_lifo_streak = 1;
+                self.io_completion_batch_size = LOW_LATENCY_IO_COMPLETION_BATCH_SIZE;
+                self.io_completion_batches_per_cycle = io::DEFAULT_COMPLETION_BATCHES_PER_CYCLE;
+            }
+            Profile::Throughput => {
+                self.coop_budget = THROUGHPUT_COOP_BUDGET;
+                self.idle_strategy = IdleStrategy::Park;
+                self.stealing = true;
+                self.max_lifo_streak = THROUGHPUT_MAX_LIFO_STREAK;
+                self.io_completion_batch_size = io::IO_DEQUEUE_BATCH_SIZE;
+                self.io_completion_batches_per_cycle = THROUGHPUT_IO_COMPLETION_BATCHES_PER_CYCLE;
+            }
+        }
+
+        self
+    }
+
+    /// Sets the interval of the maintenance tick, at which every async worker thread performs
+    /// periodic housekeeping (e.g. trimming per-thread caches) and invokes the callback registered
+    /// via `on_maintenance_tick()`. The default is 1 second.
+    pub fn maintenance_interval(mut self, value: Duration) -> Self {
+        self.maintenance_interval = value;
+        self
+    }
+
+    /// Registers a function to call on every async worker thread at each maintenance tick. This is
+    /// a low-cost place for periodic per-worker work (e.g. flushing per-worker statistics) that
+    /// does not require spawning a recurring task on every worker.
+    ///
+    /// The function is called between task polls, including when the worker is busy, so it must
+    /// be quick. The tick is not precise - it may be delayed by slow polls or by the worker
+    /// sleeping while idle.
+    pub fn on_maintenance_tick<F>(mut self, f: F) -> Self
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        self.maintenance_callback = Some(Arc::new(f));
+        self
+    }
+
+    /// Sets the number of I/O buffers to reserve in the buffer pool of every async worker thread
+    /// when the worker starts, so the pool does not need to grow while the worker serves the first
+    /// burst of I/O operations. By default, no buffers are reserved and pools grow on demand. See
+    /// [`BufferPool`][crate::io::BufferPool].
+    pub fn io_buffers_per_worker(mut self, value: usize) -> Self {
+        self.io_buffers_per_worker = value;
+        self
+    }
+
+    /// Sets whether the I/O buffers reserved via `io_buffers_per_worker()` are registered with the
+    /// operating system by locking them into physical memory. By default, they are not.
+    ///
+    /// Failing to register the buffers is not fatal - the worker logs a warning and starts with
+    /// regular buffers.
+    pub fn register_io_buffers(mut self, value: bool) -> Self {
+        self.register_io_buffers = value;
+        self
+    }
+
+    /// Sets the number of sync worker threads per processor, which execute blocking work offloaded
+    /// via `spawn_sync()`. Sync workers often spend their time blocked (e.g. on file I/O), so there
+    /// are several of them per processor. The default is 2.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is zero.
+    pub fn sync_workers_per_processor(mut self, value: usize) -> Self {
+        assert!(
+            value > 0,
+            "there must be at least one sync worker per processor"
+        );
+
+        self.sync_workers_per_processor = value;
+        self
+    }
+
+    /// Sets the maximum number of I/O completions that an async worker thread dequeues from the
+    /// operating system at once, between 1 and [`IO_DEQUEUE_BATCH_SIZE`][io::IO_DEQUEUE_BATCH_SIZE]
+    /// (the default).
+    ///
+    /// Smaller batches let the worker start executing the tasks woken by the first completions
+    /// sooner, at the cost of more system calls when many operations complete at the same time.
+    pub fn io_completion_batch_size(mut self, value: usize) -> Self {
+        self.io_completion_batch_size = value;
+        self
+    }
+
+    /// Sets how many batches of I/O completions an async worker thread may dequeue in one cycle
+    /// of its loop if the batches keep coming back full. The default is 1, which means that the
+    /// worker executes the tasks woken by a full batch before dequeuing more completions.
+    ///
+    /// Higher values drain a backlog of completions with fewer cycles, at the cost of delaying
+    /// the tasks woken by the first batch. There is no corresponding setting for submissions,
+    /// as every overlapped I/O call is handed to the operating system immediately by its own
+    /// system call.
+    pub fn io_completion_batches_per_cycle(mut self, value: usize) -> Self {
+        self.io_completion_batches_per_cycle = value;
+        self
+    }
+
+    /// Sets the maximum number of connections accepted by the listeners of the runtime that may be
+    /// open at the same time. When the limit is reached, listeners stop accepting connections until
+    /// some of the open connections are closed. By default, there is no limit.
+    ///
+    /// This applies to connections accepted via [`TcpListener`][crate::net::TcpListener], in
+    /// addition to any limit of the individual listener. The current and peak number of open
+    /// connections is available via [`net::runtime_connection_limit()`].
+    pub fn max_connections(mut self, value: usize) -> Self {
+        self.connection_limit = Some(ConnectionLimit::new(value));
+        self
+    }
+
+    /// Sets a soft limit on the number of handles (files, sockets, pipes and other operating
+    /// system objects) owned by Folo in this process. When the limit is reached, accepting
+    /// connections and opening files waits or fails (see
+    /// [`handle_limit_policy()`][Self::handle_limit_policy]) until some handles are closed,
+    /// instead of running into the hard limit of the operating system, which tends to fail
+    /// arbitrary operations all over the process. By default, there is no limit.
+    ///
+    /// The limit is approximate - operations that start at the same time may open a few handles
+    /// over it. The current and peak number of open handles is available via
+    /// [`io::open_handles()`] and [`io::peak_open_handles()`].
+    pub fn max_open_handles(mut self, value: usize) -> Self {
+        self.max_open_handles = Some(value);
+        self
+    }
+
+    /// Sets what happens when an operation that opens a handle starts while the limit set via
+    /// [`max_open_handles()`][Self::max_open_handles] has been reached. By default, the operation
+    /// waits for room.
+    pub fn handle_limit_policy(mut self, value: HandleLimitPolicy) -> Self {
+        self.handle_limit_policy = value;
+        self
+    }
+
+    /// Sets whether I/O primitives that cannot be associated with the I/O completion port of a
+    /// worker thread (e.g. because they were opened for synchronous I/O or are already associated
+    /// with a completion port owned by other code) fall back to blocking I/O performed on the
+    /// synchronous worker threads. If disabled, binding such an I/O primitive fails.
+    ///
+    /// Enabled by default. The first fallback is reported via a
+    /// [diagnostic][crate::rt::Diagnostic::IoBlockingFallback] and whether any I/O primitive has
+    /// fallen back is available via [`RuntimeClient::io_backend()`].
+    ///
+    /// [`RuntimeClient::io_backend()`]: crate::rt::RuntimeClient::io_backend
+    pub fn io_blocking_fallback(mut self, enabled: bool) -> Self {
+        self.io_blocking_fallback = enabled;
+        self
+    }
+
+    /// Creates a builder for a worker thread with the configured name and stack size.
+    fn thread_builder(
+        &self,
+        name_template: &str,
+        processor_id: CoreId,
+        index: usize,
+    ) -> thread::Builder {
+        let builder = thread::Builder::new().name(thread_name(name_template, processor_id, index));
+
+        match self.stack_size {
+            Some(stack_size) => builder.stack_size(stack_size),
+            None => builder,
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)] // Ssssshhhhh, sleep little Clippy!
     fn start_async_agent(
         &self,
-        processor_id: core_affinity::CoreId,
+        processor_id: CoreId,
         io_shared: Arc<io::DriverShared>,
         worker_index: usize,
+        stealable_queues: Option<StealableQueues>,
+        task_limits: TaskLimits,
+        io_fallback: Option<Arc<AtomicBool>>,
+        in_flight_tasks: InFlightTasks,
     ) -> std::io::Result<ThreadStartResult<AsyncAgentReady, channel::Sender<AsyncAgentCommand>>>
     {
-        let worker_init = Arc::clone(&self.worker_init);
-        let metrics_tx = self.metrics_tx.clone();
+        let pin = self.affinity.is_pinned();
+        let diagnostics = self.diagnostics.clone();
         let (start_tx, start_rx) = oneshot::channel::<AgentStartArguments>();
         let (ready_tx, ready_rx) = oneshot::channel::<AsyncAgentReady>();
-        let (command_tx, command_rx) = channel::unbounded::<AsyncAgentCommand>();
+        let (create_agent, command_tx) = self.async_agent_factory(
+            processor_id,
+            io_shared,
+            stealable_queues,
+            task_limits,
+            io_fallback,
+            in_flight_tasks,
+        );
 
-        let join_handle = thread::Builder::new()
-            .name(format!("async-{}", worker_index))
+        let join_handle = self
+            .thread_builder(&self.async_thread_name, processor_id, worker_index)
             .spawn(move || {
-                worker_init();
+                // We pin the thread before anything else, so all the memory the worker allocates
+                // for itself (e.g. buffer pools) is first touched on the processor's NUMA node.
+                if pin {
+                    pin_current_thread(processor_id, &diagnostics);
+                }
 
-                let agent = Rc::new(AsyncAgent::new(
-                    command_rx,
-                    metrics_tx,
-                    io_shared,
-                    processor_id,
-                ));
+                let agent = create_agent();
 
                 // Signal that we are ready to start.
                 ready_tx
-                    .send(AsyncAgentReady {
-                        io_waker: agent.with_io(|io| io.waker()),
-                    })
+                    .send(AsyncAgentReady::new(&agent))
                     .expect("runtime startup process failed in infallible code");
 
                 // We first wait for the startup signal, which indicates that all agents have been
@@ -124,7 +635,6 @@ impl RuntimeBuilder {
                     .recv()
                     .expect("runtime startup process failed in infallible code");
 
-                core_affinity::set_for_current(processor_id);
                 current_async_agent::set(Rc::clone(&agent));
                 current_runtime::set(start.runtime_client);
 
@@ -139,24 +649,110 @@ impl RuntimeBuilder {
         })
     }
 
+    /// Prepares the creation of an async agent, returning a function that creates the agent on
+    /// the thread that calls it (after applying the thread-local settings of the worker) and the
+    /// sender for commands to the agent.
+    fn async_agent_factory(
+        &self,
+        processor_id: CoreId,
+        io_shared: Arc<io::DriverShared>,
+        stealable_queues: Option<StealableQueues>,
+        task_limits: TaskLimits,
+        io_fallback: Option<Arc<AtomicBool>>,
+        in_flight_tasks: InFlightTasks,
+    ) -> (
+        impl FnOnce() -> Rc<AsyncAgent> + Send + 'static,
+        channel::Sender<AsyncAgentCommand>,
+    ) {
+        let worker_init = Arc::clone(&self.worker_init);
+        let metrics_tx = self.metrics_tx.clone();
+        let coop_budget = self.coop_budget;
+        let panic_policy = self.panic_policy;
+        let diagnostics = self.diagnostics.clone();
+        let slow_poll_threshold = self.slow_poll_threshold;
+        let flight_recorder_capacity = self.flight_recorder_capacity;
+        let detect_local_deadlocks = self.detect_local_deadlocks;
+        let stalled_task_threshold = self.stalled_task_threshold;
+        let idle_strategy = self.idle_strategy;
+        let max_lifo_streak = self.max_lifo_streak;
+        let maintenance_interval = self.maintenance_interval;
+        let maintenance_callback = self.maintenance_callback.clone();
+        let io_buffers_per_worker = self.io_buffers_per_worker;
+        let register_io_buffers = self.register_io_buffers;
+        let io_completion_batch_size = self.io_completion_batch_size;
+        let io_completion_batches_per_cycle = self.io_completion_batches_per_cycle;
+        let connection_limit = self.connection_limit.clone();
+        let max_open_handles = self.max_open_handles;
+        let handle_limit_policy = self.handle_limit_policy;
+        let (command_tx, command_rx) = channel::unbounded::<AsyncAgentCommand>();
+
+        let create_agent = move || {
+            worker_init();
+
+            coop::set_budget_size(coop_budget);
+            panic_policy::set_panic_policy(panic_policy);
+            diagnostics::set_backend(diagnostics);
+            async_task_engine::set_slow_poll_threshold(slow_poll_threshold);
+            flight_recorder::set_current(
+                (flight_recorder_capacity > 0)
+                    .then(|| FlightRecorder::new(processor_id.id, flight_recorder_capacity)),
+            );
+            sync::deadlock::set_enabled(detect_local_deadlocks);
+            async_task_engine::set_stalled_task_threshold(stalled_task_threshold);
+            async_task_engine::set_max_lifo_streak(max_lifo_streak);
+            admission::set_task_limits(task_limits);
+            net::set_runtime_connection_limit(connection_limit);
+            io::set_handle_limit(max_open_handles, handle_limit_policy);
+            io::set_blocking_fallback(io_fallback);
+            io::set_completion_batching(io_completion_batch_size, io_completion_batches_per_cycle);
+
+            reserve_io_buffers(io_buffers_per_worker, register_io_buffers);
+
+            Rc::new(AsyncAgent::new(
+                command_rx,
+                metrics_tx,
+                io_shared,
+                processor_id,
+                stealable_queues,
+                idle_strategy,
+                MaintenanceTicker::new(maintenance_interval, maintenance_callback),
+                in_flight_tasks,
+            ))
+        };
+
+        (create_agent, command_tx)
+    }
+
     fn start_sync_agent(
         &self,
-        processor_id: core_affinity::CoreId,
+        processor_id: CoreId,
         worker_index: usize,
         task_queue: Arc<SegQueue<ErasedSyncTask>>,
         priority_task_queue: Arc<SegQueue<ErasedSyncTask>>,
     ) -> std::io::Result<ThreadStartResult<SyncAgentReady, channel::Sender<SyncAgentCommand>>> {
         let worker_init = Arc::clone(&self.worker_init);
         let metrics_tx = self.metrics_tx.clone();
+        let pin = self.affinity.is_pinned();
+        let panic_policy = self.panic_policy;
+        let diagnostics = self.diagnostics.clone();
         let (start_tx, start_rx) = oneshot::channel::<AgentStartArguments>();
         let (ready_tx, ready_rx) = oneshot::channel::<SyncAgentReady>();
         let (command_tx, command_rx) = channel::unbounded::<SyncAgentCommand>();
 
-        let join_handle = thread::Builder::new()
-            .name(format!("sync-{}-{}", processor_id.id, worker_index))
+        let join_handle = self
+            .thread_builder(&self.sync_thread_name, processor_id, worker_index)
             .spawn(move || {
+                // We pin the thread before anything else, so all the memory the worker allocates
+                // for itself is first touched on the processor's NUMA node.
+                if pin {
+                    pin_current_thread(processor_id, &diagnostics);
+                }
+
                 (worker_init)();
 
+                panic_policy::set_panic_policy(panic_policy);
+                diagnostics::set_backend(diagnostics);
+
                 let agent = Rc::new(SyncAgent::new(
                     command_rx,
                     metrics_tx,
@@ -175,8 +771,6 @@ impl RuntimeBuilder {
                     .recv()
                     .expect("runtime startup process failed in infallible code");
 
-                core_affinity::set_for_current(processor_id);
-
                 current_sync_agent::set(Rc::clone(&agent));
                 current_runtime::set(start.runtime_client);
 
@@ -199,8 +793,55 @@ impl RuntimeBuilder {
             }
         }
 
-        let mut processor_ids =
-            core_affinity::get_core_ids().expect("must always be able to identify processor IDs");
+        let (client, _) = self.build_core(false)?;
+        Ok(client)
+    }
+
+    /// Builds a runtime whose first async worker is the current thread instead of a thread owned
+    /// by the runtime. This allows the runtime to be embedded into an event loop that the host
+    /// application already runs on the current thread, such as a GUI message pump. The host drives
+    /// the worker by calling [`EmbeddedRuntime::turn()`] regularly.
+    ///
+    /// The other workers are started as usual. The current thread is initialized like the other
+    /// worker threads (including the `worker_init` function) but it is never pinned to a processor.
+    /// A thread can host at most one embedded runtime in its lifetime.
+    ///
+    /// The ad-hoc entrypoint option does not apply to embedded runtimes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the current thread is already owned by a Folo runtime.
+    pub fn build_embedded(self) -> io::Result<EmbeddedRuntime> {
+        let (client, agent) = self.build_core(true)?;
+
+        Ok(EmbeddedRuntime::new(
+            client,
+            agent.expect("the first worker is always embedded when requested"),
+        ))
+    }
+
+    fn build_core(self, embedded: bool) -> io::Result<(RuntimeClient, Option<Rc<AsyncAgent>>)> {
+        if embedded {
+            assert!(
+                !current_runtime::is_some() && !current_async_agent::is_some(),
+                "thread is already registered to a Folo runtime"
+            );
+        }
+
+        if let Some(path) = &self.crash_report_path {
+            metrics::set_crash_report_path(path.clone());
+        }
+
+        #[cfg(feature = "metrics-persistence")]
+        if let Some((path, interval)) = &self.metrics_persistence {
+            metrics::set_metrics_persistence(path.clone(), *interval);
+        }
+
+        if let Some(path) = &self.flight_record_path {
+            flight_recorder::set_dump_path(path.clone());
+        }
+
+        let mut processor_ids = self.affinity.select_processors()?;
 
         if let Some(max_processors) = self.max_processors {
             processor_ids.truncate(max_processors);
@@ -212,7 +853,7 @@ impl RuntimeBuilder {
         let processor_count = processor_ids.len();
 
         let async_worker_count = processor_count;
-        let sync_worker_count = SYNC_WORKERS_PER_PROCESSOR * processor_count;
+        let sync_worker_count = self.sync_workers_per_processor * processor_count;
 
         event!(Level::INFO, processor_count);
 
@@ -226,20 +867,90 @@ impl RuntimeBuilder {
 
         // # Async workers & Sync workers
 
+        // Initially all the async workers are active. The count may be lowered later via the
+        // runtime client, to retire some workers.
+        let active_worker_count = Arc::new(AtomicUsize::new(async_worker_count));
+
+        // If work stealing is enabled, each async worker gets a queue for tasks that any worker
+        // may execute, and can see the queues of all the other workers.
+        let stealable_queues = self.stealing.then(|| {
+            StealableQueues::new_set(async_worker_count, Arc::clone(&active_worker_count))
+        });
+
+        // The runtime-wide task limit is enforced via a counter shared by all the async workers.
+        let task_limits = TaskLimits {
+            per_worker: self.max_tasks_per_worker,
+            global: self
+                .max_tasks
+                .map(|limit| (limit, Arc::new(AtomicUsize::new(0)))),
+        };
+
+        // Every task spawned on the runtime is counted here until it completes, so a graceful
+        // shutdown can tell when all in-flight work has finished.
+        let in_flight_tasks = InFlightTasks::new();
+
+        // Set once any I/O primitive of any worker falls back to blocking I/O, if enabled.
+        let io_fallback_used = Arc::new(AtomicBool::new(false));
+        let io_fallback = self
+            .io_blocking_fallback
+            .then(|| Arc::clone(&io_fallback_used));
+
+        let mut watched_workers = Vec::with_capacity(async_worker_count);
+
         let mut async_start_txs = Vec::with_capacity(async_worker_count);
         let mut sync_start_txs = Vec::with_capacity(sync_worker_count);
 
+        let mut embedded_agent = None;
+
         for worker_index in 0..async_worker_count {
             let processor_id = processor_ids[worker_index];
-            let ThreadStartResult {
-                join_handle: async_join_handle,
-                start_tx: async_start_tx,
-                ready_rx: async_ready_rx,
-                result: async_command_tx,
-            } = self.start_async_agent(processor_id, Arc::clone(&io_shared), worker_index)?;
+            let worker_stealable_queues = stealable_queues
+                .as_ref()
+                .map(|queues| queues[worker_index].clone());
 
-            async_start_txs.push(async_start_tx);
-            join_handles.push(async_join_handle);
+            let (async_command_tx, async_ready_rx) = if embedded && worker_index == 0 {
+                // The first async worker is the current thread, which the host application uses
+                // to drive the worker loop via `EmbeddedRuntime::turn()`.
+                let (create_agent, command_tx) = self.async_agent_factory(
+                    processor_id,
+                    Arc::clone(&io_shared),
+                    worker_stealable_queues.clone(),
+                    task_limits.clone(),
+                    io_fallback.clone(),
+                    in_flight_tasks.clone(),
+                );
+
+                let agent = create_agent();
+
+                let (ready_tx, ready_rx) = oneshot::channel::<AsyncAgentReady>();
+                ready_tx
+                    .send(AsyncAgentReady::new(&agent))
+                    .expect("runtime startup process failed in infallible code");
+
+                embedded_agent = Some(agent);
+
+                (command_tx, ready_rx)
+            } else {
+                let ThreadStartResult {
+                    join_handle: async_join_handle,
+                    start_tx: async_start_tx,
+                    ready_rx: async_ready_rx,
+                    result: async_command_tx,
+                } = self.start_async_agent(
+                    processor_id,
+                    Arc::clone(&io_shared),
+                    worker_index,
+                    worker_stealable_queues.clone(),
+                    task_limits.clone(),
+                    io_fallback.clone(),
+                    in_flight_tasks.clone(),
+                )?;
+
+                async_start_txs.push(async_start_tx);
+                join_handles.push(async_join_handle);
+
+                (async_command_tx, async_ready_rx)
+            };
 
             // There is a single queue of synchronous tasks per processor, shared by all the sync
             // workers assigned to that processor, to try balance out the load given that these may
@@ -252,7 +963,7 @@ impl RuntimeBuilder {
             let mut sync_command_txs = Vec::with_capacity(sync_worker_count);
             let mut sync_ready_rxs = Vec::with_capacity(sync_worker_count);
 
-            for worker_index in 0..SYNC_WORKERS_PER_PROCESSOR {
+            for worker_index in 0..self.sync_workers_per_processor {
                 let ThreadStartResult {
                     join_handle,
                     start_tx,
@@ -272,10 +983,24 @@ impl RuntimeBuilder {
                 join_handles.push(join_handle);
             }
 
-            let async_io_waker = async_ready_rx
+            let AsyncAgentReady {
+                io_waker: async_io_waker,
+                drain_acknowledged: async_drain_acknowledged,
+                polling_task: async_polling_task,
+                counters: async_counters,
+                flight_recorder: async_flight_recorder,
+            } = async_ready_rx
                 .recv()
-                .expect("async worker thread failed before even starting")
-                .io_waker;
+                .expect("async worker thread failed before even starting");
+
+            if self.watchdog.is_some() {
+                watched_workers.push(WatchedWorker {
+                    worker_index,
+                    processor_id: processor_id.id,
+                    counters: Arc::clone(&async_counters),
+                    polling_task: Arc::clone(&async_polling_task),
+                });
+            }
 
             sync_ready_rxs.into_iter().for_each(|ready_rx| {
                 ready_rx
@@ -288,9 +1013,14 @@ impl RuntimeBuilder {
                 processor_id,
                 async_command_tx,
                 async_io_waker,
+                async_drain_acknowledged,
+                async_polling_task,
+                async_counters,
+                async_flight_recorder,
                 sync_command_txs.into_boxed_slice(),
                 sync_task_queue,
                 sync_priority_task_queue,
+                worker_stealable_queues,
             );
 
             core_processors.insert(processor_id, proc);
@@ -304,11 +1034,25 @@ impl RuntimeBuilder {
 
         let is_stopping = Arc::new(AtomicBool::new(false));
 
+        // The watchdog exits when the runtime stops, so it is waited for like a worker thread.
+        if let Some((threshold, policy)) = self.watchdog.clone() {
+            join_handles.push(watchdog::start(
+                threshold,
+                policy,
+                self.diagnostics.clone(),
+                watched_workers,
+                Arc::clone(&is_stopping),
+            )?);
+        }
+
         let client = RuntimeClient::new(
             core_processors,
             processor_ids.clone(),
+            active_worker_count,
             join_handles.into_boxed_slice(),
             Arc::clone(&is_stopping),
+            io_fallback_used,
+            in_flight_tasks,
         );
 
         // In most cases, the entrypoint thread is merely parked. However, for interoperability
@@ -316,7 +1060,12 @@ impl RuntimeBuilder {
         // entrypoint thread, as well. This allows custom entrypoint logic to execute code
         // that calls `spawn_on_any()` to schedule work on the Folo runtime, while not being truly
         // on a Folo owned thread.
-        if self.ad_hoc_entrypoint {
+        //
+        // An embedded runtime registers the entrypoint thread as a real async worker instead.
+        if let Some(agent) = &embedded_agent {
+            current_async_agent::set(Rc::clone(agent));
+            current_runtime::set(client.clone());
+        } else if self.ad_hoc_entrypoint {
             current_runtime::set(client.clone());
         }
 
@@ -336,7 +1085,7 @@ impl RuntimeBuilder {
         }
 
         // All the agents are now running and the runtime is ready to be used.
-        Ok(client)
+        Ok((client, embedded_agent))
     }
 }
 
@@ -352,10 +1101,63 @@ impl Debug for RuntimeBuilder {
     }
 }
 
+/// Expands the placeholders in a worker thread name template.
+fn thread_name(template: &str, processor_id: CoreId, index: usize) -> String {
+    template
+        .replace("{index}", &index.to_string())
+        .replace("{processor}", &processor_id.id.to_string())
+}
+
+fn reserve_io_buffers(count: usize, register: bool) {
+    if count == 0 {
+        return;
+    }
+
+    if !register {
+        io::BufferPool::reserve(count);
+        return;
+    }
+
+    // Failing to register is not fatal - the buffers still work, just with less predictable
+    // performance.
+    if let Err(e) = io::BufferPool::reserve_registered(count) {
+        diagnostics::emit(Diagnostic::BufferRegistrationFailed { count, error: &e });
+    }
+}
+
+// Pinning happens before the thread-local settings of the worker are applied, so the diagnostics
+// backend is passed in explicitly.
+fn pin_current_thread(processor_id: CoreId, diagnostics: &DiagnosticsBackend) {
+    // Failing to pin is not fatal - the worker still works, just with less predictable performance.
+    if let Err(e) = affinity::pin_current_thread(processor_id) {
+        diagnostics.emit(Diagnostic::PinningFailed {
+            processor: processor_id.id,
+            error: &e,
+        });
+    }
+}
+
 /// A signal that an async agent is ready to start, providing inputs required for the runtime start.
 #[derive(Debug)]
 struct AsyncAgentReady {
     io_waker: IoWaker,
+    drain_acknowledged: Arc<AtomicBool>,
+    polling_task: Arc<PollingTask>,
+    counters: Arc<WorkerCounters>,
+    flight_recorder: Option<Arc<FlightRecorder>>,
+}
+
+impl AsyncAgentReady {
+    fn new(agent: &AsyncAgent) -> Self {
+        Self {
+            io_waker: agent.with_io(|io| io.waker()),
+            drain_acknowledged: agent.drain_acknowledged(),
+            polling_task: agent.polling_task(),
+            counters: agent.counters(),
+            // The agent is created on the worker thread, which holds the recorder.
+            flight_recorder: flight_recorder::current(),
+        }
+    }
 }
 
 /// A signal that a sync agent is ready to start, providing inputs required for the runtime start.
@@ -368,3 +1170,20 @@ struct SyncAgentReady {}
 struct AgentStartArguments {
     runtime_client: RuntimeClient,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thread_name_expands_placeholders() {
+        let processor_id = CoreId { id: 7 };
+
+        assert_eq!(thread_name("async-{index}", processor_id, 3), "async-3");
+        assert_eq!(
+            thread_name("folo-{processor}-{index}", processor_id, 1),
+            "folo-7-1"
+        );
+        assert_eq!(thread_name("worker", processor_id, 1), "worker");
+    }
+}
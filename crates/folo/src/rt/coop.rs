@@ -0,0 +1,291 @@
+use crate::metrics::{observe, Event, EventBuilder};
+use pin_project::pin_project;
+use std::{
+    cell::Cell,
+    future::Future,
+    pin::Pin,
+    task::{self, ready},
+};
+
+// Cooperative scheduling budget. Each time a task is polled, it receives a budget of operations
+// that it may complete. Resource types (channels, I/O operations, join handles) consume one unit
+// of the budget whenever they are ready - waiting for a resource that is not ready is free. Once
+// the budget is exhausted, they return `Pending` (and immediately wake the task) even if they
+// could complete, forcing the task to yield to the others on the same worker.
+//
+// Without this, a task that always finds its resources ready (e.g. a chatty connection with data
+// always waiting in the buffers) would never return from `poll()` and would starve every other
+// task on its worker thread.
+//
+// The budget is only enforced inside tasks polled by the async task engine. Code polled by other
+// executors (e.g. `futures::executor::block_on()`) is unconstrained.
+
+thread_local! {
+    static BUDGET_SIZE: Cell<u32> = const { Cell::new(DEFAULT_BUDGET_SIZE) };
+
+    // None means the current code is unconstrained.
+    static REMAINING: Cell<Option<u32>> = const { Cell::new(None) };
+}
+
+/// Default number of ready operations a task may complete in one poll before being forced to yield.
+pub(crate) const DEFAULT_BUDGET_SIZE: u32 = 128;
+
+/// Sets the budget for tasks polled on the current thread. Zero disables the budget.
+pub(crate) fn set_budget_size(value: u32) {
+    BUDGET_SIZE.with(|x| x.set(value));
+}
+
+/// Grants a fresh budget to the task about to be polled on the current thread. The previous state
+/// is restored when the returned guard is dropped.
+pub(crate) fn enter_task() -> BudgetGuard {
+    let budget = match BUDGET_SIZE.with(Cell::get) {
+        0 => None,
+        size => Some(size),
+    };
+
+    BudgetGuard {
+        previous: REMAINING.with(|x| x.replace(budget)),
+    }
+}
+
+/// Polls an operation of a resource type within the budget of the current task. One unit of the
+/// budget is consumed only if the operation completes - polls that return `Pending` are free, so a
+/// task may wait for any number of resources in one poll. If the budget is exhausted, wakes the
+/// task and returns `Pending` without polling the operation (it will be polled again once other
+/// tasks had their turn).
+pub(crate) fn poll_budgeted<T>(
+    cx: &mut task::Context<'_>,
+    poll: impl FnOnce(&mut task::Context<'_>) -> task::Poll<T>,
+) -> task::Poll<T> {
+    let restore = ready!(poll_proceed(cx));
+
+    let result = poll(cx);
+
+    if result.is_ready() {
+        restore.made_progress();
+    }
+
+    result
+}
+
+/// Consumes one unit of the budget of the current task if the budget is not yet exhausted. If it
+/// is exhausted, wakes the task and returns `Pending` - the caller must then return `Pending`
+/// without completing its operation (it will be polled again once other tasks had their turn).
+///
+/// The unit is returned to the budget when the returned guard is dropped, unless the caller
+/// reports via [`RestoreOnPending::made_progress()`] that its operation completed.
+pub(crate) fn poll_proceed(cx: &mut task::Context<'_>) -> task::Poll<RestoreOnPending> {
+    REMAINING.with(|remaining| match remaining.get() {
+        None => task::Poll::Ready(RestoreOnPending { consumed: false }),
+        Some(0) => {
+            observe!(BUDGET_EXHAUSTED.observe_unit());
+
+            cx.waker().wake_by_ref();
+            task::Poll::Pending
+        }
+        Some(n) => {
+            remaining.set(Some(n - 1));
+            task::Poll::Ready(RestoreOnPending { consumed: true })
+        }
+    })
+}
+
+/// Returns a consumed unit of budget unless the operation it was consumed for completed. Without
+/// this, waiting for resources that are not ready would exhaust the budget and a task waiting for
+/// many of them at once would be woken up forever without ever being able to register its wakers.
+#[derive(Debug)]
+pub(crate) struct RestoreOnPending {
+    consumed: bool,
+}
+
+impl RestoreOnPending {
+    pub(crate) fn made_progress(mut self) {
+        self.consumed = false;
+    }
+}
+
+impl Drop for RestoreOnPending {
+    fn drop(&mut self) {
+        if !self.consumed {
+            return;
+        }
+
+        REMAINING.with(|remaining| {
+            if let Some(n) = remaining.get() {
+                remaining.set(Some(n + 1));
+            }
+        });
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct BudgetGuard {
+    previous: Option<u32>,
+}
+
+impl Drop for BudgetGuard {
+    fn drop(&mut self) {
+        REMAINING.with(|x| x.set(self.previous));
+    }
+}
+
+/// Exempts a future from the cooperative scheduling budget. Resources used by the future will
+/// never return `Pending` merely because the task has completed too many operations in one poll.
+///
+/// Use with care - an unconstrained future that always finds its resources ready will never yield
+/// and will starve all other tasks on its worker thread.
+pub fn unconstrained<F>(future: F) -> Unconstrained<F>
+where
+    F: Future,
+{
+    Unconstrained { inner: future }
+}
+
+/// A future that is exempt from the cooperative scheduling budget. See [`unconstrained()`].
+#[pin_project]
+#[derive(Debug)]
+pub struct Unconstrained<F> {
+    #[pin]
+    inner: F,
+}
+
+impl<F> Future for Unconstrained<F>
+where
+    F: Future,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> task::Poll<Self::Output> {
+        let _guard = BudgetGuard {
+            previous: REMAINING.with(|x| x.replace(None)),
+        };
+
+        self.project().inner.poll(cx)
+    }
+}
+
+thread_local! {
+    static BUDGET_EXHAUSTED: Event = EventBuilder::new("rt_coop_budget_exhausted")
+        .build();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::task::{noop_waker_ref, waker, ArcWake};
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    // Completes an operation within the budget, as a resource type that is ready would.
+    fn complete(cx: &mut task::Context<'_>) -> bool {
+        poll_budgeted(cx, |_| task::Poll::Ready(())).is_ready()
+    }
+
+    #[derive(Default)]
+    struct CountingWaker {
+        wakes: AtomicUsize,
+    }
+
+    impl ArcWake for CountingWaker {
+        fn wake_by_ref(arc_self: &Arc<Self>) {
+            arc_self.wakes.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn unconstrained_outside_task() {
+        let mut cx = task::Context::from_waker(noop_waker_ref());
+
+        for _ in 0..DEFAULT_BUDGET_SIZE * 2 {
+            assert!(complete(&mut cx));
+        }
+    }
+
+    #[test]
+    fn exhausted_budget_returns_pending() {
+        set_budget_size(3);
+        let mut cx = task::Context::from_waker(noop_waker_ref());
+
+        {
+            let _guard = enter_task();
+
+            assert!(complete(&mut cx));
+            assert!(complete(&mut cx));
+            assert!(complete(&mut cx));
+            assert!(!complete(&mut cx));
+        }
+
+        // A new poll of the task gets a new budget.
+        {
+            let _guard = enter_task();
+            assert!(complete(&mut cx));
+        }
+
+        set_budget_size(DEFAULT_BUDGET_SIZE);
+    }
+
+    #[test]
+    fn zero_disables_budget() {
+        set_budget_size(0);
+        let mut cx = task::Context::from_waker(noop_waker_ref());
+
+        let _guard = enter_task();
+
+        for _ in 0..DEFAULT_BUDGET_SIZE * 2 {
+            assert!(complete(&mut cx));
+        }
+
+        set_budget_size(DEFAULT_BUDGET_SIZE);
+    }
+
+    #[test]
+    fn unconstrained_future_ignores_budget() {
+        set_budget_size(1);
+        let mut cx = task::Context::from_waker(noop_waker_ref());
+
+        let _guard = enter_task();
+
+        let mut future = Box::pin(unconstrained(futures::future::poll_fn(|cx| {
+            for _ in 0..10 {
+                assert!(complete(cx));
+            }
+
+            task::Poll::Ready(())
+        })));
+
+        assert!(future.as_mut().poll(&mut cx).is_ready());
+
+        // Outside the unconstrained future, the budget still applies.
+        assert!(complete(&mut cx));
+        assert!(!complete(&mut cx));
+
+        set_budget_size(DEFAULT_BUDGET_SIZE);
+    }
+
+    #[test]
+    fn pending_polls_do_not_consume_budget() {
+        let counting_waker = Arc::new(CountingWaker::default());
+        let waker = waker(Arc::clone(&counting_waker));
+        let mut cx = task::Context::from_waker(&waker);
+
+        let _guard = enter_task();
+
+        // A task waiting for more resources than its budget allows must still be able to register
+        // its wakers with all of them and go to sleep, instead of being woken up forever.
+        for _ in 0..DEFAULT_BUDGET_SIZE * 2 {
+            assert!(poll_budgeted(&mut cx, |_| task::Poll::<()>::Pending).is_pending());
+        }
+
+        assert_eq!(counting_waker.wakes.load(Ordering::Relaxed), 0);
+
+        // The whole budget is still available for completing operations.
+        for _ in 0..DEFAULT_BUDGET_SIZE {
+            assert!(complete(&mut cx));
+        }
+
+        assert!(!complete(&mut cx));
+        assert_eq!(counting_waker.wakes.load(Ordering::Relaxed), 1);
+    }
+}
@@ -66,6 +66,15 @@ where
     CURRENT_AGENT.with_borrow(|agent| agent.as_ref().map(|agent| agent.with_io(f)))
 }
 
+/// Executes a closure that receives the current thread's async agent, if the current thread is an
+/// async worker thread owned by the Folo runtime.
+pub fn try_with<F, R>(f: F) -> Option<R>
+where
+    F: FnOnce(&AsyncAgent) -> R,
+{
+    CURRENT_AGENT.with_borrow(|agent| agent.as_ref().map(|agent| f(agent)))
+}
+
 pub fn is_some() -> bool {
     CURRENT_AGENT.with_borrow(|agent| agent.is_some())
 }
@@ -18,6 +18,10 @@ where
     })
 }
 
+pub fn is_some() -> bool {
+    CURRENT_AGENT.with_borrow(|agent| agent.is_some())
+}
+
 pub fn set(value: Rc<SyncAgent>) {
     CURRENT_AGENT.with_borrow_mut(|agent| {
         if agent.is_some() {
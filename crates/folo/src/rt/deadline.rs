@@ -0,0 +1,42 @@
+use crate::{
+    rt::{JoinError, JoinResult},
+    time::{Deadline, DeadlineScope},
+};
+use pin_project::pin_project;
+use std::{future::Future, pin::Pin, task};
+
+/// Wraps the future of a task, completing with `JoinError::DeadlineExceeded` if the future has not
+/// completed by the deadline. The inner future is dropped when the task engine clears the task.
+///
+/// The future runs under the deadline for its entire lifetime, so `Deadline::current()` returns it
+/// to any code the task runs.
+///
+/// If the future and the deadline become ready at the same time, the result of the future wins.
+#[pin_project]
+#[derive(Debug)]
+pub(crate) struct WithDeadline<F> {
+    #[pin]
+    inner: DeadlineScope<F>,
+}
+
+impl<F: Future> WithDeadline<F> {
+    pub fn new(inner: F, deadline: Deadline) -> Self {
+        Self {
+            inner: deadline.scope(inner),
+        }
+    }
+}
+
+impl<F> Future for WithDeadline<F>
+where
+    F: Future,
+{
+    type Output = JoinResult<F::Output>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> task::Poll<Self::Output> {
+        self.project()
+            .inner
+            .poll(cx)
+            .map(|result| result.map_err(|_| JoinError::DeadlineExceeded))
+    }
+}
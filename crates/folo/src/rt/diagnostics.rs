@@ -0,0 +1,353 @@
+use crate::rt::TaskId;
+use std::{
+    cell::RefCell,
+    error::Error,
+    fmt::{self, Debug, Display, Formatter},
+    sync::Arc,
+    time::Duration,
+};
+use tracing::{event, Level};
+
+const SLOW_POLL_MESSAGE: &str =
+    "task poll exceeded slow poll threshold; the task is blocking the worker thread";
+const DEADLOCK_MESSAGE: &str =
+    "deadlock detected; tasks are waiting for thread-local locks held by each other";
+const STALLED_TASK_MESSAGE: &str =
+    "task has not been polled for longer than the stall threshold; it may be leaked";
+const LOST_WAKER_MESSAGE: &str =
+    "task is waiting but nothing holds its waker; it will never be woken up";
+const WORKER_BLOCKED_MESSAGE: &str =
+    "worker thread has not completed a cycle within the watchdog threshold; it is blocked";
+const IO_BLOCKING_FALLBACK_MESSAGE: &str =
+    "failed to associate I/O primitive with I/O completion port; falling back to blocking I/O";
+
+/// A problem detected by the runtime itself that does not prevent it from working but that the
+/// application operator likely wants to know about.
+///
+/// Delivered to the [`DiagnosticsBackend`] configured via
+/// [`RuntimeBuilder::diagnostics()`][crate::rt::RuntimeBuilder::diagnostics].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Diagnostic<'a> {
+    /// A single poll of a task took longer than the slow poll threshold, blocking every other task
+    /// on the worker thread.
+    SlowPoll {
+        task_id: TaskId,
+        task_name: Option<&'a str>,
+        duration: Duration,
+        threshold: Duration,
+    },
+
+    /// The operating system refused to cancel an I/O operation whose future was dropped.
+    CancellationFailed { error: &'a (dyn Error + 'static) },
+
+    /// A worker thread could not be pinned to its processor.
+    PinningFailed {
+        processor: usize,
+        error: &'a (dyn Error + 'static),
+    },
+
+    /// The I/O buffers of a worker thread could not be registered with the operating system.
+    BufferRegistrationFailed {
+        count: usize,
+        error: &'a (dyn Error + 'static),
+    },
+
+    /// Tasks on a worker thread are waiting for thread-local locks held by each other.
+    DeadlockDetected { cycle: &'a dyn Display },
+
+    /// A task has been waiting for longer than the stall threshold. It may be waiting for
+    /// something that never happens (e.g. a message on a channel nobody sends to).
+    TaskStalled {
+        task_id: TaskId,
+        task_name: Option<&'a str>,
+        idle: Duration,
+    },
+
+    /// A task has been waiting for longer than the stall threshold and every clone of its waker
+    /// has been dropped, so nothing can ever wake it up. Typically a future returned `Pending`
+    /// without registering the waker or a resource dropped the waker without invoking it.
+    LostWaker {
+        task_id: TaskId,
+        task_name: Option<&'a str>,
+        idle: Duration,
+    },
+
+    /// An async worker thread has been busy without completing a cycle of its loop for longer
+    /// than the watchdog threshold, typically because a task is making a blocking call. Reported
+    /// by the watchdog thread, with the task the worker is polling, if any.
+    WorkerBlocked {
+        processor_id: usize,
+        task_id: Option<TaskId>,
+        task_name: Option<&'a str>,
+        blocked_for: Duration,
+    },
+
+    /// An I/O primitive could not be associated with the I/O completion port of a worker thread,
+    /// so operations on it are performed on synchronous worker threads. Reported once per runtime.
+    IoBlockingFallback { error: &'a (dyn Error + 'static) },
+}
+
+impl Diagnostic<'_> {
+    /// Whether the diagnostic indicates a definite bug (as opposed to a potential problem).
+    pub fn is_error(&self) -> bool {
+        matches!(self, Self::DeadlockDetected { .. } | Self::LostWaker { .. })
+    }
+}
+
+impl Display for Diagnostic<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SlowPoll {
+                task_id,
+                task_name,
+                duration,
+                threshold,
+            } => {
+                write!(f, "{SLOW_POLL_MESSAGE} (task {task_id}")?;
+
+                if let Some(name) = task_name {
+                    write!(f, " '{name}'")?;
+                }
+
+                write!(f, ", {duration:?} > {threshold:?})")
+            }
+            Self::CancellationFailed { error } => {
+                write!(f, "failed to cancel abandoned I/O operation: {error}")
+            }
+            Self::PinningFailed { processor, error } => {
+                write!(
+                    f,
+                    "failed to pin worker thread to processor {processor}: {error}"
+                )
+            }
+            Self::BufferRegistrationFailed { count, error } => {
+                write!(
+                    f,
+                    "failed to register {count} I/O buffers of worker thread: {error}"
+                )
+            }
+            Self::DeadlockDetected { cycle } => {
+                write!(f, "{DEADLOCK_MESSAGE}: {cycle}")
+            }
+            Self::TaskStalled {
+                task_id,
+                task_name,
+                idle,
+            } => write_idle_task(f, STALLED_TASK_MESSAGE, *task_id, *task_name, *idle),
+            Self::LostWaker {
+                task_id,
+                task_name,
+                idle,
+            } => write_idle_task(f, LOST_WAKER_MESSAGE, *task_id, *task_name, *idle),
+            Self::WorkerBlocked {
+                processor_id,
+                task_id,
+                task_name,
+                blocked_for,
+            } => {
+                write!(f, "{WORKER_BLOCKED_MESSAGE} (processor {processor_id}")?;
+
+                if let Some(task_id) = task_id {
+                    write!(f, ", polling task {task_id}")?;
+                }
+
+                if let Some(name) = task_name {
+                    write!(f, " '{name}'")?;
+                }
+
+                write!(f, ", blocked for {blocked_for:?})")
+            }
+            Self::IoBlockingFallback { error } => {
+                write!(f, "{IO_BLOCKING_FALLBACK_MESSAGE}: {error}")
+            }
+        }
+    }
+}
+
+fn write_idle_task(
+    f: &mut Formatter<'_>,
+    message: &str,
+    task_id: TaskId,
+    task_name: Option<&str>,
+    idle: Duration,
+) -> fmt::Result {
+    write!(f, "{message} (task {task_id}")?;
+
+    if let Some(name) = task_name {
+        write!(f, " '{name}'")?;
+    }
+
+    write!(f, ", idle for {idle:?})")
+}
+
+/// Determines where the runtime delivers its own [diagnostics][Diagnostic]. Configured via
+/// [`RuntimeBuilder::diagnostics()`][crate::rt::RuntimeBuilder::diagnostics].
+#[derive(Clone, Default)]
+pub enum DiagnosticsBackend {
+    /// Emitted as `tracing` events, with the details as structured fields.
+    #[default]
+    Tracing,
+
+    /// Emitted as records of the `log` crate, with the details in the message.
+    #[cfg(feature = "log")]
+    Log,
+
+    /// Passed to a callback, on the thread that detected the problem. The callback must not block
+    /// for long, as it is typically called on an async worker thread.
+    Callback(Arc<dyn Fn(&Diagnostic<'_>) + Send + Sync>),
+
+    /// Discarded.
+    Off,
+}
+
+impl DiagnosticsBackend {
+    /// Creates a backend that passes diagnostics to the callback.
+    pub fn callback(f: impl Fn(&Diagnostic<'_>) + Send + Sync + 'static) -> Self {
+        Self::Callback(Arc::new(f))
+    }
+
+    pub(crate) fn emit(&self, diagnostic: Diagnostic<'_>) {
+        match self {
+            Self::Tracing => emit_tracing(&diagnostic),
+            #[cfg(feature = "log")]
+            Self::Log => {
+                let level = if diagnostic.is_error() {
+                    log::Level::Error
+                } else {
+                    log::Level::Warn
+                };
+
+                log::log!(target: "folo", level, "{diagnostic}");
+            }
+            Self::Callback(f) => f(&diagnostic),
+            Self::Off => {}
+        }
+    }
+}
+
+impl Debug for DiagnosticsBackend {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tracing => write!(f, "Tracing"),
+            #[cfg(feature = "log")]
+            Self::Log => write!(f, "Log"),
+            // The callback is not Debug, so we just say there is one.
+            Self::Callback(_) => write!(f, "Callback(..)"),
+            Self::Off => write!(f, "Off"),
+        }
+    }
+}
+
+fn emit_tracing(diagnostic: &Diagnostic<'_>) {
+    match diagnostic {
+        Diagnostic::SlowPoll {
+            task_id,
+            task_name,
+            duration,
+            threshold,
+        } => {
+            event!(
+                Level::WARN,
+                message = SLOW_POLL_MESSAGE,
+                task_id = %task_id,
+                task_name,
+                ?duration,
+                ?threshold
+            );
+        }
+        Diagnostic::CancellationFailed { error } => {
+            event!(
+                Level::WARN,
+                message = "failed to cancel abandoned I/O operation",
+                error = error.to_string()
+            );
+        }
+        Diagnostic::PinningFailed { processor, error } => {
+            event!(
+                Level::WARN,
+                message = "failed to pin worker thread to processor",
+                processor,
+                error = %error
+            );
+        }
+        Diagnostic::BufferRegistrationFailed { count, error } => {
+            event!(
+                Level::WARN,
+                message = "failed to register I/O buffers of worker thread",
+                count,
+                error = %error
+            );
+        }
+        Diagnostic::DeadlockDetected { cycle } => {
+            event!(
+                Level::ERROR,
+                message = DEADLOCK_MESSAGE,
+                %cycle
+            );
+        }
+        Diagnostic::TaskStalled {
+            task_id,
+            task_name,
+            idle,
+        } => {
+            event!(
+                Level::WARN,
+                message = STALLED_TASK_MESSAGE,
+                task_id = %task_id,
+                task_name,
+                ?idle
+            );
+        }
+        Diagnostic::LostWaker {
+            task_id,
+            task_name,
+            idle,
+        } => {
+            event!(
+                Level::ERROR,
+                message = LOST_WAKER_MESSAGE,
+                task_id = %task_id,
+                task_name,
+                ?idle
+            );
+        }
+        Diagnostic::WorkerBlocked {
+            processor_id,
+            task_id,
+            task_name,
+            blocked_for,
+        } => {
+            event!(
+                Level::WARN,
+                message = WORKER_BLOCKED_MESSAGE,
+                processor_id,
+                task_id = task_id.map(|x| x.to_string()),
+                task_name,
+                ?blocked_for
+            );
+        }
+        Diagnostic::IoBlockingFallback { error } => {
+            event!(
+                Level::WARN,
+                message = IO_BLOCKING_FALLBACK_MESSAGE,
+                error = %error
+            );
+        }
+    }
+}
+
+thread_local! {
+    static BACKEND: RefCell<DiagnosticsBackend> = RefCell::new(DiagnosticsBackend::Tracing);
+}
+
+/// Sets the backend for diagnostics detected on the current thread.
+pub(crate) fn set_backend(value: DiagnosticsBackend) {
+    BACKEND.with_borrow_mut(|x| *x = value);
+}
+
+/// Delivers a diagnostic to the backend of the current thread. Threads that do not belong to a
+/// runtime use `tracing`.
+pub(crate) fn emit(diagnostic: Diagnostic<'_>) {
+    BACKEND.with_borrow(|backend| backend.emit(diagnostic));
+}
@@ -0,0 +1,305 @@
+use crate::{
+    constants::POISONED_LOCK,
+    rt::{task_trace::Suspension, TaskId},
+    time::LowPrecisionInstant,
+};
+use std::{
+    cmp::Reverse,
+    fmt::{self, Display, Formatter},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+/// A snapshot of the live tasks of a runtime, for diagnosing hangs. Created via
+/// [`RuntimeClient::dump()`][crate::rt::RuntimeClient::dump].
+///
+/// The `Display` implementation renders the snapshot as a human-readable listing, suitable for
+/// logging.
+#[derive(Clone, Debug)]
+pub struct RuntimeDump {
+    workers: Box<[WorkerDump]>,
+}
+
+impl RuntimeDump {
+    pub(crate) fn new(workers: Box<[WorkerDump]>) -> Self {
+        Self { workers }
+    }
+
+    /// The async worker threads of the runtime.
+    pub fn workers(&self) -> &[WorkerDump] {
+        &self.workers
+    }
+
+    /// The tasks with the most [memory][TaskDump::memory] attributed to them, largest first, up
+    /// to `count` of them.
+    pub fn top_memory_consumers(&self, count: usize) -> Vec<&TaskDump> {
+        let mut tasks = self
+            .workers
+            .iter()
+            .flat_map(|worker| worker.tasks.iter())
+            .collect::<Vec<_>>();
+
+        tasks.sort_by_key(|task| Reverse(task.memory()));
+        tasks.truncate(count);
+        tasks
+    }
+}
+
+impl Display for RuntimeDump {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for worker in self.workers.iter() {
+            write!(f, "worker on processor {}", worker.processor_id)?;
+
+            if !worker.responded {
+                write!(f, " (did not respond)")?;
+            }
+
+            writeln!(f, ": {} tasks", worker.tasks.len())?;
+
+            for task in worker.tasks.iter() {
+                write!(f, "  {} [{:?}]", task.id, task.state)?;
+
+                if let Some(name) = &task.name {
+                    write!(f, " {name}")?;
+                }
+
+                if let Some(age) = task.age {
+                    write!(f, ", age {age:?}")?;
+                }
+
+                match task.since_last_poll {
+                    Some(since_last_poll) => writeln!(f, ", last polled {since_last_poll:?} ago")?,
+                    None => writeln!(f, ", never polled")?,
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The live tasks of one async worker thread.
+#[derive(Clone, Debug)]
+pub struct WorkerDump {
+    processor_id: usize,
+    responded: bool,
+    tasks: Box<[TaskDump]>,
+}
+
+impl WorkerDump {
+    pub(crate) fn new(processor_id: usize, responded: bool, tasks: Box<[TaskDump]>) -> Self {
+        Self {
+            processor_id,
+            responded,
+            tasks,
+        }
+    }
+
+    /// The processor the worker thread is assigned to.
+    pub fn processor_id(&self) -> usize {
+        self.processor_id
+    }
+
+    /// Whether the worker responded to the dump request in time.
+    ///
+    /// A worker that does not respond is typically stuck in a single poll of a task that is
+    /// blocking the thread. In that case, the only task listed is the one being polled, in the
+    /// `Running` state.
+    pub fn responded(&self) -> bool {
+        self.responded
+    }
+
+    pub fn tasks(&self) -> &[TaskDump] {
+        &self.tasks
+    }
+}
+
+/// Describes one live task.
+#[derive(Clone, Debug)]
+pub struct TaskDump {
+    id: TaskId,
+    name: Option<Arc<str>>,
+    state: TaskState,
+    age: Option<Duration>,
+    since_last_poll: Option<Duration>,
+    polls: u64,
+    busy_time: Duration,
+    frame_size: usize,
+    charged_memory: u64,
+    suspension: Option<Suspension>,
+}
+
+impl TaskDump {
+    pub(crate) fn new(
+        id: TaskId,
+        name: Option<Arc<str>>,
+        state: TaskState,
+        age: Option<Duration>,
+        since_last_poll: Option<Duration>,
+    ) -> Self {
+        Self {
+            id,
+            name,
+            state,
+            age,
+            since_last_poll,
+            polls: 0,
+            busy_time: Duration::ZERO,
+            frame_size: 0,
+            charged_memory: 0,
+            suspension: None,
+        }
+    }
+
+    pub(crate) fn with_polls(mut self, polls: u64, busy_time: Duration) -> Self {
+        self.polls = polls;
+        self.busy_time = busy_time;
+        self
+    }
+
+    pub(crate) fn with_memory(mut self, frame_size: usize, charged_memory: u64) -> Self {
+        self.frame_size = frame_size;
+        self.charged_memory = charged_memory;
+        self
+    }
+
+    pub(crate) fn with_suspension(mut self, suspension: Option<Suspension>) -> Self {
+        self.suspension = suspension;
+        self
+    }
+
+    /// Where the task was suspended at the end of its last poll. See `TaskTrace`.
+    pub(crate) fn suspension(&self) -> Option<&Suspension> {
+        self.suspension.as_ref()
+    }
+
+    pub fn id(&self) -> TaskId {
+        self.id
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn state(&self) -> TaskState {
+        self.state
+    }
+
+    /// How long ago the task was spawned. Not known for tasks of workers that did not respond.
+    pub fn age(&self) -> Option<Duration> {
+        self.age
+    }
+
+    /// How long ago the task was last polled (or started being polled, if it is running), or
+    /// `None` if it has never been polled.
+    pub fn since_last_poll(&self) -> Option<Duration> {
+        self.since_last_poll
+    }
+
+    /// How many times the task has been polled. Every poll after the first one follows a wake.
+    /// Zero for tasks of workers that did not respond.
+    pub fn polls(&self) -> u64 {
+        self.polls
+    }
+
+    /// The total time the task has spent being polled, measured with a low precision clock.
+    /// Zero for tasks of workers that did not respond.
+    pub fn busy_time(&self) -> Duration {
+        self.busy_time
+    }
+
+    /// The size of the future the task was spawned with. Zero for tasks of workers that did not
+    /// respond.
+    pub fn frame_size(&self) -> usize {
+        self.frame_size
+    }
+
+    /// The number of bytes currently charged to the task (e.g. for I/O buffers it holds). Always
+    /// zero unless [task memory accounting][crate::rt::set_task_memory_accounting] was enabled when
+    /// the task was spawned.
+    pub fn charged_memory(&self) -> u64 {
+        self.charged_memory
+    }
+
+    /// The total memory attributed to the task - its frame plus the memory charged to it.
+    pub fn memory(&self) -> u64 {
+        self.frame_size as u64 + self.charged_memory
+    }
+}
+
+/// The scheduling state of a task.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TaskState {
+    /// The task is being polled right now.
+    Running,
+
+    /// The task is ready to be polled and is waiting for its turn.
+    Ready,
+
+    /// The task is waiting to be woken up (e.g. for I/O to complete or for a timer to elapse).
+    Waiting,
+}
+
+/// Published by an async worker so the task it is currently polling can be identified from another
+/// thread even if the worker itself is stuck in that poll.
+#[derive(Debug, Default)]
+pub(crate) struct PollingTask {
+    // The ID of the task plus one, or zero if no task is being polled.
+    task_id: AtomicU64,
+
+    // When the poll started, in `LowPrecisionInstant` milliseconds.
+    started: AtomicU64,
+
+    // Publishing the name takes a lock on every poll, so it is only done for observers that need
+    // the name (e.g. the watchdog), which enable it via `publish_names()`.
+    // The name is stored with the ID of its task, as it is not updated together with `task_id`.
+    names_published: AtomicBool,
+    task_name: Mutex<Option<(TaskId, Arc<str>)>>,
+}
+
+impl PollingTask {
+    pub fn enter(&self, task_id: TaskId, started: LowPrecisionInstant, name: Option<&Arc<str>>) {
+        if self.names_published.load(Ordering::Relaxed) {
+            *self.task_name.lock().expect(POISONED_LOCK) = name.map(|x| (task_id, Arc::clone(x)));
+        }
+
+        self.started.store(started.as_millis(), Ordering::Relaxed);
+        self.task_id.store(task_id.as_u64() + 1, Ordering::Relaxed);
+    }
+
+    /// Starts publishing the name of the task being polled, in addition to its ID.
+    pub fn publish_names(&self) {
+        self.names_published.store(true, Ordering::Relaxed);
+    }
+
+    pub fn exit(&self) {
+        self.task_id.store(0, Ordering::Relaxed);
+    }
+
+    /// Describes the task being polled, if any.
+    pub fn dump(&self) -> Option<TaskDump> {
+        let task_id = match self.task_id.load(Ordering::Relaxed) {
+            0 => return None,
+            value => TaskId::from_u64(value - 1),
+        };
+
+        let started = LowPrecisionInstant::from_millis(self.started.load(Ordering::Relaxed));
+
+        // The worker may have moved on to another task since we loaded the ID.
+        let name = match &*self.task_name.lock().expect(POISONED_LOCK) {
+            Some((name_task_id, name)) if *name_task_id == task_id => Some(Arc::clone(name)),
+            _ => None,
+        };
+
+        Some(TaskDump::new(
+            task_id,
+            name,
+            TaskState::Running,
+            None,
+            Some(started.elapsed()),
+        ))
+    }
+}
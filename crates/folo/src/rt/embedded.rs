@@ -0,0 +1,131 @@
+use crate::rt::{async_agent::AsyncAgent, RuntimeClient};
+use std::{
+    fmt::{self, Debug, Formatter},
+    rc::Rc,
+    time::Duration,
+};
+
+// How long a single turn waits for work when the runtime is dropped without having finished.
+const DROP_TURN_MAX_WAIT: Duration = Duration::from_millis(10);
+
+/// A Folo runtime whose first async worker is a thread owned by the host application, which drives
+/// the worker by calling [`turn()`][Self::turn] from its own event loop. Created via
+/// [`RuntimeBuilder::build_embedded()`][crate::rt::RuntimeBuilder::build_embedded].
+///
+/// The embedded worker is a full async worker - tasks can be spawned on it (e.g. via `spawn()`
+/// from the host thread) and it can perform I/O. However, it only makes progress while the host is
+/// inside `turn()`, so the host must call it regularly.
+///
+/// To stop the runtime, call `stop()` on the client and keep calling `turn()` until it returns
+/// `false`, then call `wait()` on the client to wait for the other workers. Graceful shutdown via
+/// `RuntimeClient::shutdown()` blocks the calling thread, so it must be started from a thread
+/// other than the embedded worker.
+///
+/// If the runtime is dropped before it has finished, it is stopped and the embedded worker is
+/// driven to completion as part of the drop, which may block until pending I/O has completed.
+///
+/// # Example
+///
+/// ```
+/// use folo::rt::{spawn, RuntimeBuilder};
+/// use std::{cell::Cell, rc::Rc, time::Duration};
+///
+/// let mut runtime = RuntimeBuilder::new().build_embedded().unwrap();
+///
+/// let done = Rc::new(Cell::new(false));
+///
+/// spawn({
+///     let done = Rc::clone(&done);
+///     async move { done.set(true) }
+/// });
+///
+/// // The host application calls this from its event loop.
+/// while !done.get() {
+///     runtime.turn(Duration::from_millis(10));
+/// }
+///
+/// runtime.client().stop();
+/// while runtime.turn(Duration::from_millis(10)) {}
+/// runtime.client().wait();
+/// ```
+pub struct EmbeddedRuntime {
+    client: RuntimeClient,
+    agent: Rc<AsyncAgent>,
+
+    // Set once the worker loop has finished and the agent has released its resources.
+    finished: bool,
+}
+
+impl EmbeddedRuntime {
+    pub(crate) fn new(client: RuntimeClient, agent: Rc<AsyncAgent>) -> Self {
+        agent.begin_run();
+
+        Self {
+            client,
+            agent,
+            finished: false,
+        }
+    }
+
+    /// The client of the runtime, which can be used to spawn tasks on any worker and to stop the
+    /// runtime.
+    pub fn client(&self) -> &RuntimeClient {
+        &self.client
+    }
+
+    /// Performs one iteration of the worker loop on the current thread: processes commands from
+    /// other threads, harvests I/O completions, fires expired timers and polls the tasks that are
+    /// ready. If there is nothing to do, waits for up to `max_wait` for new work to arrive (or less,
+    /// depending on the idle strategy). A zero `max_wait` never waits.
+    ///
+    /// Returns `false` once the runtime has been stopped and the embedded worker has finished,
+    /// after which calling this has no effect.
+    pub fn turn(&mut self, max_wait: Duration) -> bool {
+        if self.finished {
+            return false;
+        }
+
+        let max_wait_ms = u32::try_from(max_wait.as_millis()).unwrap_or(u32::MAX);
+
+        // Wakeups are only batched within the turn - the host thread may wake up tasks of other
+        // workers between turns, which must not wait for the next turn to be delivered.
+        self.agent.enable_batching();
+
+        let more = self.agent.turn(max_wait_ms);
+
+        if !more {
+            self.agent.finish_run();
+            self.finished = true;
+        }
+
+        self.agent.disable_batching();
+
+        more
+    }
+
+    /// Whether the runtime has been stopped and the embedded worker has finished.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+}
+
+impl Drop for EmbeddedRuntime {
+    fn drop(&mut self) {
+        // The resources of the worker (e.g. buffers of pending I/O operations) may only be
+        // released once the worker has finished, so we have to drive it to completion here.
+        if !self.finished {
+            self.client.stop();
+
+            while self.turn(DROP_TURN_MAX_WAIT) {}
+        }
+    }
+}
+
+impl Debug for EmbeddedRuntime {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EmbeddedRuntime")
+            .field("client", &self.client)
+            .field("finished", &self.finished)
+            .finish()
+    }
+}
@@ -1,3 +1,4 @@
+use crate::rt::TaskMeta;
 use std::future::Future;
 
 /// An asyncronous task whose return type has been erased - we do not know what exactly the future
@@ -10,4 +11,17 @@ pub trait ErasedResultAsyncTask: Future<Output = ()> + 'static {
     /// Clears all references this task holds to other tasks on the same worker thread. After this,
     /// the task must not be polled again.
     fn clear(&self);
+
+    /// Returns true if the task was spawned by code running on a thread owned by the runtime. Such
+    /// tasks are part of the work already in flight and are accepted even while the runtime is
+    /// draining.
+    fn is_spawned_by_runtime(&self) -> bool;
+
+    /// Resolves the join handle of a task that will never start as cancelled. Called when the
+    /// worker refuses the task because it is no longer accepting new work. After this, the task
+    /// must not be polled.
+    fn reject(&self);
+
+    /// Describes the task for diagnostic purposes.
+    fn meta(&self) -> &TaskMeta;
 }
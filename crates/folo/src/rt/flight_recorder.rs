@@ -0,0 +1,439 @@
+//! Records the most recent runtime events of every async worker thread in a fixed-size ring
+//! buffer, so that the moments before a crash or a stall can be examined after the fact.
+//!
+//! Each worker owns its recorder but the recorder is shared with the runtime client, so a record
+//! can be taken even if the worker is stuck.
+
+use crate::{constants, metrics::install_panic_hook, rt::TaskId, time::LowPrecisionInstant};
+use std::{
+    cell::RefCell,
+    fmt::{self, Display, Formatter},
+    fs::OpenOptions,
+    io::Write,
+    panic::PanicHookInfo,
+    path::PathBuf,
+    sync::{Arc, Mutex, Once, Weak},
+    thread,
+    time::{Duration, SystemTime},
+};
+use tracing::{event, Level};
+use windows::Win32::{
+    Foundation::{BOOL, FALSE, TRUE},
+    System::Console::{SetConsoleCtrlHandler, CTRL_BREAK_EVENT},
+};
+
+/// A runtime event captured by the flight recorder.
+#[derive(Clone, Copy, Debug)]
+#[non_exhaustive]
+pub enum FlightEvent {
+    /// A task was added to the worker.
+    TaskSpawned { task_id: TaskId },
+
+    /// A task was polled. `completed` is `true` if the task finished in this poll.
+    TaskPolled {
+        task_id: TaskId,
+        duration: Duration,
+        completed: bool,
+    },
+
+    /// An I/O operation was submitted to the operating system. The operation is identified by its
+    /// slot in the I/O driver, which is reused once the operation completes. The kind is the type
+    /// name of the function that started the operation.
+    IoSubmitted {
+        operation: usize,
+        kind: &'static str,
+    },
+
+    /// An I/O operation completed, successfully or not.
+    IoCompleted {
+        operation: usize,
+        bytes_transferred: usize,
+        duration: Duration,
+        succeeded: bool,
+    },
+
+    /// A timer fired, `latency` after its due time.
+    TimerFired { latency: Duration },
+}
+
+impl Display for FlightEvent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TaskSpawned { task_id } => write!(f, "{task_id} spawned"),
+            Self::TaskPolled {
+                task_id,
+                duration,
+                completed,
+            } => {
+                write!(f, "{task_id} polled for {duration:?}")?;
+
+                if *completed {
+                    write!(f, ", completed")?;
+                }
+
+                Ok(())
+            }
+            Self::IoSubmitted { operation, kind } => {
+                let kind = kind.trim_end_matches("::{{closure}}");
+                write!(f, "I/O operation {operation} submitted by {kind}")
+            }
+            Self::IoCompleted {
+                operation,
+                bytes_transferred,
+                duration,
+                succeeded,
+            } => {
+                let outcome = if *succeeded { "completed" } else { "failed" };
+                write!(f, "I/O operation {operation} {outcome} after {duration:?}")?;
+                write!(f, ", {bytes_transferred} bytes")
+            }
+            Self::TimerFired { latency } => write!(f, "timer fired {latency:?} late"),
+        }
+    }
+}
+
+/// The recent events of the async worker threads of a runtime. Created via
+/// [`RuntimeClient::dump_flight_record()`][crate::rt::RuntimeClient::dump_flight_record].
+///
+/// The `Display` implementation renders the record as a human-readable listing, oldest event first.
+#[derive(Clone, Debug)]
+pub struct FlightRecord {
+    workers: Box<[WorkerFlightRecord]>,
+}
+
+impl FlightRecord {
+    pub(crate) fn new(workers: Box<[WorkerFlightRecord]>) -> Self {
+        Self { workers }
+    }
+
+    /// The async worker threads of the runtime. Empty if the flight recorder is not enabled.
+    pub fn workers(&self) -> &[WorkerFlightRecord] {
+        &self.workers
+    }
+}
+
+impl Display for FlightRecord {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for worker in self.workers.iter() {
+            write!(f, "worker on processor {}", worker.processor_id)?;
+
+            if worker.overwritten > 0 {
+                write!(f, " ({} older events overwritten)", worker.overwritten)?;
+            }
+
+            writeln!(f, ": {} events", worker.entries.len())?;
+
+            for entry in worker.entries.iter() {
+                writeln!(f, "  {:?} ago: {}", entry.age, entry.event)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The recent events of one async worker thread.
+#[derive(Clone, Debug)]
+pub struct WorkerFlightRecord {
+    processor_id: usize,
+    entries: Box<[FlightRecordEntry]>,
+    overwritten: u64,
+}
+
+impl WorkerFlightRecord {
+    /// The processor the worker is assigned to.
+    pub fn processor_id(&self) -> usize {
+        self.processor_id
+    }
+
+    /// The recorded events, oldest first.
+    pub fn entries(&self) -> &[FlightRecordEntry] {
+        &self.entries
+    }
+
+    /// How many events were overwritten by newer ones because the recorder was full.
+    pub fn overwritten(&self) -> u64 {
+        self.overwritten
+    }
+}
+
+/// An event in a [`FlightRecord`].
+#[derive(Clone, Copy, Debug)]
+pub struct FlightRecordEntry {
+    age: Duration,
+    event: FlightEvent,
+}
+
+impl FlightRecordEntry {
+    /// How long before the record was taken the event happened. The precision is that of the
+    /// system tick, typically 15-20 milliseconds.
+    pub fn age(&self) -> Duration {
+        self.age
+    }
+
+    pub fn event(&self) -> FlightEvent {
+        self.event
+    }
+}
+
+/// The flight recorder of one async worker thread.
+#[derive(Debug)]
+pub(crate) struct FlightRecorder {
+    processor_id: usize,
+    ring: Mutex<Ring>,
+}
+
+impl FlightRecorder {
+    /// Creates a recorder that keeps the specified number of most recent events. The recorder is
+    /// included in the records written on panic or Ctrl+Break for as long as it is alive.
+    pub fn new(processor_id: usize, capacity: usize) -> Arc<Self> {
+        assert!(capacity > 0, "flight recorder capacity must be nonzero");
+
+        let recorder = Arc::new(Self {
+            processor_id,
+            ring: Mutex::new(Ring {
+                entries: Vec::with_capacity(capacity),
+                capacity,
+                next: 0,
+                overwritten: 0,
+            }),
+        });
+
+        let mut recorders = RECORDERS.lock().expect(constants::POISONED_LOCK);
+        recorders.retain(|x| x.strong_count() > 0);
+        recorders.push(Arc::downgrade(&recorder));
+
+        recorder
+    }
+
+    fn record(&self, event: FlightEvent) {
+        let at = LowPrecisionInstant::now();
+
+        self.ring
+            .lock()
+            .expect(constants::POISONED_LOCK)
+            .push(at, event);
+    }
+
+    pub fn snapshot(&self) -> WorkerFlightRecord {
+        self.ring
+            .lock()
+            .expect(constants::POISONED_LOCK)
+            .snapshot(self.processor_id)
+    }
+
+    /// Takes a snapshot unless the recorder is in use, for use in situations where we must not
+    /// block or panic (e.g. when the current thread panicked while recording).
+    fn try_snapshot(&self) -> Option<WorkerFlightRecord> {
+        self.ring
+            .try_lock()
+            .ok()
+            .map(|ring| ring.snapshot(self.processor_id))
+    }
+}
+
+#[derive(Debug)]
+struct Ring {
+    entries: Vec<(LowPrecisionInstant, FlightEvent)>,
+    capacity: usize,
+
+    // Once the ring is full, the index of the oldest entry, which is the next one overwritten.
+    next: usize,
+
+    overwritten: u64,
+}
+
+impl Ring {
+    fn push(&mut self, at: LowPrecisionInstant, event: FlightEvent) {
+        if self.entries.len() < self.capacity {
+            self.entries.push((at, event));
+            return;
+        }
+
+        self.entries[self.next] = (at, event);
+        self.next = (self.next + 1) % self.capacity;
+        self.overwritten += 1;
+    }
+
+    fn snapshot(&self, processor_id: usize) -> WorkerFlightRecord {
+        let now = LowPrecisionInstant::now();
+
+        let (newer, older) = self.entries.split_at(self.next);
+
+        let entries = older
+            .iter()
+            .chain(newer)
+            .map(|(at, event)| FlightRecordEntry {
+                age: now.duration_since(*at),
+                event: *event,
+            })
+            .collect();
+
+        WorkerFlightRecord {
+            processor_id,
+            entries,
+            overwritten: self.overwritten,
+        }
+    }
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<Arc<FlightRecorder>>> = const { RefCell::new(None) };
+}
+
+/// Sets the recorder for events on the current thread. `None` disables recording.
+pub(crate) fn set_current(recorder: Option<Arc<FlightRecorder>>) {
+    CURRENT.with_borrow_mut(|x| *x = recorder);
+}
+
+pub(crate) fn current() -> Option<Arc<FlightRecorder>> {
+    CURRENT.with_borrow(Clone::clone)
+}
+
+/// Records an event in the recorder of the current thread, if it has one.
+pub(crate) fn record(event: FlightEvent) {
+    CURRENT.with_borrow(|recorder| {
+        if let Some(recorder) = recorder {
+            recorder.record(event);
+        }
+    });
+}
+
+/// Configures the process to append the flight records of all live recorders to the specified file
+/// when a thread panics or when Ctrl+Break is pressed in the console of the process.
+///
+/// The panic hook (shared with the crash reports of the metrics module) and the console control
+/// handler are installed once per process. The panic hook chains to any previously installed hook
+/// and the console control handler lets the event proceed to the next handler, so the existing
+/// behavior is preserved. Calling this again merely changes the target path.
+pub(crate) fn set_dump_path(path: PathBuf) {
+    *DUMP_PATH.lock().expect(constants::POISONED_LOCK) = Some(path);
+
+    install_panic_hook();
+
+    INSTALL_CTRL_HANDLER.call_once(|| {
+        // SAFETY: The handler is a valid function for the lifetime of the process. We never
+        // remove it, so it does not matter which thread installs it.
+        if let Err(e) = unsafe { SetConsoleCtrlHandler(Some(handle_ctrl_event), TRUE) } {
+            event!(
+                Level::WARN,
+                message = "failed to install console control handler for flight records",
+                error = %e
+            );
+        }
+    });
+}
+
+/// Appends the flight records to the dump file, if one has been configured. Called from the panic
+/// hook of the process.
+pub(crate) fn dump_on_panic(info: &PanicHookInfo<'_>) {
+    let thread = thread::current();
+    let thread_name = thread.name().unwrap_or("<unnamed>");
+    let location = info
+        .location()
+        .map_or_else(String::new, |x| format!(" at {x}"));
+
+    write_dump(&format!("thread '{thread_name}' panicked{location}"));
+}
+
+pub(crate) fn write_dump(reason: &str) {
+    // We must not panic in here, as a panic inside a panic hook aborts the process. Everything is
+    // best-effort - if we cannot write the record, we just move on.
+    let Ok(path) = DUMP_PATH.try_lock() else {
+        return;
+    };
+
+    let Some(path) = path.as_ref() else {
+        return;
+    };
+
+    let Ok(recorders) = RECORDERS.try_lock() else {
+        return;
+    };
+
+    let workers = recorders
+        .iter()
+        .filter_map(Weak::upgrade)
+        .filter_map(|recorder| recorder.try_snapshot())
+        .collect();
+
+    drop(recorders);
+
+    let record = FlightRecord::new(workers);
+
+    let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) else {
+        return;
+    };
+
+    let timestamp = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|x| x.as_secs())
+        .unwrap_or_default();
+
+    _ = writeln!(
+        file,
+        "=== flight record: {reason} at unix time {timestamp} ===\n{record}"
+    );
+    _ = file.flush();
+}
+
+/// Called by the operating system on a thread of its own whenever a console control event occurs.
+unsafe extern "system" fn handle_ctrl_event(ctrl_type: u32) -> BOOL {
+    if ctrl_type == CTRL_BREAK_EVENT {
+        write_dump("Ctrl+Break");
+    }
+
+    // We only observe the event, so it proceeds to the next handler (or the default treatment).
+    FALSE
+}
+
+static DUMP_PATH: Mutex<Option<PathBuf>> = Mutex::new(None);
+static INSTALL_CTRL_HANDLER: Once = Once::new();
+
+// All the recorders in the process, so the whole process can be dumped from a panic hook or a
+// console control handler, which know nothing about runtimes.
+static RECORDERS: Mutex<Vec<Weak<FlightRecorder>>> = Mutex::new(Vec::new());
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ring_keeps_most_recent_events_in_order() {
+        let recorder = FlightRecorder::new(7, 3);
+
+        for latency_ms in 0..5 {
+            recorder.record(FlightEvent::TimerFired {
+                latency: Duration::from_millis(latency_ms),
+            });
+        }
+
+        let record = recorder.snapshot();
+
+        assert_eq!(record.processor_id(), 7);
+        assert_eq!(record.overwritten(), 2);
+
+        let latencies = record
+            .entries()
+            .iter()
+            .map(|entry| match entry.event() {
+                FlightEvent::TimerFired { latency } => latency.as_millis(),
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(latencies, vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn record_without_recorder_is_ignored() {
+        set_current(None);
+
+        // Nothing to observe - this just must not fail.
+        record(FlightEvent::TimerFired {
+            latency: Duration::ZERO,
+        });
+
+        assert!(current().is_none());
+    }
+}
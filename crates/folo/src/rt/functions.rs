@@ -1,11 +1,14 @@
 //! Top-level free functions that can be called to manipulate the Folo runtime.
 
 use super::SynchronousTaskType;
-use crate::rt::{
-    current_async_agent, current_runtime, ready_after_poll::ReadyAfterPoll, LocalJoinHandle,
-    RemoteJoinHandle,
+use crate::{
+    rt::{
+        current_async_agent, current_runtime, ready_after_poll::ReadyAfterPoll, LocalJoinHandle,
+        RemoteJoinHandle, SpawnError, TaskMeta, WorkerContext,
+    },
+    time::Deadline,
 };
-use std::future::Future;
+use std::{future::Future, sync::Arc};
 
 /// Spawns a task to execute a future on the current async worker thread.
 ///
@@ -17,7 +20,72 @@ where
     F: Future<Output = R> + 'static,
     R: 'static,
 {
-    current_async_agent::with(|agent| agent.spawn(future))
+    current_async_agent::with(|agent| agent.spawn(TaskMeta::anonymous(), future))
+}
+
+/// Spawns a task to execute a future on the current async worker thread, unless the task count
+/// limits of the runtime have been reached, in which case the future is dropped and an error is
+/// returned. Use this to shed load at the point where work enters the system (e.g. when accepting
+/// connections) instead of letting the task queues grow without bound.
+///
+/// The limits are configured via [`RuntimeBuilder::max_tasks_per_worker()`][1] and
+/// [`RuntimeBuilder::max_tasks()`][2]. Tasks spawned via `spawn()` and friends are never refused
+/// but do count towards the limits.
+///
+/// # Panics
+///
+/// Panics if the current thread is not an async worker thread owned by a Folo runtime.
+///
+/// [1]: crate::rt::RuntimeBuilder::max_tasks_per_worker
+/// [2]: crate::rt::RuntimeBuilder::max_tasks
+pub fn try_spawn<F, R>(future: F) -> Result<LocalJoinHandle<R>, SpawnError>
+where
+    F: Future<Output = R> + 'static,
+    R: 'static,
+{
+    current_async_agent::with(|agent| agent.try_spawn(TaskMeta::anonymous(), future))
+}
+
+/// Spawns a task to execute a future on the current async worker thread, cancelling it if it has
+/// not completed by the deadline. A cancelled task reports [`JoinError::DeadlineExceeded`][1] via
+/// `result()` on its join handle.
+///
+/// The future is not polled again after the deadline passes and is dropped like the future of an
+/// aborted task. This bounds the time spent on work whose result nobody is waiting for anymore,
+/// such as handling a request whose client has already timed out.
+///
+/// The task runs under the deadline, so the code it runs can obtain it via
+/// [`Deadline::current()`][2] and nested operations inherit the remaining budget. If this is
+/// called from code that runs under an earlier deadline, the task inherits that deadline instead.
+///
+/// # Panics
+///
+/// Panics if the current thread is not an async worker thread owned by a Folo runtime.
+///
+/// [1]: crate::rt::JoinError::DeadlineExceeded
+/// [2]: crate::time::Deadline::current
+pub fn spawn_with_deadline<F, R>(deadline: impl Into<Deadline>, future: F) -> LocalJoinHandle<R>
+where
+    F: Future<Output = R> + 'static,
+    R: 'static,
+{
+    current_async_agent::with(|agent| {
+        agent.spawn_with_deadline(TaskMeta::anonymous(), deadline.into(), future)
+    })
+}
+
+/// Spawns a named task to execute a future on the current async worker thread. The name
+/// identifies the task in diagnostic output such as panic logs and crash reports.
+///
+/// # Panics
+///
+/// Panics if the current thread is not an async worker thread owned by a Folo runtime.
+pub fn spawn_named<F, R>(name: impl Into<Arc<str>>, future: F) -> LocalJoinHandle<R>
+where
+    F: Future<Output = R> + 'static,
+    R: 'static,
+{
+    current_async_agent::with(|agent| agent.spawn(TaskMeta::new(Some(name.into())), future))
 }
 
 /// Spawns a task to execute a future on any worker thread owned by the same Folo runtime
@@ -37,6 +105,67 @@ where
     current_runtime::with(|runtime| runtime.spawn_on_any(future_fn))
 }
 
+/// Spawns a named task to execute a future on any worker thread owned by the same Folo runtime
+/// as the current thread. The future is provided by a closure. The name identifies the task in
+/// diagnostic output such as panic logs and crash reports.
+///
+/// The future itself does not have to be thread-safe. However, the closure must be.
+///
+/// # Panics
+///
+/// Panics if the current thread is not owned by a Folo runtime.
+pub fn spawn_on_any_named<FN, F, R>(name: impl Into<Arc<str>>, future_fn: FN) -> RemoteJoinHandle<R>
+where
+    FN: FnOnce() -> F + Send + 'static,
+    F: Future<Output = R> + 'static,
+    R: Send + 'static,
+{
+    current_runtime::with(|runtime| runtime.spawn_on_any_named(name, future_fn))
+}
+
+/// Spawns a task to execute a future on a specific async worker thread of the Folo runtime that
+/// owns the current thread. The future is provided by a closure. Workers are identified by their
+/// index, from zero to `worker_count() - 1`.
+///
+/// The future itself does not have to be thread-safe. However, the closure must be.
+///
+/// # Panics
+///
+/// Panics if the current thread is not owned by a Folo runtime or if the worker index is out of
+/// bounds.
+pub fn spawn_on<FN, F, R>(worker_index: usize, future_fn: FN) -> RemoteJoinHandle<R>
+where
+    FN: FnOnce() -> F + Send + 'static,
+    F: Future<Output = R> + 'static,
+    R: Send + 'static,
+{
+    current_runtime::with(|runtime| runtime.spawn_on(worker_index, future_fn))
+}
+
+/// Returns the number of async worker threads of the Folo runtime that owns the current thread.
+/// Workers are identified by their index, from zero to `worker_count() - 1`.
+///
+/// # Panics
+///
+/// Panics if the current thread is not owned by a Folo runtime.
+pub fn worker_count() -> usize {
+    current_runtime::with(|runtime| runtime.worker_count())
+}
+
+/// Returns the index of the current async worker thread, as used by [`spawn_on()`].
+///
+/// Returns `None` if the current thread is not an async worker thread.
+pub fn current_worker_index() -> Option<usize> {
+    let processor_id = current_async_agent::try_with(|agent| agent.processor_id())?;
+
+    current_runtime::with(|runtime| {
+        runtime
+            .processor_ids()
+            .iter()
+            .position(|id| *id == processor_id)
+    })
+}
+
 /// Spawns a task to execute a future on every worker thread.
 ///
 /// There are two layers of callbacks involved here, with the overall sequence being:
@@ -58,6 +187,38 @@ where
     current_runtime::with(|runtime| runtime.spawn_on_all(clone_future_fn))
 }
 
+/// Executes a job once on every async worker thread of the Folo runtime that owns the current
+/// thread, returning a future that completes with the results of all the workers once they have
+/// all finished, in worker index order.
+///
+/// The job is a closure that receives a [`WorkerContext`] identifying the worker and returns the
+/// future to execute on that worker. The future itself does not have to be thread-safe.
+///
+/// # Panics
+///
+/// Panics if the current thread is not owned by a Folo runtime.
+///
+/// # Example
+///
+/// ```
+/// use folo::rt::for_each_worker;
+///
+/// #[folo::main]
+/// async fn main() {
+///     let worker_indexes = for_each_worker(|ctx| async move { ctx.worker_index() }).await;
+///
+///     assert_eq!(worker_indexes[0], 0);
+/// }
+/// ```
+pub fn for_each_worker<FN, F, R>(job: FN) -> impl Future<Output = Box<[R]>>
+where
+    FN: Fn(WorkerContext) -> F + Send + Sync + 'static,
+    F: Future<Output = R> + 'static,
+    R: Send + 'static,
+{
+    current_runtime::with(|runtime| runtime.for_each_worker(job))
+}
+
 /// Spawns a task on a synchronous worker thread suitable for the specific type of synchronous
 /// work requested, returning the result via a join handle suitable for use in asynchronous
 /// tasks.
@@ -80,9 +241,41 @@ where
     current_runtime::with(|runtime| runtime.spawn_sync_on_any(task_type, f))
 }
 
+/// Registers an async cleanup function that the Folo runtime that owns the current thread executes
+/// during graceful shutdown, before in-flight tasks are drained. See
+/// [`RuntimeClient::on_shutdown()`][crate::rt::RuntimeClient::on_shutdown].
+///
+/// # Panics
+///
+/// Panics if the current thread is not owned by a Folo runtime.
+pub fn on_shutdown<FN, F>(hook: FN)
+where
+    FN: FnOnce() -> F + Send + 'static,
+    F: Future<Output = ()> + 'static,
+{
+    current_runtime::with(|runtime| runtime.on_shutdown(hook))
+}
+
 /// Yields control back to the async task runtime to allow other tasks to run.
-/// There is no guarantee that other tasks will run in any particular order.
-/// Even the same task that called this may be scheduled again immediately.
+///
+/// The current task is rescheduled to the back of its worker's queue, so every other task that is
+/// ready to run on the same worker gets polled before the current task resumes. Long-running
+/// cooperative loops (e.g. parsers or compression) should call this periodically to avoid
+/// monopolizing the worker thread.
+///
+/// There is no guarantee that other tasks will run in any particular order. If no other tasks are
+/// ready to run, the current task resumes immediately.
 pub fn yield_now() -> impl Future<Output = ()> {
     ReadyAfterPoll::default()
 }
+
+/// Returns the NUMA node of the processor that the current async worker thread is assigned to.
+///
+/// Memory that a worker allocates and touches first is placed on its own NUMA node by the
+/// operating system, so data that is partitioned by worker (e.g. via `ShardedKv` or thread-local
+/// state) is automatically node-local. Use this to align other data partitioning with the workers.
+///
+/// Returns `None` if the current thread is not an async worker thread or if the node is not known.
+pub fn current_numa_node() -> Option<u16> {
+    current_async_agent::try_with(|agent| agent.numa_node()).flatten()
+}
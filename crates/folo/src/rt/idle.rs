@@ -0,0 +1,90 @@
+/// Determines what an async worker thread does when it has no tasks ready to execute. Configured
+/// via [`RuntimeBuilder::idle_strategy()`][crate::rt::RuntimeBuilder::idle_strategy].
+///
+/// A worker that sleeps uses no processor time while idle but takes some time to wake up when new
+/// work arrives. A worker that keeps polling reacts to new work immediately but keeps its processor
+/// busy all the time. Which tradeoff is right depends on the deployment.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "config",
+    derive(serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum IdleStrategy {
+    /// The worker immediately sleeps until an I/O completion or a wakeup from another thread
+    /// arrives. This is the default and is appropriate when processors are shared with other
+    /// processes.
+    #[default]
+    Park,
+
+    /// The worker first keeps polling for new work in a tight loop for `spin_cycles` cycles, then
+    /// yields its time slice to other threads between polls for `yield_cycles` cycles and only then
+    /// sleeps like with [`Park`][Self::Park]. Work that arrives soon after the worker runs out of
+    /// work is picked up without the latency of waking up a sleeping thread.
+    SpinThenPark { spin_cycles: u32, yield_cycles: u32 },
+
+    /// The worker never sleeps and keeps polling for new work in a tight loop, using 100% of its
+    /// processor at all times. This offers the lowest latency but is only appropriate when the
+    /// worker threads have dedicated processors.
+    BusyPoll,
+}
+
+impl IdleStrategy {
+    /// Decides what to do in an idle cycle, given how many consecutive cycles before this one were
+    /// also idle.
+    pub(crate) fn action(&self, idle_cycles: u32) -> IdleAction {
+        match *self {
+            IdleStrategy::Park => IdleAction::Park,
+            IdleStrategy::SpinThenPark {
+                spin_cycles,
+                yield_cycles,
+            } => {
+                if idle_cycles < spin_cycles {
+                    IdleAction::Spin
+                } else if idle_cycles - spin_cycles < yield_cycles {
+                    IdleAction::Yield
+                } else {
+                    IdleAction::Park
+                }
+            }
+            IdleStrategy::BusyPoll => IdleAction::Spin,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum IdleAction {
+    /// Poll again immediately, hinting to the processor that we are in a spin loop.
+    Spin,
+
+    /// Yield the time slice to other threads, then poll again.
+    Yield,
+
+    /// Sleep until there is new work or the periodic cross-thread work check is due.
+    Park,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spin_then_park_progresses_through_phases() {
+        let strategy = IdleStrategy::SpinThenPark {
+            spin_cycles: 2,
+            yield_cycles: 1,
+        };
+
+        assert_eq!(strategy.action(0), IdleAction::Spin);
+        assert_eq!(strategy.action(1), IdleAction::Spin);
+        assert_eq!(strategy.action(2), IdleAction::Yield);
+        assert_eq!(strategy.action(3), IdleAction::Park);
+        assert_eq!(strategy.action(u32::MAX), IdleAction::Park);
+    }
+
+    #[test]
+    fn park_and_busy_poll_are_fixed() {
+        assert_eq!(IdleStrategy::Park.action(0), IdleAction::Park);
+        assert_eq!(IdleStrategy::BusyPoll.action(u32::MAX), IdleAction::Spin);
+    }
+}
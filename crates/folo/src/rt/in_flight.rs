@@ -0,0 +1,45 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+/// Counts the tasks of a runtime that have been spawned but have not yet completed, both async and
+/// sync, across all the worker threads. See `RuntimeClient::shutdown()`.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct InFlightTasks {
+    count: Arc<AtomicUsize>,
+}
+
+impl InFlightTasks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counts a new task, returning a guard that must be kept alive for as long as the task is.
+    /// This must be called before the task is enqueued anywhere, so the task is already counted
+    /// by the time any worker can pick it up (and spawn more tasks from it).
+    pub fn track(&self) -> InFlightTask {
+        self.count.fetch_add(1, Ordering::Relaxed);
+
+        InFlightTask {
+            count: Arc::clone(&self.count),
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.count.load(Ordering::Acquire)
+    }
+}
+
+/// Keeps a task counted as in flight. Dropped together with the task, whether it completes, is
+/// aborted or is rejected without ever executing.
+#[derive(Debug)]
+pub(crate) struct InFlightTask {
+    count: Arc<AtomicUsize>,
+}
+
+impl Drop for InFlightTask {
+    fn drop(&mut self) {
+        self.count.fetch_sub(1, Ordering::Release);
+    }
+}
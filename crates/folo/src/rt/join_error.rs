@@ -0,0 +1,47 @@
+use std::any::Any;
+use thiserror::Error;
+
+/// The reason why a task failed to produce a result, as reported by its join handle.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum JoinError {
+    /// The task was aborted via its join handle before it completed.
+    #[error("task was cancelled before it completed")]
+    Cancelled,
+
+    /// The task panicked. Contains the panic payload, which can be used to resume the panic via
+    /// `std::panic::resume_unwind()`. Only reported if the runtime is configured with
+    /// `PanicPolicy::DeliverToJoinHandle`.
+    #[error("task panicked")]
+    Panic(Box<dyn Any + Send>),
+
+    /// The task did not complete before the deadline it was spawned with (see
+    /// `spawn_with_deadline()`) and was cancelled.
+    #[error("task did not complete before its deadline")]
+    DeadlineExceeded,
+}
+
+impl JoinError {
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self, Self::Cancelled)
+    }
+
+    pub fn is_panic(&self) -> bool {
+        matches!(self, Self::Panic(_))
+    }
+
+    pub fn is_deadline_exceeded(&self) -> bool {
+        matches!(self, Self::DeadlineExceeded)
+    }
+
+    /// Returns the panic payload if the task panicked.
+    pub fn into_panic(self) -> Option<Box<dyn Any + Send>> {
+        match self {
+            Self::Panic(payload) => Some(payload),
+            _ => None,
+        }
+    }
+}
+
+/// The result of a task as observed via `LocalJoinHandle::result()` or `RemoteJoinHandle::result()`.
+pub type JoinResult<R> = Result<R, JoinError>;
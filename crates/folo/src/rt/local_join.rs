@@ -1,20 +1,57 @@
-use crate::sync::once_event;
+use crate::{
+    rt::{abort::AbortState, JoinError, JoinResult, TaskId},
+    sync::once_event,
+};
 use futures::FutureExt;
 use negative_impl::negative_impl;
-use std::{future::Future, pin::Pin, task};
+use std::{future::Future, panic, pin::Pin, sync::Arc, task};
 
 /// Allows a unit of work to be awaited and its result to be observed on the same thread as it is
 /// scheduled on.
 ///
 /// Awaiting this is optional - the task will continue even if you drop the join handle.
+///
+/// Awaiting the handle directly yields the result of the task. If the task may have been aborted,
+/// use `result()` instead, which reports cancellation as `JoinError::Cancelled`. If the task
+/// panicked and the panic was delivered to the join handle, awaiting directly resumes the panic.
 #[derive(Debug)]
 pub struct LocalJoinHandle<R> {
-    rx: once_event::EmbeddedReceiver<R>,
+    rx: once_event::EmbeddedReceiver<JoinResult<R>>,
+    abort: Arc<AbortState>,
+    id: TaskId,
 }
 
 impl<R> LocalJoinHandle<R> {
-    pub(crate) fn new(rx: once_event::EmbeddedReceiver<R>) -> Self {
-        Self { rx }
+    pub(crate) fn new(
+        rx: once_event::EmbeddedReceiver<JoinResult<R>>,
+        abort: Arc<AbortState>,
+        id: TaskId,
+    ) -> Self {
+        Self { rx, abort, id }
+    }
+
+    /// Returns the ID of the task, as seen in diagnostic output.
+    pub fn id(&self) -> TaskId {
+        self.id
+    }
+
+    /// Requests the task to be aborted. The task is dropped the next time it would be polled,
+    /// which is at its next yield point (any `.await` that does not complete immediately). If the
+    /// task has already completed, this has no effect.
+    ///
+    /// After aborting, the handle resolves to `JoinError::Cancelled` when awaited via `result()`.
+    pub fn abort(&self) {
+        self.abort.request();
+    }
+
+    /// Returns a future that resolves to the result of the task or to the reason why the task
+    /// failed to produce a result.
+    pub fn result(mut self) -> impl Future<Output = JoinResult<R>> {
+        futures::future::poll_fn(move |cx| self.rx.poll_unpin(cx))
+    }
+
+    pub(crate) fn abort_state(&self) -> Arc<AbortState> {
+        Arc::clone(&self.abort)
     }
 }
 
@@ -22,7 +59,22 @@ impl<R> Future for LocalJoinHandle<R> {
     type Output = R;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> task::Poll<Self::Output> {
-        self.rx.poll_unpin(cx)
+        self.rx.poll_unpin(cx).map(unwrap_join_result)
+    }
+}
+
+/// Used when awaiting a join handle directly, which implies that the caller expects the task to
+/// always produce a result.
+pub(crate) fn unwrap_join_result<R>(result: JoinResult<R>) -> R {
+    match result {
+        Ok(result) => result,
+        Err(JoinError::Cancelled) => {
+            panic!("awaited a task that was aborted; use `result()` to observe cancellation")
+        }
+        Err(JoinError::Panic(payload)) => panic::resume_unwind(payload),
+        Err(JoinError::DeadlineExceeded) => {
+            panic!("awaited a task that exceeded its deadline; use `result()` to observe timeouts")
+        }
     }
 }
 
@@ -0,0 +1,85 @@
+use crate::rt::{current_async_agent, current_runtime, TaskMeta};
+use futures::{
+    future::{FutureObj, LocalFutureObj},
+    task::{LocalSpawn, Spawn, SpawnError},
+};
+use negative_impl::negative_impl;
+
+/// Spawns tasks on the current async worker thread via the `Spawn` and `LocalSpawn` traits of the
+/// `futures` crate, for use with libraries that are generic over the spawner instead of being tied
+/// to a specific runtime.
+///
+/// This is the equivalent of [`spawn()`][crate::rt::spawn] - the spawned futures do not need to be
+/// `Send`. To spawn `Send` futures on any worker thread, use the `Spawn` implementation of
+/// [`RuntimeClient`][crate::rt::RuntimeClient] instead.
+///
+/// The spawned tasks are detached - their results (always `()`) cannot be awaited. Spawning fails
+/// if the runtime is shutting down.
+///
+/// # Example
+///
+/// ```
+/// use folo::rt::LocalSpawner;
+/// use futures::task::LocalSpawnExt;
+///
+/// #[folo::main]
+/// async fn main() {
+///     let handle = LocalSpawner::current()
+///         .spawn_local_with_handle(async { 42 })
+///         .unwrap();
+///
+///     assert_eq!(handle.await, 42);
+/// }
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct LocalSpawner {}
+
+impl LocalSpawner {
+    /// Returns a spawner for the current async worker thread.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the current thread is not an async worker thread owned by a Folo runtime.
+    pub fn current() -> Self {
+        assert!(
+            current_async_agent::is_some(),
+            "LocalSpawner can only be used on an async worker thread owned by a Folo runtime"
+        );
+
+        Self {}
+    }
+}
+
+#[negative_impl]
+impl !Send for LocalSpawner {}
+#[negative_impl]
+impl !Sync for LocalSpawner {}
+
+impl LocalSpawn for LocalSpawner {
+    fn spawn_local_obj(&self, future: LocalFutureObj<'static, ()>) -> Result<(), SpawnError> {
+        self.status_local()?;
+
+        // The task is detached, so we have no use for the join handle.
+        _ = current_async_agent::with(|agent| agent.spawn(TaskMeta::anonymous(), future));
+
+        Ok(())
+    }
+
+    fn status_local(&self) -> Result<(), SpawnError> {
+        if current_runtime::with(|runtime| runtime.is_stopping()) {
+            return Err(SpawnError::shutdown());
+        }
+
+        Ok(())
+    }
+}
+
+impl Spawn for LocalSpawner {
+    fn spawn_obj(&self, future: FutureObj<'static, ()>) -> Result<(), SpawnError> {
+        self.spawn_local_obj(future.into())
+    }
+
+    fn status(&self) -> Result<(), SpawnError> {
+        self.status_local()
+    }
+}
@@ -1,6 +1,5 @@
 use crate::{
-    rt::erased_async_task::ErasedResultAsyncTask,
-    rt::LocalJoinHandle,
+    rt::{erased_async_task::ErasedResultAsyncTask, task_frames, TaskMeta},
     sync::once_event::{self, OnceEvent, OnceEventEmbeddedStorage},
 };
 use negative_impl::negative_impl;
@@ -26,6 +25,8 @@ where
     // future is dropped, releasing critical references that may be blocking runtime shutdown.
     future: RefCell<Option<F>>,
 
+    meta: TaskMeta,
+
     // Value is consumed after the result is set or when the task itself is dropped.
     result_tx: Option<once_event::EmbeddedSender<R>>,
 
@@ -52,12 +53,13 @@ where
     /// The caller is responsible for not dropping the LocalTask as long as there may be someone
     /// awaiting its result. You can verify this by calling `.is_inert()` - dropping is safe only
     /// when this is true.
-    pub unsafe fn new(future: F) -> Pin<Box<Self>> {
+    pub unsafe fn new(meta: TaskMeta, future: F) -> Pin<Box<Self>> {
         // A LocalTask is always pinned, as this is required by the OnceEvent embedded into it.
 
         // We initialize in two steps, initializing the OnceEvent after we are pinned.
-        let mut instance = Box::pin(LocalTask {
+        let mut instance = task_frames::allocate_pinned(LocalTask {
             future: RefCell::new(Some(future)),
+            meta,
             result_tx: None,
             result_rx: None,
             result: OnceEvent::new_embedded_storage_single(),
@@ -78,13 +80,11 @@ where
         instance
     }
 
-    pub fn join_handle(self: Pin<&mut Self>) -> LocalJoinHandle<R> {
-        LocalJoinHandle::new(
-            self.project()
-                .result_rx
-                .take()
-                .expect("join handle for task can only be acquired once"),
-        )
+    pub fn take_result_rx(self: Pin<&mut Self>) -> once_event::EmbeddedReceiver<R> {
+        self.project()
+            .result_rx
+            .take()
+            .expect("join handle for task can only be acquired once")
     }
 
     pub fn is_inert(&self) -> bool {
@@ -160,6 +160,19 @@ where
     fn clear(&self) {
         *self.future.borrow_mut() = None;
     }
+
+    fn is_spawned_by_runtime(&self) -> bool {
+        // Local tasks can only be spawned on the worker thread that executes them.
+        true
+    }
+
+    fn reject(&self) {
+        unreachable!("local tasks are accepted as soon as they are spawned and never rejected");
+    }
+
+    fn meta(&self) -> &TaskMeta {
+        &self.meta
+    }
 }
 
 // Perhaps already implied but let's be super explicit here.
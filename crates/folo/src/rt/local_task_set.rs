@@ -0,0 +1,177 @@
+use crate::{
+    metrics::{observe, Event, EventBuilder},
+    rt::coop,
+};
+use futures::{
+    stream::{FusedStream, FuturesUnordered},
+    Stream, StreamExt,
+};
+use negative_impl::negative_impl;
+use std::{
+    fmt::{self, Debug, Formatter},
+    future::Future,
+    pin::Pin,
+    task,
+};
+
+/// Executes a large number of homogeneous futures as part of the current task, polling only the
+/// ones that have been woken up, and yields their results as they complete.
+///
+/// This is a lightweight alternative to spawning each future as a separate task, for cases where
+/// the per-task overhead matters (e.g. one future per connection, with thousands of connections
+/// per worker). The futures do not need to be `Send` or `'static` and they are stored inline,
+/// without being boxed. The futures only make progress while the set is being polled, i.e. while
+/// the owning task is awaiting the next result.
+///
+/// Results are obtained via the `Stream` implementation (or the inherent [`next()`][Self::next]).
+/// Every result consumes one unit of the cooperative scheduling budget of the owning task, so a
+/// set whose futures are always ready does not starve the other tasks on the worker thread.
+///
+/// # Example
+///
+/// ```
+/// use folo::rt::{yield_now, LocalTaskSet};
+///
+/// #[folo::main]
+/// async fn main() {
+///     let mut set = LocalTaskSet::new();
+///
+///     for id in 0..1000 {
+///         set.push(async move {
+///             yield_now().await;
+///             id
+///         });
+///     }
+///
+///     let mut sum = 0;
+///
+///     while let Some(id) = set.next().await {
+///         sum += id;
+///     }
+///
+///     assert_eq!(sum, 499500);
+/// }
+/// ```
+pub struct LocalTaskSet<F> {
+    futures: FuturesUnordered<F>,
+}
+
+impl<F> LocalTaskSet<F>
+where
+    F: Future,
+{
+    pub fn new() -> Self {
+        Self {
+            futures: FuturesUnordered::new(),
+        }
+    }
+
+    /// Adds a future to the set. It is first polled the next time the set is polled.
+    pub fn push(&mut self, future: F) {
+        observe!(LOCAL_TASK_SET_FUTURES.observe_unit());
+
+        self.futures.push(future);
+    }
+
+    /// The number of futures in the set that have not yet completed.
+    pub fn len(&self) -> usize {
+        self.futures.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.futures.is_empty()
+    }
+
+    /// Drops all the futures in the set without waiting for them to complete.
+    pub fn clear(&mut self) {
+        self.futures.clear();
+    }
+
+    /// Waits for the next future in the set to complete, returning its result. Returns `None` if
+    /// the set is empty.
+    pub async fn next(&mut self) -> Option<F::Output> {
+        StreamExt::next(self).await
+    }
+}
+
+impl<F> Default for LocalTaskSet<F>
+where
+    F: Future,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F> Stream for LocalTaskSet<F>
+where
+    F: Future,
+{
+    type Item = F::Output;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+    ) -> task::Poll<Option<Self::Item>> {
+        // An empty set does not produce any results, so there is nothing to spend the budget on.
+        if self.futures.is_empty() {
+            return task::Poll::Ready(None);
+        }
+
+        coop::poll_budgeted(cx, |cx| self.futures.poll_next_unpin(cx))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.futures.len(), Some(self.futures.len()))
+    }
+}
+
+impl<F> FusedStream for LocalTaskSet<F>
+where
+    F: Future,
+{
+    fn is_terminated(&self) -> bool {
+        // An empty set returns `None` without polling anything, until more futures are pushed.
+        self.futures.is_empty()
+    }
+}
+
+impl<F> Extend<F> for LocalTaskSet<F>
+where
+    F: Future,
+{
+    fn extend<I: IntoIterator<Item = F>>(&mut self, iter: I) {
+        for future in iter {
+            self.push(future);
+        }
+    }
+}
+
+impl<F> FromIterator<F> for LocalTaskSet<F>
+where
+    F: Future,
+{
+    fn from_iter<I: IntoIterator<Item = F>>(iter: I) -> Self {
+        let mut set = Self::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl<F> Debug for LocalTaskSet<F> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LocalTaskSet")
+            .field("len", &self.futures.len())
+            .finish()
+    }
+}
+
+#[negative_impl]
+impl<F> !Send for LocalTaskSet<F> {}
+#[negative_impl]
+impl<F> !Sync for LocalTaskSet<F> {}
+
+thread_local! {
+    static LOCAL_TASK_SET_FUTURES: Event = EventBuilder::new("rt_local_task_set_futures")
+        .build();
+}
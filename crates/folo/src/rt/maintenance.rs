@@ -0,0 +1,111 @@
+use crate::{
+    metrics::{observe, Event, EventBuilder},
+    rt::task_frames,
+};
+use std::{
+    fmt::{self, Debug, Formatter},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// A user callback invoked on every async worker thread at each maintenance tick.
+pub(crate) type MaintenanceCallback = Arc<dyn Fn() + Send + Sync + 'static>;
+
+pub(crate) const DEFAULT_MAINTENANCE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Performs periodic housekeeping on an async worker thread: trims per-thread caches and invokes
+/// the user callback registered via
+/// [`RuntimeBuilder::on_maintenance_tick()`][crate::rt::RuntimeBuilder::on_maintenance_tick].
+///
+/// The ticker is checked once per worker loop cycle, so a tick happens between task polls, whether
+/// or not the worker is busy. It is not precise - an idle worker may notice the tick only when it
+/// next wakes up, which happens at least every few milliseconds.
+pub(crate) struct MaintenanceTicker {
+    interval: Duration,
+    callback: Option<MaintenanceCallback>,
+    next_tick: Option<Instant>,
+}
+
+impl MaintenanceTicker {
+    pub fn new(interval: Duration, callback: Option<MaintenanceCallback>) -> Self {
+        Self {
+            interval,
+            callback,
+            next_tick: None,
+        }
+    }
+
+    /// Performs the maintenance if a tick is due, returning whether it did. The first tick is one
+    /// interval after the first call. If the worker falls behind (e.g. due to a slow poll), missed
+    /// ticks are skipped.
+    pub fn tick_if_due(&mut self, now: Instant) -> bool {
+        let next_tick = *self.next_tick.get_or_insert(now + self.interval);
+
+        if now < next_tick {
+            return false;
+        }
+
+        self.next_tick = Some(now + self.interval);
+
+        observe!(MAINTENANCE_TICKS.observe_unit());
+
+        task_frames::trim();
+
+        if let Some(callback) = &self.callback {
+            callback();
+        }
+
+        true
+    }
+}
+
+impl Debug for MaintenanceTicker {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MaintenanceTicker")
+            .field("interval", &self.interval)
+            .field("callback", &self.callback.is_some())
+            .field("next_tick", &self.next_tick)
+            .finish()
+    }
+}
+
+thread_local! {
+    static MAINTENANCE_TICKS: Event = EventBuilder::new("rt_maintenance_ticks")
+        .build();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn callback_invoked_once_per_interval() {
+        let ticks = Arc::new(AtomicUsize::new(0));
+
+        let mut ticker = MaintenanceTicker::new(
+            Duration::from_secs(1),
+            Some({
+                let ticks = Arc::clone(&ticks);
+                Arc::new(move || {
+                    ticks.fetch_add(1, Ordering::Relaxed);
+                })
+            }),
+        );
+
+        let start = Instant::now();
+
+        assert!(!ticker.tick_if_due(start));
+        assert!(!ticker.tick_if_due(start + Duration::from_millis(500)));
+        assert_eq!(ticks.load(Ordering::Relaxed), 0);
+
+        assert!(ticker.tick_if_due(start + Duration::from_secs(1)));
+        assert!(!ticker.tick_if_due(start + Duration::from_millis(1500)));
+        assert_eq!(ticks.load(Ordering::Relaxed), 1);
+
+        // Missed ticks are skipped, not caught up on.
+        ticker.tick_if_due(start + Duration::from_secs(10));
+        ticker.tick_if_due(start + Duration::from_millis(10500));
+        assert_eq!(ticks.load(Ordering::Relaxed), 2);
+    }
+}
@@ -0,0 +1,101 @@
+use crate::{
+    metrics::{observe, Event, EventBuilder},
+    rt::current_task_id,
+};
+use std::{any::Any, cell::Cell, panic, process};
+use tracing::{event, Level};
+
+/// Determines what happens when a task panics. Configured via
+/// [`RuntimeBuilder::panic_policy()`][crate::rt::RuntimeBuilder::panic_policy].
+///
+/// The policy applies to both async tasks and synchronous tasks (`spawn_sync()`). Whatever the
+/// policy, the panic hook (including the crash report, if configured) is invoked first, as the
+/// panic happens.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum PanicPolicy {
+    /// The process is aborted. This is the safe default - a panic means a bug was detected and
+    /// continuing may expose other tasks on the same worker to broken shared state.
+    #[default]
+    Abort,
+
+    /// The panic is logged and the task is dropped. The worker thread and all other tasks on it
+    /// keep running. The join handle of the task resolves to [`JoinError::Cancelled`][1], as the
+    /// task never produced a result.
+    ///
+    /// [1]: crate::rt::JoinError::Cancelled
+    LogAndContinue,
+
+    /// The task is dropped and the panic payload is delivered to its join handle as
+    /// [`JoinError::Panic`][1]. The worker thread and all other tasks on it keep running.
+    ///
+    /// Awaiting the join handle directly resumes the panic in the awaiting task, so the panic
+    /// propagates up the chain of tasks that await each other, like it would for a function call.
+    /// Use `result()` on the join handle to handle the panic instead.
+    ///
+    /// [1]: crate::rt::JoinError::Panic
+    DeliverToJoinHandle,
+
+    /// The panic unwinds through the worker thread, terminating the worker and dropping all tasks
+    /// on it. `RuntimeClient::wait()` panics once it reaches the terminated worker thread.
+    ///
+    /// This is used by `#[folo::test]`, so a panic in a test fails that test (on the thread the
+    /// test harness is watching) instead of aborting the entire test process.
+    TerminateWorker,
+}
+
+thread_local! {
+    static POLICY: Cell<PanicPolicy> = const { Cell::new(PanicPolicy::Abort) };
+
+    static TASK_PANICS: Event = EventBuilder::new("rt_async_task_panics")
+        .build();
+}
+
+/// Sets the policy for tasks polled on the current thread.
+pub(crate) fn set_panic_policy(value: PanicPolicy) {
+    POLICY.with(|x| x.set(value));
+}
+
+/// Applies the panic policy of the current thread to a panic caught at the task boundary.
+///
+/// Returns the payload if it is to be delivered to the join handle, `None` if the join handle is
+/// to report the task as cancelled. Does not return at all if the process is to be aborted or the
+/// worker terminated.
+pub(crate) fn handle_task_panic(payload: Box<dyn Any + Send>) -> Option<Box<dyn Any + Send>> {
+    observe!(TASK_PANICS.observe_unit());
+
+    match POLICY.with(Cell::get) {
+        PanicPolicy::Abort => {
+            event!(
+                Level::ERROR,
+                message = "task panicked; aborting process",
+                task_id = ?current_task_id(),
+                panic = panic_message(&*payload)
+            );
+
+            process::abort();
+        }
+        PanicPolicy::LogAndContinue => {
+            event!(
+                Level::ERROR,
+                message = "task panicked; dropping task",
+                task_id = ?current_task_id(),
+                panic = panic_message(&*payload)
+            );
+
+            None
+        }
+        PanicPolicy::DeliverToJoinHandle => Some(payload),
+        PanicPolicy::TerminateWorker => panic::resume_unwind(payload),
+    }
+}
+
+/// Extracts the message from a panic payload, if it has one.
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&'static str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "<non-string panic payload>"
+    }
+}
@@ -0,0 +1,37 @@
+/// A preset that tunes the runtime for a class of workloads. Applied via
+/// [`RuntimeBuilder::profile()`][crate::rt::RuntimeBuilder::profile].
+///
+/// A profile sets several builder options together: the cooperative scheduling budget, the idle
+/// strategy, work stealing, the LIFO slot and the batching of I/O completions. Options set
+/// individually after the profile override the values set by the profile.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "config",
+    derive(serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Profile {
+    /// The defaults of the individual options, which work reasonably well for most workloads.
+    #[default]
+    Balanced,
+
+    /// Minimizes the time between work becoming available and it being executed, at the cost of
+    /// processor time. Workers spin for a while before going to sleep, tasks are forced to yield
+    /// sooner so that no task delays the others for long, idle workers steal queued tasks from
+    /// busy ones, the LIFO slot is limited so that a pair of tasks that keep waking each other
+    /// cannot delay the rest of the queue and I/O completions are dequeued in small batches so
+    /// that the first woken tasks can start sooner.
+    ///
+    /// Appropriate for request/response services with tight latency targets.
+    LowLatency,
+
+    /// Maximizes the amount of work done per unit of processor time, at the cost of latency.
+    /// Workers sleep as soon as they run out of work, tasks may consume more ready operations
+    /// before being forced to yield and recently woken tasks are preferentially polled while their
+    /// data is still in the processor cache. Idle workers steal queued tasks from busy ones so
+    /// that no processor goes unused while work is waiting and a backlog of I/O completions is
+    /// drained in several batches per cycle.
+    ///
+    /// Appropriate for batch processing and streaming pipelines.
+    Throughput,
+}
@@ -1,7 +1,10 @@
 use super::remote_waker::RemoteWaker;
 use crate::{
     io::IoWaker,
-    rt::{remote_result_box::RemoteResultBox, LocalJoinHandle},
+    rt::{
+        abort::AbortState, coop, local_join::unwrap_join_result,
+        remote_result_box::RemoteResultBox, JoinResult, LocalJoinHandle, TaskId,
+    },
 };
 use futures::{channel::oneshot, FutureExt};
 use std::future::Future;
@@ -13,21 +16,34 @@ use std::{pin::Pin, task};
 /// You can convert a `LocalJoinHandle` into a `RemoteJoinHandle` using `Into::into`.
 ///
 /// Awaiting this is optional - the task will continue even if you drop the join handle.
+///
+/// Awaiting the handle directly yields the result of the task. If the task may have been aborted,
+/// use `result()` instead, which reports cancellation as `JoinError::Cancelled`. If the task
+/// panicked and the panic was delivered to the join handle, awaiting directly resumes the panic.
 #[derive(Debug)]
 pub struct RemoteJoinHandle<R>
 where
     R: Send + 'static,
 {
     model: ImplementationModel<R>,
+    abort: Arc<AbortState>,
+    id: TaskId,
 }
 
 impl<R> RemoteJoinHandle<R>
 where
     R: Send + 'static,
 {
-    pub(crate) fn new(result: Arc<RemoteResultBox<R>>, io_waker: Option<IoWaker>) -> Self {
+    pub(crate) fn new(
+        result: Arc<RemoteResultBox<JoinResult<R>>>,
+        io_waker: Option<IoWaker>,
+        abort: Arc<AbortState>,
+        id: TaskId,
+    ) -> Self {
         Self {
             model: ImplementationModel::RemoteTask { result, io_waker },
+            abort,
+            id,
         }
     }
 
@@ -39,10 +55,15 @@ where
         // a new task here and allocating a channel and so forth. We could probably improve this
         // with some "direct wiring" between the two endpoints. Worry about it later - it works.
 
-        let (tx, rx) = oneshot::channel::<R>();
+        let (tx, rx) = oneshot::channel::<JoinResult<R>>();
+
+        // Aborting the remote join handle aborts the local task directly, after which the
+        // cancellation is relayed to us just like any other result would be.
+        let abort = local.abort_state();
+        let id = local.id();
 
         _ = crate::rt::spawn(async {
-            let result = local.await;
+            let result = local.result().await;
 
             // If the join handle was dropped, this will return an error, which is fine.
             _ = tx.send(result);
@@ -50,8 +71,72 @@ where
 
         Self {
             model: ImplementationModel::LocalJoinHandle { result_rx: rx },
+            abort,
+            id,
         }
     }
+
+    /// Returns the ID of the task, as seen in diagnostic output.
+    pub fn id(&self) -> TaskId {
+        self.id
+    }
+
+    /// Requests the task to be aborted. The task is dropped the next time it would be polled,
+    /// which is at its next yield point (any `.await` that does not complete immediately). If the
+    /// task has already completed, this has no effect.
+    ///
+    /// Synchronous tasks cannot be interrupted once started - aborting them only has an effect if
+    /// they have not yet started executing.
+    ///
+    /// After aborting, the handle resolves to `JoinError::Cancelled` when awaited via `result()`.
+    pub fn abort(&self) {
+        self.abort.request();
+    }
+
+    /// Returns a future that resolves to the result of the task or to the reason why the task
+    /// failed to produce a result.
+    pub fn result(mut self) -> impl Future<Output = JoinResult<R>> {
+        futures::future::poll_fn(move |cx| self.poll_result(cx))
+    }
+
+    pub(crate) fn abort_state(&self) -> Arc<AbortState> {
+        Arc::clone(&self.abort)
+    }
+
+    fn poll_result(&mut self, cx: &mut task::Context<'_>) -> task::Poll<JoinResult<R>> {
+        coop::poll_budgeted(cx, |cx| {
+            match &mut self.model {
+                ImplementationModel::LocalJoinHandle { ref mut result_rx } => {
+                    match result_rx.poll_unpin(cx) {
+                        task::Poll::Ready(Ok(result)) => task::Poll::Ready(result),
+                        // An error result may be returned if, for example, the sender was dropped
+                        // before sending. When that may happen is up to the implementation of the
+                        // runtime. For example, this may happen when the runtime is shutting down
+                        // and dropping queued tasks. We take no strong dependencies here on the
+                        // design of the runtime - if no result has arrived, we simply treat this as
+                        // pending forever. The caller is expected to apply a suitable abandonment
+                        // timeout if there is a risk of it awaiting forever.
+                        task::Poll::Ready(Err(_)) | task::Poll::Pending => task::Poll::Pending,
+                    }
+                }
+                ImplementationModel::RemoteTask { result, io_waker } => {
+                    let poll_result = match io_waker {
+                        None => result.poll(cx.waker()),
+                        Some(io_waker) => {
+                            let composite_waker =
+                                RemoteWaker::new(io_waker.clone(), cx.waker().clone());
+                            result.poll(&composite_waker.into())
+                        }
+                    };
+
+                    match poll_result {
+                        Some(result) => task::Poll::Ready(result),
+                        None => task::Poll::Pending,
+                    }
+                }
+            }
+        })
+    }
 }
 
 impl<R> Future for RemoteJoinHandle<R>
@@ -61,35 +146,7 @@ where
     type Output = R;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> task::Poll<Self::Output> {
-        match &mut self.model {
-            ImplementationModel::LocalJoinHandle { ref mut result_rx } => {
-                match result_rx.poll_unpin(cx) {
-                    task::Poll::Ready(Ok(result)) => task::Poll::Ready(result),
-                    // An error result may be returned if, for example, the sender was dropped before
-                    // sending. When that may happen is up to the implementation of the runtime. For
-                    // example, this may happen when the runtime is shutting down and dropping queued
-                    // tasks. We take no strong dependencies here on the design of the runtime - if no
-                    // result has arrived, we simply treat this as pending forever. The caller is expected
-                    // to apply a suitable abandonment timeout if there is a risk of it awaiting forever.
-                    task::Poll::Ready(Err(_)) | task::Poll::Pending => task::Poll::Pending,
-                }
-            }
-            ImplementationModel::RemoteTask { result, io_waker } => {
-                let poll_result = match io_waker {
-                    None => result.poll(cx.waker()),
-                    Some(io_waker) => {
-                        let composite_waker =
-                            RemoteWaker::new(io_waker.clone(), cx.waker().clone());
-                        result.poll(&composite_waker.into())
-                    }
-                };
-
-                match poll_result {
-                    Some(result) => task::Poll::Ready(result),
-                    None => task::Poll::Pending,
-                }
-            }
-        }
+        self.poll_result(cx).map(unwrap_join_result)
     }
 }
 
@@ -97,13 +154,13 @@ where
 enum ImplementationModel<R> {
     // We are wrapping a `LocalJoinHandle`, which will send the result via oneshot channel.
     LocalJoinHandle {
-        result_rx: oneshot::Receiver<R>,
+        result_rx: oneshot::Receiver<JoinResult<R>>,
     },
 
     // We are observing a `RemoteTask` to obtain the result from it. We use a special waker to
     // also wake up our thread from I/O sleep if it is sleeping.
     RemoteTask {
-        result: Arc<RemoteResultBox<R>>,
+        result: Arc<RemoteResultBox<JoinResult<R>>>,
         io_waker: Option<IoWaker>,
     },
 }
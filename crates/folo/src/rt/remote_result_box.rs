@@ -1,6 +1,6 @@
 use crate::{
     constants::{self, GENERAL_MILLISECONDS_BUCKETS},
-    metrics::{Event, EventBuilder},
+    metrics::{observe, Event, EventBuilder},
     time::UltraLowPrecisionInstant,
 };
 use std::{mem, sync::Mutex, task::Waker};
@@ -38,9 +38,7 @@ impl<R> RemoteResultBox<R> {
     }
 
     pub fn set(&self, result: R) {
-        FILL_DURATION.with(|x| {
-            x.observe_millis(self.created.elapsed());
-        });
+        observe!(FILL_DURATION.observe_millis(self.created.elapsed()));
 
         let mut waker: Option<Waker> = None;
 
@@ -79,7 +77,7 @@ impl<R> RemoteResultBox<R> {
     // We expose a poll-like API for getting the result, as the ResultBox is only intended to be
     // read from a future's poll() function (via a join handle).
     pub fn poll(&self, waker: &Waker) -> Option<R> {
-        POLL_COUNT.with(|x| x.observe_unit());
+        observe!(POLL_COUNT.observe_unit());
 
         let mut self_result = self.result.lock().expect(constants::POISONED_LOCK);
 
@@ -95,9 +93,7 @@ impl<R> RemoteResultBox<R> {
                 None
             }
             TaskResult::Ready(_) => {
-                CONSUME_DURATION.with(|x| {
-                    x.observe_millis(self.created.elapsed());
-                });
+                observe!(CONSUME_DURATION.observe_millis(self.created.elapsed()));
 
                 let existing_result = mem::replace(&mut *self_result, TaskResult::Consumed);
 
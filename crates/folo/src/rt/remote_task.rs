@@ -1,9 +1,6 @@
-use crate::{
-    io::IoWaker,
-    rt::{
-        erased_async_task::ErasedResultAsyncTask, remote_result_box::RemoteResultBox,
-        RemoteJoinHandle,
-    },
+use crate::rt::{
+    current_async_agent, current_sync_agent, erased_async_task::ErasedResultAsyncTask,
+    remote_result_box::RemoteResultBox, JoinError, JoinResult, TaskMeta,
 };
 use std::{cell::RefCell, future::Future, pin::Pin, sync::Arc, task};
 
@@ -18,39 +15,47 @@ use std::{cell::RefCell, future::Future, pin::Pin, sync::Arc, task};
 #[derive(Debug)]
 pub(crate) struct RemoteTask<F, R>
 where
-    F: Future<Output = R> + Send + 'static,
+    F: Future<Output = JoinResult<R>> + Send + 'static,
     R: Send + 'static,
 {
     // We drop this on `ErasedResultAsyncTask::clear()` to ensure that any captured state in the
     // future is dropped, releasing critical references that may be blocking runtime shutdown.
     future: RefCell<Option<F>>,
 
+    meta: TaskMeta,
+
+    // Whether the task was spawned by code running on a thread owned by the runtime, making it
+    // part of the work that is already in flight.
+    spawned_by_runtime: bool,
+
     // This is an Arc because we need to share it both with the task and with the JoinHandle, each
     // of which has an independent lifetime (runtime-defined and caller-defined, respectively).
-    result: Arc<RemoteResultBox<R>>,
+    result: Arc<RemoteResultBox<JoinResult<R>>>,
 }
 
 impl<F, R> RemoteTask<F, R>
 where
-    F: Future<Output = R> + Send + 'static,
+    F: Future<Output = JoinResult<R>> + Send + 'static,
     R: Send + 'static,
 {
-    pub fn new(future: F) -> Self {
+    pub fn new(meta: TaskMeta, future: F) -> Self {
         Self {
             future: RefCell::new(Some(future)),
+            meta,
+            spawned_by_runtime: current_async_agent::is_some() || current_sync_agent::is_some(),
             result: Arc::new(RemoteResultBox::new()),
         }
     }
 
-    pub fn join_handle(&self, io_waker: Option<IoWaker>) -> RemoteJoinHandle<R> {
+    pub fn result_box(&self) -> Arc<RemoteResultBox<JoinResult<R>>> {
         // TODO: Protect this so only one join handle can be taken.
-        RemoteJoinHandle::new(Arc::clone(&self.result), io_waker)
+        Arc::clone(&self.result)
     }
 }
 
 impl<F, R> ErasedResultAsyncTask for RemoteTask<F, R>
 where
-    F: Future<Output = R> + Send + 'static,
+    F: Future<Output = JoinResult<R>> + Send + 'static,
     R: Send + 'static,
 {
     fn is_inert(&self) -> bool {
@@ -61,11 +66,25 @@ where
     fn clear(&self) {
         *self.future.borrow_mut() = None;
     }
+
+    fn is_spawned_by_runtime(&self) -> bool {
+        self.spawned_by_runtime
+    }
+
+    fn reject(&self) {
+        // The task never started, so nothing else can have set the result.
+        *self.future.borrow_mut() = None;
+        self.result.set(Err(JoinError::Cancelled));
+    }
+
+    fn meta(&self) -> &TaskMeta {
+        &self.meta
+    }
 }
 
 impl<F, R> Future for RemoteTask<F, R>
 where
-    F: Future<Output = R> + Send + 'static,
+    F: Future<Output = JoinResult<R>> + Send + 'static,
     R: Send + 'static,
 {
     type Output = ();
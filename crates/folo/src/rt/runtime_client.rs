@@ -1,24 +1,42 @@
 use std::any::type_name;
 use std::cell::Cell;
 use std::collections::HashMap;
+use std::convert;
 use std::future::Future;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
-use std::{fmt, thread};
+use std::time::{Duration, Instant};
+use std::{fmt, mem, thread};
 
 use core_affinity::CoreId;
 use crossbeam::channel;
 use crossbeam::queue::SegQueue;
+use futures::future::{FutureObj, LocalBoxFuture};
+use futures::task::{Spawn, SpawnError};
+use futures::FutureExt;
+use scopeguard::ScopeGuard;
 use tracing::{event, Level};
 
 use crate::constants::{self, GENERAL_MILLISECONDS_BUCKETS};
-use crate::io::IoWaker;
-use crate::metrics::{Event, EventBuilder};
+use crate::io::{IoBackend, IoWaker};
+use crate::metrics::{observe, Event, EventBuilder};
+use crate::rt::abort::{AbortState, Abortable};
 use crate::rt::async_agent::AsyncAgentCommand;
+use crate::rt::dump::{PollingTask, TaskDump, WorkerDump};
+use crate::rt::flight_recorder::FlightRecorder;
+use crate::rt::in_flight::InFlightTasks;
+use crate::rt::panic_policy;
 use crate::rt::remote_result_box::RemoteResultBox;
 use crate::rt::remote_task::RemoteTask;
+use crate::rt::singleton::SingletonRegistry;
+use crate::rt::stealing::StealableQueues;
 use crate::rt::sync_agent::SyncAgentCommand;
-use crate::rt::{current_async_agent, ErasedSyncTask, RemoteJoinHandle};
+use crate::rt::worker_stats::WorkerCounters;
+use crate::rt::{
+    current_async_agent, current_sync_agent, ErasedSyncTask, FlightRecord, JoinError, JoinResult,
+    RemoteJoinHandle, RuntimeDump, TaskId, TaskMeta, TaskTrace, WorkerContext, WorkerStats,
+};
 use crate::time::UltraLowPrecisionInstant;
 
 // TODO: In a real implementation we should split this up into multiple layers:
@@ -39,6 +57,19 @@ pub(super) struct CoreClient {
     async_command_tx: channel::Sender<AsyncAgentCommand>,
     async_io_waker: IoWaker,
 
+    // Published by the async agent once it has started draining. See `RuntimeClient::shutdown()`.
+    async_drain_acknowledged: Arc<AtomicBool>,
+
+    // Published by the async agent on every poll. See `RuntimeClient::dump()`.
+    async_polling_task: Arc<PollingTask>,
+
+    // Published by the async agent on every cycle. See `RuntimeClient::worker_stats()`.
+    async_counters: Arc<WorkerCounters>,
+
+    // Written by the async agent on every event, if the flight recorder is enabled. See
+    // `RuntimeClient::dump_flight_record()`.
+    async_flight_recorder: Option<Arc<FlightRecorder>>,
+
     // We often prefer to give work to the same processor, so we split
     // the sync command architecture up by the processor ID.
     sync_command_txs: Box<[channel::Sender<SyncAgentCommand>]>,
@@ -52,32 +83,47 @@ pub(super) struct CoreClient {
     // Note that runtime clients are passed to worker threads from another thread, so thread-safe.
     pub pending_sync_tasks: Arc<SegQueue<ErasedSyncTask>>,
     pub pending_sync_priority_tasks: Arc<SegQueue<ErasedSyncTask>>,
+
+    // If work stealing is enabled, tasks that may run on any worker are queued here instead of
+    // being sent as commands, so that idle workers can steal them.
+    stealable_queues: Option<StealableQueues>,
 }
 
 impl CoreClient {
+    #[allow(clippy::too_many_arguments)] // Ssssshhhhh, sleep little Clippy!
     pub(super) fn new(
         processor_id: CoreId,
         async_command_tx: channel::Sender<AsyncAgentCommand>,
         async_io_waker: IoWaker,
+        async_drain_acknowledged: Arc<AtomicBool>,
+        async_polling_task: Arc<PollingTask>,
+        async_counters: Arc<WorkerCounters>,
+        async_flight_recorder: Option<Arc<FlightRecorder>>,
         sync_command_txs: Box<[channel::Sender<SyncAgentCommand>]>,
         sync_task_queue: Arc<SegQueue<ErasedSyncTask>>,
         sync_priority_task_queue: Arc<SegQueue<ErasedSyncTask>>,
+        stealable_queues: Option<StealableQueues>,
     ) -> Self {
         Self {
             processor_id,
             async_command_tx,
             async_io_waker,
+            async_drain_acknowledged,
+            async_polling_task,
+            async_counters,
+            async_flight_recorder,
             sync_command_txs,
             sync_task_queue,
             sync_priority_task_queue,
             pending_sync_tasks: Arc::new(SegQueue::new()),
             pending_sync_priority_tasks: Arc::new(SegQueue::new()),
+            stealable_queues,
         }
     }
 
     fn enqueue_async_task<F, R>(&self, task: RemoteTask<F, R>)
     where
-        F: Future<Output = R> + Send + 'static,
+        F: Future<Output = JoinResult<R>> + Send + 'static,
         R: Send + 'static,
     {
         // We ignore the return value because it is theoretically possible that something is trying
@@ -90,6 +136,52 @@ impl CoreClient {
         self.async_io_waker.wake();
     }
 
+    /// Enqueues a task that does not need to run on this specific worker. If work stealing is
+    /// enabled, another worker may pick it up if this one is busy.
+    fn enqueue_stealable_async_task<F, R>(&self, task: RemoteTask<F, R>)
+    where
+        F: Future<Output = JoinResult<R>> + Send + 'static,
+        R: Send + 'static,
+    {
+        let Some(stealable_queues) = &self.stealable_queues else {
+            self.enqueue_async_task(task);
+            return;
+        };
+
+        stealable_queues.push(Box::pin(task));
+
+        // Wake up the agent if it might be sleeping and waiting for I/O.
+        self.async_io_waker.wake();
+    }
+
+    /// Asks the async agent to describe its live tasks. The reply arrives once the agent gets
+    /// around to processing commands, which may be never if it is stuck.
+    fn request_dump(&self) -> oneshot::Receiver<Box<[TaskDump]>> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+
+        // We ignore the return value because if the worker has already stopped, the channel
+        // may be closed in which case the send may simply fail. The requester then times out.
+        _ = self
+            .async_command_tx
+            .send(AsyncAgentCommand::Dump { reply_tx });
+        self.async_io_waker.wake();
+
+        reply_rx
+    }
+
+    fn drain(&self) {
+        // We ignore the return value because if the worker has already stopped, the channel
+        // may be closed in which case the send may simply fail.
+        _ = self.async_command_tx.send(AsyncAgentCommand::Drain);
+        self.async_io_waker.wake();
+    }
+
+    /// Whether the async agent has acknowledged the drain command, after which it accepts no more
+    /// remote tasks spawned from outside the runtime.
+    fn is_draining(&self) -> bool {
+        self.async_drain_acknowledged.load(Ordering::Acquire)
+    }
+
     fn terminate(&self) {
         // We ignore the return value because if the worker has already stopped, the channel
         // may be closed in which case the send may simply fail.
@@ -157,6 +249,10 @@ impl fmt::Debug for CoreClient {
             .field("processor_id", &self.processor_id)
             .field("async_command_tx", &self.async_command_tx)
             .field("async_io_waker", &self.async_io_waker)
+            .field("async_drain_acknowledged", &self.async_drain_acknowledged)
+            .field("async_polling_task", &self.async_polling_task)
+            .field("async_counters", &self.async_counters)
+            .field("async_flight_recorder", &self.async_flight_recorder)
             .field("sync_command_txs", &self.sync_command_txs)
             .field("sync_task_queue", &self.sync_task_queue)
             .field("sync_priority_task_queue", &self.sync_priority_task_queue)
@@ -179,6 +275,10 @@ pub struct RuntimeClient {
 
     processor_ids: Box<[CoreId]>,
 
+    // New work that may execute on any worker is only given to the first N workers, so the others
+    // can go idle. See `set_active_worker_count()`.
+    active_worker_count: Arc<AtomicUsize>,
+
     // This is None if `.wait()` has already been called - the field can be consumed only once,
     // typically done by the runtime client provided to the entry point thread.
     #[allow(clippy::type_complexity)] // One day we may refactor this but not today.
@@ -186,26 +286,190 @@ pub struct RuntimeClient {
 
     // This can be used by cleanup logic to detect that the runtime is not usable anymore.
     is_stopping: Arc<AtomicBool>,
+
+    // Set once any I/O primitive falls back to blocking I/O. See `io_backend()`.
+    io_fallback_used: Arc<AtomicBool>,
+
+    // Executed by `shutdown()` before in-flight tasks are drained. See `on_shutdown()`.
+    shutdown_hooks: Arc<Mutex<Vec<ShutdownHook>>>,
+
+    // Every task spawned on the runtime, async or sync, is counted here from the moment it is
+    // spawned until it completes. See `shutdown()`.
+    in_flight_tasks: InFlightTasks,
+
+    // The singletons running on the runtime, by name. See `spawn_singleton()`.
+    singletons: Arc<SingletonRegistry>,
 }
 
+type ShutdownHook = Box<dyn FnOnce() -> LocalBoxFuture<'static, ()> + Send>;
+
 impl RuntimeClient {
     #[allow(clippy::too_many_arguments)] // Ssssshhhhh, sleep little Clippy!
     pub(super) fn new(
         core_clients: HashMap<CoreId, CoreClient>,
         processor_ids: Box<[CoreId]>,
+        active_worker_count: Arc<AtomicUsize>,
         join_handles: Box<[thread::JoinHandle<()>]>,
         is_stopping: Arc<AtomicBool>,
+        io_fallback_used: Arc<AtomicBool>,
+        in_flight_tasks: InFlightTasks,
     ) -> Self {
         Self {
             core_clients,
             processor_ids,
+            active_worker_count,
             join_handles: Arc::new(Mutex::new(Some(join_handles))),
             is_stopping,
+            io_fallback_used,
+            shutdown_hooks: Arc::new(Mutex::new(Vec::new())),
+            in_flight_tasks,
+            singletons: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
     /// Spawns a task to execute a future on any worker thread, creating the future via closure.
     pub fn spawn_on_any<FN, F, R>(&self, future_fn: FN) -> RemoteJoinHandle<R>
+    where
+        FN: FnOnce() -> F + Send + 'static,
+        F: Future<Output = R> + 'static,
+        R: Send + 'static,
+    {
+        self.spawn_on_any_core(TaskMeta::anonymous(), future_fn)
+    }
+
+    /// Spawns a named task to execute a future on any worker thread, creating the future via
+    /// closure. The name identifies the task in diagnostic output.
+    pub fn spawn_on_any_named<FN, F, R>(
+        &self,
+        name: impl Into<Arc<str>>,
+        future_fn: FN,
+    ) -> RemoteJoinHandle<R>
+    where
+        FN: FnOnce() -> F + Send + 'static,
+        F: Future<Output = R> + 'static,
+        R: Send + 'static,
+    {
+        self.spawn_on_any_core(TaskMeta::new(Some(name.into())), future_fn)
+    }
+
+    fn spawn_on_any_core<FN, F, R>(&self, meta: TaskMeta, future_fn: FN) -> RemoteJoinHandle<R>
+    where
+        FN: FnOnce() -> F + Send + 'static,
+        F: Future<Output = R> + 'static,
+        R: Send + 'static,
+    {
+        let processor_id = self.processor_ids[next_async_worker(self.active_worker_count())];
+        let (task, join_handle) = self.new_spawned_remote_task(meta, future_fn);
+
+        self.core_clients[&processor_id].enqueue_stealable_async_task(task);
+
+        join_handle
+    }
+
+    /// Spawns a task to execute a future on a specific async worker thread, creating the future via
+    /// closure. Workers are identified by their index, from zero to `worker_count() - 1`.
+    ///
+    /// Use this to route work to the worker that owns the data it operates on, e.g. if state is
+    /// sharded per worker and work for shard N must be performed by worker N.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the worker index is out of bounds.
+    pub fn spawn_on<FN, F, R>(&self, worker_index: usize, future_fn: FN) -> RemoteJoinHandle<R>
+    where
+        FN: FnOnce() -> F + Send + 'static,
+        F: Future<Output = R> + 'static,
+        R: Send + 'static,
+    {
+        let processor_id = *self.processor_ids.get(worker_index).unwrap_or_else(|| {
+            panic!(
+                "worker index {worker_index} is out of bounds; the runtime has {} async workers",
+                self.processor_ids.len()
+            )
+        });
+
+        self.spawn_on_processor(processor_id, TaskMeta::anonymous(), future_fn)
+    }
+
+    /// The number of async worker threads of the runtime. Workers are identified by their index,
+    /// from zero to `worker_count() - 1`, e.g. when spawning tasks via `spawn_on()`.
+    pub fn worker_count(&self) -> usize {
+        self.processor_ids.len()
+    }
+
+    /// The number of async worker threads that are given new work that may execute on any worker.
+    /// See [`set_active_worker_count()`][Self::set_active_worker_count].
+    pub fn active_worker_count(&self) -> usize {
+        self.active_worker_count.load(Ordering::Relaxed)
+    }
+
+    /// Sets how many of the async worker threads (and the sync worker threads on the same
+    /// processors) are given new work that may execute on any worker, e.g. via `spawn_on_any()`.
+    /// The active workers are the ones with the lowest indexes; the rest are retired.
+    ///
+    /// Retired workers are not stopped - they keep executing the tasks they already have, as
+    /// tasks that have started are bound to their worker thread, together with any I/O
+    /// operations they have started. They no longer receive new work, so once their existing tasks
+    /// complete they go idle and release their processor to other processes on the system. With
+    /// work stealing enabled, tasks that were queued on a retired worker but have not yet started
+    /// are picked up by the active workers, and retired workers do not steal. Work explicitly
+    /// targeted at a worker (e.g. via `spawn_on()` or `spawn_on_all()`) is still executed by it.
+    ///
+    /// Worker threads are only started when the runtime is built, so the active worker count can be
+    /// raised back up to at most `worker_count()`. To be able to scale up beyond the initial load,
+    /// build the runtime with all the processors it may ever need and lower the count afterwards.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the count is zero or greater than `worker_count()`.
+    pub fn set_active_worker_count(&self, count: usize) {
+        assert!(
+            (1..=self.processor_ids.len()).contains(&count),
+            "active worker count must be between 1 and {}, was {count}",
+            self.processor_ids.len()
+        );
+
+        let previous = self.active_worker_count.swap(count, Ordering::Relaxed);
+
+        if previous != count {
+            event!(
+                Level::INFO,
+                message = "active worker count changed",
+                previous,
+                count
+            );
+        }
+    }
+
+    /// Spawns a task to execute a future on the async worker thread of a specific processor.
+    pub(crate) fn spawn_on_processor<FN, F, R>(
+        &self,
+        processor_id: CoreId,
+        meta: TaskMeta,
+        future_fn: FN,
+    ) -> RemoteJoinHandle<R>
+    where
+        FN: FnOnce() -> F + Send + 'static,
+        F: Future<Output = R> + 'static,
+        R: Send + 'static,
+    {
+        let (task, join_handle) = self.new_spawned_remote_task(meta, future_fn);
+
+        self.core_clients[&processor_id].enqueue_async_task(task);
+
+        join_handle
+    }
+
+    /// Creates a remote task that spawns the future returned by the closure as a local task on
+    /// whichever async worker thread it ends up executing on.
+    fn new_spawned_remote_task<FN, F, R>(
+        &self,
+        meta: TaskMeta,
+        future_fn: FN,
+    ) -> (
+        RemoteTask<impl Future<Output = JoinResult<R>> + Send + 'static, R>,
+        RemoteJoinHandle<R>,
+    )
     where
         FN: FnOnce() -> F + Send + 'static,
         F: Future<Output = R> + 'static,
@@ -213,27 +477,26 @@ impl RuntimeClient {
     {
         let started = UltraLowPrecisionInstant::now();
 
+        // The wrapper and the local task that does the real work are the same task as far as the
+        // user is concerned, so they share the metadata.
+        let local_meta = meta.clone();
+
         // Just because we are spawning a future on another thread does not mean it has to be a
         // thread-safe future (although the return value has to be). Therefore, we kajigger it
         // around via a remote join handle from the same thread, to allow a single-threaded future
         // to execute, as long as the closure that creates it is thread-safe.
         let thread_safe_wrapper_future = async move {
-            REMOTE_SPAWN_DELAY.with(|x| x.observe_millis(started.elapsed()));
+            observe!(REMOTE_SPAWN_DELAY.observe_millis(started.elapsed()));
 
             // TODO: This seems inefficient. Surely we can do better?
             // This is: RemoteJoinHandle -> RemoteJoinHandle -> LocalJoinHandle -> LocalJoinHandle
             // Desired is: RemoteJoinHandle -> LocalJoinHandle
-            let join_handle: RemoteJoinHandle<R> = crate::rt::spawn(future_fn()).into();
-            join_handle.await
+            let join_handle: RemoteJoinHandle<R> =
+                current_async_agent::with(|agent| agent.spawn(local_meta, future_fn())).into();
+            await_and_forward_abort(join_handle).await
         };
 
-        let task = RemoteTask::new(thread_safe_wrapper_future);
-        let join_handle = task.join_handle(self.current_thread_io_waker());
-
-        let processor_id = self.processor_ids[next_async_worker(self.processor_ids.len())];
-        self.core_clients[&processor_id].enqueue_async_task(task);
-
-        join_handle
+        self.new_remote_task(meta, thread_safe_wrapper_future)
     }
 
     /// Spawns a task to execute a future on every worker thread.
@@ -265,18 +528,21 @@ impl RuntimeClient {
             // thread-safe future (although the return value has to be). Therefore, we kajigger it
             // around via a remote join handle from the same thread, to allow a single-threaded future
             // to execute, as long as the closure that creates it is thread-safe.
+            let meta = TaskMeta::anonymous();
+            let local_meta = meta.clone();
+
             let thread_safe_wrapper_future = async move {
-                REMOTE_SPAWN_DELAY.with(|x| x.observe_millis(started.elapsed()));
+                observe!(REMOTE_SPAWN_DELAY.observe_millis(started.elapsed()));
 
                 // TODO: This seems inefficient. Surely we can do better?
                 // This is: RemoteJoinHandle -> RemoteJoinHandle -> LocalJoinHandle -> LocalJoinHandle
                 // Desired is: RemoteJoinHandle -> LocalJoinHandle
-                let join_handle: RemoteJoinHandle<R> = crate::rt::spawn(future_fn()).into();
-                join_handle.await
+                let join_handle: RemoteJoinHandle<R> =
+                    current_async_agent::with(|agent| agent.spawn(local_meta, future_fn())).into();
+                await_and_forward_abort(join_handle).await
             };
 
-            let task = RemoteTask::new(thread_safe_wrapper_future);
-            let join_handle = task.join_handle(self.current_thread_io_waker());
+            let (task, join_handle) = self.new_remote_task(meta, thread_safe_wrapper_future);
             proc.enqueue_async_task(task);
             join_handles.push(join_handle);
         }
@@ -284,6 +550,43 @@ impl RuntimeClient {
         join_handles.into_boxed_slice()
     }
 
+    /// Executes a job once on every async worker thread, returning a future that completes with the
+    /// results of all the workers once they have all finished, in worker index order.
+    ///
+    /// The job is a closure that receives a [`WorkerContext`] identifying the worker and returns
+    /// the future to execute on that worker. The future itself does not have to be thread-safe.
+    ///
+    /// Use this for work that needs to be done on every worker, such as warming up or flushing
+    /// per-worker caches and buffers, or reloading per-worker configuration.
+    pub fn for_each_worker<FN, F, R>(&self, job: FN) -> impl Future<Output = Box<[R]>>
+    where
+        FN: Fn(WorkerContext) -> F + Send + Sync + 'static,
+        F: Future<Output = R> + 'static,
+        R: Send + 'static,
+    {
+        let job = Arc::new(job);
+        let worker_count = self.processor_ids.len();
+
+        let join_handles = self
+            .processor_ids
+            .iter()
+            .enumerate()
+            .map(|(worker_index, processor_id)| {
+                let job = Arc::clone(&job);
+
+                self.spawn_on_processor(*processor_id, TaskMeta::anonymous(), move || {
+                    job(WorkerContext::new(worker_index, worker_count))
+                })
+            })
+            .collect::<Vec<_>>();
+
+        async move {
+            futures::future::join_all(join_handles)
+                .await
+                .into_boxed_slice()
+        }
+    }
+
     /// Spawns a task on a synchronous worker thread suitable for the specific type of synchronous
     /// work requested, returning the result via a join handle suitable for use in asynchronous
     /// tasks.
@@ -307,20 +610,32 @@ impl RuntimeClient {
         let result_box_rx = Arc::new(RemoteResultBox::new());
         let result_box_tx = Arc::clone(&result_box_rx);
 
+        let abort = AbortState::new();
+        let abort_rx = Arc::clone(&abort);
+
         let started = UltraLowPrecisionInstant::now();
+        let in_flight = self.in_flight_tasks.track();
 
         let task = move || {
+            let _in_flight = in_flight;
+
+            // A synchronous task cannot be interrupted but it can be skipped if not yet started.
+            if abort_rx.is_requested() {
+                result_box_tx.set(Err(JoinError::Cancelled));
+                return;
+            }
+
             match task_type {
                 SynchronousTaskType::Syscall => {
-                    SYNC_SPAWN_DELAY_LOW_PRIORITY.with(|x| x.observe_millis(started.elapsed()))
+                    observe!(SYNC_SPAWN_DELAY_LOW_PRIORITY.observe_millis(started.elapsed()))
                 }
                 SynchronousTaskType::HighPrioritySyscall => {
-                    SYNC_SPAWN_DELAY_HIGH_PRIORITY.with(|x| x.observe_millis(started.elapsed()))
+                    observe!(SYNC_SPAWN_DELAY_HIGH_PRIORITY.observe_millis(started.elapsed()))
                 }
                 _ => unreachable!(),
             };
 
-            result_box_tx.set(f())
+            result_box_tx.set(call_sync_task(f))
         };
 
         // TODO: Support this from arbitrary threads, not just async worker threads.
@@ -355,7 +670,12 @@ impl RuntimeClient {
             _ => unreachable!(),
         }
 
-        RemoteJoinHandle::new(result_box_rx, self.current_thread_io_waker())
+        RemoteJoinHandle::new(
+            result_box_rx,
+            self.current_thread_io_waker(),
+            abort,
+            TaskId::next(),
+        )
     }
 
     /// Spawns a task on a synchronous worker thread suitable for the specific type of synchronous
@@ -387,31 +707,43 @@ impl RuntimeClient {
         let result_box_rx = Arc::new(RemoteResultBox::new());
         let result_box_tx = Arc::clone(&result_box_rx);
 
+        let abort = AbortState::new();
+        let abort_rx = Arc::clone(&abort);
+
         let started = UltraLowPrecisionInstant::now();
+        let in_flight = self.in_flight_tasks.track();
 
         let task = move || {
+            let _in_flight = in_flight;
+
+            // A synchronous task cannot be interrupted but it can be skipped if not yet started.
+            if abort_rx.is_requested() {
+                result_box_tx.set(Err(JoinError::Cancelled));
+                return;
+            }
+
             match task_type {
                 SynchronousTaskType::Syscall => {
-                    SYNC_SPAWN_DELAY_LOW_PRIORITY.with(|x| x.observe_millis(started.elapsed()))
+                    observe!(SYNC_SPAWN_DELAY_LOW_PRIORITY.observe_millis(started.elapsed()))
                 }
                 SynchronousTaskType::HighPrioritySyscall => {
-                    SYNC_SPAWN_DELAY_HIGH_PRIORITY.with(|x| x.observe_millis(started.elapsed()))
+                    observe!(SYNC_SPAWN_DELAY_HIGH_PRIORITY.observe_millis(started.elapsed()))
                 }
                 _ => unreachable!(),
             };
 
-            result_box_tx.set(f())
+            result_box_tx.set(call_sync_task(f))
         };
 
         // We pick an arbitrary processor. The assumption being that whoever is calling this has
         // so much work that it is unlikely to scale on one one processor, so they want to spread
         // the load around.
-        let processor_id = self.processor_ids[next_sync_processor(self.processor_ids.len())];
+        let processor_id = self.processor_ids[next_sync_processor(self.active_worker_count())];
 
         // We just add it to the pending task queue for now, to be submitted at the end of the cycle.
         let boxed_task = Box::new(task);
         let task_addr = format!("{:p}", &*boxed_task);
-        
+
         match task_type {
             SynchronousTaskType::Syscall => {
                 self.core_clients[&processor_id]
@@ -440,7 +772,12 @@ impl RuntimeClient {
             _ => unreachable!(),
         }
 
-        RemoteJoinHandle::new(result_box_rx, self.current_thread_io_waker())
+        RemoteJoinHandle::new(
+            result_box_rx,
+            self.current_thread_io_waker(),
+            abort,
+            TaskId::next(),
+        )
     }
 
     /// Submits any tasks that have been queued for submission. We expect this to be called by
@@ -466,12 +803,300 @@ impl RuntimeClient {
         }
     }
 
+    /// Registers an async cleanup function that `shutdown()` executes before in-flight tasks are
+    /// drained, e.g. to flush write buffers or to deregister from service discovery. The function
+    /// is called on an arbitrary async worker thread and the future it returns is executed there.
+    ///
+    /// All the hooks execute concurrently and count towards the timeout of `shutdown()`. Hooks
+    /// that do not complete before the deadline are canceled together with the remaining tasks.
+    ///
+    /// Hooks are not executed if the runtime is stopped via `stop()`, which does not wait for
+    /// anything. Hooks registered after `shutdown()` has been called are never executed.
+    pub fn on_shutdown<FN, F>(&self, hook: FN)
+    where
+        FN: FnOnce() -> F + Send + 'static,
+        F: Future<Output = ()> + 'static,
+    {
+        self.shutdown_hooks
+            .lock()
+            .expect(constants::POISONED_LOCK)
+            .push(Box::new(move || hook().boxed_local()));
+    }
+
+    /// Gracefully shuts down the runtime, giving shutdown hooks and in-flight tasks up to `timeout`
+    /// to complete.
+    ///
+    /// The shutdown process is:
+    ///
+    /// 1. The hooks registered via `on_shutdown()` are executed and we wait for them to complete
+    ///    or for the deadline to expire. The runtime operates normally while they execute.
+    /// 2. Async workers stop accepting new tasks from threads not owned by the runtime. Tasks
+    ///    spawned from such threads after this point are dropped without executing and their join
+    ///    handles resolve to `JoinError::Cancelled`. Tasks already accepted by a worker may still
+    ///    spawn tasks, both local and on other workers (e.g. via `spawn_on_any()`), as those are
+    ///    considered part of the in-flight work.
+    /// 3. We wait until all in-flight tasks have completed or until the deadline expires. This
+    ///    includes both async and sync tasks, counted from the moment they were spawned.
+    /// 4. The runtime is stopped, canceling any tasks that are still in flight, and we wait for
+    ///    all worker threads to terminate, just as `stop()` followed by `wait()` would.
+    ///
+    /// Returns `true` if all hooks and in-flight tasks completed before the deadline, `false` if
+    /// some had to be canceled.
+    ///
+    /// # Panics
+    ///
+    /// If called from a thread owned by the runtime (that would be waiting for itself to stop).
+    ///
+    /// If `wait()` or `shutdown()` has already been called.
+    pub fn shutdown(&self, timeout: Duration) -> bool {
+        assert!(
+            !current_async_agent::is_some() && !current_sync_agent::is_some(),
+            "shutdown() cannot be called from a runtime-owned thread because it blocks until all runtime threads have terminated"
+        );
+
+        let deadline = Instant::now() + timeout;
+
+        event!(
+            Level::TRACE,
+            message = "graceful shutdown starting",
+            ?timeout
+        );
+
+        let hooks_completed = self.run_shutdown_hooks(deadline);
+
+        for proc in self.core_clients.values() {
+            proc.drain();
+        }
+
+        // Only once every worker is draining can we be sure that nothing from outside the runtime
+        // adds more work - until then, the in-flight count may drop to zero and climb back up.
+        let tasks_completed = loop {
+            if self.is_draining() && self.in_flight_tasks.count() == 0 {
+                break true;
+            }
+
+            let now = Instant::now();
+
+            if now >= deadline {
+                break false;
+            }
+
+            thread::sleep(SHUTDOWN_POLL_INTERVAL.min(deadline - now));
+        };
+
+        if tasks_completed {
+            event!(
+                Level::TRACE,
+                "all in-flight tasks completed; stopping runtime"
+            );
+        } else {
+            event!(
+                Level::WARN,
+                message = "graceful shutdown deadline expired; canceling remaining tasks",
+                in_flight_tasks = self.in_flight_tasks.count()
+            );
+        }
+
+        self.stop();
+        self.wait();
+
+        hooks_completed && tasks_completed
+    }
+
+    /// Executes the shutdown hooks and waits for them to complete or for the deadline to expire.
+    /// Returns `true` if all of them completed.
+    fn run_shutdown_hooks(&self, deadline: Instant) -> bool {
+        let hooks = mem::take(&mut *self.shutdown_hooks.lock().expect(constants::POISONED_LOCK));
+
+        if hooks.is_empty() {
+            return true;
+        }
+
+        event!(
+            Level::TRACE,
+            message = "executing shutdown hooks",
+            count = hooks.len()
+        );
+
+        let remaining = Arc::new(AtomicUsize::new(hooks.len()));
+
+        for hook in hooks {
+            let remaining = Arc::clone(&remaining);
+
+            // We do not need the join handle, the counter tells us when the hook is done.
+            _ = self.spawn_on_any(move || async move {
+                hook().await;
+                remaining.fetch_sub(1, Ordering::Relaxed);
+            });
+        }
+
+        loop {
+            if remaining.load(Ordering::Relaxed) == 0 {
+                return true;
+            }
+
+            let now = Instant::now();
+
+            if now >= deadline {
+                event!(
+                    Level::WARN,
+                    message = "graceful shutdown deadline expired while executing shutdown hooks",
+                    remaining_hooks = remaining.load(Ordering::Relaxed)
+                );
+
+                return false;
+            }
+
+            thread::sleep(SHUTDOWN_POLL_INTERVAL.min(deadline - now));
+        }
+    }
+
+    /// Whether a graceful shutdown has progressed to the point where every async worker has stopped
+    /// accepting tasks spawned from threads not owned by the runtime. From this point on, such
+    /// tasks are dropped without executing. See [`shutdown()`][Self::shutdown].
+    pub fn is_draining(&self) -> bool {
+        self.core_clients.values().all(CoreClient::is_draining)
+    }
+
+    /// Describes all the live tasks of the runtime, for diagnosing hangs. Blocks the current thread
+    /// until every async worker has responded or until the timeout expires.
+    ///
+    /// Workers respond between polls, so a worker that is stuck in a single poll (e.g. because a
+    /// task is blocking the thread with synchronous I/O) cannot respond. For such workers, the dump
+    /// only identifies the task being polled and for how long it has been polled.
+    ///
+    /// To take a dump from async code, call this via `spawn_sync()`.
+    ///
+    /// # Panics
+    ///
+    /// If called from an async worker thread (that would be waiting for itself to respond).
+    pub fn dump(&self, timeout: Duration) -> RuntimeDump {
+        assert!(
+            !current_async_agent::is_some(),
+            "dump() cannot be called from an async worker thread because it blocks until all async workers have responded"
+        );
+
+        let deadline = Instant::now() + timeout;
+
+        let requests = self
+            .processor_ids
+            .iter()
+            .map(|processor_id| {
+                let core_client = self
+                    .core_clients
+                    .get(processor_id)
+                    .expect("every processor ID has a core client");
+
+                (core_client, core_client.request_dump())
+            })
+            .collect::<Vec<_>>();
+
+        let workers = requests
+            .into_iter()
+            .map(|(core_client, reply_rx)| {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+
+                match reply_rx.recv_timeout(remaining) {
+                    Ok(tasks) => WorkerDump::new(core_client.processor_id.id, true, tasks),
+                    Err(_) => WorkerDump::new(
+                        core_client.processor_id.id,
+                        false,
+                        core_client.async_polling_task.dump().into_iter().collect(),
+                    ),
+                }
+            })
+            .collect();
+
+        RuntimeDump::new(workers)
+    }
+
+    /// Reports where each live task was suspended at the end of its last poll, to answer the
+    /// question of which await point every task is stuck on. Blocks the current thread like
+    /// [`dump()`][Self::dump], with the same limitations.
+    ///
+    /// Only instrumented await points are known: futures wrapped via [`traced()`][1] and the I/O
+    /// operations and timers of the runtime. If a task waits on several futures at once (e.g. via
+    /// `join!`), only the first one that was polled is reported.
+    ///
+    /// # Panics
+    ///
+    /// If called from an async worker thread (that would be waiting for itself to respond).
+    ///
+    /// [1]: crate::rt::traced
+    pub fn capture_task_traces(&self, timeout: Duration) -> Box<[TaskTrace]> {
+        self.dump(timeout)
+            .workers()
+            .iter()
+            .flat_map(|worker| worker.tasks())
+            .map(|task| {
+                TaskTrace::new(
+                    task.id(),
+                    task.name().map(Arc::from),
+                    task.suspension().cloned(),
+                )
+            })
+            .collect()
+    }
+
+    /// Returns how each async worker thread has spent its time since the runtime started, in worker
+    /// index order. This does not block and may be called from any thread.
+    pub fn worker_stats(&self) -> Box<[WorkerStats]> {
+        self.processor_ids
+            .iter()
+            .enumerate()
+            .map(|(worker_index, processor_id)| {
+                self.core_clients
+                    .get(processor_id)
+                    .expect("every processor ID has a core client")
+                    .async_counters
+                    .snapshot(worker_index)
+            })
+            .collect()
+    }
+
+    /// Returns the most recent events of each async worker thread, as captured by the flight
+    /// recorder, in worker index order. This does not block on the workers, so it also works if a
+    /// worker is stuck, and may be called from any thread.
+    ///
+    /// The record is empty unless the flight recorder is enabled via
+    /// [`RuntimeBuilder::flight_recorder()`][crate::rt::RuntimeBuilder::flight_recorder].
+    pub fn dump_flight_record(&self) -> FlightRecord {
+        let workers = self
+            .processor_ids
+            .iter()
+            .filter_map(|processor_id| {
+                self.core_clients
+                    .get(processor_id)
+                    .expect("every processor ID has a core client")
+                    .async_flight_recorder
+                    .as_ref()
+                    .map(|recorder| recorder.snapshot())
+            })
+            .collect();
+
+        FlightRecord::new(workers)
+    }
+
     /// Returns `true` if the runtime has been asked to stop.
     /// If so, enqueued tasks are unlikely to actually execute.
     pub fn is_stopping(&self) -> bool {
         self.is_stopping.load(Ordering::Relaxed)
     }
 
+    /// Returns how the I/O operations of the runtime are completed. This is
+    /// [`IoBackend::CompletionPort`] unless some I/O primitive could not be associated with the
+    /// completion port of a worker thread and fell back to blocking I/O on synchronous worker
+    /// threads (see [`RuntimeBuilder::io_blocking_fallback()`]).
+    ///
+    /// [`RuntimeBuilder::io_blocking_fallback()`]: crate::rt::RuntimeBuilder::io_blocking_fallback
+    pub fn io_backend(&self) -> IoBackend {
+        if self.io_fallback_used.load(Ordering::Relaxed) {
+            IoBackend::BlockingFallback
+        } else {
+            IoBackend::CompletionPort
+        }
+    }
+
     /// Returns `true` if the runtime has stopped (i.e. calling `wait()` would not block).
     ///
     /// # Panics
@@ -512,6 +1137,54 @@ impl RuntimeClient {
         }
     }
 
+    // Creates a remote task that can be aborted via its join handle. The future is expected to
+    // forward the abort to whatever it delegates the real work to, which is why it returns a
+    // `JoinResult` itself.
+    fn new_remote_task<F, R>(
+        &self,
+        meta: TaskMeta,
+        future: F,
+    ) -> (
+        RemoteTask<impl Future<Output = JoinResult<R>> + Send + 'static, R>,
+        RemoteJoinHandle<R>,
+    )
+    where
+        F: Future<Output = JoinResult<R>> + Send + 'static,
+        R: Send + 'static,
+    {
+        let abort = AbortState::new();
+        let id = meta.id();
+
+        // The guard is dropped together with the future, whether the task completes or is
+        // rejected by the worker without ever being polled.
+        let in_flight = self.in_flight_tasks.track();
+        let future = async move {
+            let _in_flight = in_flight;
+            future.await
+        };
+
+        let task = RemoteTask::new(
+            meta,
+            Abortable::new(future, Arc::clone(&abort))
+                .map(|result| result.and_then(convert::identity)),
+        );
+
+        let join_handle =
+            RemoteJoinHandle::new(task.result_box(), self.current_thread_io_waker(), abort, id);
+
+        (task, join_handle)
+    }
+
+    /// The processors that have an async worker thread, in the order tasks are distributed
+    /// among them.
+    pub(crate) fn processor_ids(&self) -> &[CoreId] {
+        &self.processor_ids
+    }
+
+    pub(crate) fn singletons(&self) -> &SingletonRegistry {
+        &self.singletons
+    }
+
     fn current_thread_io_waker(&self) -> Option<IoWaker> {
         current_async_agent::try_with_io(|io| io.waker())
     }
@@ -522,12 +1195,46 @@ impl fmt::Debug for RuntimeClient {
         f.debug_struct("RuntimeClient")
             .field("core_clients", &self.core_clients)
             .field("processor_ids", &self.processor_ids)
+            .field("active_worker_count", &self.active_worker_count)
             .field("join_handles", &self.join_handles)
             .field("is_stopping", &self.is_stopping)
+            .field("io_fallback_used", &self.io_fallback_used)
+            .field(
+                "shutdown_hooks",
+                &self.shutdown_hooks.try_lock().map(|hooks| hooks.len()).ok(),
+            )
+            .field("in_flight_tasks", &self.in_flight_tasks)
+            .field(
+                "singletons",
+                &self.singletons.try_lock().map(|x| x.len()).ok(),
+            )
             .finish()
     }
 }
 
+/// Spawns `Send` futures on any async worker thread, for use with libraries that are generic over
+/// the spawner. The spawned tasks are detached. Spawning fails if the runtime is stopping.
+///
+/// See also [`LocalSpawner`][crate::rt::LocalSpawner] for futures that are not `Send`.
+impl Spawn for RuntimeClient {
+    fn spawn_obj(&self, future: FutureObj<'static, ()>) -> Result<(), SpawnError> {
+        self.status()?;
+
+        // The task is detached, so we have no use for the join handle.
+        _ = self.spawn_on_any(move || future);
+
+        Ok(())
+    }
+
+    fn status(&self) -> Result<(), SpawnError> {
+        if self.is_stopping() {
+            return Err(SpawnError::shutdown());
+        }
+
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SynchronousTaskType {
     /// Some syscall that the runtime needs to perform synchronously and which may take an unknown
@@ -548,6 +1255,25 @@ pub enum SynchronousTaskType {
     Compute,
 }
 
+/// Awaits the result of a task. If the calling future is dropped before the task completes (e.g.
+/// because the remote task wrapping it was aborted), the awaited task is aborted, as well.
+async fn await_and_forward_abort<R>(join_handle: RemoteJoinHandle<R>) -> JoinResult<R>
+where
+    R: Send + 'static,
+{
+    let abort_guard = scopeguard::guard(join_handle.abort_state(), |abort| abort.request());
+
+    let result = join_handle.result().await;
+
+    // Completed normally, nothing to abort.
+    ScopeGuard::into_inner(abort_guard);
+
+    result
+}
+
+/// How often `RuntimeClient::shutdown()` checks whether in-flight tasks have completed.
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
 // Basic round-robin implementation for distributing work across async workers.
 thread_local! {
     static NEXT_ASYNC_WORKER_INDEX: Cell<usize> = const { Cell::new(0) };
@@ -557,14 +1283,32 @@ thread_local! {
     static NEXT_SYNC_PROCESSOR_INDEX: Cell<usize> = const { Cell::new(0) };
 }
 
+/// Executes the body of a synchronous task on a sync worker thread. A panic is caught at the task
+/// boundary and handled according to the panic policy, so the sync worker thread survives it and
+/// keeps executing other tasks.
+fn call_sync_task<F, R>(f: F) -> JoinResult<R>
+where
+    F: FnOnce() -> R,
+{
+    // The closure is consumed by the call, so nothing can observe it in a broken state afterwards.
+    panic::catch_unwind(AssertUnwindSafe(f)).map_err(
+        |payload| match panic_policy::handle_task_panic(payload) {
+            Some(payload) => JoinError::Panic(payload),
+            None => JoinError::Cancelled,
+        },
+    )
+}
+
 fn next_async_worker(max: usize) -> usize {
-    let next = NEXT_ASYNC_WORKER_INDEX.get();
+    // The maximum may have been lowered since the previous call.
+    let next = NEXT_ASYNC_WORKER_INDEX.get() % max;
     NEXT_ASYNC_WORKER_INDEX.set((next + 1) % max);
     next
 }
 
 fn next_sync_processor(max: usize) -> usize {
-    let next = NEXT_SYNC_PROCESSOR_INDEX.get();
+    // The maximum may have been lowered since the previous call.
+    let next = NEXT_SYNC_PROCESSOR_INDEX.get() % max;
     NEXT_SYNC_PROCESSOR_INDEX.set((next + 1) % max);
     next
 }
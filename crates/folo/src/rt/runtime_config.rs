@@ -0,0 +1,323 @@
+use crate::{
+    config::{self, Error},
+    rt::{Affinity, IdleStrategy, Profile, RuntimeBuilder},
+};
+use serde::{
+    de::{value, DeserializeOwned, IntoDeserializer},
+    Deserialize,
+};
+use std::{env, str::FromStr, time::Duration};
+
+const ENV_WORKER_THREADS: &str = "FOLO_WORKER_THREADS";
+const ENV_PROFILE: &str = "FOLO_PROFILE";
+const ENV_BLOCKING_THREADS: &str = "FOLO_BLOCKING_THREADS";
+const ENV_STEALING: &str = "FOLO_STEALING";
+const ENV_COOP_BUDGET: &str = "FOLO_COOP_BUDGET";
+const ENV_MAX_TASKS: &str = "FOLO_MAX_TASKS";
+const ENV_MAX_TASKS_PER_WORKER: &str = "FOLO_MAX_TASKS_PER_WORKER";
+const ENV_MAX_CONNECTIONS: &str = "FOLO_MAX_CONNECTIONS";
+const ENV_MAX_OPEN_HANDLES: &str = "FOLO_MAX_OPEN_HANDLES";
+const ENV_SLOW_POLL_THRESHOLD_MS: &str = "FOLO_SLOW_POLL_THRESHOLD_MS";
+
+/// Tuning of a runtime that can be loaded at deployment time (e.g. from a JSON or TOML file or
+/// from environment variables, via any `serde` data format) instead of being compiled into the
+/// application. Applied via [`RuntimeBuilder::from_config()`].
+///
+/// Every field is optional - options that are not set keep the default of the builder. The
+/// profile is applied first, so the individual options override the values set by the profile.
+/// See the builder method of the same name for the meaning of each option.
+///
+/// Operators can tune a deployed binary without a code change via environment variables (see
+/// [`from_env()`][Self::from_env]). Configurations from several sources (e.g. a file, command line
+/// arguments parsed via `clap` and the environment) are combined via [`merge()`][Self::merge].
+///
+/// # Example
+///
+/// ```
+/// use folo::rt::{RuntimeBuilder, RuntimeConfig};
+///
+/// let config: RuntimeConfig = serde_json::from_str(
+///     r#"{
+///         "max_processors": 4,
+///         "affinity": "physical_cores",
+///         "profile": "low_latency",
+///         "sync_workers_per_processor": 4
+///     }"#,
+/// )
+/// .unwrap();
+///
+/// let runtime = RuntimeBuilder::from_config(&config).build().unwrap();
+/// runtime.stop();
+/// runtime.wait();
+/// ```
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct RuntimeConfig {
+    /// Limits the number of processors and therefore the number of async worker threads.
+    pub max_processors: Option<usize>,
+    pub affinity: Option<Affinity>,
+    pub profile: Option<Profile>,
+    pub idle_strategy: Option<IdleStrategy>,
+    pub stealing: Option<bool>,
+    pub coop_budget: Option<u32>,
+    pub max_lifo_streak: Option<usize>,
+    pub max_tasks_per_worker: Option<usize>,
+    pub max_tasks: Option<usize>,
+
+    /// The size of the pool of threads that execute blocking work, per processor.
+    pub sync_workers_per_processor: Option<usize>,
+    pub stack_size: Option<usize>,
+    pub io_buffers_per_worker: Option<usize>,
+    pub register_io_buffers: Option<bool>,
+    pub io_completion_batch_size: Option<usize>,
+    pub io_completion_batches_per_cycle: Option<usize>,
+    pub max_connections: Option<usize>,
+    pub max_open_handles: Option<usize>,
+    pub slow_poll_threshold_ms: Option<u64>,
+
+    /// The interval of the maintenance tick, which also paces the periodic work of the workers
+    /// (e.g. trimming caches and invoking `on_maintenance_tick()` callbacks that publish metrics).
+    pub maintenance_interval_ms: Option<u64>,
+}
+
+impl RuntimeConfig {
+    /// Loads the options that are set via environment variables. Options whose variable is not
+    /// set are left unset.
+    ///
+    /// | Variable                      | Option                         |
+    /// |-------------------------------|--------------------------------|
+    /// | `FOLO_WORKER_THREADS`         | `max_processors`               |
+    /// | `FOLO_PROFILE`                | `profile` (e.g. `low_latency`) |
+    /// | `FOLO_BLOCKING_THREADS`       | `sync_workers_per_processor`   |
+    /// | `FOLO_STEALING`               | `stealing` (`true` or `false`) |
+    /// | `FOLO_COOP_BUDGET`            | `coop_budget`                  |
+    /// | `FOLO_MAX_TASKS`              | `max_tasks`                    |
+    /// | `FOLO_MAX_TASKS_PER_WORKER`   | `max_tasks_per_worker`         |
+    /// | `FOLO_MAX_CONNECTIONS`        | `max_connections`              |
+    /// | `FOLO_MAX_OPEN_HANDLES`       | `max_open_handles`             |
+    /// | `FOLO_SLOW_POLL_THRESHOLD_MS` | `slow_poll_threshold_ms`       |
+    ///
+    /// Returns an error if a variable is set to a value that is not valid for its option.
+    pub fn from_env() -> config::Result<Self> {
+        Ok(Self {
+            max_processors: env_var(ENV_WORKER_THREADS, parse_from_str)?,
+            profile: env_var(ENV_PROFILE, parse_deserialize)?,
+            sync_workers_per_processor: env_var(ENV_BLOCKING_THREADS, parse_from_str)?,
+            stealing: env_var(ENV_STEALING, parse_from_str)?,
+            coop_budget: env_var(ENV_COOP_BUDGET, parse_from_str)?,
+            max_tasks: env_var(ENV_MAX_TASKS, parse_from_str)?,
+            max_tasks_per_worker: env_var(ENV_MAX_TASKS_PER_WORKER, parse_from_str)?,
+            max_connections: env_var(ENV_MAX_CONNECTIONS, parse_from_str)?,
+            max_open_handles: env_var(ENV_MAX_OPEN_HANDLES, parse_from_str)?,
+            slow_poll_threshold_ms: env_var(ENV_SLOW_POLL_THRESHOLD_MS, parse_from_str)?,
+            ..Default::default()
+        })
+    }
+
+    /// Overrides the options of this configuration with the options that are set in `overrides`,
+    /// keeping the options that are not set there. Apply the sources from the least to the most
+    /// specific, e.g. the configuration file, then command line arguments, then the environment.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// #[derive(clap::Parser)]
+    /// struct Args {
+    ///     #[arg(long)]
+    ///     worker_threads: Option<usize>,
+    /// }
+    ///
+    /// let args = Args::parse();
+    ///
+    /// let mut config = RuntimeConfig::default();
+    /// config.merge(RuntimeConfig {
+    ///     max_processors: args.worker_threads,
+    ///     ..Default::default()
+    /// });
+    /// config.merge(RuntimeConfig::from_env()?);
+    ///
+    /// let runtime = RuntimeBuilder::from_config(&config).build()?;
+    /// ```
+    pub fn merge(&mut self, overrides: RuntimeConfig) {
+        // Destructured so that a new option cannot be forgotten here.
+        let RuntimeConfig {
+            max_processors,
+            affinity,
+            profile,
+            idle_strategy,
+            stealing,
+            coop_budget,
+            max_lifo_streak,
+            max_tasks_per_worker,
+            max_tasks,
+            sync_workers_per_processor,
+            stack_size,
+            io_buffers_per_worker,
+            register_io_buffers,
+            io_completion_batch_size,
+            io_completion_batches_per_cycle,
+            max_connections,
+            max_open_handles,
+            slow_poll_threshold_ms,
+            maintenance_interval_ms,
+        } = overrides;
+
+        merge_option(&mut self.max_processors, max_processors);
+        merge_option(&mut self.affinity, affinity);
+        merge_option(&mut self.profile, profile);
+        merge_option(&mut self.idle_strategy, idle_strategy);
+        merge_option(&mut self.stealing, stealing);
+        merge_option(&mut self.coop_budget, coop_budget);
+        merge_option(&mut self.max_lifo_streak, max_lifo_streak);
+        merge_option(&mut self.max_tasks_per_worker, max_tasks_per_worker);
+        merge_option(&mut self.max_tasks, max_tasks);
+        merge_option(
+            &mut self.sync_workers_per_processor,
+            sync_workers_per_processor,
+        );
+        merge_option(&mut self.stack_size, stack_size);
+        merge_option(&mut self.io_buffers_per_worker, io_buffers_per_worker);
+        merge_option(&mut self.register_io_buffers, register_io_buffers);
+        merge_option(&mut self.io_completion_batch_size, io_completion_batch_size);
+        merge_option(
+            &mut self.io_completion_batches_per_cycle,
+            io_completion_batches_per_cycle,
+        );
+        merge_option(&mut self.max_connections, max_connections);
+        merge_option(&mut self.max_open_handles, max_open_handles);
+        merge_option(&mut self.slow_poll_threshold_ms, slow_poll_threshold_ms);
+        merge_option(&mut self.maintenance_interval_ms, maintenance_interval_ms);
+    }
+}
+
+impl RuntimeBuilder {
+    /// Creates a builder with the options set in the configuration. The builder can be further
+    /// customized before building the runtime, e.g. to register callbacks.
+    pub fn from_config(config: &RuntimeConfig) -> Self {
+        Self::new().apply_config(config)
+    }
+
+    /// Applies the options that are set in the configuration on top of the options already set on
+    /// the builder, e.g. to let operators override the tuning chosen by the application via
+    /// `RuntimeConfig::from_env()`.
+    ///
+    /// If the configuration sets a profile, it is applied first, so it replaces the values of the
+    /// options it covers that were set on the builder before.
+    pub fn apply_config(self, config: &RuntimeConfig) -> Self {
+        let mut builder = self;
+
+        // The profile goes first, as it sets several other options.
+        if let Some(profile) = config.profile {
+            builder = builder.profile(profile);
+        }
+
+        if let Some(value) = config.max_processors {
+            builder = builder.max_processors(value);
+        }
+
+        if let Some(value) = &config.affinity {
+            builder = builder.affinity(value.clone());
+        }
+
+        if let Some(value) = config.idle_strategy {
+            builder = builder.idle_strategy(value);
+        }
+
+        if let Some(value) = config.stealing {
+            builder = builder.stealing(value);
+        }
+
+        if let Some(value) = config.coop_budget {
+            builder = builder.coop_budget(value);
+        }
+
+        if let Some(value) = config.max_lifo_streak {
+            builder = builder.max_lifo_streak(value);
+        }
+
+        if let Some(value) = config.max_tasks_per_worker {
+            builder = builder.max_tasks_per_worker(value);
+        }
+
+        if let Some(value) = config.max_tasks {
+            builder = builder.max_tasks(value);
+        }
+
+        if let Some(value) = config.sync_workers_per_processor {
+            builder = builder.sync_workers_per_processor(value);
+        }
+
+        if let Some(value) = config.stack_size {
+            builder = builder.stack_size(value);
+        }
+
+        if let Some(value) = config.io_buffers_per_worker {
+            builder = builder.io_buffers_per_worker(value);
+        }
+
+        if let Some(value) = config.register_io_buffers {
+            builder = builder.register_io_buffers(value);
+        }
+
+        if let Some(value) = config.io_completion_batch_size {
+            builder = builder.io_completion_batch_size(value);
+        }
+
+        if let Some(value) = config.io_completion_batches_per_cycle {
+            builder = builder.io_completion_batches_per_cycle(value);
+        }
+
+        if let Some(value) = config.max_connections {
+            builder = builder.max_connections(value);
+        }
+
+        if let Some(value) = config.max_open_handles {
+            builder = builder.max_open_handles(value);
+        }
+
+        if let Some(value) = config.slow_poll_threshold_ms {
+            builder = builder.slow_poll_threshold(Duration::from_millis(value));
+        }
+
+        if let Some(value) = config.maintenance_interval_ms {
+            builder = builder.maintenance_interval(Duration::from_millis(value));
+        }
+
+        builder
+    }
+}
+
+fn merge_option<T>(target: &mut Option<T>, value: Option<T>) {
+    if value.is_some() {
+        *target = value;
+    }
+}
+
+/// Reads and parses an environment variable, returning `None` if it is not set.
+fn env_var<T>(
+    name: &'static str,
+    parse: impl FnOnce(&str) -> Option<T>,
+) -> config::Result<Option<T>> {
+    let Some(value) = env::var_os(name) else {
+        return Ok(None);
+    };
+
+    let value = value.to_string_lossy();
+
+    match parse(value.trim()) {
+        Some(parsed) => Ok(Some(parsed)),
+        None => Err(Error::InvalidEnvironmentVariable {
+            name,
+            value: value.into_owned(),
+        }),
+    }
+}
+
+fn parse_from_str<T: FromStr>(value: &str) -> Option<T> {
+    value.parse().ok()
+}
+
+// Enums are parsed from the same names as in configuration files, e.g. `low_latency`.
+fn parse_deserialize<T: DeserializeOwned>(value: &str) -> Option<T> {
+    T::deserialize(IntoDeserializer::<value::Error>::into_deserializer(value)).ok()
+}
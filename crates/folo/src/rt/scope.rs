@@ -0,0 +1,289 @@
+use crate::{
+    metrics::{observe, Event, EventBuilder},
+    rt::coop,
+};
+use futures::{
+    future::LocalBoxFuture,
+    stream::{FuturesUnordered, StreamExt},
+    FutureExt,
+};
+use negative_impl::negative_impl;
+use std::{
+    cell::RefCell,
+    fmt::{self, Debug, Formatter},
+    future::Future,
+    marker::PhantomData,
+    mem,
+    pin::Pin,
+    rc::Rc,
+    task::{self, ready, Waker},
+};
+
+/// Executes a group of concurrent tasks that may borrow data from the enclosing stack frame,
+/// similar to `std::thread::scope()`.
+///
+/// The closure receives a [`Scope`] that can be used to spawn tasks. Unlike tasks spawned via
+/// `spawn()`, scoped tasks do not need to be `'static`, so they can borrow local variables of the
+/// caller instead of sharing them via `Rc<RefCell<...>>`.
+///
+/// The returned future completes once the future returned by the closure and all the scoped tasks
+/// have completed. Scoped tasks are polled as part of the returned future (on the current worker
+/// thread), so they execute concurrently with each other but never in parallel. If the returned
+/// future is dropped before completion, all the scoped tasks are dropped with it.
+///
+/// # Example
+///
+/// ```
+/// use folo::rt::scope;
+///
+/// #[folo::main]
+/// async fn main() {
+///     let records = vec![1, 2, 3, 4, 5, 6];
+///     let mut total = 0;
+///
+///     scope(|s| {
+///         let (records, total) = (&records, &mut total);
+///
+///         Box::pin(async move {
+///             let first_half = s.spawn(async move { records[..3].iter().sum::<i32>() });
+///             let second_half = s.spawn(async move { records[3..].iter().sum::<i32>() });
+///
+///             *total = first_half.await + second_half.await;
+///         })
+///     })
+///     .await;
+///
+///     assert_eq!(total, 21);
+/// }
+/// ```
+pub async fn scope<'env, F, R>(f: F) -> R
+where
+    F: for<'scope> FnOnce(&'scope Scope<'scope, 'env>) -> LocalBoxFuture<'scope, R>,
+{
+    observe!(SCOPES.observe_unit());
+
+    let scope = Scope::new();
+
+    // NB! This is declared after the scope, so it is dropped first (it borrows the scope).
+    let mut body = f(&scope);
+    let mut result = None;
+
+    futures::future::poll_fn(|cx| {
+        if result.is_none() {
+            if let task::Poll::Ready(value) = body.poll_unpin(cx) {
+                result = Some(value);
+            }
+        }
+
+        ready!(scope.poll_tasks(cx));
+
+        match result.take() {
+            Some(value) => task::Poll::Ready(value),
+            None => task::Poll::Pending,
+        }
+    })
+    .await
+}
+
+/// Allows tasks to be spawned that borrow data from the stack frame that called [`scope()`].
+///
+/// `'env` is the lifetime of the data that the tasks may borrow and `'scope` is the lifetime of
+/// the scope itself - tasks may also borrow the scope to spawn more tasks.
+pub struct Scope<'scope, 'env: 'scope> {
+    tasks: ScopeTasks,
+
+    // Invariant over 'scope and 'env, just like `std::thread::Scope`.
+    _scope: PhantomData<&'scope mut &'scope ()>,
+    _env: PhantomData<&'env mut &'env ()>,
+}
+
+impl<'scope, 'env> Scope<'scope, 'env> {
+    fn new() -> Self {
+        Self {
+            tasks: ScopeTasks {
+                active: RefCell::new(FuturesUnordered::new()),
+                incoming: RefCell::new(Vec::new()),
+                waker: RefCell::new(None),
+            },
+            _scope: PhantomData,
+            _env: PhantomData,
+        }
+    }
+
+    /// Spawns a task in the scope. The task starts executing the next time the scope is polled.
+    ///
+    /// Awaiting the returned join handle is optional - the scope waits for the task to complete
+    /// either way.
+    pub fn spawn<F, R>(&'scope self, future: F) -> ScopedJoinHandle<R>
+    where
+        F: Future<Output = R> + 'scope,
+        R: 'scope,
+    {
+        observe!(SCOPED_TASKS.observe_unit());
+
+        let state = Rc::new(RefCell::new(ResultState::NotSet));
+        let task_state = Rc::clone(&state);
+
+        let task: LocalBoxFuture<'scope, ()> = Box::pin(async move {
+            let result = future.await;
+
+            let previous = mem::replace(&mut *task_state.borrow_mut(), ResultState::Set(result));
+
+            if let ResultState::Awaiting(waker) = previous {
+                waker.wake();
+            }
+        });
+
+        // SAFETY: Only the lifetime is changed, the layout is identical. The tasks never outlive
+        // 'scope because they are owned by the scope, which is owned by the future returned by
+        // `scope()` and never exposed by value. That future in turn cannot outlive 'env. If it is
+        // leaked instead of dropped, the tasks are leaked with it and never polled again.
+        let task: LocalBoxFuture<'static, ()> = unsafe { mem::transmute(task) };
+
+        self.tasks.incoming.borrow_mut().push(task);
+
+        if let Some(waker) = self.tasks.waker.borrow().as_ref() {
+            waker.wake_by_ref();
+        }
+
+        ScopedJoinHandle { state }
+    }
+
+    /// Polls the tasks of the scope, returning `Ready` once there are no more tasks to execute.
+    fn poll_tasks(&self, cx: &mut task::Context<'_>) -> task::Poll<()> {
+        {
+            let mut waker = self.tasks.waker.borrow_mut();
+
+            if !waker.as_ref().is_some_and(|w| w.will_wake(cx.waker())) {
+                *waker = Some(cx.waker().clone());
+            }
+        }
+
+        let mut active = self.tasks.active.borrow_mut();
+
+        loop {
+            active.extend(self.tasks.incoming.borrow_mut().drain(..));
+
+            match active.poll_next_unpin(cx) {
+                // One task completed - there may be more that are ready.
+                task::Poll::Ready(Some(())) => continue,
+                task::Poll::Ready(None) => {
+                    // Tasks completing may have spawned more tasks, in which case we keep going.
+                    if self.tasks.incoming.borrow().is_empty() {
+                        return task::Poll::Ready(());
+                    }
+                }
+                task::Poll::Pending => return task::Poll::Pending,
+            }
+        }
+    }
+}
+
+impl Debug for Scope<'_, '_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Scope")
+            .field("active", &self.tasks.active.borrow().len())
+            .field("incoming", &self.tasks.incoming.borrow().len())
+            .finish()
+    }
+}
+
+#[negative_impl]
+impl<'scope, 'env> !Send for Scope<'scope, 'env> {}
+#[negative_impl]
+impl<'scope, 'env> !Sync for Scope<'scope, 'env> {}
+
+// The tasks of a scope, with their lifetimes erased. This type deliberately has no lifetime
+// parameters, so the scope can be borrowed for its entire lifetime by the tasks it owns (just like
+// `std::thread::Scope`) without the borrow checker objecting to it being dropped.
+struct ScopeTasks {
+    // Tasks that are being executed by the scope.
+    active: RefCell<FuturesUnordered<LocalBoxFuture<'static, ()>>>,
+
+    // Tasks that have been spawned but not yet picked up. We cannot add them directly to `active`
+    // because a task may be spawned by another task, while `active` is borrowed for polling.
+    incoming: RefCell<Vec<LocalBoxFuture<'static, ()>>>,
+
+    // The waker of the future returned by `scope()`, woken when new tasks are spawned.
+    waker: RefCell<Option<Waker>>,
+}
+
+impl Drop for ScopeTasks {
+    fn drop(&mut self) {
+        // We drop the tasks while the rest of the scope is still intact, in case dropping a task
+        // touches the scope (e.g. spawns another task, which we then also drop here).
+        let active = mem::take(&mut *self.active.borrow_mut());
+        drop(active);
+
+        loop {
+            let incoming = mem::take(&mut *self.incoming.borrow_mut());
+
+            if incoming.is_empty() {
+                break;
+            }
+
+            drop(incoming);
+        }
+    }
+}
+
+/// Allows the result of a task spawned via [`Scope::spawn()`] to be awaited.
+pub struct ScopedJoinHandle<R> {
+    state: Rc<RefCell<ResultState<R>>>,
+}
+
+impl<R> Future for ScopedJoinHandle<R> {
+    type Output = R;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> task::Poll<Self::Output> {
+        coop::poll_budgeted(cx, |cx| {
+            let mut state = self.state.borrow_mut();
+
+            match mem::replace(&mut *state, ResultState::Consumed) {
+                ResultState::Set(result) => task::Poll::Ready(result),
+                ResultState::NotSet | ResultState::Awaiting(_) => {
+                    *state = ResultState::Awaiting(cx.waker().clone());
+                    task::Poll::Pending
+                }
+                // The futures API contract allows us to panic in this situation.
+                ResultState::Consumed => {
+                    panic!("ScopedJoinHandle polled after result was consumed")
+                }
+            }
+        })
+    }
+}
+
+impl<R> Debug for ScopedJoinHandle<R> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let state = match &*self.state.borrow() {
+            ResultState::NotSet | ResultState::Awaiting(_) => "pending",
+            ResultState::Set(_) => "completed",
+            ResultState::Consumed => "consumed",
+        };
+
+        f.debug_struct("ScopedJoinHandle")
+            .field("state", &state)
+            .finish()
+    }
+}
+
+#[negative_impl]
+impl<R> !Send for ScopedJoinHandle<R> {}
+#[negative_impl]
+impl<R> !Sync for ScopedJoinHandle<R> {}
+
+enum ResultState<R> {
+    NotSet,
+    Awaiting(Waker),
+    Set(R),
+    Consumed,
+}
+
+thread_local! {
+    static SCOPES: Event = EventBuilder::new("rt_scopes")
+        .build();
+
+    static SCOPED_TASKS: Event = EventBuilder::new("rt_scoped_tasks")
+        .build();
+}
@@ -0,0 +1,219 @@
+use crate::{
+    constants,
+    metrics::{observe, Event, EventBuilder},
+    rt::{current_async_agent, current_runtime, panic_policy::panic_message, TaskMeta},
+    sync::CancellationToken,
+    time::{Clock, Delay},
+};
+use futures::{future::LocalBoxFuture, FutureExt};
+use std::{
+    collections::HashMap,
+    fmt::{self, Debug, Formatter},
+    future::Future,
+    panic::AssertUnwindSafe,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+use tracing::{event, Level};
+
+/// Runs a background task on exactly one async worker thread of the runtime, instead of one per
+/// worker. Useful for work that must not be duplicated, such as exporting metrics or refreshing a
+/// shared cache.
+///
+/// Singletons are identified by name. If a singleton with the same name is already running on the
+/// runtime, no new one is started - the factory is dropped and a handle to the existing singleton
+/// is returned. Once a singleton has finished, the name may be used again.
+///
+/// The factory is called to create the future of the task, which starts on the current worker
+/// thread. If the future panics, the panic is caught and the task is restarted on the next worker
+/// thread, calling the factory again. If the future completes normally, the singleton is finished
+/// and is not restarted.
+///
+/// The state of the singleton is owned by the runtime, not by any particular worker thread - each
+/// instance of the task starts the next one itself if it panics.
+///
+/// # Panics
+///
+/// Panics if the current thread is not an async worker thread owned by a Folo runtime.
+pub fn spawn_singleton<FN, F>(name: impl Into<Arc<str>>, factory: FN) -> SingletonHandle
+where
+    FN: Fn() -> F + Send + Sync + 'static,
+    F: Future<Output = ()> + 'static,
+{
+    let name = name.into();
+
+    // We start on the current worker, as good as any other.
+    let current_processor_id = current_async_agent::with(|agent| agent.processor_id());
+
+    let worker_index = current_runtime::with(|runtime| {
+        runtime
+            .processor_ids()
+            .iter()
+            .position(|x| *x == current_processor_id)
+            .unwrap_or_default()
+    });
+
+    let state = current_runtime::with(|runtime| {
+        let mut singletons = runtime.singletons().lock().expect(constants::POISONED_LOCK);
+
+        if let Some(existing) = singletons.get(&name) {
+            return Err(existing.clone());
+        }
+
+        let state = Arc::new(SingletonState {
+            name: Arc::clone(&name),
+            factory: Box::new(move || factory().boxed_local()),
+            failovers: AtomicUsize::new(0),
+            stop: CancellationToken::new(),
+            finished: CancellationToken::new(),
+        });
+
+        singletons.insert(
+            name,
+            SingletonHandle {
+                state: Arc::clone(&state),
+            },
+        );
+
+        Ok(state)
+    });
+
+    match state {
+        Ok(state) => {
+            start_instance(Arc::clone(&state), worker_index, Duration::ZERO);
+            SingletonHandle { state }
+        }
+        Err(existing) => existing,
+    }
+}
+
+/// Controls a singleton task started via [`spawn_singleton()`]. All the handles of a singleton
+/// control the same task, no matter which call to `spawn_singleton()` returned them.
+///
+/// Awaiting this is optional - the singleton continues running even if you drop the handle.
+#[derive(Clone, Debug)]
+pub struct SingletonHandle {
+    state: Arc<SingletonState>,
+}
+
+impl SingletonHandle {
+    /// Stops the singleton. The current instance of the task is dropped at its next yield point
+    /// and no new instance is started.
+    pub fn stop(&self) {
+        self.state.stop.cancel();
+    }
+
+    /// Returns the number of times the singleton has been restarted on another worker thread after
+    /// a panic.
+    pub fn failovers(&self) -> usize {
+        self.state.failovers.load(Ordering::Relaxed)
+    }
+
+    /// Waits for the singleton to finish, either by the task completing normally or by the
+    /// singleton being stopped.
+    pub async fn wait(self) {
+        self.state.finished.cancelled().await;
+    }
+}
+
+/// The singletons running on a runtime, by name. Owned by the runtime client.
+pub(crate) type SingletonRegistry = Mutex<HashMap<Arc<str>, SingletonHandle>>;
+
+type SingletonFactory = Box<dyn Fn() -> LocalBoxFuture<'static, ()> + Send + Sync>;
+
+struct SingletonState {
+    name: Arc<str>,
+    factory: SingletonFactory,
+    failovers: AtomicUsize,
+
+    // Cancelled by `SingletonHandle::stop()`. Every instance of the task runs until this is
+    // cancelled, so it also covers an instance that has not yet started when the stop arrives.
+    stop: CancellationToken,
+
+    // Cancelled once the singleton has finished and been removed from the registry.
+    finished: CancellationToken,
+}
+
+impl SingletonState {
+    fn finish(self: &Arc<Self>) {
+        current_runtime::with(|runtime| {
+            let mut singletons = runtime.singletons().lock().expect(constants::POISONED_LOCK);
+
+            // Nobody else can register under our name before we are removed, so this is a sanity
+            // check - we must not remove another singleton under any circumstances.
+            if singletons
+                .get(&self.name)
+                .is_some_and(|handle| Arc::ptr_eq(&handle.state, self))
+            {
+                singletons.remove(&self.name);
+            }
+        });
+
+        self.finished.cancel();
+    }
+}
+
+impl Debug for SingletonState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SingletonState")
+            .field("name", &self.name)
+            .field("failovers", &self.failovers)
+            .field("stop", &self.stop)
+            .field("finished", &self.finished)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Starts an instance of the singleton task on an async worker, after the given delay.
+fn start_instance(state: Arc<SingletonState>, worker_index: usize, delay: Duration) {
+    current_runtime::with(|runtime| {
+        // Nobody waits for the task itself - the singleton handle observes the state instead.
+        _ = runtime.spawn_on_processor(
+            runtime.processor_ids()[worker_index],
+            TaskMeta::new(Some(Arc::clone(&state.name))),
+            move || run_instance(state, worker_index, delay),
+        );
+    });
+}
+
+async fn run_instance(state: Arc<SingletonState>, worker_index: usize, delay: Duration) {
+    let instance = async {
+        if !delay.is_zero() {
+            Delay::with_clock(&Clock::new(), delay).await;
+        }
+
+        AssertUnwindSafe((state.factory)()).catch_unwind().await
+    };
+
+    let Some(Err(payload)) = state.stop.run_until_cancelled(instance).await else {
+        // Completed normally or stopped - either way, the singleton has done what it was meant to.
+        state.finish();
+        return;
+    };
+
+    observe!(FAILOVERS.observe_unit());
+    state.failovers.fetch_add(1, Ordering::Relaxed);
+
+    let next_worker_index = (worker_index + 1) % current_runtime::with(|x| x.worker_count());
+
+    event!(
+        Level::WARN,
+        message = "singleton task panicked; restarting on another worker",
+        name = &*state.name,
+        panic = panic_message(&*payload),
+        next_worker_index
+    );
+
+    // If the task panics immediately on startup, we do not want to spin in a tight loop.
+    start_instance(state, next_worker_index, FAILOVER_DELAY);
+}
+
+const FAILOVER_DELAY: Duration = Duration::from_millis(100);
+
+thread_local! {
+    static FAILOVERS: Event = EventBuilder::new("rt_singleton_failovers")
+        .build();
+}
@@ -0,0 +1,106 @@
+use super::erased_async_task::ErasedResultAsyncTask;
+use crossbeam::queue::SegQueue;
+use std::{
+    fmt::{self, Debug, Formatter},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+type StealableTask = Pin<Box<dyn ErasedResultAsyncTask + Send>>;
+
+/// The queues of remote tasks that have not yet started executing, one per async worker, used when
+/// work stealing is enabled. Each instance is a view from the perspective of one worker, which
+/// owns one of the queues.
+///
+/// Only tasks that have not started are stealable because they are still `Send`. Once a task
+/// starts on a worker, it may create thread-bound state and remains on that worker forever.
+#[derive(Clone)]
+pub(crate) struct StealableQueues {
+    queues: Arc<[SegQueue<StealableTask>]>,
+    own_index: usize,
+
+    // Workers at or above this index are retired and do not steal. Shared with the runtime client.
+    active_worker_count: Arc<AtomicUsize>,
+}
+
+impl StealableQueues {
+    /// Creates a set of queues for the specified number of workers, returning the view of each
+    /// worker in order.
+    pub fn new_set(worker_count: usize, active_worker_count: Arc<AtomicUsize>) -> Vec<Self> {
+        let queues: Arc<[_]> = (0..worker_count).map(|_| SegQueue::new()).collect();
+
+        (0..worker_count)
+            .map(|own_index| Self {
+                queues: Arc::clone(&queues),
+                own_index,
+                active_worker_count: Arc::clone(&active_worker_count),
+            })
+            .collect()
+    }
+
+    pub fn push(&self, task: StealableTask) {
+        self.queues[self.own_index].push(task);
+    }
+
+    pub fn pop_own(&self) -> Option<StealableTask> {
+        self.queues[self.own_index].pop()
+    }
+
+    /// Takes up to half of the queued tasks of the first sibling that has any, starting with the
+    /// next worker after us so that the load of stealing is spread among siblings. Each stolen task
+    /// is handed to the callback. Returns the number of stolen tasks.
+    ///
+    /// Retired workers do not steal, while their queues remain open to stealing by others, so any
+    /// tasks still queued on a retired worker are drained by the active workers.
+    pub fn steal(&self, mut accept: impl FnMut(StealableTask)) -> usize {
+        if self.own_index >= self.active_worker_count.load(Ordering::Relaxed) {
+            return 0;
+        }
+
+        let worker_count = self.queues.len();
+
+        for offset in 1..worker_count {
+            let sibling = &self.queues[(self.own_index + offset) % worker_count];
+
+            let available = sibling.len();
+
+            if available == 0 {
+                continue;
+            }
+
+            let mut stolen = 0;
+
+            while stolen < available.div_ceil(2).min(MAX_STEAL_BATCH_SIZE) {
+                // The owner may have taken the tasks between our length check and now.
+                let Some(task) = sibling.pop() else {
+                    break;
+                };
+
+                accept(task);
+                stolen += 1;
+            }
+
+            if stolen > 0 {
+                return stolen;
+            }
+        }
+
+        0
+    }
+}
+
+impl Debug for StealableQueues {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StealableQueues")
+            .field("own_index", &self.own_index)
+            .field("own_len", &self.queues[self.own_index].len())
+            .finish()
+    }
+}
+
+/// Upper bound on how many tasks a worker steals at once. Stealing too much at once just moves the
+/// overload to the thief.
+const MAX_STEAL_BATCH_SIZE: usize = 32;
@@ -1,7 +1,7 @@
 use super::ErasedSyncTask;
 use crate::{
     constants::GENERAL_MILLISECONDS_BUCKETS,
-    metrics::{self, Event, EventBuilder, Magnitude, ReportPage},
+    metrics::{self, observe, Event, EventBuilder, Magnitude, ReportPage},
 };
 use crossbeam::{channel, queue::SegQueue};
 use std::{fmt::Debug, sync::Arc};
@@ -53,7 +53,7 @@ impl SyncAgent {
         // There is a risk of a huge buildup of commands with a pending terminate at the very end
         // but we are not going to worry about that for now.
         while let Ok(command) =
-            TASK_INTERVAL.with(|x| x.observe_duration_millis(|| self.command_rx.recv()))
+            observe!(TASK_INTERVAL.observe_duration_millis(|| self.command_rx.recv()))
         {
             match command {
                 SyncAgentCommand::CheckForTasks => {
@@ -61,9 +61,13 @@ impl SyncAgent {
                         let task_addr = format!("{:p}", &*task);
                         event!(Level::TRACE, message = "executing task", task_addr);
 
-                        TASKS.with(Event::observe_unit);
-                        TASK_DURATION.with(|x| x.observe_duration_millis(task));
+                        observe!(TASKS.observe_unit());
+                        observe!(TASK_DURATION.observe_duration_millis(task));
                     }
+
+                    // Our metrics only change when we execute tasks, so this is when we persist.
+                    #[cfg(feature = "metrics-persistence")]
+                    metrics::persist_if_due();
                 }
                 SyncAgentCommand::Terminate => {
                     event!(
@@ -80,8 +84,8 @@ impl SyncAgent {
         // without which we cannot safely shut down (because the OS is holding references into our
         // memory).
         while let Some(task) = self.priority_task_queue.pop() {
-            TASKS.with(Event::observe_unit);
-            TASK_DURATION.with(|x| x.observe_duration_millis(task));
+            observe!(TASKS.observe_unit());
+            observe!(TASK_DURATION.observe_duration_millis(task));
         }
 
         event!(
@@ -89,14 +93,17 @@ impl SyncAgent {
             "shutdown completed - no high-priority tasks remaining"
         );
 
+        #[cfg(feature = "metrics-persistence")]
+        metrics::persist_final();
+
         if let Some(tx) = &self.metrics_tx {
             _ = tx.send(metrics::report_page());
         }
     }
 
     fn next_task(&self) -> Option<ErasedSyncTask> {
-        LOW_PRIORITY_QUEUE_SIZE.with(|x| x.observe(self.task_queue.len() as i64));
-        HIGH_PRIORITY_QUEUE_SIZE.with(|x| x.observe(self.priority_task_queue.len() as i64));
+        observe!(LOW_PRIORITY_QUEUE_SIZE.observe(self.task_queue.len() as i64));
+        observe!(HIGH_PRIORITY_QUEUE_SIZE.observe(self.priority_task_queue.len() as i64));
 
         self.priority_task_queue
             .pop()
@@ -0,0 +1,223 @@
+//! Recycles the heap allocations of task frames (the state of a task, including its future) on the
+//! current thread.
+//!
+//! Tasks are pinned for their entire life, so each task frame is a separate heap allocation. With
+//! workloads that spawn millions of short-lived tasks per second, a fresh allocation for every
+//! spawn puts significant pressure on the allocator, so instead of returning the memory of a
+//! dropped task to the allocator, we keep it in a free list of the same size class and hand it out
+//! to the next task with the same layout. As a task type is typically spawned many times, most
+//! spawns end up reusing the memory of an earlier task of the same type, which is also likely to
+//! still be hot in the cache.
+
+use crate::metrics::{observe, Event, EventBuilder};
+use std::{
+    alloc::{self, Layout},
+    cell::RefCell,
+    collections::HashMap,
+    pin::Pin,
+    ptr::{self, NonNull},
+};
+
+/// Moves a value into a pinned heap allocation, reusing the allocation of a previously released
+/// frame with the same layout if one is available.
+pub(crate) fn allocate_pinned<T>(value: T) -> Pin<Box<T>> {
+    let layout = Layout::new::<T>();
+
+    // Zero-sized values are not allocated at all, so there is nothing to reuse.
+    if layout.size() == 0 {
+        return Box::pin(value);
+    }
+
+    let Some(frame) = FRAMES.with_borrow_mut(|frames| frames.take(layout)) else {
+        observe!(FRAMES_ALLOCATED.observe_unit());
+        return Box::pin(value);
+    };
+
+    observe!(FRAMES_REUSED.observe_unit());
+
+    let frame = frame.as_ptr() as *mut T;
+
+    // SAFETY: The frame was allocated by the global allocator for a value with the same layout
+    // (which is what `Box` requires) and is not referenced by anyone else since it was released.
+    unsafe {
+        frame.write(value);
+        Pin::new_unchecked(Box::from_raw(frame))
+    }
+}
+
+/// Drops the value in a pinned heap allocation in place and keeps the allocation for reuse by a
+/// future call to `allocate_pinned()`.
+pub(crate) fn release<T: ?Sized>(frame: Pin<Box<T>>) {
+    // SAFETY: We drop the value in place without moving it, so the pinning guarantee is upheld.
+    let frame = Box::into_raw(unsafe { Pin::into_inner_unchecked(frame) });
+
+    // SAFETY: The pointer came from a live `Box` just above.
+    let layout = Layout::for_value(unsafe { &*frame });
+
+    if layout.size() == 0 {
+        // SAFETY: Same as above - there is no allocation, we just need to drop the value.
+        drop(unsafe { Box::from_raw(frame) });
+        return;
+    }
+
+    // SAFETY: The pointer came from a live `Box`, which we have taken ownership of. We do not use
+    // the value after dropping it, only the memory it occupied.
+    unsafe { ptr::drop_in_place(frame) };
+
+    // SAFETY: Pointers from a `Box` are never null.
+    let memory = unsafe { NonNull::new_unchecked(frame as *mut u8) };
+
+    // If the thread is shutting down and the free lists are gone, we just give the memory back.
+    let kept = FRAMES
+        .try_with(|frames| frames.borrow_mut().put(layout, memory))
+        .unwrap_or(false);
+
+    if !kept {
+        observe!(FRAMES_FREED.observe_unit());
+
+        // SAFETY: The memory was allocated by the global allocator with this layout (via `Box`).
+        unsafe { alloc::dealloc(memory.as_ptr(), layout) };
+    }
+}
+
+/// Returns half of the frames kept for reuse to the allocator, so memory held after a burst of
+/// tasks is gradually released if the burst does not repeat. Called periodically by the worker.
+pub(crate) fn trim() {
+    FRAMES.with_borrow_mut(FramePool::trim);
+}
+
+/// Free lists of released frames, by layout.
+#[derive(Debug, Default)]
+struct FramePool {
+    free: HashMap<Layout, Vec<NonNull<u8>>>,
+}
+
+impl FramePool {
+    fn take(&mut self, layout: Layout) -> Option<NonNull<u8>> {
+        self.free.get_mut(&layout)?.pop()
+    }
+
+    /// Returns `false` if the free list of the size class is full, in which case the caller
+    /// remains responsible for the memory.
+    fn put(&mut self, layout: Layout, memory: NonNull<u8>) -> bool {
+        let free = self.free.entry(layout).or_default();
+
+        if free.len() >= MAX_FREE_FRAMES_PER_LAYOUT {
+            return false;
+        }
+
+        free.push(memory);
+        true
+    }
+
+    fn trim(&mut self) {
+        for (layout, frames) in &mut self.free {
+            let keep = frames.len() / 2;
+
+            for memory in frames.drain(keep..) {
+                observe!(FRAMES_FREED.observe_unit());
+
+                // SAFETY: The memory was allocated by the global allocator with this layout and
+                // nobody else references it since it was released to us.
+                unsafe { alloc::dealloc(memory.as_ptr(), *layout) };
+            }
+        }
+
+        self.free.retain(|_, frames| !frames.is_empty());
+    }
+}
+
+impl Drop for FramePool {
+    fn drop(&mut self) {
+        for (layout, frames) in self.free.drain() {
+            for memory in frames {
+                // SAFETY: The memory was allocated by the global allocator with this layout and
+                // nobody else references it since it was released to us.
+                unsafe { alloc::dealloc(memory.as_ptr(), layout) };
+            }
+        }
+    }
+}
+
+/// How many released frames of each layout we keep around for reuse. This bounds the memory we hold
+/// on to after a burst of tasks has completed.
+const MAX_FREE_FRAMES_PER_LAYOUT: usize = 1024;
+
+thread_local! {
+    static FRAMES: RefCell<FramePool> = RefCell::new(FramePool::default());
+
+    static FRAMES_ALLOCATED: Event = EventBuilder::new("rt_task_frames_allocated")
+        .build();
+
+    static FRAMES_REUSED: Event = EventBuilder::new("rt_task_frames_reused")
+        .build();
+
+    static FRAMES_FREED: Event = EventBuilder::new("rt_task_frames_freed")
+        .build();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{any::Any, cell::Cell, rc::Rc};
+
+    #[test]
+    fn released_frame_is_reused_for_same_layout() {
+        let first = allocate_pinned([1_u64; 4]);
+        let first_address = &*first as *const _ as usize;
+        release(first);
+
+        let second = allocate_pinned([2_u64; 4]);
+        assert_eq!(&*second as *const _ as usize, first_address);
+        assert_eq!(*second, [2_u64; 4]);
+
+        release(second);
+    }
+
+    #[test]
+    fn trim_releases_half_of_frames() {
+        let frames = (0..4)
+            .map(|_| allocate_pinned([3_u64; 5]))
+            .collect::<Vec<_>>();
+
+        for frame in frames {
+            release(frame);
+        }
+
+        let kept = || {
+            FRAMES.with_borrow(|frames| {
+                frames
+                    .free
+                    .get(&Layout::new::<[u64; 5]>())
+                    .map_or(0, Vec::len)
+            })
+        };
+
+        assert_eq!(kept(), 4);
+
+        trim();
+        assert_eq!(kept(), 2);
+
+        trim();
+        trim();
+        assert_eq!(kept(), 0);
+    }
+
+    #[test]
+    fn release_drops_value() {
+        struct DropCounter(Rc<Cell<usize>>);
+
+        impl Drop for DropCounter {
+            fn drop(&mut self) {
+                self.0.set(self.0.get() + 1);
+            }
+        }
+
+        let drops = Rc::new(Cell::new(0));
+
+        let frame: Pin<Box<dyn Any>> = allocate_pinned(DropCounter(Rc::clone(&drops)));
+        release(frame);
+
+        assert_eq!(drops.get(), 1);
+    }
+}
@@ -0,0 +1,231 @@
+use crate::{
+    io::IoQuota,
+    metrics::{observe, Event, EventBuilder},
+    rt::{abort::AbortState, spawn, JoinError, JoinResult},
+};
+use futures::{
+    future::LocalBoxFuture,
+    stream::{FuturesUnordered, StreamExt},
+    FutureExt,
+};
+use negative_impl::negative_impl;
+use std::{
+    fmt::{self, Debug, Formatter},
+    future::Future,
+    sync::Arc,
+};
+use thiserror::Error;
+
+/// A group of tasks on the current worker thread whose lifetimes are tied to the group, for
+/// structured concurrency ("nursery") patterns.
+///
+/// The child tasks are spawned via [`spawn()`][Self::spawn] and produce a `Result<T, E>`. Awaiting
+/// [`join()`][Self::join] collects the values of all the children in spawn order. By default, the
+/// first child to fail (return an error, panic or be cancelled) causes all the other children to be
+/// aborted and its error to be returned from `join()`; see
+/// [`cancel_on_failure()`][Self::cancel_on_failure].
+///
+/// Dropping the group (or the future returned by `join()`) aborts all children that have not
+/// completed, so background work never outlives the scope that started it.
+///
+/// # Example
+///
+/// ```
+/// use folo::rt::TaskGroup;
+///
+/// #[folo::main]
+/// async fn main() {
+///     let mut group = TaskGroup::<u32, String>::new();
+///
+///     for id in 1..=3 {
+///         group.spawn(async move { Ok(id * 10) });
+///     }
+///
+///     assert_eq!(group.join().await.unwrap(), vec![10, 20, 30]);
+/// }
+/// ```
+pub struct TaskGroup<T, E> {
+    // One entry per child, in spawn order. Used to abort children that have not completed.
+    aborts: Vec<Arc<AbortState>>,
+
+    // Completes with the index and result of each child, in completion order.
+    results: FuturesUnordered<LocalBoxFuture<'static, (usize, JoinResult<Result<T, E>>)>>,
+
+    cancel_on_failure: bool,
+
+    // Attached to every child spawned after it is set. See `io_quota()`.
+    io_quota: Option<IoQuota>,
+}
+
+impl<T, E> TaskGroup<T, E>
+where
+    T: 'static,
+    E: 'static,
+{
+    pub fn new() -> Self {
+        Self {
+            aborts: Vec::new(),
+            results: FuturesUnordered::new(),
+            cancel_on_failure: true,
+            io_quota: None,
+        }
+    }
+
+    /// Sets whether the failure of one child aborts all the other children. Defaults to `true`.
+    ///
+    /// If `false`, `join()` waits for all children to complete even if some fail, then returns the
+    /// first failure (in completion order).
+    pub fn cancel_on_failure(mut self, value: bool) -> Self {
+        self.cancel_on_failure = value;
+        self
+    }
+
+    /// Attaches an I/O quota to every child spawned in the group, limiting the rate of I/O they
+    /// perform together (along with any other tasks the quota is attached to). By default, there
+    /// is no quota.
+    pub fn io_quota(mut self, quota: IoQuota) -> Self {
+        self.io_quota = Some(quota);
+        self
+    }
+
+    /// Spawns a child task on the current worker thread.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the current thread is not an async worker thread owned by a Folo runtime.
+    pub fn spawn<F>(&mut self, future: F)
+    where
+        F: Future<Output = Result<T, E>> + 'static,
+    {
+        observe!(TASK_GROUP_CHILDREN.observe_unit());
+
+        let index = self.aborts.len();
+        let join_handle = match &self.io_quota {
+            Some(quota) => spawn(quota.scope(future)),
+            None => spawn(future),
+        };
+
+        self.aborts.push(join_handle.abort_state());
+        self.results.push(
+            join_handle
+                .result()
+                .map(move |result| (index, result))
+                .boxed_local(),
+        );
+    }
+
+    /// The number of children spawned in the group.
+    pub fn len(&self) -> usize {
+        self.aborts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.aborts.is_empty()
+    }
+
+    /// Requests all children to be aborted. Children that have already completed are not affected.
+    pub fn abort_all(&self) {
+        for abort in &self.aborts {
+            abort.request();
+        }
+    }
+
+    /// Waits for all the children to complete, returning their values in spawn order or the first
+    /// failure. See the type-level documentation for how failures are handled.
+    pub async fn join(mut self) -> Result<Vec<T>, TaskGroupError<E>> {
+        let mut values = Vec::with_capacity(self.aborts.len());
+        values.resize_with(self.aborts.len(), || None);
+
+        let mut first_failure = None;
+
+        while let Some((index, result)) = self.results.next().await {
+            let failure = match result {
+                Ok(Ok(value)) => {
+                    values[index] = Some(value);
+                    continue;
+                }
+                Ok(Err(error)) => TaskGroupError::Failed(error),
+                Err(join_error) => TaskGroupError::Join(join_error),
+            };
+
+            // Children we aborted ourselves report `Cancelled`, which we are not interested in.
+            if first_failure.is_some() {
+                continue;
+            }
+
+            observe!(TASK_GROUP_FAILURES.observe_unit());
+
+            if self.cancel_on_failure {
+                self.abort_all();
+            }
+
+            first_failure = Some(failure);
+        }
+
+        // Everything has completed, nothing left to abort on drop.
+        self.aborts.clear();
+
+        match first_failure {
+            Some(failure) => Err(failure),
+            None => Ok(values
+                .into_iter()
+                .map(|value| value.expect("all children completed successfully"))
+                .collect()),
+        }
+    }
+}
+
+impl<T, E> Default for TaskGroup<T, E>
+where
+    T: 'static,
+    E: 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, E> Drop for TaskGroup<T, E> {
+    fn drop(&mut self) {
+        for abort in &self.aborts {
+            abort.request();
+        }
+    }
+}
+
+impl<T, E> Debug for TaskGroup<T, E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TaskGroup")
+            .field("children", &self.aborts.len())
+            .field("pending", &self.results.len())
+            .field("cancel_on_failure", &self.cancel_on_failure)
+            .field("io_quota", &self.io_quota.as_ref().map(IoQuota::name))
+            .finish()
+    }
+}
+
+#[negative_impl]
+impl<T, E> !Send for TaskGroup<T, E> {}
+#[negative_impl]
+impl<T, E> !Sync for TaskGroup<T, E> {}
+
+/// The first failure of a child of a [`TaskGroup`].
+#[derive(Debug, Error)]
+pub enum TaskGroupError<E> {
+    /// The child returned an error.
+    #[error("task in group returned an error")]
+    Failed(E),
+
+    /// The child did not produce a result, e.g. because it panicked (if the runtime delivers
+    /// panics to join handles) or was cancelled.
+    #[error("task in group did not complete: {0}")]
+    Join(JoinError),
+}
+
+thread_local! {
+    static TASK_GROUP_CHILDREN: Event = EventBuilder::new("rt_task_group_children")
+        .build();
+
+    static TASK_GROUP_FAILURES: Event = EventBuilder::new("rt_task_group_failures")
+        .build();
+}
@@ -0,0 +1,298 @@
+use pin_project::{pin_project, pinned_drop};
+use std::{
+    cell::RefCell,
+    fmt::{self, Debug, Formatter},
+    future::Future,
+    mem,
+    pin::Pin,
+    task,
+    thread::LocalKey,
+};
+use thiserror::Error;
+
+/// Declares task-local variables. A task-local variable holds a value that is scoped to a future
+/// rather than to a thread - the value is visible only while that future (and anything it awaits
+/// directly) is being polled.
+///
+/// This is useful for request-scoped context such as trace IDs or authentication information,
+/// which would otherwise have to be passed through every function signature.
+///
+/// Values are provided via [`TaskLocalKey::scope()`] and accessed via [`TaskLocalKey::with()`].
+/// Tasks spawned from within a scope do not inherit the value - they are separate tasks.
+///
+/// # Example
+///
+/// ```
+/// use folo::rt::task_local;
+///
+/// task_local! {
+///     static TRACE_ID: u64;
+/// }
+///
+/// #[folo::main]
+/// async fn main() {
+///     TRACE_ID
+///         .scope(42, async {
+///             assert_eq!(TRACE_ID.get(), 42);
+///         })
+///         .await;
+/// }
+/// ```
+pub use folo_decl_macros::__macro_task_local as task_local;
+
+/// A key for a task-local variable declared via [`task_local!`].
+pub struct TaskLocalKey<T: 'static> {
+    inner: &'static LocalKey<RefCell<Option<T>>>,
+}
+
+impl<T: 'static> TaskLocalKey<T> {
+    #[doc(hidden)]
+    pub const fn __new(inner: &'static LocalKey<RefCell<Option<T>>>) -> Self {
+        Self { inner }
+    }
+
+    /// Sets the value of the task-local variable for the duration of the future. The value is
+    /// visible to the future and anything it awaits, each time it is polled.
+    pub fn scope<F>(&'static self, value: T, future: F) -> TaskLocalFuture<T, F>
+    where
+        F: Future,
+    {
+        TaskLocalFuture {
+            key: self,
+            slot: Some(value),
+            future: Some(future),
+        }
+    }
+
+    /// Sets the value of the task-local variable for the duration of a synchronous closure.
+    pub fn sync_scope<F, R>(&'static self, value: T, f: F) -> R
+    where
+        F: FnOnce() -> R,
+    {
+        let mut slot = Some(value);
+        self.enter(&mut slot, f)
+    }
+
+    /// Executes a closure that receives a reference to the current value of the task-local
+    /// variable.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the variable has not been set via `scope()` or `sync_scope()`.
+    pub fn with<F, R>(&'static self, f: F) -> R
+    where
+        F: FnOnce(&T) -> R,
+    {
+        self.try_with(f)
+            .expect("task-local variable accessed outside of its scope")
+    }
+
+    /// Executes a closure that receives a reference to the current value of the task-local
+    /// variable, or returns an error if the variable has not been set.
+    pub fn try_with<F, R>(&'static self, f: F) -> Result<R, AccessError>
+    where
+        F: FnOnce(&T) -> R,
+    {
+        self.inner
+            .try_with(|value| value.borrow().as_ref().map(f))
+            .ok()
+            .flatten()
+            .ok_or(AccessError)
+    }
+
+    /// Swaps the slot with the value stored in the thread-local storage for the duration of the
+    /// closure, restoring the original state afterwards (even if the closure panics).
+    fn enter<F, R>(&'static self, slot: &mut Option<T>, f: F) -> R
+    where
+        F: FnOnce() -> R,
+    {
+        struct Guard<'a, T: 'static> {
+            key: &'static LocalKey<RefCell<Option<T>>>,
+            slot: &'a mut Option<T>,
+        }
+
+        impl<T: 'static> Drop for Guard<'_, T> {
+            fn drop(&mut self) {
+                self.key
+                    .with(|value| mem::swap(self.slot, &mut *value.borrow_mut()));
+            }
+        }
+
+        self.inner.with(|value| {
+            mem::swap(
+                slot,
+                &mut *value
+                    .try_borrow_mut()
+                    .expect("task-local scope cannot be entered while the value is borrowed"),
+            )
+        });
+
+        let _guard = Guard {
+            key: self.inner,
+            slot,
+        };
+
+        f()
+    }
+}
+
+impl<T: Clone + 'static> TaskLocalKey<T> {
+    /// Returns a clone of the current value of the task-local variable.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the variable has not been set via `scope()` or `sync_scope()`.
+    pub fn get(&'static self) -> T {
+        self.with(Clone::clone)
+    }
+}
+
+impl<T: 'static> Debug for TaskLocalKey<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TaskLocalKey").finish()
+    }
+}
+
+/// The task-local variable was accessed outside of a scope that sets its value.
+#[derive(Clone, Copy, Debug, Error, Eq, PartialEq)]
+#[error("task-local variable accessed outside of its scope")]
+pub struct AccessError;
+
+/// A future that sets the value of a task-local variable while the inner future is polled.
+/// Returned by [`TaskLocalKey::scope()`].
+#[pin_project(PinnedDrop)]
+pub struct TaskLocalFuture<T: 'static, F> {
+    key: &'static TaskLocalKey<T>,
+
+    // Holds the value while the inner future is not being polled.
+    slot: Option<T>,
+
+    // This is an Option so we can drop the inner future inside the scope, making the value visible
+    // to any drop logic the future may have.
+    #[pin]
+    future: Option<F>,
+}
+
+impl<T: 'static, F> Future for TaskLocalFuture<T, F>
+where
+    F: Future,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> task::Poll<Self::Output> {
+        let mut this = self.project();
+        let key = *this.key;
+
+        key.enter(this.slot, || {
+            let poll_result = this
+                .future
+                .as_mut()
+                .as_pin_mut()
+                .expect("task-local future polled after completion")
+                .poll(cx);
+
+            if poll_result.is_ready() {
+                this.future.set(None);
+            }
+
+            poll_result
+        })
+    }
+}
+
+#[pinned_drop]
+impl<T: 'static, F> PinnedDrop for TaskLocalFuture<T, F> {
+    fn drop(self: Pin<&mut Self>) {
+        let mut this = self.project();
+
+        if this.future.is_none() {
+            return;
+        }
+
+        let key = *this.key;
+
+        // If the thread-local storage is already gone or the value is borrowed, we drop the future
+        // without the value being visible, which is the best we can do.
+        let can_enter = key
+            .inner
+            .try_with(|value| value.try_borrow_mut().is_ok())
+            .unwrap_or_default();
+
+        if can_enter {
+            key.enter(this.slot, || this.future.set(None));
+        }
+    }
+}
+
+impl<T: 'static, F> Debug for TaskLocalFuture<T, F> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TaskLocalFuture")
+            .field("completed", &self.future.is_none())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rt::task_local;
+    use futures::executor::block_on;
+
+    task_local! {
+        static NUMBER: u32;
+        static TEXT: String;
+    }
+
+    #[test]
+    fn not_set_outside_scope() {
+        assert_eq!(NUMBER.try_with(|x| *x), Err(AccessError));
+    }
+
+    #[test]
+    fn scope_sets_value() {
+        block_on(NUMBER.scope(5, async {
+            assert_eq!(NUMBER.get(), 5);
+
+            // Yields once, waking itself up so the executor polls us again.
+            let mut yielded = false;
+            std::future::poll_fn(|cx| {
+                if yielded {
+                    return task::Poll::Ready(());
+                }
+
+                yielded = true;
+                cx.waker().wake_by_ref();
+                task::Poll::Pending
+            })
+            .await;
+
+            assert_eq!(NUMBER.get(), 5);
+        }));
+
+        assert_eq!(NUMBER.try_with(|x| *x), Err(AccessError));
+    }
+
+    #[test]
+    fn nested_scopes() {
+        NUMBER.sync_scope(1, || {
+            TEXT.sync_scope("outer".to_string(), || {
+                NUMBER.sync_scope(2, || {
+                    assert_eq!(NUMBER.get(), 2);
+                    assert_eq!(TEXT.get(), "outer");
+                });
+
+                assert_eq!(NUMBER.get(), 1);
+            });
+        });
+    }
+
+    #[test]
+    fn value_not_visible_between_polls() {
+        let mut future = Box::pin(NUMBER.scope(7, futures::future::pending::<()>()));
+
+        let mut cx = task::Context::from_waker(futures::task::noop_waker_ref());
+        assert!(future.as_mut().poll(&mut cx).is_pending());
+
+        assert_eq!(NUMBER.try_with(|x| *x), Err(AccessError));
+    }
+}
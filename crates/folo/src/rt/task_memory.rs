@@ -0,0 +1,139 @@
+//! Attributes memory to the tasks on whose behalf it is allocated, so the tasks holding on to the
+//! most memory can be identified via [`RuntimeDump::top_memory_consumers()`].
+//!
+//! The frame of every task (the future it was spawned with) is always accounted for. Other memory
+//! is only accounted for when charged to a task, which the runtime does for I/O buffers from the
+//! buffer pools and which custom pools and caches can do via [`charge_current_task()`]. Charging is
+//! disabled by default, as it costs an atomic operation per allocation and release - enable it via
+//! [`set_task_memory_accounting()`].
+//!
+//! [`RuntimeDump::top_memory_consumers()`]: crate::rt::RuntimeDump::top_memory_consumers
+
+use crate::rt::task_meta;
+use std::sync::{
+    atomic::{AtomicBool, AtomicU64, Ordering},
+    Arc,
+};
+
+/// Enables or disables charging memory to tasks. Applies to the entire process and only to tasks
+/// spawned after the call - tasks spawned while accounting was disabled are never charged.
+pub fn set_task_memory_accounting(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Gets whether memory is charged to tasks. See [`set_task_memory_accounting()`].
+pub fn task_memory_accounting() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Charges memory to the task currently being polled on the current thread, until the returned
+/// charge is dropped. Charges nothing if no task is being polled or if the task was spawned while
+/// memory accounting was disabled.
+///
+/// The charge may be moved to and released on any thread, also after the task has completed.
+pub fn charge_current_task(bytes: usize) -> MemoryCharge {
+    let Some(memory) = task_meta::current_task_memory() else {
+        return MemoryCharge::none();
+    };
+
+    memory.charge(bytes);
+
+    MemoryCharge {
+        memory: Some(memory),
+        bytes,
+    }
+}
+
+/// Memory charged to a task via [`charge_current_task()`]. Released when dropped.
+#[derive(Debug)]
+pub struct MemoryCharge {
+    // `None` if nothing was charged.
+    memory: Option<Arc<TaskMemory>>,
+    bytes: usize,
+}
+
+impl MemoryCharge {
+    pub(crate) fn none() -> Self {
+        Self {
+            memory: None,
+            bytes: 0,
+        }
+    }
+
+    /// The number of bytes charged, which is zero if there was no task to charge.
+    pub fn bytes(&self) -> usize {
+        if self.memory.is_some() {
+            self.bytes
+        } else {
+            0
+        }
+    }
+}
+
+impl Drop for MemoryCharge {
+    fn drop(&mut self) {
+        if let Some(memory) = self.memory.take() {
+            memory.release(self.bytes);
+        }
+    }
+}
+
+/// The memory charged to one task. Shared between the task and the charges made to it, which may
+/// outlive the task.
+#[derive(Debug, Default)]
+pub(crate) struct TaskMemory {
+    charged: AtomicU64,
+    peak: AtomicU64,
+}
+
+impl TaskMemory {
+    /// Creates the accounting state for a new task, if memory accounting is enabled.
+    pub fn for_new_task() -> Option<Arc<Self>> {
+        task_memory_accounting().then(Arc::default)
+    }
+
+    fn charge(&self, bytes: usize) {
+        let charged = self.charged.fetch_add(bytes as u64, Ordering::Relaxed) + bytes as u64;
+        self.peak.fetch_max(charged, Ordering::Relaxed);
+    }
+
+    fn release(&self, bytes: usize) {
+        self.charged.fetch_sub(bytes as u64, Ordering::Relaxed);
+    }
+
+    /// The number of bytes currently charged to the task.
+    pub fn charged(&self) -> u64 {
+        self.charged.load(Ordering::Relaxed)
+    }
+
+    /// The highest number of bytes that has been charged to the task at the same time.
+    pub fn peak(&self) -> u64 {
+        self.peak.load(Ordering::Relaxed)
+    }
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn charged_and_peak_bytes_are_tracked() {
+        let memory = TaskMemory::default();
+
+        memory.charge(100);
+        memory.charge(50);
+        assert_eq!(memory.charged(), 150);
+
+        memory.release(100);
+        assert_eq!(memory.charged(), 50);
+        assert_eq!(memory.peak(), 150);
+    }
+
+    #[test]
+    fn nothing_is_charged_outside_tasks() {
+        let charge = charge_current_task(1234);
+        assert_eq!(charge.bytes(), 0);
+    }
+}
@@ -0,0 +1,217 @@
+use crate::{rt::task_memory::TaskMemory, time::LowPrecisionInstant};
+#[cfg(feature = "runtime-tracing")]
+use std::time::Duration;
+use std::{
+    cell::RefCell,
+    fmt::{self, Display, Formatter},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    thread,
+};
+use tracing::{event, Level};
+
+/// Identifies a task. IDs are unique within the process and remain the same for the entire
+/// lifetime of the task.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+pub struct TaskId(u64);
+
+impl TaskId {
+    pub(crate) fn next() -> Self {
+        Self(NEXT_TASK_ID.fetch_add(1, Ordering::Relaxed))
+    }
+
+    pub(crate) fn from_u64(value: u64) -> Self {
+        Self(value)
+    }
+
+    pub fn as_u64(&self) -> u64 {
+        self.0
+    }
+}
+
+impl Display for TaskId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "task-{}", self.0)
+    }
+}
+
+/// Describes a task for diagnostic purposes. Attached to every task when it is spawned.
+///
+/// This is thread-safe because remote tasks are created on one thread and executed on another.
+#[derive(Clone, Debug)]
+pub(crate) struct TaskMeta {
+    id: TaskId,
+    name: Option<Arc<str>>,
+    spawned_at: LowPrecisionInstant,
+
+    // Only present if task memory accounting was enabled when the task was spawned.
+    memory: Option<Arc<TaskMemory>>,
+
+    // Entered whenever the task is polled. The parent is the span that was current when the task
+    // was spawned, so the work of the task is traced as part of the work that spawned it.
+    #[cfg(feature = "runtime-tracing")]
+    span: tracing::Span,
+}
+
+impl TaskMeta {
+    pub fn new(name: Option<Arc<str>>) -> Self {
+        let id = TaskId::next();
+
+        #[cfg(feature = "runtime-tracing")]
+        let span = {
+            let span = tracing::trace_span!("task", task_id = %id, task_name = name.as_deref());
+            event!(parent: &span, Level::TRACE, message = "task spawned");
+            span
+        };
+
+        Self {
+            id,
+            name,
+            spawned_at: LowPrecisionInstant::now(),
+            memory: TaskMemory::for_new_task(),
+            #[cfg(feature = "runtime-tracing")]
+            span,
+        }
+    }
+
+    pub fn anonymous() -> Self {
+        Self::new(None)
+    }
+
+    pub fn id(&self) -> TaskId {
+        self.id
+    }
+
+    pub fn name(&self) -> Option<&Arc<str>> {
+        self.name.as_ref()
+    }
+
+    pub fn spawned_at(&self) -> LowPrecisionInstant {
+        self.spawned_at
+    }
+
+    /// The memory charged to the task, if task memory accounting is enabled.
+    pub fn memory(&self) -> Option<&Arc<TaskMemory>> {
+        self.memory.as_ref()
+    }
+
+    /// Marks the task as the one currently being polled on this thread until the returned guard
+    /// is dropped.
+    ///
+    /// If the guard is dropped due to a panic unwinding through the poll, the panic is attributed
+    /// to the task in the log.
+    pub fn enter(&self) -> CurrentTaskGuard {
+        let previous = CURRENT_TASK.with_borrow_mut(|current| current.replace(self.clone()));
+
+        CurrentTaskGuard {
+            previous,
+            #[cfg(feature = "runtime-tracing")]
+            _span: self.span.clone().entered(),
+        }
+    }
+
+    /// Records a poll of the task in its span, including whether the poll completed the task.
+    #[cfg(feature = "runtime-tracing")]
+    pub fn trace_poll(&self, duration: Duration, completed: bool) {
+        if completed {
+            event!(
+                parent: &self.span,
+                Level::TRACE,
+                message = "task completed",
+                poll_duration = ?duration
+            );
+        } else {
+            event!(
+                parent: &self.span,
+                Level::TRACE,
+                message = "task polled",
+                poll_duration = ?duration
+            );
+        }
+    }
+}
+
+impl Display for TaskMeta {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "{} ({})", self.id, name),
+            None => write!(f, "{}", self.id),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct CurrentTaskGuard {
+    previous: Option<TaskMeta>,
+
+    #[cfg(feature = "runtime-tracing")]
+    _span: tracing::span::EnteredSpan,
+}
+
+impl Drop for CurrentTaskGuard {
+    fn drop(&mut self) {
+        // We may be called during thread-local storage teardown if the thread is exiting due to a
+        // panic, in which case we just do nothing - there is nobody left to care.
+        _ = CURRENT_TASK.try_with(|current| {
+            let Ok(mut current) = current.try_borrow_mut() else {
+                return;
+            };
+
+            if thread::panicking() {
+                if let Some(task) = current.as_ref() {
+                    event!(
+                        Level::ERROR,
+                        message = "task panicked",
+                        task_id = %task.id,
+                        task_name = task.name.as_deref()
+                    );
+                }
+            }
+
+            *current = self.previous.take();
+        });
+    }
+}
+
+/// Returns the ID of the task currently being polled on the current thread, if any.
+pub fn current_task_id() -> Option<TaskId> {
+    CURRENT_TASK
+        .try_with(|current| current.try_borrow().ok()?.as_ref().map(TaskMeta::id))
+        .ok()
+        .flatten()
+}
+
+/// Returns the name of the task currently being polled on the current thread, if the task has a
+/// name.
+pub fn current_task_name() -> Option<Arc<str>> {
+    CURRENT_TASK
+        .try_with(|current| current.try_borrow().ok()?.as_ref()?.name.clone())
+        .ok()
+        .flatten()
+}
+
+/// Returns the memory accounting state of the task currently being polled on the current thread,
+/// if any task is being polled and memory accounting is enabled for it.
+pub(crate) fn current_task_memory() -> Option<Arc<TaskMemory>> {
+    CURRENT_TASK
+        .try_with(|current| current.try_borrow().ok()?.as_ref()?.memory.clone())
+        .ok()
+        .flatten()
+}
+
+/// Describes the task currently being polled on the current thread, for diagnostic output.
+/// Never panics, so it is safe to call from a panic hook.
+pub(crate) fn try_describe_current_task() -> Option<String> {
+    CURRENT_TASK
+        .try_with(|current| current.try_borrow().ok()?.as_ref().map(ToString::to_string))
+        .ok()
+        .flatten()
+}
+
+thread_local! {
+    static CURRENT_TASK: RefCell<Option<TaskMeta>> = const { RefCell::new(None) };
+}
+
+static NEXT_TASK_ID: AtomicU64 = AtomicU64::new(0);
@@ -0,0 +1,205 @@
+//! Records where tasks are suspended, so a hang can be diagnosed by asking which await point every
+//! task is waiting on.
+//!
+//! Rust futures carry no record of where they are suspended, so the trace is assembled during each
+//! poll from instrumented await points: futures wrapped via [`traced()`] contribute the location
+//! of the wrapping call and runtime resources (I/O operations, timers) contribute a description
+//! of what they are waiting for. The innermost such point that returns `Pending` captures the
+//! trace for the task, together with all the instrumented points enclosing it.
+
+use crate::rt::TaskId;
+use pin_project::pin_project;
+use std::{
+    cell::RefCell,
+    fmt::{self, Display, Formatter},
+    future::{Future, IntoFuture},
+    panic::Location,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+/// Marks an await point for [task traces][crate::rt::RuntimeClient::capture_task_traces]. If the
+/// task is suspended inside the future, the trace includes the location of this call.
+///
+/// # Example
+///
+/// ```
+/// use folo::rt::traced;
+///
+/// # async fn receive_request() {}
+/// # async fn example() {
+/// traced(receive_request()).await;
+/// # }
+/// ```
+#[track_caller]
+pub fn traced<F>(future: F) -> Traced<F::IntoFuture>
+where
+    F: IntoFuture,
+{
+    Traced {
+        inner: future.into_future(),
+        location: Location::caller(),
+    }
+}
+
+/// A future wrapped via [`traced()`].
+#[pin_project]
+#[derive(Debug)]
+pub struct Traced<F> {
+    #[pin]
+    inner: F,
+
+    location: &'static Location<'static>,
+}
+
+impl<F> Future for Traced<F>
+where
+    F: Future,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        STATE.with_borrow_mut(|state| state.frames.push(this.location));
+
+        let result = this.inner.poll(cx);
+
+        STATE.with_borrow_mut(|state| {
+            if result.is_pending() {
+                state.capture(None);
+            }
+
+            state.frames.pop();
+        });
+
+        result
+    }
+}
+
+/// Where a task is suspended, as reported by
+/// [`RuntimeClient::capture_task_traces()`][crate::rt::RuntimeClient::capture_task_traces].
+///
+/// The `Display` implementation renders the trace in the style of a backtrace, innermost first.
+#[derive(Clone, Debug)]
+pub struct TaskTrace {
+    task_id: TaskId,
+    task_name: Option<Arc<str>>,
+    suspension: Option<Suspension>,
+}
+
+impl TaskTrace {
+    pub(crate) fn new(
+        task_id: TaskId,
+        task_name: Option<Arc<str>>,
+        suspension: Option<Suspension>,
+    ) -> Self {
+        Self {
+            task_id,
+            task_name,
+            suspension,
+        }
+    }
+
+    pub fn task_id(&self) -> TaskId {
+        self.task_id
+    }
+
+    pub fn task_name(&self) -> Option<&str> {
+        self.task_name.as_deref()
+    }
+
+    /// What the task is waiting for (e.g. an I/O operation), if it is suspended in a runtime
+    /// resource that describes itself.
+    pub fn awaiting(&self) -> Option<&'static str> {
+        self.suspension.as_ref().and_then(|x| x.awaiting)
+    }
+
+    /// The locations of the [`traced()`] await points the task is suspended in, innermost first.
+    /// Empty if the task is not suspended in any.
+    pub fn locations(&self) -> &[&'static Location<'static>] {
+        self.suspension.as_ref().map_or(&[], |x| &x.frames)
+    }
+}
+
+impl Display for TaskTrace {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.task_id)?;
+
+        if let Some(name) = &self.task_name {
+            write!(f, " '{name}'")?;
+        }
+
+        let Some(suspension) = &self.suspension else {
+            return writeln!(f, ": not suspended at an instrumented await point");
+        };
+
+        writeln!(f, ":")?;
+
+        if let Some(awaiting) = suspension.awaiting {
+            writeln!(f, "  awaiting {awaiting}")?;
+        }
+
+        for location in suspension.frames.iter() {
+            writeln!(f, "  at {location}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The captured suspension point of a task.
+#[derive(Clone, Debug)]
+pub(crate) struct Suspension {
+    awaiting: Option<&'static str>,
+
+    // Innermost first.
+    frames: Box<[&'static Location<'static>]>,
+}
+
+#[derive(Debug, Default)]
+struct TraceState {
+    // The `traced()` futures being polled on the current thread, outermost first.
+    frames: Vec<&'static Location<'static>>,
+
+    // The suspension point captured during the current poll, if any.
+    captured: Option<Suspension>,
+}
+
+impl TraceState {
+    fn capture(&mut self, awaiting: Option<&'static str>) {
+        // The innermost point returns first, so anything captured before us is more precise.
+        if self.captured.is_some() {
+            return;
+        }
+
+        self.captured = Some(Suspension {
+            awaiting,
+            frames: self.frames.iter().rev().copied().collect(),
+        });
+    }
+}
+
+thread_local! {
+    static STATE: RefCell<TraceState> = RefCell::new(TraceState::default());
+}
+
+/// Reports that a runtime resource is returning `Pending` because it is waiting for something.
+pub(crate) fn awaiting(what: &'static str) {
+    STATE.with_borrow_mut(|state| state.capture(Some(what)));
+}
+
+/// Starts capturing the suspension point of a task that is about to be polled.
+pub(crate) fn begin_poll() {
+    STATE.with_borrow_mut(|state| {
+        // A panic in an earlier poll may have left frames behind.
+        state.frames.clear();
+        state.captured = None;
+    });
+}
+
+/// Returns the suspension point captured since `begin_poll()`, if any.
+pub(crate) fn end_poll() -> Option<Suspension> {
+    STATE.with_borrow_mut(|state| state.captured.take())
+}
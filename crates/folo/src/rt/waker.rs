@@ -1,7 +1,11 @@
-use crate::rt::async_task_engine::Task;
+use crate::{
+    io::IoWaker,
+    metrics::{observe, Event, EventBuilder},
+    rt::async_task_engine::Task,
+};
 use negative_impl::negative_impl;
 use std::{
-    cell::UnsafeCell,
+    cell::{RefCell, UnsafeCell},
     collections::VecDeque,
     pin::Pin,
     sync::{
@@ -35,6 +39,26 @@ use std::{
 ///
 /// The type itself is single-threaded, although the `std::task::Waker` obtained from it are thread-
 /// safe as required by the Waker API contract.
+///
+/// # Design
+///
+/// The wakers are intrusive: the raw waker data is a pointer to the signal embedded in the task's
+/// slot in the slab of the async task engine, so creating, cloning and waking a waker never
+/// allocates. Waking by reference does not touch the reference count at all, only the awakened
+/// queue (or the awakened flag if the queue is contended).
+///
+/// We do not use generation counters to detect stale wakers. A waker only carries a single pointer,
+/// so there is no room for a generation next to it, and a stale waker would still need the slot
+/// memory to stay valid to read the generation. Instead, the slot is not released (and therefore
+/// not reused) while any waker clone exists, which is what `waker_count` tracks. Clones are rare
+/// compared to wakes, so this keeps the hot path free of any reference counting.
+///
+/// When a task on one async worker thread wakes many tasks owned by another worker (e.g. via
+/// cross-thread channels), locking the other worker's awakened queue and waking up its thread for
+/// every wake would cause a lot of contention and syscalls. Instead, wakes of tasks owned by other
+/// workers are collected in a per-thread batch and delivered at the end of the worker's cycle, with
+/// one lock of each target queue and one wake-up of each target thread. See
+/// `enable_remote_wake_batching()`.
 #[derive(Debug)]
 pub(crate) struct WakeSignal {
     // The task that we are waking up. We will insert this pointer into a list of awakened tasks.
@@ -49,6 +73,10 @@ pub(crate) struct WakeSignal {
     // needs to read each signal to identify what has woken up.
     probe_embedded_wake_signals: Arc<AtomicBool>,
 
+    // Wakes up the thread that owns the task, if the wake-up is delivered from another thread as
+    // part of a batch. `None` if the owner is not an async worker thread (e.g. in unit tests).
+    io_waker: Option<IoWaker>,
+
     /// Counts each waker we have created (both the initial one and any clones). The instance cannot
     /// be dropped until the clones are all gone because each clone holds a self-reference to the
     /// wake signal.
@@ -74,11 +102,13 @@ impl WakeSignal {
     pub(crate) fn new(
         awakened_queue: Arc<Mutex<VecDeque<*mut Task>>>,
         probe_embedded_wake_signals: Arc<AtomicBool>,
+        io_waker: Option<IoWaker>,
     ) -> Self {
         Self {
             task_ptr: std::ptr::null_mut(),
             awakened_queue,
             probe_embedded_wake_signals,
+            io_waker,
             waker_count: AtomicUsize::new(0),
             awakened: AtomicBool::new(false),
             waker: UnsafeCell::new(None),
@@ -144,6 +174,10 @@ impl WakeSignal {
     }
 
     fn wake(&self) {
+        if self.try_add_to_remote_batch() {
+            return;
+        }
+
         if let Ok(mut awakened_set) = self.awakened_queue.try_lock() {
             // We only add if we can do so without increasing capacity, because increasing capacity
             // from an arbitrary thread may require reallocation, which we do not want to do on a
@@ -157,6 +191,10 @@ impl WakeSignal {
             }
         }
 
+        self.set_awakened();
+    }
+
+    fn set_awakened(&self) {
         // We release the awakened flag here, which means when someone acquires it
         // they will see all the memory operations that happened up to this point.
         self.awakened.store(true, Ordering::Release);
@@ -164,6 +202,35 @@ impl WakeSignal {
         self.probe_embedded_wake_signals
             .store(true, Ordering::Release);
     }
+
+    /// Adds the wake to the batch of the current thread if batching is enabled and the task is
+    /// owned by another thread. Returns true if the wake was added to the batch and no explicit
+    /// wake is needed.
+    fn try_add_to_remote_batch(&self) -> bool {
+        REMOTE_WAKE_BATCH
+            .try_with(|batch| {
+                let mut batch = batch.borrow_mut();
+
+                let Some(batch) = batch.as_mut() else {
+                    return false;
+                };
+
+                // Wakes of tasks owned by the current thread go straight to the queue, as nobody
+                // else is competing for it and there is no thread to wake up.
+                if Arc::as_ptr(&self.awakened_queue) == batch.own_queue {
+                    return false;
+                }
+
+                // The batch holds on to the signal just like a waker would, so the task cannot be
+                // released before the batch is submitted.
+                self.waker_count.fetch_add(1, Ordering::Relaxed);
+                batch.signals.push(self as *const WakeSignal);
+
+                true
+            })
+            // If the thread is being torn down, we just deliver the wake immediately.
+            .unwrap_or(false)
+    }
 }
 
 impl Drop for WakeSignal {
@@ -218,13 +285,128 @@ unsafe fn resurrect_signal_ptr(ptr: *const ()) -> &'static WakeSignal {
     &*(ptr as *const WakeSignal)
 }
 
+/// Enables wakes of tasks owned by other async worker threads to be processed in batches from the
+/// current thread. After this, such wakes are merely collected and not delivered until we call
+/// `submit_remote_wake_batch()`. Wakes of tasks on the current thread (identified by the awakened
+/// queue of its async task engine) are not affected.
+pub(crate) fn enable_remote_wake_batching(own_queue: *const Mutex<VecDeque<*mut Task>>) {
+    REMOTE_WAKE_BATCH.with_borrow_mut(|batch| {
+        assert!(batch.is_none());
+
+        *batch = Some(RemoteWakeBatch {
+            own_queue,
+            signals: Vec::new(),
+        });
+    });
+}
+
+/// Delivers any collected wakes and disables batching on the current thread, after which wakes of
+/// tasks owned by other async worker threads are delivered immediately again.
+pub(crate) fn disable_remote_wake_batching() {
+    submit_remote_wake_batch();
+    REMOTE_WAKE_BATCH.with_borrow_mut(|batch| *batch = None);
+}
+
+/// Delivers the wakes collected since the previous call, locking the awakened queue of each target
+/// engine and waking up each target thread only once.
+pub(crate) fn submit_remote_wake_batch() {
+    REMOTE_WAKE_BATCH.with_borrow_mut(|batch| {
+        let batch = batch
+            .as_mut()
+            .expect("if the caller is calling to submit a batch, they must enable batching first");
+
+        if batch.signals.is_empty() {
+            return;
+        }
+
+        observe!(REMOTE_WAKES_BATCHED.observe(batch.signals.len() as i64));
+
+        // SAFETY: Each signal in the batch holds a waker reference, so it remains valid until we
+        // release that reference below.
+        batch
+            .signals
+            .sort_unstable_by_key(|signal| Arc::as_ptr(unsafe { &(**signal).awakened_queue }));
+
+        let mut remaining = &batch.signals[..];
+
+        while let Some(first) = remaining.first() {
+            // SAFETY: See above.
+            let first = unsafe { &**first };
+            let queue = Arc::as_ptr(&first.awakened_queue);
+
+            // SAFETY: See above.
+            let group_len = remaining
+                .iter()
+                .take_while(|signal| Arc::as_ptr(unsafe { &(***signal).awakened_queue }) == queue)
+                .count();
+
+            let (group, rest) = remaining.split_at(group_len);
+            remaining = rest;
+
+            // Same as with individual wakes, we only use the queue if we can do so without waiting
+            // for the lock or allocating, otherwise we fall back to the embedded signals.
+            match first.awakened_queue.try_lock() {
+                Ok(mut awakened_queue) => {
+                    for signal in group {
+                        // SAFETY: See above.
+                        let signal = unsafe { &**signal };
+
+                        if awakened_queue.len() < awakened_queue.capacity() {
+                            awakened_queue.push_back(signal.task_ptr);
+                        } else {
+                            signal.set_awakened();
+                        }
+                    }
+                }
+                Err(_) => {
+                    for signal in group {
+                        // SAFETY: See above.
+                        unsafe { &**signal }.set_awakened();
+                    }
+                }
+            }
+
+            if let Some(io_waker) = &first.io_waker {
+                io_waker.wake();
+            }
+
+            for signal in group {
+                // SAFETY: See above. This is the last time we touch the signal.
+                unsafe { &**signal }
+                    .waker_count
+                    .fetch_sub(1, Ordering::Relaxed);
+            }
+        }
+
+        batch.signals.clear();
+    });
+}
+
+/// Wakes of tasks owned by other threads, collected on the current thread.
+struct RemoteWakeBatch {
+    // The awakened queue of the async task engine of the current thread. Wakes of tasks that use
+    // this queue are not batched.
+    own_queue: *const Mutex<VecDeque<*mut Task>>,
+
+    // Each entry holds a waker reference to the signal, released when the batch is submitted.
+    signals: Vec<*const WakeSignal>,
+}
+
+thread_local! {
+    static REMOTE_WAKE_BATCH: RefCell<Option<RemoteWakeBatch>> = const { RefCell::new(None) };
+
+    static REMOTE_WAKES_BATCHED: Event = EventBuilder::new("rt_remote_wakes_batched")
+        .build();
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn awaken_via_embedded_signal() {
-        #[allow(clippy::arc_with_non_send_sync)] // False positive? Or needs more annotations in type layers?
+        // False positive? Or needs more annotations in type layers?
+        #[allow(clippy::arc_with_non_send_sync)]
         let awakened_queue = Arc::new(Mutex::new(VecDeque::with_capacity(10)));
         let probe_embedded_wake_signals = Arc::new(AtomicBool::new(false));
 
@@ -234,6 +416,7 @@ mod tests {
         let signal = WakeSignal::new(
             Arc::clone(&awakened_queue),
             Arc::clone(&probe_embedded_wake_signals),
+            None,
         );
         let signal = unsafe { Pin::new_unchecked(&signal) };
 
@@ -268,13 +451,15 @@ mod tests {
 
     #[test]
     fn awaken_via_awakened_set() {
-        #[allow(clippy::arc_with_non_send_sync)] // False positive? Or needs more annotations in type layers?
+        // False positive? Or needs more annotations in type layers?
+        #[allow(clippy::arc_with_non_send_sync)]
         let awakened_queue = Arc::new(Mutex::new(VecDeque::with_capacity(10)));
         let probe_embedded_wake_signals = Arc::new(AtomicBool::new(false));
 
         let signal = WakeSignal::new(
             Arc::clone(&awakened_queue),
             Arc::clone(&probe_embedded_wake_signals),
+            None,
         );
         let signal = unsafe { Pin::new_unchecked(&signal) };
 
@@ -309,16 +494,50 @@ mod tests {
         assert!(signal.is_inert());
     }
 
+    #[test]
+    fn wake_by_ref_does_not_count_references() {
+        // False positive? Or needs more annotations in type layers?
+        #[allow(clippy::arc_with_non_send_sync)]
+        let awakened_queue = Arc::new(Mutex::new(VecDeque::with_capacity(10)));
+        let probe_embedded_wake_signals = Arc::new(AtomicBool::new(false));
+
+        let signal = WakeSignal::new(
+            Arc::clone(&awakened_queue),
+            Arc::clone(&probe_embedded_wake_signals),
+            None,
+        );
+        let signal = unsafe { Pin::new_unchecked(&signal) };
+
+        let waker = unsafe { signal.waker() };
+        let waker_clone = waker.clone();
+
+        for _ in 0..5 {
+            waker_clone.wake_by_ref();
+        }
+
+        // Waking by reference only enqueues the task, the reference count is untouched.
+        assert_eq!(signal.waker_count.load(Ordering::Relaxed), 2);
+        assert_eq!(awakened_queue.lock().unwrap().len(), 5);
+
+        // Waking by value consumes the clone.
+        waker_clone.wake();
+
+        assert_eq!(signal.waker_count.load(Ordering::Relaxed), 1);
+        assert!(signal.is_inert());
+    }
+
     #[test]
     fn awaken_via_full_awakened_set() {
         // Capacity is 0 so the queue is not allowed to allocate (== is never used).
-        #[allow(clippy::arc_with_non_send_sync)] // False positive? Or needs more annotations in type layers?
+        // False positive? Or needs more annotations in type layers?
+        #[allow(clippy::arc_with_non_send_sync)]
         let awakened_queue = Arc::new(Mutex::new(VecDeque::with_capacity(0)));
         let probe_embedded_wake_signals = Arc::new(AtomicBool::new(false));
 
         let signal = WakeSignal::new(
             Arc::clone(&awakened_queue),
             Arc::clone(&probe_embedded_wake_signals),
+            None,
         );
         let signal = unsafe { Pin::new_unchecked(&signal) };
 
@@ -351,4 +570,42 @@ mod tests {
         assert_eq!(signal.waker_count.load(Ordering::Relaxed), 1);
         assert!(signal.is_inert());
     }
+
+    #[test]
+    fn remote_wakes_are_batched() {
+        // False positive? Or needs more annotations in type layers?
+        #[allow(clippy::arc_with_non_send_sync)]
+        let awakened_queue = Arc::new(Mutex::new(VecDeque::with_capacity(10)));
+        let probe_embedded_wake_signals = Arc::new(AtomicBool::new(false));
+
+        // The current thread pretends to own some other engine.
+        // False positive? Or needs more annotations in type layers?
+        #[allow(clippy::arc_with_non_send_sync)]
+        let own_queue = Arc::new(Mutex::new(VecDeque::<*mut Task>::new()));
+        enable_remote_wake_batching(Arc::as_ptr(&own_queue));
+
+        let signal = WakeSignal::new(
+            Arc::clone(&awakened_queue),
+            Arc::clone(&probe_embedded_wake_signals),
+            None,
+        );
+        let signal = unsafe { Pin::new_unchecked(&signal) };
+
+        let waker = unsafe { signal.waker() };
+        let waker_clone = waker.clone();
+
+        waker_clone.wake_by_ref();
+        waker_clone.wake();
+
+        // Nothing is delivered yet but the batch keeps the signal alive.
+        assert!(awakened_queue.lock().unwrap().is_empty());
+        assert_eq!(signal.waker_count.load(Ordering::Relaxed), 3);
+        assert!(!signal.is_inert());
+
+        submit_remote_wake_batch();
+
+        assert_eq!(awakened_queue.lock().unwrap().len(), 2);
+        assert!(!probe_embedded_wake_signals.load(Ordering::Relaxed));
+        assert!(signal.is_inert());
+    }
 }
@@ -0,0 +1,177 @@
+use crate::rt::{
+    diagnostics::{Diagnostic, DiagnosticsBackend},
+    dump::PollingTask,
+    flight_recorder,
+    worker_stats::WorkerCounters,
+    TaskId,
+};
+use std::{
+    fmt::{self, Debug, Formatter},
+    io,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+use tracing::{event, Level};
+
+// Bounds for how often the watchdog looks at the workers. It looks a few times per threshold, so
+// a blocked worker is reported soon after crossing the threshold.
+const MIN_CHECK_INTERVAL: Duration = Duration::from_millis(10);
+const MAX_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// What the watchdog does when it finds a blocked async worker thread. Configured via
+/// [`RuntimeBuilder::watchdog()`][crate::rt::RuntimeBuilder::watchdog].
+#[derive(Clone, Default)]
+pub enum WatchdogPolicy {
+    /// Reports a [`Diagnostic::WorkerBlocked`] to the diagnostics backend of the runtime.
+    #[default]
+    Diagnostic,
+
+    /// Reports the diagnostic and appends the flight records of the runtime to the file set via
+    /// [`RuntimeBuilder::flight_record_path()`][crate::rt::RuntimeBuilder::flight_record_path],
+    /// capturing what led up to the worker getting blocked.
+    DumpFlightRecord,
+
+    /// Passes the blocked worker to a callback, on the watchdog thread.
+    Callback(Arc<dyn Fn(&BlockedWorker) + Send + Sync>),
+}
+
+impl WatchdogPolicy {
+    /// Creates a policy that passes blocked workers to the callback.
+    pub fn callback(f: impl Fn(&BlockedWorker) + Send + Sync + 'static) -> Self {
+        Self::Callback(Arc::new(f))
+    }
+}
+
+impl Debug for WatchdogPolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Diagnostic => write!(f, "Diagnostic"),
+            Self::DumpFlightRecord => write!(f, "DumpFlightRecord"),
+            // The callback is not Debug, so we just say there is one.
+            Self::Callback(_) => write!(f, "Callback(..)"),
+        }
+    }
+}
+
+/// An async worker thread that the watchdog found blocked.
+#[derive(Clone, Debug)]
+pub struct BlockedWorker {
+    worker_index: usize,
+    processor_id: usize,
+    task_id: Option<TaskId>,
+    task_name: Option<Arc<str>>,
+    blocked_for: Duration,
+}
+
+impl BlockedWorker {
+    /// The index of the worker, as used by `spawn_on()`.
+    pub fn worker_index(&self) -> usize {
+        self.worker_index
+    }
+
+    pub fn processor_id(&self) -> usize {
+        self.processor_id
+    }
+
+    /// The task the worker is polling, if it is blocked in a task.
+    pub fn task_id(&self) -> Option<TaskId> {
+        self.task_id
+    }
+
+    pub fn task_name(&self) -> Option<&str> {
+        self.task_name.as_deref()
+    }
+
+    /// How long the worker has been busy without completing a cycle of its loop.
+    pub fn blocked_for(&self) -> Duration {
+        self.blocked_for
+    }
+}
+
+/// The state of one async worker that the watchdog observes.
+pub(crate) struct WatchedWorker {
+    pub worker_index: usize,
+    pub processor_id: usize,
+    pub counters: Arc<WorkerCounters>,
+    pub polling_task: Arc<PollingTask>,
+}
+
+/// Starts the watchdog thread, which exits once `is_stopping` is set.
+pub(crate) fn start(
+    threshold: Duration,
+    policy: WatchdogPolicy,
+    diagnostics: DiagnosticsBackend,
+    workers: Vec<WatchedWorker>,
+    is_stopping: Arc<AtomicBool>,
+) -> io::Result<JoinHandle<()>> {
+    for worker in &workers {
+        worker.polling_task.publish_names();
+    }
+
+    let check_interval = (threshold / 4).clamp(MIN_CHECK_INTERVAL, MAX_CHECK_INTERVAL);
+
+    thread::Builder::new()
+        .name("folo-watchdog".to_string())
+        .spawn(move || {
+            // The start of the stretch of work we last reported for each worker, so every time a
+            // worker gets blocked is reported once.
+            let mut reported = vec![None; workers.len()];
+
+            while !is_stopping.load(Ordering::Relaxed) {
+                thread::sleep(check_interval);
+
+                for (worker, reported) in workers.iter().zip(reported.iter_mut()) {
+                    let Some(busy_since) = worker.counters.busy_since() else {
+                        continue;
+                    };
+
+                    let blocked_for = busy_since.elapsed();
+
+                    if blocked_for < threshold || *reported == Some(busy_since.as_millis()) {
+                        continue;
+                    }
+
+                    *reported = Some(busy_since.as_millis());
+
+                    let task = worker.polling_task.dump();
+
+                    let blocked = BlockedWorker {
+                        worker_index: worker.worker_index,
+                        processor_id: worker.processor_id,
+                        task_id: task.as_ref().map(|x| x.id()),
+                        task_name: task.and_then(|x| x.name().map(Arc::from)),
+                        blocked_for,
+                    };
+
+                    respond(&policy, &diagnostics, &blocked);
+                }
+            }
+
+            event!(Level::TRACE, "watchdog stopped");
+        })
+}
+
+fn respond(policy: &WatchdogPolicy, diagnostics: &DiagnosticsBackend, blocked: &BlockedWorker) {
+    let diagnostic = || Diagnostic::WorkerBlocked {
+        processor_id: blocked.processor_id,
+        task_id: blocked.task_id,
+        task_name: blocked.task_name(),
+        blocked_for: blocked.blocked_for,
+    };
+
+    match policy {
+        WatchdogPolicy::Diagnostic => diagnostics.emit(diagnostic()),
+        WatchdogPolicy::DumpFlightRecord => {
+            diagnostics.emit(diagnostic());
+            flight_recorder::write_dump(&format!(
+                "worker on processor {} blocked for {:?}",
+                blocked.processor_id, blocked.blocked_for
+            ));
+        }
+        WatchdogPolicy::Callback(f) => f(blocked),
+    }
+}
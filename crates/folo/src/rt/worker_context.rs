@@ -0,0 +1,26 @@
+/// Identifies the async worker thread that a job started via
+/// [`for_each_worker()`][crate::rt::for_each_worker] is executing on.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct WorkerContext {
+    worker_index: usize,
+    worker_count: usize,
+}
+
+impl WorkerContext {
+    pub(crate) fn new(worker_index: usize, worker_count: usize) -> Self {
+        Self {
+            worker_index,
+            worker_count,
+        }
+    }
+
+    /// The index of the worker, from zero to `worker_count() - 1`, as used by `spawn_on()`.
+    pub fn worker_index(&self) -> usize {
+        self.worker_index
+    }
+
+    /// The number of async worker threads of the runtime.
+    pub fn worker_count(&self) -> usize {
+        self.worker_count
+    }
+}
@@ -0,0 +1,175 @@
+use crate::time::LowPrecisionInstant;
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+/// How one async worker thread has spent its time since the runtime started. Obtained via
+/// [`RuntimeClient::worker_stats()`][crate::rt::RuntimeClient::worker_stats].
+///
+/// Comparing the utilization of the workers shows whether the processors are saturated or idle and
+/// whether the load is balanced between them.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct WorkerStats {
+    worker_index: usize,
+    busy_time: Duration,
+    parked_time: Duration,
+    cycles: u64,
+    io_operations: u64,
+}
+
+impl WorkerStats {
+    /// The index of the worker, as used by `spawn_on()`.
+    pub fn worker_index(&self) -> usize {
+        self.worker_index
+    }
+
+    /// The time the worker has spent doing work: polling tasks, processing I/O completions and
+    /// commands from other threads.
+    pub fn busy_time(&self) -> Duration {
+        self.busy_time
+    }
+
+    /// The time the worker has spent sleeping while it had no work to do.
+    pub fn parked_time(&self) -> Duration {
+        self.parked_time
+    }
+
+    /// The number of iterations of the worker loop.
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    /// The number of I/O operations of the worker that were in progress at the end of its latest
+    /// cycle. Unlike the other values, this is a current value and not a total.
+    pub fn io_operations_in_flight(&self) -> u64 {
+        self.io_operations
+    }
+
+    /// The fraction of time the worker has been busy, from 0.0 (always parked) to 1.0 (never
+    /// parked). Workers that spin instead of parking when idle (see
+    /// [`IdleStrategy`][crate::rt::IdleStrategy]) count the spinning as busy time.
+    pub fn utilization(&self) -> f64 {
+        let total = self.busy_time + self.parked_time;
+
+        if total.is_zero() {
+            return 0.0;
+        }
+
+        self.busy_time.as_secs_f64() / total.as_secs_f64()
+    }
+
+    /// Returns the change from an earlier snapshot of the same worker, e.g. to calculate the
+    /// utilization over the last reporting period instead of the lifetime of the runtime.
+    pub fn since(&self, earlier: &WorkerStats) -> WorkerStats {
+        WorkerStats {
+            worker_index: self.worker_index,
+            busy_time: self.busy_time.saturating_sub(earlier.busy_time),
+            parked_time: self.parked_time.saturating_sub(earlier.parked_time),
+            cycles: self.cycles.saturating_sub(earlier.cycles),
+            io_operations: self.io_operations,
+        }
+    }
+}
+
+/// Time accounting that an async worker publishes for other threads to read.
+#[derive(Debug, Default)]
+pub(crate) struct WorkerCounters {
+    busy_nanos: AtomicU64,
+    parked_nanos: AtomicU64,
+    cycles: AtomicU64,
+    io_operations: AtomicU64,
+
+    // When the current uninterrupted stretch of work of the worker started, in
+    // `LowPrecisionInstant` milliseconds, or zero if the worker is not working (it is parked or
+    // between cycles of an embedded runtime). Observed by the watchdog.
+    busy_since: AtomicU64,
+}
+
+impl WorkerCounters {
+    /// Records one cycle of the worker loop, of which `parked` was spent sleeping.
+    pub fn record_cycle(&self, total: Duration, parked: Duration) {
+        let busy = total.saturating_sub(parked);
+
+        // Only the owning worker writes, so there is no need for read-modify-write atomics.
+        self.busy_nanos.store(
+            self.busy_nanos.load(Ordering::Relaxed) + busy.as_nanos() as u64,
+            Ordering::Relaxed,
+        );
+        self.parked_nanos.store(
+            self.parked_nanos.load(Ordering::Relaxed) + parked.as_nanos() as u64,
+            Ordering::Relaxed,
+        );
+        self.cycles
+            .store(self.cycles.load(Ordering::Relaxed) + 1, Ordering::Relaxed);
+    }
+
+    /// Records the number of I/O operations of the worker that are in progress.
+    pub fn record_io_operations(&self, count: usize) {
+        self.io_operations.store(count as u64, Ordering::Relaxed);
+    }
+
+    /// Records that the worker has started working, at the start of a cycle or after parking.
+    pub fn record_busy(&self) {
+        self.busy_since
+            .store(LowPrecisionInstant::now().as_millis(), Ordering::Relaxed);
+    }
+
+    /// Records that the worker has stopped working, to park or at the end of a cycle.
+    pub fn record_not_busy(&self) {
+        self.busy_since.store(0, Ordering::Relaxed);
+    }
+
+    /// When the current stretch of work of the worker started, if it is working.
+    pub fn busy_since(&self) -> Option<LowPrecisionInstant> {
+        match self.busy_since.load(Ordering::Relaxed) {
+            0 => None,
+            value => Some(LowPrecisionInstant::from_millis(value)),
+        }
+    }
+
+    pub fn snapshot(&self, worker_index: usize) -> WorkerStats {
+        WorkerStats {
+            worker_index,
+            busy_time: Duration::from_nanos(self.busy_nanos.load(Ordering::Relaxed)),
+            parked_time: Duration::from_nanos(self.parked_nanos.load(Ordering::Relaxed)),
+            cycles: self.cycles.load(Ordering::Relaxed),
+            io_operations: self.io_operations.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn utilization_from_recorded_cycles() {
+        let counters = WorkerCounters::default();
+
+        counters.record_cycle(Duration::from_millis(10), Duration::from_millis(10));
+        counters.record_cycle(Duration::from_millis(10), Duration::ZERO);
+
+        let stats = counters.snapshot(3);
+
+        assert_eq!(stats.worker_index(), 3);
+        assert_eq!(stats.busy_time(), Duration::from_millis(10));
+        assert_eq!(stats.parked_time(), Duration::from_millis(10));
+        assert_eq!(stats.cycles(), 2);
+        assert_eq!(stats.utilization(), 0.5);
+
+        counters.record_cycle(Duration::from_millis(10), Duration::ZERO);
+
+        let delta = counters.snapshot(3).since(&stats);
+
+        assert_eq!(delta.cycles(), 1);
+        assert_eq!(delta.utilization(), 1.0);
+
+        counters.record_io_operations(5);
+
+        assert_eq!(
+            counters.snapshot(3).since(&stats).io_operations_in_flight(),
+            5
+        );
+    }
+}
@@ -0,0 +1,3 @@
+mod ctrl_c;
+
+pub use ctrl_c::*;
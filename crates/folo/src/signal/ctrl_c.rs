@@ -0,0 +1,142 @@
+use crate::{constants, io};
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Mutex,
+    task::{Context, Poll, Waker},
+};
+use tracing::{event, Level};
+use windows::Win32::{
+    Foundation::{BOOL, FALSE, TRUE},
+    System::Console::{SetConsoleCtrlHandler, CTRL_BREAK_EVENT, CTRL_C_EVENT},
+};
+
+/// Waits for the next Ctrl+C (or Ctrl+Break) to be pressed in the console of the process, e.g. to
+/// trigger a graceful shutdown of a service.
+///
+/// The first call installs a console control handler for the process. From then on, Ctrl+C no
+/// longer terminates the process - it is up to the app to react to it. Every Ctrl+C completes all
+/// the futures waiting for it at that time.
+///
+/// Folo only runs on Windows, where console control events take the place of Unix signals.
+///
+/// # Example
+///
+/// ```no_run
+/// use folo::signal::ctrl_c;
+///
+/// #[folo::main]
+/// async fn main() {
+///     // Start serving requests here.
+///
+///     ctrl_c().await.unwrap();
+///     println!("Ctrl+C received, shutting down.");
+/// }
+/// ```
+pub fn ctrl_c() -> CtrlC {
+    CtrlC { generation: None }
+}
+
+/// A future that completes on the next Ctrl+C after it is first polled. Created via [`ctrl_c()`].
+#[derive(Debug)]
+pub struct CtrlC {
+    // The number of events received before we started waiting, once we have started.
+    generation: Option<u64>,
+}
+
+impl Future for CtrlC {
+    type Output = io::Result<()>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = STATE.lock().expect(constants::POISONED_LOCK);
+
+        if !state.handler_installed {
+            // SAFETY: The handler is a valid function for the lifetime of the process. We never
+            // remove it, so it does not matter which thread installs it.
+            unsafe { SetConsoleCtrlHandler(Some(handle_ctrl_event), TRUE) }?;
+
+            event!(Level::DEBUG, message = "console control handler installed");
+
+            state.handler_installed = true;
+        }
+
+        let generation = *self.generation.get_or_insert(state.generation);
+
+        if state.generation > generation {
+            return Poll::Ready(Ok(()));
+        }
+
+        if !state.wakers.iter().any(|w| w.will_wake(cx.waker())) {
+            state.wakers.push(cx.waker().clone());
+        }
+
+        Poll::Pending
+    }
+}
+
+struct CtrlState {
+    handler_installed: bool,
+
+    // The number of Ctrl+C events received so far.
+    generation: u64,
+
+    // Wakers of the futures waiting for the next event. Woken and cleared on every event.
+    wakers: Vec<Waker>,
+}
+
+static STATE: Mutex<CtrlState> = Mutex::new(CtrlState {
+    handler_installed: false,
+    generation: 0,
+    wakers: Vec::new(),
+});
+
+/// Called by the operating system on a thread of its own whenever a console control event occurs.
+unsafe extern "system" fn handle_ctrl_event(ctrl_type: u32) -> BOOL {
+    if ctrl_type != CTRL_C_EVENT && ctrl_type != CTRL_BREAK_EVENT {
+        // Other events (e.g. the console window closing) get the default treatment, which is to
+        // terminate the process.
+        return FALSE;
+    }
+
+    let wakers = {
+        let mut state = STATE.lock().expect(constants::POISONED_LOCK);
+        state.generation += 1;
+        std::mem::take(&mut state.wakers)
+    };
+
+    for waker in wakers {
+        waker.wake();
+    }
+
+    // We handled the event, so the process is not terminated.
+    TRUE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::task::noop_waker_ref;
+    use std::pin::pin;
+
+    #[test]
+    fn completes_on_next_ctrl_c() {
+        let mut cx = Context::from_waker(noop_waker_ref());
+
+        let mut first = pin!(ctrl_c());
+        assert!(first.as_mut().poll(&mut cx).is_pending());
+
+        // SAFETY: The handler is safe to call from any thread.
+        assert_eq!(unsafe { handle_ctrl_event(CTRL_C_EVENT) }, TRUE);
+
+        assert!(matches!(first.as_mut().poll(&mut cx), Poll::Ready(Ok(()))));
+
+        // A future created after the event waits for the next one.
+        let mut second = pin!(ctrl_c());
+        assert!(second.as_mut().poll(&mut cx).is_pending());
+
+        // SAFETY: The handler is safe to call from any thread.
+        assert_eq!(unsafe { handle_ctrl_event(CTRL_BREAK_EVENT) }, TRUE);
+
+        assert!(matches!(second.as_mut().poll(&mut cx), Poll::Ready(Ok(()))));
+    }
+}
@@ -0,0 +1,20 @@
+//! Asynchronous streams of values and adapters for them that cooperate with the Folo runtime.
+//!
+//! Many Folo types are streams already: listeners yield their connections via `incoming()` (e.g.
+//! [`TcpListener::incoming()`][crate::net::TcpListener::incoming]), channel receivers (e.g.
+//! [`mpsc::Receiver`][crate::sync::mpsc::Receiver]) yield their values and [`WatchStream`] yields
+//! the versions of a watched value. [`StreamExt`] adds the adapters that depend on the runtime,
+//! such as batching with a timeout and stopping when a cancellation token is cancelled.
+
+mod buffer_unordered;
+mod chunks_timeout;
+mod ext;
+mod take_until_cancelled;
+mod watch_stream;
+
+pub use buffer_unordered::*;
+pub use chunks_timeout::*;
+pub use ext::*;
+pub use futures::stream::{FusedStream, Stream};
+pub use take_until_cancelled::*;
+pub use watch_stream::*;
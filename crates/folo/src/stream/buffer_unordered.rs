@@ -0,0 +1,133 @@
+use crate::rt::coop;
+use futures::{
+    stream::{Fuse, FusedStream, FuturesUnordered},
+    Stream, StreamExt,
+};
+use pin_project::pin_project;
+use std::{
+    fmt::{self, Debug, Formatter},
+    future::Future,
+    pin::Pin,
+    task,
+};
+
+/// Stream returned by [`StreamExt::buffer_unordered()`][super::StreamExt::buffer_unordered].
+#[pin_project]
+#[must_use = "streams do nothing unless polled"]
+pub struct BufferUnordered<S>
+where
+    S: Stream,
+    S::Item: Future,
+{
+    #[pin]
+    stream: Fuse<S>,
+    in_progress: FuturesUnordered<S::Item>,
+    limit: usize,
+}
+
+impl<S> BufferUnordered<S>
+where
+    S: Stream,
+    S::Item: Future,
+{
+    pub(crate) fn new(stream: S, limit: usize) -> Self {
+        assert!(limit > 0, "buffer_unordered() limit must be at least 1");
+
+        Self {
+            stream: stream.fuse(),
+            in_progress: FuturesUnordered::new(),
+            limit,
+        }
+    }
+}
+
+impl<S> Stream for BufferUnordered<S>
+where
+    S: Stream,
+    S::Item: Future,
+{
+    type Item = <S::Item as Future>::Output;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+    ) -> task::Poll<Option<Self::Item>> {
+        coop::poll_budgeted(cx, |cx| {
+            let mut this = self.project();
+
+            while this.in_progress.len() < *this.limit {
+                match this.stream.as_mut().poll_next(cx) {
+                    task::Poll::Ready(Some(future)) => this.in_progress.push(future),
+                    task::Poll::Ready(None) | task::Poll::Pending => break,
+                }
+            }
+
+            match this.in_progress.poll_next_unpin(cx) {
+                task::Poll::Ready(Some(output)) => task::Poll::Ready(Some(output)),
+                // Nothing is in progress - we are done if the source stream is done, too.
+                task::Poll::Ready(None) if this.stream.is_done() => task::Poll::Ready(None),
+                _ => task::Poll::Pending,
+            }
+        })
+    }
+}
+
+impl<S> FusedStream for BufferUnordered<S>
+where
+    S: Stream,
+    S::Item: Future,
+{
+    fn is_terminated(&self) -> bool {
+        self.stream.is_done() && self.in_progress.is_empty()
+    }
+}
+
+impl<S> Debug for BufferUnordered<S>
+where
+    S: Stream,
+    S::Item: Future,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BufferUnordered")
+            .field("in_progress", &self.in_progress.len())
+            .field("limit", &self.limit)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{executor::block_on, stream, StreamExt};
+    use std::{cell::Cell, future};
+
+    #[test]
+    fn yields_outputs_of_all_futures() {
+        let mut outputs = block_on(
+            crate::stream::StreamExt::buffer_unordered(
+                stream::iter((0..10).map(|i| async move { i * 2 })),
+                3,
+            )
+            .collect::<Vec<_>>(),
+        );
+
+        outputs.sort_unstable();
+        assert_eq!(outputs, (0..10).map(|i| i * 2).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn limits_futures_in_progress() {
+        let started = Cell::new(0);
+
+        let mut buffered = crate::stream::StreamExt::buffer_unordered(
+            stream::iter(0..10).map(|_| {
+                started.set(started.get() + 1);
+                future::pending::<()>()
+            }),
+            3,
+        );
+
+        let cx = &mut std::task::Context::from_waker(futures::task::noop_waker_ref());
+        assert!(buffered.poll_next_unpin(cx).is_pending());
+        assert_eq!(started.get(), 3);
+    }
+}
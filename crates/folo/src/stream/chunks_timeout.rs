@@ -0,0 +1,157 @@
+use crate::time::{Clock, Delay};
+use futures::{
+    stream::{Fuse, FusedStream},
+    Stream, StreamExt,
+};
+use pin_project::pin_project;
+use std::{
+    fmt::{self, Debug, Formatter},
+    future::Future,
+    mem,
+    pin::Pin,
+    task::{self, ready},
+    time::Duration,
+};
+
+/// Stream returned by [`StreamExt::chunks_timeout()`][super::StreamExt::chunks_timeout].
+#[pin_project]
+#[must_use = "streams do nothing unless polled"]
+pub struct ChunksTimeout<S: Stream> {
+    #[pin]
+    stream: Fuse<S>,
+    items: Vec<S::Item>,
+    max_items: usize,
+
+    timeout: Duration,
+    clock: Clock,
+
+    // Armed when the first item of a chunk arrives.
+    delay: Option<Delay>,
+}
+
+impl<S: Stream> ChunksTimeout<S> {
+    pub(crate) fn new(stream: S, clock: &Clock, max_items: usize, timeout: Duration) -> Self {
+        assert!(
+            max_items > 0,
+            "chunks_timeout() max_items must be at least 1"
+        );
+
+        Self {
+            stream: stream.fuse(),
+            items: Vec::with_capacity(max_items),
+            max_items,
+            timeout,
+            clock: clock.clone(),
+            delay: None,
+        }
+    }
+}
+
+impl<S: Stream> Stream for ChunksTimeout<S> {
+    type Item = Vec<S::Item>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+    ) -> task::Poll<Option<Self::Item>> {
+        let mut this = self.project();
+
+        loop {
+            match this.stream.as_mut().poll_next(cx) {
+                task::Poll::Ready(Some(item)) => {
+                    if this.items.is_empty() {
+                        *this.delay = Some(Delay::with_clock(this.clock, *this.timeout));
+                    }
+
+                    this.items.push(item);
+
+                    if this.items.len() >= *this.max_items {
+                        *this.delay = None;
+                        return task::Poll::Ready(Some(mem::replace(
+                            this.items,
+                            Vec::with_capacity(*this.max_items),
+                        )));
+                    }
+                }
+                task::Poll::Ready(None) => {
+                    *this.delay = None;
+
+                    // The remainder is yielded as the last chunk, however few items it has.
+                    return task::Poll::Ready(
+                        (!this.items.is_empty()).then(|| mem::take(this.items)),
+                    );
+                }
+                task::Poll::Pending => break,
+            }
+        }
+
+        let Some(delay) = this.delay.as_mut() else {
+            return task::Poll::Pending;
+        };
+
+        ready!(Pin::new(delay).poll(cx));
+        *this.delay = None;
+
+        task::Poll::Ready(Some(mem::replace(
+            this.items,
+            Vec::with_capacity(*this.max_items),
+        )))
+    }
+}
+
+impl<S: Stream> FusedStream for ChunksTimeout<S> {
+    fn is_terminated(&self) -> bool {
+        self.stream.is_done() && self.items.is_empty()
+    }
+}
+
+impl<S: Stream> Debug for ChunksTimeout<S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChunksTimeout")
+            .field("items", &self.items.len())
+            .field("max_items", &self.max_items)
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+#[cfg(all(test, feature = "fakes"))]
+mod tests {
+    use crate::{stream::StreamExt as _, test_rt::TestRuntime};
+    use futures::{stream, StreamExt};
+    use std::time::Duration;
+
+    #[test]
+    fn full_chunks_are_yielded_without_waiting() {
+        let mut runtime = TestRuntime::new();
+        let clock = runtime.clock();
+
+        let chunks = runtime.block_on(
+            stream::iter(0..5)
+                .chunks_timeout_with_clock(&clock, 2, Duration::from_secs(10))
+                .collect::<Vec<_>>(),
+        );
+
+        assert_eq!(chunks, vec![vec![0, 1], vec![2, 3], vec![4]]);
+    }
+
+    #[test]
+    fn partial_chunk_is_yielded_after_timeout() {
+        let mut runtime = TestRuntime::new();
+        let clock = runtime.clock();
+
+        runtime.block_on(async move {
+            let start = clock.instant_now();
+
+            let mut chunks = stream::iter(0..2)
+                .chain(stream::pending())
+                .chunks_timeout_with_clock(&clock, 10, Duration::from_millis(10));
+
+            assert_eq!(chunks.next().await, Some(vec![0, 1]));
+            assert_eq!(
+                clock.instant_now().duration_since(start),
+                Duration::from_millis(10)
+            );
+        });
+    }
+}
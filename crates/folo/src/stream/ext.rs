@@ -0,0 +1,71 @@
+use crate::{
+    stream::{BufferUnordered, ChunksTimeout, TakeUntilCancelled},
+    sync::CancellationToken,
+    time::Clock,
+};
+use futures::Stream;
+use std::{future::Future, time::Duration};
+
+/// Adapters for streams that depend on the Folo runtime (timers, cancellation, cooperative
+/// scheduling). Implemented for all streams.
+///
+/// These complement the adapters of `futures::StreamExt`. If both traits are in scope,
+/// [`buffer_unordered()`][Self::buffer_unordered] is ambiguous and has to be called as
+/// `folo::stream::StreamExt::buffer_unordered(stream, limit)`.
+pub trait StreamExt: Stream {
+    /// Executes the futures yielded by this stream concurrently, up to `limit` at a time, and
+    /// yields their outputs in the order they complete.
+    ///
+    /// Each yielded output consumes the cooperative scheduling budget of the task, so a stream of
+    /// futures that are always ready does not starve other tasks on the same thread.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `limit` is zero.
+    fn buffer_unordered(self, limit: usize) -> BufferUnordered<Self>
+    where
+        Self: Sized,
+        Self::Item: Future,
+    {
+        BufferUnordered::new(self, limit)
+    }
+
+    /// Batches the items of this stream into chunks of up to `max_items` items. A chunk is yielded
+    /// once it is full or once `timeout` has passed since its first item arrived, whichever comes
+    /// first, so items are never held back for longer than `timeout`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_items` is zero.
+    fn chunks_timeout(self, max_items: usize, timeout: Duration) -> ChunksTimeout<Self>
+    where
+        Self: Sized,
+    {
+        self.chunks_timeout_with_clock(&Clock::new(), max_items, timeout)
+    }
+
+    /// Batches the items of this stream like [`chunks_timeout()`][Self::chunks_timeout], measuring
+    /// the timeout with the specified clock.
+    fn chunks_timeout_with_clock(
+        self,
+        clock: &Clock,
+        max_items: usize,
+        timeout: Duration,
+    ) -> ChunksTimeout<Self>
+    where
+        Self: Sized,
+    {
+        ChunksTimeout::new(self, clock, max_items, timeout)
+    }
+
+    /// Yields the items of this stream until the token is cancelled, after which the stream ends
+    /// without polling this stream again.
+    fn take_until_cancelled(self, token: CancellationToken) -> TakeUntilCancelled<Self>
+    where
+        Self: Sized,
+    {
+        TakeUntilCancelled::new(self, token)
+    }
+}
+
+impl<S: Stream + ?Sized> StreamExt for S {}
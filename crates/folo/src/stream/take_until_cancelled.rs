@@ -0,0 +1,112 @@
+use crate::sync::{CancellationToken, CancelledOwned};
+use futures::{stream::FusedStream, Stream};
+use pin_project::pin_project;
+use std::{
+    fmt::{self, Debug, Formatter},
+    pin::Pin,
+    task,
+};
+
+/// Stream returned by [`take_until_cancelled()`][super::StreamExt::take_until_cancelled].
+#[pin_project]
+#[must_use = "streams do nothing unless polled"]
+pub struct TakeUntilCancelled<S> {
+    #[pin]
+    stream: S,
+    cancelled: CancelledOwned,
+
+    // Set once the token is cancelled or the stream ends, after which the stream is not polled.
+    done: bool,
+}
+
+impl<S> TakeUntilCancelled<S> {
+    pub(crate) fn new(stream: S, token: CancellationToken) -> Self {
+        Self {
+            stream,
+            cancelled: token.cancelled_owned(),
+            done: false,
+        }
+    }
+}
+
+impl<S: Stream> Stream for TakeUntilCancelled<S> {
+    type Item = S::Item;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+    ) -> task::Poll<Option<Self::Item>> {
+        let this = self.project();
+
+        if *this.done {
+            return task::Poll::Ready(None);
+        }
+
+        // Cancellation is checked first, so an item that is ready at the same time is not yielded.
+        // The stream consumes the cooperative scheduling budget itself, so we do not.
+        if this.cancelled.poll_cancelled(cx.waker()).is_ready() {
+            *this.done = true;
+            return task::Poll::Ready(None);
+        }
+
+        let item = task::ready!(this.stream.poll_next(cx));
+        *this.done = item.is_none();
+
+        task::Poll::Ready(item)
+    }
+}
+
+impl<S: Stream> FusedStream for TakeUntilCancelled<S> {
+    fn is_terminated(&self) -> bool {
+        self.done
+    }
+}
+
+impl<S: Debug> Debug for TakeUntilCancelled<S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TakeUntilCancelled")
+            .field("stream", &self.stream)
+            .field("done", &self.done)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{stream::StreamExt as _, sync::CancellationToken};
+    use futures::{
+        stream::{self, FusedStream},
+        task::noop_waker_ref,
+        StreamExt,
+    };
+    use std::task;
+
+    #[test]
+    fn ends_when_cancelled() {
+        let token = CancellationToken::new();
+        let cx = &mut task::Context::from_waker(noop_waker_ref());
+
+        let mut numbers = stream::iter(0..).take_until_cancelled(token.clone());
+
+        assert_eq!(numbers.poll_next_unpin(cx), task::Poll::Ready(Some(0)));
+        assert_eq!(numbers.poll_next_unpin(cx), task::Poll::Ready(Some(1)));
+
+        token.cancel();
+
+        assert_eq!(numbers.poll_next_unpin(cx), task::Poll::Ready(None));
+        assert!(numbers.is_terminated());
+    }
+
+    #[test]
+    fn ends_with_stream() {
+        let token = CancellationToken::new();
+
+        let numbers = futures::executor::block_on(
+            stream::iter(0..3)
+                .take_until_cancelled(token)
+                .collect::<Vec<_>>(),
+        );
+
+        assert_eq!(numbers, vec![0, 1, 2]);
+    }
+}
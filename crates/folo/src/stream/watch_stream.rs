@@ -0,0 +1,112 @@
+use crate::sync::watch;
+use futures::Stream;
+use std::{
+    pin::Pin,
+    sync::Arc,
+    task::{self, ready},
+};
+
+/// Yields the versions of the value of a watch channel: first the latest value at the time the
+/// stream is first polled, then the latest value after each change. Like the receiver, the stream
+/// skips intermediate versions that are replaced before it is polled.
+///
+/// The stream ends once the sender has been dropped and the last version has been yielded.
+///
+/// # Example
+///
+/// ```no_run
+/// use folo::{stream::WatchStream, sync::watch};
+/// use futures::StreamExt;
+///
+/// #[folo::main]
+/// async fn main() {
+///     let (tx, rx) = watch::channel(1);
+///     let mut versions = WatchStream::new(rx);
+///
+///     tx.send(2);
+///     drop(tx);
+///
+///     assert_eq!(versions.next().await.as_deref(), Some(&2));
+///     assert_eq!(versions.next().await, None);
+/// }
+/// ```
+#[derive(Debug)]
+#[must_use = "streams do nothing unless polled"]
+pub struct WatchStream<T> {
+    receiver: watch::Receiver<T>,
+
+    // The current value is yielded on the first poll even if the receiver has seen it.
+    started: bool,
+}
+
+impl<T> WatchStream<T> {
+    pub fn new(receiver: watch::Receiver<T>) -> Self {
+        Self {
+            receiver,
+            started: false,
+        }
+    }
+
+    /// Consumes the stream, returning the receiver.
+    pub fn into_inner(self) -> watch::Receiver<T> {
+        self.receiver
+    }
+}
+
+impl<T> From<watch::Receiver<T>> for WatchStream<T> {
+    fn from(receiver: watch::Receiver<T>) -> Self {
+        Self::new(receiver)
+    }
+}
+
+impl<T> Stream for WatchStream<T> {
+    type Item = Arc<T>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+    ) -> task::Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        if !this.started {
+            this.started = true;
+            return task::Poll::Ready(Some(this.receiver.borrow_and_update()));
+        }
+
+        match ready!(this.receiver.poll_changed(cx)) {
+            Ok(()) => task::Poll::Ready(Some(this.receiver.borrow_and_update())),
+            Err(watch::RecvError) => task::Poll::Ready(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{task::noop_waker_ref, StreamExt};
+
+    #[test]
+    fn yields_current_value_then_changes() {
+        let (tx, rx) = watch::channel(1);
+        let cx = &mut task::Context::from_waker(noop_waker_ref());
+
+        let mut versions = WatchStream::from(rx);
+
+        assert_eq!(
+            versions.poll_next_unpin(cx),
+            task::Poll::Ready(Some(Arc::new(1)))
+        );
+        assert!(versions.poll_next_unpin(cx).is_pending());
+
+        tx.send(2);
+        tx.send(3);
+        assert_eq!(
+            versions.poll_next_unpin(cx),
+            task::Poll::Ready(Some(Arc::new(3)))
+        );
+        assert!(versions.poll_next_unpin(cx).is_pending());
+
+        drop(tx);
+        assert_eq!(versions.poll_next_unpin(cx), task::Poll::Ready(None));
+    }
+}
@@ -1,4 +1,28 @@
+mod actor;
+mod cancellation;
+mod condvar;
+pub(crate) mod deadlock;
+pub mod mpmc;
+pub mod mpsc;
+mod mutexes;
+mod notify;
 pub mod once_event;
+pub mod oneshot;
+mod permits;
+mod rate_limiter;
+mod rwlocks;
 mod semaphores;
+pub mod spsc;
+mod thread_waker;
+pub mod watch;
 
-pub use semaphores::*;
\ No newline at end of file
+pub use actor::*;
+pub use cancellation::*;
+pub use condvar::*;
+pub use mutexes::*;
+pub use notify::*;
+pub(crate) use permits::*;
+pub use rate_limiter::*;
+pub use rwlocks::*;
+pub use semaphores::*;
+pub(crate) use thread_waker::*;
@@ -0,0 +1,189 @@
+use crate::sync::{
+    mpsc::{self, SendError, SharedReceiver, SharedSender},
+    oneshot,
+};
+use std::{
+    fmt::{self, Debug, Formatter},
+    future::Future,
+};
+use thiserror::Error;
+
+/// State that is owned by a task and only accessed by processing the messages sent to its
+/// [`Mailbox`]. The state never leaves the thread it was spawned on, so it does not need to be
+/// `Send` or protected by locks, whereas the mailbox may be used from any thread.
+///
+/// Messages that expect a response carry a [`oneshot::Sender`] for the actor to respond with. See
+/// [`Mailbox::call()`].
+///
+/// # Example
+///
+/// ```no_run
+/// use folo::sync::{oneshot, spawn_actor, Actor};
+/// use std::collections::HashMap;
+///
+/// #[derive(Default)]
+/// struct Cache {
+///     entries: HashMap<String, String>,
+/// }
+///
+/// enum CacheMessage {
+///     Insert(String, String),
+///     Get(String, oneshot::Sender<Option<String>>),
+/// }
+///
+/// impl Actor for Cache {
+///     type Message = CacheMessage;
+///
+///     async fn handle(&mut self, message: CacheMessage) {
+///         match message {
+///             CacheMessage::Insert(key, value) => {
+///                 self.entries.insert(key, value);
+///             }
+///             CacheMessage::Get(key, reply) => {
+///                 _ = reply.send(self.entries.get(&key).cloned());
+///             }
+///         }
+///     }
+/// }
+///
+/// #[folo::main]
+/// async fn main() {
+///     let cache = spawn_actor(Cache::default());
+///
+///     cache
+///         .cast(CacheMessage::Insert("key".to_string(), "value".to_string()))
+///         .unwrap();
+///
+///     let value = cache
+///         .call(|reply| CacheMessage::Get("key".to_string(), reply))
+///         .await
+///         .unwrap();
+///
+///     assert_eq!(value.as_deref(), Some("value"));
+///
+///     cache.stop().await;
+/// }
+/// ```
+pub trait Actor: 'static {
+    type Message: Send + 'static;
+
+    /// Processes one message. The next message is not processed until the returned future
+    /// completes, so the actor has exclusive access to its state across await points.
+    fn handle(&mut self, message: Self::Message) -> impl Future<Output = ()>;
+
+    /// Called once after the last message has been processed, either because the actor was
+    /// stopped via [`Mailbox::stop()`] or because all mailboxes have been dropped.
+    fn stopped(&mut self) -> impl Future<Output = ()> {
+        async {}
+    }
+}
+
+/// Spawns a task on the current thread that owns the actor and processes the messages sent to the
+/// returned mailbox, in the order they were sent. Clone the mailbox to send messages from more
+/// tasks or threads.
+///
+/// The task ends after [`Mailbox::stop()`] is called or all mailboxes are dropped, once the
+/// messages sent before that have been processed.
+pub fn spawn_actor<A: Actor>(mut actor: A) -> Mailbox<A::Message> {
+    let (tx, mut rx) = mpsc::shared_channel();
+
+    crate::rt::spawn(async move {
+        let mut stop_request = None;
+
+        while let Some(envelope) = rx.recv().await {
+            match envelope {
+                Envelope::Message(message) => actor.handle(message).await,
+                Envelope::Stop(stopped_tx) => {
+                    stop_request = Some(stopped_tx);
+                    break;
+                }
+            }
+        }
+
+        actor.stopped().await;
+
+        // Stop requests are answered by dropping their senders once the actor has stopped. Any
+        // later stop requests are still in the channel and are dropped with the receiver.
+        drop(stop_request);
+        drop(rx);
+    });
+
+    Mailbox { tx }
+}
+
+enum Envelope<M> {
+    Message(M),
+    Stop(oneshot::Sender<()>),
+}
+
+/// The error returned by [`Mailbox::call()`] if the actor stopped before responding.
+#[derive(Clone, Copy, Debug, Eq, Error, PartialEq)]
+#[error("the actor stopped before responding")]
+pub struct CallError;
+
+/// Sends messages to an [`Actor`] spawned via [`spawn_actor()`]. Can be used on any thread. Clone
+/// it to create more mailboxes for the same actor.
+pub struct Mailbox<M> {
+    tx: SharedSender<Envelope<M>>,
+}
+
+impl<M> Mailbox<M> {
+    /// Sends a message without waiting for it to be processed. Fails if the actor has stopped,
+    /// returning the message back to the caller.
+    pub fn cast(&self, message: M) -> Result<(), SendError<M>> {
+        self.tx
+            .send(Envelope::Message(message))
+            .map_err(|SendError(envelope)| match envelope {
+                Envelope::Message(message) => SendError(message),
+                Envelope::Stop(_) => unreachable!("we sent a message, not a stop request"),
+            })
+    }
+
+    /// Sends a message that carries a response channel and waits for the actor to respond. The
+    /// message is created by `message` from the sender the actor is expected to respond with.
+    ///
+    /// Fails if the actor stops, or has already stopped, without responding.
+    pub async fn call<R>(
+        &self,
+        message: impl FnOnce(oneshot::Sender<R>) -> M,
+    ) -> Result<R, CallError> {
+        let (tx, rx) = oneshot::channel();
+
+        self.cast(message(tx)).map_err(|_| CallError)?;
+        rx.await.map_err(|_| CallError)
+    }
+
+    /// Asks the actor to stop after processing the messages that were sent before this call and
+    /// waits until it has stopped. Messages sent after this call are dropped without being
+    /// processed. Completes immediately if the actor has already stopped.
+    pub async fn stop(&self) {
+        let (tx, rx) = oneshot::channel();
+
+        if self.tx.send(Envelope::Stop(tx)).is_ok() {
+            // The actor drops the sender without sending anything once it has stopped.
+            _ = rx.await;
+        }
+    }
+
+    /// Whether the actor has stopped, in which case sending messages is pointless.
+    pub fn is_stopped(&self) -> bool {
+        self.tx.is_closed()
+    }
+}
+
+impl<M> Debug for Mailbox<M> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        // The messages are not necessarily `Debug`, so we leave the channel out.
+        f.debug_struct("Mailbox")
+            .field("is_stopped", &self.is_stopped())
+            .finish()
+    }
+}
+
+impl<M> Clone for Mailbox<M> {
+    fn clone(&self) -> Self {
+        Self {
+            tx: self.tx.clone(),
+        }
+    }
+}
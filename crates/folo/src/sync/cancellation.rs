@@ -0,0 +1,481 @@
+use crate::{constants, rt::coop};
+use pin_project::pin_project;
+use std::{
+    collections::HashMap,
+    fmt::{self, Debug, Formatter},
+    future::Future,
+    mem,
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard, Weak},
+    task::{self, Waker},
+};
+
+/// Signals to any number of tasks, on any thread, that the work they are doing is no longer needed.
+///
+/// Tokens form a tree: cancelling a token also cancels all the tokens created from it via
+/// [`child_token()`][Self::child_token], while cancelling a child token does not affect its
+/// parent or siblings. This matches the shape of request processing - when a client disconnects,
+/// the token of its request is cancelled and all downstream work started on behalf of the request
+/// observes the cancellation, even if it is executing on other worker threads.
+///
+/// Cancellation is cooperative. Tasks observe it by awaiting [`cancelled()`][Self::cancelled] or
+/// by wrapping the work (e.g. an I/O operation or a delay) with
+/// [`run_until_cancelled()`][Self::run_until_cancelled], which drops the work once the token is
+/// cancelled.
+///
+/// This type is thread-safe. Clones refer to the same token.
+///
+/// # Example
+///
+/// ```
+/// use folo::rt::yield_now;
+/// use folo::sync::CancellationToken;
+///
+/// #[folo::main]
+/// async fn main() {
+///     let request = CancellationToken::new();
+///     let downstream = request.child_token();
+///
+///     request.cancel();
+///
+///     let result = downstream.run_until_cancelled(yield_now()).await;
+///     assert!(result.is_none());
+/// }
+/// ```
+pub struct CancellationToken {
+    inner: Arc<Node>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(Node::new(false)),
+        }
+    }
+
+    /// Creates a token that is cancelled when this token is cancelled. If this token is already
+    /// cancelled, the new token starts out cancelled.
+    pub fn child_token(&self) -> CancellationToken {
+        let mut state = self.inner.lock();
+
+        if state.cancelled {
+            return Self {
+                inner: Arc::new(Node::new(true)),
+            };
+        }
+
+        let child = Arc::new(Node::new(false));
+
+        // Children that have been dropped no longer need to be cancelled. We clean them up here,
+        // so a long-lived parent that creates many short-lived children does not accumulate them.
+        state.children.retain(|child| child.strong_count() > 0);
+        state.children.push(Arc::downgrade(&child));
+
+        Self { inner: child }
+    }
+
+    /// Cancels this token and all its descendants, waking up all tasks awaiting cancellation.
+    /// Cancelling an already cancelled token has no effect.
+    pub fn cancel(&self) {
+        self.inner.cancel();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.lock().cancelled
+    }
+
+    /// Waits until the token is cancelled.
+    pub fn cancelled(&self) -> Cancelled<'_> {
+        Cancelled {
+            token: self,
+            waiter_id: None,
+        }
+    }
+
+    /// Waits until the token is cancelled, like [`cancelled()`][Self::cancelled], with a future
+    /// that owns the token, so it can be stored in other futures and streams.
+    pub fn cancelled_owned(self) -> CancelledOwned {
+        CancelledOwned {
+            token: self,
+            waiter_id: None,
+        }
+    }
+
+    /// Executes a future until it completes or the token is cancelled, whichever happens first.
+    /// Returns `None` if the token was cancelled, in which case the future is dropped without
+    /// being polled again.
+    ///
+    /// If the token is already cancelled, the future is not polled at all.
+    pub fn run_until_cancelled<F>(&self, future: F) -> RunUntilCancelled<'_, F>
+    where
+        F: Future,
+    {
+        RunUntilCancelled {
+            cancelled: self.cancelled(),
+            future,
+        }
+    }
+
+    /// Returns a guard that cancels the token when dropped, e.g. to cancel all work started on
+    /// behalf of a request when the request handler returns or is dropped.
+    pub fn drop_guard(self) -> CancelOnDrop {
+        CancelOnDrop { token: Some(self) }
+    }
+}
+
+impl Clone for CancellationToken {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl Default for CancellationToken {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for CancellationToken {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CancellationToken")
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
+
+/// Future returned by `CancellationToken::cancelled()`.
+pub struct Cancelled<'a> {
+    token: &'a CancellationToken,
+
+    // Assigned on the first poll that has to wait, so the waker can be removed when we are dropped.
+    waiter_id: Option<u64>,
+}
+
+impl Cancelled<'_> {
+    fn poll_cancelled(&mut self, waker: &Waker) -> task::Poll<()> {
+        self.token.inner.poll_cancelled(&mut self.waiter_id, waker)
+    }
+}
+
+impl Future for Cancelled<'_> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> task::Poll<Self::Output> {
+        coop::poll_budgeted(cx, |cx| self.get_mut().poll_cancelled(cx.waker()))
+    }
+}
+
+impl Drop for Cancelled<'_> {
+    fn drop(&mut self) {
+        self.token.inner.remove_waiter(self.waiter_id);
+    }
+}
+
+impl Debug for Cancelled<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Cancelled")
+            .field("token", &self.token)
+            .finish()
+    }
+}
+
+/// Future returned by `CancellationToken::cancelled_owned()`.
+#[derive(Debug)]
+pub struct CancelledOwned {
+    token: CancellationToken,
+
+    // Assigned on the first poll that has to wait, so the waker can be removed when we are dropped.
+    waiter_id: Option<u64>,
+}
+
+impl CancelledOwned {
+    /// Checks for cancellation without consuming the cooperative scheduling budget, for adapters
+    /// that only check for cancellation before doing work that consumes the budget itself.
+    pub(crate) fn poll_cancelled(&mut self, waker: &Waker) -> task::Poll<()> {
+        self.token.inner.poll_cancelled(&mut self.waiter_id, waker)
+    }
+}
+
+impl Future for CancelledOwned {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> task::Poll<Self::Output> {
+        coop::poll_budgeted(cx, |cx| self.get_mut().poll_cancelled(cx.waker()))
+    }
+}
+
+impl Drop for CancelledOwned {
+    fn drop(&mut self) {
+        self.token.inner.remove_waiter(self.waiter_id);
+    }
+}
+
+/// Future returned by `CancellationToken::run_until_cancelled()`.
+#[pin_project]
+pub struct RunUntilCancelled<'a, F> {
+    cancelled: Cancelled<'a>,
+    #[pin]
+    future: F,
+}
+
+impl<F> Future for RunUntilCancelled<'_, F>
+where
+    F: Future,
+{
+    type Output = Option<F::Output>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> task::Poll<Self::Output> {
+        let this = self.project();
+
+        // We check for cancellation first, so work is not started (or continued) after the token
+        // is cancelled even if the future would be ready. This does not consume the cooperative
+        // scheduling budget - the inner future consumes it for any work it does.
+        if this.cancelled.poll_cancelled(cx.waker()).is_ready() {
+            return task::Poll::Ready(None);
+        }
+
+        this.future.poll(cx).map(Some)
+    }
+}
+
+impl<F> Debug for RunUntilCancelled<'_, F> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RunUntilCancelled")
+            .field("token", &self.cancelled.token)
+            .finish()
+    }
+}
+
+/// Cancels a token when dropped. Returned by `CancellationToken::drop_guard()`.
+#[derive(Debug)]
+pub struct CancelOnDrop {
+    token: Option<CancellationToken>,
+}
+
+impl CancelOnDrop {
+    /// Returns the token without cancelling it.
+    pub fn disarm(mut self) -> CancellationToken {
+        self.token
+            .take()
+            .expect("token is only taken when the guard is consumed")
+    }
+}
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        if let Some(token) = &self.token {
+            token.cancel();
+        }
+    }
+}
+
+struct Node {
+    state: Mutex<State>,
+}
+
+impl Node {
+    fn new(cancelled: bool) -> Self {
+        Self {
+            state: Mutex::new(State {
+                cancelled,
+                next_waiter_id: 0,
+                waiting: HashMap::new(),
+                children: Vec::new(),
+            }),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().expect(constants::POISONED_LOCK)
+    }
+
+    fn poll_cancelled(&self, waiter_id: &mut Option<u64>, waker: &Waker) -> task::Poll<()> {
+        let mut state = self.lock();
+
+        if state.cancelled {
+            return task::Poll::Ready(());
+        }
+
+        let waiter_id = *waiter_id.get_or_insert_with(|| {
+            let id = state.next_waiter_id;
+            state.next_waiter_id += 1;
+            id
+        });
+
+        // Each future has at most one registered waker, so repeated polls do not pile up.
+        state.waiting.insert(waiter_id, waker.clone());
+        task::Poll::Pending
+    }
+
+    fn remove_waiter(&self, waiter_id: Option<u64>) {
+        if let Some(waiter_id) = waiter_id {
+            self.lock().waiting.remove(&waiter_id);
+        }
+    }
+
+    fn cancel(&self) {
+        let (wakers, children) = {
+            let mut state = self.lock();
+
+            if state.cancelled {
+                return;
+            }
+
+            state.cancelled = true;
+
+            (
+                state
+                    .waiting
+                    .drain()
+                    .map(|(_, waker)| waker)
+                    .collect::<Vec<_>>(),
+                mem::take(&mut state.children),
+            )
+        };
+
+        // We wake and cancel children outside the lock to avoid needless contention with the
+        // woken tasks and to never hold the locks of two nodes at the same time.
+        for waker in wakers {
+            waker.wake();
+        }
+
+        for child in children {
+            if let Some(child) = child.upgrade() {
+                child.cancel();
+            }
+        }
+    }
+}
+
+struct State {
+    cancelled: bool,
+
+    next_waiter_id: u64,
+
+    // Futures that are waiting for cancellation, keyed by waiter ID.
+    waiting: HashMap<u64, Waker>,
+
+    // A child that has been dropped is never cancelled, as nobody can observe it anymore.
+    children: Vec<Weak<Node>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{future, task::noop_waker_ref, FutureExt};
+    use std::thread;
+
+    #[test]
+    fn cancel_propagates_to_descendants_only() {
+        let parent = CancellationToken::new();
+        let child = parent.child_token();
+        let grandchild = child.child_token();
+        let sibling = parent.child_token();
+
+        child.cancel();
+
+        assert!(!parent.is_cancelled());
+        assert!(child.is_cancelled());
+        assert!(grandchild.is_cancelled());
+        assert!(!sibling.is_cancelled());
+
+        parent.cancel();
+
+        assert!(parent.is_cancelled());
+        assert!(sibling.is_cancelled());
+
+        // Children of an already cancelled token start out cancelled.
+        assert!(parent.child_token().is_cancelled());
+    }
+
+    #[test]
+    fn dropped_children_are_pruned() {
+        let parent = CancellationToken::new();
+
+        for _ in 0..10 {
+            drop(parent.child_token());
+        }
+
+        let _child = parent.child_token();
+
+        assert_eq!(parent.inner.lock().children.len(), 1);
+    }
+
+    #[test]
+    fn cancelled_completes_after_cancel() {
+        let token = CancellationToken::new();
+
+        let mut cx = task::Context::from_waker(noop_waker_ref());
+
+        let mut cancelled = token.cancelled();
+        assert!(cancelled.poll_unpin(&mut cx).is_pending());
+        assert_eq!(token.inner.lock().waiting.len(), 1);
+
+        token.cancel();
+
+        assert!(cancelled.poll_unpin(&mut cx).is_ready());
+    }
+
+    #[test]
+    fn dropped_future_unregisters_waker() {
+        let token = CancellationToken::new();
+
+        let mut cx = task::Context::from_waker(noop_waker_ref());
+
+        let mut cancelled = token.cancelled();
+        assert!(cancelled.poll_unpin(&mut cx).is_pending());
+        assert!(cancelled.poll_unpin(&mut cx).is_pending());
+        assert_eq!(token.inner.lock().waiting.len(), 1);
+
+        drop(cancelled);
+
+        assert!(token.inner.lock().waiting.is_empty());
+    }
+
+    #[test]
+    fn run_until_cancelled_returns_result_or_none() {
+        let token = CancellationToken::new();
+
+        let result = futures::executor::block_on(token.run_until_cancelled(async { 42 }));
+        assert_eq!(result, Some(42));
+
+        let mut cx = task::Context::from_waker(noop_waker_ref());
+
+        let mut pending = Box::pin(token.run_until_cancelled(future::pending::<()>()));
+        assert!(pending.poll_unpin(&mut cx).is_pending());
+
+        token.cancel();
+
+        assert_eq!(pending.poll_unpin(&mut cx), task::Poll::Ready(None));
+
+        // Once cancelled, even a ready future is not polled.
+        let result = futures::executor::block_on(token.run_until_cancelled(async { 42 }));
+        assert_eq!(result, None);
+    }
+
+    #[test]
+    fn cross_thread_cancel() {
+        let parent = CancellationToken::new();
+        let child = parent.child_token();
+
+        let canceller = parent.clone();
+        let canceller = thread::spawn(move || canceller.cancel());
+
+        futures::executor::block_on(child.cancelled());
+
+        canceller.join().unwrap();
+    }
+
+    #[test]
+    fn drop_guard_cancels_unless_disarmed() {
+        let token = CancellationToken::new();
+
+        let token = token.drop_guard().disarm();
+        assert!(!token.is_cancelled());
+
+        drop(token.clone().drop_guard());
+        assert!(token.is_cancelled());
+    }
+}
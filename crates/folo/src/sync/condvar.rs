@@ -0,0 +1,159 @@
+use crate::sync::{MutexGuard, Notify};
+use std::pin::pin;
+
+/// An async condition variable for waiting until the value protected by a [`Mutex`][1] reaches a
+/// certain state, e.g. a queue becoming non-empty or a state machine reaching a certain phase.
+/// Waiting suspends the task instead of blocking the worker thread.
+///
+/// [`wait()`][Self::wait] releases the lock while waiting and acquires it again before returning.
+/// A notification sent after the lock is released is never missed, as the waiting task starts
+/// listening for notifications before it releases the lock.
+///
+/// Like with the condition variable of the standard library, a waiting task may also be woken up
+/// without a matching notification, so the condition has to be checked again after waking up.
+/// [`wait_while()`][Self::wait_while] does this for you.
+///
+/// Tasks waiting on async worker threads are woken up via the I/O driver of their thread when
+/// notified from another thread, so they do not wait for an I/O wait to time out.
+///
+/// # Example
+///
+/// ```no_run
+/// use folo::sync::{Condvar, Mutex};
+/// use std::sync::Arc;
+///
+/// #[folo::main]
+/// async fn main() {
+///     let state = Arc::new((Mutex::new(Vec::<u32>::new()), Condvar::new()));
+///
+///     let consumer = folo::rt::spawn_on_any({
+///         let state = Arc::clone(&state);
+///
+///         move || async move {
+///             let (queue, condvar) = &*state;
+///
+///             let mut queue = condvar
+///                 .wait_while(queue.lock().await, |queue| queue.is_empty())
+///                 .await;
+///
+///             queue.pop()
+///         }
+///     });
+///
+///     let (queue, condvar) = &*state;
+///     queue.lock().await.push(42);
+///     condvar.notify_one();
+///
+///     assert_eq!(consumer.await, Some(42));
+/// }
+/// ```
+///
+/// [1]: crate::sync::Mutex
+#[derive(Debug, Default)]
+pub struct Condvar {
+    notify: Notify,
+}
+
+impl Condvar {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Releases the lock, waits for a notification and acquires the lock again.
+    ///
+    /// The task may also be woken up without a matching notification, so check the condition
+    /// again after this returns or use [`wait_while()`][Self::wait_while] instead.
+    pub async fn wait<'m, T: ?Sized>(&self, guard: MutexGuard<'m, T>) -> MutexGuard<'m, T> {
+        let mutex = MutexGuard::mutex(&guard);
+
+        let mut notified = pin!(self.notify.notified());
+
+        // We start listening while we still hold the lock, so a task that changes the value and
+        // notifies us right after we release the lock cannot slip in between.
+        notified.as_mut().get_mut().enable();
+        drop(guard);
+
+        notified.await;
+        mutex.lock().await
+    }
+
+    /// Waits until `condition` returns `false`, releasing the lock while waiting. The condition is
+    /// checked with the lock held, first right away and then whenever the task is woken up.
+    pub async fn wait_while<'m, T: ?Sized>(
+        &self,
+        mut guard: MutexGuard<'m, T>,
+        mut condition: impl FnMut(&mut T) -> bool,
+    ) -> MutexGuard<'m, T> {
+        while condition(&mut guard) {
+            guard = self.wait(guard).await;
+        }
+
+        guard
+    }
+
+    /// Wakes up the task that has been waiting the longest. If no task is waiting, the next task
+    /// to wait is woken up right away.
+    pub fn notify_one(&self) {
+        self.notify.notify_one();
+    }
+
+    /// Wakes up all the tasks that are waiting.
+    pub fn notify_all(&self) {
+        self.notify.notify_waiters();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::Mutex;
+    use futures::{executor::block_on, task::noop_waker_ref, FutureExt};
+    use std::{sync::Arc, task, thread};
+
+    #[test]
+    fn notification_after_unlock_is_not_missed() {
+        let mutex = Mutex::new(false);
+        let condvar = Condvar::new();
+        let cx = &mut task::Context::from_waker(noop_waker_ref());
+
+        let guard = mutex.try_lock().unwrap();
+        let mut wait = Box::pin(condvar.wait(guard));
+        assert!(wait.poll_unpin(cx).is_pending());
+
+        *mutex.try_lock().unwrap() = true;
+        condvar.notify_one();
+
+        let task::Poll::Ready(guard) = wait.poll_unpin(cx) else {
+            panic!("waiter was notified and the lock is free, so it must complete");
+        };
+
+        assert!(*guard);
+    }
+
+    #[test]
+    fn notify_all_wakes_all_waiters() {
+        let state = Arc::new((Mutex::new(false), Condvar::new()));
+
+        let waiters = (0..4)
+            .map(|_| {
+                let state = Arc::clone(&state);
+
+                thread::spawn(move || {
+                    let (ready, condvar) = &*state;
+
+                    block_on(async {
+                        drop(condvar.wait_while(ready.lock().await, |r| !*r).await);
+                    });
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let (ready, condvar) = &*state;
+        *block_on(ready.lock()) = true;
+        condvar.notify_all();
+
+        for waiter in waiters {
+            waiter.join().unwrap();
+        }
+    }
+}
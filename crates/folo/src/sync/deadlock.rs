@@ -0,0 +1,261 @@
+//! Opt-in detection of deadlocks between tasks that wait for each other's thread-local locks.
+//!
+//! All the tasks that use a thread-local lock are on the same thread, so the thread can keep track
+//! of which task holds which lock and which lock each task waits for. When a task starts waiting,
+//! we follow the chain of holders and waiters from it. If the chain leads back to the task, none of
+//! the tasks in the chain can ever proceed, so we report them, turning a silent hang
+//! into an actionable error.
+//!
+//! Enabled via [`RuntimeBuilder::detect_local_deadlocks()`][1].
+//!
+//! [1]: crate::rt::RuntimeBuilder::detect_local_deadlocks
+
+use crate::rt::{
+    current_task_id, current_task_name,
+    diagnostics::{self, Diagnostic},
+    TaskId,
+};
+use std::{
+    cell::{Cell, RefCell},
+    collections::{HashMap, HashSet},
+    fmt::{self, Display, Formatter},
+    sync::Arc,
+};
+
+/// Identifies a lock for the purposes of deadlock detection, via the address of its permit queue.
+pub(crate) type LockId = usize;
+
+/// Enables or disables deadlock detection for the locks on the current thread.
+pub(crate) fn set_enabled(value: bool) {
+    ENABLED.with(|x| x.set(value));
+}
+
+/// Records that the current task acquired a lock.
+pub(crate) fn acquired(lock: LockId) {
+    with_current_task(|registry, task| registry.acquired(lock, task));
+}
+
+/// Records that a holder of a lock released it.
+pub(crate) fn released(lock: LockId) {
+    with_current_task(|registry, task| registry.released(lock, task.id));
+}
+
+/// Records that the current task started waiting for a lock, reporting a deadlock if that closes a
+/// cycle of tasks waiting for each other.
+pub(crate) fn waiting(lock: LockId) {
+    with_current_task(|registry, task| {
+        let task_id = task.id;
+        registry.waiting(lock, task);
+
+        if let Some(cycle) = registry.find_cycle(task_id) {
+            diagnostics::emit(Diagnostic::DeadlockDetected { cycle: &cycle });
+        }
+    });
+}
+
+/// Records that the current task stopped waiting for a lock, either because it acquired the lock or
+/// because it gave up.
+pub(crate) fn stopped_waiting() {
+    with_current_task(|registry, task| registry.stopped_waiting(task.id));
+}
+
+fn with_current_task(f: impl FnOnce(&mut Registry, Task)) {
+    if !ENABLED.with(Cell::get) {
+        return;
+    }
+
+    // Locks used outside of tasks (e.g. in tests that poll futures by hand) are not tracked.
+    let Some(id) = current_task_id() else {
+        return;
+    };
+
+    REGISTRY.with_borrow_mut(|registry| {
+        f(
+            registry,
+            Task {
+                id,
+                name: current_task_name(),
+            },
+        )
+    });
+}
+
+#[derive(Clone, Debug)]
+struct Task {
+    id: TaskId,
+    name: Option<Arc<str>>,
+}
+
+impl Display for Task {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "{} ({})", self.id, name),
+            None => write!(f, "{}", self.id),
+        }
+    }
+}
+
+/// The tasks in a deadlock, each waiting for a lock held by the next one and the last one waiting
+/// for a lock held by the first one.
+#[derive(Debug)]
+struct Cycle {
+    tasks: Vec<Task>,
+}
+
+impl Display for Cycle {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} waits for a lock held by ", self.tasks[0])?;
+
+        for task in &self.tasks[1..] {
+            write!(f, "{task}, which waits for a lock held by ")?;
+        }
+
+        write!(f, "{}", self.tasks[0])
+    }
+}
+
+#[derive(Debug, Default)]
+struct Registry {
+    // The tasks holding each lock. Read locks may have many holders.
+    holders: HashMap<LockId, Vec<Task>>,
+
+    // The lock each task is waiting for.
+    waiting: HashMap<TaskId, (LockId, Task)>,
+}
+
+impl Registry {
+    fn acquired(&mut self, lock: LockId, task: Task) {
+        self.holders.entry(lock).or_default().push(task);
+    }
+
+    fn released(&mut self, lock: LockId, task: TaskId) {
+        let Some(holders) = self.holders.get_mut(&lock) else {
+            return;
+        };
+
+        // A guard may be released by a task other than the one that acquired the lock, if it was
+        // handed over, in which case we cannot tell which holder it was and remove any of them.
+        let index = holders.iter().position(|h| h.id == task).unwrap_or(0);
+        holders.swap_remove(index);
+
+        if holders.is_empty() {
+            self.holders.remove(&lock);
+        }
+    }
+
+    fn waiting(&mut self, lock: LockId, task: Task) {
+        self.waiting.insert(task.id, (lock, task));
+    }
+
+    fn stopped_waiting(&mut self, task: TaskId) {
+        self.waiting.remove(&task);
+    }
+
+    /// Follows the holders of the lock that the task is waiting for, and the holders of the locks
+    /// that those are waiting for, looking for a path back to the task.
+    fn find_cycle(&self, start: TaskId) -> Option<Cycle> {
+        let mut path = Vec::new();
+        let mut visited = HashSet::new();
+
+        self.visit(start, start, &mut path, &mut visited)
+            .then(|| Cycle { tasks: path })
+    }
+
+    fn visit(
+        &self,
+        task: TaskId,
+        start: TaskId,
+        path: &mut Vec<Task>,
+        visited: &mut HashSet<TaskId>,
+    ) -> bool {
+        let Some((lock, waiter)) = self.waiting.get(&task) else {
+            return false;
+        };
+
+        if !visited.insert(task) {
+            return false;
+        }
+
+        path.push(waiter.clone());
+
+        for holder in self.holders.get(lock).into_iter().flatten() {
+            if holder.id == start || self.visit(holder.id, start, path, visited) {
+                return true;
+            }
+        }
+
+        path.pop();
+        false
+    }
+}
+
+thread_local! {
+    static ENABLED: Cell<bool> = const { Cell::new(false) };
+
+    static REGISTRY: RefCell<Registry> = RefCell::new(Registry::default());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(id: u64) -> Task {
+        Task {
+            id: TaskId::from_u64(id),
+            name: Some(format!("worker {id}").into()),
+        }
+    }
+
+    #[test]
+    fn detects_cycle() {
+        let mut registry = Registry::default();
+
+        registry.acquired(1, task(10));
+        registry.acquired(2, task(20));
+        registry.waiting(2, task(10));
+        assert!(registry.find_cycle(TaskId::from_u64(10)).is_none());
+
+        registry.waiting(1, task(20));
+        let cycle = registry.find_cycle(TaskId::from_u64(20)).unwrap();
+
+        assert_eq!(
+            cycle.to_string(),
+            "task-20 (worker 20) waits for a lock held by task-10 (worker 10), which waits for a \
+             lock held by task-20 (worker 20)"
+        );
+    }
+
+    #[test]
+    fn detects_waiting_for_own_lock() {
+        let mut registry = Registry::default();
+
+        registry.acquired(1, task(10));
+        registry.waiting(1, task(10));
+
+        assert_eq!(
+            registry
+                .find_cycle(TaskId::from_u64(10))
+                .unwrap()
+                .tasks
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn released_lock_breaks_chain() {
+        let mut registry = Registry::default();
+
+        registry.acquired(1, task(10));
+        registry.acquired(1, task(30));
+        registry.acquired(2, task(20));
+        registry.waiting(2, task(10));
+
+        registry.released(1, TaskId::from_u64(10));
+        registry.waiting(1, task(20));
+        assert!(registry.find_cycle(TaskId::from_u64(20)).is_none());
+
+        registry.stopped_waiting(TaskId::from_u64(20));
+        assert!(registry.waiting.get(&TaskId::from_u64(20)).is_none());
+    }
+}
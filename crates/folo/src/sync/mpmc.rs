@@ -0,0 +1,515 @@
+//! An injector channel for distributing jobs between worker threads: any number of senders push
+//! jobs from any thread and any number of receivers, typically one per worker, pull them as they
+//! become idle.
+//!
+//! This is a lighter-weight alternative to work stealing for coarse-grained jobs (e.g. processing
+//! one file per job), where the cost of a shared queue is negligible compared to the job itself.
+//! Jobs are received in the order they were sent and each job is received by exactly one receiver.
+//!
+//! The channel reports the following metrics:
+//!
+//! * `sync_mpmc_depth` - the number of queued jobs, observed whenever a job is sent.
+//! * `sync_mpmc_latency_millis` - the time a job spent in the queue, observed whenever a job is
+//!   received.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use folo::sync::mpmc;
+//!
+//! #[folo::main]
+//! async fn main() {
+//!     let (tx, rx) = mpmc::channel::<String>();
+//!
+//!     let workers = (0..4)
+//!         .map(|_| {
+//!             let mut rx = rx.clone();
+//!
+//!             folo::rt::spawn_on_any(move || async move {
+//!                 while let Some(path) = rx.recv().await {
+//!                     println!("processing {path}");
+//!                 }
+//!             })
+//!         })
+//!         .collect::<Vec<_>>();
+//!
+//!     for i in 0..100 {
+//!         tx.send(format!("file{i}.txt")).unwrap();
+//!     }
+//!
+//!     // The workers stop once the queue is empty and all senders have been dropped.
+//!     drop(tx);
+//!
+//!     for worker in workers {
+//!         worker.await;
+//!     }
+//! }
+//! ```
+
+use crate::{
+    constants::{GENERAL_MILLISECONDS_BUCKETS, POISONED_LOCK},
+    metrics::{observe, Event, EventBuilder, Magnitude},
+    rt::coop,
+    sync::ThreadWaker,
+    time::LowPrecisionInstant,
+};
+use futures::Stream;
+use std::{
+    collections::VecDeque,
+    future::Future,
+    mem,
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard},
+    task,
+};
+
+pub use crate::sync::mpsc::{SendError, TryRecvError};
+
+/// Creates an unbounded injector channel. The senders and the receivers may be used on any thread.
+/// Clone them to create more senders and receivers.
+///
+/// Receivers waiting for a job on async worker threads are woken up via the I/O driver of their
+/// thread when a job is sent from another thread, so the job is picked up without delay even if the
+/// worker is parked waiting for I/O.
+pub fn channel<T: Send>() -> (Sender<T>, Receiver<T>) {
+    let channel = Arc::new(Channel {
+        state: Mutex::new(State {
+            queue: VecDeque::new(),
+            waiting: VecDeque::new(),
+            senders: 1,
+            receivers: 1,
+            next_receiver_id: 1,
+        }),
+    });
+
+    (
+        Sender {
+            channel: Arc::clone(&channel),
+        },
+        Receiver {
+            channel,
+            id: 0,
+            registered: false,
+        },
+    )
+}
+
+#[derive(Debug)]
+struct Channel<T> {
+    state: Mutex<State<T>>,
+}
+
+impl<T> Channel<T> {
+    fn lock(&self) -> MutexGuard<'_, State<T>> {
+        self.state.lock().expect(POISONED_LOCK)
+    }
+}
+
+#[derive(Debug)]
+struct State<T> {
+    queue: VecDeque<Job<T>>,
+
+    // The receivers waiting for a job, in the order they started waiting. Each job that is sent
+    // wakes up the receiver that has been waiting the longest.
+    waiting: VecDeque<Waiter>,
+
+    senders: usize,
+    receivers: usize,
+
+    // Each receiver has an ID, so a receiver that is polled again can replace its waker.
+    next_receiver_id: u64,
+}
+
+impl<T> State<T> {
+    /// Takes the waker of the receiver that has been waiting the longest, if there is a job for
+    /// it. Called whenever a job may have been left unclaimed by a receiver that gave up.
+    fn wake_next(&mut self) -> Option<ThreadWaker> {
+        if self.queue.is_empty() {
+            return None;
+        }
+
+        self.waiting.pop_front().map(|w| w.waker)
+    }
+}
+
+#[derive(Debug)]
+struct Job<T> {
+    value: T,
+    sent: LowPrecisionInstant,
+}
+
+#[derive(Debug)]
+struct Waiter {
+    receiver_id: u64,
+    waker: ThreadWaker,
+}
+
+fn wake(waker: Option<ThreadWaker>) {
+    // We wake outside the lock, so the receiver does not have to wait for us to release it.
+    if let Some(waker) = waker {
+        waker.wake();
+    }
+}
+
+/// Sends jobs to an injector [`channel()`] from any thread. Clone it to create more senders.
+///
+/// The receivers observe the end of the stream once all senders have been dropped and the queue is
+/// empty.
+#[derive(Debug)]
+pub struct Sender<T> {
+    channel: Arc<Channel<T>>,
+}
+
+impl<T> Sender<T> {
+    /// Queues a job, waking up the receiver that has been waiting the longest. Fails if all the
+    /// receivers have been dropped, returning the job back to the caller.
+    pub fn send(&self, value: T) -> Result<(), SendError<T>> {
+        let (waker, depth) = {
+            let mut state = self.channel.lock();
+
+            if state.receivers == 0 {
+                return Err(SendError(value));
+            }
+
+            state.queue.push_back(Job {
+                value,
+                sent: LowPrecisionInstant::now(),
+            });
+
+            (
+                state.waiting.pop_front().map(|w| w.waker),
+                state.queue.len(),
+            )
+        };
+
+        observe!(DEPTH.observe(depth as Magnitude));
+
+        wake(waker);
+        Ok(())
+    }
+
+    /// The number of queued jobs that no receiver has picked up yet.
+    pub fn len(&self) -> usize {
+        self.channel.lock().queue.len()
+    }
+
+    /// Whether no jobs are queued.
+    pub fn is_empty(&self) -> bool {
+        self.channel.lock().queue.is_empty()
+    }
+
+    /// Whether all receivers have been dropped, in which case sending jobs is pointless.
+    pub fn is_closed(&self) -> bool {
+        self.channel.lock().receivers == 0
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.channel.lock().senders += 1;
+
+        Self {
+            channel: Arc::clone(&self.channel),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let waiting = {
+            let mut state = self.channel.lock();
+            state.senders -= 1;
+
+            // The waiting receivers need to learn that no more jobs are coming.
+            if state.senders == 0 {
+                mem::take(&mut state.waiting)
+            } else {
+                VecDeque::new()
+            }
+        };
+
+        for waiter in waiting {
+            waiter.waker.wake();
+        }
+    }
+}
+
+/// Receives the jobs sent to an injector [`channel()`], on any thread. Can also be consumed as a
+/// [`Stream`]. Clone it to create more receivers, e.g. one for each worker thread.
+///
+/// Dropping the last receiver drops the queued jobs and makes further sends fail.
+#[derive(Debug)]
+pub struct Receiver<T> {
+    channel: Arc<Channel<T>>,
+
+    // Unique among the receivers of the channel.
+    id: u64,
+
+    // Whether we have registered as waiting since we last received a job. If we were woken up but
+    // give up waiting, the job we were woken up for is passed on to the next waiting receiver.
+    registered: bool,
+}
+
+impl<T> Receiver<T> {
+    /// Receives the next job, waiting for one to be sent if the queue is empty. Returns `None` once
+    /// the queue is empty and all senders have been dropped.
+    ///
+    /// If the returned future is dropped before it completes, a job that this receiver was woken
+    /// up for is passed on to the next waiting receiver.
+    pub fn recv(&mut self) -> Recv<'_, T> {
+        Recv { receiver: self }
+    }
+
+    /// Receives the next job if one is queued, without waiting.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        let job = {
+            let mut state = self.channel.lock();
+
+            match state.queue.pop_front() {
+                Some(job) => job,
+                None if state.senders == 0 => return Err(TryRecvError::Disconnected),
+                None => return Err(TryRecvError::Empty),
+            }
+        };
+
+        Ok(self.received(job))
+    }
+
+    /// Polls for the next job, registering the waker of the current task to be woken when one is
+    /// sent. Returns `None` once the queue is empty and all senders have been dropped.
+    pub fn poll_recv(&mut self, cx: &mut task::Context<'_>) -> task::Poll<Option<T>> {
+        coop::poll_budgeted(cx, |cx| {
+            let job = {
+                let mut state = self.channel.lock();
+
+                match state.queue.pop_front() {
+                    Some(job) => {
+                        // We may still be queued as waiting if another receiver took the job we
+                        // were woken up for and we found a later one.
+                        if self.registered {
+                            state.waiting.retain(|w| w.receiver_id != self.id);
+                            self.registered = false;
+                        }
+
+                        job
+                    }
+                    None if state.senders == 0 => return task::Poll::Ready(None),
+                    None => {
+                        match state.waiting.iter_mut().find(|w| w.receiver_id == self.id) {
+                            Some(waiter) if waiter.waker.will_wake(cx.waker()) => {}
+                            Some(waiter) => waiter.waker = ThreadWaker::new(cx.waker()),
+                            None => state.waiting.push_back(Waiter {
+                                receiver_id: self.id,
+                                waker: ThreadWaker::new(cx.waker()),
+                            }),
+                        }
+
+                        self.registered = true;
+                        return task::Poll::Pending;
+                    }
+                }
+            };
+
+            task::Poll::Ready(Some(self.received(job)))
+        })
+    }
+
+    /// The number of queued jobs that no receiver has picked up yet.
+    pub fn len(&self) -> usize {
+        self.channel.lock().queue.len()
+    }
+
+    /// Whether no jobs are queued.
+    pub fn is_empty(&self) -> bool {
+        self.channel.lock().queue.is_empty()
+    }
+
+    fn received(&self, job: Job<T>) -> T {
+        observe!(LATENCY.observe_millis(job.sent.elapsed()));
+        job.value
+    }
+
+    /// Stops waiting for a job. If we had already been woken up for a job, the wake-up passes on
+    /// to the next waiting receiver, so the job is not left in the queue while receivers wait.
+    fn cancel_wait(&mut self) {
+        if !mem::take(&mut self.registered) {
+            return;
+        }
+
+        let waker = {
+            let mut state = self.channel.lock();
+
+            match state.waiting.iter().position(|w| w.receiver_id == self.id) {
+                Some(index) => {
+                    state.waiting.remove(index);
+                    None
+                }
+                None => state.wake_next(),
+            }
+        };
+
+        wake(waker);
+    }
+}
+
+impl<T> Clone for Receiver<T> {
+    fn clone(&self) -> Self {
+        let mut state = self.channel.lock();
+
+        let id = state.next_receiver_id;
+        state.next_receiver_id += 1;
+        state.receivers += 1;
+
+        Self {
+            channel: Arc::clone(&self.channel),
+            id,
+            registered: false,
+        }
+    }
+}
+
+impl<T> Stream for Receiver<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> task::Poll<Option<T>> {
+        self.get_mut().poll_recv(cx)
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.cancel_wait();
+
+        // The jobs are dropped outside the lock, in case dropping them takes a while.
+        let jobs = {
+            let mut state = self.channel.lock();
+            state.receivers -= 1;
+
+            if state.receivers == 0 {
+                mem::take(&mut state.queue)
+            } else {
+                VecDeque::new()
+            }
+        };
+
+        drop(jobs);
+    }
+}
+
+/// Receives a job from an injector [`channel()`]. Created by [`Receiver::recv()`].
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Recv<'r, T> {
+    receiver: &'r mut Receiver<T>,
+}
+
+impl<T> Future for Recv<'_, T> {
+    type Output = Option<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> task::Poll<Option<T>> {
+        self.get_mut().receiver.poll_recv(cx)
+    }
+}
+
+impl<T> Drop for Recv<'_, T> {
+    fn drop(&mut self) {
+        self.receiver.cancel_wait();
+    }
+}
+
+const DEPTH_BUCKETS: &[Magnitude] = &[0, 1, 10, 100, 1000, 10000];
+
+thread_local! {
+    static DEPTH: Event = EventBuilder::new("sync_mpmc_depth")
+        .buckets(DEPTH_BUCKETS)
+        .build();
+
+    static LATENCY: Event = EventBuilder::new("sync_mpmc_latency_millis")
+        .buckets(GENERAL_MILLISECONDS_BUCKETS)
+        .build();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{executor::block_on, task::noop_waker_ref, FutureExt, StreamExt};
+    use std::{pin::pin, thread};
+
+    #[test]
+    fn each_job_is_received_once() {
+        let (tx, rx) = channel();
+
+        let workers = (0..4)
+            .map(|_| {
+                let rx = rx.clone();
+                thread::spawn(move || block_on(rx.collect::<Vec<_>>()))
+            })
+            .collect::<Vec<_>>();
+
+        drop(rx);
+
+        for i in 0..100 {
+            tx.send(i).unwrap();
+        }
+
+        drop(tx);
+
+        let mut received = workers
+            .into_iter()
+            .flat_map(|w| w.join().unwrap())
+            .collect::<Vec<_>>();
+
+        received.sort_unstable();
+        assert_eq!(received, (0..100).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn longest_waiting_receiver_is_woken() {
+        let (tx, mut first) = channel();
+        let mut second = first.clone();
+        let cx = &mut task::Context::from_waker(noop_waker_ref());
+
+        let mut first_recv = pin!(first.recv());
+        let mut second_recv = pin!(second.recv());
+        assert!(first_recv.poll_unpin(cx).is_pending());
+        assert!(second_recv.poll_unpin(cx).is_pending());
+
+        tx.send(1).unwrap();
+
+        assert_eq!(first_recv.poll_unpin(cx), task::Poll::Ready(Some(1)));
+        assert!(second_recv.poll_unpin(cx).is_pending());
+    }
+
+    #[test]
+    fn wake_up_passes_on_when_receiver_gives_up() {
+        let (tx, mut first) = channel();
+        let mut second = first.clone();
+        let cx = &mut task::Context::from_waker(noop_waker_ref());
+
+        let mut first_recv = Box::pin(first.recv());
+        let mut second_recv = pin!(second.recv());
+        assert!(first_recv.poll_unpin(cx).is_pending());
+        assert!(second_recv.poll_unpin(cx).is_pending());
+
+        tx.send(1).unwrap();
+        drop(first_recv);
+
+        assert_eq!(second_recv.poll_unpin(cx), task::Poll::Ready(Some(1)));
+        assert_eq!(first.try_recv(), Err(TryRecvError::Empty));
+    }
+
+    #[test]
+    fn send_fails_after_receivers_dropped() {
+        let (tx, rx) = channel();
+        let rx2 = rx.clone();
+
+        tx.send(1).unwrap();
+        drop(rx);
+        assert_eq!(tx.len(), 1);
+
+        drop(rx2);
+        assert!(tx.is_closed());
+        assert!(tx.is_empty());
+        assert!(matches!(tx.send(2), Err(SendError(2))));
+    }
+}
@@ -0,0 +1,18 @@
+//! Channels for sending a stream of values from any number of senders to one receiver.
+//!
+//! Most channels in this module are specialized for communication between tasks on the same async
+//! worker thread, e.g. the stages of a pipeline running on one core. None of their endpoints can
+//! leave the thread that created the channel, which allows them to work without atomics or locking.
+//! Bounded channels limit the number of values that can be buffered, with `send()` waiting for
+//! the receiver to make room. Unbounded channels buffer any number of values.
+//!
+//! To feed values into a worker thread from other threads, use a [`shared_channel()`], whose
+//! senders may be used on any thread.
+
+mod error;
+mod local;
+mod shared;
+
+pub use error::*;
+pub use local::*;
+pub use shared::*;
@@ -0,0 +1,77 @@
+use std::fmt::{self, Debug, Display, Formatter};
+
+/// The error returned when sending to a channel whose receiver has been dropped. Contains the value
+/// that could not be sent.
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub struct SendError<T>(pub T);
+
+impl<T> Debug for SendError<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        // The value itself is not necessarily `Debug`, so we leave it out.
+        f.write_str("SendError(..)")
+    }
+}
+
+impl<T> Display for SendError<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "the receiver has been dropped")
+    }
+}
+
+impl<T> std::error::Error for SendError<T> {}
+
+/// The error returned by `try_send()` when the value cannot be sent right away. Contains the value
+/// that could not be sent.
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub enum TrySendError<T> {
+    /// The channel is at capacity.
+    Full(T),
+
+    /// The receiver has been dropped.
+    Closed(T),
+}
+
+impl<T> TrySendError<T> {
+    /// Returns the value that could not be sent.
+    pub fn into_inner(self) -> T {
+        match self {
+            Self::Full(value) | Self::Closed(value) => value,
+        }
+    }
+}
+
+impl<T> Debug for TrySendError<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Full(_) => f.write_str("Full(..)"),
+            Self::Closed(_) => f.write_str("Closed(..)"),
+        }
+    }
+}
+
+impl<T> Display for TrySendError<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Full(_) => write!(f, "the channel is at capacity"),
+            Self::Closed(_) => write!(f, "the receiver has been dropped"),
+        }
+    }
+}
+
+impl<T> std::error::Error for TrySendError<T> {}
+
+impl<T> From<SendError<T>> for TrySendError<T> {
+    fn from(error: SendError<T>) -> Self {
+        Self::Closed(error.0)
+    }
+}
+
+/// The error returned by `try_recv()` when no value can be received right away.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, thiserror::Error)]
+pub enum TryRecvError {
+    #[error("the channel is empty")]
+    Empty,
+
+    #[error("the channel is empty and all senders have been dropped")]
+    Disconnected,
+}
@@ -0,0 +1,440 @@
+use super::{SendError, TryRecvError, TrySendError};
+use crate::rt::coop;
+use futures::Stream;
+use negative_impl::negative_impl;
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    future::poll_fn,
+    pin::Pin,
+    rc::Rc,
+    task::{self, Waker},
+};
+
+/// Creates a bounded channel that buffers up to `capacity` values. Sending waits for the receiver
+/// to make room once the channel is at capacity, which applies backpressure to the senders.
+///
+/// All endpoints must stay on the current thread.
+///
+/// # Panics
+///
+/// Panics if `capacity` is zero.
+///
+/// # Example
+///
+/// ```no_run
+/// use folo::sync::mpsc;
+///
+/// #[folo::main]
+/// async fn main() {
+///     let (tx, mut rx) = mpsc::channel(16);
+///
+///     folo::rt::spawn(async move {
+///         for i in 0..100 {
+///             tx.send(i).await.unwrap();
+///         }
+///     });
+///
+///     while let Some(i) = rx.recv().await {
+///         println!("{i}");
+///     }
+/// }
+/// ```
+pub fn channel<T>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    assert!(capacity > 0, "bounded channel capacity must be at least 1");
+
+    let channel = Channel::new(Some(capacity));
+
+    (
+        Sender {
+            channel: Rc::clone(&channel),
+        },
+        Receiver { channel },
+    )
+}
+
+/// Creates an unbounded channel that buffers any number of values. Sending never waits.
+///
+/// All endpoints must stay on the current thread.
+pub fn unbounded_channel<T>() -> (UnboundedSender<T>, Receiver<T>) {
+    let channel = Channel::new(None);
+
+    (
+        UnboundedSender {
+            channel: Rc::clone(&channel),
+        },
+        Receiver { channel },
+    )
+}
+
+#[derive(Debug)]
+struct Channel<T> {
+    queue: VecDeque<T>,
+
+    // `None` for unbounded channels.
+    capacity: Option<usize>,
+
+    // The receiver, if it is waiting for a value.
+    receiver_waker: Option<Waker>,
+
+    // Senders waiting for the channel to have room.
+    waiting_senders: Vec<Waker>,
+
+    senders: usize,
+    receiver_dropped: bool,
+}
+
+impl<T> Channel<T> {
+    fn new(capacity: Option<usize>) -> Rc<RefCell<Self>> {
+        Rc::new(RefCell::new(Self {
+            queue: VecDeque::new(),
+            capacity,
+            receiver_waker: None,
+            waiting_senders: Vec::new(),
+            senders: 1,
+            receiver_dropped: false,
+        }))
+    }
+
+    fn is_full(&self) -> bool {
+        self.capacity
+            .is_some_and(|capacity| self.queue.len() >= capacity)
+    }
+
+    fn push(&mut self, value: T) {
+        self.queue.push_back(value);
+
+        if let Some(waker) = self.receiver_waker.take() {
+            waker.wake();
+        }
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        let was_full = self.is_full();
+        let value = self.queue.pop_front()?;
+
+        if was_full {
+            // We wake all the waiting senders because some of them may have given up waiting, in
+            // which case waking only one could leave the rest waiting for room that is available.
+            // Those that do not get the room go back to waiting.
+            self.wake_senders();
+        }
+
+        Some(value)
+    }
+
+    fn wake_senders(&mut self) {
+        for waker in self.waiting_senders.drain(..) {
+            waker.wake();
+        }
+    }
+
+    fn add_sender(&mut self) {
+        self.senders += 1;
+    }
+
+    fn remove_sender(&mut self) {
+        self.senders -= 1;
+
+        if self.senders == 0 {
+            // The receiver needs to learn that no more values are coming.
+            if let Some(waker) = self.receiver_waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+/// Sends values to a bounded [`channel()`]. Clone it to create more senders.
+///
+/// The receiver observes the end of the stream once all senders have been dropped.
+#[derive(Debug)]
+pub struct Sender<T> {
+    channel: Rc<RefCell<Channel<T>>>,
+}
+
+impl<T> Sender<T> {
+    /// Sends a value, waiting for the channel to have room if it is at capacity. Fails if the
+    /// receiver has been dropped, returning the value back to the caller.
+    pub async fn send(&self, value: T) -> Result<(), SendError<T>> {
+        let mut value = Some(value);
+
+        poll_fn(|cx| {
+            let mut channel = self.channel.borrow_mut();
+
+            if !channel.receiver_dropped && channel.is_full() {
+                channel.waiting_senders.push(cx.waker().clone());
+                return task::Poll::Pending;
+            }
+
+            let value = value
+                .take()
+                .expect("value is only taken when the send completes");
+
+            if channel.receiver_dropped {
+                return task::Poll::Ready(Err(SendError(value)));
+            }
+
+            channel.push(value);
+            task::Poll::Ready(Ok(()))
+        })
+        .await
+    }
+
+    /// Sends a value if the channel has room, without waiting.
+    pub fn try_send(&self, value: T) -> Result<(), TrySendError<T>> {
+        let mut channel = self.channel.borrow_mut();
+
+        if channel.receiver_dropped {
+            return Err(TrySendError::Closed(value));
+        }
+
+        if channel.is_full() {
+            return Err(TrySendError::Full(value));
+        }
+
+        channel.push(value);
+        Ok(())
+    }
+
+    /// Whether the receiver has been dropped, in which case sending values is pointless.
+    pub fn is_closed(&self) -> bool {
+        self.channel.borrow().receiver_dropped
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.channel.borrow_mut().add_sender();
+
+        Self {
+            channel: Rc::clone(&self.channel),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        self.channel.borrow_mut().remove_sender();
+    }
+}
+
+#[negative_impl]
+impl<T> !Send for Sender<T> {}
+#[negative_impl]
+impl<T> !Sync for Sender<T> {}
+
+/// Sends values to an [`unbounded_channel()`]. Clone it to create more senders.
+///
+/// The receiver observes the end of the stream once all senders have been dropped.
+#[derive(Debug)]
+pub struct UnboundedSender<T> {
+    channel: Rc<RefCell<Channel<T>>>,
+}
+
+impl<T> UnboundedSender<T> {
+    /// Sends a value. Fails if the receiver has been dropped, returning the value back to the
+    /// caller.
+    pub fn send(&self, value: T) -> Result<(), SendError<T>> {
+        let mut channel = self.channel.borrow_mut();
+
+        if channel.receiver_dropped {
+            return Err(SendError(value));
+        }
+
+        channel.push(value);
+        Ok(())
+    }
+
+    /// Whether the receiver has been dropped, in which case sending values is pointless.
+    pub fn is_closed(&self) -> bool {
+        self.channel.borrow().receiver_dropped
+    }
+}
+
+impl<T> Clone for UnboundedSender<T> {
+    fn clone(&self) -> Self {
+        self.channel.borrow_mut().add_sender();
+
+        Self {
+            channel: Rc::clone(&self.channel),
+        }
+    }
+}
+
+impl<T> Drop for UnboundedSender<T> {
+    fn drop(&mut self) {
+        self.channel.borrow_mut().remove_sender();
+    }
+}
+
+#[negative_impl]
+impl<T> !Send for UnboundedSender<T> {}
+#[negative_impl]
+impl<T> !Sync for UnboundedSender<T> {}
+
+/// Receives the values sent to a [`channel()`] or [`unbounded_channel()`], in the order they were
+/// sent. Can also be consumed as a [`Stream`].
+///
+/// Dropping the receiver drops the buffered values and makes further sends fail.
+#[derive(Debug)]
+pub struct Receiver<T> {
+    channel: Rc<RefCell<Channel<T>>>,
+}
+
+impl<T> Receiver<T> {
+    /// Receives the next value, waiting for one to be sent if the channel is empty. Returns `None`
+    /// once the channel is empty and all senders have been dropped.
+    pub async fn recv(&mut self) -> Option<T> {
+        poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
+    /// Receives the next value if one has already been sent, without waiting.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        let mut channel = self.channel.borrow_mut();
+
+        match channel.pop() {
+            Some(value) => Ok(value),
+            None if channel.senders == 0 => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    /// Polls for the next value, registering the waker of the current task to be woken when one
+    /// is sent. Returns `None` once the channel is empty and all senders have been dropped.
+    pub fn poll_recv(&mut self, cx: &mut task::Context<'_>) -> task::Poll<Option<T>> {
+        coop::poll_budgeted(cx, |cx| {
+            let mut channel = self.channel.borrow_mut();
+
+            if let Some(value) = channel.pop() {
+                return task::Poll::Ready(Some(value));
+            }
+
+            if channel.senders == 0 {
+                return task::Poll::Ready(None);
+            }
+
+            channel.receiver_waker = Some(cx.waker().clone());
+            task::Poll::Pending
+        })
+    }
+
+    /// The number of values buffered in the channel.
+    pub fn len(&self) -> usize {
+        self.channel.borrow().queue.len()
+    }
+
+    /// Whether the channel has no buffered values.
+    pub fn is_empty(&self) -> bool {
+        self.channel.borrow().queue.is_empty()
+    }
+}
+
+impl<T> Stream for Receiver<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> task::Poll<Option<T>> {
+        self.get_mut().poll_recv(cx)
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        // The values are dropped outside the borrow, in case dropping them touches the channel.
+        let values = {
+            let mut channel = self.channel.borrow_mut();
+
+            channel.receiver_dropped = true;
+
+            // Waiting senders need to learn that their sends are going to fail.
+            channel.wake_senders();
+
+            std::mem::take(&mut channel.queue)
+        };
+
+        drop(values);
+    }
+}
+
+#[negative_impl]
+impl<T> !Send for Receiver<T> {}
+#[negative_impl]
+impl<T> !Sync for Receiver<T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{executor::block_on, task::noop_waker_ref, FutureExt};
+    use std::pin::pin;
+
+    #[test]
+    fn unbounded_send_and_recv() {
+        let (tx, mut rx) = unbounded_channel();
+
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+        assert_eq!(rx.len(), 2);
+
+        assert_eq!(block_on(rx.recv()), Some(1));
+        assert_eq!(rx.try_recv(), Ok(2));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+
+        drop(tx);
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+        assert_eq!(block_on(rx.recv()), None);
+    }
+
+    #[test]
+    fn bounded_send_waits_for_room() {
+        let (tx, mut rx) = channel(1);
+        let cx = &mut task::Context::from_waker(noop_waker_ref());
+
+        tx.try_send(1).unwrap();
+        assert!(matches!(tx.try_send(2), Err(TrySendError::Full(2))));
+
+        let mut send = pin!(tx.send(2));
+        assert!(send.poll_unpin(cx).is_pending());
+
+        assert_eq!(rx.try_recv(), Ok(1));
+        assert!(matches!(send.poll_unpin(cx), task::Poll::Ready(Ok(()))));
+        assert_eq!(rx.try_recv(), Ok(2));
+    }
+
+    #[test]
+    fn recv_waits_for_value() {
+        let (tx, mut rx) = unbounded_channel();
+        let cx = &mut task::Context::from_waker(noop_waker_ref());
+
+        let mut recv = pin!(rx.recv());
+        assert!(recv.poll_unpin(cx).is_pending());
+
+        tx.send(42).unwrap();
+        assert_eq!(recv.poll_unpin(cx), task::Poll::Ready(Some(42)));
+    }
+
+    #[test]
+    fn send_fails_after_receiver_dropped() {
+        let (tx, rx) = channel(1);
+
+        drop(rx);
+
+        assert!(tx.is_closed());
+        assert!(matches!(tx.try_send(1), Err(TrySendError::Closed(1))));
+        assert!(matches!(block_on(tx.send(1)), Err(SendError(1))));
+    }
+
+    #[test]
+    fn recv_ends_after_all_senders_dropped() {
+        let (tx, mut rx) = channel(4);
+        let tx2 = tx.clone();
+
+        block_on(tx.send(1)).unwrap();
+        drop(tx);
+        block_on(tx2.send(2)).unwrap();
+        drop(tx2);
+
+        assert_eq!(block_on(rx.recv()), Some(1));
+        assert_eq!(block_on(rx.recv()), Some(2));
+        assert_eq!(block_on(rx.recv()), None);
+    }
+}
@@ -0,0 +1,300 @@
+use super::{SendError, TryRecvError};
+use crate::{constants::POISONED_LOCK, rt::coop, sync::ThreadWaker};
+use futures::Stream;
+use negative_impl::negative_impl;
+use std::{
+    collections::VecDeque,
+    future::poll_fn,
+    mem,
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard},
+    task,
+};
+
+/// Creates an unbounded channel whose receiver stays on the current thread and whose senders may
+/// be used on any thread, including threads that are not owned by a Folo runtime.
+///
+/// This is the bridge for feeding external events (e.g. callbacks of a native library or the
+/// results of blocking work on a dedicated thread) into tasks on an async worker thread. When a
+/// value is sent from another thread while the receiver is waiting, the sender wakes up the
+/// receiving worker thread via its I/O driver, so the value is delivered without delay even if the
+/// worker is parked waiting for I/O.
+///
+/// For channels whose endpoints all stay on the same thread, [`unbounded_channel()`][1] is more
+/// efficient.
+///
+/// # Example
+///
+/// ```no_run
+/// use folo::sync::mpsc;
+/// use std::thread;
+///
+/// #[folo::main]
+/// async fn main() {
+///     let (tx, mut rx) = mpsc::shared_channel();
+///
+///     let producer = thread::spawn(move || {
+///         for i in 0..10 {
+///             tx.send(i).unwrap();
+///         }
+///     });
+///
+///     while let Some(i) = rx.recv().await {
+///         println!("{i}");
+///     }
+///
+///     producer.join().unwrap();
+/// }
+/// ```
+///
+/// [1]: super::unbounded_channel
+pub fn shared_channel<T: Send>() -> (SharedSender<T>, SharedReceiver<T>) {
+    let channel = Arc::new(Channel {
+        state: Mutex::new(State {
+            queue: VecDeque::new(),
+            receiver_waker: None,
+            senders: 1,
+            receiver_dropped: false,
+        }),
+    });
+
+    (
+        SharedSender {
+            channel: Arc::clone(&channel),
+        },
+        SharedReceiver { channel },
+    )
+}
+
+#[derive(Debug)]
+struct Channel<T> {
+    state: Mutex<State<T>>,
+}
+
+impl<T> Channel<T> {
+    fn lock(&self) -> MutexGuard<'_, State<T>> {
+        self.state.lock().expect(POISONED_LOCK)
+    }
+}
+
+#[derive(Debug)]
+struct State<T> {
+    queue: VecDeque<T>,
+
+    // The receiver, if it is waiting for a value. Also wakes up the thread of the receiver if it is
+    // parked waiting for I/O, so values sent from other threads are delivered without delay.
+    receiver_waker: Option<ThreadWaker>,
+
+    senders: usize,
+    receiver_dropped: bool,
+}
+
+/// Sends values to a [`shared_channel()`] from any thread. Clone it to create more senders.
+///
+/// The receiver observes the end of the stream once all senders have been dropped.
+#[derive(Debug)]
+pub struct SharedSender<T> {
+    channel: Arc<Channel<T>>,
+}
+
+impl<T> SharedSender<T> {
+    /// Sends a value, waking up the receiver if it is waiting. Fails if the receiver has been
+    /// dropped, returning the value back to the caller.
+    pub fn send(&self, value: T) -> Result<(), SendError<T>> {
+        let waker = {
+            let mut state = self.channel.lock();
+
+            if state.receiver_dropped {
+                return Err(SendError(value));
+            }
+
+            state.queue.push_back(value);
+            state.receiver_waker.take()
+        };
+
+        // We wake outside the lock, so the receiver does not have to wait for us to release it.
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+
+        Ok(())
+    }
+
+    /// Whether the receiver has been dropped, in which case sending values is pointless.
+    pub fn is_closed(&self) -> bool {
+        self.channel.lock().receiver_dropped
+    }
+}
+
+impl<T> Clone for SharedSender<T> {
+    fn clone(&self) -> Self {
+        self.channel.lock().senders += 1;
+
+        Self {
+            channel: Arc::clone(&self.channel),
+        }
+    }
+}
+
+impl<T> Drop for SharedSender<T> {
+    fn drop(&mut self) {
+        let waker = {
+            let mut state = self.channel.lock();
+            state.senders -= 1;
+
+            // The receiver needs to learn that no more values are coming.
+            if state.senders == 0 {
+                state.receiver_waker.take()
+            } else {
+                None
+            }
+        };
+
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+/// Receives the values sent to a [`shared_channel()`], in the order they were sent. Can also be
+/// consumed as a [`Stream`]. Must stay on the thread that created the channel.
+///
+/// Dropping the receiver drops the buffered values and makes further sends fail.
+#[derive(Debug)]
+pub struct SharedReceiver<T> {
+    channel: Arc<Channel<T>>,
+}
+
+impl<T> SharedReceiver<T> {
+    /// Receives the next value, waiting for one to be sent if the channel is empty. Returns `None`
+    /// once the channel is empty and all senders have been dropped.
+    pub async fn recv(&mut self) -> Option<T> {
+        poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
+    /// Receives the next value if one has already been sent, without waiting.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        let mut state = self.channel.lock();
+
+        match state.queue.pop_front() {
+            Some(value) => Ok(value),
+            None if state.senders == 0 => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    /// Polls for the next value, registering the waker of the current task to be woken when one
+    /// is sent. Returns `None` once the channel is empty and all senders have been dropped.
+    pub fn poll_recv(&mut self, cx: &mut task::Context<'_>) -> task::Poll<Option<T>> {
+        coop::poll_budgeted(cx, |cx| {
+            let mut state = self.channel.lock();
+
+            if let Some(value) = state.queue.pop_front() {
+                return task::Poll::Ready(Some(value));
+            }
+
+            if state.senders == 0 {
+                return task::Poll::Ready(None);
+            }
+
+            if !state
+                .receiver_waker
+                .as_ref()
+                .is_some_and(|w| w.will_wake(cx.waker()))
+            {
+                state.receiver_waker = Some(ThreadWaker::new(cx.waker()));
+            }
+
+            task::Poll::Pending
+        })
+    }
+
+    /// The number of values buffered in the channel.
+    pub fn len(&self) -> usize {
+        self.channel.lock().queue.len()
+    }
+
+    /// Whether the channel has no buffered values.
+    pub fn is_empty(&self) -> bool {
+        self.channel.lock().queue.is_empty()
+    }
+}
+
+impl<T> Stream for SharedReceiver<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> task::Poll<Option<T>> {
+        self.get_mut().poll_recv(cx)
+    }
+}
+
+impl<T> Drop for SharedReceiver<T> {
+    fn drop(&mut self) {
+        // The values are dropped outside the lock, in case dropping them takes a while.
+        let values = {
+            let mut state = self.channel.lock();
+            state.receiver_dropped = true;
+            state.receiver_waker = None;
+
+            mem::take(&mut state.queue)
+        };
+
+        drop(values);
+    }
+}
+
+#[negative_impl]
+impl<T> !Send for SharedReceiver<T> {}
+#[negative_impl]
+impl<T> !Sync for SharedReceiver<T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{executor::block_on, task::noop_waker_ref, FutureExt, StreamExt};
+    use std::{pin::pin, thread};
+
+    #[test]
+    fn send_from_other_threads() {
+        let (tx, rx) = shared_channel();
+
+        let producers = (0..4)
+            .map(|i| {
+                let tx = tx.clone();
+                thread::spawn(move || tx.send(i).unwrap())
+            })
+            .collect::<Vec<_>>();
+
+        drop(tx);
+
+        for producer in producers {
+            producer.join().unwrap();
+        }
+
+        let mut received = block_on(rx.collect::<Vec<_>>());
+        received.sort_unstable();
+        assert_eq!(received, vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn recv_waits_for_value() {
+        let (tx, mut rx) = shared_channel();
+        let cx = &mut task::Context::from_waker(noop_waker_ref());
+
+        let mut recv = pin!(rx.recv());
+        assert!(recv.poll_unpin(cx).is_pending());
+
+        thread::spawn(move || tx.send(42).unwrap()).join().unwrap();
+        assert_eq!(recv.poll_unpin(cx), task::Poll::Ready(Some(42)));
+    }
+
+    #[test]
+    fn send_fails_after_receiver_dropped() {
+        let (tx, rx) = shared_channel();
+
+        drop(rx);
+
+        assert!(tx.is_closed());
+        assert!(matches!(tx.send(1), Err(SendError(1))));
+    }
+}
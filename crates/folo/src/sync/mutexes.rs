@@ -0,0 +1,371 @@
+use crate::sync::{LocalPermitQueue, SharedPermitQueue};
+use negative_impl::negative_impl;
+use std::{
+    cell::UnsafeCell,
+    future::Future,
+    ops::{Deref, DerefMut},
+    pin::Pin,
+    task::{self, ready},
+};
+
+/// An async mutual exclusion lock for protecting a thread-local resource that is used across
+/// await points. Waiting for the lock suspends the task instead of blocking the worker thread.
+///
+/// Tasks acquire the lock in the order they started waiting for it. The lock uses no atomic
+/// operations, so it is cheap to use if all the tasks that use the resource are on the same
+/// thread, as is usually the case. Use [`Mutex`] to share a resource between threads.
+///
+/// # Example
+///
+/// ```no_run
+/// use folo::sync::LocalMutex;
+/// use std::rc::Rc;
+///
+/// #[folo::main]
+/// async fn main() {
+///     let connections = Rc::new(LocalMutex::new(Vec::<String>::new()));
+///
+///     let task = folo::rt::spawn({
+///         let connections = Rc::clone(&connections);
+///
+///         async move {
+///             let mut connections = connections.lock().await;
+///             folo::rt::yield_now().await;
+///             connections.push("example.com".to_string());
+///         }
+///     });
+///
+///     task.await;
+///     assert_eq!(connections.lock().await.len(), 1);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct LocalMutex<T: ?Sized> {
+    permits: LocalPermitQueue,
+    value: UnsafeCell<T>,
+}
+
+impl<T> LocalMutex<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            permits: LocalPermitQueue::new(1),
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    /// Consumes the mutex, returning the protected value.
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+}
+
+impl<T: ?Sized> LocalMutex<T> {
+    /// Acquires the lock, waiting for it to be released if it is held by another task.
+    ///
+    /// If the returned future is dropped before it completes, the task gives up its place in the
+    /// queue without affecting the other waiting tasks.
+    pub fn lock(&self) -> LocalLock<'_, T> {
+        LocalLock {
+            mutex: self,
+            waiter: None,
+        }
+    }
+
+    /// Acquires the lock if nobody holds it or waits for it, without waiting.
+    pub fn try_lock(&self) -> Option<LocalMutexGuard<'_, T>> {
+        if self.permits.try_acquire(1) {
+            Some(LocalMutexGuard { mutex: self })
+        } else {
+            None
+        }
+    }
+
+    /// A mutable reference to the protected value. No locking is needed because the mutable
+    /// reference to the mutex guarantees that nobody else is using it.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.value.get_mut()
+    }
+}
+
+impl<T: Default> Default for LocalMutex<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+#[negative_impl]
+impl<T: ?Sized> !Send for LocalMutex<T> {}
+#[negative_impl]
+impl<T: ?Sized> !Sync for LocalMutex<T> {}
+
+/// Acquires a [`LocalMutex`]. Created by [`LocalMutex::lock()`].
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct LocalLock<'m, T: ?Sized> {
+    mutex: &'m LocalMutex<T>,
+
+    // Our place in the queue while we wait for the lock.
+    waiter: Option<u64>,
+}
+
+impl<'m, T: ?Sized> Future for LocalLock<'m, T> {
+    type Output = LocalMutexGuard<'m, T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> task::Poll<Self::Output> {
+        let this = self.get_mut();
+
+        ready!(this
+            .mutex
+            .permits
+            .poll_acquire(1, false, &mut this.waiter, cx));
+
+        task::Poll::Ready(LocalMutexGuard { mutex: this.mutex })
+    }
+}
+
+impl<T: ?Sized> Drop for LocalLock<'_, T> {
+    fn drop(&mut self) {
+        self.mutex.permits.cancel(&mut self.waiter);
+    }
+}
+
+/// Grants access to the value protected by a [`LocalMutex`]. The lock is released when the guard
+/// is dropped.
+#[derive(Debug)]
+#[must_use = "the lock is released as soon as the guard is dropped"]
+pub struct LocalMutexGuard<'m, T: ?Sized> {
+    mutex: &'m LocalMutex<T>,
+}
+
+impl<T: ?Sized> Deref for LocalMutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: The guard holds the lock, so nobody else accesses the value.
+        unsafe { &*self.mutex.value.get() }
+    }
+}
+
+impl<T: ?Sized> DerefMut for LocalMutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: The guard holds the lock, so nobody else accesses the value.
+        unsafe { &mut *self.mutex.value.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for LocalMutexGuard<'_, T> {
+    fn drop(&mut self) {
+        self.mutex.permits.release(1);
+    }
+}
+
+/// An async mutual exclusion lock for protecting a resource that is shared between threads and
+/// used across await points. Waiting for the lock suspends the task instead of blocking the worker
+/// thread, unlike with the mutex of the standard library.
+///
+/// Tasks acquire the lock in the order they started waiting for it. Tasks waiting for the lock on
+/// async worker threads are woken up via the I/O driver of their thread when the lock is released
+/// on another thread, so they do not wait for an I/O wait to time out.
+///
+/// If all the tasks that use the resource are on the same thread, [`LocalMutex`] is cheaper.
+#[derive(Debug)]
+pub struct Mutex<T: ?Sized> {
+    permits: SharedPermitQueue,
+    value: UnsafeCell<T>,
+}
+
+impl<T> Mutex<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            permits: SharedPermitQueue::new(1),
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    /// Consumes the mutex, returning the protected value.
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+}
+
+impl<T: ?Sized> Mutex<T> {
+    /// Acquires the lock, waiting for it to be released if it is held by another task.
+    ///
+    /// If the returned future is dropped before it completes, the task gives up its place in the
+    /// queue without affecting the other waiting tasks.
+    pub fn lock(&self) -> Lock<'_, T> {
+        Lock {
+            mutex: self,
+            waiter: None,
+        }
+    }
+
+    /// Acquires the lock if nobody holds it or waits for it, without waiting.
+    pub fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+        if self.permits.try_acquire(1) {
+            Some(MutexGuard { mutex: self })
+        } else {
+            None
+        }
+    }
+
+    /// A mutable reference to the protected value. No locking is needed because the mutable
+    /// reference to the mutex guarantees that nobody else is using it.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.value.get_mut()
+    }
+}
+
+impl<T: Default> Default for Mutex<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+// SAFETY: The value is only accessed by the holder of the lock, so the mutex can be sent and
+// shared between threads as long as the value can be sent between threads.
+unsafe impl<T: ?Sized + Send> Send for Mutex<T> {}
+// SAFETY: See above.
+unsafe impl<T: ?Sized + Send> Sync for Mutex<T> {}
+
+/// Acquires a [`Mutex`]. Created by [`Mutex::lock()`].
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Lock<'m, T: ?Sized> {
+    mutex: &'m Mutex<T>,
+
+    // Our place in the queue while we wait for the lock.
+    waiter: Option<u64>,
+}
+
+impl<'m, T: ?Sized> Future for Lock<'m, T> {
+    type Output = MutexGuard<'m, T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> task::Poll<Self::Output> {
+        let this = self.get_mut();
+
+        ready!(this
+            .mutex
+            .permits
+            .poll_acquire(1, false, &mut this.waiter, cx));
+
+        task::Poll::Ready(MutexGuard { mutex: this.mutex })
+    }
+}
+
+impl<T: ?Sized> Drop for Lock<'_, T> {
+    fn drop(&mut self) {
+        self.mutex.permits.cancel(&mut self.waiter);
+    }
+}
+
+/// Grants access to the value protected by a [`Mutex`]. The lock is released when the guard is
+/// dropped.
+#[derive(Debug)]
+#[must_use = "the lock is released as soon as the guard is dropped"]
+pub struct MutexGuard<'m, T: ?Sized> {
+    mutex: &'m Mutex<T>,
+}
+
+impl<'m, T: ?Sized> MutexGuard<'m, T> {
+    /// The mutex that the guard holds the lock of. An associated function rather than a method,
+    /// so it does not shadow methods of the protected value.
+    pub(crate) fn mutex(this: &Self) -> &'m Mutex<T> {
+        this.mutex
+    }
+}
+
+impl<T: ?Sized> Deref for MutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: The guard holds the lock, so nobody else accesses the value.
+        unsafe { &*self.mutex.value.get() }
+    }
+}
+
+impl<T: ?Sized> DerefMut for MutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: The guard holds the lock, so nobody else accesses the value.
+        unsafe { &mut *self.mutex.value.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for MutexGuard<'_, T> {
+    fn drop(&mut self) {
+        self.mutex.permits.release(1);
+    }
+}
+
+// SAFETY: The guard grants shared access to the value via shared references to the guard, so
+// those may only cross threads if the value can be shared between threads.
+unsafe impl<T: ?Sized + Send + Sync> Sync for MutexGuard<'_, T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{executor::block_on, task::noop_waker_ref, FutureExt};
+    use std::{pin::pin, sync::Arc, thread};
+
+    #[test]
+    fn local_lock_waits_for_release() {
+        let mutex = LocalMutex::new(1);
+        let cx = &mut task::Context::from_waker(noop_waker_ref());
+
+        let mut guard = block_on(mutex.lock());
+        *guard += 1;
+
+        let mut lock = pin!(mutex.lock());
+        assert!(lock.poll_unpin(cx).is_pending());
+        assert!(mutex.try_lock().is_none());
+
+        drop(guard);
+
+        let task::Poll::Ready(guard) = lock.poll_unpin(cx) else {
+            panic!("lock was released, so it must be acquired");
+        };
+
+        assert_eq!(*guard, 2);
+    }
+
+    #[test]
+    fn local_lock_cancelled_while_waiting() {
+        let mutex = LocalMutex::new(());
+        let cx = &mut task::Context::from_waker(noop_waker_ref());
+
+        let guard = mutex.try_lock().unwrap();
+
+        let mut first = Box::pin(mutex.lock());
+        let mut second = pin!(mutex.lock());
+        assert!(first.poll_unpin(cx).is_pending());
+        assert!(second.poll_unpin(cx).is_pending());
+
+        drop(guard);
+        drop(first);
+
+        assert!(second.poll_unpin(cx).is_ready());
+    }
+
+    #[test]
+    fn shared_lock_across_threads() {
+        let mutex = Arc::new(Mutex::new(0));
+
+        let threads = (0..4)
+            .map(|_| {
+                let mutex = Arc::clone(&mutex);
+
+                thread::spawn(move || {
+                    for _ in 0..100 {
+                        *block_on(mutex.lock()) += 1;
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        assert_eq!(*mutex.try_lock().unwrap(), 400);
+    }
+}
@@ -0,0 +1,359 @@
+use crate::{constants::POISONED_LOCK, rt::coop, sync::ThreadWaker};
+use std::{
+    collections::VecDeque,
+    future::Future,
+    mem,
+    pin::Pin,
+    sync::{Mutex, MutexGuard},
+    task,
+};
+
+/// Notifies waiting tasks that something has happened, without carrying any data. The building
+/// block for hand-rolled futures and condition-like waits, for tasks on one thread or many.
+///
+/// [`notify_one()`][Self::notify_one] wakes up the task that has been waiting the longest. If no
+/// task is waiting, the notification is stored as a permit, so the next task to wait completes
+/// immediately. At most one permit is stored, so notifications do not accumulate.
+///
+/// [`notify_waiters()`][Self::notify_waiters] wakes up all the tasks whose [`Notified`] futures
+/// were created before the call, without storing a permit.
+///
+/// Tasks waiting on async worker threads are woken up via the I/O driver of their thread when
+/// notified from another thread, so they do not wait for an I/O wait to time out.
+///
+/// # Example
+///
+/// ```no_run
+/// use folo::sync::Notify;
+/// use std::sync::Arc;
+///
+/// #[folo::main]
+/// async fn main() {
+///     let notify = Arc::new(Notify::new());
+///
+///     let waiter = folo::rt::spawn({
+///         let notify = Arc::clone(&notify);
+///
+///         async move {
+///             notify.notified().await;
+///             println!("notified");
+///         }
+///     });
+///
+///     notify.notify_one();
+///     waiter.await;
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct Notify {
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    // Stored by `notify_one()` if nobody is waiting.
+    permit: bool,
+
+    // Incremented by every `notify_waiters()`, so futures created before the call complete.
+    generation: u64,
+
+    // In the order the tasks started waiting. Notified waiters stay in the queue until their
+    // futures observe the notification or are dropped.
+    waiters: VecDeque<Waiter>,
+    next_waiter_id: u64,
+}
+
+#[derive(Debug)]
+struct Waiter {
+    id: u64,
+    waker: Option<ThreadWaker>,
+
+    // Set by `notify_one()`.
+    notified: bool,
+}
+
+impl State {
+    /// Delivers a notification to the longest waiting task that has not yet been notified, or
+    /// stores it as a permit if there is no such task. Returns the waker of the notified task.
+    fn notify_one(&mut self) -> Option<ThreadWaker> {
+        match self.waiters.iter_mut().find(|w| !w.notified) {
+            Some(waiter) => {
+                waiter.notified = true;
+                waiter.waker.take()
+            }
+            None => {
+                self.permit = true;
+                None
+            }
+        }
+    }
+}
+
+impl Notify {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Waits for a notification. The future observes calls to
+    /// [`notify_waiters()`][Self::notify_waiters] from the moment it is created, even before it is
+    /// first polled, whereas it only competes for [`notify_one()`][Self::notify_one] notifications
+    /// once it is polled.
+    pub fn notified(&self) -> Notified<'_> {
+        Notified {
+            notify: self,
+            generation: self.lock().generation,
+            waiter: None,
+            done: false,
+        }
+    }
+
+    /// Wakes up the task that has been waiting the longest or, if no task is waiting, stores a
+    /// permit that the next task to wait consumes.
+    pub fn notify_one(&self) {
+        let waker = self.lock().notify_one();
+
+        // We wake outside the lock, so the task does not have to wait for us to release it.
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    /// Wakes up all the tasks whose [`Notified`] futures were created before this call. Does not
+    /// store a permit if no task is waiting.
+    pub fn notify_waiters(&self) {
+        let wakers = {
+            let mut state = self.lock();
+            state.generation = state.generation.wrapping_add(1);
+
+            state
+                .waiters
+                .iter_mut()
+                .filter_map(|w| w.waker.take())
+                .collect::<Vec<_>>()
+        };
+
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().expect(POISONED_LOCK)
+    }
+}
+
+/// Waits for a notification from a [`Notify`]. Created by [`Notify::notified()`].
+///
+/// If a future that received a [`notify_one()`][Notify::notify_one] notification is dropped
+/// before observing it, the notification passes on to the next waiting task, so it is not lost.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Notified<'n> {
+    notify: &'n Notify,
+
+    // The number of `notify_waiters()` calls before we were created.
+    generation: u64,
+
+    // Our place in the queue while we wait.
+    waiter: Option<u64>,
+
+    done: bool,
+}
+
+impl Notified<'_> {
+    /// Enters the queue of waiting tasks without polling, so the future competes for
+    /// [`notify_one()`][Notify::notify_one] notifications from now on. The task is woken up once
+    /// the future has been polled.
+    pub(crate) fn enable(&mut self) {
+        if self.waiter.is_some() || self.done {
+            return;
+        }
+
+        let mut state = self.notify.lock();
+
+        let id = state.next_waiter_id;
+        state.next_waiter_id += 1;
+
+        state.waiters.push_back(Waiter {
+            id,
+            waker: None,
+            notified: false,
+        });
+
+        self.waiter = Some(id);
+    }
+}
+
+impl Future for Notified<'_> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> task::Poll<()> {
+        coop::poll_budgeted(cx, |cx| {
+            let this = self.get_mut();
+            assert!(!this.done, "future polled after completion");
+
+            let mut state = this.notify.lock();
+
+            let Some(id) = this.waiter else {
+                if state.generation != this.generation || mem::take(&mut state.permit) {
+                    this.done = true;
+                    return task::Poll::Ready(());
+                }
+
+                let id = state.next_waiter_id;
+                state.next_waiter_id += 1;
+
+                state.waiters.push_back(Waiter {
+                    id,
+                    waker: Some(ThreadWaker::new(cx.waker())),
+                    notified: false,
+                });
+
+                this.waiter = Some(id);
+                return task::Poll::Pending;
+            };
+
+            let index = state
+                .waiters
+                .iter()
+                .position(|w| w.id == id)
+                .expect("a waiter is in the queue until its future completes or is dropped");
+
+            if state.waiters[index].notified || state.generation != this.generation {
+                // If we were notified both ways, the `notify_one()` notification is consumed, too.
+                state.waiters.remove(index);
+
+                this.waiter = None;
+                this.done = true;
+                return task::Poll::Ready(());
+            }
+
+            let waiter = &mut state.waiters[index];
+
+            if !waiter
+                .waker
+                .as_ref()
+                .is_some_and(|w| w.will_wake(cx.waker()))
+            {
+                waiter.waker = Some(ThreadWaker::new(cx.waker()));
+            }
+
+            task::Poll::Pending
+        })
+    }
+}
+
+impl Drop for Notified<'_> {
+    fn drop(&mut self) {
+        let Some(id) = self.waiter else {
+            return;
+        };
+
+        let waker = {
+            let mut state = self.notify.lock();
+
+            let index = state
+                .waiters
+                .iter()
+                .position(|w| w.id == id)
+                .expect("a waiter is in the queue until its future completes or is dropped");
+
+            let waiter = state
+                .waiters
+                .remove(index)
+                .expect("we just found the waiter");
+
+            // A notification meant for one task must not be lost just because that task gave up.
+            if waiter.notified {
+                state.notify_one()
+            } else {
+                None
+            }
+        };
+
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{task::noop_waker_ref, FutureExt};
+    use std::{pin::pin, sync::Arc, thread};
+
+    #[test]
+    fn permit_is_stored_once() {
+        let notify = Notify::new();
+        let cx = &mut task::Context::from_waker(noop_waker_ref());
+
+        notify.notify_one();
+        notify.notify_one();
+
+        assert!(notify.notified().poll_unpin(cx).is_ready());
+        assert!(notify.notified().poll_unpin(cx).is_pending());
+    }
+
+    #[test]
+    fn notify_one_wakes_longest_waiting() {
+        let notify = Notify::new();
+        let cx = &mut task::Context::from_waker(noop_waker_ref());
+
+        let mut first = pin!(notify.notified());
+        let mut second = pin!(notify.notified());
+        assert!(first.poll_unpin(cx).is_pending());
+        assert!(second.poll_unpin(cx).is_pending());
+
+        notify.notify_one();
+
+        assert!(second.poll_unpin(cx).is_pending());
+        assert!(first.poll_unpin(cx).is_ready());
+    }
+
+    #[test]
+    fn notification_passes_on_when_dropped() {
+        let notify = Notify::new();
+        let cx = &mut task::Context::from_waker(noop_waker_ref());
+
+        let mut first = Box::pin(notify.notified());
+        let mut second = pin!(notify.notified());
+        assert!(first.poll_unpin(cx).is_pending());
+        assert!(second.poll_unpin(cx).is_pending());
+
+        notify.notify_one();
+        drop(first);
+
+        assert!(second.poll_unpin(cx).is_ready());
+    }
+
+    #[test]
+    fn notify_waiters_wakes_futures_created_before() {
+        let notify = Notify::new();
+        let cx = &mut task::Context::from_waker(noop_waker_ref());
+
+        let mut polled = pin!(notify.notified());
+        assert!(polled.poll_unpin(cx).is_pending());
+        let mut not_yet_polled = pin!(notify.notified());
+
+        notify.notify_waiters();
+        let mut created_after = pin!(notify.notified());
+
+        assert!(polled.poll_unpin(cx).is_ready());
+        assert!(not_yet_polled.poll_unpin(cx).is_ready());
+        assert!(created_after.poll_unpin(cx).is_pending());
+    }
+
+    #[test]
+    fn notify_from_other_thread() {
+        let notify = Arc::new(Notify::new());
+
+        let notifier = thread::spawn({
+            let notify = Arc::clone(&notify);
+            move || notify.notify_one()
+        });
+
+        futures::executor::block_on(notify.notified());
+        notifier.join().unwrap();
+    }
+}
@@ -1,4 +1,5 @@
 use crate::mem::{RcSlabRc, RefSlabRc, SlabRcBox, SlabRcStorage, UnsafeSlabRc};
+use crate::rt::coop;
 use crate::util::WithRefCount;
 use std::marker::PhantomPinned;
 use std::{
@@ -249,12 +250,14 @@ impl<T> Future for RefReceiver<'_, T> {
     type Output = T;
 
     fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> task::Poll<Self::Output> {
-        let result = self.event.deref_pin().poll(cx.waker());
+        coop::poll_budgeted(cx, |cx| {
+            let result = self.event.deref_pin().poll(cx.waker());
 
-        match result {
-            Some(result) => task::Poll::Ready(result),
-            None => task::Poll::Pending,
-        }
+            match result {
+                Some(result) => task::Poll::Ready(result),
+                None => task::Poll::Pending,
+            }
+        })
     }
 }
 
@@ -280,12 +283,14 @@ impl<T> Future for RcReceiver<T> {
     type Output = T;
 
     fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> task::Poll<Self::Output> {
-        let result = self.event.deref_pin().poll(cx.waker());
+        coop::poll_budgeted(cx, |cx| {
+            let result = self.event.deref_pin().poll(cx.waker());
 
-        match result {
-            Some(result) => task::Poll::Ready(result),
-            None => task::Poll::Pending,
-        }
+            match result {
+                Some(result) => task::Poll::Ready(result),
+                None => task::Poll::Pending,
+            }
+        })
     }
 }
 
@@ -311,12 +316,14 @@ impl<T> Future for UnsafeReceiver<T> {
     type Output = T;
 
     fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> task::Poll<Self::Output> {
-        let result = self.event.deref_pin().poll(cx.waker());
+        coop::poll_budgeted(cx, |cx| {
+            let result = self.event.deref_pin().poll(cx.waker());
 
-        match result {
-            Some(result) => task::Poll::Ready(result),
-            None => task::Poll::Pending,
-        }
+            match result {
+                Some(result) => task::Poll::Ready(result),
+                None => task::Poll::Pending,
+            }
+        })
     }
 }
 
@@ -401,23 +408,25 @@ impl<T> Future for EmbeddedReceiver<T> {
     type Output = T;
 
     fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> task::Poll<Self::Output> {
-        // SAFETY: We rely on the owner of the event to guarantee that the backing storage remains
-        // alive for at least as long as the event itself.
-        let storage = unsafe { &*self.event };
-
-        // SAFETY: See comments on storage type alias.
-        let storage = unsafe { &*storage.inner.get() };
-
-        let result = storage
-            .get()
-            .as_ref()
-            .expect("OnceEvent must still exist because receiver exists")
-            .poll(cx.waker());
-
-        match result {
-            Some(result) => task::Poll::Ready(result),
-            None => task::Poll::Pending,
-        }
+        coop::poll_budgeted(cx, |cx| {
+            // SAFETY: We rely on the owner of the event to guarantee that the backing storage
+            // remains alive for at least as long as the event itself.
+            let storage = unsafe { &*self.event };
+
+            // SAFETY: See comments on storage type alias.
+            let storage = unsafe { &*storage.inner.get() };
+
+            let result = storage
+                .get()
+                .as_ref()
+                .expect("OnceEvent must still exist because receiver exists")
+                .poll(cx.waker());
+
+            match result {
+                Some(result) => task::Poll::Ready(result),
+                None => task::Poll::Pending,
+            }
+        })
     }
 }
 
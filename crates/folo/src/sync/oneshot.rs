@@ -0,0 +1,322 @@
+//! A channel for delivering a single value from one task to another, e.g. the response to a
+//! request. The sender may be used on any thread, the receiver is awaited on the thread that
+//! created the channel.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use folo::sync::oneshot;
+//!
+//! #[folo::main]
+//! async fn main() {
+//!     let (tx, rx) = oneshot::channel();
+//!
+//!     folo::rt::spawn_on_any(move || async move {
+//!         _ = tx.send(42);
+//!     });
+//!
+//!     assert_eq!(rx.await.unwrap(), 42);
+//! }
+//! ```
+
+use crate::{rt::coop, util::current_thread_token};
+use negative_impl::negative_impl;
+use std::{
+    cell::UnsafeCell,
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Arc,
+    },
+    task,
+};
+use thiserror::Error;
+
+/// Creates a channel for delivering a single value. The receiver must be awaited on the current
+/// thread, whereas the sender may be sent to any thread.
+///
+/// The common case of both ends staying on the same worker thread is the fast path: sending the
+/// value then uses no atomic read-modify-write operations and wakes the receiver via the local
+/// waker of the same thread.
+pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    let shared = Arc::new(Shared {
+        receiver_thread: current_thread_token(),
+        state: AtomicU8::new(0),
+        value: UnsafeCell::new(None),
+        waker: UnsafeCell::new(None),
+    });
+
+    (
+        Sender {
+            shared: Some(Arc::clone(&shared)),
+        },
+        Receiver { shared },
+    )
+}
+
+/// The error returned by a [`Receiver`] if the [`Sender`] was dropped without sending a value.
+#[derive(Clone, Copy, Debug, Eq, Error, PartialEq)]
+#[error("the sender was dropped without sending a value")]
+pub struct RecvError;
+
+/// The error returned by [`Receiver::try_recv()`].
+#[derive(Clone, Copy, Debug, Eq, Error, PartialEq)]
+pub enum TryRecvError {
+    #[error("no value has been sent yet")]
+    Empty,
+
+    #[error("the sender was dropped without sending a value")]
+    Closed,
+}
+
+/// Sends the value of a [`channel()`]. Can be sent to any thread.
+#[derive(Debug)]
+pub struct Sender<T> {
+    // Taken when the value is sent, so the drop does not complete the channel a second time.
+    shared: Option<Arc<Shared<T>>>,
+}
+
+impl<T> Sender<T> {
+    /// Sends the value to the receiver, waking it up if it is waiting. If the receiver has been
+    /// dropped, the value is returned back to the caller.
+    pub fn send(mut self, value: T) -> Result<(), T> {
+        let shared = self
+            .shared
+            .take()
+            .expect("shared state is only taken when the sender is consumed");
+
+        // SAFETY: Only the sender writes the value and only before it completes the channel. The
+        // receiver only reads the value after it has observed the completion.
+        unsafe {
+            *shared.value.get() = Some(value);
+        }
+
+        let previous = shared.complete();
+
+        if previous & CLOSED != 0 {
+            // SAFETY: The receiver no longer exists, so nobody else can access the value.
+            let value = unsafe { (*shared.value.get()).take() };
+
+            return Err(value.expect("we just stored the value and nobody else accesses it"));
+        }
+
+        Ok(())
+    }
+
+    /// Whether the receiver has been dropped, in which case sending the value is pointless.
+    pub fn is_closed(&self) -> bool {
+        self.shared
+            .as_ref()
+            .expect("shared state is only taken when the sender is consumed")
+            .state
+            .load(Ordering::Acquire)
+            & CLOSED
+            != 0
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        // If no value was sent, the receiver needs to learn that none ever will be.
+        if let Some(shared) = self.shared.take() {
+            shared.complete();
+        }
+    }
+}
+
+/// Receives the value of a [`channel()`] by being awaited. Must stay on the thread that created
+/// the channel.
+///
+/// Completes with [`RecvError`] if the sender is dropped without sending a value.
+#[derive(Debug)]
+pub struct Receiver<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Receiver<T> {
+    /// Takes the value if it has already been sent, without waiting.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        if self.shared.state.load(Ordering::Acquire) & COMPLETE == 0 {
+            return Err(TryRecvError::Empty);
+        }
+
+        self.take_value().map_err(|_| TryRecvError::Closed)
+    }
+
+    fn take_value(&mut self) -> Result<T, RecvError> {
+        // SAFETY: We only get here after observing the completion of the channel, after which the
+        // sender no longer accesses the value.
+        unsafe { (*self.shared.value.get()).take() }.ok_or(RecvError)
+    }
+}
+
+impl<T> Future for Receiver<T> {
+    type Output = Result<T, RecvError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> task::Poll<Self::Output> {
+        coop::poll_budgeted(cx, |cx| {
+            let this = self.get_mut();
+            let state = this.shared.state.load(Ordering::Acquire);
+
+            if state & COMPLETE != 0 {
+                return task::Poll::Ready(this.take_value());
+            }
+
+            if state & WAKER_SET != 0 {
+                // SAFETY: While the waker is marked as set, the sender may only read it, as we do
+                // here.
+                let waker = unsafe { (*this.shared.waker.get()).as_ref() };
+
+                if waker.is_some_and(|w| w.will_wake(cx.waker())) {
+                    return task::Poll::Pending;
+                }
+
+                // We need to replace the waker, which we may only do while it is not marked as set.
+                let state = this.shared.state.fetch_and(!WAKER_SET, Ordering::AcqRel);
+
+                if state & COMPLETE != 0 {
+                    // The sender may still be using the old waker but we no longer need a new one.
+                    return task::Poll::Ready(this.take_value());
+                }
+            }
+
+            // SAFETY: The waker is not marked as set, so the sender does not access it.
+            unsafe {
+                *this.shared.waker.get() = Some(cx.waker().clone());
+            }
+
+            let state = this.shared.state.fetch_or(WAKER_SET, Ordering::AcqRel);
+
+            if state & COMPLETE != 0 {
+                return task::Poll::Ready(this.take_value());
+            }
+
+            task::Poll::Pending
+        })
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        // Lets the sender know that nobody is waiting for the value anymore. Any value already
+        // sent is dropped together with the shared state.
+        self.shared.state.fetch_or(CLOSED, Ordering::AcqRel);
+    }
+}
+
+#[negative_impl]
+impl<T> !Send for Receiver<T> {}
+#[negative_impl]
+impl<T> !Sync for Receiver<T> {}
+
+// The receiver has stored a waker that the sender must wake when completing the channel.
+const WAKER_SET: u8 = 1 << 0;
+
+// The sender has either sent the value or been dropped without sending one.
+const COMPLETE: u8 = 1 << 1;
+
+// The receiver has been dropped.
+const CLOSED: u8 = 1 << 2;
+
+#[derive(Debug)]
+struct Shared<T> {
+    // Identifies the thread of the receiver, which never leaves it.
+    receiver_thread: usize,
+
+    state: AtomicU8,
+
+    // Access is coordinated via `state`, see comments at each access.
+    value: UnsafeCell<Option<T>>,
+    waker: UnsafeCell<Option<task::Waker>>,
+}
+
+impl<T> Shared<T> {
+    /// Marks the channel as complete, waking up the receiver if it is waiting. Returns the
+    /// previous state.
+    fn complete(&self) -> u8 {
+        let previous = if self.receiver_thread == current_thread_token() {
+            // The receiver is on this thread, so it cannot access the state while we do.
+            let previous = self.state.load(Ordering::Relaxed);
+            self.state.store(previous | COMPLETE, Ordering::Relaxed);
+            previous
+        } else {
+            self.state.fetch_or(COMPLETE, Ordering::AcqRel)
+        };
+
+        if previous & (WAKER_SET | CLOSED) == WAKER_SET {
+            // SAFETY: The receiver does not modify the waker while it is marked as set.
+            if let Some(waker) = unsafe { (*self.waker.get()).as_ref() } {
+                waker.wake_by_ref();
+            }
+        }
+
+        previous
+    }
+}
+
+// SAFETY: Access to the cells is coordinated via the atomic state, so the shared state can be
+// accessed from any thread as long as the value itself can be sent between threads.
+unsafe impl<T: Send> Send for Shared<T> {}
+// SAFETY: See above.
+unsafe impl<T: Send> Sync for Shared<T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{executor::block_on, task::noop_waker_ref, FutureExt};
+    use std::thread;
+
+    #[test]
+    fn send_before_receive() {
+        let (tx, mut rx) = channel();
+
+        tx.send(42).unwrap();
+
+        let cx = &mut task::Context::from_waker(noop_waker_ref());
+        assert_eq!(rx.poll_unpin(cx), task::Poll::Ready(Ok(42)));
+    }
+
+    #[test]
+    fn receive_before_send() {
+        let (tx, mut rx) = channel();
+
+        let cx = &mut task::Context::from_waker(noop_waker_ref());
+        assert_eq!(rx.poll_unpin(cx), task::Poll::Pending);
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+
+        tx.send(42).unwrap();
+
+        assert_eq!(rx.poll_unpin(cx), task::Poll::Ready(Ok(42)));
+    }
+
+    #[test]
+    fn sender_dropped() {
+        let (tx, mut rx) = channel::<i32>();
+
+        drop(tx);
+
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Closed));
+        assert_eq!(block_on(rx), Err(RecvError));
+    }
+
+    #[test]
+    fn receiver_dropped() {
+        let (tx, rx) = channel();
+
+        drop(rx);
+
+        assert!(tx.is_closed());
+        assert_eq!(tx.send(42), Err(42));
+    }
+
+    #[test]
+    fn send_from_other_thread() {
+        let (tx, rx) = channel();
+
+        let sender = thread::spawn(move || tx.send(42).unwrap());
+
+        assert_eq!(block_on(rx), Ok(42));
+        sender.join().unwrap();
+    }
+}
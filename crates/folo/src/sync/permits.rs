@@ -0,0 +1,435 @@
+use crate::{
+    constants::POISONED_LOCK,
+    rt::coop,
+    sync::{deadlock, ThreadWaker},
+};
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    sync::{Mutex, MutexGuard},
+    task::{self, ready, Waker},
+};
+
+/// A pool of permits that tasks on the current thread acquire in FIFO order. The building block
+/// of the thread-local async locks, which need no atomic operations.
+#[derive(Debug)]
+pub(crate) struct LocalPermitQueue {
+    queue: RefCell<PermitQueue<Waker>>,
+}
+
+impl LocalPermitQueue {
+    pub(crate) fn new(permits: usize) -> Self {
+        Self {
+            queue: RefCell::new(PermitQueue::new(permits)),
+        }
+    }
+
+    pub(crate) fn try_acquire(&self, permits: usize) -> bool {
+        let acquired = self.queue.borrow_mut().try_acquire(permits);
+
+        if acquired {
+            deadlock::acquired(self.lock_id());
+        }
+
+        acquired
+    }
+
+    /// Polls for permits. See [`PermitQueue::poll_acquire()`].
+    pub(crate) fn poll_acquire(
+        &self,
+        permits: usize,
+        priority: bool,
+        waiter: &mut Option<u64>,
+        cx: &mut task::Context<'_>,
+    ) -> task::Poll<()> {
+        coop::poll_budgeted(cx, |cx| {
+            let was_waiting = waiter.is_some();
+
+            let poll = self
+                .queue
+                .borrow_mut()
+                .poll_acquire(permits, priority, waiter, cx.waker());
+
+            let task::Poll::Ready(waker) = poll else {
+                if !was_waiting {
+                    deadlock::waiting(self.lock_id());
+                }
+
+                return task::Poll::Pending;
+            };
+
+            if was_waiting {
+                deadlock::stopped_waiting();
+            }
+
+            deadlock::acquired(self.lock_id());
+
+            // We wake outside the borrow, in case waking up the next waiter touches the queue.
+            wake(waker);
+            task::Poll::Ready(())
+        })
+    }
+
+    pub(crate) fn release(&self, permits: usize) {
+        deadlock::released(self.lock_id());
+
+        let waker = self.queue.borrow_mut().release(permits);
+        wake(waker);
+    }
+
+    /// Gives up the place of a waiter in the queue, if it has one.
+    pub(crate) fn cancel(&self, waiter: &mut Option<u64>) {
+        if let Some(id) = waiter.take() {
+            deadlock::stopped_waiting();
+
+            let waker = self.queue.borrow_mut().cancel(id);
+            wake(waker);
+        }
+    }
+
+    fn lock_id(&self) -> deadlock::LockId {
+        self as *const Self as deadlock::LockId
+    }
+}
+
+/// A pool of permits that tasks on any thread acquire in FIFO order. The building block of the
+/// async locks that may be used across threads.
+///
+/// Tasks waiting for permits on async worker threads are woken up via the I/O driver of their
+/// thread when permits are released on another thread, so they do not wait for an I/O wait to
+/// time out.
+#[derive(Debug)]
+pub(crate) struct SharedPermitQueue {
+    queue: Mutex<PermitQueue<ThreadWaker>>,
+}
+
+impl SharedPermitQueue {
+    pub(crate) fn new(permits: usize) -> Self {
+        Self {
+            queue: Mutex::new(PermitQueue::new(permits)),
+        }
+    }
+
+    pub(crate) fn available(&self) -> usize {
+        self.lock().available
+    }
+
+    pub(crate) fn try_acquire(&self, permits: usize) -> bool {
+        self.lock().try_acquire(permits)
+    }
+
+    /// Polls for permits. See [`PermitQueue::poll_acquire()`].
+    pub(crate) fn poll_acquire(
+        &self,
+        permits: usize,
+        priority: bool,
+        waiter: &mut Option<u64>,
+        cx: &mut task::Context<'_>,
+    ) -> task::Poll<()> {
+        coop::poll_budgeted(cx, |cx| {
+            let poll = self
+                .lock()
+                .poll_acquire(permits, priority, waiter, cx.waker());
+
+            // We wake outside the lock, so the next waiter does not have to wait for us to release
+            // it.
+            wake(ready!(poll));
+            task::Poll::Ready(())
+        })
+    }
+
+    pub(crate) fn release(&self, permits: usize) {
+        let waker = self.lock().release(permits);
+        wake(waker);
+    }
+
+    /// Gives up the place of a waiter in the queue, if it has one.
+    pub(crate) fn cancel(&self, waiter: &mut Option<u64>) {
+        if let Some(id) = waiter.take() {
+            let waker = self.lock().cancel(id);
+            wake(waker);
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, PermitQueue<ThreadWaker>> {
+        self.queue.lock().expect(POISONED_LOCK)
+    }
+}
+
+fn wake(waker: Option<impl QueueWaker>) {
+    if let Some(waker) = waker {
+        waker.wake();
+    }
+}
+
+/// The bookkeeping of a pool of permits that tasks acquire in FIFO order.
+///
+/// Permits are never handed out while someone is waiting ahead of the caller, so a task that needs
+/// many permits is not starved by tasks that need few. Methods that may make a waiter eligible to
+/// acquire its permits return its waker for the caller to wake after releasing the borrow or lock.
+#[derive(Debug)]
+struct PermitQueue<W> {
+    available: usize,
+    waiters: VecDeque<Waiter<W>>,
+    next_waiter_id: u64,
+}
+
+#[derive(Debug)]
+struct Waiter<W> {
+    id: u64,
+    permits: usize,
+
+    // Priority waiters are queued ahead of all other waiters.
+    priority: bool,
+
+    // Taken when the waiter is woken up to acquire its permits.
+    waker: Option<W>,
+}
+
+impl<W: QueueWaker> PermitQueue<W> {
+    fn new(permits: usize) -> Self {
+        Self {
+            available: permits,
+            waiters: VecDeque::new(),
+            next_waiter_id: 0,
+        }
+    }
+
+    /// Acquires permits if they are available and nobody is waiting for permits.
+    fn try_acquire(&mut self, permits: usize) -> bool {
+        if self.waiters.is_empty() && self.available >= permits {
+            self.available -= permits;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Polls for permits. `waiter` identifies the position of the caller in the queue, which is
+    /// assigned when the caller first has to wait and cleared when the permits are acquired.
+    ///
+    /// A priority caller is queued ahead of all waiters without priority, behind earlier priority
+    /// waiters. On success, returns the waker of the next waiter if it can now acquire its permits.
+    fn poll_acquire(
+        &mut self,
+        permits: usize,
+        priority: bool,
+        waiter: &mut Option<u64>,
+        waker: &Waker,
+    ) -> task::Poll<Option<W>> {
+        let Some(id) = *waiter else {
+            if self.try_acquire(permits) {
+                return task::Poll::Ready(None);
+            }
+
+            let index = if priority {
+                self.waiters.iter().take_while(|w| w.priority).count()
+            } else {
+                self.waiters.len()
+            };
+
+            if index == 0 && self.available >= permits {
+                // We jumped ahead of all the waiters, so the permits are ours. If the previous
+                // front had been woken up to take them, it finds itself behind us and goes back
+                // to waiting.
+                self.available -= permits;
+                return task::Poll::Ready(None);
+            }
+
+            let id = self.next_waiter_id;
+            self.next_waiter_id += 1;
+
+            self.waiters.insert(
+                index,
+                Waiter {
+                    id,
+                    permits,
+                    priority,
+                    waker: Some(W::new(waker)),
+                },
+            );
+
+            *waiter = Some(id);
+            return task::Poll::Pending;
+        };
+
+        let front = self
+            .waiters
+            .front()
+            .expect("a waiter is in the queue until it acquires its permits or gives up");
+
+        if front.id == id && self.available >= permits {
+            self.waiters.pop_front();
+            self.available -= permits;
+            *waiter = None;
+
+            // There may be enough permits left for the next waiter, too.
+            return task::Poll::Ready(self.wake_front());
+        }
+
+        let entry = self
+            .waiters
+            .iter_mut()
+            .find(|w| w.id == id)
+            .expect("a waiter is in the queue until it acquires its permits or gives up");
+
+        if !entry.waker.as_ref().is_some_and(|w| w.will_wake(waker)) {
+            entry.waker = Some(W::new(waker));
+        }
+
+        task::Poll::Pending
+    }
+
+    /// Returns permits to the pool. Returns the waker of the next waiter if it can now acquire its
+    /// permits.
+    fn release(&mut self, permits: usize) -> Option<W> {
+        self.available += permits;
+        self.wake_front()
+    }
+
+    /// Removes a waiter that gave up waiting from the queue. Returns the waker of the next waiter
+    /// if it can now acquire its permits.
+    fn cancel(&mut self, id: u64) -> Option<W> {
+        let index = self
+            .waiters
+            .iter()
+            .position(|w| w.id == id)
+            .expect("a waiter is in the queue until it acquires its permits or gives up");
+
+        self.waiters.remove(index);
+
+        // If the waiter was at the front, it may have been woken up already, in which case the
+        // wake-up passes to the new front.
+        if index == 0 {
+            self.wake_front()
+        } else {
+            None
+        }
+    }
+
+    fn wake_front(&mut self) -> Option<W> {
+        let front = self.waiters.front_mut()?;
+
+        if self.available >= front.permits {
+            front.waker.take()
+        } else {
+            None
+        }
+    }
+}
+
+/// The waker that a [`PermitQueue`] stores for each waiter.
+pub(crate) trait QueueWaker {
+    fn new(waker: &Waker) -> Self;
+    fn will_wake(&self, waker: &Waker) -> bool;
+    fn wake(self);
+}
+
+impl QueueWaker for Waker {
+    fn new(waker: &Waker) -> Self {
+        waker.clone()
+    }
+
+    fn will_wake(&self, waker: &Waker) -> bool {
+        Waker::will_wake(self, waker)
+    }
+
+    fn wake(self) {
+        Waker::wake(self);
+    }
+}
+
+impl QueueWaker for ThreadWaker {
+    fn new(waker: &Waker) -> Self {
+        ThreadWaker::new(waker)
+    }
+
+    fn will_wake(&self, waker: &Waker) -> bool {
+        ThreadWaker::will_wake(self, waker)
+    }
+
+    fn wake(self) {
+        ThreadWaker::wake(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::task::noop_waker_ref;
+
+    #[test]
+    fn waiters_acquire_in_order() {
+        let mut queue = PermitQueue::<Waker>::new(2);
+        let waker = noop_waker_ref();
+
+        let mut first = None;
+        let mut second = None;
+        let mut third = None;
+
+        assert!(queue.poll_acquire(2, false, &mut first, waker).is_ready());
+        assert!(queue
+            .poll_acquire(2, false, &mut second, waker)
+            .is_pending());
+
+        // Even though a permit is available, it is reserved for the waiter ahead of us.
+        assert!(queue.release(1).is_none());
+        assert!(queue.poll_acquire(1, false, &mut third, waker).is_pending());
+        assert!(!queue.try_acquire(1));
+
+        assert!(queue.release(1).is_some());
+        assert!(queue.poll_acquire(1, false, &mut third, waker).is_pending());
+        assert!(queue.poll_acquire(2, false, &mut second, waker).is_ready());
+
+        assert!(queue.release(2).is_some());
+        assert!(queue.poll_acquire(1, false, &mut third, waker).is_ready());
+        assert_eq!(queue.available, 1);
+    }
+
+    #[test]
+    fn priority_waiters_go_first() {
+        let mut queue = PermitQueue::<Waker>::new(2);
+        let waker = noop_waker_ref();
+
+        let mut regular = None;
+        let mut priority = None;
+
+        assert!(queue.try_acquire(2));
+        assert!(queue
+            .poll_acquire(1, false, &mut regular, waker)
+            .is_pending());
+        assert!(queue
+            .poll_acquire(2, true, &mut priority, waker)
+            .is_pending());
+
+        assert!(queue.release(1).is_none());
+        assert!(queue
+            .poll_acquire(1, false, &mut regular, waker)
+            .is_pending());
+
+        assert!(queue.release(1).is_some());
+        assert!(queue.poll_acquire(2, true, &mut priority, waker).is_ready());
+    }
+
+    #[test]
+    fn cancel_passes_wake_up_to_next_waiter() {
+        let mut queue = PermitQueue::<Waker>::new(1);
+        let waker = noop_waker_ref();
+
+        let mut first = None;
+        let mut second = None;
+
+        assert!(queue.try_acquire(1));
+        assert!(queue.poll_acquire(1, false, &mut first, waker).is_pending());
+        assert!(queue
+            .poll_acquire(1, false, &mut second, waker)
+            .is_pending());
+
+        // The first waiter is woken up but gives up before acquiring the permit.
+        assert!(queue.release(1).is_some());
+        assert!(queue.cancel(first.unwrap()).is_some());
+
+        assert!(queue.poll_acquire(1, false, &mut second, waker).is_ready());
+        assert_eq!(queue.available, 0);
+    }
+}
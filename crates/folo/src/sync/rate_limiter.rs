@@ -0,0 +1,252 @@
+use crate::{
+    constants::POISONED_LOCK,
+    sync::Semaphore,
+    time::{Clock, Delay},
+};
+use std::{
+    sync::{Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+/// Limits the rate at which tasks perform an operation (e.g. outbound calls to a throttled
+/// service or the requests of one tenant), using a token bucket.
+///
+/// The bucket holds up to `tokens_per_period` tokens and starts out full, allowing bursts of that
+/// size. It refills continuously at a rate of `tokens_per_period` tokens per `period`. A task that
+/// finds too few tokens in the bucket waits on a timer until enough have been refilled.
+///
+/// Tasks take tokens in the order they started waiting, so a task that needs many tokens is not
+/// starved by tasks that need few. The limiter may be shared between the tasks of one worker
+/// thread or, via an `Arc`, between tasks on any number of worker threads.
+///
+/// # Example
+///
+/// ```no_run
+/// use folo::sync::RateLimiter;
+/// use std::{rc::Rc, time::Duration};
+///
+/// #[folo::main]
+/// async fn main() {
+///     // At most 100 calls per second to the upstream service.
+///     let upstream_limit = Rc::new(RateLimiter::new(100, Duration::from_secs(1)));
+///
+///     for _ in 0..1000 {
+///         upstream_limit.acquire(1).await;
+///
+///         folo::rt::spawn(async move {
+///             // ... call the upstream service ...
+///         });
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct RateLimiter {
+    // One permit, held by the task whose turn it is to take tokens, in FIFO order.
+    turn: Semaphore,
+
+    bucket: Mutex<Bucket>,
+    capacity: u64,
+    period: Duration,
+
+    clock: Clock,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: u64,
+
+    // The tokens refilled since this instant are not yet added to `tokens`.
+    refilled_until: Instant,
+}
+
+impl RateLimiter {
+    /// Creates a limiter that allows `tokens_per_period` tokens to be taken per `period`, with
+    /// bursts of up to `tokens_per_period` tokens.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tokens_per_period` or `period` is zero.
+    pub fn new(tokens_per_period: u64, period: Duration) -> Self {
+        Self::with_clock(&Clock::new(), tokens_per_period, period)
+    }
+
+    /// Creates a limiter like [`new()`][Self::new], measuring time with the specified clock.
+    pub fn with_clock(clock: &Clock, tokens_per_period: u64, period: Duration) -> Self {
+        assert!(
+            tokens_per_period > 0,
+            "rate limiter must allow at least 1 token"
+        );
+        assert!(!period.is_zero(), "rate limiter period must not be zero");
+
+        Self {
+            turn: Semaphore::new(1),
+            bucket: Mutex::new(Bucket {
+                tokens: tokens_per_period,
+                refilled_until: clock.instant_now(),
+            }),
+            capacity: tokens_per_period,
+            period,
+            clock: clock.clone(),
+        }
+    }
+
+    /// Takes the specified number of tokens, waiting for the bucket to refill if it holds too few.
+    ///
+    /// If the returned future is dropped before it completes, no tokens are taken and the task
+    /// gives up its place in the queue without affecting the other waiting tasks.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tokens` is greater than the capacity of the bucket, as the tokens could never be
+    /// taken.
+    pub async fn acquire(&self, tokens: u64) {
+        assert!(
+            tokens <= self.capacity,
+            "cannot acquire {tokens} tokens from a rate limiter with a capacity of {}",
+            self.capacity
+        );
+
+        let _turn = self.turn.acquire().await;
+
+        loop {
+            let Err(wait) = self.take(tokens) else {
+                return;
+            };
+
+            Delay::with_clock(&self.clock, wait).await;
+        }
+    }
+
+    /// Takes the specified number of tokens if the bucket holds enough and nobody is waiting for
+    /// tokens, without waiting. Returns whether the tokens were taken.
+    pub fn try_acquire(&self, tokens: u64) -> bool {
+        let Some(_turn) = self.turn.try_acquire() else {
+            return false;
+        };
+
+        self.take(tokens).is_ok()
+    }
+
+    /// The number of tokens in the bucket right now.
+    pub fn available(&self) -> u64 {
+        let mut bucket = self.lock_bucket();
+        bucket.refill(self.capacity, self.period, self.clock.instant_now());
+
+        bucket.tokens
+    }
+
+    fn take(&self, tokens: u64) -> Result<(), Duration> {
+        self.lock_bucket()
+            .take(tokens, self.capacity, self.period, self.clock.instant_now())
+    }
+
+    fn lock_bucket(&self) -> MutexGuard<'_, Bucket> {
+        self.bucket.lock().expect(POISONED_LOCK)
+    }
+}
+
+impl Bucket {
+    /// Takes tokens from the bucket if it holds enough. Otherwise, returns how long it takes for
+    /// the bucket to refill enough to hold them.
+    fn take(
+        &mut self,
+        tokens: u64,
+        capacity: u64,
+        period: Duration,
+        now: Instant,
+    ) -> Result<(), Duration> {
+        self.refill(capacity, period, now);
+
+        if self.tokens >= tokens {
+            self.tokens -= tokens;
+            return Ok(());
+        }
+
+        let missing = u128::from(tokens - self.tokens);
+        let needed_nanos = (missing * period.as_nanos()).div_ceil(u128::from(capacity));
+
+        // Part of the next token has been refilled already.
+        let partial_nanos = now
+            .saturating_duration_since(self.refilled_until)
+            .as_nanos();
+
+        Err(nanos_to_duration(
+            needed_nanos.saturating_sub(partial_nanos),
+        ))
+    }
+
+    fn refill(&mut self, capacity: u64, period: Duration, now: Instant) {
+        let elapsed_nanos = now
+            .saturating_duration_since(self.refilled_until)
+            .as_nanos();
+        let refilled = elapsed_nanos * u128::from(capacity) / period.as_nanos();
+
+        if refilled == 0 {
+            return;
+        }
+
+        let space = u128::from(capacity - self.tokens);
+
+        if refilled >= space {
+            // The bucket is full, so the time spent refilling beyond that is lost.
+            self.tokens = capacity;
+            self.refilled_until = now;
+        } else {
+            self.tokens += u64::try_from(refilled).expect("refilled less than the capacity");
+
+            // We carry over the time spent refilling the next token.
+            self.refilled_until +=
+                nanos_to_duration(refilled * period.as_nanos() / u128::from(capacity));
+        }
+    }
+}
+
+fn nanos_to_duration(nanos: u128) -> Duration {
+    Duration::from_nanos(u64::try_from(nanos).unwrap_or(u64::MAX))
+}
+
+#[cfg(all(test, feature = "fakes"))]
+mod tests {
+    use super::*;
+    use crate::test_rt::TestRuntime;
+
+    #[test]
+    fn burst_then_refill_rate() {
+        let mut runtime = TestRuntime::new();
+        let clock = runtime.clock();
+
+        runtime.block_on(async move {
+            let limiter = RateLimiter::with_clock(&clock, 10, Duration::from_secs(1));
+            let start = clock.instant_now();
+
+            limiter.acquire(10).await;
+            assert_eq!(clock.instant_now(), start);
+            assert!(!limiter.try_acquire(1));
+
+            limiter.acquire(1).await;
+            assert_eq!(clock.instant_now() - start, Duration::from_millis(100));
+
+            limiter.acquire(5).await;
+            assert_eq!(clock.instant_now() - start, Duration::from_millis(600));
+            assert_eq!(limiter.available(), 0);
+        });
+    }
+
+    #[test]
+    fn refill_stops_at_capacity() {
+        let mut runtime = TestRuntime::new();
+        let clock = runtime.clock();
+        let mut clock_control = runtime.clock_control();
+
+        runtime.block_on(async move {
+            let limiter = RateLimiter::with_clock(&clock, 10, Duration::from_secs(1));
+
+            limiter.acquire(10).await;
+            clock_control.advance(Duration::from_secs(5));
+
+            assert_eq!(limiter.available(), 10);
+            assert!(limiter.try_acquire(10));
+            assert!(!limiter.try_acquire(1));
+        });
+    }
+}
@@ -0,0 +1,561 @@
+use crate::sync::{LocalPermitQueue, SharedPermitQueue};
+use negative_impl::negative_impl;
+use std::{
+    cell::UnsafeCell,
+    future::Future,
+    ops::{Deref, DerefMut},
+    pin::Pin,
+    task::{self, ready},
+};
+
+// Each reader holds one permit, the writer holds all of them.
+const MAX_READERS: usize = u32::MAX as usize;
+
+/// An async reader-writer lock for protecting a thread-local resource that is read far more often
+/// than it is modified (e.g. a cache or a routing table) and is used across await points. Any
+/// number of tasks may read at the same time, whereas writing requires exclusive access.
+///
+/// By default, tasks acquire the lock in the order they started waiting for it, so a waiting
+/// writer holds off readers that arrive after it and is not starved by a steady stream of readers.
+/// With [`with_writer_priority()`][Self::with_writer_priority], waiting writers additionally go
+/// ahead of readers that are already waiting, which keeps updates timely under heavy read load at
+/// the expense of read latency.
+///
+/// The lock uses no atomic operations, so it is cheap to use if all the tasks that use the
+/// resource are on the same thread. Use [`RwLock`] to share a resource between threads.
+///
+/// # Example
+///
+/// ```no_run
+/// use folo::sync::LocalRwLock;
+/// use std::collections::HashMap;
+///
+/// #[folo::main]
+/// async fn main() {
+///     let routes = LocalRwLock::new(HashMap::new());
+///
+///     routes.write().await.insert("/", "index");
+///
+///     assert_eq!(routes.read().await.get("/"), Some(&"index"));
+/// }
+/// ```
+#[derive(Debug)]
+pub struct LocalRwLock<T: ?Sized> {
+    permits: LocalPermitQueue,
+    writer_priority: bool,
+    value: UnsafeCell<T>,
+}
+
+impl<T> LocalRwLock<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            permits: LocalPermitQueue::new(MAX_READERS),
+            writer_priority: false,
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    /// Creates a lock whose waiting writers go ahead of waiting readers.
+    pub fn with_writer_priority(value: T) -> Self {
+        Self {
+            writer_priority: true,
+            ..Self::new(value)
+        }
+    }
+
+    /// Consumes the lock, returning the protected value.
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+}
+
+impl<T: ?Sized> LocalRwLock<T> {
+    /// Acquires shared read access, waiting for any writer to release the lock.
+    ///
+    /// If the returned future is dropped before it completes, the task gives up its place in the
+    /// queue without affecting the other waiting tasks.
+    pub fn read(&self) -> LocalReadLock<'_, T> {
+        LocalReadLock {
+            lock: self,
+            waiter: None,
+        }
+    }
+
+    /// Acquires exclusive write access, waiting for all readers and any writer to release the lock.
+    ///
+    /// If the returned future is dropped before it completes, the task gives up its place in the
+    /// queue without affecting the other waiting tasks.
+    pub fn write(&self) -> LocalWriteLock<'_, T> {
+        LocalWriteLock {
+            lock: self,
+            waiter: None,
+        }
+    }
+
+    /// Acquires shared read access if no writer holds the lock and nobody waits for it, without
+    /// waiting.
+    pub fn try_read(&self) -> Option<LocalRwLockReadGuard<'_, T>> {
+        if self.permits.try_acquire(1) {
+            Some(LocalRwLockReadGuard { lock: self })
+        } else {
+            None
+        }
+    }
+
+    /// Acquires exclusive write access if nobody holds the lock or waits for it, without waiting.
+    pub fn try_write(&self) -> Option<LocalRwLockWriteGuard<'_, T>> {
+        if self.permits.try_acquire(MAX_READERS) {
+            Some(LocalRwLockWriteGuard { lock: self })
+        } else {
+            None
+        }
+    }
+
+    /// A mutable reference to the protected value. No locking is needed because the mutable
+    /// reference to the lock guarantees that nobody else is using it.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.value.get_mut()
+    }
+}
+
+impl<T: Default> Default for LocalRwLock<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+#[negative_impl]
+impl<T: ?Sized> !Send for LocalRwLock<T> {}
+#[negative_impl]
+impl<T: ?Sized> !Sync for LocalRwLock<T> {}
+
+/// Acquires read access to a [`LocalRwLock`]. Created by [`LocalRwLock::read()`].
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct LocalReadLock<'l, T: ?Sized> {
+    lock: &'l LocalRwLock<T>,
+
+    // Our place in the queue while we wait for the lock.
+    waiter: Option<u64>,
+}
+
+impl<'l, T: ?Sized> Future for LocalReadLock<'l, T> {
+    type Output = LocalRwLockReadGuard<'l, T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> task::Poll<Self::Output> {
+        let this = self.get_mut();
+
+        ready!(this
+            .lock
+            .permits
+            .poll_acquire(1, false, &mut this.waiter, cx));
+
+        task::Poll::Ready(LocalRwLockReadGuard { lock: this.lock })
+    }
+}
+
+impl<T: ?Sized> Drop for LocalReadLock<'_, T> {
+    fn drop(&mut self) {
+        self.lock.permits.cancel(&mut self.waiter);
+    }
+}
+
+/// Acquires write access to a [`LocalRwLock`]. Created by [`LocalRwLock::write()`].
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct LocalWriteLock<'l, T: ?Sized> {
+    lock: &'l LocalRwLock<T>,
+
+    // Our place in the queue while we wait for the lock.
+    waiter: Option<u64>,
+}
+
+impl<'l, T: ?Sized> Future for LocalWriteLock<'l, T> {
+    type Output = LocalRwLockWriteGuard<'l, T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> task::Poll<Self::Output> {
+        let this = self.get_mut();
+
+        ready!(this.lock.permits.poll_acquire(
+            MAX_READERS,
+            this.lock.writer_priority,
+            &mut this.waiter,
+            cx
+        ));
+
+        task::Poll::Ready(LocalRwLockWriteGuard { lock: this.lock })
+    }
+}
+
+impl<T: ?Sized> Drop for LocalWriteLock<'_, T> {
+    fn drop(&mut self) {
+        self.lock.permits.cancel(&mut self.waiter);
+    }
+}
+
+/// Grants read access to the value protected by a [`LocalRwLock`]. The access is released when
+/// the guard is dropped.
+#[derive(Debug)]
+#[must_use = "the lock is released as soon as the guard is dropped"]
+pub struct LocalRwLockReadGuard<'l, T: ?Sized> {
+    lock: &'l LocalRwLock<T>,
+}
+
+impl<T: ?Sized> Deref for LocalRwLockReadGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: The guard holds read access, so nobody modifies the value.
+        unsafe { &*self.lock.value.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for LocalRwLockReadGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.permits.release(1);
+    }
+}
+
+/// Grants write access to the value protected by a [`LocalRwLock`]. The access is released when
+/// the guard is dropped.
+#[derive(Debug)]
+#[must_use = "the lock is released as soon as the guard is dropped"]
+pub struct LocalRwLockWriteGuard<'l, T: ?Sized> {
+    lock: &'l LocalRwLock<T>,
+}
+
+impl<T: ?Sized> Deref for LocalRwLockWriteGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: The guard holds write access, so nobody else accesses the value.
+        unsafe { &*self.lock.value.get() }
+    }
+}
+
+impl<T: ?Sized> DerefMut for LocalRwLockWriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: The guard holds write access, so nobody else accesses the value.
+        unsafe { &mut *self.lock.value.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for LocalRwLockWriteGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.permits.release(MAX_READERS);
+    }
+}
+
+/// An async reader-writer lock for protecting a resource that is shared between threads, read far
+/// more often than it is modified and used across await points. Any number of tasks may read at
+/// the same time, whereas writing requires exclusive access.
+///
+/// Waiting tasks acquire the lock in the same order as with [`LocalRwLock`], including the option
+/// of [writer priority][Self::with_writer_priority]. Tasks waiting for the lock on async worker
+/// threads are woken up via the I/O driver of their thread when the lock is released on another
+/// thread, so they do not wait for an I/O wait to time out.
+///
+/// If all the tasks that use the resource are on the same thread, [`LocalRwLock`] is cheaper.
+#[derive(Debug)]
+pub struct RwLock<T: ?Sized> {
+    permits: SharedPermitQueue,
+    writer_priority: bool,
+    value: UnsafeCell<T>,
+}
+
+impl<T> RwLock<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            permits: SharedPermitQueue::new(MAX_READERS),
+            writer_priority: false,
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    /// Creates a lock whose waiting writers go ahead of waiting readers.
+    pub fn with_writer_priority(value: T) -> Self {
+        Self {
+            writer_priority: true,
+            ..Self::new(value)
+        }
+    }
+
+    /// Consumes the lock, returning the protected value.
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+}
+
+impl<T: ?Sized> RwLock<T> {
+    /// Acquires shared read access, waiting for any writer to release the lock.
+    ///
+    /// If the returned future is dropped before it completes, the task gives up its place in the
+    /// queue without affecting the other waiting tasks.
+    pub fn read(&self) -> ReadLock<'_, T> {
+        ReadLock {
+            lock: self,
+            waiter: None,
+        }
+    }
+
+    /// Acquires exclusive write access, waiting for all readers and any writer to release the lock.
+    ///
+    /// If the returned future is dropped before it completes, the task gives up its place in the
+    /// queue without affecting the other waiting tasks.
+    pub fn write(&self) -> WriteLock<'_, T> {
+        WriteLock {
+            lock: self,
+            waiter: None,
+        }
+    }
+
+    /// Acquires shared read access if no writer holds the lock and nobody waits for it, without
+    /// waiting.
+    pub fn try_read(&self) -> Option<RwLockReadGuard<'_, T>> {
+        if self.permits.try_acquire(1) {
+            Some(RwLockReadGuard { lock: self })
+        } else {
+            None
+        }
+    }
+
+    /// Acquires exclusive write access if nobody holds the lock or waits for it, without waiting.
+    pub fn try_write(&self) -> Option<RwLockWriteGuard<'_, T>> {
+        if self.permits.try_acquire(MAX_READERS) {
+            Some(RwLockWriteGuard { lock: self })
+        } else {
+            None
+        }
+    }
+
+    /// A mutable reference to the protected value. No locking is needed because the mutable
+    /// reference to the lock guarantees that nobody else is using it.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.value.get_mut()
+    }
+}
+
+impl<T: Default> Default for RwLock<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+// SAFETY: The value is only modified by the holder of write access, so the lock can be sent
+// between threads as long as the value can be sent between threads.
+unsafe impl<T: ?Sized + Send> Send for RwLock<T> {}
+// SAFETY: Readers on different threads access the value at the same time, so the value must also
+// be shareable between threads.
+unsafe impl<T: ?Sized + Send + Sync> Sync for RwLock<T> {}
+
+/// Acquires read access to a [`RwLock`]. Created by [`RwLock::read()`].
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct ReadLock<'l, T: ?Sized> {
+    lock: &'l RwLock<T>,
+
+    // Our place in the queue while we wait for the lock.
+    waiter: Option<u64>,
+}
+
+impl<'l, T: ?Sized> Future for ReadLock<'l, T> {
+    type Output = RwLockReadGuard<'l, T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> task::Poll<Self::Output> {
+        let this = self.get_mut();
+
+        ready!(this
+            .lock
+            .permits
+            .poll_acquire(1, false, &mut this.waiter, cx));
+
+        task::Poll::Ready(RwLockReadGuard { lock: this.lock })
+    }
+}
+
+impl<T: ?Sized> Drop for ReadLock<'_, T> {
+    fn drop(&mut self) {
+        self.lock.permits.cancel(&mut self.waiter);
+    }
+}
+
+/// Acquires write access to a [`RwLock`]. Created by [`RwLock::write()`].
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct WriteLock<'l, T: ?Sized> {
+    lock: &'l RwLock<T>,
+
+    // Our place in the queue while we wait for the lock.
+    waiter: Option<u64>,
+}
+
+impl<'l, T: ?Sized> Future for WriteLock<'l, T> {
+    type Output = RwLockWriteGuard<'l, T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> task::Poll<Self::Output> {
+        let this = self.get_mut();
+
+        ready!(this.lock.permits.poll_acquire(
+            MAX_READERS,
+            this.lock.writer_priority,
+            &mut this.waiter,
+            cx
+        ));
+
+        task::Poll::Ready(RwLockWriteGuard { lock: this.lock })
+    }
+}
+
+impl<T: ?Sized> Drop for WriteLock<'_, T> {
+    fn drop(&mut self) {
+        self.lock.permits.cancel(&mut self.waiter);
+    }
+}
+
+/// Grants read access to the value protected by a [`RwLock`]. The access is released when the
+/// guard is dropped.
+#[derive(Debug)]
+#[must_use = "the lock is released as soon as the guard is dropped"]
+pub struct RwLockReadGuard<'l, T: ?Sized> {
+    lock: &'l RwLock<T>,
+}
+
+impl<T: ?Sized> Deref for RwLockReadGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: The guard holds read access, so nobody modifies the value.
+        unsafe { &*self.lock.value.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for RwLockReadGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.permits.release(1);
+    }
+}
+
+/// Grants write access to the value protected by a [`RwLock`]. The access is released when the
+/// guard is dropped.
+#[derive(Debug)]
+#[must_use = "the lock is released as soon as the guard is dropped"]
+pub struct RwLockWriteGuard<'l, T: ?Sized> {
+    lock: &'l RwLock<T>,
+}
+
+impl<T: ?Sized> Deref for RwLockWriteGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: The guard holds write access, so nobody else accesses the value.
+        unsafe { &*self.lock.value.get() }
+    }
+}
+
+impl<T: ?Sized> DerefMut for RwLockWriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: The guard holds write access, so nobody else accesses the value.
+        unsafe { &mut *self.lock.value.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for RwLockWriteGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.permits.release(MAX_READERS);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{executor::block_on, task::noop_waker_ref, FutureExt};
+    use std::{pin::pin, sync::Arc, thread};
+
+    #[test]
+    fn local_readers_share_access() {
+        let lock = LocalRwLock::new(1);
+
+        let first = block_on(lock.read());
+        let second = block_on(lock.read());
+        assert_eq!(*first + *second, 2);
+        assert!(lock.try_write().is_none());
+
+        drop(first);
+        drop(second);
+
+        *block_on(lock.write()) += 1;
+        assert_eq!(*lock.try_read().unwrap(), 2);
+    }
+
+    #[test]
+    fn local_waiting_writer_holds_off_new_readers() {
+        let lock = LocalRwLock::new(());
+        let cx = &mut task::Context::from_waker(noop_waker_ref());
+
+        let reader = lock.try_read().unwrap();
+
+        let mut write = pin!(lock.write());
+        assert!(write.poll_unpin(cx).is_pending());
+        assert!(lock.try_read().is_none());
+
+        drop(reader);
+        assert!(write.poll_unpin(cx).is_ready());
+    }
+
+    #[test]
+    fn local_writer_priority() {
+        let lock = LocalRwLock::with_writer_priority(0);
+        let cx = &mut task::Context::from_waker(noop_waker_ref());
+
+        let writer = lock.try_write().unwrap();
+
+        let mut read = pin!(lock.read());
+        let mut write = pin!(lock.write());
+        assert!(read.poll_unpin(cx).is_pending());
+        assert!(write.poll_unpin(cx).is_pending());
+
+        drop(writer);
+
+        // The writer went ahead of the reader that was already waiting.
+        assert!(read.poll_unpin(cx).is_pending());
+
+        let task::Poll::Ready(mut writer) = write.poll_unpin(cx) else {
+            panic!("the writer has priority, so it must acquire the lock first");
+        };
+
+        *writer = 1;
+        drop(writer);
+
+        let task::Poll::Ready(reader) = read.poll_unpin(cx) else {
+            panic!("lock was released, so it must be acquired");
+        };
+
+        assert_eq!(*reader, 1);
+    }
+
+    #[test]
+    fn shared_lock_across_threads() {
+        let lock = Arc::new(RwLock::new(0));
+
+        let threads = (0..4)
+            .map(|_| {
+                let lock = Arc::clone(&lock);
+
+                thread::spawn(move || {
+                    for _ in 0..100 {
+                        let value = *block_on(lock.read());
+                        assert!(value <= 400);
+
+                        *block_on(lock.write()) += 1;
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        assert_eq!(*lock.try_read().unwrap(), 400);
+    }
+}
@@ -1,11 +1,14 @@
+use crate::sync::SharedPermitQueue;
 use negative_impl::negative_impl;
 use std::{
     cell::{Cell, RefCell},
     collections::VecDeque,
     future::Future,
+    mem,
     pin::Pin,
     rc::Rc,
-    task::{self, Waker},
+    sync::Arc,
+    task::{self, ready, Waker},
 };
 
 /// Controls access to a thread-local resource, granting only a limited number of concurrent tasks
@@ -24,7 +27,7 @@ impl<const MAX: usize> LocalSemaphore<MAX> {
     }
 
     pub fn acquire(&self) -> impl Future<Output = LocalSemaphoreGuard<'_, MAX>> {
-        Acquire { semaphore: self }
+        LocalAcquire { semaphore: self }
     }
 
     fn release_one(&self) {
@@ -47,11 +50,11 @@ impl<const MAX: usize> !Send for LocalSemaphore<MAX> {}
 #[negative_impl]
 impl<const MAX: usize> !Sync for LocalSemaphore<MAX> {}
 
-struct Acquire<'s, const MAX: usize> {
+struct LocalAcquire<'s, const MAX: usize> {
     semaphore: &'s LocalSemaphore<MAX>,
 }
 
-impl<'s, const MAX: usize> Future for Acquire<'s, MAX> {
+impl<'s, const MAX: usize> Future for LocalAcquire<'s, MAX> {
     type Output = LocalSemaphoreGuard<'s, MAX>;
 
     fn poll(self: Pin<&mut Self>, cx: &mut task::Context) -> task::Poll<Self::Output> {
@@ -80,3 +83,363 @@ impl<'s, const MAX: usize> Drop for LocalSemaphoreGuard<'s, MAX> {
         self.semaphore.release_one();
     }
 }
+
+/// Limits the number of tasks that concurrently use a resource (e.g. the number of in-flight calls
+/// to an upstream service or of files read in parallel), on any number of threads.
+///
+/// Tasks acquire permits in the order they started waiting for them, so a task that needs many
+/// permits (see [`acquire_many()`][Self::acquire_many]) is not starved by tasks that need few.
+/// Tasks waiting for permits on async worker threads are woken up via the I/O driver of their
+/// thread when permits are released on another thread.
+///
+/// Permits are returned to the semaphore when dropped, including when the task holding them is
+/// canceled. A task that is canceled while waiting gives up its place in the queue without taking
+/// any permits, so permits are never leaked.
+///
+/// # Example
+///
+/// ```no_run
+/// use folo::sync::Semaphore;
+/// use std::sync::Arc;
+///
+/// #[folo::main]
+/// async fn main() {
+///     // At most 8 concurrent requests to the upstream service.
+///     let upstream_limit = Arc::new(Semaphore::new(8));
+///
+///     for _ in 0..100 {
+///         let permit = Arc::clone(&upstream_limit).acquire_owned().await;
+///
+///         folo::rt::spawn_on_any(move || async move {
+///             // ... call the upstream service ...
+///             drop(permit);
+///         });
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Semaphore {
+    permits: SharedPermitQueue,
+}
+
+impl Semaphore {
+    pub fn new(permits: usize) -> Self {
+        Self {
+            permits: SharedPermitQueue::new(permits),
+        }
+    }
+
+    /// The number of permits that are not currently acquired.
+    pub fn available_permits(&self) -> usize {
+        self.permits.available()
+    }
+
+    /// Adds permits to the semaphore, waking up waiting tasks that can now acquire their permits.
+    pub fn add_permits(&self, permits: usize) {
+        self.permits.release(permits);
+    }
+
+    /// Acquires a permit, waiting for one to be released if none are available.
+    pub fn acquire(&self) -> Acquire<'_> {
+        self.acquire_many(1)
+    }
+
+    /// Acquires the specified number of permits at once, waiting for them to be released if not
+    /// enough are available. Never completes if the semaphore never has that many permits.
+    pub fn acquire_many(&self, permits: usize) -> Acquire<'_> {
+        Acquire {
+            semaphore: self,
+            permits,
+            waiter: None,
+        }
+    }
+
+    /// Acquires a permit if one is available and nobody is waiting for permits, without waiting.
+    pub fn try_acquire(&self) -> Option<SemaphorePermit<'_>> {
+        self.try_acquire_many(1)
+    }
+
+    /// Acquires the specified number of permits if they are available and nobody is waiting for
+    /// permits, without waiting.
+    pub fn try_acquire_many(&self, permits: usize) -> Option<SemaphorePermit<'_>> {
+        if self.permits.try_acquire(permits) {
+            Some(SemaphorePermit {
+                semaphore: self,
+                permits,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Acquires a permit that keeps the semaphore alive, so it can be moved into a spawned task.
+    pub fn acquire_owned(self: Arc<Self>) -> AcquireOwned {
+        self.acquire_many_owned(1)
+    }
+
+    /// Acquires the specified number of permits at once, like
+    /// [`acquire_many()`][Self::acquire_many], as a permit that keeps the semaphore alive.
+    pub fn acquire_many_owned(self: Arc<Self>, permits: usize) -> AcquireOwned {
+        AcquireOwned {
+            semaphore: Some(self),
+            permits,
+            waiter: None,
+        }
+    }
+
+    /// Acquires a permit that keeps the semaphore alive if one is available and nobody is waiting
+    /// for permits, without waiting.
+    pub fn try_acquire_owned(self: Arc<Self>) -> Option<OwnedSemaphorePermit> {
+        self.try_acquire_many_owned(1)
+    }
+
+    /// Acquires the specified number of permits as a permit that keeps the semaphore alive if they
+    /// are available and nobody is waiting for permits, without waiting.
+    pub fn try_acquire_many_owned(self: Arc<Self>, permits: usize) -> Option<OwnedSemaphorePermit> {
+        if self.permits.try_acquire(permits) {
+            Some(OwnedSemaphorePermit {
+                semaphore: self,
+                permits,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+/// Acquires permits of a [`Semaphore`]. Created by [`Semaphore::acquire()`] and
+/// [`Semaphore::acquire_many()`].
+///
+/// If dropped before it completes, the task gives up its place in the queue.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Acquire<'s> {
+    semaphore: &'s Semaphore,
+    permits: usize,
+
+    // Our place in the queue while we wait for permits.
+    waiter: Option<u64>,
+}
+
+impl<'s> Future for Acquire<'s> {
+    type Output = SemaphorePermit<'s>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> task::Poll<Self::Output> {
+        let this = self.get_mut();
+
+        ready!(this
+            .semaphore
+            .permits
+            .poll_acquire(this.permits, false, &mut this.waiter, cx));
+
+        task::Poll::Ready(SemaphorePermit {
+            semaphore: this.semaphore,
+            permits: this.permits,
+        })
+    }
+}
+
+impl Drop for Acquire<'_> {
+    fn drop(&mut self) {
+        self.semaphore.permits.cancel(&mut self.waiter);
+    }
+}
+
+/// Acquires permits of a [`Semaphore`] as an [`OwnedSemaphorePermit`]. Created by
+/// [`Semaphore::acquire_owned()`] and [`Semaphore::acquire_many_owned()`].
+///
+/// If dropped before it completes, the task gives up its place in the queue.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct AcquireOwned {
+    // Taken when the permits are acquired, to be handed over to the permit.
+    semaphore: Option<Arc<Semaphore>>,
+    permits: usize,
+
+    // Our place in the queue while we wait for permits.
+    waiter: Option<u64>,
+}
+
+impl Future for AcquireOwned {
+    type Output = OwnedSemaphorePermit;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> task::Poll<Self::Output> {
+        let this = self.get_mut();
+
+        let semaphore = this
+            .semaphore
+            .as_ref()
+            .expect("future polled after completion");
+
+        ready!(semaphore
+            .permits
+            .poll_acquire(this.permits, false, &mut this.waiter, cx));
+
+        task::Poll::Ready(OwnedSemaphorePermit {
+            semaphore: this
+                .semaphore
+                .take()
+                .expect("we just used the semaphore to acquire the permits"),
+            permits: this.permits,
+        })
+    }
+}
+
+impl Drop for AcquireOwned {
+    fn drop(&mut self) {
+        if let Some(semaphore) = &self.semaphore {
+            semaphore.permits.cancel(&mut self.waiter);
+        }
+    }
+}
+
+/// Permits acquired from a [`Semaphore`], which are returned to the semaphore when dropped.
+#[derive(Debug)]
+#[must_use = "the permits are released as soon as they are dropped"]
+pub struct SemaphorePermit<'s> {
+    semaphore: &'s Semaphore,
+    permits: usize,
+}
+
+impl SemaphorePermit<'_> {
+    /// The number of permits held.
+    pub fn num_permits(&self) -> usize {
+        self.permits
+    }
+
+    /// Drops the permits without returning them to the semaphore, permanently reducing the number
+    /// of permits it has.
+    pub fn forget(mut self) {
+        self.permits = 0;
+    }
+}
+
+impl Drop for SemaphorePermit<'_> {
+    fn drop(&mut self) {
+        if self.permits != 0 {
+            self.semaphore.permits.release(self.permits);
+        }
+    }
+}
+
+/// Permits acquired from a [`Semaphore`] that keep the semaphore alive, so they can be moved into
+/// a spawned task. The permits are returned to the semaphore when dropped.
+#[derive(Debug)]
+#[must_use = "the permits are released as soon as they are dropped"]
+pub struct OwnedSemaphorePermit {
+    semaphore: Arc<Semaphore>,
+    permits: usize,
+}
+
+impl OwnedSemaphorePermit {
+    /// The number of permits held.
+    pub fn num_permits(&self) -> usize {
+        self.permits
+    }
+
+    /// The semaphore the permits were acquired from.
+    pub fn semaphore(&self) -> &Arc<Semaphore> {
+        &self.semaphore
+    }
+
+    /// Drops the permits without returning them to the semaphore, permanently reducing the number
+    /// of permits it has.
+    pub fn forget(mut self) {
+        self.permits = 0;
+    }
+
+    /// Splits off the specified number of permits into a separate permit. Returns `None` if we do
+    /// not hold that many permits.
+    pub fn split(&mut self, permits: usize) -> Option<Self> {
+        self.permits = self.permits.checked_sub(permits)?;
+
+        Some(Self {
+            semaphore: Arc::clone(&self.semaphore),
+            permits,
+        })
+    }
+
+    /// Merges the permits of another permit of the same semaphore into this one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the permits were acquired from different semaphores.
+    pub fn merge(&mut self, mut other: Self) {
+        assert!(
+            Arc::ptr_eq(&self.semaphore, &other.semaphore),
+            "permits of different semaphores cannot be merged"
+        );
+
+        self.permits += mem::take(&mut other.permits);
+    }
+}
+
+impl Drop for OwnedSemaphorePermit {
+    fn drop(&mut self) {
+        if self.permits != 0 {
+            self.semaphore.permits.release(self.permits);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{executor::block_on, task::noop_waker_ref, FutureExt};
+    use std::pin::pin;
+
+    #[test]
+    fn permits_are_returned_on_drop() {
+        let semaphore = Semaphore::new(3);
+
+        let permit = block_on(semaphore.acquire_many(2));
+        assert_eq!(permit.num_permits(), 2);
+        assert_eq!(semaphore.available_permits(), 1);
+        assert!(semaphore.try_acquire_many(2).is_none());
+
+        drop(permit);
+        assert_eq!(semaphore.available_permits(), 3);
+
+        semaphore.try_acquire().unwrap().forget();
+        assert_eq!(semaphore.available_permits(), 2);
+    }
+
+    #[test]
+    fn canceled_acquire_takes_no_permits() {
+        let semaphore = Arc::new(Semaphore::new(1));
+        let cx = &mut task::Context::from_waker(noop_waker_ref());
+
+        let permit = Arc::clone(&semaphore).try_acquire_owned().unwrap();
+
+        let mut canceled = Box::pin(Arc::clone(&semaphore).acquire_owned());
+        let mut waiting = pin!(semaphore.acquire());
+        assert!(canceled.poll_unpin(cx).is_pending());
+        assert!(waiting.poll_unpin(cx).is_pending());
+
+        drop(permit);
+        drop(canceled);
+
+        let task::Poll::Ready(permit) = waiting.poll_unpin(cx) else {
+            panic!("the canceled acquire gave up its place, so we must get the permit");
+        };
+
+        drop(permit);
+        assert_eq!(semaphore.available_permits(), 1);
+    }
+
+    #[test]
+    fn owned_permits_split_and_merge() {
+        let semaphore = Arc::new(Semaphore::new(4));
+
+        let mut permit = block_on(Arc::clone(&semaphore).acquire_many_owned(4));
+        let split = permit.split(3).unwrap();
+        assert!(permit.split(2).is_none());
+
+        drop(split);
+        assert_eq!(semaphore.available_permits(), 3);
+
+        permit.merge(Arc::clone(&semaphore).try_acquire_many_owned(3).unwrap());
+        assert_eq!(permit.num_permits(), 4);
+        assert_eq!(semaphore.available_permits(), 0);
+    }
+}
@@ -0,0 +1,507 @@
+//! A fixed-capacity channel for sending a stream of values from one task to one other task, on any
+//! threads, optimized for a dedicated pair of worker threads.
+//!
+//! This is the building block for pipelines where each stage runs on its own worker thread (e.g.
+//! "network core → parser core → storage core") and the per-value cost of a general-purpose channel
+//! is measurable. The values are stored in a ring buffer that the two ends access without locks.
+//! The positions of the two ends are on separate cache lines, so the threads do not invalidate each
+//! other's caches with every value, and each end re-reads the position of the other end only when
+//! its cached copy says the buffer is full or empty.
+//!
+//! Wake-ups are batched: an end only wakes up the other end if the other end is waiting, so a
+//! receiver that keeps up with the sender is never woken up, and a receiver that waits is woken up
+//! once even if many values are sent before it gets to run.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use folo::sync::spsc;
+//!
+//! #[folo::main]
+//! async fn main() {
+//!     let (mut tx, mut rx) = spsc::channel::<Vec<u8>>(1024);
+//!
+//!     let parser = folo::rt::spawn_on(1, move || async move {
+//!         while let Some(packet) = rx.recv().await {
+//!             println!("parsing {} bytes", packet.len());
+//!         }
+//!     });
+//!
+//!     folo::rt::spawn_on(0, move || async move {
+//!         for i in 0..100 {
+//!             tx.send(vec![0; i]).await.unwrap();
+//!         }
+//!     });
+//!
+//!     parser.await;
+//! }
+//! ```
+
+use crate::{constants::POISONED_LOCK, rt::coop, sync::ThreadWaker};
+use crossbeam::utils::CachePadded;
+use futures::Stream;
+use std::{
+    cell::UnsafeCell,
+    fmt::{self, Debug, Formatter},
+    future::poll_fn,
+    mem::MaybeUninit,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    task,
+};
+
+pub use crate::sync::mpsc::{SendError, TryRecvError, TrySendError};
+
+/// Creates a channel that buffers up to `capacity` values, rounded up to the next power of two.
+/// Sending waits for the receiver to make room once the channel is at capacity.
+///
+/// The sender and the receiver may each be used on any thread, typically each on its own worker
+/// thread. A waiting end on an async worker thread is woken up via the I/O driver of its thread,
+/// so it does not wait for an I/O wait to time out.
+///
+/// # Panics
+///
+/// Panics if `capacity` is zero.
+pub fn channel<T: Send>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    assert!(capacity > 0, "spsc channel capacity must be at least 1");
+
+    let capacity = capacity.next_power_of_two();
+
+    let shared = Arc::new(Shared {
+        buffer: (0..capacity)
+            .map(|_| UnsafeCell::new(MaybeUninit::uninit()))
+            .collect(),
+        head: CachePadded::new(AtomicUsize::new(0)),
+        tail: CachePadded::new(AtomicUsize::new(0)),
+        receiver: CachePadded::new(Waiting::default()),
+        sender: CachePadded::new(Waiting::default()),
+        sender_dropped: AtomicBool::new(false),
+        receiver_dropped: AtomicBool::new(false),
+    });
+
+    (
+        Sender {
+            shared: Arc::clone(&shared),
+            tail: 0,
+            cached_head: 0,
+        },
+        Receiver {
+            shared,
+            head: 0,
+            cached_tail: 0,
+        },
+    )
+}
+
+struct Shared<T> {
+    // The length is a power of two, so positions map to slots via a mask and can wrap around.
+    buffer: Box<[UnsafeCell<MaybeUninit<T>>]>,
+
+    // The position of the next value to receive. Only written by the receiver.
+    head: CachePadded<AtomicUsize>,
+
+    // The position of the next value to send. Only written by the sender.
+    tail: CachePadded<AtomicUsize>,
+
+    receiver: CachePadded<Waiting>,
+    sender: CachePadded<Waiting>,
+
+    sender_dropped: AtomicBool,
+    receiver_dropped: AtomicBool,
+}
+
+impl<T> Shared<T> {
+    fn slot(&self, position: usize) -> &UnsafeCell<MaybeUninit<T>> {
+        &self.buffer[position & (self.buffer.len() - 1)]
+    }
+}
+
+impl<T> Debug for Shared<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        // The values are not necessarily `Debug` and may be accessed by the other end right now,
+        // so we leave them out.
+        f.debug_struct("Shared")
+            .field("capacity", &self.buffer.len())
+            .field("head", &self.head.load(Ordering::Relaxed))
+            .field("tail", &self.tail.load(Ordering::Relaxed))
+            .field(
+                "sender_dropped",
+                &self.sender_dropped.load(Ordering::Relaxed),
+            )
+            .field(
+                "receiver_dropped",
+                &self.receiver_dropped.load(Ordering::Relaxed),
+            )
+            .finish()
+    }
+}
+
+// SAFETY: Each slot is accessed by one end at a time. The sender only writes the slots between
+// `tail` and `head + capacity`, the receiver only reads the slots between `head` and `tail`, and
+// each end publishes its position with release semantics after accessing a slot, so the values
+// are handed over from one thread to the other like with any channel.
+unsafe impl<T: Send> Send for Shared<T> {}
+// SAFETY: See above.
+unsafe impl<T: Send> Sync for Shared<T> {}
+
+impl<T> Drop for Shared<T> {
+    fn drop(&mut self) {
+        let head = *self.head.get_mut();
+        let tail = *self.tail.get_mut();
+
+        let mut position = head;
+
+        while position != tail {
+            // SAFETY: The slots between `head` and `tail` hold values that were sent but not
+            // received, and both ends are gone, so nobody else accesses them.
+            unsafe {
+                (*self.slot(position).get()).assume_init_drop();
+            }
+
+            position = position.wrapping_add(1);
+        }
+    }
+}
+
+/// One end of the channel waiting for the other end to make progress.
+///
+/// The waiting end registers its waker and then sets the flag, after which it checks again for
+/// progress. The other end makes progress and then checks the flag. Both use sequentially
+/// consistent operations, so at least one of them observes the other and the wake-up is not lost.
+#[derive(Debug, Default)]
+struct Waiting {
+    flag: AtomicBool,
+    waker: Mutex<Option<ThreadWaker>>,
+}
+
+impl Waiting {
+    fn register(&self, cx: &task::Context<'_>) {
+        let mut waker = self.waker.lock().expect(POISONED_LOCK);
+
+        if !waker.as_ref().is_some_and(|w| w.will_wake(cx.waker())) {
+            *waker = Some(ThreadWaker::new(cx.waker()));
+        }
+
+        drop(waker);
+        self.flag.store(true, Ordering::SeqCst);
+    }
+
+    fn cancel(&self) {
+        self.flag.store(false, Ordering::Relaxed);
+    }
+
+    /// Wakes up the waiting end, if it is waiting. The plain load keeps the common case of nobody
+    /// waiting free of read-modify-write operations on the shared cache line.
+    fn wake(&self) {
+        if !self.flag.load(Ordering::SeqCst) || !self.flag.swap(false, Ordering::SeqCst) {
+            return;
+        }
+
+        let waker = self.waker.lock().expect(POISONED_LOCK).take();
+
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+/// Sends values to an spsc [`channel()`]. Can be used on any thread.
+///
+/// The receiver observes the end of the stream once the sender has been dropped.
+#[derive(Debug)]
+pub struct Sender<T> {
+    shared: Arc<Shared<T>>,
+
+    // Our own position, which only we write.
+    tail: usize,
+
+    // The position of the receiver when we last looked. The receiver only moves forward, so the
+    // buffer has at least as much room as this says.
+    cached_head: usize,
+}
+
+impl<T> Sender<T> {
+    /// Sends a value, waiting for the channel to have room if it is at capacity. Fails if the
+    /// receiver has been dropped, returning the value back to the caller.
+    pub async fn send(&mut self, value: T) -> Result<(), SendError<T>> {
+        let mut value = Some(value);
+
+        poll_fn(|cx| {
+            coop::poll_budgeted(cx, |cx| {
+                let pending = value
+                    .take()
+                    .expect("value is only taken when the send completes");
+
+                match self.try_send(pending) {
+                    Ok(()) => task::Poll::Ready(Ok(())),
+                    Err(TrySendError::Closed(pending)) => {
+                        task::Poll::Ready(Err(SendError(pending)))
+                    }
+                    Err(TrySendError::Full(pending)) => {
+                        self.shared.sender.register(cx);
+
+                        // The receiver may have made room before it saw that we are waiting.
+                        match self.try_send(pending) {
+                            Err(TrySendError::Full(pending)) => {
+                                value = Some(pending);
+                                task::Poll::Pending
+                            }
+                            result => {
+                                self.shared.sender.cancel();
+                                task::Poll::Ready(result.map_err(|e| SendError(e.into_inner())))
+                            }
+                        }
+                    }
+                }
+            })
+        })
+        .await
+    }
+
+    /// Sends a value if the channel has room, without waiting.
+    pub fn try_send(&mut self, value: T) -> Result<(), TrySendError<T>> {
+        if self.shared.receiver_dropped.load(Ordering::Acquire) {
+            return Err(TrySendError::Closed(value));
+        }
+
+        if self.tail.wrapping_sub(self.cached_head) == self.shared.buffer.len() {
+            self.cached_head = self.shared.head.load(Ordering::SeqCst);
+
+            if self.tail.wrapping_sub(self.cached_head) == self.shared.buffer.len() {
+                return Err(TrySendError::Full(value));
+            }
+        }
+
+        // SAFETY: The slot is not between `head` and `tail`, so the receiver does not access it
+        // until we publish the new tail below.
+        unsafe {
+            (*self.shared.slot(self.tail).get()).write(value);
+        }
+
+        self.tail = self.tail.wrapping_add(1);
+        self.shared.tail.store(self.tail, Ordering::SeqCst);
+
+        self.shared.receiver.wake();
+        Ok(())
+    }
+
+    /// The maximum number of values the channel buffers.
+    pub fn capacity(&self) -> usize {
+        self.shared.buffer.len()
+    }
+
+    /// Whether the receiver has been dropped, in which case sending values is pointless.
+    pub fn is_closed(&self) -> bool {
+        self.shared.receiver_dropped.load(Ordering::Acquire)
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        self.shared.sender_dropped.store(true, Ordering::SeqCst);
+
+        // The receiver needs to learn that no more values are coming.
+        self.shared.receiver.wake();
+    }
+}
+
+/// Receives the values sent to an spsc [`channel()`], in the order they were sent. Can be used on
+/// any thread. Can also be consumed as a [`Stream`].
+///
+/// Dropping the receiver makes further sends fail. The buffered values are dropped once the sender
+/// is dropped, too.
+#[derive(Debug)]
+pub struct Receiver<T> {
+    shared: Arc<Shared<T>>,
+
+    // Our own position, which only we write.
+    head: usize,
+
+    // The position of the sender when we last looked. The sender only moves forward, so the buffer
+    // holds at least as many values as this says.
+    cached_tail: usize,
+}
+
+impl<T> Receiver<T> {
+    /// Receives the next value, waiting for one to be sent if the channel is empty. Returns `None`
+    /// once the channel is empty and the sender has been dropped.
+    pub async fn recv(&mut self) -> Option<T> {
+        poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
+    /// Receives the next value if one has already been sent, without waiting.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        if let Some(value) = self.pop() {
+            return Ok(value);
+        }
+
+        if !self.shared.sender_dropped.load(Ordering::SeqCst) {
+            return Err(TryRecvError::Empty);
+        }
+
+        // The sender may have sent more values right before it was dropped.
+        self.pop().ok_or(TryRecvError::Disconnected)
+    }
+
+    /// Polls for the next value, registering the waker of the current task to be woken when one
+    /// is sent. Returns `None` once the channel is empty and the sender has been dropped.
+    pub fn poll_recv(&mut self, cx: &mut task::Context<'_>) -> task::Poll<Option<T>> {
+        coop::poll_budgeted(cx, |cx| {
+            match self.try_recv() {
+                Ok(value) => return task::Poll::Ready(Some(value)),
+                Err(TryRecvError::Disconnected) => return task::Poll::Ready(None),
+                Err(TryRecvError::Empty) => {}
+            }
+
+            self.shared.receiver.register(cx);
+
+            // The sender may have sent a value (or been dropped) before it saw that we are waiting.
+            match self.try_recv() {
+                Ok(value) => {
+                    self.shared.receiver.cancel();
+                    task::Poll::Ready(Some(value))
+                }
+                Err(TryRecvError::Disconnected) => {
+                    self.shared.receiver.cancel();
+                    task::Poll::Ready(None)
+                }
+                Err(TryRecvError::Empty) => task::Poll::Pending,
+            }
+        })
+    }
+
+    /// The number of values buffered in the channel.
+    pub fn len(&self) -> usize {
+        self.shared
+            .tail
+            .load(Ordering::Acquire)
+            .wrapping_sub(self.head)
+    }
+
+    /// Whether the channel has no buffered values.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The maximum number of values the channel buffers.
+    pub fn capacity(&self) -> usize {
+        self.shared.buffer.len()
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        if self.head == self.cached_tail {
+            self.cached_tail = self.shared.tail.load(Ordering::SeqCst);
+
+            if self.head == self.cached_tail {
+                return None;
+            }
+        }
+
+        // SAFETY: The slot is between `head` and `tail`, so the sender has written a value to it
+        // and does not access it again until we publish the new head below.
+        let value = unsafe { (*self.shared.slot(self.head).get()).assume_init_read() };
+
+        self.head = self.head.wrapping_add(1);
+        self.shared.head.store(self.head, Ordering::SeqCst);
+
+        self.shared.sender.wake();
+        Some(value)
+    }
+}
+
+impl<T> Stream for Receiver<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> task::Poll<Option<T>> {
+        self.get_mut().poll_recv(cx)
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.shared.receiver_dropped.store(true, Ordering::SeqCst);
+
+        // A waiting sender needs to learn that its send is going to fail.
+        self.shared.sender.wake();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{executor::block_on, task::noop_waker_ref, FutureExt, StreamExt};
+    use std::{pin::pin, thread};
+
+    #[test]
+    fn capacity_is_rounded_up() {
+        let (mut tx, mut rx) = channel(3);
+        assert_eq!(tx.capacity(), 4);
+
+        for i in 0..4 {
+            tx.try_send(i).unwrap();
+        }
+
+        assert!(matches!(tx.try_send(4), Err(TrySendError::Full(4))));
+        assert_eq!(rx.len(), 4);
+        assert_eq!(rx.try_recv(), Ok(0));
+
+        tx.try_send(4).unwrap();
+        drop(tx);
+
+        assert_eq!(block_on(rx.collect::<Vec<_>>()), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn send_waits_for_room() {
+        let (mut tx, mut rx) = channel(1);
+        let cx = &mut task::Context::from_waker(noop_waker_ref());
+
+        tx.try_send(1).unwrap();
+
+        {
+            let mut send = pin!(tx.send(2));
+            assert!(send.poll_unpin(cx).is_pending());
+
+            assert_eq!(rx.try_recv(), Ok(1));
+            assert!(matches!(send.poll_unpin(cx), task::Poll::Ready(Ok(()))));
+        }
+
+        drop(rx);
+        assert!(tx.is_closed());
+        assert!(matches!(tx.try_send(3), Err(TrySendError::Closed(3))));
+    }
+
+    #[test]
+    fn values_cross_threads_in_order() {
+        let (mut tx, rx) = channel(16);
+
+        let producer = thread::spawn(move || {
+            block_on(async {
+                for i in 0..10_000 {
+                    tx.send(i).await.unwrap();
+                }
+            });
+        });
+
+        let received = block_on(rx.collect::<Vec<_>>());
+        producer.join().unwrap();
+
+        assert_eq!(received, (0..10_000).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn unreceived_values_are_dropped() {
+        let value = Arc::new(());
+        let (mut tx, rx) = channel(4);
+
+        tx.try_send(Arc::clone(&value)).unwrap();
+        tx.try_send(Arc::clone(&value)).unwrap();
+        drop(rx);
+        drop(tx);
+
+        assert_eq!(Arc::strong_count(&value), 1);
+    }
+}
@@ -0,0 +1,50 @@
+use crate::{io::IoWaker, rt::current_async_agent, util::current_thread_token};
+use std::task::Waker;
+
+/// The waker of a task, together with the means to wake up the thread of the task if it is woken
+/// from another thread.
+///
+/// Waking a task from a thread that is not an async worker thread merely marks the task as
+/// awakened. If its thread is parked waiting for I/O, it only notices the task once the wait times
+/// out. Primitives that are used to deliver values across threads (e.g. from threads not owned by
+/// Folo) therefore also wake up the I/O driver of the thread of the task. On async worker threads,
+/// this is batched and delivered at the end of the cycle, together with other wake-ups of the same
+/// thread.
+#[derive(Debug)]
+pub(crate) struct ThreadWaker {
+    waker: Waker,
+
+    // Identifies the thread that registered the waker.
+    thread: usize,
+
+    // `None` if the thread that registered the waker is not an async worker thread, in which case
+    // the task waker is all we have.
+    io_waker: Option<IoWaker>,
+}
+
+impl ThreadWaker {
+    /// Creates a waker for the task that is being polled on the current thread.
+    pub(crate) fn new(waker: &Waker) -> Self {
+        Self {
+            waker: waker.clone(),
+            thread: current_thread_token(),
+            io_waker: current_async_agent::try_with_io(|io| io.waker()),
+        }
+    }
+
+    /// Whether the waker wakes the same task as another waker of the current thread, in which case
+    /// there is no need to replace it.
+    pub(crate) fn will_wake(&self, waker: &Waker) -> bool {
+        self.thread == current_thread_token() && self.waker.will_wake(waker)
+    }
+
+    pub(crate) fn wake(self) {
+        self.waker.wake();
+
+        if self.thread != current_thread_token() {
+            if let Some(io_waker) = &self.io_waker {
+                io_waker.wake();
+            }
+        }
+    }
+}
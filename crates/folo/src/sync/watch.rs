@@ -0,0 +1,365 @@
+//! A channel for distributing the latest version of a value from one producer to any number of
+//! tasks on any threads, e.g. configuration that is reloaded at runtime or a routing table that
+//! every core consults.
+//!
+//! Receivers do not see every version of the value, only the latest one. Each receiver keeps track
+//! of the version it has last seen, so it can await a change and then read a snapshot of the value
+//! that stays valid for as long as the receiver holds on to it, regardless of further changes.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use folo::sync::watch;
+//!
+//! #[folo::main]
+//! async fn main() {
+//!     let (tx, rx) = watch::channel(String::from("initial"));
+//!
+//!     for _ in 0..4 {
+//!         let mut rx = rx.clone();
+//!
+//!         folo::rt::spawn_on_any(move || async move {
+//!             while rx.changed().await.is_ok() {
+//!                 println!("new configuration: {}", rx.borrow_and_update());
+//!             }
+//!         });
+//!     }
+//!
+//!     tx.send(String::from("reloaded"));
+//! }
+//! ```
+
+use crate::{constants::POISONED_LOCK, rt::coop, sync::ThreadWaker};
+use std::{
+    collections::HashMap,
+    future::poll_fn,
+    mem,
+    sync::{Arc, Mutex, MutexGuard},
+    task,
+};
+use thiserror::Error;
+
+/// Creates a channel that starts out with the specified value. The sender and the receivers may be
+/// used on any thread. Clone the receiver to create more receivers.
+///
+/// Receivers waiting for a change on async worker threads are woken up via the I/O driver of their
+/// thread when the value is sent from another thread, so the change is observed without delay even
+/// if the worker is parked waiting for I/O.
+pub fn channel<T: Send + Sync>(initial: T) -> (Sender<T>, Receiver<T>) {
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            value: Arc::new(initial),
+            version: 0,
+            sender_dropped: false,
+            receivers: 1,
+            next_receiver_id: 1,
+            waiting: HashMap::new(),
+        }),
+    });
+
+    (
+        Sender {
+            shared: Arc::clone(&shared),
+        },
+        Receiver {
+            shared,
+            id: 0,
+            seen_version: 0,
+        },
+    )
+}
+
+/// The error returned by [`Receiver::changed()`] once the [`Sender`] has been dropped and the
+/// receiver has seen the last version of the value.
+#[derive(Clone, Copy, Debug, Eq, Error, PartialEq)]
+#[error("the sender was dropped")]
+pub struct RecvError;
+
+#[derive(Debug)]
+struct Shared<T> {
+    state: Mutex<State<T>>,
+}
+
+impl<T> Shared<T> {
+    fn lock(&self) -> MutexGuard<'_, State<T>> {
+        self.state.lock().expect(POISONED_LOCK)
+    }
+
+    /// Stores a new version of the value, waking up all the receivers waiting for a change.
+    fn publish(&self, value: Arc<T>) {
+        // The previous value is dropped outside the lock, in case dropping it takes a while.
+        let (previous, waiting) = {
+            let mut state = self.lock();
+
+            state.version = state.version.wrapping_add(1);
+            let previous = mem::replace(&mut state.value, value);
+
+            (previous, mem::take(&mut state.waiting))
+        };
+
+        drop(previous);
+        wake_all(waiting);
+    }
+}
+
+#[derive(Debug)]
+struct State<T> {
+    value: Arc<T>,
+
+    // Incremented whenever a new version of the value is sent.
+    version: u64,
+
+    sender_dropped: bool,
+    receivers: usize,
+
+    // Each receiver has an ID, so a receiver that is polled again can replace its waker.
+    next_receiver_id: u64,
+
+    // The receivers waiting for a change, keyed by receiver ID. Also wakes up the threads of the
+    // receivers if they are parked waiting for I/O.
+    waiting: HashMap<u64, ThreadWaker>,
+}
+
+fn wake_all(waiting: HashMap<u64, ThreadWaker>) {
+    // We wake outside the lock, so the receivers do not have to wait for us to release it.
+    for waker in waiting.into_values() {
+        waker.wake();
+    }
+}
+
+/// Sends new versions of the value of a watch [`channel()`]. Can be used on any thread.
+///
+/// Dropping the sender lets the receivers know that no more versions are coming.
+#[derive(Debug)]
+pub struct Sender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Sender<T> {
+    /// Replaces the value, notifying all the receivers of the change. The value is replaced even if
+    /// there are no receivers, so receivers subscribed later see it.
+    pub fn send(&self, value: T) {
+        self.shared.publish(Arc::new(value));
+    }
+
+    /// Modifies a copy of the latest value and sends the result, notifying all the receivers of the
+    /// change. Receivers holding a snapshot of the previous value keep seeing it unchanged.
+    pub fn send_modify(&self, modify: impl FnOnce(&mut T))
+    where
+        T: Clone,
+    {
+        let mut value = T::clone(&self.borrow());
+        modify(&mut value);
+
+        self.send(value);
+    }
+
+    /// A snapshot of the latest value.
+    pub fn borrow(&self) -> Arc<T> {
+        Arc::clone(&self.shared.lock().value)
+    }
+
+    /// Creates a receiver that has seen the latest value, so it only observes later changes.
+    pub fn subscribe(&self) -> Receiver<T> {
+        let mut state = self.shared.lock();
+
+        let id = state.next_receiver_id;
+        state.next_receiver_id += 1;
+        state.receivers += 1;
+
+        Receiver {
+            shared: Arc::clone(&self.shared),
+            id,
+            seen_version: state.version,
+        }
+    }
+
+    /// The number of receivers of the channel.
+    pub fn receiver_count(&self) -> usize {
+        self.shared.lock().receivers
+    }
+
+    /// Whether all receivers have been dropped, in which case sending values is pointless (though
+    /// receivers can still be created via [`subscribe()`][Self::subscribe]).
+    pub fn is_closed(&self) -> bool {
+        self.receiver_count() == 0
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let waiting = {
+            let mut state = self.shared.lock();
+            state.sender_dropped = true;
+
+            // The waiting receivers need to learn that no more versions are coming.
+            mem::take(&mut state.waiting)
+        };
+
+        wake_all(waiting);
+    }
+}
+
+/// Receives the versions of the value of a watch [`channel()`]. Can be used on any thread. Clone it
+/// to create more receivers, which start out having seen the same version as the original.
+#[derive(Debug)]
+pub struct Receiver<T> {
+    shared: Arc<Shared<T>>,
+
+    // Unique among the receivers of the channel.
+    id: u64,
+
+    // The version of the value this receiver has last seen.
+    seen_version: u64,
+}
+
+impl<T> Receiver<T> {
+    /// A snapshot of the latest value, without marking it as seen.
+    pub fn borrow(&self) -> Arc<T> {
+        Arc::clone(&self.shared.lock().value)
+    }
+
+    /// A snapshot of the latest value, marking it as seen so [`changed()`][Self::changed] only
+    /// completes once a newer version is sent.
+    pub fn borrow_and_update(&mut self) -> Arc<T> {
+        let state = self.shared.lock();
+        self.seen_version = state.version;
+
+        Arc::clone(&state.value)
+    }
+
+    /// Whether a version of the value has been sent that this receiver has not yet seen.
+    pub fn has_changed(&self) -> bool {
+        self.shared.lock().version != self.seen_version
+    }
+
+    /// Waits until a version of the value is sent that this receiver has not yet seen and marks it
+    /// as seen. Completes immediately if there already is such a version.
+    ///
+    /// Fails once the sender has been dropped and this receiver has seen the last version.
+    pub async fn changed(&mut self) -> Result<(), RecvError> {
+        poll_fn(|cx| self.poll_changed(cx)).await
+    }
+
+    pub(crate) fn poll_changed(
+        &mut self,
+        cx: &mut task::Context<'_>,
+    ) -> task::Poll<Result<(), RecvError>> {
+        coop::poll_budgeted(cx, |cx| {
+            let mut state = self.shared.lock();
+
+            if state.version != self.seen_version {
+                self.seen_version = state.version;
+                return task::Poll::Ready(Ok(()));
+            }
+
+            if state.sender_dropped {
+                return task::Poll::Ready(Err(RecvError));
+            }
+
+            if !state
+                .waiting
+                .get(&self.id)
+                .is_some_and(|w| w.will_wake(cx.waker()))
+            {
+                state.waiting.insert(self.id, ThreadWaker::new(cx.waker()));
+            }
+
+            task::Poll::Pending
+        })
+    }
+}
+
+impl<T> Clone for Receiver<T> {
+    fn clone(&self) -> Self {
+        let mut state = self.shared.lock();
+
+        let id = state.next_receiver_id;
+        state.next_receiver_id += 1;
+        state.receivers += 1;
+
+        Self {
+            shared: Arc::clone(&self.shared),
+            id,
+            seen_version: self.seen_version,
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let mut state = self.shared.lock();
+
+        state.receivers -= 1;
+        state.waiting.remove(&self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{executor::block_on, task::noop_waker_ref, FutureExt};
+    use std::{pin::pin, thread};
+
+    #[test]
+    fn receivers_see_latest_value() {
+        let (tx, mut rx) = channel(1);
+        let rx2 = rx.clone();
+
+        assert!(!rx.has_changed());
+        assert_eq!(*rx.borrow(), 1);
+
+        tx.send(2);
+        tx.send_modify(|value| *value += 1);
+
+        assert!(rx.has_changed());
+        assert!(rx2.has_changed());
+        assert_eq!(*rx.borrow_and_update(), 3);
+        assert!(!rx.has_changed());
+        assert_eq!(*rx2.borrow(), 3);
+    }
+
+    #[test]
+    fn snapshot_survives_changes() {
+        let (tx, rx) = channel(String::from("old"));
+
+        let snapshot = rx.borrow();
+        tx.send(String::from("new"));
+
+        assert_eq!(*snapshot, "old");
+        assert_eq!(*rx.borrow(), "new");
+    }
+
+    #[test]
+    fn changed_waits_for_change() {
+        let (tx, mut rx) = channel(1);
+        let cx = &mut task::Context::from_waker(noop_waker_ref());
+
+        {
+            let mut changed = pin!(rx.changed());
+            assert!(changed.poll_unpin(cx).is_pending());
+
+            thread::spawn(move || tx.send(2)).join().unwrap();
+            assert_eq!(changed.poll_unpin(cx), task::Poll::Ready(Ok(())));
+        }
+
+        assert_eq!(*rx.borrow(), 2);
+        assert_eq!(block_on(rx.changed()), Err(RecvError));
+    }
+
+    #[test]
+    fn subscriber_only_sees_later_changes() {
+        let (tx, rx) = channel(1);
+
+        tx.send(2);
+        let subscriber = tx.subscribe();
+
+        assert!(rx.has_changed());
+        assert!(!subscriber.has_changed());
+        assert_eq!(tx.receiver_count(), 2);
+
+        drop(rx);
+        drop(subscriber);
+        assert!(tx.is_closed());
+    }
+}
@@ -0,0 +1,580 @@
+//! A deterministic single-threaded runtime for testing timing-dependent logic (retries, timeouts,
+//! backoff and similar) without waiting for real time to pass.
+//!
+//! All tasks execute on the thread that calls [`TestRuntime::block_on()`], in a deterministic
+//! order. Time is virtual: whenever every task is waiting, the virtual clock jumps straight to the
+//! next pending timer, so a test that sleeps for an hour completes instantly and observes exactly
+//! one hour having passed.
+//!
+//! Only timers created from the clock of the test runtime ([`TestRuntime::clock()`]) use virtual
+//! time, so the code under test must accept a [`Clock`] instead of creating its own. The test
+//! runtime does not execute I/O - code under test that performs I/O needs to be given in-memory
+//! fakes of its I/O dependencies. Futures waiting for anything outside the test runtime (I/O, other
+//! threads) are not supported - if all tasks are waiting and no timer is pending, the test runtime
+//! panics, as nothing could ever wake them up.
+//!
+//! # Schedule exploration
+//!
+//! By default, tasks that are ready are polled in the order they were woken up. A runtime created
+//! via [`TestRuntime::with_seed()`] instead polls the ready tasks in a pseudo-random order chosen
+//! from the seed. As tasks are woken up when a message is delivered to them (e.g. via a channel),
+//! this also shuffles the order in which the receivers observe messages relative to the progress of
+//! the other tasks. Running a test under many seeds explores interleavings that a fixed order
+//! never exercises, shaking out ordering bugs between tasks.
+//!
+//! The same seed always results in the same schedule, so a failure can be replayed by running the
+//! test with the seed it failed with. [`explore()`] runs a test under a number of seeds and reports
+//! the seed of a failing run, which can then be replayed by setting the `FOLO_TEST_SEED`
+//! environment variable.
+//!
+//! # Example
+//!
+//! ```
+//! use folo::{test_rt::TestRuntime, time::Delay};
+//! use std::time::Duration;
+//!
+//! let mut runtime = TestRuntime::new();
+//! let clock = runtime.clock();
+//!
+//! let start = clock.now();
+//!
+//! runtime.block_on(async {
+//!     Delay::with_clock(&clock, Duration::from_secs(3600)).await;
+//! });
+//!
+//! assert_eq!(clock.now().duration_since(start).unwrap(), Duration::from_secs(3600));
+//! ```
+
+use crate::{
+    time::{Clock, ClockControl},
+    util::SplitMix64,
+};
+use futures::task::{waker, ArcWake};
+use negative_impl::negative_impl;
+use std::{
+    cell::RefCell,
+    collections::{
+        hash_map::{HashMap, RandomState},
+        HashSet, VecDeque,
+    },
+    env,
+    fmt::{self, Debug, Formatter},
+    future::Future,
+    hash::{BuildHasher, Hasher},
+    pin::{pin, Pin},
+    rc::Rc,
+    sync::{Arc, Mutex},
+    task::{self, Context, Waker},
+    thread,
+};
+
+/// The environment variable that [`explore()`] takes the seed to replay from.
+pub const SEED_ENV_VAR: &str = "FOLO_TEST_SEED";
+
+// The future given to `block_on()` is scheduled like a task with this ID.
+const MAIN_TASK_ID: u64 = 0;
+
+type LocalTask = Pin<Box<dyn Future<Output = ()>>>;
+
+// Tasks spawned since the scheduler last looked, not yet assigned an ID.
+type SpawnedTasks = Rc<RefCell<Vec<LocalTask>>>;
+
+/// A deterministic single-threaded runtime with a virtual clock. See the [module
+/// documentation][self].
+pub struct TestRuntime {
+    tasks: HashMap<u64, LocalTask>,
+    next_task_id: u64,
+
+    spawned: SpawnedTasks,
+
+    ready: Arc<ReadyQueue>,
+
+    // If set, ready tasks are polled in a pseudo-random order instead of the order they were woken.
+    seed: Option<u64>,
+    rng: SplitMix64,
+
+    clock_control: ClockControl,
+    clock: Clock,
+}
+
+impl TestRuntime {
+    pub fn new() -> Self {
+        Self::new_core(None)
+    }
+
+    /// Creates a runtime that polls ready tasks in a pseudo-random order chosen from the seed. The
+    /// same seed always results in the same order, given the same test.
+    pub fn with_seed(seed: u64) -> Self {
+        Self::new_core(Some(seed))
+    }
+
+    fn new_core(seed: Option<u64>) -> Self {
+        let clock_control = ClockControl::new();
+        let clock = Clock::with_control(&clock_control);
+
+        Self {
+            tasks: HashMap::new(),
+            next_task_id: MAIN_TASK_ID + 1,
+            spawned: Rc::new(RefCell::new(Vec::new())),
+            ready: Arc::new(ReadyQueue::default()),
+            seed,
+            rng: SplitMix64::new(seed.unwrap_or_default()),
+            clock_control,
+            clock,
+        }
+    }
+
+    /// The seed the schedule is chosen from, if the runtime was created via
+    /// [`with_seed()`][Self::with_seed].
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    /// The virtual clock of the runtime. Timers created from this clock are fired by the test
+    /// runtime as soon as all tasks are waiting.
+    pub fn clock(&self) -> Clock {
+        self.clock.clone()
+    }
+
+    /// Allows the virtual clock to be advanced manually (e.g. from within a task, to simulate time
+    /// passing while a task is busy).
+    pub fn clock_control(&self) -> ClockControl {
+        self.clock_control.clone()
+    }
+
+    /// Executes a future to completion on the current thread, together with any tasks it spawns via
+    /// [`spawn()`], advancing virtual time whenever all of them are waiting.
+    ///
+    /// Tasks that are still pending when the future completes are not dropped - they continue
+    /// executing the next time `block_on()` is called on the same runtime.
+    ///
+    /// # Panics
+    ///
+    /// Panics if all tasks are waiting for something other than a timer of the virtual clock, as
+    /// this means the test would never complete.
+    ///
+    /// Panics in the future or in any spawned task are propagated to the caller.
+    pub fn block_on<F: Future>(&mut self, future: F) -> F::Output {
+        let _spawner_guard = SpawnerGuard::new(Rc::clone(&self.spawned));
+
+        let mut future = pin!(future);
+
+        let main_waker = self.waker(MAIN_TASK_ID);
+        self.ready.push(MAIN_TASK_ID);
+
+        loop {
+            self.admit_spawned();
+
+            let Some(task_id) = self.next_ready() else {
+                // Everything is waiting. The only thing that can make progress now is time passing.
+                assert!(
+                    self.clock_control.advance_to_next_timer(),
+                    "all tasks in the test runtime are waiting but no timer is pending, so they can never complete"
+                );
+
+                continue;
+            };
+
+            if task_id == MAIN_TASK_ID {
+                if let task::Poll::Ready(result) =
+                    future.as_mut().poll(&mut Context::from_waker(&main_waker))
+                {
+                    return result;
+                }
+
+                continue;
+            }
+
+            // Wake-ups of tasks that have already completed are ignored.
+            let Some(mut task) = self.tasks.remove(&task_id) else {
+                continue;
+            };
+
+            let waker = self.waker(task_id);
+
+            if task
+                .as_mut()
+                .poll(&mut Context::from_waker(&waker))
+                .is_pending()
+            {
+                self.tasks.insert(task_id, task);
+            }
+        }
+    }
+
+    fn admit_spawned(&mut self) {
+        for task in self.spawned.borrow_mut().drain(..) {
+            let task_id = self.next_task_id;
+            self.next_task_id += 1;
+
+            self.tasks.insert(task_id, task);
+            self.ready.push(task_id);
+        }
+    }
+
+    fn next_ready(&mut self) -> Option<u64> {
+        let mut ready = self.ready.inner.lock().expect("poisoned lock");
+
+        let index = match self.seed {
+            Some(_) if !ready.order.is_empty() => {
+                self.rng.next_below(ready.order.len() as u64) as usize
+            }
+            _ => 0,
+        };
+
+        let task_id = ready.order.remove(index)?;
+        ready.queued.remove(&task_id);
+
+        Some(task_id)
+    }
+
+    fn waker(&self, task_id: u64) -> Waker {
+        waker(Arc::new(TaskWaker {
+            task_id,
+            ready: Arc::clone(&self.ready),
+        }))
+    }
+}
+
+impl Default for TestRuntime {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for TestRuntime {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TestRuntime")
+            .field("tasks", &self.tasks.len())
+            .field("seed", &self.seed)
+            .field("clock_control", &self.clock_control)
+            .finish()
+    }
+}
+
+#[negative_impl]
+impl !Send for TestRuntime {}
+#[negative_impl]
+impl !Sync for TestRuntime {}
+
+/// Spawns a task on the test runtime that is executing the current thread. The task starts
+/// executing once the current task yields.
+///
+/// # Panics
+///
+/// Panics if the current thread is not executing [`TestRuntime::block_on()`].
+pub fn spawn<F, R>(future: F) -> TestJoinHandle<R>
+where
+    F: Future<Output = R> + 'static,
+    R: 'static,
+{
+    let (result_tx, result_rx) = oneshot::channel();
+
+    CURRENT_SPAWNER.with_borrow(|spawner| {
+        spawner
+            .as_ref()
+            .expect("test_rt::spawn() can only be called from within TestRuntime::block_on()")
+            .borrow_mut()
+            .push(Box::pin(async move {
+                // The join handle may have been dropped already, which is fine.
+                _ = result_tx.send(future.await);
+            }))
+    });
+
+    TestJoinHandle { result_rx }
+}
+
+/// Runs a test under `iterations` different schedules, each on a new runtime created via
+/// [`TestRuntime::with_seed()`]. If a run panics, the seed of that run is printed to stderr.
+///
+/// If the `FOLO_TEST_SEED` environment variable is set, the test is instead run once, with the seed
+/// from the variable, to replay a failure.
+///
+/// # Example
+///
+/// ```
+/// use folo::test_rt::{self, explore};
+///
+/// explore(100, |runtime| {
+///     runtime.block_on(async {
+///         let a = test_rt::spawn(async { 1 });
+///         let b = test_rt::spawn(async { 2 });
+///
+///         assert_eq!(a.await + b.await, 3);
+///     });
+/// });
+/// ```
+pub fn explore<F>(iterations: usize, mut test: F)
+where
+    F: FnMut(&mut TestRuntime),
+{
+    if let Some(seed) = replay_seed() {
+        test(&mut TestRuntime::with_seed(seed));
+        return;
+    }
+
+    // Every run of the test process explores different schedules.
+    let mut seeds = SplitMix64::new(RandomState::new().build_hasher().finish());
+
+    for _ in 0..iterations {
+        let seed = seeds.next_u64();
+        let _seed_reporter = SeedReporter(seed);
+
+        test(&mut TestRuntime::with_seed(seed));
+    }
+}
+
+fn replay_seed() -> Option<u64> {
+    let value = env::var(SEED_ENV_VAR).ok()?;
+
+    Some(value.trim().parse().unwrap_or_else(|_| {
+        panic!("{SEED_ENV_VAR} must be an unsigned 64-bit integer but was '{value}'")
+    }))
+}
+
+/// Allows the result of a task spawned via [`spawn()`] to be awaited. Dropping the join handle
+/// does not cancel the task.
+pub struct TestJoinHandle<R> {
+    result_rx: oneshot::Receiver<R>,
+}
+
+impl<R> Future for TestJoinHandle<R> {
+    type Output = R;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> task::Poll<Self::Output> {
+        match Pin::new(&mut self.result_rx).poll(cx) {
+            task::Poll::Ready(Ok(result)) => task::Poll::Ready(result),
+            // The task can only be dropped without a result if it panicked (which propagates out
+            // of the test runtime) or if the test runtime itself was dropped.
+            task::Poll::Ready(Err(_)) => panic!("test runtime task was dropped before completing"),
+            task::Poll::Pending => task::Poll::Pending,
+        }
+    }
+}
+
+impl<R> Debug for TestJoinHandle<R> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TestJoinHandle").finish()
+    }
+}
+
+// The IDs of the tasks that have been woken up and are waiting to be polled. Wakers may be used
+// from any thread, so this is thread-safe even though the tasks themselves are not.
+#[derive(Default)]
+struct ReadyQueue {
+    inner: Mutex<ReadyQueueInner>,
+}
+
+#[derive(Default)]
+struct ReadyQueueInner {
+    // In the order the tasks were woken up.
+    order: VecDeque<u64>,
+
+    // The same IDs, to ignore repeated wake-ups of a task that is already queued.
+    queued: HashSet<u64>,
+}
+
+impl ReadyQueue {
+    fn push(&self, task_id: u64) {
+        let mut inner = self.inner.lock().expect("poisoned lock");
+
+        if inner.queued.insert(task_id) {
+            inner.order.push_back(task_id);
+        }
+    }
+}
+
+struct TaskWaker {
+    task_id: u64,
+    ready: Arc<ReadyQueue>,
+}
+
+impl ArcWake for TaskWaker {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        arc_self.ready.push(arc_self.task_id);
+    }
+}
+
+// Prints the seed of the schedule if the test panics, so the failure can be replayed.
+struct SeedReporter(u64);
+
+impl Drop for SeedReporter {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            eprintln!(
+                "test failed with schedule seed {seed} - set {SEED_ENV_VAR}={seed} to replay",
+                seed = self.0
+            );
+        }
+    }
+}
+
+// Makes `spawn()` target the test runtime for as long as it is executing on the current thread.
+struct SpawnerGuard;
+
+impl SpawnerGuard {
+    fn new(spawner: SpawnedTasks) -> Self {
+        CURRENT_SPAWNER.with_borrow_mut(|current| {
+            assert!(
+                current.is_none(),
+                "TestRuntime::block_on() cannot be called from within another test runtime"
+            );
+
+            *current = Some(spawner);
+        });
+
+        Self
+    }
+}
+
+impl Drop for SpawnerGuard {
+    fn drop(&mut self) {
+        CURRENT_SPAWNER.with_borrow_mut(|current| *current = None);
+    }
+}
+
+thread_local! {
+    static CURRENT_SPAWNER: RefCell<Option<SpawnedTasks>> = const { RefCell::new(None) };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{rt::yield_now, time::Delay};
+    use std::{cell::Cell, time::Duration};
+
+    #[test]
+    fn delays_complete_in_virtual_time() {
+        let mut runtime = TestRuntime::new();
+        let clock = runtime.clock();
+        let start = clock.now();
+
+        runtime.block_on(async {
+            Delay::with_clock(&clock, Duration::from_secs(10)).await;
+            Delay::with_clock(&clock, Duration::from_secs(5)).await;
+        });
+
+        assert_eq!(
+            clock.now().duration_since(start).unwrap(),
+            Duration::from_secs(15)
+        );
+    }
+
+    #[test]
+    fn spawned_tasks_wake_in_deadline_order() {
+        let mut runtime = TestRuntime::new();
+        let clock = runtime.clock();
+        let order = Rc::new(RefCell::new(Vec::new()));
+
+        runtime.block_on({
+            let order = Rc::clone(&order);
+
+            async move {
+                let handles = [30, 10, 20].map(|seconds| {
+                    let clock = clock.clone();
+                    let order = Rc::clone(&order);
+
+                    spawn(async move {
+                        Delay::with_clock(&clock, Duration::from_secs(seconds)).await;
+                        order.borrow_mut().push(seconds);
+                        seconds
+                    })
+                });
+
+                let mut sum = 0;
+
+                for handle in handles {
+                    sum += handle.await;
+                }
+
+                assert_eq!(sum, 60);
+            }
+        });
+
+        assert_eq!(*order.borrow(), vec![10, 20, 30]);
+    }
+
+    // Two tasks that each record their progress twice, yielding in between.
+    fn interleaving(runtime: &mut TestRuntime) -> Vec<&'static str> {
+        let order = Rc::new(RefCell::new(Vec::new()));
+
+        runtime.block_on({
+            let order = Rc::clone(&order);
+
+            async move {
+                let handles = ["a", "b"].map(|name| {
+                    let order = Rc::clone(&order);
+
+                    spawn(async move {
+                        for _ in 0..2 {
+                            order.borrow_mut().push(name);
+                            yield_now().await;
+                        }
+                    })
+                });
+
+                for handle in handles {
+                    handle.await;
+                }
+            }
+        });
+
+        order.take()
+    }
+
+    #[test]
+    fn same_seed_same_schedule() {
+        for seed in 0..20 {
+            assert_eq!(
+                interleaving(&mut TestRuntime::with_seed(seed)),
+                interleaving(&mut TestRuntime::with_seed(seed))
+            );
+        }
+    }
+
+    #[test]
+    fn seeds_explore_different_schedules() {
+        assert_eq!(
+            interleaving(&mut TestRuntime::new()),
+            vec!["a", "b", "a", "b"]
+        );
+
+        let schedules = (0..50)
+            .map(|seed| interleaving(&mut TestRuntime::with_seed(seed)))
+            .collect::<HashSet<_>>();
+
+        assert!(schedules.len() > 1);
+    }
+
+    #[test]
+    fn explore_runs_every_iteration() {
+        let runs = Cell::new(0);
+
+        explore(10, |runtime| {
+            assert!(runtime.seed().is_some());
+            runs.set(runs.get() + 1);
+        });
+
+        // Unless a seed is being replayed.
+        let expected = if env::var(SEED_ENV_VAR).is_ok() {
+            1
+        } else {
+            10
+        };
+        assert_eq!(runs.get(), expected);
+    }
+
+    #[test]
+    #[should_panic]
+    fn waiting_without_timers_panics() {
+        let mut runtime = TestRuntime::new();
+
+        runtime.block_on(futures::future::pending::<()>());
+    }
+
+    #[test]
+    #[should_panic]
+    fn spawn_outside_test_runtime_panics() {
+        _ = spawn(async {});
+    }
+}
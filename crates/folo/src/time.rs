@@ -3,21 +3,36 @@
 mod clock;
 #[cfg(feature = "fakes")]
 mod clock_control;
+mod coarse;
+mod cron;
+mod deadline;
 mod delay;
 mod error;
+mod interval;
 mod low_precision;
+#[cfg(feature = "test-util")]
+mod pause;
 mod periodic_timer;
+mod schedule;
 mod stopwatch;
 mod timers;
 mod ultra_low_precision;
+mod wake_timer;
 
 pub use clock::*;
 #[cfg(feature = "fakes")]
 pub use clock_control::*;
+pub use coarse::*;
+pub use deadline::*;
 pub use delay::*;
 pub use error::*;
+pub use interval::*;
 pub use low_precision::*;
+#[cfg(feature = "test-util")]
+pub use pause::*;
 pub use periodic_timer::*;
+pub use schedule::*;
 pub use stopwatch::*;
 pub(crate) use timers::*;
-pub use ultra_low_precision::*;
\ No newline at end of file
+pub use ultra_low_precision::*;
+pub(crate) use wake_timer::*;
\ No newline at end of file
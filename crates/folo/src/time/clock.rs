@@ -5,12 +5,15 @@ use std::time::Instant;
 
 #[cfg(feature = "fakes")]
 use super::clock_control::ClockControl;
-use super::{TimerKey, LOCAL_TIMERS};
+use super::{coarse_now, TimerKey, LOCAL_TIMERS};
 
 #[derive(Debug, Clone)]
 pub struct Clock {
     _private: (),
 
+    // Whether the instant of the current cycle is good enough for timers created from this clock.
+    coarse: bool,
+
     #[cfg(feature = "fakes")]
     clock_control: Option<ClockControl>,
 }
@@ -24,14 +27,24 @@ impl Clock {
     // running with the "fakes" feature.
     #[cfg(not(feature = "fakes"))]
     fn new_core() -> Self {
-        Self { _private: () }
+        Self {
+            _private: (),
+            coarse: false,
+        }
     }
 
     #[cfg(feature = "fakes")]
     fn new_core() -> Self {
+        // Clocks created while time is paused observe the paused time.
+        #[cfg(feature = "test-util")]
+        let clock_control = super::pause::paused_clock_control();
+        #[cfg(not(feature = "test-util"))]
+        let clock_control = None;
+
         Self {
             _private: (),
-            clock_control: None
+            coarse: false,
+            clock_control
         }
     }
 
@@ -41,10 +54,23 @@ impl Clock {
 
         Self {
             _private: (),
+            coarse: false,
             clock_control: Some(clone.clone())
         }
     }
 
+    /// Creates a clock that uses the instant observed at the start of the current cycle of the
+    /// async worker thread (see [`coarse_now()`][super::coarse_now]) instead of querying the
+    /// precise current instant. Timers created from this clock (e.g. the timeouts of hot paths
+    /// that arm a timer for every request) avoid the cost of the query, at the expense of possibly
+    /// firing slightly later than requested.
+    pub fn coarse() -> Self {
+        Self {
+            coarse: true,
+            ..Clock::new_core()
+        }
+    }
+
     pub fn now(&self) -> SystemTime {
         // This method is mutated, but cannot be tested due to tests
         // running with the "fakes" feature.
@@ -69,14 +95,14 @@ impl Clock {
         // This method is mutated, but cannot be tested due to tests
         // running with the "fakes" feature.
         #[cfg(not(feature = "fakes"))]
-        fn now_core(_clock: &Clock) -> Instant {
-            Instant::now()
+        fn now_core(clock: &Clock) -> Instant {
+            clock.real_instant_now()
         }
 
         #[cfg(feature = "fakes")]
         fn now_core(clock: &Clock) -> Instant {
             match &clock.clock_control {
-                None => Instant::now(),
+                None => clock.real_instant_now(),
                 Some(control) => control.instant_now(),
             }
         }
@@ -84,6 +110,14 @@ impl Clock {
         now_core(self)
     }
 
+    fn real_instant_now(&self) -> Instant {
+        if self.coarse {
+            coarse_now()
+        } else {
+            Instant::now()
+        }
+    }
+
     // This method is mutated, but cannot be tested due to tests
     // running with the "fakes" feature.
     #[cfg(not(feature = "fakes"))]
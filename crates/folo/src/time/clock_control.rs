@@ -31,6 +31,18 @@ impl ClockControl {
         }
     }
 
+    /// Creates a clock control whose time starts at the current real time, instead of at the
+    /// Unix epoch.
+    #[cfg(feature = "test-util")]
+    pub(super) fn starting_now() -> ClockControl {
+        let mut state = State::new();
+        state.timestamp = SystemTime::now();
+
+        ClockControl {
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+
     pub fn auto_advance(&self) -> Duration {
         self.with_state(|v| v.auto_advance)
     }
@@ -47,6 +59,18 @@ impl ClockControl {
         self.with_state(|v| v.advance(duration));
     }
 
+    /// Advances the clock to the instant the earliest pending timer fires, firing it. Returns
+    /// `false` if there is no pending timer.
+    pub(crate) fn advance_to_next_timer(&mut self) -> bool {
+        self.with_state(|s| match s.timers.next_tick() {
+            Some(tick) => {
+                s.advance(tick.saturating_duration_since(s.instant));
+                true
+            }
+            None => false,
+        })
+    }
+
     pub(super) fn now(&self) -> SystemTime {
         self.with_state(State::now)
     }
@@ -0,0 +1,50 @@
+// Copyright (c) Microsoft Corporation.
+
+use std::cell::Cell;
+use std::time::Instant;
+
+/// Returns the current instant as observed at the start of the current cycle of the async worker
+/// thread, which is far cheaper than [`Instant::now()`] because it only reads a thread-local
+/// variable instead of querying the performance counter.
+///
+/// Use this on hot paths that timestamp every request or packet and can tolerate the timestamp
+/// lagging behind by however long the current cycle has taken so far (typically microseconds, up
+/// to the duration of the longest task poll in the cycle). Timers that should use this instant
+/// instead of the precise one can be created from [`Clock::coarse()`][super::Clock::coarse].
+///
+/// On threads that are not async worker threads, this falls back to [`Instant::now()`].
+pub fn coarse_now() -> Instant {
+    COARSE_NOW.get().unwrap_or_else(Instant::now)
+}
+
+/// Updates the instant observed by `coarse_now()` on the current thread. The expectation is that
+/// the async task runtime calls this once per cycle.
+pub(crate) fn update_coarse_now(now: Instant) {
+    COARSE_NOW.set(Some(now));
+}
+
+thread_local! {
+    static COARSE_NOW: Cell<Option<Instant>> = const { Cell::new(None) };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn returns_updated_instant() {
+        let now = Instant::now() + Duration::from_secs(60);
+        update_coarse_now(now);
+
+        assert_eq!(coarse_now(), now);
+    }
+
+    #[test]
+    fn coarse_clock_uses_updated_instant() {
+        let now = Instant::now() + Duration::from_secs(60);
+        update_coarse_now(now);
+
+        assert_eq!(crate::time::Clock::coarse().instant_now(), now);
+    }
+}
@@ -0,0 +1,285 @@
+// Copyright (c) Microsoft Corporation.
+
+use std::time::{Duration, SystemTime};
+
+use super::{Error, Result};
+
+/// A parsed cron expression in the classic 5-field format:
+///
+/// ```text
+/// minute (0-59) hour (0-23) day-of-month (1-31) month (1-12) day-of-week (0-6, 0 = Sunday)
+/// ```
+///
+/// Each field supports `*`, single values, ranges (`a-b`), steps (`*/n` and `a-b/n`) and lists of
+/// any of these (`a,b-c,*/n`). Day-of-week also accepts 7 as Sunday. Names of months and days are
+/// not supported.
+///
+/// If both day-of-month and day-of-week are restricted, a day matches if either of them matches,
+/// as in traditional cron implementations. As there, a field that starts with `*` (e.g. `*/2`)
+/// does not count as restricted.
+///
+/// All times are evaluated in UTC.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(super) struct CronExpression {
+    minutes: u64,
+    hours: u32,
+    days_of_month: u32,
+    months: u16,
+    days_of_week: u8,
+
+    days_of_month_restricted: bool,
+    days_of_week_restricted: bool,
+}
+
+impl CronExpression {
+    pub fn parse(expression: &str) -> Result<Self> {
+        let fields = expression.split_whitespace().collect::<Vec<_>>();
+
+        let [minutes, hours, days_of_month, months, days_of_week] = fields[..] else {
+            return Err(Error::invalid_schedule(format!(
+                "cron expression must have 5 fields but '{expression}' has {}",
+                fields.len()
+            )));
+        };
+
+        let mut days_of_week_bits = parse_field(days_of_week, 0, 7)?;
+
+        // Both 0 and 7 mean Sunday.
+        if days_of_week_bits & (1 << 7) != 0 {
+            days_of_week_bits = (days_of_week_bits | 1) & !(1 << 7);
+        }
+
+        Ok(Self {
+            minutes: parse_field(minutes, 0, 59)?,
+            hours: parse_field(hours, 0, 23)? as u32,
+            days_of_month: parse_field(days_of_month, 1, 31)? as u32,
+            months: parse_field(months, 1, 12)? as u16,
+            days_of_week: days_of_week_bits as u8,
+            days_of_month_restricted: !days_of_month.starts_with('*'),
+            days_of_week_restricted: !days_of_week.starts_with('*'),
+        })
+    }
+
+    /// Returns the first matching point in time strictly after `after`, or `None` if there is no
+    /// such point within a reasonable search horizon (e.g. "February 31st").
+    pub fn next_after(&self, after: SystemTime) -> Option<SystemTime> {
+        let after_secs = after.duration_since(SystemTime::UNIX_EPOCH).ok()?.as_secs();
+
+        // Cron has minute granularity, so we start from the next whole minute.
+        let first_minute = after_secs / 60 + 1;
+        let first_day = (first_minute / MINUTES_PER_DAY) as i64;
+        let first_minute_of_day = (first_minute % MINUTES_PER_DAY) as u32;
+
+        for day in first_day..first_day + SEARCH_HORIZON_DAYS {
+            if !self.matches_day(day) {
+                continue;
+            }
+
+            let start_minute_of_day = if day == first_day {
+                first_minute_of_day
+            } else {
+                0
+            };
+
+            if let Some(minute_of_day) = self.first_time_of_day(start_minute_of_day) {
+                let minutes = day as u64 * MINUTES_PER_DAY + minute_of_day as u64;
+                return SystemTime::UNIX_EPOCH.checked_add(Duration::from_secs(minutes * 60));
+            }
+        }
+
+        None
+    }
+
+    fn matches_day(&self, days_since_epoch: i64) -> bool {
+        let (_, month, day) = civil_from_days(days_since_epoch);
+
+        // 1970-01-01 was a Thursday.
+        let day_of_week = (days_since_epoch + 4).rem_euclid(7) as u32;
+
+        if self.months & (1 << month) == 0 {
+            return false;
+        }
+
+        let day_of_month_matches = self.days_of_month & (1 << day) != 0;
+        let day_of_week_matches = self.days_of_week & (1 << day_of_week) != 0;
+
+        if self.days_of_month_restricted && self.days_of_week_restricted {
+            day_of_month_matches || day_of_week_matches
+        } else {
+            day_of_month_matches && day_of_week_matches
+        }
+    }
+
+    fn first_time_of_day(&self, start_minute_of_day: u32) -> Option<u32> {
+        let start_hour = start_minute_of_day / 60;
+
+        for hour in start_hour..24 {
+            if self.hours & (1 << hour) == 0 {
+                continue;
+            }
+
+            let start_minute = if hour == start_hour {
+                start_minute_of_day % 60
+            } else {
+                0
+            };
+
+            if let Some(minute) = (start_minute..60).find(|m| self.minutes & (1 << m) != 0) {
+                return Some(hour * 60 + minute);
+            }
+        }
+
+        None
+    }
+}
+
+/// Parses one field of a cron expression into a bit set, where bit N is set if value N matches.
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64> {
+    let mut bits = 0;
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, parse_value(step, 1, max.max(1))?),
+            None => (part, 1),
+        };
+
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (parse_value(start, min, max)?, parse_value(end, min, max)?)
+        } else {
+            let value = parse_value(range, min, max)?;
+
+            // "5/10" means "starting at 5, every 10" in most cron implementations.
+            if part.contains('/') {
+                (value, max)
+            } else {
+                (value, value)
+            }
+        };
+
+        if start > end {
+            return Err(Error::invalid_schedule(format!(
+                "invalid range '{range}' in cron field '{field}'"
+            )));
+        }
+
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+
+    Ok(bits)
+}
+
+fn parse_value(value: &str, min: u32, max: u32) -> Result<u32> {
+    match value.parse::<u32>() {
+        Ok(parsed) if (min..=max).contains(&parsed) => Ok(parsed),
+        _ => Err(Error::invalid_schedule(format!(
+            "invalid cron value '{value}', expected a number in {min}..={max}"
+        ))),
+    }
+}
+
+/// Converts days since the Unix epoch to a (year, month, day) civil date in the proleptic
+/// Gregorian calendar. See http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days_since_epoch: i64) -> (i64, u32, u32) {
+    let z = days_since_epoch + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day)
+}
+
+const MINUTES_PER_DAY: u64 = 24 * 60;
+
+// Long enough to find any valid combination, including "February 29th on a Monday" (which happens
+// at least once every 28 years).
+const SEARCH_HORIZON_DAYS: i64 = 366 * 30;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    fn secs(time: SystemTime) -> u64 {
+        time.duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    #[test]
+    fn civil_dates() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(59), (1970, 3, 1));
+        // 2000-02-29
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+    }
+
+    #[test]
+    fn every_minute() {
+        let cron = CronExpression::parse("* * * * *").unwrap();
+
+        assert_eq!(secs(cron.next_after(at(0)).unwrap()), 60);
+        assert_eq!(secs(cron.next_after(at(59)).unwrap()), 60);
+        assert_eq!(secs(cron.next_after(at(60)).unwrap()), 120);
+    }
+
+    #[test]
+    fn steps_and_lists() {
+        let cron = CronExpression::parse("*/15 1,3 * * *").unwrap();
+
+        // First match after the epoch is 01:00.
+        assert_eq!(secs(cron.next_after(at(0)).unwrap()), 3600);
+        // After 01:50 comes 03:00.
+        assert_eq!(secs(cron.next_after(at(3600 + 50 * 60)).unwrap()), 3 * 3600);
+    }
+
+    #[test]
+    fn day_of_week() {
+        // 1970-01-01 was a Thursday, so the first Monday is 1970-01-05.
+        let cron = CronExpression::parse("0 0 * * 1").unwrap();
+        assert_eq!(secs(cron.next_after(at(0)).unwrap()), 4 * 86_400);
+
+        // 7 is also Sunday, which is 1970-01-04.
+        let cron = CronExpression::parse("0 0 * * 7").unwrap();
+        assert_eq!(secs(cron.next_after(at(0)).unwrap()), 3 * 86_400);
+    }
+
+    #[test]
+    fn step_day_of_month_is_not_restricted() {
+        // Odd days of the month that are Mondays, the first being 1970-01-05. If the step counted
+        // as a restriction, any odd day or Monday would match, starting with 1970-01-03.
+        let cron = CronExpression::parse("0 0 */2 * 1").unwrap();
+        assert_eq!(secs(cron.next_after(at(0)).unwrap()), 4 * 86_400);
+    }
+
+    #[test]
+    fn impossible_date() {
+        let cron = CronExpression::parse("0 0 31 2 *").unwrap();
+        assert!(cron.next_after(at(0)).is_none());
+    }
+
+    #[test]
+    fn invalid_expressions() {
+        assert!(CronExpression::parse("* * * *").is_err());
+        assert!(CronExpression::parse("60 * * * *").is_err());
+        assert!(CronExpression::parse("5-1 * * * *").is_err());
+        assert!(CronExpression::parse("*/0 * * * *").is_err());
+        assert!(CronExpression::parse("mon * * * *").is_err());
+    }
+}
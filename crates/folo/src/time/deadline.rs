@@ -0,0 +1,257 @@
+// Copyright (c) Microsoft Corporation.
+
+use std::cell::Cell;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use pin_project::pin_project;
+
+use super::{Clock, Delay};
+
+/// The instant by which an operation must complete, after which its result is no longer of
+/// interest to anyone (e.g. because the client that requested it has given up waiting).
+///
+/// Unlike a timeout, a deadline composes: code running under a deadline (see
+/// [`scope()`][Self::scope]) can obtain it via [`Deadline::current()`] and nested scopes inherit
+/// the remaining budget automatically, as the effective deadline of a nested scope is the earlier
+/// of its own deadline and that of the enclosing scope. Operations that take a deadline or a
+/// timeout of their own (e.g. [`TcpConnector`][crate::net::TcpConnector]) honor the current
+/// deadline, so a nested operation never outlives the request it is part of.
+///
+/// Tasks spawned via [`spawn_with_deadline()`][crate::rt::spawn_with_deadline] run under their
+/// deadline for their entire lifetime.
+///
+/// # Example
+///
+/// ```no_run
+/// use folo::net::TcpConnector;
+/// use folo::time::Deadline;
+/// use std::time::Duration;
+///
+/// #[folo::main]
+/// async fn main() {
+///     let deadline = Deadline::after(Duration::from_secs(2));
+///
+///     // The connect attempt gives up when the deadline passes, even though the connector has no
+///     // timeout of its own.
+///     let result = deadline
+///         .scope(TcpConnector::new().connect("example.com", 80))
+///         .await;
+///
+///     match result {
+///         Ok(Ok(_stream)) => println!("connected"),
+///         Ok(Err(e)) => println!("failed to connect: {e}"),
+///         Err(e) => println!("gave up: {e}"),
+///     }
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Deadline {
+    instant: Instant,
+}
+
+impl Deadline {
+    /// Creates a deadline at the specified instant.
+    pub fn at(instant: Instant) -> Self {
+        Self { instant }
+    }
+
+    /// Creates a deadline the specified duration from now. Durations beyond the range of
+    /// [`Instant`] are limited to a point in the far future.
+    pub fn after(duration: Duration) -> Self {
+        Self::after_with_clock(&Clock::new(), duration)
+    }
+
+    /// Creates a deadline the specified duration from now, according to the specified clock.
+    pub fn after_with_clock(clock: &Clock, duration: Duration) -> Self {
+        let now = clock.instant_now();
+
+        Self {
+            instant: now
+                .checked_add(duration)
+                .or_else(|| now.checked_add(FAR_FUTURE))
+                .unwrap_or(now),
+        }
+    }
+
+    /// The deadline of the code that is currently running on this thread, if it runs under one
+    /// (see [`scope()`][Self::scope]).
+    pub fn current() -> Option<Self> {
+        CURRENT_DEADLINE.get()
+    }
+
+    /// The instant of the deadline.
+    pub fn instant(&self) -> Instant {
+        self.instant
+    }
+
+    /// The time left until the deadline, or zero if it has passed.
+    pub fn remaining(&self) -> Duration {
+        self.remaining_with_clock(&Clock::new())
+    }
+
+    /// The time left until the deadline according to the specified clock, or zero if it has
+    /// passed.
+    pub fn remaining_with_clock(&self, clock: &Clock) -> Duration {
+        self.instant.saturating_duration_since(clock.instant_now())
+    }
+
+    /// Whether the deadline has passed.
+    pub fn is_expired(&self) -> bool {
+        self.remaining().is_zero()
+    }
+
+    /// Runs a future under this deadline, completing with [`DeadlineExceeded`] if the future has
+    /// not completed by the deadline or by the deadline of the enclosing scope, whichever comes
+    /// first. The future is dropped once the deadline is exceeded.
+    ///
+    /// While the future is polled, [`Deadline::current()`] returns the effective deadline. If the
+    /// future and the deadline become ready at the same time, the result of the future wins.
+    pub fn scope<F: Future>(self, future: F) -> DeadlineScope<F> {
+        self.scope_with_clock(&Clock::new(), future)
+    }
+
+    /// Runs a future under this deadline like [`scope()`][Self::scope], measuring time with the
+    /// specified clock.
+    pub fn scope_with_clock<F: Future>(self, clock: &Clock, future: F) -> DeadlineScope<F> {
+        DeadlineScope {
+            inner: future,
+            deadline: self,
+            clock: clock.clone(),
+            delay: None,
+        }
+    }
+}
+
+impl From<Instant> for Deadline {
+    fn from(instant: Instant) -> Self {
+        Self::at(instant)
+    }
+}
+
+/// The error returned by a future running under a [`Deadline`] that has passed before the future
+/// completed.
+///
+/// When converted to an [`io::Error`][crate::io::Error], this becomes an error of kind
+/// [`TimedOut`][std::io::ErrorKind::TimedOut].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, thiserror::Error)]
+#[error("deadline exceeded")]
+pub struct DeadlineExceeded;
+
+/// Runs a future under a [`Deadline`]. Created by [`Deadline::scope()`].
+#[pin_project]
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct DeadlineScope<F> {
+    #[pin]
+    inner: F,
+    deadline: Deadline,
+    clock: Clock,
+
+    // Armed on the first poll, when the deadline of the enclosing scope (if any) is known.
+    delay: Option<Delay>,
+}
+
+impl<F: Future> Future for DeadlineScope<F> {
+    type Output = Result<F::Output, DeadlineExceeded>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        if this.delay.is_none() {
+            // A nested scope cannot extend the deadline of the enclosing scope.
+            if let Some(outer) = Deadline::current() {
+                *this.deadline = (*this.deadline).min(outer);
+            }
+
+            *this.delay = Some(Delay::with_clock(
+                this.clock,
+                this.deadline.remaining_with_clock(this.clock),
+            ));
+        }
+
+        let previous = CURRENT_DEADLINE.replace(Some(*this.deadline));
+        let result = this.inner.poll(cx);
+        CURRENT_DEADLINE.set(previous);
+
+        if let Poll::Ready(result) = result {
+            return Poll::Ready(Ok(result));
+        }
+
+        let delay = this
+            .delay
+            .as_mut()
+            .expect("delay is armed on the first poll");
+
+        Pin::new(delay).poll(cx).map(|()| Err(DeadlineExceeded))
+    }
+}
+
+// Roughly 30 years, which is as good as never for any operation.
+const FAR_FUTURE: Duration = Duration::from_secs(86400 * 365 * 30);
+
+thread_local! {
+    static CURRENT_DEADLINE: Cell<Option<Deadline>> = const { Cell::new(None) };
+}
+
+#[cfg(all(test, feature = "fakes"))]
+mod tests {
+    use super::*;
+    use crate::test_rt::TestRuntime;
+    use std::future;
+
+    #[test]
+    fn exceeded_when_future_is_late() {
+        let mut runtime = TestRuntime::new();
+        let clock = runtime.clock();
+        let mut clock_control = runtime.clock_control();
+
+        let result = runtime.block_on(async move {
+            let deadline = Deadline::after_with_clock(&clock, Duration::from_millis(10));
+            let scope = deadline.scope_with_clock(&clock, future::pending::<()>());
+
+            clock_control.advance(Duration::from_millis(10));
+            scope.await
+        });
+
+        assert_eq!(result, Err(DeadlineExceeded));
+    }
+
+    #[test]
+    fn completes_in_time() {
+        let mut runtime = TestRuntime::new();
+        let clock = runtime.clock();
+
+        let result = runtime.block_on(async move {
+            Deadline::after_with_clock(&clock, Duration::from_millis(10))
+                .scope_with_clock(&clock, async { 42 })
+                .await
+        });
+
+        assert_eq!(result, Ok(42));
+    }
+
+    #[test]
+    fn nested_scope_inherits_earlier_deadline() {
+        let mut runtime = TestRuntime::new();
+        let clock = runtime.clock();
+
+        runtime.block_on(async move {
+            let outer = Deadline::after_with_clock(&clock, Duration::from_millis(10));
+            let inner = Deadline::after_with_clock(&clock, Duration::from_secs(10));
+
+            let observed = outer
+                .scope_with_clock(&clock, async {
+                    inner
+                        .scope_with_clock(&clock, async { Deadline::current() })
+                        .await
+                })
+                .await;
+
+            assert_eq!(observed, Ok(Ok(Some(outer))));
+            assert_eq!(Deadline::current(), None);
+        });
+    }
+}
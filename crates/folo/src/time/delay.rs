@@ -9,6 +9,7 @@ use negative_impl::negative_impl;
 
 use super::timers::TimerKey;
 use super::Clock;
+use crate::rt::task_trace;
 
 /// Asynchronously delays for the specified duration.
 #[derive(Debug)]
@@ -59,7 +60,7 @@ impl Future for Delay {
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let this = self.get_mut();
 
-        match this.current_timer {
+        let result = match this.current_timer {
             None if this.duration == Duration::MAX => Poll::Pending,
             None if this.duration == Duration::ZERO => Poll::Ready(()),
             None => this.register_timer(cx.waker()),
@@ -73,7 +74,22 @@ impl Future for Delay {
                 Poll::Ready(())
             }
             Some(_) => Poll::Pending,
+        };
+
+        if result.is_pending() {
+            task_trace::awaiting("timer");
         }
+
+        result
     }
 }
 
+impl Drop for Delay {
+    fn drop(&mut self) {
+        // A delay that is dropped before it finishes (e.g. because it lost a race against some
+        // other future) must not keep the waker of its task registered until the timer fires.
+        if let Some(key) = self.current_timer.take() {
+            self.clock.unregister_timer(key);
+        }
+    }
+}
@@ -42,6 +42,9 @@ pub(super) enum ErrorKind {
 
     #[error("{0}")]
     OutOfRange(Cow<'static, str>),
+
+    #[error("{0}")]
+    InvalidSchedule(Cow<'static, str>),
 }
 
 impl Error {
@@ -53,6 +56,10 @@ impl Error {
         Self::from_kind(ErrorKind::OutOfRange(message.into()))
     }
 
+    pub(super) fn invalid_schedule(message: impl Into<Cow<'static, str>>) -> Self {
+        Self::from_kind(ErrorKind::InvalidSchedule(message.into()))
+    }
+
     #[cfg(test)]
     pub(super) fn kind(&self) -> &ErrorKind {
         &self.0
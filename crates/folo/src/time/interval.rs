@@ -0,0 +1,248 @@
+// Copyright (c) Microsoft Corporation.
+
+use std::future::poll_fn;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use futures::Stream;
+use negative_impl::negative_impl;
+
+use super::timers::TimerKey;
+use super::{Clock, TIMER_RESOLUTION};
+
+/// What an [`Interval`] does when it is polled so late that one or more ticks have been missed
+/// (e.g. because the task was busy or the worker thread was overloaded).
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum MissedTickBehavior {
+    /// Delivers all the missed ticks immediately, one after another, until the interval has
+    /// caught up with its original schedule. Use this when every tick matters (e.g. each tick
+    /// accounts for one period of a rate calculation).
+    #[default]
+    Burst,
+
+    /// Delivers one tick immediately and schedules the following ticks one period apart starting
+    /// from now, shifting the schedule by the delay. Use this when the time between the ticks
+    /// matters more than their alignment (e.g. heartbeats that must not be sent back to back).
+    Delay,
+
+    /// Delivers one tick immediately and skips the other missed ticks, keeping the original
+    /// schedule. Use this when the ticks should stay aligned to the schedule but catching up is
+    /// pointless (e.g. flushing metrics or evicting expired cache entries).
+    Skip,
+}
+
+/// Creates an [`Interval`] that ticks every `period`, with the first tick one period from now.
+pub fn interval(period: Duration) -> Interval {
+    Interval::with_clock(&Clock::new(), period)
+}
+
+/// A timer that ticks on a fixed schedule, one period apart, yielding the instant each tick was
+/// scheduled for.
+///
+/// Unlike a [`PeriodicTimer`][super::PeriodicTimer], which schedules each tick one period after
+/// the previous tick was consumed, the schedule of an interval does not drift when the consumer
+/// takes time to process each tick. What happens when ticks are missed altogether is determined
+/// by the [`MissedTickBehavior`].
+///
+/// The ticks can be consumed either via [`tick()`][Self::tick] or as a [`Stream`] that never ends.
+///
+/// # Example
+///
+/// ```no_run
+/// use folo::time::{interval, MissedTickBehavior};
+/// use std::time::Duration;
+///
+/// #[folo::main]
+/// async fn main() {
+///     let mut heartbeat =
+///         interval(Duration::from_secs(5)).with_missed_tick_behavior(MissedTickBehavior::Delay);
+///
+///     for _ in 0..3 {
+///         heartbeat.tick().await;
+///         println!("still alive");
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Interval {
+    clock: Clock,
+    period: Duration,
+    missed_tick_behavior: MissedTickBehavior,
+
+    // When the next tick is due. `None` if it would be beyond the range of `Instant`, in which
+    // case the interval never ticks again.
+    next_tick: Option<Instant>,
+
+    // Currently scheduled timer for the next tick. This value is not initialized before
+    // the interval is actually polled.
+    current_timer: Option<TimerKey>,
+}
+
+#[negative_impl]
+impl !Send for Interval {}
+#[negative_impl]
+impl !Sync for Interval {}
+
+impl Interval {
+    /// Creates an interval that ticks every `period` according to the specified clock, with the
+    /// first tick one period from now. Periods shorter than the timer resolution are rounded up.
+    pub fn with_clock(clock: &Clock, period: Duration) -> Self {
+        let period = period.max(TIMER_RESOLUTION);
+
+        Self {
+            next_tick: clock.instant_now().checked_add(period),
+            clock: clock.clone(),
+            period,
+            missed_tick_behavior: MissedTickBehavior::default(),
+            current_timer: None,
+        }
+    }
+
+    /// Sets what the interval does when ticks are missed. See [`MissedTickBehavior`].
+    pub fn with_missed_tick_behavior(mut self, value: MissedTickBehavior) -> Self {
+        self.missed_tick_behavior = value;
+        self
+    }
+
+    /// The time between two ticks.
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    /// Waits for the next tick and returns the instant it was scheduled for, which is earlier than
+    /// the current instant if the tick was late.
+    pub async fn tick(&mut self) -> Instant {
+        poll_fn(|cx| self.poll_tick(cx)).await
+    }
+
+    /// Restarts the schedule, with the next tick one period from now.
+    pub fn reset(&mut self) {
+        self.unregister_timer();
+        self.next_tick = self.clock.instant_now().checked_add(self.period);
+    }
+
+    /// Polls for the next tick, registering the waker of the current task to be woken when the
+    /// tick is due.
+    pub fn poll_tick(&mut self, cx: &mut Context<'_>) -> Poll<Instant> {
+        let Some(tick) = self.next_tick else {
+            return Poll::Pending;
+        };
+
+        let now = self.clock.instant_now();
+
+        if tick > now {
+            if self.current_timer.is_none() {
+                self.current_timer = Some(self.clock.register_timer(tick, cx.waker().clone()));
+            }
+
+            return Poll::Pending;
+        }
+
+        // Unregister timer, just in case this call was explicit and not due to timers advancing.
+        self.unregister_timer();
+
+        self.next_tick = match self.missed_tick_behavior {
+            MissedTickBehavior::Burst => tick.checked_add(self.period),
+            MissedTickBehavior::Delay => now.checked_add(self.period),
+            MissedTickBehavior::Skip => {
+                let missed_periods = now.duration_since(tick).as_nanos() / self.period.as_nanos();
+
+                u32::try_from(missed_periods + 1)
+                    .ok()
+                    .and_then(|periods| self.period.checked_mul(periods))
+                    .and_then(|skipped| tick.checked_add(skipped))
+            }
+        };
+
+        Poll::Ready(tick)
+    }
+
+    fn unregister_timer(&mut self) {
+        if let Some(key) = self.current_timer.take() {
+            self.clock.unregister_timer(key);
+        }
+    }
+}
+
+impl Stream for Interval {
+    type Item = Instant;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().poll_tick(cx).map(Some)
+    }
+}
+
+impl Drop for Interval {
+    fn drop(&mut self) {
+        // An interval that is dropped between ticks must not keep the waker of its task
+        // registered until the timer fires.
+        self.unregister_timer();
+    }
+}
+
+#[cfg(all(test, feature = "fakes"))]
+mod tests {
+    use super::*;
+    use crate::test_rt::TestRuntime;
+
+    /// Consumes `count` ticks, with the clock jumping forward by `work` after the first one, and
+    /// returns the offsets of the ticks from the start, in milliseconds.
+    fn tick_offsets(behavior: MissedTickBehavior, count: usize, work: Duration) -> Vec<u128> {
+        let mut runtime = TestRuntime::new();
+        let clock = runtime.clock();
+        let mut clock_control = runtime.clock_control();
+
+        runtime.block_on(async move {
+            let start = clock.instant_now();
+            let mut interval = Interval::with_clock(&clock, Duration::from_millis(10))
+                .with_missed_tick_behavior(behavior);
+
+            let mut offsets = Vec::new();
+
+            for i in 0..count {
+                let tick = interval.tick().await;
+                offsets.push(clock.instant_now().duration_since(start).as_millis());
+                assert!(tick <= clock.instant_now());
+
+                if i == 0 {
+                    clock_control.advance(work);
+                }
+            }
+
+            offsets
+        })
+    }
+
+    #[test]
+    fn ticks_on_schedule() {
+        assert_eq!(
+            tick_offsets(MissedTickBehavior::Burst, 3, Duration::ZERO),
+            vec![10, 20, 30]
+        );
+    }
+
+    #[test]
+    fn burst_catches_up() {
+        assert_eq!(
+            tick_offsets(MissedTickBehavior::Burst, 5, Duration::from_millis(25)),
+            vec![10, 35, 35, 40, 50]
+        );
+    }
+
+    #[test]
+    fn delay_shifts_schedule() {
+        assert_eq!(
+            tick_offsets(MissedTickBehavior::Delay, 4, Duration::from_millis(25)),
+            vec![10, 35, 45, 55]
+        );
+    }
+
+    #[test]
+    fn skip_keeps_schedule() {
+        assert_eq!(
+            tick_offsets(MissedTickBehavior::Skip, 4, Duration::from_millis(25)),
+            vec![10, 35, 40, 50]
+        );
+    }
+}
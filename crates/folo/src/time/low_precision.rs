@@ -2,7 +2,7 @@ use windows::Win32::System::SystemInformation::GetTickCount64;
 
 /// A cheaper version of `Instant` that is capable of representing time with less precision. The
 /// granularity is typically around 15-20 ms, so no point trying to see differences below that.
-/// 
+///
 /// TODO: Some thread local variable we update once per tick might be even better for performance,
 /// so we can avoid the FFI call (which is fast but still expensive compared to a variable read).
 #[derive(Clone, Copy, Debug)]
@@ -25,4 +25,14 @@ impl LowPrecisionInstant {
     pub fn elapsed(&self) -> std::time::Duration {
         LowPrecisionInstant::now().duration_since(*self)
     }
+
+    /// The raw value, for storing in atomic variables.
+    pub(crate) fn as_millis(&self) -> u64 {
+        self.value
+    }
+
+    /// Restores an instant from a value returned by `as_millis()`.
+    pub(crate) fn from_millis(value: u64) -> Self {
+        Self { value }
+    }
 }
@@ -0,0 +1,119 @@
+// Copyright (c) Microsoft Corporation.
+
+use std::cell::RefCell;
+use std::time::Duration;
+
+use super::ClockControl;
+use crate::rt::yield_now;
+
+/// Pauses time on the current thread, so that tests of retry, backoff and timeout logic can run
+/// instantly and deterministically instead of waiting for real time to pass.
+///
+/// Every [`Clock`][super::Clock] created on the current thread after this call (including the ones
+/// created internally, e.g. by [`interval()`][super::interval]) observes the paused time, which
+/// starts at the current time and only moves forward via [`advance()`]. Clocks created before the
+/// call keep observing real time.
+///
+/// Pausing time that is already paused has no effect.
+///
+/// # Example
+///
+/// ```no_run
+/// use folo::time::{advance, pause, Clock, Delay};
+/// use std::time::Duration;
+///
+/// #[folo::main]
+/// async fn main() {
+///     pause();
+///
+///     let delay = Delay::with_clock(&Clock::new(), Duration::from_secs(3600));
+///
+///     // The hour passes instantly.
+///     futures::join!(delay, advance(Duration::from_secs(3600)));
+/// }
+/// ```
+pub fn pause() {
+    PAUSED.with_borrow_mut(|paused| {
+        paused.get_or_insert_with(ClockControl::starting_now);
+    });
+}
+
+/// Resumes the flow of real time on the current thread. Clocks created on the current thread
+/// after this call observe real time again, whereas the clocks created while time was paused keep
+/// observing the paused time.
+pub fn resume() {
+    PAUSED.set(None);
+}
+
+/// Whether time has been paused on the current thread via [`pause()`].
+pub fn is_paused() -> bool {
+    PAUSED.with_borrow(Option::is_some)
+}
+
+/// Advances the paused time of the current thread by `duration`, waking the tasks whose timers
+/// fire in the meantime, and then yields to let them run before returning.
+///
+/// # Panics
+///
+/// Panics if time has not been paused on the current thread via [`pause()`].
+pub async fn advance(duration: Duration) {
+    let mut clock_control = paused_clock_control()
+        .expect("time::advance() requires time to be paused via time::pause() first");
+
+    clock_control.advance(duration);
+
+    yield_now().await;
+}
+
+/// The clock control of the paused time of the current thread, if time is paused.
+pub(super) fn paused_clock_control() -> Option<ClockControl> {
+    PAUSED.with_borrow(Clone::clone)
+}
+
+thread_local! {
+    static PAUSED: RefCell<Option<ClockControl>> = const { RefCell::new(None) };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::{Clock, Delay};
+    use futures::task::noop_waker_ref;
+    use std::future::Future;
+    use std::pin::pin;
+    use std::task::Context;
+
+    #[test]
+    fn paused_clock_only_moves_on_advance() {
+        pause();
+        let clock = Clock::new();
+        let start = clock.instant_now();
+
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(clock.instant_now(), start);
+
+        paused_clock_control()
+            .unwrap()
+            .advance(Duration::from_secs(10));
+        assert_eq!(clock.instant_now(), start + Duration::from_secs(10));
+
+        resume();
+        assert!(!is_paused());
+    }
+
+    #[test]
+    fn delay_completes_on_advance() {
+        pause();
+
+        let mut cx = Context::from_waker(noop_waker_ref());
+        let mut delay = pin!(Delay::with_clock(&Clock::new(), Duration::from_secs(60)));
+        assert!(delay.as_mut().poll(&mut cx).is_pending());
+
+        paused_clock_control()
+            .unwrap()
+            .advance(Duration::from_secs(60));
+        assert!(delay.as_mut().poll(&mut cx).is_ready());
+
+        resume();
+    }
+}
@@ -0,0 +1,419 @@
+// Copyright (c) Microsoft Corporation.
+
+use std::cell::{Cell, RefCell};
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::time::{Duration, Instant, SystemTime};
+
+use futures::future::{self, AbortHandle};
+
+use super::cron::CronExpression;
+use super::{Clock, Delay, Result};
+use crate::constants::GENERAL_MILLISECONDS_BUCKETS;
+use crate::metrics::{observe, Event, EventBuilder};
+use crate::rt::{self, LocalJoinHandle};
+
+/// Describes when a scheduled job runs and what happens if it is still running when it is due to
+/// run again. See [`schedule()`].
+#[derive(Clone, Debug)]
+pub struct Schedule {
+    kind: ScheduleKind,
+    overlap_policy: OverlapPolicy,
+}
+
+#[derive(Clone, Debug)]
+enum ScheduleKind {
+    Every(Duration),
+    Cron(CronExpression),
+}
+
+impl Schedule {
+    /// Runs the job repeatedly with a fixed period between runs, starting one period from now.
+    ///
+    /// The runs are due at whole periods from the start of the schedule, so the schedule does not
+    /// drift even if each run starts slightly later than it was due.
+    pub fn every(period: Duration) -> Self {
+        Self {
+            kind: ScheduleKind::Every(period),
+            overlap_policy: OverlapPolicy::default(),
+        }
+    }
+
+    /// Runs the job at the times matching a 5-field cron expression, evaluated in UTC. For example,
+    /// `"*/15 * * * *"` runs the job every 15 minutes and `"30 2 * * 0"` runs it at 02:30 every
+    /// Sunday.
+    ///
+    /// Each field supports `*`, single values, ranges (`a-b`), steps (`*/n`, `a-b/n`) and lists.
+    pub fn cron(expression: &str) -> Result<Self> {
+        Ok(Self {
+            kind: ScheduleKind::Cron(CronExpression::parse(expression)?),
+            overlap_policy: OverlapPolicy::default(),
+        })
+    }
+
+    /// What to do if the previous run of the job is still in progress when the next run is due.
+    pub fn overlap_policy(mut self, value: OverlapPolicy) -> Self {
+        self.overlap_policy = value;
+        self
+    }
+
+    /// Returns when the next run is due, given when the previous run was due (or when the schedule
+    /// started, before the first run). Returns `None` if the job will never run again.
+    fn next_deadline(&self, previous: Instant, clock: &Clock) -> Option<Instant> {
+        match &self.kind {
+            // Measured from the previous deadline instead of from now, so the time it takes us to
+            // wake up for each run does not add up over the runs.
+            ScheduleKind::Every(period) => previous.checked_add(*period),
+            ScheduleKind::Cron(expression) => {
+                let now = clock.now();
+                let next = expression.next_after(now)?;
+
+                clock
+                    .instant_now()
+                    .checked_add(next.duration_since(now).unwrap_or_default())
+            }
+        }
+    }
+}
+
+/// What to do if the previous run of a scheduled job is still in progress when the next run is due.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum OverlapPolicy {
+    /// The new run is skipped.
+    #[default]
+    Skip,
+
+    /// The new run starts as soon as the previous run completes. If multiple runs are due while
+    /// the previous run is in progress, they all execute one after another.
+    Queue,
+
+    /// The previous run is aborted and the new run starts immediately.
+    Replace,
+}
+
+/// Runs a job on a schedule on the current async worker thread. The task factory is called to
+/// create the future for each run of the job.
+///
+/// The job keeps running until stopped via the returned handle, even if the handle is dropped.
+///
+/// # Panics
+///
+/// Panics if the current thread is not an async worker thread owned by a Folo runtime.
+pub fn schedule<FN, F>(schedule: Schedule, task_factory: FN) -> ScheduledJob
+where
+    FN: Fn() -> F + 'static,
+    F: Future<Output = ()> + 'static,
+{
+    let state = Rc::new(JobState::new(schedule.overlap_policy));
+
+    let driver = rt::spawn(drive(
+        schedule,
+        Rc::new(task_factory),
+        Rc::clone(&state),
+        Clock::new(),
+        |run| _ = rt::spawn(run),
+    ));
+
+    ScheduledJob { state, driver }
+}
+
+/// Controls a job started via [`schedule()`] and provides information about its runs.
+#[derive(Debug)]
+pub struct ScheduledJob {
+    state: Rc<JobState>,
+    driver: LocalJoinHandle<()>,
+}
+
+impl ScheduledJob {
+    /// Stops the job. Any run in progress is aborted and no new runs are started.
+    pub fn stop(&self) {
+        self.driver.abort();
+
+        if let Some(current_run) = self.state.current_run.borrow_mut().take() {
+            current_run.abort();
+        }
+    }
+
+    /// The number of runs that have been started.
+    pub fn runs(&self) -> u64 {
+        self.state.runs.get()
+    }
+
+    /// When the most recent run was started.
+    pub fn last_run(&self) -> Option<SystemTime> {
+        self.state.last_run_started.get()
+    }
+
+    /// How long the most recently completed run took.
+    pub fn last_duration(&self) -> Option<Duration> {
+        self.state.last_run_duration.get()
+    }
+}
+
+#[derive(Debug)]
+struct JobState {
+    overlap_policy: OverlapPolicy,
+
+    running: Cell<bool>,
+
+    // Number of runs waiting for the current run to complete (with `OverlapPolicy::Queue`).
+    queued: Cell<usize>,
+
+    // Incremented whenever a new run task is started. A run task only updates `running` when it
+    // ends if it is still the latest one, so a replaced run does not mark its successor finished.
+    generation: Cell<u64>,
+
+    current_run: RefCell<Option<AbortHandle>>,
+
+    runs: Cell<u64>,
+    last_run_started: Cell<Option<SystemTime>>,
+    last_run_duration: Cell<Option<Duration>>,
+}
+
+impl JobState {
+    fn new(overlap_policy: OverlapPolicy) -> Self {
+        Self {
+            overlap_policy,
+            running: Cell::new(false),
+            queued: Cell::new(0),
+            generation: Cell::new(0),
+            current_run: RefCell::new(None),
+            runs: Cell::new(0),
+            last_run_started: Cell::new(None),
+            last_run_duration: Cell::new(None),
+        }
+    }
+}
+
+// Starts a task that executes a run of the job. Injected so the scheduling logic can be tested on
+// the test runtime.
+type SpawnRun = fn(Pin<Box<dyn Future<Output = ()>>>);
+
+async fn drive<FN, F>(
+    schedule: Schedule,
+    task_factory: Rc<FN>,
+    state: Rc<JobState>,
+    clock: Clock,
+    spawn_run: SpawnRun,
+) where
+    FN: Fn() -> F + 'static,
+    F: Future<Output = ()> + 'static,
+{
+    let mut deadline = clock.instant_now();
+
+    while let Some(next_deadline) = schedule.next_deadline(deadline, &clock) {
+        deadline = next_deadline;
+
+        let delay = deadline.saturating_duration_since(clock.instant_now());
+        Delay::with_clock(&clock, delay).await;
+
+        if state.running.get() {
+            match state.overlap_policy {
+                OverlapPolicy::Skip => {
+                    observe!(RUNS_SKIPPED.observe_unit());
+                    continue;
+                }
+                OverlapPolicy::Queue => {
+                    observe!(RUNS_QUEUED.observe_unit());
+                    state.queued.set(state.queued.get() + 1);
+                    continue;
+                }
+                OverlapPolicy::Replace => {
+                    observe!(RUNS_REPLACED.observe_unit());
+
+                    if let Some(current_run) = state.current_run.borrow_mut().take() {
+                        current_run.abort();
+                    }
+                }
+            }
+        }
+
+        start_run(&state, &task_factory, &clock, spawn_run);
+    }
+}
+
+fn start_run<FN, F>(state: &Rc<JobState>, task_factory: &Rc<FN>, clock: &Clock, spawn_run: SpawnRun)
+where
+    FN: Fn() -> F + 'static,
+    F: Future<Output = ()> + 'static,
+{
+    let generation = state.generation.get() + 1;
+    state.generation.set(generation);
+    state.running.set(true);
+
+    let run_state = Rc::clone(state);
+    let task_factory = Rc::clone(task_factory);
+    let clock = clock.clone();
+
+    let (run, abort_handle) = future::abortable(async move {
+        // Whether we complete or are aborted, the job is no longer running after this.
+        let state = scopeguard::guard(run_state, move |state| {
+            if state.generation.get() == generation {
+                state.running.set(false);
+                state.queued.set(0);
+            }
+        });
+
+        loop {
+            state.runs.set(state.runs.get() + 1);
+            state.last_run_started.set(Some(clock.now()));
+            observe!(RUNS.observe_unit());
+
+            let started = clock.instant_now();
+            task_factory().await;
+            let duration = clock.instant_now().duration_since(started);
+
+            state.last_run_duration.set(Some(duration));
+            observe!(RUN_DURATION.observe_millis(duration));
+
+            match state.queued.get() {
+                0 => break,
+                queued => state.queued.set(queued - 1),
+            }
+        }
+    });
+
+    spawn_run(Box::pin(async move {
+        // If the run is aborted, there is nothing to report.
+        _ = run.await;
+    }));
+
+    *state.current_run.borrow_mut() = Some(abort_handle);
+}
+
+thread_local! {
+    static RUNS: Event = EventBuilder::new("time_scheduled_job_runs")
+        .build();
+
+    static RUN_DURATION: Event = EventBuilder::new("time_scheduled_job_duration_millis")
+        .buckets(GENERAL_MILLISECONDS_BUCKETS)
+        .build();
+
+    static RUNS_SKIPPED: Event = EventBuilder::new("time_scheduled_job_runs_skipped")
+        .build();
+
+    static RUNS_QUEUED: Event = EventBuilder::new("time_scheduled_job_runs_queued")
+        .build();
+
+    static RUNS_REPLACED: Event = EventBuilder::new("time_scheduled_job_runs_replaced")
+        .build();
+}
+
+#[cfg(all(test, feature = "fakes"))]
+mod tests {
+    use super::*;
+    use crate::test_rt::{self, TestRuntime};
+
+    /// Drives a job that is due every 10 ms but takes 25 ms per run for 55 ms and returns the
+    /// offsets of the starts of the runs from the start, in milliseconds, together with the state
+    /// of the job.
+    fn run_starts(overlap_policy: OverlapPolicy) -> (Vec<u128>, Rc<JobState>) {
+        let mut runtime = TestRuntime::new();
+        let clock = runtime.clock();
+
+        runtime.block_on(async move {
+            let start = clock.instant_now();
+            let starts = Rc::new(RefCell::new(Vec::new()));
+            let state = Rc::new(JobState::new(overlap_policy));
+
+            let task_factory = {
+                let clock = clock.clone();
+                let starts = Rc::clone(&starts);
+
+                move || {
+                    let offset = clock.instant_now().duration_since(start).as_millis();
+                    starts.borrow_mut().push(offset);
+
+                    Delay::with_clock(&clock, Duration::from_millis(25))
+                }
+            };
+
+            _ = test_rt::spawn(drive(
+                Schedule::every(Duration::from_millis(10)).overlap_policy(overlap_policy),
+                Rc::new(task_factory),
+                Rc::clone(&state),
+                clock.clone(),
+                |run| _ = test_rt::spawn(run),
+            ));
+
+            Delay::with_clock(&clock, Duration::from_millis(55)).await;
+
+            (starts.take(), state)
+        })
+    }
+
+    #[test]
+    fn skip_drops_runs_due_while_running() {
+        let (starts, state) = run_starts(OverlapPolicy::Skip);
+
+        assert_eq!(starts, vec![10, 40]);
+        assert_eq!(state.runs.get(), 2);
+        assert_eq!(
+            state.last_run_duration.get(),
+            Some(Duration::from_millis(25))
+        );
+    }
+
+    #[test]
+    fn queue_runs_back_to_back() {
+        let (starts, state) = run_starts(OverlapPolicy::Queue);
+
+        // The runs due at 20 and 30 wait for the first run, then execute one after another.
+        assert_eq!(starts, vec![10, 35]);
+        assert_eq!(state.queued.get(), 3);
+    }
+
+    #[test]
+    fn late_run_does_not_shift_schedule() {
+        let mut runtime = TestRuntime::new();
+        let clock = runtime.clock();
+        let mut clock_control = runtime.clock_control();
+
+        let starts = runtime.block_on(async move {
+            let start = clock.instant_now();
+            let starts = Rc::new(RefCell::new(Vec::new()));
+
+            let task_factory = {
+                let clock = clock.clone();
+                let starts = Rc::clone(&starts);
+
+                move || {
+                    let offset = clock.instant_now().duration_since(start).as_millis();
+                    starts.borrow_mut().push(offset);
+
+                    future::ready(())
+                }
+            };
+
+            _ = test_rt::spawn(drive(
+                Schedule::every(Duration::from_millis(10)),
+                Rc::new(task_factory),
+                Rc::new(JobState::new(OverlapPolicy::default())),
+                clock.clone(),
+                |run| _ = test_rt::spawn(run),
+            ));
+
+            // The clock jumps past the first deadline, so the first run starts 3 ms late.
+            Delay::with_clock(&clock, Duration::from_millis(5)).await;
+            clock_control.advance(Duration::from_millis(8));
+
+            Delay::with_clock(&clock, Duration::from_millis(42)).await;
+
+            starts.take()
+        });
+
+        assert_eq!(starts, vec![13, 20, 30, 40, 50]);
+    }
+
+    #[test]
+    fn replace_aborts_previous_run() {
+        let (starts, state) = run_starts(OverlapPolicy::Replace);
+
+        assert_eq!(starts, vec![10, 20, 30, 40, 50]);
+        assert!(state.running.get());
+
+        // No run ever got to complete.
+        assert_eq!(state.last_run_duration.get(), None);
+    }
+}
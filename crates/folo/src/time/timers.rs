@@ -1,28 +1,30 @@
 // Copyright (c) Microsoft Corporation.
 
 use std::cell::RefCell;
-use std::collections::BTreeMap;
 use std::mem;
 use std::task::Waker;
 use std::time::{Duration, Instant};
 
+use crate::metrics::{observe, Event, EventBuilder, Magnitude};
+use crate::rt::flight_recorder::{self, FlightEvent};
+#[cfg(feature = "runtime-tracing")]
+use tracing::{event, Level};
+
 /// Unique identifier for a timer.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub(super) struct TimerKey {
     tick: Instant,
 
-    /// Discriminator that ensures two timer IDs with the same instant can be created.
-    discriminator: u32,
+    /// Index of the timer in the timer storage, which is reused once the timer fires or is
+    /// unregistered.
+    index: u32,
+
+    /// Distinguishes the timers that have used the same index over time, so a stale key (e.g. of
+    /// a timer that already fired) never unregisters a different timer.
+    generation: u32,
 }
 
 impl TimerKey {
-    fn new(tick: Instant, id: u32) -> TimerKey {
-        TimerKey {
-            tick,
-            discriminator: id,
-        }
-    }
-
     /// Determines when the timer will fire.
     pub fn tick(&self) -> Instant {
         self.tick
@@ -41,50 +43,202 @@ pub(crate) fn advance_local_timers(now: Instant) {
     LOCAL_TIMERS.with_borrow_mut(|timer_manager| timer_manager.advance_timers(now));
 }
 
-/// The management of one-shot timers, inspired by [glommio runtime](https://github.com/DataDog/glommio/blob/d3f6e7a2ee7fb071ada163edcf90fc3286424c31/glommio/src/reactor.rs#L80)
+/// Returns the instant at which the next thread-local timer is due, if any.
+pub(crate) fn next_local_timer() -> Option<Instant> {
+    LOCAL_TIMERS.with_borrow(Timers::next_tick)
+}
+
+// Each level of the wheel has 64 slots, so the occupied slots of a level fit into a u64 bitmap.
+const SLOT_BITS: u32 = 6;
+const SLOTS_PER_LEVEL: usize = 1 << SLOT_BITS;
+const SLOT_MASK: u64 = SLOTS_PER_LEVEL as u64 - 1;
+
+// With 6 levels and a resolution of 1 ms, the wheel covers a range of 64^6 ms (about 2 years).
+// Timers further in the future are stored in the last level and cascaded again once their slot
+// comes around, until they are in range.
+const LEVELS: usize = 6;
+const MAX_RANGE_TICKS: u64 = 1 << (SLOT_BITS * LEVELS as u32);
+
+/// The management of one-shot timers, as a hierarchical timing wheel inspired by the one in the
+/// [Tokio runtime](https://github.com/tokio-rs/tokio), so that arming and cancelling a timer is
+/// O(1) regardless of how many timers exist (e.g. one idle timeout per connection for hundreds of
+/// thousands of connections).
+///
+/// Time is divided into ticks of [`TIMER_RESOLUTION`], counted from the creation of the wheel.
+/// Level 0 has one slot per tick for the next 64 ticks, level 1 has one slot per 64 ticks for
+/// the next 64 * 64 ticks and so on. As time advances, the timers in a slot of a higher level are
+/// cascaded into the lower levels, until they reach the tick they are due in. Timers that are due
+/// in the current tick are kept aside and fire as soon as their exact instant has passed, so the
+/// timers fire at the same instants as if they were kept in a sorted collection.
 ///
 /// The timers managed by this collection are one-shot, meaning after they fire they won't be fired again.
 #[derive(Debug)]
 pub(super) struct Timers {
-    /// An ordered map of registered timers.
-    ///
-    /// Timers are in the order in which they fire.
-    /// The [`Waker`] represents the task awaiting the timer.
-    wakers: BTreeMap<TimerKey, Waker>,
-    last_discriminator: u32,
+    /// The instant of tick 0.
+    origin: Instant,
+
+    /// The last tick that has been processed. The timers due in or before this tick are in
+    /// `current`, all others are in the wheel.
+    elapsed: u64,
+
+    levels: [Level; LEVELS],
+
+    /// Timers whose tick has been processed but whose exact instant may not have passed yet.
+    current: Vec<u32>,
+
+    /// Storage for all timers, indexed by `TimerKey::index`. Free entries are listed in `free`.
+    entries: Vec<Entry>,
+    free: Vec<u32>,
+
+    len: usize,
+}
+
+#[derive(Debug)]
+struct Level {
+    /// Bit N is set if slot N contains at least one timer.
+    occupied: u64,
+
+    /// The indexes of the timers in each slot.
+    slots: [Vec<u32>; SLOTS_PER_LEVEL],
+}
+
+#[derive(Debug)]
+struct Entry {
+    when: Instant,
+    tick: u64,
+    generation: u32,
+
+    /// The task awaiting the timer. `None` if the entry is free.
+    waker: Option<Waker>,
+
+    /// Where the index of the entry is stored, to remove it in O(1).
+    location: Location,
+    position: usize,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Location {
+    Current,
+    Wheel { level: usize, slot: usize },
+}
+
+/// The next slot that is due to be processed.
+#[derive(Debug)]
+struct Expiration {
+    level: usize,
+    slot: usize,
+    deadline: u64,
 }
 
 impl Timers {
     pub fn new() -> Timers {
         Timers {
-            last_discriminator: 0,
-            wakers: BTreeMap::new(),
+            origin: Instant::now(),
+            elapsed: 0,
+            levels: std::array::from_fn(|_| Level::new()),
+            current: Vec::new(),
+            entries: Vec::new(),
+            free: Vec::new(),
+            len: 0,
         }
     }
 
     #[cfg(test)]
     pub fn len(&self) -> usize {
-        self.wakers.len()
+        self.len
     }
 
     #[cfg(test)]
     fn contains(&self, id: TimerKey) -> bool {
-        self.wakers.contains_key(&id)
+        self.entry_for(id).is_some()
     }
 
     pub fn register(&mut self, when: Instant, waker: Waker) -> TimerKey {
-        // We can wrap discriminator because it's only used to distinguish timers with the same instant
-        // and actual value can start from 0 again.
-        self.last_discriminator = self.last_discriminator.wrapping_add(1);
-        let key = TimerKey::new(when, self.last_discriminator);
-
-        self.wakers.insert(key, waker);
+        let tick = self.tick_for(when);
+
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                self.entries.push(Entry {
+                    when,
+                    tick,
+                    generation: 0,
+                    waker: None,
+                    location: Location::Current,
+                    position: 0,
+                });
+
+                u32::try_from(self.entries.len() - 1).expect("more than u32::MAX timers registered")
+            }
+        };
+
+        let entry = &mut self.entries[index as usize];
+
+        // We can wrap the generation because it only needs to differ from the recent keys that
+        // used the same index and the actual value can start from 0 again.
+        entry.generation = entry.generation.wrapping_add(1);
+        entry.when = when;
+        entry.tick = tick;
+        entry.waker = Some(waker);
+
+        let key = TimerKey {
+            tick: when,
+            index,
+            generation: entry.generation,
+        };
+
+        #[cfg(feature = "runtime-tracing")]
+        event!(
+            Level::TRACE,
+            message = "timer registered",
+            timer = index,
+            generation = key.generation,
+            ?when
+        );
+
+        self.insert(index);
+        self.len += 1;
+
+        observe!(ACTIVE_TIMERS.observe(self.len as Magnitude));
 
         key
     }
 
     pub fn unregister(&mut self, id: TimerKey) {
-        self.wakers.remove(&id);
+        if self.entry_for(id).is_none() {
+            // The timer has already fired or been unregistered.
+            return;
+        }
+
+        #[cfg(feature = "runtime-tracing")]
+        event!(
+            Level::TRACE,
+            message = "timer canceled",
+            timer = id.index,
+            generation = id.generation
+        );
+
+        self.remove(id.index);
+        self.release(id.index);
+    }
+
+    /// Determines when the earliest registered timer fires, if any timer is registered.
+    pub fn next_tick(&self) -> Option<Instant> {
+        let earliest = |indexes: &[u32]| {
+            indexes
+                .iter()
+                .map(|&index| self.entries[index as usize].when)
+                .min()
+        };
+
+        if !self.current.is_empty() {
+            return earliest(&self.current);
+        }
+
+        // The slot that expires next holds the earliest timers, as all the timers in other slots
+        // are due in later ticks.
+        let expiration = self.next_expiration()?;
+        earliest(&self.levels[expiration.level].slots[expiration.slot])
     }
 
     /// Advance timers that are ready to be woken.
@@ -92,20 +246,219 @@ impl Timers {
     /// Later, the signature of this method can be easily expanded to return more
     /// information about the timers that fired and when the next timer fires.
     pub fn advance_timers(&mut self, now: Instant) {
-        // We are adding 1ns to the instant to ensure that even timers whose deadline is the current
-        // instant are advanced. This is required because of the way BTreeMap::split_off works; it does
-        // not include the keys that are equal to the split key. Adding 1ns to the value makes this work.
-        let adjusted_now = now.checked_add(Duration::from_nanos(1)).unwrap_or(now);
+        let target = self.tick_for(now);
+
+        // Process all the slots due by the target tick, in order. The timers in a slot are either
+        // due in the tick of the slot (for level 0) or cascaded closer to their tick.
+        while let Some(expiration) = self.next_expiration() {
+            if expiration.deadline > target {
+                break;
+            }
+
+            self.elapsed = expiration.deadline;
 
-        // Split timers into ready and pending timers.
-        let pending = self.wakers.split_off(&TimerKey::new(adjusted_now, 0));
-        let ready = mem::replace(&mut self.wakers, pending);
+            let level = &mut self.levels[expiration.level];
+            level.occupied &= !(1 << expiration.slot);
+            let indexes = mem::take(&mut level.slots[expiration.slot]);
+
+            for index in indexes {
+                self.insert(index);
+            }
+        }
 
-        // Invoke the wakers for timers that ticked.
-        for (_, waker) in ready {
+        self.elapsed = self.elapsed.max(target);
+
+        self.fire_current(now);
+    }
+
+    /// Wakes the tasks awaiting the timers that are due in the current tick whose instant has
+    /// passed.
+    fn fire_current(&mut self, now: Instant) {
+        let mut position = 0;
+
+        while position < self.current.len() {
+            let index = self.current[position];
+
+            if self.entries[index as usize].when > now {
+                position += 1;
+                continue;
+            }
+
+            // This moves another timer into this position, so we do not advance the position.
+            self.remove(index);
+
+            let entry = &self.entries[index as usize];
+            let latency = now.saturating_duration_since(entry.when);
+            observe!(FIRE_LATENCY.observe(latency.as_micros() as Magnitude));
+            flight_recorder::record(FlightEvent::TimerFired { latency });
+
+            #[cfg(feature = "runtime-tracing")]
+            event!(
+                Level::TRACE,
+                message = "timer fired",
+                timer = index,
+                generation = entry.generation,
+                ?latency
+            );
+
+            let waker = self.release(index);
             waker.wake();
         }
     }
+
+    /// Places a timer into the wheel, or aside into `current` if its tick has been processed.
+    fn insert(&mut self, index: u32) {
+        let tick = self.entries[index as usize].tick;
+
+        let (location, indexes) = if tick <= self.elapsed {
+            (Location::Current, &mut self.current)
+        } else {
+            let level = level_for(self.elapsed, tick);
+            let slot = slot_for(tick, level);
+
+            let level_data = &mut self.levels[level];
+            level_data.occupied |= 1 << slot;
+
+            (Location::Wheel { level, slot }, &mut level_data.slots[slot])
+        };
+
+        indexes.push(index);
+
+        let entry = &mut self.entries[index as usize];
+        entry.location = location;
+        entry.position = indexes.len() - 1;
+    }
+
+    /// Removes a timer from the slot (or `current`) it is in.
+    fn remove(&mut self, index: u32) {
+        let entry = &self.entries[index as usize];
+        let position = entry.position;
+
+        let indexes = match entry.location {
+            Location::Current => &mut self.current,
+            Location::Wheel { level, slot } => {
+                let level_data = &mut self.levels[level];
+                let indexes = &mut level_data.slots[slot];
+
+                if indexes.len() == 1 {
+                    level_data.occupied &= !(1 << slot);
+                }
+
+                indexes
+            }
+        };
+
+        indexes.swap_remove(position);
+
+        // The last timer of the slot has taken the place of the removed one.
+        if let Some(&moved) = indexes.get(position) {
+            self.entries[moved as usize].position = position;
+        }
+    }
+
+    /// Frees the storage entry of a timer that is no longer in any slot.
+    fn release(&mut self, index: u32) -> Waker {
+        self.free.push(index);
+        self.len -= 1;
+
+        self.entries[index as usize]
+            .waker
+            .take()
+            .expect("only registered timers are released")
+    }
+
+    fn entry_for(&self, id: TimerKey) -> Option<&Entry> {
+        self.entries
+            .get(id.index as usize)
+            .filter(|entry| entry.generation == id.generation && entry.waker.is_some())
+    }
+
+    fn next_expiration(&self) -> Option<Expiration> {
+        // The slots of lower levels always expire before those of higher levels.
+        self.levels
+            .iter()
+            .enumerate()
+            .find_map(|(level, data)| data.next_expiration(level, self.elapsed))
+    }
+
+    fn tick_for(&self, instant: Instant) -> u64 {
+        let since_origin = instant.saturating_duration_since(self.origin);
+        (since_origin.as_nanos() / TIMER_RESOLUTION.as_nanos()) as u64
+    }
+}
+
+impl Level {
+    fn new() -> Self {
+        Self {
+            occupied: 0,
+            slots: std::array::from_fn(|_| Vec::new()),
+        }
+    }
+
+    fn next_expiration(&self, level: usize, elapsed: u64) -> Option<Expiration> {
+        if self.occupied == 0 {
+            return None;
+        }
+
+        let slot_range = slot_range(level);
+        let level_range = slot_range * SLOTS_PER_LEVEL as u64;
+
+        // Search for the next occupied slot, starting from the slot that contains the elapsed tick
+        // and wrapping around.
+        let elapsed_slot = (elapsed / slot_range) & SLOT_MASK;
+        let occupied = self.occupied.rotate_right(elapsed_slot as u32);
+        let slot = (occupied.trailing_zeros() as u64 + elapsed_slot) & SLOT_MASK;
+
+        let level_start = elapsed & !(level_range - 1);
+        let mut deadline = level_start + slot * slot_range;
+
+        if deadline <= elapsed {
+            // The slot is "before" the elapsed tick, which happens for timers so far in the
+            // future that they wrapped around the last level. They are due one rotation later.
+            deadline += level_range;
+        }
+
+        Some(Expiration {
+            level,
+            slot: slot as usize,
+            deadline,
+        })
+    }
+}
+
+/// The number of ticks covered by one slot of a level.
+fn slot_range(level: usize) -> u64 {
+    1 << (SLOT_BITS * level as u32)
+}
+
+/// The level a timer belongs to, which is determined by the most significant bit in which its
+/// tick differs from the elapsed tick.
+fn level_for(elapsed: u64, tick: u64) -> usize {
+    let mut masked = (elapsed ^ tick) | SLOT_MASK;
+
+    if masked >= MAX_RANGE_TICKS {
+        masked = MAX_RANGE_TICKS - 1;
+    }
+
+    let significant = 63 - masked.leading_zeros();
+    (significant / SLOT_BITS) as usize
+}
+
+fn slot_for(tick: u64, level: usize) -> usize {
+    ((tick >> (SLOT_BITS * level as u32)) & SLOT_MASK) as usize
+}
+
+const ACTIVE_TIMERS_BUCKETS: &[Magnitude] = &[0, 10, 100, 1000, 10000, 100000];
+const FIRE_LATENCY_MICROS_BUCKETS: &[Magnitude] = &[0, 100, 1000, 5000, 20000];
+
+thread_local! {
+    static ACTIVE_TIMERS: Event = EventBuilder::new("time_active_timers")
+        .buckets(ACTIVE_TIMERS_BUCKETS)
+        .build();
+
+    static FIRE_LATENCY: Event = EventBuilder::new("time_timer_fire_latency_micros")
+        .buckets(FIRE_LATENCY_MICROS_BUCKETS)
+        .build();
 }
 
 #[cfg(test)]
@@ -197,6 +550,113 @@ mod tests {
         assert!(!timers.contains(id));
     }
 
+    #[test]
+    fn cascades_distant_timers() {
+        let mut timers = Timers::new();
+        let origin = timers.origin;
+
+        // These land in different levels of the wheel and must fire in order as time advances.
+        let delays = [
+            Duration::from_millis(70),
+            Duration::from_secs(5),
+            Duration::from_secs(600),
+        ];
+
+        for delay in delays {
+            timers.register(origin + delay, noop_waker());
+        }
+
+        for (fired, delay) in delays.into_iter().enumerate() {
+            timers.advance_timers(origin + delay - Duration::from_nanos(1));
+            assert_eq!(timers.len(), delays.len() - fired);
+
+            timers.advance_timers(origin + delay);
+            assert_eq!(timers.len(), delays.len() - fired - 1);
+        }
+    }
+
+    #[test]
+    fn beyond_wheel_range() {
+        let mut timers = Timers::new();
+        let origin = timers.origin;
+
+        // Further in the future than the range of the wheel, so it wraps around the last level.
+        let when = origin + Duration::from_secs(3 * 365 * 24 * 60 * 60);
+        timers.register(when, noop_waker());
+        assert_eq!(timers.next_tick(), Some(when));
+
+        timers.advance_timers(origin + Duration::from_secs(2 * 365 * 24 * 60 * 60));
+        assert_eq!(timers.len(), 1);
+        assert_eq!(timers.next_tick(), Some(when));
+
+        timers.advance_timers(when);
+        assert_eq!(timers.len(), 0);
+    }
+
+    #[test]
+    fn next_tick_is_exact() {
+        let mut timers = Timers::new();
+        let origin = timers.origin;
+
+        let early = origin + Duration::from_micros(5_300);
+        let late = origin + Duration::from_micros(5_700);
+
+        timers.register(late, noop_waker());
+        timers.register(early, noop_waker());
+        assert_eq!(timers.next_tick(), Some(early));
+
+        // Both are due in the same tick but only the early one has passed.
+        timers.advance_timers(early);
+        assert_eq!(timers.len(), 1);
+        assert_eq!(timers.next_tick(), Some(late));
+
+        timers.advance_timers(late);
+        assert_eq!(timers.len(), 0);
+        assert_eq!(timers.next_tick(), None);
+    }
+
+    #[test]
+    fn stale_key_does_not_unregister_reused_entry() {
+        let mut timers = Timers::new();
+        let origin = timers.origin;
+
+        let fired = timers.register(origin + Duration::from_millis(1), noop_waker());
+        timers.advance_timers(origin + Duration::from_millis(1));
+        assert!(!timers.contains(fired));
+
+        // The new timer reuses the storage of the fired one.
+        let current = timers.register(origin + Duration::from_millis(10), noop_waker());
+
+        timers.unregister(fired);
+        assert!(timers.contains(current));
+
+        timers.unregister(current);
+        assert_eq!(timers.len(), 0);
+    }
+
+    #[test]
+    fn many_timers() {
+        let mut timers = Timers::new();
+        let origin = timers.origin;
+
+        let keys: Vec<_> = (0..10_000_u64)
+            .map(|i| timers.register(origin + Duration::from_millis(i * 7), noop_waker()))
+            .collect();
+
+        // Cancel every other timer.
+        for key in keys.iter().step_by(2) {
+            timers.unregister(*key);
+        }
+
+        assert_eq!(timers.len(), 5_000);
+
+        timers.advance_timers(origin + Duration::from_millis(35_000));
+        assert_eq!(timers.len(), 2_500);
+
+        timers.advance_timers(origin + Duration::from_millis(70_000));
+        assert_eq!(timers.len(), 0);
+    }
+
     fn timers_len() -> usize {
         LOCAL_TIMERS.with_borrow(Timers::len)
     }
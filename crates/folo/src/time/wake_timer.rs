@@ -0,0 +1,269 @@
+// Copyright (c) Microsoft Corporation.
+
+use std::time::{Duration, Instant};
+
+use negative_impl::negative_impl;
+use windows::core::PCWSTR;
+use windows::Win32::Foundation::HANDLE;
+use windows::Win32::Media::{timeBeginPeriod, timeEndPeriod};
+use windows::Win32::System::Threading::{
+    CancelWaitableTimer, CreateWaitableTimerExW, SetWaitableTimer,
+    CREATE_WAITABLE_TIMER_HIGH_RESOLUTION, TIMER_ALL_ACCESS,
+};
+
+use crate::metrics::{observe, Event, EventBuilder};
+use crate::windows::OwnedHandle;
+
+/// The period of the system timer unless some process requests a higher resolution. Waits for I/O
+/// completions are quantized to this period, so without help a 1 ms sleep takes up to ~15.6 ms.
+const DEFAULT_SYSTEM_TIMER_PERIOD: Duration = Duration::from_micros(15_625);
+
+/// Ends the wait of an async worker thread for I/O completions when the next timer of the thread
+/// is due, so sleeps and timeouts fire on time instead of being quantized to the period of the
+/// system timer.
+///
+/// If the operating system supports high-resolution waitable timers, the worker waits for I/O in
+/// an alertable state and a high-resolution timer queues an APC to the thread when the next timer
+/// is due, which ends the wait with sub-millisecond accuracy. Otherwise, the wait is shortened to
+/// the whole milliseconds until the next timer is due and the resolution of the system timer is
+/// raised to 1 ms for as long as short timers are pending.
+#[derive(Debug)]
+pub(crate) struct WakeTimer {
+    // `None` if high-resolution waitable timers are not supported (before Windows 10 1803).
+    timer: Option<OwnedHandle<HANDLE>>,
+
+    // Whether the timer may still queue an APC for a previous wait.
+    armed: bool,
+
+    // Held while we rely on a raised system timer resolution for a pending timer.
+    resolution_request: Option<TimerResolutionRequest>,
+}
+
+impl WakeTimer {
+    pub(crate) fn new() -> Self {
+        // SAFETY: All we need to be concerned about is passing in valid arguments, which we do.
+        let timer = unsafe {
+            CreateWaitableTimerExW(
+                None,
+                PCWSTR::null(),
+                CREATE_WAITABLE_TIMER_HIGH_RESOLUTION,
+                TIMER_ALL_ACCESS.0,
+            )
+        };
+
+        Self {
+            // Waitable timers are safe to close from any thread, as required by OwnedHandle.
+            timer: timer.ok().map(OwnedHandle::from),
+            armed: false,
+            resolution_request: None,
+        }
+    }
+
+    /// Prepares for the thread to wait for I/O completions for up to `max_wait_ms` milliseconds,
+    /// given the instant at which the next timer of the thread is due.
+    ///
+    /// Returns the wait time to use and whether the wait must be alertable.
+    pub(crate) fn prepare_wait(
+        &mut self,
+        now: Instant,
+        next_timer: Option<Instant>,
+        max_wait_ms: u32,
+    ) -> (u32, bool) {
+        let max_wait = Duration::from_millis(u64::from(max_wait_ms));
+
+        let until_next_timer = match next_timer {
+            Some(next_timer) if next_timer.saturating_duration_since(now) < max_wait => {
+                next_timer.saturating_duration_since(now)
+            }
+            _ => {
+                // No timer is due before the wait ends on its own.
+                self.disarm();
+                self.resolution_request = None;
+                return (max_wait_ms, false);
+            }
+        };
+
+        if until_next_timer.is_zero() {
+            return (0, false);
+        }
+
+        if let Some(timer) = &self.timer {
+            // Negative due times are relative, in units of 100 nanoseconds.
+            let due_time = -i64::try_from(until_next_timer.as_nanos().div_ceil(100))
+                .expect("the wait time is limited to u32::MAX milliseconds, which fits in i64");
+
+            // SAFETY: All we need to be concerned about is passing in valid arguments, which we do.
+            let result =
+                unsafe { SetWaitableTimer(**timer, &due_time, 0, Some(wake_up), None, false) };
+
+            if result.is_ok() {
+                self.armed = true;
+                observe!(HIGH_RESOLUTION_WAITS.observe_unit());
+                return (max_wait_ms, true);
+            }
+        }
+
+        if until_next_timer < DEFAULT_SYSTEM_TIMER_PERIOD {
+            self.resolution_request
+                .get_or_insert_with(TimerResolutionRequest::new);
+        }
+
+        // Rounding up means we may wake up a bit late but never too early, which would only cost
+        // us another cycle.
+        let wait_ms = u32::try_from(until_next_timer.as_millis())
+            .unwrap_or(u32::MAX)
+            .saturating_add(u32::from(until_next_timer.subsec_nanos() % 1_000_000 != 0));
+
+        (wait_ms.min(max_wait_ms), false)
+    }
+
+    fn disarm(&mut self) {
+        if !self.armed {
+            return;
+        }
+
+        self.armed = false;
+
+        if let Some(timer) = &self.timer {
+            // If the APC has already been queued, it will merely end some future wait early,
+            // which costs us one extra cycle.
+            // SAFETY: All we need to be concerned about is passing in valid arguments, which we do.
+            _ = unsafe { CancelWaitableTimer(**timer) };
+        }
+    }
+}
+
+#[negative_impl]
+impl !Send for WakeTimer {}
+#[negative_impl]
+impl !Sync for WakeTimer {}
+
+/// Does nothing - merely queueing the APC ends the alertable wait of the worker thread.
+unsafe extern "system" fn wake_up(
+    _argument: *const core::ffi::c_void,
+    _timer_low_value: u32,
+    _timer_high_value: u32,
+) {
+}
+
+/// Raises the resolution of the system timer to 1 ms for as long as it exists. The operating
+/// system keeps count of the requests, restoring the original resolution once all of them (from
+/// any thread or process) have been dropped.
+#[derive(Debug)]
+struct TimerResolutionRequest {
+    _private: (),
+}
+
+impl TimerResolutionRequest {
+    fn new() -> Self {
+        // SAFETY: All we need to be concerned about is passing in valid arguments, which we do.
+        unsafe {
+            timeBeginPeriod(1);
+        }
+
+        observe!(TIMER_RESOLUTION_REQUESTS.observe_unit());
+
+        Self { _private: () }
+    }
+}
+
+impl Drop for TimerResolutionRequest {
+    fn drop(&mut self) {
+        // SAFETY: All we need to be concerned about is passing in valid arguments, which we do.
+        unsafe {
+            timeEndPeriod(1);
+        }
+    }
+}
+
+thread_local! {
+    static HIGH_RESOLUTION_WAITS: Event = EventBuilder::new("time_high_resolution_waits")
+        .build();
+
+    static TIMER_RESOLUTION_REQUESTS: Event = EventBuilder::new("time_timer_resolution_requests")
+        .build();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAX_WAIT_MS: u32 = 100;
+
+    fn without_high_resolution_timer() -> WakeTimer {
+        WakeTimer {
+            timer: None,
+            armed: false,
+            resolution_request: None,
+        }
+    }
+
+    #[test]
+    fn fallback_rounds_up_to_whole_milliseconds() {
+        let mut wake_timer = without_high_resolution_timer();
+        let now = Instant::now();
+
+        let mut wait_ms = |until_next_timer| {
+            let (wait_ms, alertable) =
+                wake_timer.prepare_wait(now, Some(now + until_next_timer), MAX_WAIT_MS);
+            assert!(!alertable);
+            wait_ms
+        };
+
+        assert_eq!(wait_ms(Duration::from_millis(2)), 2);
+        assert_eq!(wait_ms(Duration::from_micros(2_500)), 3);
+        assert_eq!(wait_ms(Duration::from_nanos(1)), 1);
+        assert_eq!(wait_ms(Duration::ZERO), 0);
+
+        // A timer that is not due before the wait ends on its own does not shorten the wait.
+        assert_eq!(wait_ms(Duration::from_millis(150)), MAX_WAIT_MS);
+
+        // Neither does the absence of timers.
+        assert_eq!(
+            wake_timer.prepare_wait(now, None, MAX_WAIT_MS),
+            (MAX_WAIT_MS, false)
+        );
+    }
+
+    #[test]
+    fn fallback_raises_resolution_while_short_timers_are_pending() {
+        let mut wake_timer = without_high_resolution_timer();
+        let now = Instant::now();
+
+        // Timers due after more than the default system timer period do not need help.
+        wake_timer.prepare_wait(now, Some(now + Duration::from_millis(50)), MAX_WAIT_MS);
+        assert!(wake_timer.resolution_request.is_none());
+
+        wake_timer.prepare_wait(now, Some(now + Duration::from_millis(5)), MAX_WAIT_MS);
+        assert!(wake_timer.resolution_request.is_some());
+
+        // The request is released once no timer is due within the wait.
+        wake_timer.prepare_wait(now, None, MAX_WAIT_MS);
+        assert!(wake_timer.resolution_request.is_none());
+    }
+
+    #[test]
+    fn high_resolution_timer_is_disarmed_without_timers() {
+        let mut wake_timer = WakeTimer::new();
+        assert!(
+            wake_timer.timer.is_some(),
+            "high-resolution waitable timers require Windows 10 1803 or newer"
+        );
+
+        let now = Instant::now();
+
+        // The wait itself is not shortened - the timer ends it via an APC.
+        assert_eq!(
+            wake_timer.prepare_wait(now, Some(now + Duration::from_millis(5)), MAX_WAIT_MS),
+            (MAX_WAIT_MS, true)
+        );
+        assert!(wake_timer.armed);
+        assert!(wake_timer.resolution_request.is_none());
+
+        assert_eq!(
+            wake_timer.prepare_wait(now, None, MAX_WAIT_MS),
+            (MAX_WAIT_MS, false)
+        );
+        assert!(!wake_timer.armed);
+    }
+}
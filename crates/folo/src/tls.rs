@@ -0,0 +1,7 @@
+mod tls_acceptor;
+mod tls_connector;
+mod tls_stream;
+
+pub use tls_acceptor::*;
+pub use tls_connector::*;
+pub use tls_stream::*;
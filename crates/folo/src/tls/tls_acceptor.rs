@@ -0,0 +1,54 @@
+use crate::{
+    io,
+    net::ByteStream,
+    tls::{tls_stream::map_tls_error, TlsStream},
+};
+use rustls::{ServerConfig, ServerConnection};
+use std::sync::Arc;
+
+/// Accepts TLS sessions on the server side of connected streams.
+///
+/// # Example
+///
+/// ```no_run
+/// use folo::net::TcpListener;
+/// use folo::tls::TlsAcceptor;
+/// use std::{net::SocketAddr, sync::Arc};
+///
+/// # fn server_config() -> rustls::ServerConfig { unimplemented!() }
+/// #[folo::main]
+/// async fn main() {
+///     let acceptor = TlsAcceptor::new(Arc::new(server_config()));
+///
+///     let listener = TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 443)))
+///         .await
+///         .unwrap();
+///
+///     let (stream, _) = listener.accept().await.unwrap();
+///     let mut stream = acceptor.accept(stream).await.unwrap();
+///
+///     // Communicate with the client via `stream.read()` and `stream.write()`.
+///     stream.shutdown().await.unwrap();
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct TlsAcceptor {
+    config: Arc<ServerConfig>,
+}
+
+impl TlsAcceptor {
+    pub fn new(config: Arc<ServerConfig>) -> Self {
+        Self { config }
+    }
+
+    /// Performs the server side of the TLS handshake on a connected stream, returning the stream
+    /// wrapped in a TLS session once the handshake has completed.
+    pub async fn accept<S>(&self, stream: S) -> io::Result<TlsStream<S>>
+    where
+        S: ByteStream,
+    {
+        let connection = ServerConnection::new(Arc::clone(&self.config)).map_err(map_tls_error)?;
+
+        TlsStream::handshake(stream, connection.into()).await
+    }
+}
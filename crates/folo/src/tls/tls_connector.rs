@@ -0,0 +1,62 @@
+use crate::{
+    io,
+    net::ByteStream,
+    tls::{tls_stream::map_tls_error, TlsStream},
+};
+use rustls::{pki_types::ServerName, ClientConfig, ClientConnection};
+use std::sync::Arc;
+
+/// Establishes TLS sessions on the client side of connected streams.
+///
+/// # Example
+///
+/// ```no_run
+/// use folo::net::TcpStream;
+/// use folo::tls::TlsConnector;
+/// use std::{net::SocketAddr, sync::Arc};
+///
+/// # fn client_config() -> rustls::ClientConfig { unimplemented!() }
+/// #[folo::main]
+/// async fn main() {
+///     let connector = TlsConnector::new(Arc::new(client_config()));
+///
+///     let stream = TcpStream::connect(SocketAddr::from(([127, 0, 0, 1], 443)))
+///         .await
+///         .unwrap();
+///
+///     let mut stream = connector
+///         .connect("example.com".try_into().unwrap(), stream)
+///         .await
+///         .unwrap();
+///
+///     // Communicate with the server via `stream.read()` and `stream.write()`.
+///     stream.shutdown().await.unwrap();
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct TlsConnector {
+    config: Arc<ClientConfig>,
+}
+
+impl TlsConnector {
+    pub fn new(config: Arc<ClientConfig>) -> Self {
+        Self { config }
+    }
+
+    /// Performs the client side of the TLS handshake on a connected stream, returning the stream
+    /// wrapped in a TLS session once the handshake has completed. The server must present a
+    /// certificate valid for `server_name`.
+    pub async fn connect<S>(
+        &self,
+        server_name: ServerName<'static>,
+        stream: S,
+    ) -> io::Result<TlsStream<S>>
+    where
+        S: ByteStream,
+    {
+        let connection =
+            ClientConnection::new(Arc::clone(&self.config), server_name).map_err(map_tls_error)?;
+
+        TlsStream::handshake(stream, connection.into()).await
+    }
+}
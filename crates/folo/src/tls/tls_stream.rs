@@ -0,0 +1,378 @@
+use crate::{
+    io::{self, Buffer, OperationResultExt, OperationResultFuture},
+    mem::isolation::Isolated,
+    net::{ByteStream, ShutdownFuture},
+};
+use futures::FutureExt;
+use negative_impl::negative_impl;
+use rustls::Connection;
+use std::{
+    future::Future,
+    io::{ErrorKind, Read, Write},
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+use tracing::{event, Level};
+
+/// A TLS session on top of a connected stream, created via
+/// [`TlsAcceptor`][super::TlsAcceptor] or [`TlsConnector`][super::TlsConnector].
+///
+/// Data is encrypted and decrypted on the current async worker thread, with the encrypted records
+/// transferred via the completion-based reads and writes of the underlying stream.
+///
+/// Besides the async methods, the session can be driven via poll-based methods (e.g.
+/// [`poll_read()`][Self::poll_read]) for implementing I/O traits of other crates on top of it. The
+/// two sets of methods must not be mixed on the same session.
+#[derive(Debug)]
+pub struct TlsStream<S> {
+    stream: S,
+    connection: Connection,
+
+    // Records received from the peer that the session has not yet accepted because its own
+    // buffers were full. We hand them over once the session has made room for them.
+    pending_records: Option<Buffer<Isolated>>,
+
+    // Whether the peer has sent a close_notify alert, after which it sends no more data.
+    peer_closed: bool,
+
+    // Operations on the underlying stream that are in flight between calls to the poll-based
+    // methods. Reads and writes are independent, so a pending read does not hold up writes.
+    active_receive: Option<OperationResultFuture>,
+    active_send: Option<OperationResultFuture>,
+    active_shutdown: Option<Pin<Box<ShutdownFuture>>>,
+
+    close_notify_sent: bool,
+}
+
+impl<S> TlsStream<S>
+where
+    S: ByteStream,
+{
+    /// Drives the handshake of a new TLS session to completion.
+    pub(super) async fn handshake(stream: S, connection: Connection) -> io::Result<Self> {
+        let mut this = Self {
+            stream,
+            connection,
+            pending_records: None,
+            peer_closed: false,
+            active_receive: None,
+            active_send: None,
+            active_shutdown: None,
+            close_notify_sent: false,
+        };
+
+        while this.connection.is_handshaking() {
+            if this.connection.wants_write() {
+                this.send_records().await?;
+            } else if !this.receive_records().await? {
+                return Err(io::Error::StdIo(ErrorKind::UnexpectedEof.into()));
+            }
+        }
+
+        // The last flight of the handshake may still be waiting to be sent.
+        this.send_records().await?;
+
+        event!(Level::TRACE, message = "TLS handshake completed");
+
+        Ok(this)
+    }
+
+    /// Reads the next decrypted data from the session into the active region of the buffer.
+    ///
+    /// Returns the buffer with the active region set to the bytes read, with a length of 0 if the
+    /// peer has closed the session.
+    pub async fn read(&mut self, mut buffer: Buffer<Isolated>) -> io::Result<Buffer<Isolated>> {
+        loop {
+            match self.connection.reader().read(&mut buffer.as_mut_slice()) {
+                Ok(len) => {
+                    buffer.set_len(len);
+                    return Ok(buffer);
+                }
+                // No complete record has been received yet, so we need more data from the peer.
+                Err(e) if e.kind() == ErrorKind::WouldBlock => {}
+                Err(e) => return Err(e.into()),
+            }
+
+            // If the underlying stream ends without a close_notify, the reader reports it as an
+            // error on the next iteration, as the data may have been truncated by an attacker.
+            self.receive_records().await?;
+
+            // Receiving may require a response (e.g. a key update) from us.
+            self.send_records().await?;
+        }
+    }
+
+    /// Encrypts the active region of the buffer and writes it to the session.
+    ///
+    /// Returns the buffer to allow reuse.
+    pub async fn write(&mut self, buffer: Buffer<Isolated>) -> io::Result<Buffer<Isolated>> {
+        self.connection.writer().write_all(&buffer.as_slice())?;
+        self.send_records().await?;
+
+        Ok(buffer)
+    }
+
+    /// Closes the TLS session by sending a close_notify alert, waits for the peer to close its end
+    /// of the session and then performs a graceful shutdown of the underlying stream.
+    ///
+    /// Any data the peer sends before it closes its end of the session is discarded.
+    pub async fn shutdown(&mut self) -> io::Result<()> {
+        self.connection.send_close_notify();
+        self.send_records().await?;
+
+        while !self.peer_closed {
+            if !self.receive_records().await? {
+                break;
+            }
+
+            self.discard_received_data()?;
+        }
+
+        self.stream.shutdown().await
+    }
+
+    /// Reads decrypted data from the session into `buf`, returning the number of bytes read. A
+    /// result of 0 indicates that the peer has closed the session, so `buf` must not be empty.
+    pub fn poll_read(&mut self, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        loop {
+            match self.connection.reader().read(buf) {
+                Ok(len) => return Poll::Ready(Ok(len)),
+                // No complete record has been received yet, so we need more data from the peer.
+                Err(e) if e.kind() == ErrorKind::WouldBlock => {}
+                Err(e) => return Poll::Ready(Err(e.into())),
+            }
+
+            // If the underlying stream ends without a close_notify, the reader reports it as an
+            // error on the next iteration, as the data may have been truncated by an attacker.
+            ready!(self.poll_receive_records(cx))?;
+
+            // Receiving may require a response (e.g. a key update) from us. We send it in the
+            // background - any error is reported by a later call.
+            if let Poll::Ready(Err(e)) = self.poll_send_records(cx) {
+                return Poll::Ready(Err(e));
+            }
+        }
+    }
+
+    /// Encrypts data from `buf`, returning the number of bytes accepted.
+    ///
+    /// The encrypted records are written to the underlying stream in the background. Before
+    /// accepting more data, this waits for the previously accepted data to be written, which
+    /// also reports any error that occurred in the meantime.
+    pub fn poll_write(&mut self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        ready!(self.poll_flush(cx))?;
+
+        let len = self.connection.writer().write(buf)?;
+
+        if let Poll::Ready(Err(e)) = self.poll_send_records(cx) {
+            return Poll::Ready(Err(e));
+        }
+
+        Poll::Ready(Ok(len))
+    }
+
+    /// Waits for all the accepted data to be written to the underlying stream.
+    pub fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_send_records(cx)
+    }
+
+    /// Closes the TLS session in the same way as [`shutdown()`][Self::shutdown].
+    pub fn poll_shutdown(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if !self.close_notify_sent {
+            self.connection.send_close_notify();
+            self.close_notify_sent = true;
+        }
+
+        ready!(self.poll_send_records(cx))?;
+
+        while !self.peer_closed {
+            if !ready!(self.poll_receive_records(cx))? {
+                break;
+            }
+
+            self.discard_received_data()?;
+        }
+
+        let shutdown = self
+            .active_shutdown
+            .get_or_insert_with(|| Box::pin(self.stream.shutdown()));
+
+        let result = ready!(shutdown.as_mut().poll(cx));
+        self.active_shutdown = None;
+
+        Poll::Ready(result)
+    }
+
+    /// The underlying stream. Reading from or writing to it directly corrupts the TLS session.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// The protocol agreed upon via ALPN during the handshake, if any.
+    pub fn alpn_protocol(&self) -> Option<&[u8]> {
+        self.connection.alpn_protocol()
+    }
+
+    /// Sends all the TLS records that are waiting to be sent.
+    async fn send_records(&mut self) -> io::Result<()> {
+        while self.connection.wants_write() {
+            let buffer = self.take_records()?;
+            self.stream.write(buffer).await.into_inner()?;
+        }
+
+        Ok(())
+    }
+
+    /// Sends all the TLS records that are waiting to be sent, one buffer at a time.
+    fn poll_send_records(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        loop {
+            if let Some(active_send) = self.active_send.as_mut() {
+                let result = ready!(active_send.poll_unpin(cx));
+                self.active_send = None;
+
+                result.into_inner()?;
+            }
+
+            if !self.connection.wants_write() {
+                return Poll::Ready(Ok(()));
+            }
+
+            let buffer = self.take_records()?;
+            self.active_send = Some(self.stream.write(buffer));
+        }
+    }
+
+    /// Moves the next batch of TLS records waiting to be sent into a buffer.
+    fn take_records(&mut self) -> io::Result<Buffer<Isolated>> {
+        let mut buffer = Buffer::<Isolated>::from_pool();
+
+        let len = {
+            let mut slice = buffer.as_mut_slice();
+            let mut slice: &mut [u8] = &mut slice;
+            self.connection.write_tls(&mut slice)?
+        };
+
+        buffer.set_len(len);
+
+        Ok(buffer)
+    }
+
+    /// Hands the next batch of TLS records received from the peer to the session and processes
+    /// them. The caller must consume any decrypted data before calling this again, to make room for
+    /// more.
+    ///
+    /// Returns `false` if the underlying stream has ended.
+    async fn receive_records(&mut self) -> io::Result<bool> {
+        let buffer = match self.pending_records.take() {
+            Some(buffer) => buffer,
+            None => self
+                .stream
+                .read(Buffer::<Isolated>::from_pool())
+                .await
+                .into_inner()?,
+        };
+
+        let stream_open = self.accept_records(buffer)?;
+        self.process_records().await?;
+
+        Ok(stream_open)
+    }
+
+    /// Like [`receive_records()`][Self::receive_records] but for the poll-based methods.
+    fn poll_receive_records(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<bool>> {
+        let buffer = match self.pending_records.take() {
+            Some(buffer) => buffer,
+            None => {
+                let active_receive = self
+                    .active_receive
+                    .get_or_insert_with(|| self.stream.read(Buffer::<Isolated>::from_pool()));
+
+                let result = ready!(active_receive.poll_unpin(cx));
+                self.active_receive = None;
+
+                result.into_inner()?
+            }
+        };
+
+        let stream_open = self.accept_records(buffer)?;
+
+        if let Err(e) = self.process_new_packets() {
+            // The session may have queued an alert to tell the peer what went wrong. This is
+            // best effort - the original error is what matters.
+            _ = self.poll_send_records(cx);
+
+            return Poll::Ready(Err(map_tls_error(e)));
+        }
+
+        Poll::Ready(Ok(stream_open))
+    }
+
+    /// Hands TLS records received from the peer to the session, keeping any that it does not
+    /// accept yet for later.
+    ///
+    /// Returns `false` if the buffer is empty, which means the underlying stream has ended.
+    fn accept_records(&mut self, mut buffer: Buffer<Isolated>) -> io::Result<bool> {
+        if buffer.is_empty() {
+            // Reading from an empty slice signals the end of the stream to the session.
+            self.connection.read_tls(&mut &[][..])?;
+            return Ok(false);
+        }
+
+        let accepted = {
+            let received = buffer.as_slice();
+            let mut received: &[u8] = &received;
+            self.connection.read_tls(&mut received)?
+        };
+
+        if accepted < buffer.len() {
+            let remaining = buffer.len() - accepted;
+            buffer.set_len(remaining);
+            buffer.set_start(buffer.start() + accepted);
+
+            self.pending_records = Some(buffer);
+        }
+
+        Ok(true)
+    }
+
+    async fn process_records(&mut self) -> io::Result<()> {
+        if let Err(e) = self.process_new_packets() {
+            // The session may have queued an alert to tell the peer what went wrong. This is
+            // best effort - the original error is what matters.
+            _ = self.send_records().await;
+
+            return Err(map_tls_error(e));
+        }
+
+        Ok(())
+    }
+
+    fn process_new_packets(&mut self) -> Result<(), rustls::Error> {
+        let state = self.connection.process_new_packets()?;
+        self.peer_closed |= state.peer_has_closed();
+
+        Ok(())
+    }
+
+    fn discard_received_data(&mut self) -> io::Result<()> {
+        let mut buffer = Buffer::<Isolated>::from_pool();
+
+        loop {
+            match self.connection.reader().read(&mut buffer.as_mut_slice()) {
+                Ok(0) => return Ok(()),
+                Ok(_) => {}
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(()),
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+#[negative_impl]
+impl<S> !Send for TlsStream<S> {}
+#[negative_impl]
+impl<S> !Sync for TlsStream<S> {}
+
+pub(super) fn map_tls_error(error: rustls::Error) -> io::Error {
+    io::Error::Other(Box::new(error))
+}
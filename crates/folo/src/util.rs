@@ -1,5 +1,11 @@
+#[cfg(feature = "fakes")]
+mod split_mix;
 mod thread_safe;
+mod thread_token;
 mod with_ref_count;
 
+#[cfg(feature = "fakes")]
+pub(crate) use split_mix::*;
 pub use thread_safe::*;
+pub(crate) use thread_token::*;
 pub use with_ref_count::*;
@@ -0,0 +1,10 @@
+/// Returns a number that uniquely identifies the current thread among the threads that are alive.
+/// Cheaper to obtain than a `ThreadId`, so suitable for hot paths that need to know whether they
+/// are on the same thread as some other party.
+pub(crate) fn current_thread_token() -> usize {
+    THREAD_TOKEN.with(|token| token as *const u8 as usize)
+}
+
+thread_local! {
+    static THREAD_TOKEN: u8 = const { 0 };
+}
@@ -0,0 +1,108 @@
+//! Spawning of tasks on `wasm32`, where there are no worker threads and tasks execute on the event
+//! loop of the host (e.g. the browser). This is a subset of the native `rt` module - code that only
+//! uses the functions and types available here compiles for both native and WASM targets.
+
+#[path = "../rt/join_error.rs"]
+mod join_error;
+#[path = "../rt/ready_after_poll.rs"]
+mod ready_after_poll;
+
+pub use join_error::*;
+
+use futures::{
+    channel::oneshot,
+    future::{AbortHandle, Abortable},
+    FutureExt,
+};
+use negative_impl::negative_impl;
+use ready_after_poll::ReadyAfterPoll;
+use std::{future::Future, panic, pin::Pin, sync::Arc, task};
+
+/// Spawns a task to execute a future on the event loop of the host.
+///
+/// The future does not need to be `Send`, as there is only one thread.
+pub fn spawn<F, R>(future: F) -> LocalJoinHandle<R>
+where
+    F: Future<Output = R> + 'static,
+    R: 'static,
+{
+    let (abort, registration) = AbortHandle::new_pair();
+    let (tx, rx) = oneshot::channel();
+
+    wasm_bindgen_futures::spawn_local(async move {
+        if let Ok(result) = Abortable::new(future, registration).await {
+            // The join handle may have been dropped already, which is fine.
+            _ = tx.send(result);
+        }
+    });
+
+    LocalJoinHandle { rx, abort }
+}
+
+/// Same as [`spawn()`]. The name is accepted for compatibility with native code but is not used,
+/// as there is no diagnostic output on WASM targets.
+pub fn spawn_named<F, R>(_name: impl Into<Arc<str>>, future: F) -> LocalJoinHandle<R>
+where
+    F: Future<Output = R> + 'static,
+    R: 'static,
+{
+    spawn(future)
+}
+
+/// Yields control back to the event loop of the host, allowing other tasks to execute before the
+/// current task resumes.
+pub fn yield_now() -> impl Future<Output = ()> {
+    ReadyAfterPoll::default()
+}
+
+/// Allows a unit of work to be awaited and its result to be observed.
+///
+/// Awaiting this is optional - the task will continue even if you drop the join handle.
+///
+/// Awaiting the handle directly yields the result of the task. If the task may have been aborted,
+/// use `result()` instead, which reports cancellation as `JoinError::Cancelled`. Panics on WASM
+/// targets abort the program, so they are never delivered to the join handle.
+#[derive(Debug)]
+pub struct LocalJoinHandle<R> {
+    rx: oneshot::Receiver<R>,
+    abort: AbortHandle,
+}
+
+impl<R> LocalJoinHandle<R> {
+    /// Requests the task to be aborted. The task is dropped the next time it would be polled,
+    /// which is at its next yield point (any `.await` that does not complete immediately). If the
+    /// task has already completed, this has no effect.
+    ///
+    /// After aborting, the handle resolves to `JoinError::Cancelled` when awaited via `result()`.
+    pub fn abort(&self) {
+        self.abort.abort();
+    }
+
+    /// Returns a future that resolves to the result of the task or to the reason why the task
+    /// failed to produce a result.
+    pub fn result(self) -> impl Future<Output = JoinResult<R>> {
+        // The sender is only dropped without sending if the task was aborted.
+        self.rx
+            .map(|result| result.map_err(|_| JoinError::Cancelled))
+    }
+}
+
+impl<R> Future for LocalJoinHandle<R> {
+    type Output = R;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> task::Poll<Self::Output> {
+        match self.rx.poll_unpin(cx) {
+            task::Poll::Ready(Ok(result)) => task::Poll::Ready(result),
+            task::Poll::Ready(Err(_)) => {
+                panic!("awaited a task that was aborted; use `result()` to observe cancellation")
+            }
+            task::Poll::Pending => task::Poll::Pending,
+        }
+    }
+}
+
+// Perhaps already implied but let's be super explicit here.
+#[negative_impl]
+impl<R> !Send for LocalJoinHandle<R> {}
+#[negative_impl]
+impl<R> !Sync for LocalJoinHandle<R> {}
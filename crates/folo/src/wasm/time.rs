@@ -0,0 +1,70 @@
+// Copyright (c) Microsoft Corporation.
+
+//! Timers on `wasm32`, provided by the host (e.g. `setTimeout()` in the browser). This is a subset
+//! of the native `time` module.
+
+use std::fmt::{self, Debug, Formatter};
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use futures::FutureExt;
+use gloo_timers::future::TimeoutFuture;
+use negative_impl::negative_impl;
+
+#[derive(Debug, Clone, Default)]
+pub struct Clock {
+    _private: (),
+}
+
+impl Clock {
+    pub fn new() -> Self {
+        Self { _private: () }
+    }
+
+    /// Returns the current time, as reported by the host.
+    pub fn now(&self) -> SystemTime {
+        // The host reports the time in milliseconds since the Unix epoch, as a float.
+        UNIX_EPOCH + Duration::from_millis(js_sys::Date::now() as u64)
+    }
+}
+
+/// Asynchronously delays for the specified duration.
+pub struct Delay {
+    timeout: TimeoutFuture,
+}
+
+#[negative_impl]
+impl !Send for Delay {}
+#[negative_impl]
+impl !Sync for Delay {}
+
+impl Delay {
+    /// Creates a new delay that will finish after the specified duration.
+    ///
+    /// The host only supports millisecond precision and durations of up to `u32::MAX`
+    /// milliseconds, so the duration is rounded down and capped to fit.
+    pub fn with_clock(_clock: &Clock, duration: Duration) -> Self {
+        let millis = u32::try_from(duration.as_millis()).unwrap_or(u32::MAX);
+
+        Self {
+            timeout: TimeoutFuture::new(millis),
+        }
+    }
+}
+
+impl Future for Delay {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.timeout.poll_unpin(cx)
+    }
+}
+
+impl Debug for Delay {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        // The timeout is not `Debug`, so we just show the type name.
+        f.debug_struct("Delay").finish_non_exhaustive()
+    }
+}
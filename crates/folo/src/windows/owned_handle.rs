@@ -1,3 +1,4 @@
+use crate::io::{handle_closed, handle_opened};
 use crate::rt::SynchronousTaskType;
 use crate::util::ThreadSafe;
 use std::mem;
@@ -11,6 +12,9 @@ use windows::{
 /// a background worker thread in case closing the handle incurs synchronous work due to flushing
 /// caches etc.
 ///
+/// Every owned handle is counted towards the open handles reported by
+/// [`io::open_handles()`][crate::io::open_handles], from creation until it is closed.
+///
 /// # Safety
 ///
 /// Must be used with a type of reference handle that is valid to close from any thread.
@@ -30,7 +34,7 @@ where
     ///
     /// The caller must ensure that the reference handle is valid to close from any thread.
     pub unsafe fn new(handle: T) -> Self {
-        Self { inner: handle }
+        handle.into()
     }
 }
 
@@ -39,6 +43,8 @@ where
     T: Free + Copy + 'static,
 {
     fn from(handle: T) -> Self {
+        handle_opened();
+
         Self { inner: handle }
     }
 }
@@ -89,6 +95,7 @@ where
                 (*thread_safe).free();
             }
 
+            handle_closed();
             return;
         }
 
@@ -106,6 +113,8 @@ where
             unsafe {
                 (*thread_safe).free();
             }
+
+            handle_closed();
         });
     }
 }
@@ -120,6 +129,9 @@ impl From<OwnedHandle<HANDLE>> for HANDLE {
         // Forget the value so that the handle is not closed on drop of the original.
         mem::forget(value);
 
+        // The caller is now responsible for the handle, so we no longer count it.
+        handle_closed();
+
         inner
     }
 }
@@ -131,6 +143,9 @@ impl From<OwnedHandle<SOCKET>> for SOCKET {
         // Forget the value so that the handle is not closed on drop of the original.
         mem::forget(value);
 
+        // The caller is now responsible for the handle, so we no longer count it.
+        handle_closed();
+
         inner
     }
 }
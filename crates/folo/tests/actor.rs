@@ -0,0 +1,89 @@
+use folo::sync::{oneshot, spawn_actor, Actor, CallError};
+use folo_testing::init_test_worker;
+use std::{cell::Cell, rc::Rc};
+
+struct Counter {
+    value: u64,
+    stopped: Rc<Cell<bool>>,
+}
+
+enum CounterMessage {
+    Add(u64),
+    Get(oneshot::Sender<u64>),
+    Forget(oneshot::Sender<u64>),
+}
+
+impl Actor for Counter {
+    type Message = CounterMessage;
+
+    async fn handle(&mut self, message: CounterMessage) {
+        match message {
+            CounterMessage::Add(amount) => {
+                folo::rt::yield_now().await;
+                self.value += amount;
+            }
+            CounterMessage::Get(reply) => _ = reply.send(self.value),
+            CounterMessage::Forget(reply) => drop(reply),
+        }
+    }
+
+    async fn stopped(&mut self) {
+        self.stopped.set(true);
+    }
+}
+
+fn spawn_counter() -> (folo::sync::Mailbox<CounterMessage>, Rc<Cell<bool>>) {
+    let stopped = Rc::new(Cell::new(false));
+
+    let mailbox = spawn_actor(Counter {
+        value: 0,
+        stopped: Rc::clone(&stopped),
+    });
+
+    (mailbox, stopped)
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn messages_are_processed_in_order() {
+    let (counter, _) = spawn_counter();
+
+    for i in 1..=10 {
+        counter.cast(CounterMessage::Add(i)).unwrap();
+    }
+
+    assert_eq!(counter.call(CounterMessage::Get).await, Ok(55));
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn call_fails_without_response() {
+    let (counter, _) = spawn_counter();
+
+    assert_eq!(counter.call(CounterMessage::Forget).await, Err(CallError));
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn stop_processes_earlier_messages() {
+    let (counter, stopped) = spawn_counter();
+    let (tx, rx) = oneshot::channel();
+
+    counter.cast(CounterMessage::Add(1)).unwrap();
+    counter.cast(CounterMessage::Get(tx)).unwrap();
+    counter.stop().await;
+
+    assert!(stopped.get());
+    assert!(counter.is_stopped());
+    assert_eq!(rx.await, Ok(1));
+    assert!(counter.cast(CounterMessage::Add(1)).is_err());
+    assert_eq!(counter.call(CounterMessage::Get).await, Err(CallError));
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn stops_when_mailboxes_dropped() {
+    let (counter, stopped) = spawn_counter();
+
+    drop(counter);
+
+    while !stopped.get() {
+        folo::rt::yield_now().await;
+    }
+}
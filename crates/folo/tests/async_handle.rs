@@ -0,0 +1,39 @@
+use folo::{
+    io::{AsyncHandle, Buffer},
+    mem::isolation::Isolated,
+};
+use folo_testing::init_test_worker;
+use std::{env, fs, process};
+
+const CONTENT: &[u8] = b"hello, folo";
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn synchronous_handle_falls_back_to_blocking() {
+    let path = env::temp_dir().join(format!("folo-async-handle-{}.bin", process::id()));
+
+    // The standard library opens files for synchronous I/O.
+    let file = fs::File::create(&path).unwrap();
+    let mut handle = AsyncHandle::new(file.into()).unwrap();
+    assert!(handle.is_blocking());
+
+    let mut buffer = Buffer::<Isolated>::from_pool();
+    buffer.as_mut_slice()[..CONTENT.len()].copy_from_slice(CONTENT);
+    buffer.set_len(CONTENT.len());
+
+    let buffer = handle.write(buffer).await.unwrap();
+    assert_eq!(buffer.len(), CONTENT.len());
+    drop(handle);
+
+    let file = fs::File::open(&path).unwrap();
+    let mut handle = AsyncHandle::new(file.into()).unwrap();
+
+    let buffer = handle.read(Buffer::from_pool()).await.unwrap();
+    assert_eq!(&*buffer.as_slice(), CONTENT);
+
+    // The end of the file is reported as an empty read.
+    let buffer = handle.read(Buffer::from_pool()).await.unwrap();
+    assert!(buffer.is_empty());
+
+    drop(handle);
+    fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,127 @@
+use folo::{
+    io::{Buffer, OperationResultExt},
+    mem::isolation::Isolated,
+    net::{AsyncSocket, UdpSocket},
+    rt::spawn,
+    time::{Clock, Delay},
+};
+use folo_testing::init_test_worker;
+use std::{
+    mem::ManuallyDrop,
+    net::{self, Ipv4Addr, SocketAddr},
+    os::windows::io::{FromRawSocket, IntoRawSocket},
+    time::Duration,
+};
+use windows::Win32::Networking::WinSock::SOCKET;
+
+const MESSAGE: &[u8] = b"hello, folo";
+
+// Plays the role of a third-party library that performs non-blocking I/O on the socket itself.
+fn library_recv_from(socket: SOCKET, data: &mut [u8]) -> std::io::Result<(usize, SocketAddr)> {
+    // SAFETY: The socket is valid and remains owned by the AsyncSocket, so we must not close it.
+    let socket = ManuallyDrop::new(unsafe { net::UdpSocket::from_raw_socket(socket.0 as u64) });
+
+    socket.recv_from(data)
+}
+
+fn library_send_to(socket: SOCKET, data: &[u8], addr: SocketAddr) -> std::io::Result<usize> {
+    // SAFETY: The socket is valid and remains owned by the AsyncSocket, so we must not close it.
+    let socket = ManuallyDrop::new(unsafe { net::UdpSocket::from_raw_socket(socket.0 as u64) });
+
+    socket.send_to(data, addr)
+}
+
+fn adopt_library_socket() -> (AsyncSocket, SocketAddr) {
+    // The standard library creates its sockets for overlapped I/O.
+    let external = net::UdpSocket::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0))).unwrap();
+    let external_addr = external.local_addr().unwrap();
+
+    let raw_socket = SOCKET(external.into_raw_socket() as usize);
+
+    // SAFETY: The socket is valid, overlapped and we have given up ownership of it.
+    let socket = unsafe { AsyncSocket::from_raw_socket(raw_socket) }.unwrap();
+
+    (socket, external_addr)
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn async_socket_waits_for_readable() {
+    let (socket, socket_addr) = adopt_library_socket();
+
+    let sender = UdpSocket::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+        .await
+        .unwrap();
+    let sender_addr = sender.local_addr().unwrap();
+
+    // Nothing has been sent yet, so the first attempt of the library would block.
+    let mut data = [0; 64];
+    assert_eq!(
+        library_recv_from(socket.as_raw_socket(), &mut data)
+            .unwrap_err()
+            .kind(),
+        std::io::ErrorKind::WouldBlock
+    );
+
+    let send = spawn(async move {
+        let clock = Clock::new();
+        Delay::with_clock(&clock, Duration::from_millis(50)).await;
+
+        let mut buffer = Buffer::<Isolated>::from_pool();
+        buffer.as_mut_slice()[..MESSAGE.len()].copy_from_slice(MESSAGE);
+        buffer.set_len(MESSAGE.len());
+
+        sender
+            .send_to(buffer, socket_addr)
+            .await
+            .into_inner()
+            .unwrap();
+    });
+
+    let (len, peer_addr) = socket
+        .read_with(|raw| library_recv_from(raw, &mut data))
+        .await
+        .unwrap();
+
+    assert_eq!(&data[..len], MESSAGE);
+    assert_eq!(peer_addr, sender_addr);
+
+    send.await;
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn async_socket_writable() {
+    let (socket, _) = adopt_library_socket();
+
+    let receiver = UdpSocket::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+        .await
+        .unwrap();
+    let receiver_addr = receiver.local_addr().unwrap();
+
+    socket.writable().await.unwrap();
+
+    let len = socket
+        .write_with(|raw| library_send_to(raw, MESSAGE, receiver_addr))
+        .await
+        .unwrap();
+    assert_eq!(len, MESSAGE.len());
+
+    let datagram = receiver
+        .recv_from(Buffer::<Isolated>::from_pool())
+        .await
+        .unwrap();
+    assert_eq!(&*datagram.buffer().as_slice(), MESSAGE);
+
+    // The zero-byte readiness check does not consume the datagram.
+    let (library_socket, library_addr) = adopt_library_socket();
+    receiver
+        .send_to(datagram.into_buffer(), library_addr)
+        .await
+        .into_inner()
+        .unwrap();
+
+    library_socket.readable().await.unwrap();
+
+    let mut data = [0; 64];
+    let (len, _) = library_recv_from(library_socket.as_raw_socket(), &mut data).unwrap();
+    assert_eq!(&data[..len], MESSAGE);
+}
@@ -0,0 +1,41 @@
+use folo::bench::BenchBuilder;
+use std::time::Duration;
+
+#[folo::bench(duration_ms = 100, warmup_ms = 10, max_processors = 2)]
+async fn spawn_and_await() {
+    folo::rt::spawn(async { 42 }).await
+}
+
+#[test]
+fn bench_attribute_runs_on_every_worker() {
+    let report = spawn_and_await();
+
+    assert_eq!(report.name(), "spawn_and_await");
+    assert_eq!(report.workers().len(), 2);
+
+    for worker in report.workers() {
+        assert!(worker.iterations() > 0);
+        assert!(worker.throughput() > 0.0);
+    }
+
+    assert!(report.throughput() > 0.0);
+}
+
+// Latency is recorded via metrics, so there are no percentiles if metrics are compiled out.
+#[cfg(feature = "metrics")]
+#[test]
+fn latency_percentiles_are_ordered() {
+    let report = BenchBuilder::new("yield_now")
+        .duration(Duration::from_millis(100))
+        .warmup(Duration::ZERO)
+        .max_processors(1)
+        .run(folo::rt::yield_now);
+
+    let worker = &report.workers()[0];
+
+    // Yielding takes far less than the largest histogram bucket, so the percentiles are known.
+    let p50 = worker.latency_percentile(50.0).unwrap();
+    let p99 = worker.latency_percentile(99.0).unwrap();
+
+    assert!(p50 <= p99);
+}
@@ -0,0 +1,108 @@
+use folo::{
+    io::{BufReader, BufWriter, WriteQueue},
+    net::{TcpListener, TcpStream},
+    rt::spawn,
+};
+use folo_testing::init_test_worker;
+use std::{
+    net::{Ipv4Addr, Shutdown, SocketAddr},
+    time::Duration,
+};
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn buf_io_line_echo() {
+    let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+        .await
+        .unwrap();
+
+    let listen_addr = listener.local_addr().unwrap();
+
+    let server = spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut reader = BufReader::new(stream);
+
+        let mut lines = Vec::new();
+        let mut line = String::new();
+
+        while reader.read_line(&mut line).await.unwrap() != 0 {
+            lines.push(line.clone());
+            line.clear();
+        }
+
+        assert_eq!(lines, ["hello\n", "folo\n", "unterminated"]);
+
+        // Reading after the end of the stream keeps reporting the end of the stream.
+        let mut buf = [0; 16];
+        assert_eq!(reader.read(&mut buf).await.unwrap(), 0);
+
+        let mut writer = BufWriter::new(reader.into_inner());
+
+        for line in &lines {
+            writer.write_all(line.as_bytes()).await.unwrap();
+        }
+
+        // Nothing is written before the writer is flushed.
+        assert_eq!(writer.buffer(), b"hello\nfolo\nunterminated");
+
+        writer.shutdown().await.unwrap();
+    });
+
+    let mut writer = BufWriter::new(TcpStream::connect(listen_addr).await.unwrap());
+
+    // Many small writes are combined into one write to the stream.
+    for b in b"hello\nfolo\nunterminated" {
+        writer.write_all(&[*b]).await.unwrap();
+    }
+
+    writer.flush().await.unwrap();
+    writer
+        .get_mut()
+        .shutdown_with(Shutdown::Write)
+        .await
+        .unwrap();
+
+    let mut reader = BufReader::new(writer.into_inner());
+
+    let mut response = Vec::new();
+    assert_eq!(reader.read_until(b'\n', &mut response).await.unwrap(), 6);
+    assert_eq!(reader.read_until(b'!', &mut response).await.unwrap(), 17);
+    assert_eq!(reader.read_until(b'\n', &mut response).await.unwrap(), 0);
+    assert_eq!(response, b"hello\nfolo\nunterminated");
+
+    server.await;
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn write_queue_flushes_after_delay() {
+    let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+        .await
+        .unwrap();
+
+    let listen_addr = listener.local_addr().unwrap();
+
+    let server = spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut reader = BufReader::new(stream);
+
+        let mut line = String::new();
+        reader.read_line(&mut line).await.unwrap();
+
+        assert_eq!(line, "hello, folo\n");
+    });
+
+    let queue = WriteQueue::new(TcpStream::connect(listen_addr).await.unwrap())
+        .with_flush_delay(Duration::from_millis(10));
+
+    for b in b"hello, folo\n" {
+        queue.write_all(&[*b]).await.unwrap();
+    }
+
+    // The data is corked until the flush delay elapses.
+    assert_eq!(queue.queue_depth(), 12);
+
+    // We never flush explicitly - the server only gets the line because of the flush delay.
+    server.await;
+
+    assert_eq!(queue.queue_depth(), 0);
+    queue.shutdown().await.unwrap();
+}
@@ -0,0 +1,126 @@
+// The reloads are observed via metrics, which do not exist if metrics are compiled out.
+#![cfg(all(feature = "config", feature = "metrics"))]
+
+use folo::{
+    config::{Error, WatchBuilder},
+    metrics::{report_page, ReportBuilder},
+    time::{Clock, Delay},
+};
+use folo_testing::init_test_worker;
+use std::{env, fs, path::PathBuf, process, time::Duration};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+fn config_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("folo-config-{name}-{}.json", process::id()))
+}
+
+// The watcher runs on the worker thread that started it, so its metrics are on the current page.
+fn reload_count(event_name: &str) -> usize {
+    let mut report_builder = ReportBuilder::new();
+    report_builder.add_page(report_page());
+    report_builder.build().count(event_name)
+}
+
+async fn wait_for_reload_count(event_name: &str, count: usize) {
+    let clock = Clock::new();
+
+    for _ in 0..500 {
+        if reload_count(event_name) >= count {
+            return;
+        }
+
+        Delay::with_clock(&clock, POLL_INTERVAL).await;
+    }
+
+    panic!("{event_name} did not reach {count}");
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn reload_waits_for_file_to_settle() {
+    let path = config_path("debounce");
+    fs::write(&path, "1").unwrap();
+
+    let mut rx = WatchBuilder::<u32>::new(&path)
+        .poll_interval(POLL_INTERVAL)
+        .debounce(Duration::from_millis(300))
+        .build()
+        .await
+        .unwrap();
+
+    assert_eq!(*rx.borrow(), 1);
+
+    // A partially written file, completed well within the debounce duration.
+    fs::write(&path, "[1,").unwrap();
+    Delay::with_clock(&Clock::new(), Duration::from_millis(50)).await;
+    fs::write(&path, "2").unwrap();
+
+    rx.changed().await.unwrap();
+
+    assert_eq!(*rx.borrow_and_update(), 2);
+    assert_eq!(reload_count("config_reload_succeeded"), 1);
+    assert_eq!(reload_count("config_reload_failed"), 0);
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn failed_reload_keeps_previous_configuration() {
+    let path = config_path("failed-reload");
+    fs::write(&path, "1").unwrap();
+
+    let mut rx = WatchBuilder::<u32>::new(&path)
+        .poll_interval(POLL_INTERVAL)
+        .debounce(POLL_INTERVAL)
+        .build()
+        .await
+        .unwrap();
+
+    fs::write(&path, "not json").unwrap();
+    wait_for_reload_count("config_reload_failed", 1).await;
+
+    assert!(!rx.has_changed());
+    assert_eq!(*rx.borrow(), 1);
+
+    // The watcher keeps going after a failure.
+    fs::write(&path, "3").unwrap();
+    rx.changed().await.unwrap();
+
+    assert_eq!(*rx.borrow_and_update(), 3);
+    assert_eq!(reload_count("config_reload_succeeded"), 1);
+    assert_eq!(reload_count("config_reload_failed"), 1);
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn validation_rejects_configuration() {
+    let path = config_path("validation");
+
+    let builder = || {
+        WatchBuilder::<u32>::new(&path)
+            .poll_interval(POLL_INTERVAL)
+            .debounce(POLL_INTERVAL)
+            .validate(|value| {
+                if *value < 10 {
+                    Ok(())
+                } else {
+                    Err("too large")
+                }
+            })
+    };
+
+    fs::write(&path, "42").unwrap();
+    assert!(matches!(builder().build().await, Err(Error::Invalid(_))));
+
+    fs::write(&path, "1").unwrap();
+    let rx = builder().build().await.unwrap();
+
+    fs::write(&path, "43").unwrap();
+    wait_for_reload_count("config_reload_failed", 1).await;
+
+    assert!(!rx.has_changed());
+    assert_eq!(*rx.borrow(), 1);
+
+    fs::remove_file(&path).unwrap();
+}
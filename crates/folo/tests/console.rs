@@ -0,0 +1,63 @@
+#![cfg(feature = "console")]
+
+use folo::{console::ConsoleBuilder, rt::RuntimeBuilder};
+use folo_testing::init_test_worker;
+use futures::future;
+use std::{
+    io::{BufRead, BufReader},
+    net::{Ipv4Addr, SocketAddr, TcpStream},
+    sync::mpsc,
+    time::Duration,
+};
+
+#[test]
+fn console_streams_snapshots_with_tasks() {
+    let folo = RuntimeBuilder::new()
+        .worker_init(init_test_worker)
+        .max_processors(2)
+        .build()
+        .unwrap();
+
+    let (started_tx, started_rx) = mpsc::channel();
+
+    folo.spawn_on_any_named("observed", move || async move {
+        _ = started_tx.send(());
+        future::pending::<()>().await;
+    });
+
+    started_rx.recv().unwrap();
+
+    let console = ConsoleBuilder::new()
+        .interval(Duration::from_millis(10))
+        .start(&folo, SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+        .unwrap();
+
+    let client = TcpStream::connect(console.local_addr()).unwrap();
+    let mut lines = BufReader::new(client).lines();
+
+    // Two snapshots, to verify that the stream continues after the first one.
+    for _ in 0..2 {
+        let line = lines.next().unwrap().unwrap();
+        let snapshot: serde_json::Value = serde_json::from_str(&line).unwrap();
+
+        assert_eq!(snapshot["version"], 1);
+
+        let workers = snapshot["workers"].as_array().unwrap();
+        assert_eq!(workers.len(), 2);
+        assert!(workers.iter().all(|worker| worker["responded"] == true));
+
+        let observed = workers
+            .iter()
+            .flat_map(|worker| worker["tasks"].as_array().unwrap())
+            .find(|task| task["name"] == "observed")
+            .expect("the observed task must be listed");
+
+        assert_eq!(observed["state"], "waiting");
+        assert!(observed["polls"].as_u64().unwrap() >= 1);
+    }
+
+    console.stop();
+
+    folo.stop();
+    folo.wait();
+}
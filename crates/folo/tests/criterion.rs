@@ -0,0 +1,30 @@
+#![cfg(feature = "criterion")]
+
+use criterion::async_executor::AsyncExecutor;
+use folo::{
+    criterion::FoloExecutor,
+    rt::spawn,
+    time::{Clock, Delay},
+};
+use std::time::Duration;
+
+#[test]
+fn executor_runs_future_on_embedded_worker() {
+    let result = FoloExecutor::default().block_on(async {
+        // Spawning only works on an async worker thread.
+        spawn(async { 42 }).await
+    });
+
+    assert_eq!(result, 42);
+}
+
+#[test]
+fn executor_is_reused_across_benchmarks() {
+    let executor = FoloExecutor::default();
+
+    for _ in 0..3 {
+        executor.block_on(async {
+            Delay::with_clock(&Clock::new(), Duration::from_millis(1)).await;
+        });
+    }
+}
@@ -0,0 +1,99 @@
+use folo::rt::{spawn, yield_now, RuntimeBuilder};
+use folo_testing::init_test_worker;
+use std::{cell::Cell, rc::Rc, sync::mpsc, time::Duration};
+
+const TURN_MAX_WAIT: Duration = Duration::from_millis(10);
+
+#[test]
+fn embedded_runtime_executes_tasks_when_turned() {
+    let mut runtime = RuntimeBuilder::new()
+        .worker_init(init_test_worker)
+        .max_processors(2)
+        .build_embedded()
+        .unwrap();
+
+    let result = Rc::new(Cell::new(None));
+
+    // A local task on the embedded worker that waits for a task on some other worker.
+    spawn({
+        let result = Rc::clone(&result);
+        let remote = runtime.client().spawn_on_any(|| async {
+            yield_now().await;
+            42
+        });
+
+        async move {
+            result.set(Some(remote.await));
+        }
+    });
+
+    while result.get().is_none() {
+        assert!(runtime.turn(TURN_MAX_WAIT));
+    }
+
+    assert_eq!(result.get(), Some(42));
+
+    runtime.client().stop();
+
+    while runtime.turn(TURN_MAX_WAIT) {}
+
+    assert!(runtime.is_finished());
+    assert!(!runtime.turn(Duration::ZERO));
+
+    runtime.client().wait();
+}
+
+#[test]
+fn embedded_runtime_finishes_when_dropped() {
+    let runtime = RuntimeBuilder::new()
+        .worker_init(init_test_worker)
+        .max_processors(1)
+        .build_embedded()
+        .unwrap();
+
+    let client = runtime.client().clone();
+
+    spawn(async {
+        yield_now().await;
+    });
+
+    drop(runtime);
+
+    client.wait();
+}
+
+#[test]
+fn embedded_runtime_delivers_host_wakes_between_turns() {
+    let mut runtime = RuntimeBuilder::new()
+        .worker_init(init_test_worker)
+        .max_processors(2)
+        .build_embedded()
+        .unwrap();
+
+    let (started_tx, started_rx) = mpsc::channel();
+    let (wake_tx, wake_rx) = futures::channel::oneshot::channel::<()>();
+    let (done_tx, done_rx) = mpsc::channel();
+
+    // A task on the other worker, woken up by the host thread while it is outside `turn()`.
+    runtime.client().spawn_on(1, move || async move {
+        started_tx.send(()).unwrap();
+        wake_rx.await.unwrap();
+        done_tx.send(()).unwrap();
+    });
+
+    started_rx.recv().unwrap();
+
+    // The host thread has been through a turn, so the wake would have been stuck in its batch
+    // until the next turn if batching remained enabled between turns.
+    assert!(runtime.turn(Duration::ZERO));
+
+    wake_tx.send(()).unwrap();
+
+    done_rx.recv_timeout(Duration::from_secs(10)).unwrap();
+
+    runtime.client().stop();
+
+    while runtime.turn(TURN_MAX_WAIT) {}
+
+    runtime.client().wait();
+}
@@ -0,0 +1,124 @@
+#![cfg(feature = "fakes")]
+
+use folo::{
+    fs::{File, RecordReader},
+    io::{self, inject_faults, Buffer, Fault, FaultPlan, InjectedError},
+    mem::isolation::Isolated,
+};
+use folo_testing::init_test_worker;
+use std::{env, fs, process};
+use windows::Win32::Foundation::ERROR_OPERATION_ABORTED;
+
+const CONTENT: &[u8] = b"hello, folo";
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn scripted_faults_are_injected_in_order() {
+    let path = env::temp_dir().join(format!("folo-fault-script-{}.bin", process::id()));
+    fs::write(&path, CONTENT).unwrap();
+
+    let mut file = File::open(&path).await.unwrap();
+
+    let faults = inject_faults(
+        FaultPlan::script([
+            Fault::Error(InjectedError::OperationAborted),
+            Fault::ShortTransfer(4),
+        ])
+        .matching("read_buffer_from_file"),
+    );
+
+    let result = file.read(Buffer::<Isolated>::from_pool()).await;
+    assert!(matches!(
+        result,
+        Err(io::Error::Windows(e)) if e.code() == ERROR_OPERATION_ABORTED.to_hresult()
+    ));
+    assert_eq!(file.position(), 0);
+
+    let buffer = file.read(Buffer::<Isolated>::from_pool()).await.unwrap();
+    assert_eq!(&*buffer.as_slice(), b"hell");
+
+    // The script is exhausted, so the rest is read normally.
+    let buffer = file.read(buffer.use_all()).await.unwrap();
+    assert_eq!(&*buffer.as_slice(), b"o, folo");
+
+    assert_eq!(faults.injected(), 2);
+
+    drop(faults);
+    drop(file);
+    _ = fs::remove_file(&path);
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn record_reader_recovers_from_short_read_mid_file() {
+    let path = env::temp_dir().join(format!("folo-fault-records-{}.bin", process::id()));
+    fs::write(&path, b"first\nsecond\nthird").unwrap();
+
+    let mut reader = RecordReader::builder(&path)
+        .read_ahead(2)
+        .open()
+        .await
+        .unwrap();
+
+    // The first read ends in the middle of the second record, so the read-ahead issued after it
+    // started at the wrong offset and must be discarded.
+    let faults = inject_faults(
+        FaultPlan::script([Fault::ShortTransfer(8)]).matching("read_buffer_from_file"),
+    );
+
+    let mut records = Vec::new();
+
+    while let Some(record) = reader.next_record().await.unwrap() {
+        records.push(record);
+    }
+
+    assert_eq!(records, [&b"first"[..], b"second", b"third"]);
+    assert_eq!(faults.injected(), 1);
+
+    drop(faults);
+    drop(reader);
+    _ = fs::remove_file(&path);
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn same_seed_reproduces_same_faults() {
+    let path = env::temp_dir().join(format!("folo-fault-seeded-{}.bin", process::id()));
+    fs::write(&path, CONTENT).unwrap();
+
+    let mut outcomes = Vec::new();
+
+    for _ in 0..2 {
+        let file = File::open(&path).await.unwrap();
+
+        let _faults = inject_faults(
+            FaultPlan::seeded(1234)
+                .errors(0.3, [InjectedError::ConnectionReset])
+                .short_transfers(0.3)
+                .matching("read_buffer_from_file"),
+        );
+
+        let mut outcome = Vec::new();
+
+        for _ in 0..20 {
+            // Short transfers are chosen relative to the buffer, so we size it to the content.
+            let mut buffer = Buffer::<Isolated>::from_pool();
+            buffer.set_len(CONTENT.len());
+
+            outcome.push(file.read_at(0, buffer).await.map(|buffer| buffer.len()));
+        }
+
+        let outcome = outcome
+            .into_iter()
+            .map(|x| x.map_err(|e| e.to_string()))
+            .collect::<Vec<_>>();
+
+        assert!(outcome.iter().any(Result::is_err));
+        assert!(outcome
+            .iter()
+            .any(|x| matches!(x, Ok(len) if *len < CONTENT.len())));
+
+        outcomes.push(outcome);
+    }
+
+    assert_eq!(outcomes[0], outcomes[1]);
+
+    _ = fs::remove_file(&path);
+}
@@ -0,0 +1,526 @@
+use folo::{
+    fs::{
+        self as folo_fs, DirectFile, File, IoPriority, Mmap, ReadAheadReader, RecordReader,
+        TempDir, TempFile,
+    },
+    io::{AlignedBuffer, Buffer, IoBackend, IoQuotaBuilder},
+    mem::isolation::Isolated,
+    rt::RuntimeBuilder,
+};
+use folo_testing::init_test_worker;
+use std::{
+    env, fs,
+    os::windows::{fs::OpenOptionsExt, io::IntoRawHandle},
+    path::Path,
+    process,
+};
+use windows::Win32::{
+    Foundation::{CloseHandle, HANDLE, INVALID_HANDLE_VALUE},
+    Storage::FileSystem::FILE_FLAG_OVERLAPPED,
+    System::IO::CreateIoCompletionPort,
+};
+
+const CONTENT: &[u8] = b"hello, folo";
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn file_sequential_and_positional_io() {
+    let path = env::temp_dir().join(format!("folo-file-io-{}.bin", process::id()));
+
+    let mut file = File::create(&path).await.unwrap();
+
+    let mut buffer = Buffer::<Isolated>::from_pool();
+    buffer.as_mut_slice()[..CONTENT.len()].copy_from_slice(CONTENT);
+    buffer.set_len(CONTENT.len());
+
+    let buffer = file.write(buffer).await.unwrap();
+    assert_eq!(buffer.len(), CONTENT.len());
+    assert_eq!(file.position(), CONTENT.len());
+
+    // Positional reads do not move the position.
+    let buffer = file.read_at(7, buffer.use_all()).await.unwrap();
+    assert_eq!(&*buffer.as_slice(), b"folo");
+    assert_eq!(file.position(), CONTENT.len());
+
+    // Reading at the end of the file returns an empty buffer.
+    let buffer = file.read(buffer.use_all()).await.unwrap();
+    assert_eq!(buffer.len(), 0);
+
+    file.set_position(0);
+    let buffer = file.read(buffer.use_all()).await.unwrap();
+    assert_eq!(&*buffer.as_slice(), CONTENT);
+    assert_eq!(file.position(), CONTENT.len());
+
+    file.set_len(5).unwrap();
+    let buffer = file.read_at(0, buffer.use_all()).await.unwrap();
+    assert_eq!(&*buffer.as_slice(), b"hello");
+
+    file.set_len(8).unwrap();
+    let buffer = file.read_at(0, buffer.use_all()).await.unwrap();
+    assert_eq!(&*buffer.as_slice(), b"hello\0\0\0");
+
+    drop(file);
+    _ = fs::remove_file(&path);
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn file_vectored_io() {
+    let path = env::temp_dir().join(format!("folo-file-vectored-{}.bin", process::id()));
+
+    let mut file = File::create(&path).await.unwrap();
+
+    let mut header = Buffer::<Isolated>::from_pool();
+    header.as_mut_slice()[..7].copy_from_slice(&CONTENT[..7]);
+    header.set_len(7);
+
+    let mut body = Buffer::<Isolated>::from_pool();
+    body.as_mut_slice()[..CONTENT.len() - 7].copy_from_slice(&CONTENT[7..]);
+    body.set_len(CONTENT.len() - 7);
+
+    file.write_vectored(vec![header, body]).await.unwrap();
+    assert_eq!(file.position(), CONTENT.len());
+
+    let mut first = Buffer::<Isolated>::from_pool();
+    first.set_len(5);
+
+    // The file ends within the second buffer, so the third one remains empty.
+    let buffers = file
+        .read_vectored_at(
+            0,
+            vec![
+                first,
+                Buffer::<Isolated>::from_pool(),
+                Buffer::<Isolated>::from_pool(),
+            ],
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(&*buffers[0].as_slice(), &CONTENT[..5]);
+    assert_eq!(&*buffers[1].as_slice(), &CONTENT[5..]);
+    assert!(buffers[2].is_empty());
+
+    drop(file);
+    _ = fs::remove_file(&path);
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn file_metadata() {
+    let path = env::temp_dir().join(format!("folo-file-metadata-{}.bin", process::id()));
+    fs::write(&path, CONTENT).unwrap();
+
+    assert!(folo_fs::exists(&path).await.unwrap());
+
+    let metadata = folo_fs::metadata(&path).await.unwrap();
+    assert!(metadata.is_file());
+    assert!(!metadata.is_dir());
+    assert!(!metadata.is_readonly());
+    assert_eq!(metadata.len(), CONTENT.len() as u64);
+    assert!(metadata.modified().unwrap() >= metadata.created().unwrap());
+
+    let metadata = folo_fs::metadata(env::temp_dir()).await.unwrap();
+    assert!(metadata.is_dir());
+
+    fs::remove_file(&path).unwrap();
+
+    assert!(!folo_fs::exists(&path).await.unwrap());
+    assert!(folo_fs::metadata(&path).await.is_err());
+    assert!(folo_fs::symlink_metadata(&path).await.is_err());
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn read_and_walk_dir() {
+    let root = env::temp_dir().join(format!("folo-walk-dir-{}", process::id()));
+    fs::create_dir_all(root.join("a").join("b")).unwrap();
+    fs::create_dir_all(root.join("skipped")).unwrap();
+    fs::write(root.join("top.txt"), CONTENT).unwrap();
+    fs::write(root.join("a").join("middle.txt"), CONTENT).unwrap();
+    fs::write(root.join("a").join("b").join("bottom.txt"), CONTENT).unwrap();
+    fs::write(root.join("skipped").join("hidden.txt"), CONTENT).unwrap();
+
+    let mut names = Vec::new();
+    let mut dir = folo_fs::read_dir(&root).await.unwrap();
+
+    while let Some(entry) = dir.next().await {
+        names.push(entry.unwrap().file_name());
+    }
+
+    names.sort();
+    assert_eq!(names, ["a", "skipped", "top.txt"]);
+
+    let mut paths = Vec::new();
+    let mut walk = folo_fs::walk_dir(&root)
+        .max_depth(2)
+        .filter(|entry| entry.file_name() != "skipped");
+
+    while let Some(entry) = walk.next().await {
+        let path = entry.unwrap().path();
+        paths.push(path.strip_prefix(&root).unwrap().to_path_buf());
+    }
+
+    paths.sort();
+    assert_eq!(
+        paths,
+        [
+            Path::new("a").to_path_buf(),
+            Path::new("a").join("b"),
+            Path::new("a").join("middle.txt"),
+            Path::new("top.txt").to_path_buf(),
+        ]
+    );
+
+    _ = fs::remove_dir_all(&root);
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn file_management() {
+    let root = env::temp_dir().join(format!("folo-file-management-{}", process::id()));
+    let dir = root.join("a").join("b");
+
+    folo_fs::create_dir_all(&dir).await.unwrap();
+    folo_fs::create_dir_all(&dir).await.unwrap();
+    assert!(dir.is_dir());
+
+    let original = dir.join("original.txt");
+    fs::write(&original, CONTENT).unwrap();
+
+    let copied = dir.join("copied.txt");
+    assert_eq!(
+        folo_fs::copy(&original, &copied).await.unwrap(),
+        CONTENT.len() as u64
+    );
+    assert_eq!(fs::read(&copied).unwrap(), CONTENT);
+
+    let renamed = root.join("renamed.txt");
+    folo_fs::rename(&copied, &renamed).await.unwrap();
+    assert!(!copied.exists());
+    assert_eq!(fs::read(&renamed).unwrap(), CONTENT);
+
+    folo_fs::remove_file(&renamed).await.unwrap();
+    assert!(!renamed.exists());
+    assert!(folo_fs::remove_file(&renamed).await.is_err());
+
+    folo_fs::remove_dir_all(&root).await.unwrap();
+    assert!(!root.exists());
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn file_sync_and_preallocate() {
+    let path = env::temp_dir().join(format!("folo-file-sync-{}.bin", process::id()));
+
+    let mut file = File::create(&path).await.unwrap();
+
+    // Reserving space does not change the length of the file.
+    file.preallocate(1024 * 1024).await.unwrap();
+    assert_eq!(folo_fs::metadata(&path).await.unwrap().len(), 0);
+
+    let mut buffer = Buffer::<Isolated>::from_pool();
+    buffer.as_mut_slice()[..CONTENT.len()].copy_from_slice(CONTENT);
+    buffer.set_len(CONTENT.len());
+
+    file.write(buffer).await.unwrap();
+    file.sync_data().await.unwrap();
+    file.sync_all().await.unwrap();
+
+    assert_eq!(fs::read(&path).unwrap(), CONTENT);
+
+    drop(file);
+    _ = fs::remove_file(&path);
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn direct_file_io() {
+    let path = env::temp_dir().join(format!("folo-direct-file-{}.bin", process::id()));
+
+    let file = DirectFile::create(&path).await.unwrap();
+
+    let mut buffer = AlignedBuffer::from_pool();
+    buffer.set_len(AlignedBuffer::ALIGNMENT);
+    buffer.as_mut_slice().fill(7);
+
+    let buffer = file
+        .write_at(AlignedBuffer::ALIGNMENT, buffer)
+        .await
+        .unwrap();
+    assert_eq!(buffer.len(), AlignedBuffer::ALIGNMENT);
+
+    // Offsets must be aligned.
+    assert!(file.read_at(1, buffer.use_all()).await.is_err());
+
+    // The read reaches the end of the file, so it returns only the bytes that exist.
+    let buffer = file.read_at(0, AlignedBuffer::from_pool()).await.unwrap();
+    assert_eq!(buffer.len(), 2 * AlignedBuffer::ALIGNMENT);
+    assert!(buffer.as_slice()[AlignedBuffer::ALIGNMENT..]
+        .iter()
+        .all(|b| *b == 7));
+
+    // A buffer with an unaligned length cannot be used until the length is set again.
+    file.set_len(AlignedBuffer::ALIGNMENT + 10).unwrap();
+    let buffer = file.read_at(0, buffer.use_all()).await.unwrap();
+    assert_eq!(buffer.len(), AlignedBuffer::ALIGNMENT + 10);
+    assert!(file.write_at(0, buffer).await.is_err());
+
+    drop(file);
+    _ = fs::remove_file(&path);
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn mmap_read_only_and_copy_on_write() {
+    let path = env::temp_dir().join(format!("folo-mmap-{}.bin", process::id()));
+    fs::write(&path, CONTENT).unwrap();
+
+    // SAFETY: Nobody modifies the file while it is mapped.
+    let mut mmap = unsafe { Mmap::open(&path) }.await.unwrap();
+    assert_eq!(mmap.len(), CONTENT.len());
+
+    mmap.advise(0..mmap.len()).unwrap();
+    mmap.prefetch(0..mmap.len()).await.unwrap();
+    assert_eq!(&*mmap, CONTENT);
+    assert!(mmap.as_mut_slice().is_none());
+
+    drop(mmap);
+
+    // SAFETY: Nobody modifies the file while it is mapped.
+    let mut mmap = unsafe { Mmap::open_copy_on_write(&path) }.await.unwrap();
+    mmap.as_mut_slice().unwrap()[..5].copy_from_slice(b"HELLO");
+    assert_eq!(&mmap[..5], b"HELLO");
+
+    // Changes to a copy-on-write mapping do not reach the file.
+    drop(mmap);
+    assert_eq!(fs::read(&path).unwrap(), CONTENT);
+
+    _ = fs::remove_file(&path);
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn read_ahead_reader_streams_file_in_order() {
+    let path = env::temp_dir().join(format!("folo-read-ahead-{}.bin", process::id()));
+
+    // Enough data for several chunks, with the last one shorter than the others.
+    let content = (0..10_000u32).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+    fs::write(&path, &content).unwrap();
+
+    let mut reader = ReadAheadReader::builder(&path)
+        .window(3)
+        .chunk_size(1024)
+        .open()
+        .await
+        .unwrap();
+
+    let mut read = Vec::new();
+
+    while let Some(chunk) = reader.next_chunk().await.unwrap() {
+        read.extend_from_slice(&chunk.as_slice());
+    }
+
+    assert_eq!(read, content);
+    assert_eq!(reader.bytes_read(), content.len() as u64);
+
+    // The end of the file keeps being reported as such.
+    assert!(reader.next_chunk().await.unwrap().is_none());
+
+    drop(reader);
+    _ = fs::remove_file(&path);
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn record_reader_with_custom_delimiter_and_undelimited_last_record() {
+    let path = env::temp_dir().join(format!("folo-records-custom-{}.bin", process::id()));
+    fs::write(&path, b"a;bb;;ccc").unwrap();
+
+    let mut reader = RecordReader::builder(&path)
+        .delimiter(b';')
+        .open()
+        .await
+        .unwrap();
+
+    let mut records = Vec::new();
+
+    while let Some(record) = reader.next_record().await.unwrap() {
+        records.push(record);
+    }
+
+    assert_eq!(records, [&b"a"[..], b"bb", b"", b"ccc"]);
+    assert_eq!(reader.records_read(), 4);
+
+    // The end of the file keeps being reported as such.
+    assert!(reader.next_record().await.unwrap().is_none());
+
+    drop(reader);
+    _ = fs::remove_file(&path);
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn record_reader_rejects_oversized_delimited_record() {
+    let path = env::temp_dir().join(format!("folo-records-delimited-{}.bin", process::id()));
+
+    // The oversized record arrives in the same chunk as its delimiter.
+    let mut content = b"short\n".to_vec();
+    content.extend_from_slice(&[b'x'; 100]);
+    content.extend_from_slice(b"\nshort\n");
+    fs::write(&path, &content).unwrap();
+
+    let mut reader = RecordReader::builder(&path)
+        .max_record_size(10)
+        .open()
+        .await
+        .unwrap();
+
+    assert_eq!(reader.next_record().await.unwrap().unwrap(), b"short");
+    assert!(reader.next_record().await.is_err());
+
+    drop(reader);
+    _ = fs::remove_file(&path);
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn record_reader_rejects_oversized_undelimited_record() {
+    let path = env::temp_dir().join(format!("folo-records-undelimited-{}.bin", process::id()));
+
+    let mut content = b"short\n".to_vec();
+    content.extend_from_slice(&[b'x'; 100]);
+    fs::write(&path, &content).unwrap();
+
+    let mut reader = RecordReader::builder(&path)
+        .max_record_size(10)
+        .open()
+        .await
+        .unwrap();
+
+    assert_eq!(reader.next_record().await.unwrap().unwrap(), b"short");
+    assert!(reader.next_record().await.is_err());
+
+    drop(reader);
+    _ = fs::remove_file(&path);
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn file_io_with_background_priority() {
+    let path = env::temp_dir().join(format!("folo-file-priority-{}.bin", process::id()));
+
+    let file = File::create(&path).await.unwrap();
+    file.set_io_priority(IoPriority::Background).unwrap();
+
+    let mut buffer = Buffer::<Isolated>::from_pool();
+    buffer.as_mut_slice()[..CONTENT.len()].copy_from_slice(CONTENT);
+    buffer.set_len(CONTENT.len());
+
+    let buffer = file.write_at(0, buffer).await.unwrap();
+    let buffer = file.read_at(0, buffer.use_all()).await.unwrap();
+    assert_eq!(&*buffer.as_slice(), CONTENT);
+
+    file.set_io_priority(IoPriority::Normal).unwrap();
+
+    drop(file);
+    fs::remove_file(&path).unwrap();
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn temp_file_and_dir_are_deleted() {
+    let dir = TempDir::new().await.unwrap();
+    let dir_path = dir.path().to_path_buf();
+    assert!(dir_path.is_dir());
+
+    let mut file = TempFile::new_in(&dir).await.unwrap();
+    let file_path = file.path().to_path_buf();
+    assert!(file_path.starts_with(&dir_path));
+
+    let mut buffer = Buffer::<Isolated>::from_pool();
+    buffer.as_mut_slice()[..CONTENT.len()].copy_from_slice(CONTENT);
+    buffer.set_len(CONTENT.len());
+
+    let buffer = file.write(buffer).await.unwrap();
+    let buffer = file.read_at(0, buffer.use_all()).await.unwrap();
+    assert_eq!(&*buffer.as_slice(), CONTENT);
+
+    // Every temporary file gets its own name.
+    let other = TempFile::new_in(&dir).await.unwrap();
+    assert_ne!(other.path(), file_path);
+
+    drop(file);
+    drop(other);
+
+    // The handles are closed on a synchronous worker thread, so the files may linger briefly.
+    dir.close().await.unwrap();
+    assert!(!dir_path.exists());
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn io_quota_accounts_and_throttles_file_io() {
+    let path = env::temp_dir().join(format!("folo-file-quota-{}.bin", process::id()));
+
+    // Ten writes are just over one second worth of budget, so the last one has to wait.
+    let quota = IoQuotaBuilder::new("fs_test").bytes_per_second(100).build();
+
+    quota
+        .scope(async {
+            let mut file = File::create(&path).await.unwrap();
+
+            for _ in 0..10 {
+                let mut buffer = Buffer::<Isolated>::from_pool();
+                buffer.as_mut_slice()[..CONTENT.len()].copy_from_slice(CONTENT);
+                buffer.set_len(CONTENT.len());
+
+                file.write(buffer).await.unwrap();
+            }
+        })
+        .await;
+
+    let usage = quota.usage();
+    assert!(usage.operations >= 10);
+    assert_eq!(usage.bytes, 10 * CONTENT.len() as u64);
+    assert!(!usage.throttled.is_zero());
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn file_bound_to_foreign_port_falls_back_to_blocking() {
+    let folo = RuntimeBuilder::new().build().unwrap();
+    assert_eq!(folo.io_backend(), IoBackend::CompletionPort);
+
+    let path = env::temp_dir().join(format!("folo-file-fallback-{}.bin", process::id()));
+
+    let std_file = fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .custom_flags(FILE_FLAG_OVERLAPPED.0)
+        .open(&path)
+        .unwrap();
+
+    // Raw handles are not Send, so they are passed around as plain numbers.
+    //
+    // SAFETY: We create a completion port of our own and associate the file with it, which keeps
+    // the runtime from associating the file with the completion port of its worker thread.
+    let (port, handle) = unsafe {
+        let port = CreateIoCompletionPort(INVALID_HANDLE_VALUE, None, 0, 1).unwrap();
+        let handle = HANDLE(std_file.into_raw_handle());
+        CreateIoCompletionPort(handle, port, 0, 1).unwrap();
+        (port.0 as usize, handle.0 as usize)
+    };
+
+    futures::executor::block_on(folo.spawn_on_any(move || async move {
+        // SAFETY: We own the handle and do not touch it after this.
+        let file = unsafe { File::from_raw_handle(HANDLE(handle as _)) }.unwrap();
+
+        let mut buffer = Buffer::<Isolated>::from_pool();
+        buffer.as_mut_slice()[..CONTENT.len()].copy_from_slice(CONTENT);
+        buffer.set_len(CONTENT.len());
+
+        let buffer = file.write_at(0, buffer).await.unwrap();
+        assert_eq!(buffer.len(), CONTENT.len());
+
+        let buffer = file.read_at(7, buffer.use_all()).await.unwrap();
+        assert_eq!(&*buffer.as_slice(), b"folo");
+    }));
+
+    assert_eq!(folo.io_backend(), IoBackend::BlockingFallback);
+
+    folo.stop();
+    folo.wait();
+
+    // SAFETY: The port is ours and nothing uses it any more.
+    unsafe { CloseHandle(HANDLE(port as _)) }.unwrap();
+    fs::remove_file(&path).unwrap();
+}
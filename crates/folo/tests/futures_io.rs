@@ -0,0 +1,43 @@
+#![cfg(feature = "futures-io")]
+
+use folo::{
+    io::FuturesIo,
+    net::{TcpListener, TcpStream},
+    rt::spawn,
+};
+use folo_testing::init_test_worker;
+use futures::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt};
+use std::net::{Ipv4Addr, SocketAddr};
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn futures_io_line_echo() {
+    let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+        .await
+        .unwrap();
+
+    let listen_addr = listener.local_addr().unwrap();
+
+    let server = spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut stream = FuturesIo::new(stream);
+
+        let mut line = String::new();
+        stream.read_line(&mut line).await.unwrap();
+        assert_eq!(line, "hello, folo\n");
+
+        stream.write_all(line.as_bytes()).await.unwrap();
+        stream.close().await.unwrap();
+    });
+
+    let mut client = FuturesIo::new(TcpStream::connect(listen_addr).await.unwrap());
+
+    client.write_all(b"hello, folo\n").await.unwrap();
+    client.flush().await.unwrap();
+
+    let mut response = Vec::new();
+    client.read_to_end(&mut response).await.unwrap();
+    assert_eq!(response, b"hello, folo\n");
+
+    client.close().await.unwrap();
+    server.await;
+}
@@ -0,0 +1,150 @@
+#![cfg(feature = "grpc")]
+
+use folo::{
+    grpc::{serve_connection, Channel},
+    hyper::FoloIo,
+    net::TcpListener,
+    rt::spawn,
+};
+use folo_testing::init_test_worker;
+use hello_world::{
+    greeter_client::GreeterClient,
+    greeter_server::{Greeter, GreeterServer},
+    HelloReply, HelloRequest,
+};
+use std::net::{Ipv4Addr, SocketAddr};
+use tonic::{Request, Response, Status};
+
+pub mod hello_world {
+    tonic::include_proto!("greet");
+}
+
+#[derive(Default)]
+struct MyGreeter {}
+
+#[tonic::async_trait]
+impl Greeter for MyGreeter {
+    async fn say_hello(
+        &self,
+        request: Request<HelloRequest>,
+    ) -> Result<Response<HelloReply>, Status> {
+        Ok(Response::new(HelloReply {
+            message: format!("Hello {}!", request.into_inner().name),
+        }))
+    }
+}
+
+fn hello_request() -> HelloRequest {
+    HelloRequest {
+        name: "Folo".to_string(),
+    }
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn unary_call_over_tcp() {
+    let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+        .await
+        .unwrap();
+
+    let listen_addr = listener.local_addr().unwrap();
+
+    let server = spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+
+        serve_connection(
+            FoloIo::new(stream),
+            GreeterServer::new(MyGreeter::default()),
+        )
+        .await
+        .unwrap();
+    });
+
+    let channel = Channel::connect("127.0.0.1", listen_addr.port())
+        .await
+        .unwrap();
+    let mut client = GreeterClient::new(channel);
+
+    // Requests from clones of the channel are multiplexed over the same connection.
+    let mut other_client = client.clone();
+
+    let (reply, other_reply) = futures::join!(
+        client.say_hello(hello_request()),
+        other_client.say_hello(hello_request())
+    );
+
+    assert_eq!(reply.unwrap().into_inner().message, "Hello Folo!");
+    assert_eq!(other_reply.unwrap().into_inner().message, "Hello Folo!");
+
+    // Dropping the last client closes the connection, which ends it on the server as well.
+    drop(client);
+    drop(other_client);
+    server.await;
+}
+
+#[cfg(feature = "tls")]
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn unary_call_over_tls() {
+    use folo::{
+        hyper::FoloTlsIo,
+        tls::{TlsAcceptor, TlsConnector},
+    };
+    use rustls::{
+        crypto::ring,
+        pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer},
+        ClientConfig, RootCertStore, ServerConfig,
+    };
+    use std::sync::Arc;
+
+    let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+    let certificate = certified.cert.der().clone();
+    let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(certified.key_pair.serialize_der()));
+
+    let mut server_config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_no_client_auth()
+        .with_single_cert(vec![certificate.clone()], key)
+        .unwrap();
+    server_config.alpn_protocols = vec![b"h2".to_vec()];
+
+    let mut roots = RootCertStore::empty();
+    roots.add(certificate).unwrap();
+
+    let mut client_config = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    client_config.alpn_protocols = vec![b"h2".to_vec()];
+
+    let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+        .await
+        .unwrap();
+
+    let listen_addr = listener.local_addr().unwrap();
+    let acceptor = TlsAcceptor::new(Arc::new(server_config));
+
+    let server = spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let stream = acceptor.accept(stream).await.unwrap();
+
+        serve_connection(
+            FoloTlsIo::new(stream),
+            GreeterServer::new(MyGreeter::default()),
+        )
+        .await
+        .unwrap();
+    });
+
+    let connector = TlsConnector::new(Arc::new(client_config));
+    let channel = Channel::connect_tls("localhost", listen_addr.port(), &connector)
+        .await
+        .unwrap();
+    let mut client = GreeterClient::new(channel);
+
+    let reply = client.say_hello(hello_request()).await.unwrap();
+    assert_eq!(reply.into_inner().message, "Hello Folo!");
+
+    drop(client);
+    server.await;
+}
@@ -0,0 +1,146 @@
+#![cfg(feature = "hyper")]
+
+use bytes::Bytes;
+use folo::{
+    hyper::FoloIo,
+    net::{TcpListener, TcpStream},
+    rt::spawn,
+};
+use folo_testing::init_test_worker;
+use http::{Request, Response, StatusCode};
+use http_body_util::{BodyExt, Empty, Full};
+use hyper::{body::Incoming, service::service_fn};
+use std::{
+    convert::Infallible,
+    net::{Ipv4Addr, SocketAddr},
+};
+
+const MESSAGE: &[u8] = b"hello, folo";
+
+async fn hello(_request: Request<Incoming>) -> Result<Response<Full<Bytes>>, Infallible> {
+    Ok(Response::new(Full::new(Bytes::from_static(MESSAGE))))
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn http1_request_over_tcp() {
+    let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+        .await
+        .unwrap();
+
+    let listen_addr = listener.local_addr().unwrap();
+
+    let server = spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+
+        hyper::server::conn::http1::Builder::new()
+            .serve_connection(FoloIo::new(stream), service_fn(hello))
+            .await
+            .unwrap();
+    });
+
+    let stream = TcpStream::connect(listen_addr).await.unwrap();
+    let (mut sender, connection) = hyper::client::conn::http1::handshake(FoloIo::new(stream))
+        .await
+        .unwrap();
+
+    let connection = spawn(connection);
+
+    let response = sender
+        .send_request(Request::new(Empty::<Bytes>::new()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(&body[..], MESSAGE);
+
+    // Dropping the sender closes the connection, which ends the connection on the server as well.
+    drop(sender);
+    connection.await.unwrap();
+    server.await;
+}
+
+#[cfg(feature = "tls")]
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn http2_request_over_tls() {
+    use folo::{
+        hyper::{FoloExecutor, FoloTlsIo},
+        tls::{TlsAcceptor, TlsConnector},
+    };
+    use rustls::{
+        crypto::ring,
+        pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer},
+        ClientConfig, RootCertStore, ServerConfig,
+    };
+    use std::sync::Arc;
+
+    let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+    let certificate = certified.cert.der().clone();
+    let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(certified.key_pair.serialize_der()));
+
+    let mut server_config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_no_client_auth()
+        .with_single_cert(vec![certificate.clone()], key)
+        .unwrap();
+    server_config.alpn_protocols = vec![b"h2".to_vec()];
+
+    let mut roots = RootCertStore::empty();
+    roots.add(certificate).unwrap();
+
+    let mut client_config = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    client_config.alpn_protocols = vec![b"h2".to_vec()];
+
+    let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+        .await
+        .unwrap();
+
+    let listen_addr = listener.local_addr().unwrap();
+    let acceptor = TlsAcceptor::new(Arc::new(server_config));
+
+    let server = spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let stream = acceptor.accept(stream).await.unwrap();
+
+        hyper::server::conn::http2::Builder::new(FoloExecutor::new())
+            .serve_connection(FoloTlsIo::new(stream), service_fn(hello))
+            .await
+            .unwrap();
+    });
+
+    let connector = TlsConnector::new(Arc::new(client_config));
+    let stream = TcpStream::connect(listen_addr).await.unwrap();
+    let stream = connector
+        .connect("localhost".try_into().unwrap(), stream)
+        .await
+        .unwrap();
+
+    assert_eq!(stream.alpn_protocol(), Some(&b"h2"[..]));
+
+    let (mut sender, connection) =
+        hyper::client::conn::http2::handshake(FoloExecutor::new(), FoloTlsIo::new(stream))
+            .await
+            .unwrap();
+
+    let connection = spawn(connection);
+
+    let request = Request::builder()
+        .uri("https://localhost/")
+        .body(Empty::<Bytes>::new())
+        .unwrap();
+
+    let response = sender.send_request(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(&body[..], MESSAGE);
+
+    drop(sender);
+    connection.await.unwrap();
+    server.await;
+}
@@ -0,0 +1,163 @@
+use folo::{
+    fs::File,
+    io::{self, Buffer, OperationResultExt},
+    mem::isolation::Isolated,
+    net::{TcpListener, TcpStream},
+    rt::spawn,
+};
+use folo_testing::init_test_worker;
+use std::{
+    env, fs,
+    net::{Ipv4Addr, Shutdown, SocketAddr},
+    process,
+};
+
+async fn read_to_end(stream: &mut TcpStream) -> Vec<u8> {
+    let mut data = Vec::new();
+
+    loop {
+        let buffer = stream
+            .read(Buffer::<Isolated>::from_pool())
+            .await
+            .into_inner()
+            .unwrap();
+
+        if buffer.is_empty() {
+            return data;
+        }
+
+        data.extend_from_slice(&buffer.as_slice());
+    }
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn copy_bidirectional_through_proxy() {
+    let echo_listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+        .await
+        .unwrap();
+    let echo_addr = echo_listener.local_addr().unwrap();
+
+    let proxy_listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+        .await
+        .unwrap();
+    let proxy_addr = proxy_listener.local_addr().unwrap();
+
+    // The echo server sends everything back once the client has shut down its write direction.
+    let echo = spawn(async move {
+        let (mut stream, _) = echo_listener.accept().await.unwrap();
+
+        let data = read_to_end(&mut stream).await;
+
+        let mut buffer = Buffer::<Isolated>::from_pool();
+        buffer.as_mut_slice()[..data.len()].copy_from_slice(&data);
+        buffer.set_len(data.len());
+        stream.write(buffer).await.into_inner().unwrap();
+
+        stream.shutdown_with(Shutdown::Write).await.unwrap();
+    });
+
+    let proxy = spawn(async move {
+        let (mut client, _) = proxy_listener.accept().await.unwrap();
+        let mut backend = TcpStream::connect(echo_addr).await.unwrap();
+
+        io::copy_bidirectional(&mut client, &mut backend)
+            .await
+            .unwrap()
+    });
+
+    let mut client = TcpStream::connect(proxy_addr).await.unwrap();
+
+    let mut buffer = Buffer::<Isolated>::from_pool();
+    buffer.as_mut_slice()[..11].copy_from_slice(b"hello, folo");
+    buffer.set_len(11);
+    client.write(buffer).await.into_inner().unwrap();
+    client.shutdown_with(Shutdown::Write).await.unwrap();
+
+    assert_eq!(read_to_end(&mut client).await, b"hello, folo");
+
+    assert_eq!(proxy.await, (11, 11));
+    echo.await;
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn copy_between_streams() {
+    let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+        .await
+        .unwrap();
+    let listen_addr = listener.local_addr().unwrap();
+
+    let content = (0..200_000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+
+    // The sender writes the content in multiple buffers, which the relay copies to the receiver.
+    let sender = spawn({
+        let content = content.clone();
+
+        async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+
+            for chunk in content.chunks(Buffer::<Isolated>::from_pool().capacity()) {
+                let mut buffer = Buffer::<Isolated>::from_pool();
+                buffer.as_mut_slice()[..chunk.len()].copy_from_slice(chunk);
+                buffer.set_len(chunk.len());
+                stream.write(buffer).await.into_inner().unwrap();
+            }
+
+            stream.shutdown().await.unwrap();
+        }
+    });
+
+    let receive_listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+        .await
+        .unwrap();
+    let receive_addr = receive_listener.local_addr().unwrap();
+
+    let receiver = spawn(async move {
+        let (mut stream, _) = receive_listener.accept().await.unwrap();
+        read_to_end(&mut stream).await
+    });
+
+    let mut from = TcpStream::connect(listen_addr).await.unwrap();
+    let mut to = TcpStream::connect(receive_addr).await.unwrap();
+
+    let copied = io::copy(&mut from, &mut to).await.unwrap();
+    assert_eq!(copied, content.len() as u64);
+
+    to.shutdown().await.unwrap();
+
+    assert_eq!(receiver.await, content);
+
+    // The graceful shutdown of the sender waits for us to close our end of the connection.
+    drop(from);
+    sender.await;
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn copy_file_to_stream() {
+    let path = env::temp_dir().join(format!("folo-io-copy-file-{}.bin", process::id()));
+    let content = (0..200_000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+    fs::write(&path, &content).unwrap();
+
+    let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+        .await
+        .unwrap();
+    let listen_addr = listener.local_addr().unwrap();
+
+    let receiver = spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        read_to_end(&mut stream).await
+    });
+
+    let mut file = File::open(&path).await.unwrap();
+    file.set_position(1000);
+
+    let mut stream = TcpStream::connect(listen_addr).await.unwrap();
+    let copied = io::copy_file(&mut file, &mut stream).await.unwrap();
+    assert_eq!(copied, content.len() as u64 - 1000);
+    assert_eq!(file.position(), content.len());
+
+    stream.shutdown().await.unwrap();
+
+    assert_eq!(receiver.await, &content[1000..]);
+
+    _ = fs::remove_file(&path);
+}
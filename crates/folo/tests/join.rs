@@ -0,0 +1,69 @@
+use folo::rt::yield_now;
+use folo_testing::init_test_worker;
+use futures::future;
+use std::cell::Cell;
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn join_returns_outputs_in_order() {
+    let (a, b, c) = folo::join!(
+        async {
+            yield_now().await;
+            1
+        },
+        async { "two" },
+        async {
+            yield_now().await;
+            yield_now().await;
+            3.0
+        },
+    );
+
+    assert_eq!(a, 1);
+    assert_eq!(b, "two");
+    assert_eq!(c, 3.0);
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn join_polls_futures_concurrently() {
+    let progress = Cell::new(0);
+
+    // Each future waits for the other to make progress, which only works if both are polled.
+    folo::join!(
+        async {
+            progress.set(1);
+
+            while progress.get() != 2 {
+                yield_now().await;
+            }
+        },
+        async {
+            while progress.get() != 1 {
+                yield_now().await;
+            }
+
+            progress.set(2);
+        }
+    );
+
+    assert_eq!(progress.get(), 2);
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn try_join_returns_outputs_on_success() {
+    let result = folo::try_join!(async { Ok::<_, &str>(1) }, async {
+        yield_now().await;
+        Ok("two")
+    });
+
+    assert_eq!(result, Ok((1, "two")));
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn try_join_short_circuits_on_error() {
+    let result = folo::try_join!(future::pending::<Result<(), &str>>(), async {
+        yield_now().await;
+        Err::<(), _>("failed")
+    });
+
+    assert_eq!(result, Err("failed"));
+}
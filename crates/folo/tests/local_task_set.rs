@@ -0,0 +1,64 @@
+use folo::rt::{yield_now, LocalTaskSet};
+use folo_testing::init_test_worker;
+use futures::StreamExt;
+use std::{cell::Cell, rc::Rc};
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn results_are_streamed_in_completion_order() {
+    let mut set = LocalTaskSet::new();
+
+    for index in 0..5 {
+        set.push(async move {
+            // Later futures complete first.
+            for _ in index..5 {
+                yield_now().await;
+            }
+
+            index
+        });
+    }
+
+    assert_eq!(set.len(), 5);
+
+    let results = set.collect::<Vec<_>>().await;
+    assert_eq!(results, vec![4, 3, 2, 1, 0]);
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn futures_can_borrow_from_owner() {
+    let completed = Cell::new(0);
+
+    let mut set = (0..100)
+        .map(|_| async {
+            yield_now().await;
+            completed.set(completed.get() + 1);
+        })
+        .collect::<LocalTaskSet<_>>();
+
+    while set.next().await.is_some() {}
+
+    assert!(set.is_empty());
+    assert_eq!(completed.get(), 100);
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn clear_drops_pending_futures() {
+    let finished = Rc::new(Cell::new(false));
+
+    let mut set = LocalTaskSet::new();
+
+    set.push({
+        let finished = Rc::clone(&finished);
+
+        async move {
+            futures::future::pending::<()>().await;
+            finished.set(true);
+        }
+    });
+
+    set.clear();
+
+    assert!(set.is_empty());
+    assert_eq!(set.next().await, None);
+    assert!(!finished.get());
+}
@@ -0,0 +1,66 @@
+#![cfg(feature = "metrics-persistence")]
+
+use folo::{
+    metrics::{set_crash_report_path, set_metrics_persistence},
+    rt::{spawn_sync, RuntimeBuilder, SynchronousTaskType},
+};
+use std::{env, fs, path::Path, process, sync::Mutex, thread, time::Duration};
+
+// The persistence settings are process-wide, so the tests must not change them concurrently.
+static SETTINGS: Mutex<()> = Mutex::new(());
+
+#[test]
+fn final_report_is_persisted_on_shutdown() {
+    let _settings = SETTINGS.lock().unwrap_or_else(|e| e.into_inner());
+
+    let path = env::temp_dir().join(format!("folo-metrics-{}.json", process::id()));
+
+    let folo = RuntimeBuilder::new()
+        .max_processors(1)
+        .metrics_persistence(&path, Duration::from_millis(10))
+        .build()
+        .unwrap();
+
+    let result = futures::executor::block_on(
+        folo.spawn_on_any(|| async { spawn_sync(SynchronousTaskType::Syscall, || 42).await }),
+    );
+    assert_eq!(result, 42);
+
+    folo.stop();
+    folo.wait();
+
+    let report: serde_json::Value = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+    let tasks = &report["events"]["rt_sync_tasks"];
+    assert!(tasks["count"].as_u64().unwrap() >= 1);
+
+    // The report is written under a temporary name and renamed, so nothing is left behind.
+    let mut temp_path = path.clone().into_os_string();
+    temp_path.push(".tmp");
+    assert!(!Path::new(&temp_path).exists());
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn panic_writes_crash_report_and_persisted_report() {
+    let _settings = SETTINGS.lock().unwrap_or_else(|e| e.into_inner());
+
+    let report_path = env::temp_dir().join(format!("folo-metrics-panic-{}.json", process::id()));
+    let crash_path = env::temp_dir().join(format!("folo-crash-panic-{}.txt", process::id()));
+
+    // Both share the same panic hook, which writes both files.
+    set_crash_report_path(&crash_path);
+    set_metrics_persistence(&report_path, Duration::from_secs(3600));
+
+    assert!(thread::spawn(|| panic!("test panic")).join().is_err());
+
+    let report: serde_json::Value =
+        serde_json::from_slice(&fs::read(&report_path).unwrap()).unwrap();
+    assert!(report["events"].is_object());
+
+    let crash_report = fs::read_to_string(&crash_path).unwrap();
+    assert!(crash_report.contains("=== crash report: thread"));
+
+    fs::remove_file(&report_path).unwrap();
+    fs::remove_file(&crash_path).unwrap();
+}
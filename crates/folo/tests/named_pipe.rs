@@ -0,0 +1,105 @@
+use folo::{
+    io::Buffer,
+    mem::isolation::Isolated,
+    net::windows::{NamedPipeClientBuilder, NamedPipeServerBuilder},
+    rt::spawn,
+};
+use folo_testing::init_test_worker;
+use std::process;
+
+const MESSAGE: &[u8] = b"hello, folo";
+
+fn message_buffer() -> Buffer<Isolated> {
+    let mut buffer = Buffer::<Isolated>::from_pool();
+    buffer.as_mut_slice()[..MESSAGE.len()].copy_from_slice(MESSAGE);
+    buffer.set_len(MESSAGE.len());
+    buffer
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn named_pipe_byte_mode_echo() {
+    let name = format!(r"\\.\pipe\folo-test-bytes-{}", process::id());
+
+    let server = NamedPipeServerBuilder::new()
+        .name(&name)
+        .first_instance(true)
+        .build()
+        .await
+        .unwrap();
+
+    let server_task = spawn(async move {
+        server.connect().await.unwrap();
+
+        let buffer = server.read(Buffer::<Isolated>::from_pool()).await.unwrap();
+        server.write(buffer).await.unwrap();
+
+        // The client closing its end is seen as the end of the stream.
+        let buffer = server.read(Buffer::<Isolated>::from_pool()).await.unwrap();
+        assert!(buffer.is_empty());
+    });
+
+    let client = NamedPipeClientBuilder::new()
+        .name(&name)
+        .build()
+        .await
+        .unwrap();
+
+    client.write(message_buffer()).await.unwrap();
+
+    let buffer = client.read(Buffer::<Isolated>::from_pool()).await.unwrap();
+    assert_eq!(&*buffer.as_slice(), MESSAGE);
+
+    drop(client);
+    server_task.await;
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn named_pipe_message_mode_reports_partial_messages() {
+    let name = format!(r"\\.\pipe\folo-test-messages-{}", process::id());
+
+    let server = NamedPipeServerBuilder::new()
+        .name(&name)
+        .message_mode(true)
+        .build()
+        .await
+        .unwrap();
+
+    let client = NamedPipeClientBuilder::new()
+        .name(&name)
+        .message_mode(true)
+        .build()
+        .await
+        .unwrap();
+
+    // The client has already connected, so this completes immediately.
+    server.connect().await.unwrap();
+
+    server.write(message_buffer()).await.unwrap();
+
+    // Only room for the first 4 bytes of the message.
+    let mut buffer = Buffer::<Isolated>::from_pool();
+    buffer.set_len(4);
+
+    let first = client.read_message(buffer).await.unwrap();
+    assert!(!first.is_message_end());
+    assert_eq!(&*first.buffer().as_slice(), &MESSAGE[..4]);
+
+    let rest = client
+        .read_message(Buffer::<Isolated>::from_pool())
+        .await
+        .unwrap();
+    assert!(rest.is_message_end());
+    assert_eq!(&*rest.buffer().as_slice(), &MESSAGE[4..]);
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn named_pipe_server_requires_valid_options() {
+    assert!(NamedPipeServerBuilder::new().build().await.is_err());
+
+    assert!(NamedPipeServerBuilder::new()
+        .name(r"\\.\pipe\folo-test-invalid")
+        .max_instances(255)
+        .build()
+        .await
+        .is_err());
+}
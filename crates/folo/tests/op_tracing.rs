@@ -0,0 +1,120 @@
+#![cfg(feature = "op-tracing")]
+
+use folo::{
+    io::set_operation_trace_sample_interval,
+    net::{TcpListener, TcpStream},
+    rt::spawn,
+};
+use std::{
+    cell::Cell,
+    collections::HashSet,
+    fmt::Debug,
+    net::{Ipv4Addr, SocketAddr},
+    sync::Mutex,
+};
+use tracing::{
+    field::{Field, Visit},
+    subscriber::DefaultGuard,
+    Event, Subscriber,
+};
+use tracing_subscriber::{
+    layer::{Context, SubscriberExt},
+    Layer,
+};
+
+// The message and operation ID of each operation trace event emitted on the worker threads.
+static EVENTS: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+
+struct RecordOperationEvents;
+
+impl<S> Layer<S> for RecordOperationEvents
+where
+    S: Subscriber,
+{
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut fields = OperationFields::default();
+        event.record(&mut fields);
+
+        if let (Some(message), Some(operation_id)) = (fields.message, fields.operation_id) {
+            EVENTS.lock().unwrap().push((message, operation_id));
+        }
+    }
+}
+
+#[derive(Default)]
+struct OperationFields {
+    message: Option<String>,
+    operation_id: Option<String>,
+}
+
+impl Visit for OperationFields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = Some(value.to_owned());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        match field.name() {
+            "message" => self.message = Some(format!("{value:?}")),
+            "operation_id" => self.operation_id = Some(format!("{value:?}")),
+            _ => {}
+        }
+    }
+}
+
+thread_local! {
+    static TRACING_CONFIG_GUARD: Cell<Option<DefaultGuard>> = const { Cell::new(None) };
+}
+
+fn init_recording_worker() {
+    set_operation_trace_sample_interval(1);
+
+    let subscriber = tracing_subscriber::registry().with(RecordOperationEvents);
+
+    TRACING_CONFIG_GUARD.set(Some(tracing::subscriber::set_default(subscriber)));
+}
+
+#[folo::test(worker_init_fn = init_recording_worker)]
+async fn sampled_operations_trace_submit_complete_and_dispatch() {
+    let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+        .await
+        .unwrap();
+
+    let listen_addr = listener.local_addr().unwrap();
+
+    // Accepting uses a thread-safe operation and connecting a thread-local one, so both kinds of
+    // operations take part.
+    let server = spawn(async move {
+        listener.accept().await.unwrap();
+    });
+
+    let _client = TcpStream::connect(listen_addr).await.unwrap();
+    server.await;
+
+    let events = EVENTS.lock().unwrap();
+
+    let submitted = events
+        .iter()
+        .filter(|(message, _)| message == "I/O operation submitted")
+        .map(|(_, operation_id)| operation_id)
+        .collect::<Vec<_>>();
+
+    // Every operation has its own ID.
+    assert!(submitted.len() >= 2);
+    assert_eq!(
+        submitted.iter().collect::<HashSet<_>>().len(),
+        submitted.len()
+    );
+
+    // The dispatch event is only emitted once both the submit and the completion were recorded.
+    let dispatched = events
+        .iter()
+        .filter(|(message, _)| message == "I/O operation dispatched")
+        .map(|(_, operation_id)| operation_id)
+        .collect::<HashSet<_>>();
+
+    for operation_id in submitted {
+        assert!(dispatched.contains(operation_id));
+    }
+}
@@ -0,0 +1,83 @@
+use folo::{
+    io::Buffer,
+    mem::isolation::Isolated,
+    process::{ChildStdout, Command, Stdio},
+};
+use folo_testing::init_test_worker;
+
+async fn read_to_end(stdout: &ChildStdout) -> Vec<u8> {
+    let mut data = Vec::new();
+
+    loop {
+        let buffer = stdout.read(Buffer::<Isolated>::from_pool()).await.unwrap();
+
+        if buffer.is_empty() {
+            return data;
+        }
+
+        data.extend_from_slice(&buffer.as_slice());
+    }
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn process_output_and_exit_code() {
+    let mut child = Command::new("cmd")
+        .args(["/C", "echo hello, folo && exit 3"])
+        .stdout(Stdio::Piped)
+        .spawn()
+        .await
+        .unwrap();
+
+    let output = read_to_end(child.stdout().unwrap()).await;
+    assert_eq!(output, b"hello, folo \r\n");
+
+    let status = child.wait().await.unwrap();
+    assert_eq!(status.code(), Some(3));
+
+    // The exit status remains available after the process has exited.
+    assert_eq!(child.try_wait().unwrap().unwrap().code(), Some(3));
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn process_stdin_is_piped_to_stdout() {
+    let mut child = Command::new("findstr")
+        .arg("^")
+        .stdin(Stdio::Piped)
+        .stdout(Stdio::Piped)
+        .spawn()
+        .await
+        .unwrap();
+
+    let stdin = child.take_stdin().unwrap();
+
+    let mut buffer = Buffer::<Isolated>::from_pool();
+    buffer.as_mut_slice()[..13].copy_from_slice(b"hello, folo\r\n");
+    buffer.set_len(13);
+    stdin.write(buffer).await.unwrap();
+
+    // Closing the standard input lets the child process finish.
+    drop(stdin);
+
+    let output = read_to_end(child.stdout().unwrap()).await;
+    assert_eq!(output, b"hello, folo\r\n");
+
+    assert!(child.wait().await.unwrap().success());
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn process_kill() {
+    let mut child = Command::new("ping")
+        .args(["-n", "30", "127.0.0.1"])
+        .stdout(Stdio::Null)
+        .kill_on_drop(true)
+        .spawn()
+        .await
+        .unwrap();
+
+    assert!(child.try_wait().unwrap().is_none());
+
+    child.kill().unwrap();
+
+    let status = child.wait().await.unwrap();
+    assert!(!status.success());
+}
@@ -0,0 +1,59 @@
+use folo::{
+    io::{Buffer, OperationResultExt},
+    mem::isolation::Isolated,
+    net::{RawSocket, UdpSocket},
+};
+use folo_testing::init_test_worker;
+use std::net::{Ipv4Addr, SocketAddr};
+
+// Values of AF_INET, SOCK_DGRAM and IPPROTO_UDP. Raw sockets require administrative privileges,
+// so we exercise the generic socket with a protocol that does not.
+const AF_INET: i32 = 2;
+const SOCK_DGRAM: i32 = 2;
+const IPPROTO_UDP: i32 = 17;
+
+const MESSAGE: &[u8] = b"hello, folo";
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn raw_socket_with_custom_protocol_triple() {
+    let socket = RawSocket::new(AF_INET, SOCK_DGRAM, IPPROTO_UDP)
+        .await
+        .unwrap();
+    socket
+        .bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+        .unwrap();
+
+    let peer = UdpSocket::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+        .await
+        .unwrap();
+
+    let mut buffer = Buffer::<Isolated>::from_pool();
+    buffer.as_mut_slice()[..MESSAGE.len()].copy_from_slice(MESSAGE);
+    buffer.set_len(MESSAGE.len());
+
+    socket
+        .send_to(buffer, peer.local_addr().unwrap())
+        .await
+        .into_inner()
+        .unwrap();
+
+    let datagram = peer
+        .recv_from(Buffer::<Isolated>::from_pool())
+        .await
+        .unwrap();
+    assert_eq!(datagram.peer_addr(), socket.local_addr().unwrap());
+    assert_eq!(&*datagram.buffer().as_slice(), MESSAGE);
+
+    let peer_addr = datagram.peer_addr();
+    peer.send_to(datagram.into_buffer(), peer_addr)
+        .await
+        .into_inner()
+        .unwrap();
+
+    let echoed = socket
+        .recv_from(Buffer::<Isolated>::from_pool())
+        .await
+        .unwrap();
+    assert_eq!(echoed.peer_addr(), peer.local_addr().unwrap());
+    assert_eq!(&*echoed.buffer().as_slice(), MESSAGE);
+}
@@ -0,0 +1,117 @@
+#![cfg(feature = "config")]
+
+use folo::{
+    config,
+    rt::{Affinity, IdleStrategy, Profile, RuntimeBuilder, RuntimeConfig},
+};
+use std::env;
+
+#[test]
+fn config_deserializes_from_json() {
+    let config: RuntimeConfig = serde_json::from_str(
+        r#"{
+            "max_processors": 2,
+            "affinity": { "processors": [0, 1] },
+            "profile": "throughput",
+            "idle_strategy": { "spin_then_park": { "spin_cycles": 10, "yield_cycles": 5 } },
+            "sync_workers_per_processor": 3,
+            "slow_poll_threshold_ms": 250
+        }"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        config,
+        RuntimeConfig {
+            max_processors: Some(2),
+            affinity: Some(Affinity::Processors(vec![0, 1])),
+            profile: Some(Profile::Throughput),
+            idle_strategy: Some(IdleStrategy::SpinThenPark {
+                spin_cycles: 10,
+                yield_cycles: 5
+            }),
+            sync_workers_per_processor: Some(3),
+            slow_poll_threshold_ms: Some(250),
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+fn unknown_option_is_rejected() {
+    // A typo in a deployment configuration must not be silently ignored.
+    let result = serde_json::from_str::<RuntimeConfig>(r#"{ "max_procesors": 2 }"#);
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn runtime_is_built_from_config() {
+    let config = RuntimeConfig {
+        max_processors: Some(1),
+        profile: Some(Profile::LowLatency),
+        sync_workers_per_processor: Some(1),
+        ..Default::default()
+    };
+
+    let runtime = RuntimeBuilder::from_config(&config).build().unwrap();
+
+    assert_eq!(runtime.worker_count(), 1);
+
+    let result = futures::executor::block_on(runtime.spawn_on_any(|| async {
+        folo::rt::spawn_sync(folo::rt::SynchronousTaskType::Syscall, || 42).await
+    }));
+
+    assert_eq!(result, 42);
+
+    runtime.stop();
+    runtime.wait();
+}
+
+#[test]
+fn merge_keeps_options_not_overridden() {
+    let mut config = RuntimeConfig {
+        max_processors: Some(8),
+        profile: Some(Profile::Throughput),
+        ..Default::default()
+    };
+
+    config.merge(RuntimeConfig {
+        max_processors: Some(2),
+        stealing: Some(false),
+        ..Default::default()
+    });
+
+    assert_eq!(config.max_processors, Some(2));
+    assert_eq!(config.profile, Some(Profile::Throughput));
+    assert_eq!(config.stealing, Some(false));
+}
+
+// This is the only test that touches the environment, so it does not race with other tests.
+#[test]
+fn env_overrides_are_loaded() {
+    env::set_var("FOLO_WORKER_THREADS", "3");
+    env::set_var("FOLO_PROFILE", "low_latency");
+    env::set_var("FOLO_BLOCKING_THREADS", " 4 ");
+
+    let config = RuntimeConfig::from_env().unwrap();
+
+    assert_eq!(config.max_processors, Some(3));
+    assert_eq!(config.profile, Some(Profile::LowLatency));
+    assert_eq!(config.sync_workers_per_processor, Some(4));
+    assert_eq!(config.stealing, None);
+
+    env::set_var("FOLO_PROFILE", "fastest");
+
+    assert!(matches!(
+        RuntimeConfig::from_env(),
+        Err(config::Error::InvalidEnvironmentVariable {
+            name: "FOLO_PROFILE",
+            ..
+        })
+    ));
+
+    env::remove_var("FOLO_WORKER_THREADS");
+    env::remove_var("FOLO_PROFILE");
+    env::remove_var("FOLO_BLOCKING_THREADS");
+}
@@ -0,0 +1,60 @@
+#![cfg(feature = "runtime-tracing")]
+
+use folo::rt::{spawn, yield_now};
+use std::{cell::Cell, sync::Mutex};
+use tracing::{
+    span::{Attributes, Id},
+    subscriber::DefaultGuard,
+    Subscriber,
+};
+use tracing_subscriber::{
+    layer::{Context, SubscriberExt},
+    registry::LookupSpan,
+    Layer,
+};
+
+// The name of each span created on the worker threads, together with the name of its parent.
+static SPANS: Mutex<Vec<(&'static str, Option<&'static str>)>> = Mutex::new(Vec::new());
+
+struct RecordSpans;
+
+impl<S> Layer<S> for RecordSpans
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, _attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let span = ctx.span(id).expect("span was just created");
+
+        SPANS
+            .lock()
+            .unwrap()
+            .push((span.name(), span.parent().map(|parent| parent.name())));
+    }
+}
+
+thread_local! {
+    static TRACING_CONFIG_GUARD: Cell<Option<DefaultGuard>> = const { Cell::new(None) };
+}
+
+fn init_recording_worker() {
+    let subscriber = tracing_subscriber::registry().with(RecordSpans);
+
+    TRACING_CONFIG_GUARD.set(Some(tracing::subscriber::set_default(subscriber)));
+}
+
+#[folo::test(worker_init_fn = init_recording_worker)]
+async fn task_span_is_child_of_spawning_span() {
+    let request_span = tracing::info_span!("request");
+
+    let task = {
+        let _entered = request_span.enter();
+
+        spawn(async {
+            yield_now().await;
+        })
+    };
+
+    task.await;
+
+    assert!(SPANS.lock().unwrap().contains(&("task", Some("request"))));
+}
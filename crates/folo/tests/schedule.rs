@@ -0,0 +1,26 @@
+use folo::time::{schedule, Clock, Delay, Schedule};
+use folo_testing::init_test_worker;
+use std::time::Duration;
+
+const PERIOD: Duration = Duration::from_millis(10);
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn scheduled_job_runs_until_stopped() {
+    let clock = Clock::new();
+
+    let job = schedule(Schedule::every(PERIOD), || async {});
+
+    while job.runs() < 2 {
+        Delay::with_clock(&clock, PERIOD).await;
+    }
+
+    assert!(job.last_run().is_some());
+    assert!(job.last_duration().is_some());
+
+    job.stop();
+    let runs = job.runs();
+
+    Delay::with_clock(&clock, PERIOD * 5).await;
+
+    assert_eq!(job.runs(), runs);
+}
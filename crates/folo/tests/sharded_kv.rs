@@ -0,0 +1,51 @@
+use folo::{collections::ShardedKv, rt::spawn_on_all};
+use folo_testing::init_test_worker;
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn values_visible_from_all_workers() {
+    let store = ShardedKv::<u32, String>::new();
+
+    for key in 0..100 {
+        assert_eq!(store.insert(key, key.to_string()).await, None);
+    }
+
+    let checks = spawn_on_all({
+        let store = store.clone();
+
+        move || {
+            let store = store.clone();
+
+            move || async move {
+                for key in 0..100 {
+                    assert_eq!(store.get(key).await, Some(key.to_string()));
+                }
+            }
+        }
+    });
+
+    for check in checks.into_vec() {
+        check.await;
+    }
+
+    assert_eq!(store.len().await, 100);
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn update_and_remove() {
+    let store = ShardedKv::<&'static str, u64>::new();
+
+    store.insert("counter", 1).await;
+
+    let previous = store
+        .update("counter", |value| {
+            let value = value.unwrap();
+            *value += 10;
+            *value - 10
+        })
+        .await;
+
+    assert_eq!(previous, 1);
+    assert_eq!(store.remove("counter").await, Some(11));
+    assert_eq!(store.get("counter").await, None);
+    assert!(store.update("counter", |value| value.is_none()).await);
+}
@@ -0,0 +1,49 @@
+use folo::{
+    collections::ShardedLocal,
+    rt::{current_worker_index, for_each_worker, worker_count},
+};
+use folo_testing::init_test_worker;
+use std::cell::Cell;
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn each_worker_has_own_shard() {
+    let counters = ShardedLocal::new(|| Cell::new(0_usize));
+
+    // Every worker increments its own shard by its index + 1.
+    for_each_worker({
+        let counters = counters.clone();
+
+        move |ctx| {
+            let counters = counters.clone();
+
+            async move {
+                for _ in 0..=ctx.worker_index() {
+                    counters.with(|counter| counter.set(counter.get() + 1));
+                }
+            }
+        }
+    })
+    .await;
+
+    let values = counters.visit_all(|counter| counter.get()).await;
+
+    assert_eq!(values.len(), worker_count());
+
+    for (worker_index, value) in values.iter().enumerate() {
+        assert_eq!(*value, worker_index + 1);
+    }
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn shard_is_created_on_its_worker() {
+    let created_on = ShardedLocal::new(current_worker_index);
+
+    let values = created_on.visit_all(|created_on| *created_on).await;
+
+    for (worker_index, value) in values.iter().enumerate() {
+        assert_eq!(*value, Some(worker_index));
+    }
+
+    created_on.with_mut(|created_on| *created_on = None);
+    assert_eq!(created_on.with(|created_on| *created_on), None);
+}
@@ -1,10 +1,16 @@
-use folo::rt::{RemoteJoinHandle, RuntimeBuilder};
+use folo::{
+    rt::{spawn_sync, JoinError, RemoteJoinHandle, RuntimeBuilder, SynchronousTaskType},
+    time::{Clock, Delay},
+};
 use folo_testing::init_test_worker;
 use futures::{task::noop_waker, FutureExt};
 use std::{
     future::Future,
     pin::Pin,
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
     task::{self, Waker},
     thread,
     time::Duration,
@@ -127,7 +133,8 @@ fn runtime_stops_with_external_observer() {
     // We manually create the runtime here because we need to also operate outside of it.
     let folo = RuntimeBuilder::new()
         .worker_init(folo_testing::init_test_worker)
-        .build().unwrap();
+        .build()
+        .unwrap();
     let folo_clone = folo.clone();
 
     let (tx, rx) = oneshot::channel();
@@ -174,7 +181,8 @@ fn runtime_stops_with_external_dependency() {
     // We manually create the runtime here because we need to also operate outside of it.
     let folo = RuntimeBuilder::new()
         .worker_init(folo_testing::init_test_worker)
-        .build().unwrap();
+        .build()
+        .unwrap();
 
     let nexus = Arc::new(ManualFuture::new());
     let nexus_folo = Arc::clone(&nexus);
@@ -208,6 +216,169 @@ fn runtime_stops_with_external_dependency() {
     folo.wait();
 }
 
+#[test]
+fn graceful_shutdown_completes_in_flight_tasks() {
+    let folo = RuntimeBuilder::new()
+        .worker_init(folo_testing::init_test_worker)
+        .build()
+        .unwrap();
+
+    let (started_tx, started_rx) = oneshot::channel();
+    let completed = Arc::new(AtomicBool::new(false));
+    let completed_clone = Arc::clone(&completed);
+
+    folo.spawn_on_any(move || async move {
+        _ = started_tx.send(());
+
+        let clock = Clock::new();
+        Delay::with_clock(&clock, Duration::from_millis(50)).await;
+
+        completed_clone.store(true, Ordering::Relaxed);
+    });
+
+    started_rx.recv().unwrap();
+
+    assert!(folo.shutdown(Duration::from_secs(10)));
+    assert!(completed.load(Ordering::Relaxed));
+}
+
+#[test]
+fn graceful_shutdown_completes_in_flight_sync_tasks() {
+    let folo = RuntimeBuilder::new()
+        .worker_init(folo_testing::init_test_worker)
+        .build()
+        .unwrap();
+
+    let completed = Arc::new(AtomicBool::new(false));
+    let completed_clone = Arc::clone(&completed);
+
+    // The async task completes as soon as it has spawned the sync task, which is left running
+    // without anyone awaiting it.
+    futures::executor::block_on(folo.spawn_on_any(move || async move {
+        _ = spawn_sync(SynchronousTaskType::Syscall, move || {
+            thread::sleep(Duration::from_millis(50));
+            completed_clone.store(true, Ordering::Relaxed);
+        });
+    }));
+
+    assert!(folo.shutdown(Duration::from_secs(10)));
+    assert!(completed.load(Ordering::Relaxed));
+}
+
+#[test]
+fn graceful_shutdown_cancels_tasks_after_deadline() {
+    let folo = RuntimeBuilder::new()
+        .worker_init(folo_testing::init_test_worker)
+        .build()
+        .unwrap();
+
+    let (started_tx, started_rx) = oneshot::channel();
+
+    folo.spawn_on_any(move || ForeverSleepFuture::new(started_tx));
+
+    started_rx.recv().unwrap();
+
+    assert!(!folo.shutdown(Duration::from_millis(50)));
+    assert!(folo.is_stopping());
+}
+
+#[test]
+fn graceful_shutdown_accepts_remote_spawns_of_in_flight_tasks() {
+    let folo = RuntimeBuilder::new()
+        .worker_init(folo_testing::init_test_worker)
+        .build()
+        .unwrap();
+    let folo_clone = folo.clone();
+
+    let (started_tx, started_rx) = oneshot::channel();
+    let completed = Arc::new(AtomicBool::new(false));
+    let completed_clone = Arc::clone(&completed);
+
+    folo.spawn_on(0, move || async move {
+        _ = started_tx.send(());
+
+        // By the time this elapses, the workers are draining.
+        let clock = Clock::new();
+        Delay::with_clock(&clock, Duration::from_millis(100)).await;
+
+        let last_worker = folo_clone.worker_count() - 1;
+        let on_last = folo_clone.spawn_on(last_worker, || async { 1 }).await;
+        let on_any = folo_clone.spawn_on_any(|| async { 2 }).await;
+
+        completed_clone.store(on_last + on_any == 3, Ordering::Relaxed);
+    });
+
+    started_rx.recv().unwrap();
+
+    assert!(folo.shutdown(Duration::from_secs(10)));
+    assert!(completed.load(Ordering::Relaxed));
+}
+
+#[test]
+fn graceful_shutdown_cancels_external_spawns() {
+    let folo = RuntimeBuilder::new()
+        .worker_init(folo_testing::init_test_worker)
+        .build()
+        .unwrap();
+    let folo_clone = folo.clone();
+
+    let (started_tx, started_rx) = oneshot::channel();
+
+    folo.spawn_on_any(move || ForeverSleepFuture::new(started_tx));
+
+    started_rx.recv().unwrap();
+
+    let shutdown = thread::spawn(move || folo_clone.shutdown(Duration::from_secs(1)));
+
+    // The in-flight task holds up the shutdown, so the workers stay draining until we are done.
+    while !folo.is_draining() {
+        thread::yield_now();
+    }
+
+    let result = futures::executor::block_on(folo.spawn_on_any(|| async {}).result());
+    assert!(matches!(result, Err(JoinError::Cancelled)));
+
+    assert!(!shutdown.join().unwrap());
+}
+
+#[test]
+fn graceful_shutdown_executes_hooks() {
+    let folo = RuntimeBuilder::new()
+        .worker_init(folo_testing::init_test_worker)
+        .build()
+        .unwrap();
+
+    let flushed = Arc::new(AtomicBool::new(false));
+
+    folo.on_shutdown({
+        let flushed = Arc::clone(&flushed);
+
+        move || async move {
+            let clock = Clock::new();
+            Delay::with_clock(&clock, Duration::from_millis(10)).await;
+
+            flushed.store(true, Ordering::Relaxed);
+        }
+    });
+
+    assert!(!flushed.load(Ordering::Relaxed));
+
+    assert!(folo.shutdown(Duration::from_secs(10)));
+    assert!(flushed.load(Ordering::Relaxed));
+}
+
+#[test]
+fn graceful_shutdown_abandons_hooks_after_deadline() {
+    let folo = RuntimeBuilder::new()
+        .worker_init(folo_testing::init_test_worker)
+        .build()
+        .unwrap();
+
+    folo.on_shutdown(futures::future::pending::<()>);
+
+    assert!(!folo.shutdown(Duration::from_millis(50)));
+}
+
 /// A future that progresses or completes (and wakes up the last poller) when manually commanded.
 struct ManualFuture {
     state: Mutex<ManualFutureState>,
@@ -0,0 +1,136 @@
+#![cfg(feature = "socket2")]
+
+use folo::{
+    io::{Buffer, OperationResultExt},
+    mem::isolation::Isolated,
+    net::{TcpListener, TcpStream},
+    rt::spawn,
+};
+use folo_testing::init_test_worker;
+use socket2::{Domain, Protocol, Socket, Type};
+use std::{
+    io::{Read, Write},
+    net::{Ipv4Addr, SocketAddr},
+};
+
+const MESSAGE: &[u8] = b"hello, folo";
+
+fn new_tcp_socket() -> Socket {
+    Socket::new(Domain::IPV4, Type::STREAM, Some(Protocol::TCP)).unwrap()
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn socket2_listener_and_custom_bound_stream() {
+    let listen_socket = new_tcp_socket();
+    listen_socket.set_reuse_address(true).unwrap();
+    listen_socket
+        .bind(&SocketAddr::from((Ipv4Addr::LOCALHOST, 0)).into())
+        .unwrap();
+    listen_socket.listen(16).unwrap();
+
+    let listener = TcpListener::from_socket(listen_socket).unwrap();
+    let listen_addr = listener.local_addr().unwrap();
+
+    let server = spawn(async move {
+        let (mut stream, peer_addr) = listener.accept().await.unwrap();
+
+        let buffer = stream
+            .read(Buffer::<Isolated>::from_pool())
+            .await
+            .into_inner()
+            .unwrap();
+
+        stream.write(buffer).await.into_inner().unwrap();
+        stream.shutdown().await.unwrap();
+
+        peer_addr
+    });
+
+    // Bind-before-connect, which Folo does not offer directly.
+    let connect_socket = new_tcp_socket();
+    connect_socket.set_nodelay(true).unwrap();
+    connect_socket
+        .bind(&SocketAddr::from((Ipv4Addr::LOCALHOST, 0)).into())
+        .unwrap();
+    let local_addr = connect_socket.local_addr().unwrap().as_socket().unwrap();
+
+    let mut client = TcpStream::connect_with(connect_socket, listen_addr)
+        .await
+        .unwrap();
+    assert_eq!(client.peer_addr().unwrap(), listen_addr);
+
+    let mut buffer = Buffer::<Isolated>::from_pool();
+    buffer.as_mut_slice()[..MESSAGE.len()].copy_from_slice(MESSAGE);
+    buffer.set_len(MESSAGE.len());
+
+    let buffer = client.write(buffer).await.into_inner().unwrap();
+    let buffer = client.read(buffer.use_all()).await.into_inner().unwrap();
+    assert_eq!(&*buffer.as_slice(), MESSAGE);
+
+    client.shutdown().await.unwrap();
+    assert_eq!(server.await, local_addr);
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn socket2_unbound_stream_connects() {
+    let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+        .await
+        .unwrap();
+    let listen_addr = listener.local_addr().unwrap();
+
+    let server = spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        stream.shutdown().await.unwrap();
+    });
+
+    let client = TcpStream::connect_with(new_tcp_socket(), listen_addr)
+        .await
+        .unwrap();
+    assert_eq!(client.peer_addr().unwrap(), listen_addr);
+
+    drop(client);
+    server.await;
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn socket2_released_stream_remains_connected() {
+    let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+        .await
+        .unwrap();
+    let listen_addr = listener.local_addr().unwrap();
+
+    let server = spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+
+        let buffer = stream
+            .read(Buffer::<Isolated>::from_pool())
+            .await
+            .into_inner()
+            .unwrap();
+
+        stream.write(buffer).await.into_inner().unwrap();
+        stream.shutdown().await.unwrap();
+    });
+
+    let client = TcpStream::connect(listen_addr).await.unwrap();
+
+    let socket = client.into_socket2().unwrap();
+    assert_eq!(
+        socket.peer_addr().unwrap().as_socket().unwrap(),
+        listen_addr
+    );
+
+    // The released socket is used with blocking I/O, so we do that on a separate thread, leaving
+    // the worker free to run the server.
+    let echoed = std::thread::spawn(move || {
+        let mut socket = socket;
+        socket.write_all(MESSAGE).unwrap();
+
+        let mut echoed = Vec::new();
+        socket.read_to_end(&mut echoed).unwrap();
+        echoed
+    });
+
+    server.await;
+    assert_eq!(echoed.join().unwrap(), MESSAGE);
+}
@@ -1,5 +1,29 @@
-use folo::rt::{spawn, spawn_on_any, yield_now, RuntimeBuilder};
-use std::rc::Rc;
+use folo::io::Buffer;
+use folo::mem::isolation::Isolated;
+use folo::rt::{
+    current_numa_node, current_task_id, current_task_name, current_worker_index, for_each_worker,
+    scope, set_task_memory_accounting, spawn, spawn_named, spawn_on, spawn_on_any,
+    spawn_on_any_named, spawn_singleton, spawn_sync, spawn_with_deadline, traced, try_spawn,
+    worker_count, yield_now, Affinity, Diagnostic, DiagnosticsBackend, FlightEvent, IdleStrategy,
+    JoinError, LocalJoinHandle, LocalSpawner, PanicPolicy, Profile, RuntimeBuilder, SpawnError,
+    SynchronousTaskType, TaskState, WatchdogPolicy,
+};
+use folo::time::{Clock, Deadline, Delay};
+use folo_testing::init_test_worker;
+use futures::{
+    future,
+    task::{LocalSpawnExt, SpawnExt},
+};
+use std::{
+    cell::RefCell,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
 
 #[test]
 fn spawning() {
@@ -25,6 +49,300 @@ fn spawning() {
     folo.wait();
 }
 
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn abort_local_task() {
+    let task = spawn(future::pending::<()>());
+
+    // Let it start, so it is actually waiting for something when we abort it.
+    yield_now().await;
+
+    task.abort();
+    assert!(matches!(task.result().await, Err(JoinError::Cancelled)));
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn abort_remote_task() {
+    let task = spawn_on_any(future::pending::<()>);
+
+    task.abort();
+    assert!(matches!(task.result().await, Err(JoinError::Cancelled)));
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn spawn_with_deadline_cancels_late_task() {
+    let deadline = Instant::now() + Duration::from_millis(50);
+
+    let late = spawn_with_deadline(deadline, future::pending::<()>());
+    let punctual = spawn_with_deadline(deadline, async { 42 });
+
+    assert!(matches!(
+        late.result().await,
+        Err(JoinError::DeadlineExceeded)
+    ));
+    assert_eq!(punctual.result().await.unwrap(), 42);
+    assert!(Instant::now() >= deadline);
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn task_inherits_earlier_deadline() {
+    let outer = Deadline::after(Duration::from_secs(10));
+    let inner = Deadline::after(Duration::from_secs(60));
+
+    let observed = spawn_with_deadline(outer, async move {
+        assert_eq!(Deadline::current(), Some(outer));
+
+        spawn_with_deadline(inner, async { Deadline::current() }).await
+    })
+    .await;
+
+    assert_eq!(observed, Some(outer));
+    assert_eq!(Deadline::current(), None);
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn abort_completed_task_has_no_effect() {
+    let task = spawn(async { 42 });
+
+    yield_now().await;
+    yield_now().await;
+
+    task.abort();
+    assert_eq!(task.result().await.unwrap(), 42);
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn yield_now_lets_other_tasks_run() {
+    let log = Rc::new(RefCell::new(Vec::new()));
+
+    let first = spawn({
+        let log = Rc::clone(&log);
+
+        async move {
+            for _ in 0..3 {
+                log.borrow_mut().push("first");
+                folo::yield_now().await;
+            }
+        }
+    });
+
+    let second = spawn({
+        let log = Rc::clone(&log);
+
+        async move {
+            log.borrow_mut().push("second");
+        }
+    });
+
+    first.await;
+    second.await;
+
+    // The first task yielded after its first step, so the second task got to run before the first
+    // task finished all its steps.
+    let log = log.borrow();
+    assert_eq!(log.len(), 4);
+    assert_ne!(log.last(), Some(&"second"));
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn named_local_task() {
+    let task = spawn_named("local-worker", async {
+        (current_task_id(), current_task_name())
+    });
+
+    let expected_id = task.id();
+    let (id, name) = task.await;
+
+    assert_eq!(id, Some(expected_id));
+    assert_eq!(name.as_deref(), Some("local-worker"));
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn named_remote_task() {
+    let task = spawn_on_any_named("remote-worker", || async {
+        (current_task_id(), current_task_name())
+    });
+
+    let expected_id = task.id();
+    let (id, name) = task.await;
+
+    assert_eq!(id, Some(expected_id));
+    assert_eq!(name.as_deref(), Some("remote-worker"));
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn anonymous_tasks_have_distinct_ids() {
+    let first = spawn(async { current_task_name() });
+    let second = spawn(async { current_task_name() });
+
+    assert_ne!(first.id(), second.id());
+    assert_eq!(first.await, None);
+    assert_eq!(second.await, None);
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn singleton_restarts_after_panic() {
+    let attempts = Arc::new(AtomicUsize::new(0));
+
+    let singleton = spawn_singleton("flaky-singleton", {
+        let attempts = Arc::clone(&attempts);
+
+        move || {
+            let attempts = Arc::clone(&attempts);
+
+            async move {
+                if attempts.fetch_add(1, Ordering::Relaxed) == 0 {
+                    panic!("first attempt always fails");
+                }
+            }
+        }
+    });
+
+    singleton.clone().wait().await;
+
+    assert_eq!(attempts.load(Ordering::Relaxed), 2);
+    assert_eq!(singleton.failovers(), 1);
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn singleton_stop() {
+    let singleton = spawn_singleton("pending-singleton", future::pending::<()>);
+
+    singleton.stop();
+    singleton.wait().await;
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn singleton_is_unique_by_name() {
+    let first = spawn_singleton("unique-singleton", future::pending::<()>);
+
+    // Even from another worker, we get the singleton that is already running.
+    let last_worker = worker_count() - 1;
+    let second = spawn_on(last_worker, || async {
+        spawn_singleton("unique-singleton", || async {
+            panic!("the factory of a duplicate singleton is never called");
+        })
+    })
+    .await;
+
+    second.stop();
+    first.wait().await;
+
+    // Once the singleton has finished, the name is free again.
+    let third = spawn_singleton("unique-singleton", || async {});
+    third.wait().await;
+}
+
+#[test]
+fn idle_worker_steals_queued_tasks() {
+    const TASK_COUNT: usize = 10;
+
+    let folo = RuntimeBuilder::new()
+        .worker_init(init_test_worker)
+        .max_processors(2)
+        .stealing(true)
+        .build()
+        .unwrap();
+
+    let (blocker_started_tx, blocker_started_rx) = mpsc::channel();
+    let release_blocker = Arc::new(AtomicBool::new(false));
+
+    // This occupies one of the workers, so any tasks queued for it can only run if stolen.
+    folo.spawn_on_any({
+        let release_blocker = Arc::clone(&release_blocker);
+
+        move || async move {
+            _ = blocker_started_tx.send(());
+
+            while !release_blocker.load(Ordering::Relaxed) {
+                thread::sleep(Duration::from_millis(1));
+            }
+        }
+    });
+
+    blocker_started_rx.recv().unwrap();
+
+    let completed = Arc::new(AtomicUsize::new(0));
+
+    // Round-robin distribution assigns some of these to the blocked worker.
+    for _ in 0..TASK_COUNT {
+        let completed = Arc::clone(&completed);
+
+        folo.spawn_on_any(move || async move {
+            completed.fetch_add(1, Ordering::Relaxed);
+        });
+    }
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+
+    while completed.load(Ordering::Relaxed) < TASK_COUNT && Instant::now() < deadline {
+        thread::sleep(Duration::from_millis(1));
+    }
+
+    assert_eq!(completed.load(Ordering::Relaxed), TASK_COUNT);
+
+    release_blocker.store(true, Ordering::Relaxed);
+
+    folo.stop();
+    folo.wait();
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn spawn_on_targets_worker() {
+    for worker_index in 0..worker_count() {
+        let executed_on = spawn_on(worker_index, || async { current_worker_index() }).await;
+
+        assert_eq!(executed_on, Some(worker_index));
+    }
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn for_each_worker_runs_on_every_worker() {
+    let results = for_each_worker(|ctx| async move {
+        // Thread-local state proves the job executes on the worker itself.
+        let rc = Rc::new(ctx.worker_index());
+        yield_now().await;
+
+        (*rc, current_worker_index())
+    })
+    .await;
+
+    assert_eq!(results.len(), worker_count());
+
+    for (worker_index, result) in results.iter().enumerate() {
+        assert_eq!(*result, (worker_index, Some(worker_index)));
+    }
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn local_spawner_spawns_non_send_future() {
+    let rc = Rc::new(42);
+
+    let handle = LocalSpawner::current()
+        .spawn_local_with_handle(async move {
+            yield_now().await;
+            *rc
+        })
+        .unwrap();
+
+    assert_eq!(handle.await, 42);
+}
+
+#[test]
+fn runtime_client_spawns_via_futures_trait() {
+    let folo = RuntimeBuilder::new().build().unwrap();
+
+    let handle = folo
+        .spawn_with_handle(async { current_worker_index() })
+        .unwrap();
+
+    assert!(futures::executor::block_on(handle).is_some());
+
+    folo.stop();
+    folo.wait();
+
+    assert!(folo.spawn(async {}).unwrap_err().is_shutdown());
+}
+
 async fn thread_safe_logic() -> Option<()> {
     yield_now().await;
     Some(())
@@ -36,3 +354,789 @@ async fn single_threaded_logic() -> Option<()> {
     assert_eq!(42, *rc);
     Some(())
 }
+
+#[test]
+fn try_spawn_refuses_tasks_over_worker_limit() {
+    let folo = RuntimeBuilder::new()
+        .worker_init(init_test_worker)
+        .max_processors(1)
+        .max_tasks_per_worker(2)
+        .build()
+        .unwrap();
+    let folo_clone = folo.clone();
+
+    let (result_tx, result_rx) = mpsc::channel();
+
+    folo.spawn_on_any(move || async move {
+        // The current task is the first of the two allowed.
+        let blocker = try_spawn(future::pending::<()>()).unwrap();
+
+        let refused = matches!(
+            try_spawn(async {}),
+            Err(SpawnError::WorkerAtCapacity { limit: 2 })
+        );
+
+        // Once a task completes, there is room again.
+        blocker.abort();
+        _ = blocker.result().await;
+
+        let accepted = try_spawn(async { 42 }).unwrap().await == 42;
+
+        _ = result_tx.send((refused, accepted));
+        folo_clone.stop();
+    });
+
+    folo.wait();
+
+    assert_eq!(result_rx.recv().unwrap(), (true, true));
+}
+
+#[test]
+fn retired_workers_get_no_new_tasks() {
+    let folo = RuntimeBuilder::new()
+        .worker_init(init_test_worker)
+        .build()
+        .unwrap();
+    let folo_clone = folo.clone();
+
+    assert_eq!(folo.active_worker_count(), folo.worker_count());
+
+    folo.set_active_worker_count(1);
+    assert_eq!(folo.active_worker_count(), 1);
+
+    let (result_tx, result_rx) = mpsc::channel();
+
+    folo.spawn_on_any(move || async move {
+        let mut executed_on = Vec::new();
+
+        for _ in 0..(worker_count() * 2) {
+            executed_on.push(spawn_on_any(|| async { current_worker_index() }).await);
+        }
+
+        _ = result_tx.send(executed_on);
+        folo_clone.stop();
+    });
+
+    folo.wait();
+
+    assert!(result_rx
+        .recv()
+        .unwrap()
+        .iter()
+        .all(|index| *index == Some(0)));
+}
+
+#[test]
+fn worker_threads_use_name_template() {
+    let folo = RuntimeBuilder::new()
+        .worker_init(init_test_worker)
+        .max_processors(1)
+        .async_thread_name("test-worker-{index}")
+        .stack_size(4 * 1024 * 1024)
+        .build()
+        .unwrap();
+    let folo_clone = folo.clone();
+
+    let (result_tx, result_rx) = mpsc::channel();
+
+    folo.spawn_on_any(move || async move {
+        _ = result_tx.send(thread::current().name().map(str::to_owned));
+        folo_clone.stop();
+    });
+
+    folo.wait();
+
+    assert_eq!(result_rx.recv().unwrap().as_deref(), Some("test-worker-0"));
+}
+
+#[test]
+fn spin_then_park_idle_strategy_executes_tasks() {
+    let folo = RuntimeBuilder::new()
+        .worker_init(init_test_worker)
+        .max_processors(1)
+        .idle_strategy(IdleStrategy::SpinThenPark {
+            spin_cycles: 100,
+            yield_cycles: 10,
+        })
+        .build()
+        .unwrap();
+    let folo_clone = folo.clone();
+
+    let (result_tx, result_rx) = mpsc::channel();
+
+    folo.spawn_on_any(move || async move {
+        // Idle cycles pass between the remote spawn and its completion being delivered back.
+        let value = spawn_on_any(|| async { 42 }).await;
+
+        _ = result_tx.send(value);
+        folo_clone.stop();
+    });
+
+    folo.wait();
+
+    assert_eq!(result_rx.recv().unwrap(), 42);
+}
+
+#[test]
+fn maintenance_tick_invokes_callback() {
+    let ticks = Arc::new(AtomicUsize::new(0));
+
+    let folo = RuntimeBuilder::new()
+        .worker_init(init_test_worker)
+        .max_processors(1)
+        .maintenance_interval(Duration::from_millis(1))
+        .on_maintenance_tick({
+            let ticks = Arc::clone(&ticks);
+            move || {
+                ticks.fetch_add(1, Ordering::Relaxed);
+            }
+        })
+        .build()
+        .unwrap();
+    let folo_clone = folo.clone();
+
+    folo.spawn_on_any(move || async move {
+        // Keep the worker cycling until a few ticks have happened.
+        let started = Instant::now();
+
+        while started.elapsed() < Duration::from_millis(50) {
+            yield_now().await;
+        }
+
+        folo_clone.stop();
+    });
+
+    folo.wait();
+
+    assert!(ticks.load(Ordering::Relaxed) > 0);
+}
+
+#[test]
+fn worker_stats_account_for_time() {
+    let folo = RuntimeBuilder::new()
+        .worker_init(init_test_worker)
+        .build()
+        .unwrap();
+
+    // Give the workers some time to go around their loops.
+    thread::sleep(Duration::from_millis(50));
+
+    let stats = folo.worker_stats();
+
+    folo.stop();
+    folo.wait();
+
+    assert_eq!(stats.len(), folo.worker_count());
+
+    for (worker_index, worker) in stats.iter().enumerate() {
+        assert_eq!(worker.worker_index(), worker_index);
+        assert!(worker.cycles() > 0);
+        assert!((0.0..=1.0).contains(&worker.utilization()));
+    }
+}
+
+#[test]
+fn runtime_with_profile_executes_tasks() {
+    for profile in [Profile::Balanced, Profile::LowLatency, Profile::Throughput] {
+        let folo = RuntimeBuilder::new()
+            .worker_init(init_test_worker)
+            .max_processors(2)
+            .profile(profile)
+            .build()
+            .unwrap();
+        let folo_clone = folo.clone();
+
+        let (result_tx, result_rx) = mpsc::channel();
+
+        folo.spawn_on_any(move || async move {
+            let local = spawn(async { 1 }).await;
+            let remote = spawn_on_any(|| async { 2 }).await;
+
+            _ = result_tx.send(local + remote);
+            folo_clone.stop();
+        });
+
+        folo.wait();
+
+        assert_eq!(result_rx.recv().unwrap(), 3);
+    }
+}
+
+#[test]
+fn panic_policy_log_and_continue() {
+    let folo = RuntimeBuilder::new()
+        .worker_init(init_test_worker)
+        .max_processors(1)
+        .panic_policy(PanicPolicy::LogAndContinue)
+        .build()
+        .unwrap();
+    let folo_clone = folo.clone();
+
+    let (result_tx, result_rx) = mpsc::channel();
+
+    folo.spawn_on_any(move || async move {
+        let task: LocalJoinHandle<()> = spawn(async { panic!("deliberate panic") });
+        let cancelled = matches!(task.result().await, Err(JoinError::Cancelled));
+
+        // The worker is still alive and keeps executing tasks.
+        let still_working = spawn(async { 42 }).await == 42;
+
+        _ = result_tx.send((cancelled, still_working));
+        folo_clone.stop();
+    });
+
+    folo.wait();
+
+    assert_eq!(result_rx.recv().unwrap(), (true, true));
+}
+
+#[test]
+fn panic_policy_deliver_to_join_handle() {
+    let folo = RuntimeBuilder::new()
+        .worker_init(init_test_worker)
+        .max_processors(1)
+        .panic_policy(PanicPolicy::DeliverToJoinHandle)
+        .build()
+        .unwrap();
+    let folo_clone = folo.clone();
+
+    let (result_tx, result_rx) = mpsc::channel();
+
+    folo.spawn_on_any(move || async move {
+        let task: LocalJoinHandle<()> = spawn(async { panic!("deliberate panic") });
+
+        let message = task
+            .result()
+            .await
+            .unwrap_err()
+            .into_panic()
+            .and_then(|payload| payload.downcast_ref::<&str>().map(ToString::to_string));
+
+        _ = result_tx.send(message);
+        folo_clone.stop();
+    });
+
+    folo.wait();
+
+    assert_eq!(
+        result_rx.recv().unwrap().as_deref(),
+        Some("deliberate panic")
+    );
+}
+
+#[test]
+fn panic_policy_deliver_to_join_handle_for_sync_task() {
+    let folo = RuntimeBuilder::new()
+        .worker_init(init_test_worker)
+        .max_processors(1)
+        .panic_policy(PanicPolicy::DeliverToJoinHandle)
+        .build()
+        .unwrap();
+    let folo_clone = folo.clone();
+
+    let (result_tx, result_rx) = mpsc::channel();
+
+    folo.spawn_on_any(move || async move {
+        let message = spawn_sync(SynchronousTaskType::Syscall, || {
+            panic!("deliberate panic");
+        })
+        .result()
+        .await
+        .unwrap_err()
+        .into_panic()
+        .and_then(|payload| payload.downcast_ref::<&str>().map(ToString::to_string));
+
+        // The sync worker thread survives the panic and keeps executing tasks.
+        let value = spawn_sync(SynchronousTaskType::Syscall, || 42).await;
+
+        _ = result_tx.send((message, value));
+        folo_clone.stop();
+    });
+
+    folo.wait();
+
+    assert_eq!(
+        result_rx.recv().unwrap(),
+        (Some("deliberate panic".to_string()), 42)
+    );
+}
+
+#[test]
+fn slow_poll_reported_to_diagnostics_callback() {
+    let slow_tasks = Arc::new(Mutex::new(Vec::new()));
+    let slow_tasks_clone = Arc::clone(&slow_tasks);
+
+    let folo = RuntimeBuilder::new()
+        .worker_init(init_test_worker)
+        .max_processors(1)
+        .slow_poll_threshold(Duration::from_millis(50))
+        .diagnostics(DiagnosticsBackend::callback(move |diagnostic| {
+            if let Diagnostic::SlowPoll { task_name, .. } = diagnostic {
+                slow_tasks_clone
+                    .lock()
+                    .unwrap()
+                    .push(task_name.map(ToString::to_string));
+            }
+        }))
+        .build()
+        .unwrap();
+    let folo_clone = folo.clone();
+
+    folo.spawn_on_any(move || async move {
+        spawn_named("blocker", async {
+            // Deliberately blocks the worker thread.
+            thread::sleep(Duration::from_millis(200));
+        })
+        .await;
+
+        folo_clone.stop();
+    });
+
+    folo.wait();
+
+    assert_eq!(
+        *slow_tasks.lock().unwrap(),
+        vec![Some("blocker".to_string())]
+    );
+}
+
+/// Runs a fast and a slow task (which blocks the worker thread for 100 ms) on a runtime with the
+/// specified slow poll threshold and returns the name, duration and threshold of every slow poll
+/// reported.
+fn reported_slow_polls(threshold: Duration) -> Vec<(Option<String>, Duration, Duration)> {
+    let slow_polls = Arc::new(Mutex::new(Vec::new()));
+    let slow_polls_clone = Arc::clone(&slow_polls);
+
+    let folo = RuntimeBuilder::new()
+        .worker_init(init_test_worker)
+        .max_processors(1)
+        .slow_poll_threshold(threshold)
+        .diagnostics(DiagnosticsBackend::callback(move |diagnostic| {
+            if let Diagnostic::SlowPoll {
+                task_name,
+                duration,
+                threshold,
+                ..
+            } = diagnostic
+            {
+                slow_polls_clone.lock().unwrap().push((
+                    task_name.map(ToString::to_string),
+                    *duration,
+                    *threshold,
+                ));
+            }
+        }))
+        .build()
+        .unwrap();
+    let folo_clone = folo.clone();
+
+    folo.spawn_on_any(move || async move {
+        spawn_named("fast", yield_now()).await;
+
+        spawn_named("slow", async {
+            // Deliberately blocks the worker thread.
+            thread::sleep(Duration::from_millis(100));
+        })
+        .await;
+
+        folo_clone.stop();
+    });
+
+    folo.wait();
+
+    let reported = slow_polls.lock().unwrap();
+    reported.clone()
+}
+
+#[test]
+fn slow_poll_reports_duration_and_threshold() {
+    let threshold = Duration::from_millis(50);
+
+    let slow_polls = reported_slow_polls(threshold);
+
+    assert_eq!(slow_polls.len(), 1);
+
+    let (task_name, duration, reported_threshold) = &slow_polls[0];
+    assert_eq!(task_name.as_deref(), Some("slow"));
+    assert!(*duration >= threshold);
+    assert_eq!(*reported_threshold, threshold);
+}
+
+#[test]
+fn zero_slow_poll_threshold_disables_check() {
+    assert!(reported_slow_polls(Duration::ZERO).is_empty());
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn scoped_tasks_borrow_from_stack() {
+    let words = vec!["alpha", "beta", "gamma"];
+    let mut lengths = Vec::new();
+
+    let total = scope(|s| {
+        let (words, lengths) = (&words, &mut lengths);
+
+        Box::pin(async move {
+            let handles = words
+                .iter()
+                .map(|word| s.spawn(async move { word.len() }))
+                .collect::<Vec<_>>();
+
+            for handle in handles {
+                lengths.push(handle.await);
+            }
+
+            lengths.iter().sum::<usize>()
+        })
+    })
+    .await;
+
+    assert_eq!(lengths, vec![5, 4, 5]);
+    assert_eq!(total, 14);
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn scope_waits_for_unawaited_tasks() {
+    let completed = RefCell::new(Vec::new());
+
+    scope(|s| {
+        let completed = &completed;
+
+        Box::pin(async move {
+            for i in 0..3 {
+                s.spawn(async move {
+                    yield_now().await;
+
+                    // Tasks may spawn more tasks into the same scope.
+                    s.spawn(async move {
+                        completed.borrow_mut().push(i * 10);
+                    });
+
+                    completed.borrow_mut().push(i);
+                });
+            }
+        })
+    })
+    .await;
+
+    let mut completed = completed.into_inner();
+    completed.sort_unstable();
+
+    assert_eq!(completed, vec![0, 0, 1, 2, 10, 20]);
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn woken_task_runs_before_earlier_woken_tasks() {
+    let order = Rc::new(RefCell::new(Vec::new()));
+
+    let (bystander_tx, bystander_rx) = futures::channel::oneshot::channel::<()>();
+    let (responder_tx, responder_rx) = futures::channel::oneshot::channel::<()>();
+
+    let bystander = spawn({
+        let order = Rc::clone(&order);
+
+        async move {
+            bystander_rx.await.unwrap();
+            order.borrow_mut().push("bystander");
+        }
+    });
+
+    let responder = spawn({
+        let order = Rc::clone(&order);
+
+        async move {
+            responder_rx.await.unwrap();
+            order.borrow_mut().push("responder");
+        }
+    });
+
+    // Let both tasks start and go to sleep waiting for their signal.
+    yield_now().await;
+    yield_now().await;
+
+    bystander_tx.send(()).unwrap();
+    responder_tx.send(()).unwrap();
+
+    // The most recently woken task goes to the LIFO slot and runs as soon as we yield, before the
+    // bystander that was woken earlier.
+    responder.await;
+    bystander.await;
+
+    assert_eq!(*order.borrow(), vec!["responder", "bystander"]);
+}
+
+#[test]
+fn dump_lists_waiting_tasks() {
+    let folo = RuntimeBuilder::new()
+        .worker_init(init_test_worker)
+        .max_processors(2)
+        .build()
+        .unwrap();
+
+    let (started_tx, started_rx) = mpsc::channel();
+
+    folo.spawn_on_any_named("stuck-forever", move || async move {
+        _ = started_tx.send(());
+        future::pending::<()>().await;
+    });
+
+    started_rx.recv().unwrap();
+
+    let dump = folo.dump(Duration::from_secs(10));
+
+    assert_eq!(dump.workers().len(), 2);
+    assert!(dump.workers().iter().all(|worker| worker.responded()));
+
+    let stuck = dump
+        .workers()
+        .iter()
+        .flat_map(|worker| worker.tasks())
+        .find(|task| task.name() == Some("stuck-forever"))
+        .expect("the stuck task must be listed");
+
+    assert_eq!(stuck.state(), TaskState::Waiting);
+    assert!(stuck.since_last_poll().is_some());
+    assert!(stuck.polls() >= 1);
+
+    folo.stop();
+    folo.wait();
+}
+
+#[test]
+fn dump_attributes_memory_to_tasks() {
+    set_task_memory_accounting(true);
+
+    let folo = RuntimeBuilder::new()
+        .worker_init(init_test_worker)
+        .max_processors(1)
+        .build()
+        .unwrap();
+
+    let (started_tx, started_rx) = mpsc::channel();
+
+    folo.spawn_on_any_named("buffer-holder", move || async move {
+        let buffer = Buffer::<Isolated>::from_pool();
+        _ = started_tx.send(());
+        future::pending::<()>().await;
+        drop(buffer);
+    });
+
+    folo.spawn_on_any_named("idler", || future::pending::<()>());
+
+    started_rx.recv().unwrap();
+
+    let dump = folo.dump(Duration::from_secs(10));
+
+    let top = dump.top_memory_consumers(1);
+    assert_eq!(top.len(), 1);
+    assert_eq!(top[0].name(), Some("buffer-holder"));
+    assert!(top[0].frame_size() > 0);
+    assert!(top[0].charged_memory() > 0);
+    assert_eq!(
+        top[0].memory(),
+        top[0].frame_size() as u64 + top[0].charged_memory()
+    );
+
+    folo.stop();
+    folo.wait();
+}
+
+#[test]
+fn flight_record_lists_spawn_and_completion() {
+    let folo = RuntimeBuilder::new()
+        .worker_init(init_test_worker)
+        .max_processors(1)
+        .flight_recorder(64)
+        .build()
+        .unwrap();
+
+    let handle = folo.spawn_on_any(|| async { 42 });
+    let task_id = handle.id();
+
+    assert_eq!(futures::executor::block_on(handle), 42);
+
+    let record = folo.dump_flight_record();
+    assert_eq!(record.workers().len(), 1);
+
+    let events = record.workers()[0]
+        .entries()
+        .iter()
+        .map(|entry| entry.event())
+        .collect::<Vec<_>>();
+
+    assert!(events
+        .iter()
+        .any(|event| matches!(event, FlightEvent::TaskSpawned { task_id: id } if *id == task_id)));
+    assert!(events.iter().any(|event| matches!(
+        event,
+        FlightEvent::TaskPolled { task_id: id, completed: true, .. } if *id == task_id
+    )));
+
+    folo.stop();
+    folo.wait();
+}
+
+#[test]
+fn flight_record_empty_when_disabled() {
+    let folo = RuntimeBuilder::new()
+        .worker_init(init_test_worker)
+        .max_processors(1)
+        .build()
+        .unwrap();
+
+    assert!(folo.dump_flight_record().workers().is_empty());
+
+    folo.stop();
+    folo.wait();
+}
+
+#[test]
+fn task_traces_report_instrumented_await_points() {
+    let folo = RuntimeBuilder::new()
+        .worker_init(init_test_worker)
+        .max_processors(1)
+        .build()
+        .unwrap();
+
+    let (started_tx, started_rx) = mpsc::channel();
+
+    folo.spawn_on_any_named("sleeper", move || async move {
+        _ = started_tx.send(line!() + 1);
+        traced(async {
+            let clock = Clock::new();
+            Delay::with_clock(&clock, Duration::from_secs(3600)).await;
+        })
+        .await;
+    });
+
+    let traced_line = started_rx.recv().unwrap();
+
+    let traces = folo.capture_task_traces(Duration::from_secs(10));
+
+    let sleeper = traces
+        .iter()
+        .find(|trace| trace.task_name() == Some("sleeper"))
+        .expect("the sleeping task must be listed");
+
+    assert_eq!(sleeper.awaiting(), Some("timer"));
+    assert_eq!(sleeper.locations().len(), 1);
+    assert_eq!(sleeper.locations()[0].file(), file!());
+    assert_eq!(sleeper.locations()[0].line(), traced_line);
+
+    folo.stop();
+    folo.wait();
+}
+
+#[test]
+fn stalled_tasks_and_lost_wakers_reported_to_diagnostics_callback() {
+    let (reports_tx, reports_rx) = mpsc::channel();
+    let reports_tx = Mutex::new(reports_tx);
+
+    let folo = RuntimeBuilder::new()
+        .worker_init(init_test_worker)
+        .max_processors(1)
+        .maintenance_interval(Duration::from_millis(10))
+        .detect_stalled_tasks(Duration::from_millis(50))
+        .diagnostics(DiagnosticsBackend::callback(move |diagnostic| {
+            let report = match diagnostic {
+                Diagnostic::TaskStalled { task_name, .. } => {
+                    ("stalled", task_name.map(String::from))
+                }
+                Diagnostic::LostWaker { task_name, .. } => ("lost", task_name.map(String::from)),
+                _ => return,
+            };
+
+            _ = reports_tx.lock().unwrap().send(report);
+        }))
+        .build()
+        .unwrap();
+
+    // Nobody ever sends to this channel but the sender (and with it the waker) is kept alive.
+    let (_never_tx, never_rx) = futures::channel::oneshot::channel::<()>();
+
+    folo.spawn_on_any_named("waiting-forever", move || async move {
+        _ = never_rx.await;
+    });
+
+    // Returns `Pending` without keeping the waker, so nothing can wake it up.
+    folo.spawn_on_any_named("forgotten", || future::pending::<()>());
+
+    let mut reports = vec![
+        reports_rx.recv_timeout(Duration::from_secs(10)).unwrap(),
+        reports_rx.recv_timeout(Duration::from_secs(10)).unwrap(),
+    ];
+    reports.sort();
+
+    assert_eq!(
+        reports,
+        vec![
+            ("lost", Some("forgotten".to_string())),
+            ("stalled", Some("waiting-forever".to_string())),
+        ]
+    );
+
+    // Each stall is only reported once.
+    assert!(reports_rx.recv_timeout(Duration::from_millis(200)).is_err());
+
+    folo.stop();
+    folo.wait();
+}
+
+#[test]
+fn watchdog_reports_blocked_worker_with_task_name() {
+    let (blocked_tx, blocked_rx) = mpsc::channel();
+    let blocked_tx = Mutex::new(blocked_tx);
+
+    let folo = RuntimeBuilder::new()
+        .worker_init(init_test_worker)
+        .max_processors(1)
+        .watchdog(
+            Duration::from_millis(100),
+            WatchdogPolicy::callback(move |blocked| {
+                _ = blocked_tx.lock().unwrap().send((
+                    blocked.worker_index(),
+                    blocked.task_name().map(String::from),
+                    blocked.blocked_for(),
+                ));
+            }),
+        )
+        .build()
+        .unwrap();
+
+    folo.spawn_on_any_named("blocker", || async {
+        // A synchronous call that blocks the worker thread.
+        thread::sleep(Duration::from_millis(500));
+    });
+
+    let (worker_index, task_name, blocked_for) =
+        blocked_rx.recv_timeout(Duration::from_secs(10)).unwrap();
+
+    assert_eq!(worker_index, 0);
+    assert_eq!(task_name.as_deref(), Some("blocker"));
+    assert!(blocked_for >= Duration::from_millis(100));
+
+    // The block is only reported once and an idle worker is not blocked.
+    assert!(blocked_rx.recv_timeout(Duration::from_millis(700)).is_err());
+
+    folo.stop();
+    folo.wait();
+}
+
+#[test]
+fn current_numa_node_is_node_of_worker() {
+    // Every machine has NUMA node 0.
+    let folo = RuntimeBuilder::new()
+        .worker_init(init_test_worker)
+        .affinity(Affinity::NumaNodes(vec![0]))
+        .max_processors(2)
+        .build()
+        .unwrap();
+
+    let node = futures::executor::block_on(folo.spawn_on_any(|| async { current_numa_node() }));
+    assert_eq!(node, Some(0));
+
+    // This is not a worker thread.
+    assert_eq!(current_numa_node(), None);
+
+    folo.stop();
+    folo.wait();
+}
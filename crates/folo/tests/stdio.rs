@@ -0,0 +1,18 @@
+use folo::{
+    io::{self, Buffer},
+    mem::isolation::Isolated,
+};
+use folo_testing::init_test_worker;
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn write_to_stdout_and_stderr() {
+    let mut buffer = Buffer::<Isolated>::from_pool();
+    buffer.as_mut_slice()[..12].copy_from_slice(b"hello, folo\n");
+    buffer.set_len(12);
+
+    let buffer = io::stdout().write(buffer).await.unwrap();
+    assert_eq!(&*buffer.as_slice(), b"hello, folo\n");
+
+    io::stdout().write_all(b"hello again\n").await.unwrap();
+    io::stderr().write_all(b"hello, stderr\n").await.unwrap();
+}
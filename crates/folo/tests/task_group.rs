@@ -0,0 +1,96 @@
+use folo::rt::{yield_now, TaskGroup, TaskGroupError};
+use folo_testing::init_test_worker;
+use futures::future;
+use std::{cell::Cell, rc::Rc};
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn join_returns_values_in_spawn_order() {
+    let mut group = TaskGroup::<usize, ()>::new();
+
+    for index in 0..5 {
+        group.spawn(async move {
+            // Later children complete first.
+            for _ in index..5 {
+                yield_now().await;
+            }
+
+            Ok(index)
+        });
+    }
+
+    assert_eq!(group.len(), 5);
+    assert_eq!(group.join().await.unwrap(), vec![0, 1, 2, 3, 4]);
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn failure_cancels_siblings() {
+    let mut group = TaskGroup::<(), &'static str>::new();
+
+    let sibling_finished = Rc::new(Cell::new(false));
+
+    group.spawn({
+        let sibling_finished = Rc::clone(&sibling_finished);
+
+        async move {
+            future::pending::<()>().await;
+            sibling_finished.set(true);
+            Ok(())
+        }
+    });
+
+    group.spawn(async { Err("boom") });
+
+    let result = group.join().await;
+
+    assert!(matches!(result, Err(TaskGroupError::Failed("boom"))));
+    assert!(!sibling_finished.get());
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn failure_without_cancellation_waits_for_siblings() {
+    let mut group = TaskGroup::<(), &'static str>::new().cancel_on_failure(false);
+
+    let sibling_finished = Rc::new(Cell::new(false));
+
+    group.spawn(async { Err("boom") });
+
+    group.spawn({
+        let sibling_finished = Rc::clone(&sibling_finished);
+
+        async move {
+            yield_now().await;
+            sibling_finished.set(true);
+            Ok(())
+        }
+    });
+
+    let result = group.join().await;
+
+    assert!(matches!(result, Err(TaskGroupError::Failed("boom"))));
+    assert!(sibling_finished.get());
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn dropping_group_aborts_children() {
+    let child_finished = Rc::new(Cell::new(false));
+
+    {
+        let mut group = TaskGroup::<(), ()>::new();
+
+        group.spawn({
+            let child_finished = Rc::clone(&child_finished);
+
+            async move {
+                yield_now().await;
+                child_finished.set(true);
+                Ok(())
+            }
+        });
+    }
+
+    for _ in 0..10 {
+        yield_now().await;
+    }
+
+    assert!(!child_finished.get());
+}
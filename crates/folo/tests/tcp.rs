@@ -0,0 +1,534 @@
+use folo::{
+    fs::File,
+    io::{Buffer, OperationResultExt},
+    mem::isolation::Isolated,
+    net::{ConnectionLimit, TcpConnector, TcpListener, TcpStream},
+    rt::{spawn, spawn_on_all},
+};
+use folo_testing::init_test_worker;
+use futures::StreamExt;
+use std::{
+    env, fs,
+    net::{Ipv4Addr, Shutdown, SocketAddr},
+    process,
+    time::Duration,
+};
+
+const MESSAGE: &[u8] = b"hello, folo";
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn tcp_echo_over_loopback() {
+    let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+        .await
+        .unwrap();
+
+    let listen_addr = listener.local_addr().unwrap();
+    assert_ne!(listen_addr.port(), 0);
+
+    let server = spawn(async move {
+        let (mut stream, peer_addr) = listener.accept().await.unwrap();
+        assert!(peer_addr.ip().is_loopback());
+
+        let buffer = stream
+            .read(Buffer::<Isolated>::from_pool())
+            .await
+            .into_inner()
+            .unwrap();
+
+        stream.write(buffer).await.into_inner().unwrap();
+        stream.shutdown().await.unwrap();
+    });
+
+    let mut client = TcpStream::connect(listen_addr).await.unwrap();
+    assert_eq!(client.peer_addr().unwrap(), listen_addr);
+
+    let mut buffer = Buffer::<Isolated>::from_pool();
+    buffer.as_mut_slice()[..MESSAGE.len()].copy_from_slice(MESSAGE);
+    buffer.set_len(MESSAGE.len());
+
+    let buffer = client.write(buffer).await.into_inner().unwrap();
+
+    let buffer = client.read(buffer.use_all()).await.into_inner().unwrap();
+    assert_eq!(&*buffer.as_slice(), MESSAGE);
+
+    client.shutdown().await.unwrap();
+    server.await;
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn tcp_connect_to_closed_port_fails() {
+    // We bind a listener to get a free port and then close it, so nobody listens on the port.
+    let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+        .await
+        .unwrap();
+    let addr = listener.local_addr().unwrap();
+    drop(listener);
+
+    assert!(TcpStream::connect(addr).await.is_err());
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn tcp_vectored_io() {
+    let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+        .await
+        .unwrap();
+
+    let listen_addr = listener.local_addr().unwrap();
+
+    let server = spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+
+        let mut header = Buffer::<Isolated>::from_pool();
+        header.set_len(5);
+
+        let buffers = stream
+            .read_vectored(vec![header, Buffer::<Isolated>::from_pool()])
+            .await
+            .unwrap();
+
+        assert_eq!(&*buffers[0].as_slice(), &MESSAGE[..5]);
+        assert_eq!(&*buffers[1].as_slice(), &MESSAGE[5..]);
+
+        stream.shutdown().await.unwrap();
+    });
+
+    let mut client = TcpStream::connect(listen_addr).await.unwrap();
+
+    let mut header = Buffer::<Isolated>::from_pool();
+    header.as_mut_slice()[..7].copy_from_slice(&MESSAGE[..7]);
+    header.set_len(7);
+
+    let mut body = Buffer::<Isolated>::from_pool();
+    body.as_mut_slice()[..MESSAGE.len() - 7].copy_from_slice(&MESSAGE[7..]);
+    body.set_len(MESSAGE.len() - 7);
+
+    let buffers = client.write_vectored(vec![header, body]).await.unwrap();
+    assert_eq!(
+        buffers.iter().map(Buffer::len).sum::<usize>(),
+        MESSAGE.len()
+    );
+
+    client.shutdown().await.unwrap();
+    server.await;
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn tcp_send_file() {
+    let path = env::temp_dir().join(format!("folo-tcp-send-file-{}.bin", process::id()));
+    let content = (0..200_000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+    fs::write(&path, &content).unwrap();
+
+    let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+        .await
+        .unwrap();
+
+    let listen_addr = listener.local_addr().unwrap();
+
+    let server = spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+
+        let file = File::open(&path).await.unwrap();
+        stream.send_file(&file, 10..content.len()).await.unwrap();
+        stream.shutdown().await.unwrap();
+
+        drop(file);
+        _ = fs::remove_file(&path);
+
+        content
+    });
+
+    let mut client = TcpStream::connect(listen_addr).await.unwrap();
+    let mut received = Vec::new();
+
+    loop {
+        let buffer = client
+            .read(Buffer::<Isolated>::from_pool())
+            .await
+            .into_inner()
+            .unwrap();
+
+        if buffer.is_empty() {
+            break;
+        }
+
+        received.extend_from_slice(&buffer.as_slice());
+    }
+
+    client.shutdown().await.unwrap();
+
+    let content = server.await;
+    assert_eq!(received, &content[10..]);
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn tcp_per_worker_listener() {
+    let per_worker = TcpListener::bind_per_worker(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+        .await
+        .unwrap();
+
+    let listen_addr = per_worker.local_addr().unwrap();
+
+    // Every worker accepts one connection, so we know each of them received one.
+    let join_handles = spawn_on_all(|| {
+        let per_worker = per_worker.clone();
+
+        || async move {
+            let listener = per_worker.local();
+
+            let (mut stream, _) = listener.accept().await.unwrap();
+
+            let buffer = stream
+                .read(Buffer::<Isolated>::from_pool())
+                .await
+                .into_inner()
+                .unwrap();
+
+            stream.write(buffer).await.into_inner().unwrap();
+            stream.shutdown().await.unwrap();
+        }
+    });
+
+    for _ in 0..join_handles.len() {
+        let mut client = TcpStream::connect(listen_addr).await.unwrap();
+
+        let mut buffer = Buffer::<Isolated>::from_pool();
+        buffer.set_len(MESSAGE.len());
+        buffer.as_mut_slice().copy_from_slice(MESSAGE);
+        client.write(buffer).await.into_inner().unwrap();
+
+        let buffer = client
+            .read(Buffer::<Isolated>::from_pool())
+            .await
+            .into_inner()
+            .unwrap();
+        assert_eq!(&*buffer.as_slice(), MESSAGE);
+    }
+
+    for join_handle in join_handles.into_vec() {
+        join_handle.await;
+    }
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn tcp_registered_stream() {
+    let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+        .await
+        .unwrap();
+
+    let listen_addr = listener.local_addr().unwrap();
+
+    let server = spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut stream = stream.register();
+
+        let buffer = stream
+            .read(Buffer::<Isolated>::from_pool())
+            .await
+            .into_inner()
+            .unwrap();
+
+        stream.write(buffer).await.into_inner().unwrap();
+        stream.shutdown().await.unwrap();
+    });
+
+    let mut client = TcpStream::connect(listen_addr).await.unwrap().register();
+
+    let mut buffer = Buffer::<Isolated>::from_pool();
+    buffer.set_len(MESSAGE.len());
+    buffer.as_mut_slice().copy_from_slice(MESSAGE);
+    client.write(buffer).await.into_inner().unwrap();
+
+    let buffer = client
+        .read(Buffer::<Isolated>::from_pool())
+        .await
+        .into_inner()
+        .unwrap();
+    assert_eq!(&*buffer.as_slice(), MESSAGE);
+
+    // Once the peer has closed the connection, reads return empty buffers.
+    let buffer = client
+        .read(Buffer::<Isolated>::from_pool())
+        .await
+        .into_inner()
+        .unwrap();
+    assert!(buffer.is_empty());
+
+    let mut client = client.into_inner();
+    client.shutdown().await.unwrap();
+    server.await;
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn tcp_dropped_read_is_canceled() {
+    let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+        .await
+        .unwrap();
+
+    let listen_addr = listener.local_addr().unwrap();
+
+    let mut client = TcpStream::connect(listen_addr).await.unwrap();
+    let (mut server, _) = listener.accept().await.unwrap();
+
+    // No data has been sent yet, so the read remains pending until we drop it, which cancels it.
+    let read = client.read(Buffer::<Isolated>::from_pool());
+    drop(read);
+
+    let mut buffer = Buffer::<Isolated>::from_pool();
+    buffer.set_len(MESSAGE.len());
+    buffer.as_mut_slice().copy_from_slice(MESSAGE);
+    server.write(buffer).await.into_inner().unwrap();
+
+    // If the canceled read had stayed pending, it would have consumed the data.
+    let buffer = client
+        .read(Buffer::<Isolated>::from_pool())
+        .await
+        .into_inner()
+        .unwrap();
+    assert_eq!(&*buffer.as_slice(), MESSAGE);
+
+    server.shutdown().await.unwrap();
+    client.shutdown().await.unwrap();
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn tcp_connection_limit() {
+    let limit = ConnectionLimit::new(1);
+
+    let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+        .await
+        .unwrap()
+        .with_connection_limit(limit.clone());
+
+    let listen_addr = listener.local_addr().unwrap();
+
+    let server = spawn(async move {
+        for _ in 0..2 {
+            let (mut stream, _) = listener.accept().await.unwrap();
+
+            // The connection ends when the client shuts it down, after which the listener may
+            // accept the next connection.
+            let buffer = stream
+                .read(Buffer::<Isolated>::from_pool())
+                .await
+                .into_inner()
+                .unwrap();
+            assert_eq!(buffer.len(), 0);
+        }
+    });
+
+    // The second connection waits in the backlog until the first one is closed.
+    let mut first = TcpStream::connect(listen_addr).await.unwrap();
+    let mut second = TcpStream::connect(listen_addr).await.unwrap();
+
+    first.shutdown().await.unwrap();
+    second.shutdown().await.unwrap();
+
+    server.await;
+
+    assert_eq!(limit.current(), 0);
+    assert_eq!(limit.peak(), 1);
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn tcp_half_close() {
+    let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+        .await
+        .unwrap();
+
+    let listen_addr = listener.local_addr().unwrap();
+
+    let server = spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+
+        let buffer = stream
+            .read(Buffer::<Isolated>::from_pool())
+            .await
+            .into_inner()
+            .unwrap();
+        assert_eq!(&*buffer.as_slice(), MESSAGE);
+
+        // The client has shut down its write direction, which we see as the end of the stream.
+        let end = stream
+            .read(Buffer::<Isolated>::from_pool())
+            .await
+            .into_inner()
+            .unwrap();
+        assert!(end.is_empty());
+
+        // We can still respond, as the client continues reading.
+        stream.write(buffer).await.into_inner().unwrap();
+        stream.shutdown_with(Shutdown::Write).await.unwrap();
+    });
+
+    let mut client = TcpStream::connect(listen_addr).await.unwrap();
+
+    let mut buffer = Buffer::<Isolated>::from_pool();
+    buffer.as_mut_slice()[..MESSAGE.len()].copy_from_slice(MESSAGE);
+    buffer.set_len(MESSAGE.len());
+
+    let buffer = client.write(buffer).await.into_inner().unwrap();
+    client.shutdown_with(Shutdown::Write).await.unwrap();
+
+    let buffer = client.read(buffer.use_all()).await.into_inner().unwrap();
+    assert_eq!(&*buffer.as_slice(), MESSAGE);
+
+    let end = client.read(buffer.use_all()).await.into_inner().unwrap();
+    assert!(end.is_empty());
+
+    server.await;
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn tcp_connect_host() {
+    let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+        .await
+        .unwrap();
+
+    let port = listener.local_addr().unwrap().port();
+
+    let server = spawn(async move {
+        for _ in 0..2 {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            stream.shutdown().await.unwrap();
+        }
+    });
+
+    // The name may also resolve to an IPv6 address nobody listens on, which must not prevent us
+    // from connecting via IPv4.
+    let mut client = TcpStream::connect_host("localhost", port).await.unwrap();
+    assert!(client.peer_addr().unwrap().ip().is_loopback());
+    client.shutdown().await.unwrap();
+
+    let mut client = TcpConnector::new()
+        .timeout(Duration::from_secs(10))
+        .connect("127.0.0.1", port)
+        .await
+        .unwrap();
+    client.shutdown().await.unwrap();
+
+    server.await;
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn tcp_write_zero_copy() {
+    let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+        .await
+        .unwrap();
+
+    let listen_addr = listener.local_addr().unwrap();
+
+    let server = spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+
+        let mut buffer = Buffer::<Isolated>::from_pool();
+        buffer.as_mut_slice().fill(42);
+
+        let buffer = stream.write_zero_copy(buffer).await.into_inner().unwrap();
+        let sent = buffer.len();
+
+        stream.shutdown().await.unwrap();
+
+        sent
+    });
+
+    let mut client = TcpStream::connect(listen_addr).await.unwrap();
+    let mut received = Vec::new();
+
+    loop {
+        let buffer = client
+            .read(Buffer::<Isolated>::from_pool())
+            .await
+            .into_inner()
+            .unwrap();
+
+        if buffer.is_empty() {
+            break;
+        }
+
+        received.extend_from_slice(&buffer.as_slice());
+    }
+
+    client.shutdown().await.unwrap();
+
+    let sent = server.await;
+    assert_eq!(received.len(), sent);
+    assert!(received.iter().all(|&b| b == 42));
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn tcp_listener_incoming() {
+    let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+        .await
+        .unwrap();
+
+    let listen_addr = listener.local_addr().unwrap();
+
+    let server = spawn(async move {
+        let accepted = listener
+            .incoming()
+            .take(2)
+            .map(|connection| connection.unwrap())
+            .collect::<Vec<_>>()
+            .await;
+
+        for (mut stream, peer_addr) in accepted {
+            assert!(peer_addr.ip().is_loopback());
+            stream.shutdown().await.unwrap();
+        }
+    });
+
+    let first = TcpStream::connect(listen_addr).await.unwrap();
+    let second = TcpStream::connect(listen_addr).await.unwrap();
+
+    server.await;
+
+    drop(first);
+    drop(second);
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn tcp_peek_and_read_exact() {
+    let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+        .await
+        .unwrap();
+
+    let listen_addr = listener.local_addr().unwrap();
+
+    let server = spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+
+        // A length prefix, followed by the message itself.
+        let mut buffer = Buffer::<Isolated>::from_pool();
+        buffer.as_mut_slice()[0] = MESSAGE.len() as u8;
+        buffer.as_mut_slice()[1..=MESSAGE.len()].copy_from_slice(MESSAGE);
+        buffer.set_len(MESSAGE.len() + 1);
+
+        stream.write(buffer).await.into_inner().unwrap();
+        stream.shutdown().await.unwrap();
+    });
+
+    let mut client = TcpStream::connect(listen_addr).await.unwrap();
+
+    let mut prefix = Buffer::<Isolated>::from_pool();
+    prefix.set_len(1);
+
+    let peeked = client.peek(prefix).await.into_inner().unwrap();
+    assert_eq!(peeked.as_slice()[0] as usize, MESSAGE.len());
+
+    let prefix = client.read_exact(peeked).await.into_inner().unwrap();
+    let len = prefix.as_slice()[0] as usize;
+
+    let mut message = Buffer::<Isolated>::from_pool();
+    message.set_len(len);
+
+    let message = client.read_exact(message).await.into_inner().unwrap();
+    assert_eq!(&*message.as_slice(), MESSAGE);
+
+    // The peer has closed the connection, so there is nothing left to fill another buffer with.
+    let mut rest = Buffer::<Isolated>::from_pool();
+    rest.set_len(1);
+
+    assert!(client.read_exact(rest).await.is_err());
+
+    server.await;
+}
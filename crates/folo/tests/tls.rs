@@ -0,0 +1,86 @@
+#![cfg(feature = "tls")]
+
+use folo::{
+    io::Buffer,
+    mem::isolation::Isolated,
+    net::{TcpListener, TcpStream},
+    rt::spawn,
+    tls::{TlsAcceptor, TlsConnector},
+};
+use folo_testing::init_test_worker;
+use rustls::{
+    crypto::ring,
+    pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer},
+    ClientConfig, RootCertStore, ServerConfig,
+};
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    sync::Arc,
+};
+
+const MESSAGE: &[u8] = b"hello, folo";
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn tls_echo_over_loopback() {
+    let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+    let certificate = certified.cert.der().clone();
+    let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(certified.key_pair.serialize_der()));
+
+    let server_config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_no_client_auth()
+        .with_single_cert(vec![certificate.clone()], key)
+        .unwrap();
+
+    let mut roots = RootCertStore::empty();
+    roots.add(certificate).unwrap();
+
+    let client_config = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+
+    let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+        .await
+        .unwrap();
+
+    let listen_addr = listener.local_addr().unwrap();
+    let acceptor = TlsAcceptor::new(Arc::new(server_config));
+
+    let server = spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut stream = acceptor.accept(stream).await.unwrap();
+
+        let buffer = stream.read(Buffer::<Isolated>::from_pool()).await.unwrap();
+        assert_eq!(&*buffer.as_slice(), MESSAGE);
+
+        stream.write(buffer).await.unwrap();
+
+        // The client closes the session first.
+        let buffer = stream.read(buffer.use_all()).await.unwrap();
+        assert!(buffer.is_empty());
+
+        stream.shutdown().await.unwrap();
+    });
+
+    let connector = TlsConnector::new(Arc::new(client_config));
+    let stream = TcpStream::connect(listen_addr).await.unwrap();
+    let mut stream = connector
+        .connect("localhost".try_into().unwrap(), stream)
+        .await
+        .unwrap();
+
+    let mut buffer = Buffer::<Isolated>::from_pool();
+    buffer.as_mut_slice()[..MESSAGE.len()].copy_from_slice(MESSAGE);
+    buffer.set_len(MESSAGE.len());
+
+    let buffer = stream.write(buffer).await.unwrap();
+
+    let buffer = stream.read(buffer.use_all()).await.unwrap();
+    assert_eq!(&*buffer.as_slice(), MESSAGE);
+
+    stream.shutdown().await.unwrap();
+    server.await;
+}
@@ -0,0 +1,87 @@
+#![cfg(feature = "tokio-bridge")]
+
+use folo::{
+    bridge::tokio::{channel, spawn_on_folo, spawn_on_tokio},
+    rt::{current_worker_index, RuntimeBuilder},
+};
+use folo_testing::init_test_worker;
+use std::rc::Rc;
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn folo_awaits_tokio_task() {
+    let tokio = tokio::runtime::Runtime::new().unwrap();
+
+    let result = spawn_on_tokio(tokio.handle(), async {
+        tokio::task::yield_now().await;
+        42
+    })
+    .await;
+
+    assert_eq!(result, 42);
+
+    tokio.shutdown_background();
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn aborted_tokio_task_reports_cancellation() {
+    let tokio = tokio::runtime::Runtime::new().unwrap();
+
+    let task = spawn_on_tokio(tokio.handle(), futures::future::pending::<()>());
+    task.abort();
+
+    assert!(task.result().await.unwrap_err().is_cancelled());
+
+    tokio.shutdown_background();
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn channel_from_tokio_to_folo() {
+    let tokio = tokio::runtime::Runtime::new().unwrap();
+
+    // The small capacity makes the sender wait for the receiver, so wake-ups cross the boundary in
+    // both directions.
+    let (tx, mut rx) = channel(2);
+
+    tokio.spawn(async move {
+        for i in 0..100 {
+            tx.send(i).await.unwrap();
+        }
+    });
+
+    let mut sum = 0;
+
+    while let Some(i) = rx.recv().await {
+        sum += i;
+    }
+
+    assert_eq!(sum, 4950);
+
+    tokio.shutdown_background();
+}
+
+#[test]
+fn tokio_awaits_folo_task() {
+    let folo = RuntimeBuilder::new().build().unwrap();
+    let tokio = tokio::runtime::Runtime::new().unwrap();
+
+    let worker_index = tokio.block_on(spawn_on_folo(&folo, || async {
+        // Thread-local state proves that the future does not need to be `Send`.
+        let rc = Rc::new(current_worker_index());
+        folo::rt::yield_now().await;
+
+        *rc
+    }));
+
+    assert!(worker_index.is_some());
+
+    let (tx, mut rx) = channel(1);
+
+    folo.spawn_on_any(|| async move {
+        tx.send("hello from folo").await.unwrap();
+    });
+
+    assert_eq!(tokio.block_on(rx.recv()), Some("hello from folo"));
+
+    folo.stop();
+    folo.wait();
+}
@@ -0,0 +1,158 @@
+#![cfg(feature = "tokio-compat")]
+
+use folo::{
+    io::{Buffer, CompatExt, TokioStream},
+    mem::isolation::Isolated,
+    net::{TcpListener, TcpStream},
+    rt::spawn,
+};
+use folo_testing::init_test_worker;
+use std::net::{Ipv4Addr, SocketAddr};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn compat_echo() {
+    let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+        .await
+        .unwrap();
+
+    let listen_addr = listener.local_addr().unwrap();
+
+    let server = spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut stream = stream.compat();
+
+        let mut request = [0; 5];
+        stream.read_exact(&mut request).await.unwrap();
+        assert_eq!(&request, b"hello");
+
+        stream.write_all(b"world").await.unwrap();
+        stream.shutdown().await.unwrap();
+    });
+
+    let mut client = TcpStream::connect(listen_addr).await.unwrap().compat();
+
+    client.write_all(b"hello").await.unwrap();
+    client.flush().await.unwrap();
+
+    let mut response = Vec::new();
+    client.read_to_end(&mut response).await.unwrap();
+    assert_eq!(response, b"world");
+
+    client.shutdown().await.unwrap();
+    server.await;
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn tokio_stream_duplex() {
+    let (client, mut server) = tokio::io::duplex(1024);
+    let mut client = TokioStream::new(client);
+
+    let mut buffer = Buffer::<Isolated>::from_pool();
+    buffer.set_len(5);
+    buffer.as_mut_slice().copy_from_slice(b"hello");
+    client.write(buffer).await.unwrap();
+
+    let mut request = [0; 5];
+    server.read_exact(&mut request).await.unwrap();
+    assert_eq!(&request, b"hello");
+
+    server.write_all(b"world").await.unwrap();
+    drop(server);
+
+    let buffer = client.read(Buffer::<Isolated>::from_pool()).await.unwrap();
+    assert_eq!(&*buffer.as_slice(), b"world");
+
+    let buffer = client.read(Buffer::<Isolated>::from_pool()).await.unwrap();
+    assert!(buffer.is_empty());
+}
+
+#[cfg(feature = "tls")]
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn h2_over_tls() {
+    use bytes::Bytes;
+    use folo::{
+        io::TlsCompat,
+        tls::{TlsAcceptor, TlsConnector},
+    };
+    use http::{Request, Response};
+    use rustls::{
+        crypto::ring,
+        pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer},
+        ClientConfig, RootCertStore, ServerConfig,
+    };
+    use std::sync::Arc;
+
+    const MESSAGE: &[u8] = b"hello, folo";
+
+    let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+    let certificate = certified.cert.der().clone();
+    let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(certified.key_pair.serialize_der()));
+
+    let mut server_config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_no_client_auth()
+        .with_single_cert(vec![certificate.clone()], key)
+        .unwrap();
+    server_config.alpn_protocols = vec![b"h2".to_vec()];
+
+    let mut roots = RootCertStore::empty();
+    roots.add(certificate).unwrap();
+
+    let mut client_config = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    client_config.alpn_protocols = vec![b"h2".to_vec()];
+
+    let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+        .await
+        .unwrap();
+
+    let listen_addr = listener.local_addr().unwrap();
+    let acceptor = TlsAcceptor::new(Arc::new(server_config));
+
+    let server = spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let stream = acceptor.accept(stream).await.unwrap();
+        assert_eq!(stream.alpn_protocol(), Some(&b"h2"[..]));
+
+        let mut connection = h2::server::handshake(TlsCompat::new(stream)).await.unwrap();
+
+        while let Some(request) = connection.accept().await {
+            let (_, mut respond) = request.unwrap();
+
+            let mut body = respond.send_response(Response::new(()), false).unwrap();
+            body.send_data(Bytes::from_static(MESSAGE), true).unwrap();
+        }
+    });
+
+    let connector = TlsConnector::new(Arc::new(client_config));
+    let stream = TcpStream::connect(listen_addr).await.unwrap();
+    let stream = connector
+        .connect("localhost".try_into().unwrap(), stream)
+        .await
+        .unwrap();
+
+    let (client, connection) = h2::client::handshake(TlsCompat::new(stream)).await.unwrap();
+    let connection = spawn(connection);
+
+    let mut client = client.ready().await.unwrap();
+
+    let request = Request::builder()
+        .uri("https://localhost/")
+        .body(())
+        .unwrap();
+    let (response, _) = client.send_request(request, true).unwrap();
+
+    let mut body = response.await.unwrap().into_body();
+    let data = body.data().await.unwrap().unwrap();
+    assert_eq!(&data[..], MESSAGE);
+
+    // Dropping the client closes the connection, which ends it on the server as well.
+    drop(client);
+    connection.await.unwrap();
+    server.await;
+}
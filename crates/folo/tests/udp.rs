@@ -0,0 +1,253 @@
+use folo::{
+    io::{Buffer, OperationResultExt},
+    mem::isolation::Isolated,
+    net::{PacketInfo, UdpSocket},
+};
+use folo_testing::init_test_worker;
+use std::{
+    net::{self, IpAddr, Ipv4Addr, SocketAddr},
+    os::windows::io::IntoRawSocket,
+};
+use windows::Win32::Networking::WinSock::SOCKET;
+
+const MESSAGE: &[u8] = b"hello, folo";
+
+fn message_buffer() -> Buffer<Isolated> {
+    let mut buffer = Buffer::<Isolated>::from_pool();
+    buffer.as_mut_slice()[..MESSAGE.len()].copy_from_slice(MESSAGE);
+    buffer.set_len(MESSAGE.len());
+    buffer
+}
+
+async fn bind_loopback() -> UdpSocket {
+    UdpSocket::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+        .await
+        .unwrap()
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn udp_send_to_and_recv_from() {
+    let receiver = bind_loopback().await;
+    let sender = bind_loopback().await;
+
+    let receiver_addr = receiver.local_addr().unwrap();
+    let sender_addr = sender.local_addr().unwrap();
+
+    let receive = receiver.recv_from(Buffer::<Isolated>::from_pool());
+
+    sender
+        .send_to(message_buffer(), receiver_addr)
+        .await
+        .into_inner()
+        .unwrap();
+
+    let datagram = receive.await.unwrap();
+
+    assert_eq!(datagram.peer_addr(), sender_addr);
+    assert!(!datagram.is_truncated());
+    assert_eq!(&*datagram.buffer().as_slice(), MESSAGE);
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn udp_connected_send_and_recv() {
+    let a = bind_loopback().await;
+    let b = bind_loopback().await;
+
+    a.connect(b.local_addr().unwrap()).unwrap();
+    b.connect(a.local_addr().unwrap()).unwrap();
+
+    assert_eq!(a.peer_addr().unwrap(), b.local_addr().unwrap());
+
+    a.send(message_buffer()).await.into_inner().unwrap();
+
+    let datagram = b.recv(Buffer::<Isolated>::from_pool()).await.unwrap();
+    assert_eq!(&*datagram.buffer().as_slice(), MESSAGE);
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn udp_truncated_datagram_is_reported() {
+    let receiver = bind_loopback().await;
+    let sender = bind_loopback().await;
+
+    // Only room for the first 4 bytes of the message.
+    let mut buffer = Buffer::<Isolated>::from_pool();
+    buffer.set_len(4 + UdpSocket::RECEIVE_ADDRESS_SPACE);
+
+    let receive = receiver.recv_from(buffer);
+
+    sender
+        .send_to(message_buffer(), receiver.local_addr().unwrap())
+        .await
+        .into_inner()
+        .unwrap();
+
+    let datagram = receive.await.unwrap();
+
+    assert!(datagram.is_truncated());
+    assert_eq!(datagram.peer_addr(), sender.local_addr().unwrap());
+    assert_eq!(&*datagram.buffer().as_slice(), &MESSAGE[..4]);
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn udp_receive_buffer_too_small_is_error() {
+    let socket = bind_loopback().await;
+
+    let mut buffer = Buffer::<Isolated>::from_pool();
+    buffer.set_len(UdpSocket::RECEIVE_ADDRESS_SPACE);
+
+    assert!(socket.recv_from(buffer).await.is_err());
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn udp_multicast_loopback() {
+    const GROUP: Ipv4Addr = Ipv4Addr::new(239, 255, 70, 79);
+
+    let receiver = UdpSocket::bind(SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)))
+        .await
+        .unwrap();
+    receiver
+        .join_multicast_v4(GROUP, Ipv4Addr::LOCALHOST)
+        .unwrap();
+
+    let sender = bind_loopback().await;
+    sender
+        .set_multicast_interface_v4(Ipv4Addr::LOCALHOST)
+        .unwrap();
+    sender.set_multicast_loop_v4(true).unwrap();
+    sender.set_multicast_ttl_v4(1).unwrap();
+
+    let group_addr = SocketAddr::from((GROUP, receiver.local_addr().unwrap().port()));
+
+    let receive = receiver.recv_from(Buffer::<Isolated>::from_pool());
+
+    sender
+        .send_to(message_buffer(), group_addr)
+        .await
+        .into_inner()
+        .unwrap();
+
+    let datagram = receive.await.unwrap();
+    assert_eq!(&*datagram.buffer().as_slice(), MESSAGE);
+
+    receiver
+        .leave_multicast_v4(GROUP, Ipv4Addr::LOCALHOST)
+        .unwrap();
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn udp_message_with_control_data() {
+    let receiver = bind_loopback().await;
+    let sender = bind_loopback().await;
+
+    receiver.set_recv_packet_info(true).unwrap();
+    receiver.set_recv_ttl(true).unwrap();
+
+    let receive = receiver.recv_msg(Buffer::<Isolated>::from_pool());
+
+    sender
+        .send_msg(
+            message_buffer(),
+            Some(receiver.local_addr().unwrap()),
+            Some(PacketInfo::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0)),
+        )
+        .await
+        .into_inner()
+        .unwrap();
+
+    let message = receive.await.unwrap();
+
+    assert_eq!(&*message.buffer().as_slice(), MESSAGE);
+    assert_eq!(message.peer_addr(), sender.local_addr().unwrap());
+    assert!(!message.is_truncated());
+
+    let packet_info = message.packet_info().unwrap();
+    assert_eq!(packet_info.local_addr(), IpAddr::V4(Ipv4Addr::LOCALHOST));
+
+    assert!(message.ttl().is_some());
+
+    // Not enabled on the socket.
+    assert_eq!(message.tos(), None);
+    assert_eq!(message.timestamp(), None);
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn udp_send_many_and_recv_many() {
+    let receiver = bind_loopback().await;
+    let sender = bind_loopback().await;
+
+    // Segmentation offload is used if available, otherwise the datagrams are sent one by one.
+    // Either way, each datagram arrives separately as receive coalescing is not enabled.
+    let buffers = vec![message_buffer(), message_buffer(), message_buffer()];
+
+    let sent = sender
+        .send_many(buffers, Some(receiver.local_addr().unwrap()))
+        .await
+        .unwrap();
+    assert_eq!(sent.len(), 3);
+
+    for _ in 0..3 {
+        let batch = receiver
+            .recv_many(Buffer::<Isolated>::from_pool())
+            .await
+            .unwrap();
+
+        assert_eq!(batch.peer_addr(), sender.local_addr().unwrap());
+        assert_eq!(batch.len(), 1);
+        assert_eq!(batch.datagrams().collect::<Vec<_>>(), vec![MESSAGE]);
+    }
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn udp_peek_does_not_consume_datagram() {
+    let receiver = bind_loopback().await;
+    let sender = bind_loopback().await;
+
+    sender
+        .send_to(message_buffer(), receiver.local_addr().unwrap())
+        .await
+        .into_inner()
+        .unwrap();
+
+    let peeked = receiver
+        .peek_from(Buffer::<Isolated>::from_pool())
+        .await
+        .unwrap();
+    assert_eq!(peeked.peer_addr(), sender.local_addr().unwrap());
+    assert_eq!(&*peeked.buffer().as_slice(), MESSAGE);
+
+    let received = receiver
+        .recv_from(Buffer::<Isolated>::from_pool())
+        .await
+        .unwrap();
+    assert_eq!(&*received.buffer().as_slice(), MESSAGE);
+}
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn udp_adopts_external_socket() {
+    let receiver = bind_loopback().await;
+
+    // The standard library creates its sockets for overlapped I/O.
+    let external = net::UdpSocket::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0))).unwrap();
+    let external_addr = external.local_addr().unwrap();
+
+    let raw_socket = SOCKET(external.into_raw_socket() as usize);
+
+    // SAFETY: The socket is valid, overlapped and we have given up ownership of it.
+    let sender = unsafe { UdpSocket::from_raw_socket(raw_socket) }.unwrap();
+
+    assert_eq!(sender.local_addr().unwrap(), external_addr);
+
+    sender
+        .send_to(message_buffer(), receiver.local_addr().unwrap())
+        .await
+        .into_inner()
+        .unwrap();
+
+    let datagram = receiver
+        .recv_from(Buffer::<Isolated>::from_pool())
+        .await
+        .unwrap();
+
+    assert_eq!(datagram.peer_addr(), external_addr);
+    assert_eq!(&*datagram.buffer().as_slice(), MESSAGE);
+}
@@ -0,0 +1,48 @@
+use folo::{
+    io::{Buffer, OperationResultExt},
+    mem::isolation::Isolated,
+    net::{UnixListener, UnixStream},
+    rt::spawn,
+};
+use folo_testing::init_test_worker;
+use std::{env, fs, process};
+
+const MESSAGE: &[u8] = b"hello, folo";
+
+#[folo::test(worker_init_fn = init_test_worker)]
+async fn unix_echo() {
+    let path = env::temp_dir().join(format!("folo-unix-echo-{}.sock", process::id()));
+    _ = fs::remove_file(&path);
+
+    let listener = UnixListener::bind(&path).await.unwrap();
+    assert_eq!(listener.path(), path);
+
+    let server = spawn(async move {
+        let mut stream = listener.accept().await.unwrap();
+
+        let buffer = stream
+            .read(Buffer::<Isolated>::from_pool())
+            .await
+            .into_inner()
+            .unwrap();
+
+        stream.write(buffer).await.into_inner().unwrap();
+        stream.shutdown().await.unwrap();
+    });
+
+    let mut client = UnixStream::connect(&path).await.unwrap();
+
+    let mut buffer = Buffer::<Isolated>::from_pool();
+    buffer.as_mut_slice()[..MESSAGE.len()].copy_from_slice(MESSAGE);
+    buffer.set_len(MESSAGE.len());
+
+    let buffer = client.write(buffer).await.into_inner().unwrap();
+
+    let buffer = client.read(buffer.use_all()).await.into_inner().unwrap();
+    assert_eq!(&*buffer.as_slice(), MESSAGE);
+
+    client.shutdown().await.unwrap();
+    server.await;
+
+    _ = fs::remove_file(&path);
+}
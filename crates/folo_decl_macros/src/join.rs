@@ -0,0 +1,101 @@
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __macro_join {
+    // Each future is preceded by the `_` patterns that skip the futures before it in the tuple.
+    (@{ ( $($count:tt)* ) $( ( $($skip:tt)* ) $fut:expr, )* }) => {{
+        let futures = ( $( ::folo::__private::maybe_done($fut), )* );
+        let mut futures = ::std::pin::pin!(futures);
+
+        ::std::future::poll_fn(move |cx| {
+            let mut is_pending = false;
+
+            $(
+                // SAFETY: The futures are pinned as part of the tuple and never moved out of it.
+                let ( $($skip,)* fut, .. ) = unsafe { futures.as_mut().get_unchecked_mut() };
+                // SAFETY: See above.
+                let fut = unsafe { ::std::pin::Pin::new_unchecked(fut) };
+
+                is_pending |= ::std::future::Future::poll(fut, cx).is_pending();
+            )*
+
+            if is_pending {
+                return ::std::task::Poll::Pending;
+            }
+
+            ::std::task::Poll::Ready(( $({
+                // SAFETY: See above.
+                let ( $($skip,)* fut, .. ) = unsafe { futures.as_mut().get_unchecked_mut() };
+                // SAFETY: See above.
+                let fut = unsafe { ::std::pin::Pin::new_unchecked(fut) };
+
+                fut.take_output().expect("all futures have completed")
+            }, )* ))
+        })
+        .await
+    }};
+
+    (@{ ( $($count:tt)* ) $($acc:tt)* } $next:expr $(, $($rest:tt)*)?) => {
+        folo::join!(@{ ( $($count)* _ ) $($acc)* ( $($count)* ) $next, } $($($rest)*)?)
+    };
+
+    ($($fut:expr),+ $(,)?) => {
+        folo::join!(@{ () } $($fut),+)
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __macro_try_join {
+    // Each future is preceded by the `_` patterns that skip the futures before it in the tuple.
+    (@{ ( $($count:tt)* ) $( ( $($skip:tt)* ) $fut:expr, )* }) => {{
+        let futures = ( $( ::folo::__private::maybe_done($fut), )* );
+        let mut futures = ::std::pin::pin!(futures);
+
+        ::std::future::poll_fn(move |cx| {
+            let mut is_pending = false;
+
+            $(
+                // SAFETY: The futures are pinned as part of the tuple and never moved out of it.
+                let ( $($skip,)* fut, .. ) = unsafe { futures.as_mut().get_unchecked_mut() };
+                // SAFETY: See above.
+                let mut fut = unsafe { ::std::pin::Pin::new_unchecked(fut) };
+
+                if ::std::future::Future::poll(fut.as_mut(), cx).is_pending() {
+                    is_pending = true;
+                } else if fut
+                    .as_mut()
+                    .output_mut()
+                    .expect("the future has completed")
+                    .is_err()
+                {
+                    // The remaining futures are dropped together with the tuple.
+                    return ::std::task::Poll::Ready(::std::result::Result::Err(
+                        ::folo::__private::take_err(fut),
+                    ));
+                }
+            )*
+
+            if is_pending {
+                return ::std::task::Poll::Pending;
+            }
+
+            ::std::task::Poll::Ready(::std::result::Result::Ok(( $({
+                // SAFETY: See above.
+                let ( $($skip,)* fut, .. ) = unsafe { futures.as_mut().get_unchecked_mut() };
+                // SAFETY: See above.
+                let fut = unsafe { ::std::pin::Pin::new_unchecked(fut) };
+
+                ::folo::__private::take_ok(fut)
+            }, )* )))
+        })
+        .await
+    }};
+
+    (@{ ( $($count:tt)* ) $($acc:tt)* } $next:expr $(, $($rest:tt)*)?) => {
+        folo::try_join!(@{ ( $($count)* _ ) $($acc)* ( $($count)* ) $next, } $($($rest)*)?)
+    };
+
+    ($($fut:expr),+ $(,)?) => {
+        folo::try_join!(@{ () } $($fut),+)
+    };
+}
@@ -1 +1,3 @@
-pub mod linked;
\ No newline at end of file
+pub mod join;
+pub mod linked;
+pub mod task_local;
@@ -0,0 +1,21 @@
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __macro_task_local {
+    () => {};
+
+    ($(#[$attr:meta])* $vis:vis static $NAME:ident: $t:ty; $($rest:tt)*) => (
+        folo::rt::task_local!($(#[$attr])* $vis static $NAME: $t);
+        folo::rt::task_local!($($rest)*);
+    );
+
+    ($(#[$attr:meta])* $vis:vis static $NAME:ident: $t:ty) => {
+        $(#[$attr])* $vis static $NAME: ::folo::rt::TaskLocalKey<$t> = {
+            ::std::thread_local! {
+                static __FOLO_TASK_LOCAL: ::std::cell::RefCell<::std::option::Option<$t>> =
+                    const { ::std::cell::RefCell::new(::std::option::Option::None) };
+            }
+
+            ::folo::rt::TaskLocalKey::__new(&__FOLO_TASK_LOCAL)
+        };
+    };
+}
@@ -0,0 +1,14 @@
+use std::{env, path::PathBuf};
+
+// Regenerates the C header from the `extern "C"` surface of the crate, so it never goes stale. The
+// header is checked in, so C and C++ projects can use it without building the crate first.
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let crate_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR")?);
+
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+
+    cbindgen::generate(&crate_dir)?.write_to_file(crate_dir.join("include/folo.h"));
+
+    Ok(())
+}
@@ -0,0 +1,179 @@
+#ifndef FOLO_H
+#define FOLO_H
+
+/* Generated by cbindgen from the folo_ffi crate - do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * The outcome of a call to the C API.
+ */
+typedef enum FoloStatus {
+  /**
+   * The call succeeded.
+   */
+  FOLO_STATUS_OK = 0,
+  /**
+   * A required argument was null.
+   */
+  FOLO_STATUS_INVALID_ARGUMENT = 1,
+  /**
+   * The call was made on a thread that is not allowed to make it (e.g. shutting down the
+   * runtime from one of its own threads).
+   */
+  FOLO_STATUS_INVALID_THREAD = 2,
+  /**
+   * The runtime is stopping and does not accept new work.
+   */
+  FOLO_STATUS_STOPPING = 3,
+  /**
+   * The operation failed. The reason is logged via `tracing`.
+   */
+  FOLO_STATUS_FAILED = 4,
+  /**
+   * The runtime was shut down but some of its tasks did not complete in time and were canceled.
+   */
+  FOLO_STATUS_TIMED_OUT = 5,
+} FoloStatus;
+
+/**
+ * A Folo runtime started via `folo_runtime_create()`.
+ */
+typedef struct FoloRuntime FoloRuntime;
+
+/**
+ * A connected TCP socket adopted by a runtime via `folo_socket_adopt()`.
+ *
+ * The socket is bound to the I/O driver of one async worker thread, which performs all the
+ * operations on it and calls their callbacks.
+ */
+typedef struct FoloSocket FoloSocket;
+
+/**
+ * Called on an async worker thread to execute work submitted via `folo_runtime_submit()`.
+ */
+typedef void (*FoloCallback)(void *context);
+
+/**
+ * Called on an async worker thread when a read started via `folo_socket_read()` has completed.
+ *
+ * On success, `error` is 0 and `data` points to `len` bytes that remain valid until the callback
+ * returns. A `len` of 0 means that the peer has closed the connection. On failure, `error` is the
+ * operating system error code (or -1 if there is none), `data` is null and `len` is 0.
+ */
+typedef void (*FoloReadCallback)(void *context, int32_t error, const uint8_t *data, size_t len);
+
+/**
+ * Called on an async worker thread when a write started via `folo_socket_write()` has completed.
+ *
+ * `error` is 0 if all the data was written, otherwise the operating system error code (or -1 if
+ * there is none).
+ */
+typedef void (*FoloWriteCallback)(void *context, int32_t error);
+
+#ifdef __cplusplus
+extern "C" {
+#endif  // __cplusplus
+
+/**
+ * Starts a runtime with one async worker thread per processor, or at most `max_processors` of
+ * them if it is not 0. On success, stores the runtime in `*runtime`, which must eventually be
+ * stopped and released via `folo_runtime_shutdown()`.
+ *
+ * # Safety
+ *
+ * `runtime` must be null or valid for writes.
+ */
+FoloStatus folo_runtime_create(size_t max_processors, FoloRuntime **runtime);
+
+/**
+ * Shuts down the runtime, giving in-flight work up to `timeout_ms` milliseconds to complete, and
+ * releases it. Sockets adopted by the runtime must be closed before this is called.
+ *
+ * Returns `FOLO_STATUS_TIMED_OUT` if some work had to be canceled. The runtime is released either
+ * way, unless the call is made from one of the threads of the runtime (e.g. from a callback),
+ * which is not allowed and returns `FOLO_STATUS_INVALID_THREAD`.
+ *
+ * # Safety
+ *
+ * `runtime` must be null or a runtime created via `folo_runtime_create()` that has not been shut
+ * down.
+ */
+FoloStatus folo_runtime_shutdown(FoloRuntime *runtime, uint32_t timeout_ms);
+
+/**
+ * Calls `callback` with `context` on any async worker thread of the runtime. May be called from
+ * any thread, including from callbacks.
+ *
+ * # Safety
+ *
+ * `runtime` must be null or a runtime created via `folo_runtime_create()` that has not been shut
+ * down.
+ */
+FoloStatus folo_runtime_submit(const FoloRuntime *runtime, FoloCallback callback, void *context);
+
+/**
+ * Hands a connected TCP socket created by the host (e.g. accepted from a listening socket) to the
+ * runtime, which takes ownership of it. On success, stores the adopted socket in `*adopted`,
+ * which must eventually be closed via `folo_socket_close()`. The socket is closed on failure.
+ *
+ * If called from a callback, the socket is bound to the worker thread of the callback. Otherwise,
+ * it is bound to any worker thread and the call blocks until that thread has adopted it.
+ *
+ * # Safety
+ *
+ * `runtime` must be null or a runtime created via `folo_runtime_create()` that has not been shut
+ * down. `adopted` must be null or valid for writes.
+ *
+ * `socket` must be a valid, connected TCP socket created with `WSA_FLAG_OVERLAPPED` that is not
+ * associated with an I/O completion port. The host must not use or close it after this call.
+ */
+FoloStatus folo_socket_adopt(const FoloRuntime *runtime, size_t socket, FoloSocket **adopted);
+
+/**
+ * Closes the socket and releases it. Operations that are in progress complete first, with their
+ * callbacks still being called.
+ *
+ * # Safety
+ *
+ * `socket` must be null or a socket adopted via `folo_socket_adopt()` that has not been closed.
+ */
+FoloStatus folo_socket_close(FoloSocket *socket);
+
+/**
+ * Reads the next data received on the socket and delivers it to `callback`, together with
+ * `context`. Only one read should be in progress at a time, as the order in which concurrent reads
+ * complete is not defined.
+ *
+ * # Safety
+ *
+ * `socket` must be null or a socket adopted via `folo_socket_adopt()` that has not been closed.
+ */
+FoloStatus folo_socket_read(const FoloSocket *socket, FoloReadCallback callback, void *context);
+
+/**
+ * Writes `len` bytes from `data` to the socket, calling `callback` (if not null) with `context`
+ * once all of them have been written. The data is copied before this returns, so the host may
+ * release it right away. Data from concurrent writes may be interleaved - to keep writes in order,
+ * start the next one from the callback of the previous one.
+ *
+ * # Safety
+ *
+ * `socket` must be null or a socket adopted via `folo_socket_adopt()` that has not been closed.
+ * `data` must be null or valid for reads of `len` bytes.
+ */
+FoloStatus folo_socket_write(const FoloSocket *socket,
+                             const uint8_t *data,
+                             size_t len,
+                             FoloWriteCallback callback,
+                             void *context);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* FOLO_H */
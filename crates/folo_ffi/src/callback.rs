@@ -0,0 +1,42 @@
+use std::ffi::c_void;
+
+/// Called on an async worker thread to execute work submitted via `folo_runtime_submit()`.
+pub type FoloCallback = extern "C" fn(context: *mut c_void);
+
+/// Called on an async worker thread when a read started via `folo_socket_read()` has completed.
+///
+/// On success, `error` is 0 and `data` points to `len` bytes that remain valid until the callback
+/// returns. A `len` of 0 means that the peer has closed the connection. On failure, `error` is the
+/// operating system error code (or -1 if there is none), `data` is null and `len` is 0.
+pub type FoloReadCallback =
+    extern "C" fn(context: *mut c_void, error: i32, data: *const u8, len: usize);
+
+/// Called on an async worker thread when a write started via `folo_socket_write()` has completed.
+///
+/// `error` is 0 if all the data was written, otherwise the operating system error code (or -1 if
+/// there is none).
+pub type FoloWriteCallback = extern "C" fn(context: *mut c_void, error: i32);
+
+/// The context pointer given by the host along with a callback, passed back to the callback as-is.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct CallbackContext(*mut c_void);
+
+// SAFETY: We never dereference the pointer. Making the data it points to usable on the thread that
+// the callback is called on is the responsibility of the host, as documented on the API.
+unsafe impl Send for CallbackContext {}
+
+impl CallbackContext {
+    pub(crate) fn new(context: *mut c_void) -> Self {
+        Self(context)
+    }
+
+    // This is a method instead of a field access so closures capture the whole (`Send`) value.
+    pub(crate) fn get(self) -> *mut c_void {
+        self.0
+    }
+}
+
+/// The error code reported to callbacks for a failed operation.
+pub(crate) fn error_code(error: folo::io::Error) -> i32 {
+    std::io::Error::from(error).raw_os_error().unwrap_or(-1)
+}
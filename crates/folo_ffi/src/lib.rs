@@ -0,0 +1,53 @@
+//! A C API for embedding the Folo runtime in C and C++ applications, which use it as their async
+//! engine. The declarations are in `include/folo.h`, which is generated from this crate by cbindgen
+//! whenever the crate is built.
+//!
+//! The host application:
+//!
+//! 1. Starts a runtime via `folo_runtime_create()`.
+//! 2. Submits work via `folo_runtime_submit()`, which calls a callback on an async worker thread.
+//! 3. Hands sockets it has created or accepted to the runtime via `folo_socket_adopt()`, after
+//!    which it reads and writes via `folo_socket_read()` and `folo_socket_write()`, with the
+//!    results delivered to callbacks. `folo_socket_close()` closes the socket.
+//! 4. Stops the runtime via `folo_runtime_shutdown()`.
+//!
+//! Callbacks are called on the async worker threads of the runtime and must not block them for
+//! long, as that delays all the other work on the same thread. The context pointer given along with
+//! each callback is passed back to it as-is - the host is responsible for making whatever it points
+//! to safe to use from the worker thread.
+//!
+//! # Example
+//!
+//! ```c
+//! #include "folo.h"
+//!
+//! static void on_read(void *context, int32_t error, const uint8_t *data, size_t len) {
+//!     // len == 0 means the peer has closed the connection.
+//! }
+//!
+//! int main(void) {
+//!     FoloRuntime *runtime;
+//!     if (folo_runtime_create(0, &runtime) != FOLO_STATUS_OK) return 1;
+//!
+//!     FoloSocket *socket;
+//!     if (folo_socket_adopt(runtime, accepted_socket, &socket) == FOLO_STATUS_OK) {
+//!         folo_socket_read(socket, on_read, NULL);
+//!     }
+//!
+//!     // ...
+//!
+//!     folo_socket_close(socket);
+//!     folo_runtime_shutdown(runtime, 5000);
+//!     return 0;
+//! }
+//! ```
+
+mod callback;
+mod runtime;
+mod socket;
+mod status;
+
+pub use callback::*;
+pub use runtime::*;
+pub use socket::*;
+pub use status::*;
@@ -0,0 +1,119 @@
+use crate::{CallbackContext, FoloCallback, FoloStatus};
+use folo::rt::{current_worker_index, RuntimeBuilder, RuntimeClient};
+use std::{ffi::c_void, time::Duration};
+use tracing::{event, Level};
+
+/// A Folo runtime started via `folo_runtime_create()`.
+#[derive(Debug)]
+pub struct FoloRuntime {
+    pub(crate) client: RuntimeClient,
+}
+
+/// Starts a runtime with one async worker thread per processor, or at most `max_processors` of
+/// them if it is not 0. On success, stores the runtime in `*runtime`, which must eventually be
+/// stopped and released via `folo_runtime_shutdown()`.
+///
+/// # Safety
+///
+/// `runtime` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn folo_runtime_create(
+    max_processors: usize,
+    runtime: *mut *mut FoloRuntime,
+) -> FoloStatus {
+    if runtime.is_null() {
+        return FoloStatus::InvalidArgument;
+    }
+
+    let mut builder = RuntimeBuilder::new();
+
+    if max_processors != 0 {
+        builder = builder.max_processors(max_processors);
+    }
+
+    match builder.build() {
+        Ok(client) => {
+            // SAFETY: The caller guarantees that the pointer is valid for writes.
+            unsafe {
+                *runtime = Box::into_raw(Box::new(FoloRuntime { client }));
+            }
+
+            FoloStatus::Ok
+        }
+        Err(e) => {
+            event!(Level::ERROR, message = "failed to start Folo runtime", error = %e);
+            FoloStatus::Failed
+        }
+    }
+}
+
+/// Calls `callback` with `context` on any async worker thread of the runtime. May be called from
+/// any thread, including from callbacks.
+///
+/// # Safety
+///
+/// `runtime` must be null or a runtime created via `folo_runtime_create()` that has not been shut
+/// down.
+#[no_mangle]
+pub unsafe extern "C" fn folo_runtime_submit(
+    runtime: *const FoloRuntime,
+    callback: Option<FoloCallback>,
+    context: *mut c_void,
+) -> FoloStatus {
+    // SAFETY: The caller guarantees that the pointer is null or valid.
+    let (Some(runtime), Some(callback)) = (unsafe { runtime.as_ref() }, callback) else {
+        return FoloStatus::InvalidArgument;
+    };
+
+    if runtime.client.is_stopping() {
+        return FoloStatus::Stopping;
+    }
+
+    let context = CallbackContext::new(context);
+
+    // The task is detached - the host learns of its completion from the callback itself.
+    _ = runtime
+        .client
+        .spawn_on_any(move || async move { callback(context.get()) });
+
+    FoloStatus::Ok
+}
+
+/// Shuts down the runtime, giving in-flight work up to `timeout_ms` milliseconds to complete, and
+/// releases it. Sockets adopted by the runtime must be closed before this is called.
+///
+/// Returns `FOLO_STATUS_TIMED_OUT` if some work had to be canceled. The runtime is released either
+/// way, unless the call is made from one of the threads of the runtime (e.g. from a callback),
+/// which is not allowed and returns `FOLO_STATUS_INVALID_THREAD`.
+///
+/// # Safety
+///
+/// `runtime` must be null or a runtime created via `folo_runtime_create()` that has not been shut
+/// down.
+#[no_mangle]
+pub unsafe extern "C" fn folo_runtime_shutdown(
+    runtime: *mut FoloRuntime,
+    timeout_ms: u32,
+) -> FoloStatus {
+    if runtime.is_null() {
+        return FoloStatus::InvalidArgument;
+    }
+
+    // Shutting down blocks until all the threads of the runtime have terminated, so a thread of
+    // the runtime would be waiting for itself.
+    if current_worker_index().is_some() {
+        return FoloStatus::InvalidThread;
+    }
+
+    // SAFETY: The caller guarantees that the runtime is valid and gives up ownership of it.
+    let runtime = unsafe { Box::from_raw(runtime) };
+
+    if runtime
+        .client
+        .shutdown(Duration::from_millis(timeout_ms.into()))
+    {
+        FoloStatus::Ok
+    } else {
+        FoloStatus::TimedOut
+    }
+}
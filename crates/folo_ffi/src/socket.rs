@@ -0,0 +1,273 @@
+use crate::{
+    callback::error_code, CallbackContext, FoloReadCallback, FoloRuntime, FoloStatus,
+    FoloWriteCallback,
+};
+use folo::{
+    io::{self, Buffer},
+    mem::isolation::Isolated,
+    net::TcpStream,
+    rt::{current_worker_index, RuntimeClient},
+};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    ffi::c_void,
+    future::Future,
+    ptr,
+    rc::Rc,
+    slice,
+    sync::atomic::{AtomicU64, Ordering},
+};
+use tracing::{event, Level};
+use windows::Win32::Networking::WinSock::SOCKET;
+
+/// A connected TCP socket adopted by a runtime via `folo_socket_adopt()`.
+///
+/// The socket is bound to the I/O driver of one async worker thread, which performs all the
+/// operations on it and calls their callbacks.
+#[derive(Debug)]
+pub struct FoloSocket {
+    runtime: RuntimeClient,
+    worker_index: usize,
+
+    // Identifies the stream in the `STREAMS` of the worker thread.
+    id: u64,
+}
+
+impl FoloSocket {
+    /// Executes an operation on the stream, on the worker thread that owns it. If the socket has
+    /// been closed by the time the operation starts, it receives `None`.
+    fn spawn<FN, F>(&self, operation: FN) -> FoloStatus
+    where
+        FN: FnOnce(Option<Rc<RefCell<TcpStream>>>) -> F + Send + 'static,
+        F: Future<Output = ()> + 'static,
+    {
+        if self.runtime.is_stopping() {
+            return FoloStatus::Stopping;
+        }
+
+        let id = self.id;
+
+        // The task is detached - the host learns of its completion from the callback.
+        _ = self.runtime.spawn_on(self.worker_index, move || {
+            operation(STREAMS.with_borrow(|streams| streams.get(&id).cloned()))
+        });
+
+        FoloStatus::Ok
+    }
+}
+
+/// Hands a connected TCP socket created by the host (e.g. accepted from a listening socket) to the
+/// runtime, which takes ownership of it. On success, stores the adopted socket in `*adopted`,
+/// which must eventually be closed via `folo_socket_close()`. The socket is closed on failure.
+///
+/// If called from a callback, the socket is bound to the worker thread of the callback. Otherwise,
+/// it is bound to any worker thread and the call blocks until that thread has adopted it.
+///
+/// # Safety
+///
+/// `runtime` must be null or a runtime created via `folo_runtime_create()` that has not been shut
+/// down. `adopted` must be null or valid for writes.
+///
+/// `socket` must be a valid, connected TCP socket created with `WSA_FLAG_OVERLAPPED` that is not
+/// associated with an I/O completion port. The host must not use or close it after this call.
+#[no_mangle]
+pub unsafe extern "C" fn folo_socket_adopt(
+    runtime: *const FoloRuntime,
+    socket: usize,
+    adopted: *mut *mut FoloSocket,
+) -> FoloStatus {
+    // SAFETY: The caller guarantees that the pointer is null or valid.
+    let Some(runtime) = (unsafe { runtime.as_ref() }) else {
+        return FoloStatus::InvalidArgument;
+    };
+
+    if adopted.is_null() {
+        return FoloStatus::InvalidArgument;
+    }
+
+    if runtime.client.is_stopping() {
+        return FoloStatus::Stopping;
+    }
+
+    let id = NEXT_SOCKET_ID.fetch_add(1, Ordering::Relaxed);
+
+    let adopt = move || -> io::Result<()> {
+        // SAFETY: The caller guarantees that the socket is valid and gives up ownership of it.
+        let stream = unsafe { TcpStream::from_raw_socket(SOCKET(socket)) }?;
+
+        STREAMS.with_borrow_mut(|streams| streams.insert(id, Rc::new(RefCell::new(stream))));
+
+        Ok(())
+    };
+
+    let (worker_index, result) = match current_worker_index() {
+        Some(worker_index) => (worker_index, adopt()),
+        None => {
+            let worker_index = (id % runtime.client.worker_count() as u64) as usize;
+
+            let result = futures::executor::block_on(
+                runtime
+                    .client
+                    .spawn_on(worker_index, move || async move { adopt() }),
+            );
+
+            (worker_index, result)
+        }
+    };
+
+    if let Err(e) = result {
+        event!(Level::ERROR, message = "failed to adopt socket", error = %e);
+        return FoloStatus::Failed;
+    }
+
+    // SAFETY: The caller guarantees that the pointer is valid for writes.
+    unsafe {
+        *adopted = Box::into_raw(Box::new(FoloSocket {
+            runtime: runtime.client.clone(),
+            worker_index,
+            id,
+        }));
+    }
+
+    FoloStatus::Ok
+}
+
+/// Reads the next data received on the socket and delivers it to `callback`, together with
+/// `context`. Only one read should be in progress at a time, as the order in which concurrent reads
+/// complete is not defined.
+///
+/// # Safety
+///
+/// `socket` must be null or a socket adopted via `folo_socket_adopt()` that has not been closed.
+#[no_mangle]
+pub unsafe extern "C" fn folo_socket_read(
+    socket: *const FoloSocket,
+    callback: Option<FoloReadCallback>,
+    context: *mut c_void,
+) -> FoloStatus {
+    // SAFETY: The caller guarantees that the pointer is null or valid.
+    let (Some(socket), Some(callback)) = (unsafe { socket.as_ref() }, callback) else {
+        return FoloStatus::InvalidArgument;
+    };
+
+    let context = CallbackContext::new(context);
+
+    socket.spawn(move |stream| async move {
+        let Some(stream) = stream else {
+            callback(context.get(), -1, ptr::null(), 0);
+            return;
+        };
+
+        // The borrow ends once the read has started, so writes can start while it is pending.
+        let read = stream.borrow_mut().read(Buffer::<Isolated>::from_pool());
+
+        match read.await {
+            Ok(buffer) => {
+                let data = buffer.as_slice();
+                callback(context.get(), 0, data.as_ptr(), data.len());
+            }
+            Err(e) => callback(context.get(), error_code(e.into_inner()), ptr::null(), 0),
+        }
+    })
+}
+
+/// Writes `len` bytes from `data` to the socket, calling `callback` (if not null) with `context`
+/// once all of them have been written. The data is copied before this returns, so the host may
+/// release it right away. Data from concurrent writes may be interleaved - to keep writes in order,
+/// start the next one from the callback of the previous one.
+///
+/// # Safety
+///
+/// `socket` must be null or a socket adopted via `folo_socket_adopt()` that has not been closed.
+/// `data` must be null or valid for reads of `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn folo_socket_write(
+    socket: *const FoloSocket,
+    data: *const u8,
+    len: usize,
+    callback: Option<FoloWriteCallback>,
+    context: *mut c_void,
+) -> FoloStatus {
+    // SAFETY: The caller guarantees that the pointer is null or valid.
+    let Some(socket) = (unsafe { socket.as_ref() }) else {
+        return FoloStatus::InvalidArgument;
+    };
+
+    if data.is_null() && len != 0 {
+        return FoloStatus::InvalidArgument;
+    }
+
+    let data = if len == 0 {
+        Vec::new()
+    } else {
+        // SAFETY: The caller guarantees that the data is valid for reads of `len` bytes.
+        unsafe { slice::from_raw_parts(data, len) }.to_vec()
+    };
+
+    let context = CallbackContext::new(context);
+
+    socket.spawn(move |stream| async move {
+        let error = match stream {
+            Some(stream) => match write_all(&stream, &data).await {
+                Ok(()) => 0,
+                Err(e) => error_code(e),
+            },
+            None => -1,
+        };
+
+        if let Some(callback) = callback {
+            callback(context.get(), error);
+        }
+    })
+}
+
+async fn write_all(stream: &RefCell<TcpStream>, mut data: &[u8]) -> io::Result<()> {
+    while !data.is_empty() {
+        let mut buffer = Buffer::<Isolated>::from_pool();
+        let len = buffer.len().min(data.len());
+        buffer.set_len(len);
+        buffer.as_mut_slice().copy_from_slice(&data[..len]);
+
+        // The borrow ends once the write has started, so reads can start while it is pending.
+        let write = stream.borrow_mut().write(buffer);
+        write.await.map_err(|e| e.into_inner())?;
+
+        data = &data[len..];
+    }
+
+    Ok(())
+}
+
+/// Closes the socket and releases it. Operations that are in progress complete first, with their
+/// callbacks still being called.
+///
+/// # Safety
+///
+/// `socket` must be null or a socket adopted via `folo_socket_adopt()` that has not been closed.
+#[no_mangle]
+pub unsafe extern "C" fn folo_socket_close(socket: *mut FoloSocket) -> FoloStatus {
+    if socket.is_null() {
+        return FoloStatus::InvalidArgument;
+    }
+
+    // SAFETY: The caller guarantees that the socket is valid and gives up ownership of it.
+    let socket = unsafe { Box::from_raw(socket) };
+    let id = socket.id;
+
+    // If the runtime is stopping, the stream is released together with the worker thread.
+    _ = socket.spawn(move |_| async move {
+        // Pending operations hold on to the stream, so it is only dropped (closing the socket)
+        // once they have completed.
+        STREAMS.with_borrow_mut(|streams| streams.remove(&id));
+    });
+
+    FoloStatus::Ok
+}
+
+static NEXT_SOCKET_ID: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    // The streams adopted by the current worker thread, by the ID of their `FoloSocket`.
+    static STREAMS: RefCell<HashMap<u64, Rc<RefCell<TcpStream>>>> = RefCell::new(HashMap::new());
+}
@@ -0,0 +1,23 @@
+/// The outcome of a call to the C API.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FoloStatus {
+    /// The call succeeded.
+    Ok = 0,
+
+    /// A required argument was null.
+    InvalidArgument = 1,
+
+    /// The call was made on a thread that is not allowed to make it (e.g. shutting down the
+    /// runtime from one of its own threads).
+    InvalidThread = 2,
+
+    /// The runtime is stopping and does not accept new work.
+    Stopping = 3,
+
+    /// The operation failed. The reason is logged via `tracing`.
+    Failed = 4,
+
+    /// The runtime was shut down but some of its tasks did not complete in time and were canceled.
+    TimedOut = 5,
+}
@@ -0,0 +1,164 @@
+use folo_ffi::*;
+use std::{
+    ffi::c_void,
+    io::{Read, Write},
+    net::{Ipv4Addr, TcpListener, TcpStream},
+    os::windows::io::IntoRawSocket,
+    ptr, slice,
+    sync::mpsc,
+    time::Duration,
+};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+extern "C" fn send_unit(context: *mut c_void) {
+    // SAFETY: The tests pass a sender that outlives the callback.
+    let tx = unsafe { &*(context as *const mpsc::Sender<()>) };
+    tx.send(()).unwrap();
+}
+
+extern "C" fn send_read_result(context: *mut c_void, error: i32, data: *const u8, len: usize) {
+    // SAFETY: The tests pass a sender that outlives the callback.
+    let tx = unsafe { &*(context as *const mpsc::Sender<(i32, Vec<u8>)>) };
+
+    let data = if len == 0 {
+        Vec::new()
+    } else {
+        // SAFETY: The runtime guarantees that the data is valid until the callback returns.
+        unsafe { slice::from_raw_parts(data, len) }.to_vec()
+    };
+
+    tx.send((error, data)).unwrap();
+}
+
+extern "C" fn send_write_result(context: *mut c_void, error: i32) {
+    // SAFETY: The tests pass a sender that outlives the callback.
+    let tx = unsafe { &*(context as *const mpsc::Sender<i32>) };
+    tx.send(error).unwrap();
+}
+
+fn create_runtime() -> *mut FoloRuntime {
+    let mut runtime = ptr::null_mut();
+
+    // SAFETY: The pointer is valid for writes.
+    assert_eq!(
+        unsafe { folo_runtime_create(2, &mut runtime) },
+        FoloStatus::Ok
+    );
+
+    runtime
+}
+
+#[test]
+fn submit_calls_callback() {
+    let runtime = create_runtime();
+    let (tx, rx) = mpsc::channel::<()>();
+
+    // SAFETY: The runtime is valid and the sender outlives the callback.
+    let status =
+        unsafe { folo_runtime_submit(runtime, Some(send_unit), &tx as *const _ as *mut c_void) };
+    assert_eq!(status, FoloStatus::Ok);
+
+    rx.recv_timeout(TIMEOUT).unwrap();
+
+    // SAFETY: The runtime is valid and not used after this.
+    assert_eq!(
+        unsafe { folo_runtime_shutdown(runtime, 10_000) },
+        FoloStatus::Ok
+    );
+}
+
+#[test]
+fn null_arguments_are_rejected() {
+    // SAFETY: Null pointers are always acceptable arguments.
+    unsafe {
+        assert_eq!(
+            folo_runtime_create(0, ptr::null_mut()),
+            FoloStatus::InvalidArgument
+        );
+        assert_eq!(
+            folo_runtime_submit(ptr::null(), Some(send_unit), ptr::null_mut()),
+            FoloStatus::InvalidArgument
+        );
+        assert_eq!(
+            folo_socket_read(ptr::null(), Some(send_read_result), ptr::null_mut()),
+            FoloStatus::InvalidArgument
+        );
+        assert_eq!(
+            folo_runtime_shutdown(ptr::null_mut(), 0),
+            FoloStatus::InvalidArgument
+        );
+    }
+}
+
+#[test]
+fn adopted_socket_reads_and_writes() {
+    let runtime = create_runtime();
+
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    client.set_read_timeout(Some(TIMEOUT)).unwrap();
+
+    // The standard library creates sockets with `WSA_FLAG_OVERLAPPED`, as the runtime requires.
+    let (accepted, _) = listener.accept().unwrap();
+
+    let mut socket = ptr::null_mut();
+
+    // SAFETY: The runtime is valid, the socket is a connected TCP socket that we give up ownership
+    // of and the pointer is valid for writes.
+    let status =
+        unsafe { folo_socket_adopt(runtime, accepted.into_raw_socket() as usize, &mut socket) };
+    assert_eq!(status, FoloStatus::Ok);
+
+    client.write_all(b"hello").unwrap();
+
+    let (read_tx, read_rx) = mpsc::channel::<(i32, Vec<u8>)>();
+
+    // SAFETY: The socket is valid and the sender outlives the callback.
+    let status = unsafe {
+        folo_socket_read(
+            socket,
+            Some(send_read_result),
+            &read_tx as *const _ as *mut c_void,
+        )
+    };
+    assert_eq!(status, FoloStatus::Ok);
+
+    let (error, data) = read_rx.recv_timeout(TIMEOUT).unwrap();
+    assert_eq!(error, 0);
+    assert_eq!(data, b"hello");
+
+    let (write_tx, write_rx) = mpsc::channel::<i32>();
+
+    // SAFETY: The socket and the data are valid and the sender outlives the callback.
+    let status = unsafe {
+        folo_socket_write(
+            socket,
+            b"world".as_ptr(),
+            5,
+            Some(send_write_result),
+            &write_tx as *const _ as *mut c_void,
+        )
+    };
+    assert_eq!(status, FoloStatus::Ok);
+
+    assert_eq!(write_rx.recv_timeout(TIMEOUT).unwrap(), 0);
+
+    let mut response = [0; 5];
+    client.read_exact(&mut response).unwrap();
+    assert_eq!(&response, b"world");
+
+    // SAFETY: The socket is valid and not used after this.
+    assert_eq!(unsafe { folo_socket_close(socket) }, FoloStatus::Ok);
+
+    // Closing the socket on the runtime side ends the stream for the client.
+    let mut rest = Vec::new();
+    client.read_to_end(&mut rest).unwrap();
+    assert!(rest.is_empty());
+
+    // SAFETY: The runtime is valid and not used after this.
+    assert_eq!(
+        unsafe { folo_runtime_shutdown(runtime, 10_000) },
+        FoloStatus::Ok
+    );
+}
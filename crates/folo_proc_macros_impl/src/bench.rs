@@ -0,0 +1,213 @@
+use crate::util::token_stream_and_error;
+use darling::{ast::NestedMeta, FromMeta};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::ItemFn;
+
+#[derive(Debug, FromMeta)]
+struct BenchOptions {
+    /// How long to execute the measured iterations for, in milliseconds.
+    duration_ms: Option<u64>,
+
+    /// How long to execute the benchmark body for before starting the measurement, in milliseconds.
+    warmup_ms: Option<u64>,
+
+    /// Limits the number of processors (and therefore worker threads) the benchmark will use.
+    max_processors: Option<usize>,
+}
+
+impl BenchOptions {
+    fn parse(attr: TokenStream) -> syn::Result<Self> {
+        let attr_args = NestedMeta::parse_meta_list(attr)?;
+        Ok(BenchOptions::from_list(&attr_args)?)
+    }
+}
+
+/// Implements the Folo benchmark macro, which turns an async function (the body of one iteration)
+/// into a synchronous function that executes the benchmark via the Folo benchmark harness.
+pub fn entrypoint(attr: TokenStream, input: TokenStream) -> TokenStream {
+    let item_ast = syn::parse2::<ItemFn>(input.clone());
+
+    let result = match item_ast {
+        Ok(item) => core(item, attr),
+        Err(e) => Err(e),
+    };
+
+    match result {
+        Ok(r) => r,
+        Err(e) => token_stream_and_error(input, e),
+    }
+}
+
+fn core(item: ItemFn, attr: TokenStream) -> Result<TokenStream, syn::Error> {
+    let options = BenchOptions::parse(attr)?;
+
+    let sig = &item.sig;
+
+    if sig.asyncness.is_none() {
+        return Err(syn::Error::new_spanned(
+            sig.fn_token,
+            "function must be async to use the #[folo::bench] attribute",
+        ));
+    }
+
+    if !sig.inputs.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &sig.inputs,
+            "function must not take any arguments to use the #[folo::bench] attribute",
+        ));
+    }
+
+    let attrs = &item.attrs;
+    let vis = &item.vis;
+    let ident = &sig.ident;
+    let body = &item.block;
+
+    let mut inner_sig = sig.clone();
+    inner_sig.ident = format_ident!("__inner_{}", sig.ident);
+
+    let inner_ident = &inner_sig.ident;
+
+    // The body of the benchmark is emitted as a separate function, which the harness calls to
+    // create the future of each iteration.
+    let inner = quote! {
+        #inner_sig #body
+    };
+
+    let duration = match options.duration_ms {
+        Some(ms) => quote! {
+            .duration(::std::time::Duration::from_millis(#ms))
+        },
+        None => quote! {},
+    };
+
+    let warmup = match options.warmup_ms {
+        Some(ms) => quote! {
+            .warmup(::std::time::Duration::from_millis(#ms))
+        },
+        None => quote! {},
+    };
+
+    let max_processors = match options.max_processors {
+        Some(n) => quote! {
+            .max_processors(#n)
+        },
+        None => quote! {},
+    };
+
+    Ok(quote! {
+        #(#attrs)*
+        #vis fn #ident() -> ::folo::bench::BenchReport {
+            let __bench_report = ::folo::bench::BenchBuilder::new(stringify!(#ident))
+                #duration
+                #warmup
+                #max_processors
+                .run(#inner_ident);
+
+            println!("{}", __bench_report);
+
+            __bench_report
+        }
+
+        #inner
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::contains_compile_error;
+    use syn::parse_quote;
+
+    #[test]
+    fn bench_default() {
+        let input = parse_quote! {
+            async fn spawn_and_await() {
+                spawn(async {}).await;
+            }
+        };
+
+        let expected = quote! {
+            fn spawn_and_await() -> ::folo::bench::BenchReport {
+                let __bench_report = ::folo::bench::BenchBuilder::new(stringify!(spawn_and_await))
+                    .run(__inner_spawn_and_await);
+
+                println!("{}", __bench_report);
+
+                __bench_report
+            }
+
+            async fn __inner_spawn_and_await() {
+                spawn(async {}).await;
+            }
+        };
+
+        assert_eq!(
+            entrypoint(TokenStream::new(), input).to_string(),
+            expected.to_string()
+        );
+    }
+
+    #[test]
+    fn bench_with_options() {
+        let attr = parse_quote! {
+            duration_ms = 2000,
+            warmup_ms = 500,
+            max_processors = 4,
+        };
+
+        let input = parse_quote! {
+            pub async fn spawn_and_await() {
+                spawn(async {}).await;
+            }
+        };
+
+        let expected = quote! {
+            pub fn spawn_and_await() -> ::folo::bench::BenchReport {
+                let __bench_report = ::folo::bench::BenchBuilder::new(stringify!(spawn_and_await))
+                    .duration(::std::time::Duration::from_millis(2000u64))
+                    .warmup(::std::time::Duration::from_millis(500u64))
+                    .max_processors(4usize)
+                    .run(__inner_spawn_and_await);
+
+                println!("{}", __bench_report);
+
+                __bench_report
+            }
+
+            async fn __inner_spawn_and_await() {
+                spawn(async {}).await;
+            }
+        };
+
+        assert_eq!(entrypoint(attr, input).to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn bench_not_async_is_error() {
+        let input = parse_quote! {
+            fn spawn_and_await() {
+                spawn(async {});
+            }
+        };
+
+        assert!(contains_compile_error(&entrypoint(
+            TokenStream::new(),
+            input
+        )));
+    }
+
+    #[test]
+    fn bench_with_arguments_is_error() {
+        let input = parse_quote! {
+            async fn spawn_and_await(count: usize) {
+                spawn(async {}).await;
+            }
+        };
+
+        assert!(contains_compile_error(&entrypoint(
+            TokenStream::new(),
+            input
+        )));
+    }
+}
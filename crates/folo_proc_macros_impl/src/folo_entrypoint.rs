@@ -89,6 +89,15 @@ fn core(
         EntrypointType::Test => quote! { #[test] },
     };
 
+    // A failed assertion in a test must fail that test, not abort the entire test process, so tests
+    // let panics unwind through the worker thread, which makes `wait()` panic on the test thread.
+    let panic_policy = match entrypoint_type {
+        EntrypointType::Main => quote! {},
+        EntrypointType::Test => quote! {
+            .panic_policy(::folo::rt::PanicPolicy::TerminateWorker)
+        },
+    };
+
     let global_init = match options.global_init_fn {
         Some(ident) => quote! {
             #ident();
@@ -142,6 +151,7 @@ fn core(
                     #worker_init
                     #metrics_init
                     #max_processors
+                    #panic_policy
                     .build()
                     .unwrap();
                 let __entrypoint_runtime_clone = __entrypoint_runtime.clone();
@@ -168,6 +178,7 @@ fn core(
                     #worker_init
                     #metrics_init
                     #max_processors
+                    #panic_policy
                     .build()
                     .unwrap();
                 let __entrypoint_runtime_clone = __entrypoint_runtime.clone();
@@ -468,8 +479,9 @@ mod tests {
             #[test]
             fn my_test() {
                 let __entrypoint_metrics_collector = ::folo::__private::MetricsCollector::new();
-                
+
                 let __entrypoint_runtime = ::folo::rt::RuntimeBuilder::new()
+                    .panic_policy(::folo::rt::PanicPolicy::TerminateWorker)
                     .build()
                     .unwrap();
                 let __entrypoint_runtime_clone = __entrypoint_runtime.clone();
@@ -1,3 +1,4 @@
+pub mod bench;
 pub mod folo_entrypoint;
 pub mod linked_object;
 mod syn_helpers;